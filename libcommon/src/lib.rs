@@ -80,4 +80,7 @@ pub enum SysinspectError {
 
     #[error("RSA error: {0}")]
     RSAError(String),
+
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
 }
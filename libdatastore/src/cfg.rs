@@ -1,10 +1,42 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 #[derive(Debug, Clone, Default)]
 pub struct DataStorageConfig {
     expiration: Option<Duration>,
     max_item_size: Option<u64>,
     max_overall_size: Option<u64>,
+    namespaces: HashMap<String, DataNamespaceConfig>,
+}
+
+/// Quota and retention of one namespace. Unset values fall back to the store-wide ones.
+#[derive(Debug, Clone, Default)]
+pub struct DataNamespaceConfig {
+    expiration: Option<Duration>,
+    max_size: Option<u64>,
+}
+
+impl DataNamespaceConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn expiration(mut self, d: Duration) -> Self {
+        self.expiration = Some(d);
+        self
+    }
+
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    pub fn get_expiration(&self) -> Option<Duration> {
+        self.expiration
+    }
+
+    pub fn get_max_size(&self) -> Option<u64> {
+        self.max_size
+    }
 }
 
 impl DataStorageConfig {
@@ -27,6 +59,11 @@ impl DataStorageConfig {
         self
     }
 
+    pub fn namespace(mut self, name: &str, cfg: DataNamespaceConfig) -> Self {
+        self.namespaces.insert(name.to_string(), cfg);
+        self
+    }
+
     pub fn get_max_overall_size(&self) -> Option<u64> {
         self.max_overall_size
     }
@@ -38,4 +75,12 @@ impl DataStorageConfig {
     pub fn get_expiration(&self) -> Option<Duration> {
         self.expiration
     }
+
+    pub fn get_namespace(&self, name: &str) -> Option<&DataNamespaceConfig> {
+        self.namespaces.get(name)
+    }
+
+    pub fn get_namespaces(&self) -> &HashMap<String, DataNamespaceConfig> {
+        &self.namespaces
+    }
 }
@@ -1,31 +1,88 @@
 use crate::resources::DataItemMeta;
-use crate::util::set_file_attrs;
+use crate::util::{get_sha256, set_file_attrs};
 use futures_util::StreamExt;
-use reqwest::Client;
+use reqwest::{
+    Client, StatusCode,
+    header::{ETAG, IF_RANGE, RANGE},
+};
+use sha2::{Digest, Sha256};
 use std::io;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
+use std::time::Duration;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// How many times a broken chunk or download is resumed before giving up
+const RESUME_ATTEMPTS: u32 = 5;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// A simple client for uploading files to the datastore via HTTP API.
-/// This is a basic example and can be extended with error handling, authentication, etc.
+/// The file is sent in chunks of a staged upload, each with its own checksum. A failed chunk
+/// is sent again from the offset the master reports. The master verifies the sha256 of the
+/// whole file before storing it.
+/// This is a basic example and can be extended with authentication etc.
 /// Example usage:
 /// ```
 /// upload_artefact("http://localhost:8080", "/path/to/file").await?;
 /// ```
 pub async fn upload_artefact(master_url: &str, path: &Path) -> anyhow::Result<()> {
     let client = Client::new();
-    let bytes = fs::read(path).await?;
+    let size = fs::metadata(path).await?.len();
+    let sha256 = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || get_sha256(&path)).await??
+    };
 
     let resp = client
-        .post(format!("{master_url}/store"))
-        .header("Content-Type", "application/octet-stream")
-        .header("X-Filename", path.to_string_lossy().to_string())
-        .body(bytes)
+        .post(format!("{master_url}/store/uploads"))
+        .json(&serde_json::json!({"size_bytes": size, "sha256": sha256, "fname": path.to_string_lossy()}))
         .send()
         .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("Upload failed: {}", resp.text().await?);
+    }
+    let mut state: serde_json::Value = resp.json().await?;
+    let upload_id = state["upload_id"].as_str().unwrap_or_default().to_string();
+    let upload_url = format!("{master_url}/store/uploads/{upload_id}");
+    let mut buf = vec![0u8; state["chunk_size"].as_u64().unwrap_or(8 * 1024 * 1024).max(1) as usize];
+
+    let mut file = fs::File::open(path).await?;
+    let mut attempt = 0;
+    loop {
+        let offset = state["received_bytes"].as_u64().unwrap_or_default();
+        if offset >= size {
+            break;
+        }
+        let len = (size - offset).min(buf.len() as u64) as usize;
+        file.seek(io::SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut buf[..len]).await?;
+
+        let sent = client
+            .put(format!("{upload_url}?offset={offset}"))
+            .header("Content-Type", "application/octet-stream")
+            .header("X-Chunk-Sha256", format!("{:x}", Sha256::digest(&buf[..len])))
+            .body(buf[..len].to_vec())
+            .send()
+            .await;
+        match sent {
+            Ok(resp) if resp.status().is_success() => state = resp.json().await?,
+            failed => {
+                let err = match failed {
+                    Ok(resp) => format!("HTTP {}: {}", resp.status(), resp.text().await.unwrap_or_default()),
+                    Err(err) => err.to_string(),
+                };
+                if attempt >= RESUME_ATTEMPTS {
+                    anyhow::bail!("Upload failed at {offset} of {size} bytes: {err}, resume upload {upload_id} later");
+                }
+                log::debug!("Chunk of upload {upload_id} at {offset} failed: {err}, resuming");
+                attempt += 1;
+                state = client.get(&upload_url).send().await?.error_for_status()?.json().await?;
+            }
+        }
+    }
 
+    let resp = client.post(format!("{upload_url}/complete")).send().await?;
     if !resp.status().is_success() {
         anyhow::bail!("Upload failed: {}", resp.text().await?);
     }
@@ -71,46 +128,184 @@ pub async fn download_artefact(master_url: &str, sha256: &str) -> anyhow::Result
 
 /// Stream-download a blob from the master and write it atomically to `dst`.
 /// - No buffering whole file in memory
-/// - Writes to `dst.tmp` then renames
+/// - Writes to a partial file next to `dst`, then renames
+/// - Resumes a partial file with a range request, also when the connection breaks
 ///
 /// Example usage:
 /// ```
 /// atomic_download("http://master/store/sha256hash/blob", "/your/bin").await?;
 /// ```
 pub async fn atomic_download(url: &str, dst: impl AsRef<Path>) -> io::Result<()> {
-    let dst = dst.as_ref();
-    let tmp = dst.with_extension("tmp");
+    resume_download(url, dst.as_ref(), None, &DownloadOptions::default()).await
+}
+
+/// Same as [`atomic_download`], but `dst` is replaced only if the downloaded file has the `sha256`.
+/// A download with a different checksum is removed.
+pub async fn atomic_download_verified(url: &str, dst: impl AsRef<Path>, sha256: &str) -> io::Result<()> {
+    resume_download(url, dst.as_ref(), Some(sha256), &DownloadOptions::default()).await
+}
+
+/// Connection settings of a download from the Web API of the master
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Bearer token of a datastore session
+    pub token: Option<String>,
+
+    /// Accept a master certificate that cannot be verified
+    pub accept_invalid_certs: bool,
+    pub connect_timeout: Option<Duration>,
+
+    /// Longest wait for data of a transfer before it is resumed, 30 seconds by default
+    pub read_timeout: Option<Duration>,
+}
+
+/// Same as [`atomic_download`] with the given connection settings, verifying the `sha256` if it is known.
+pub async fn atomic_download_with(url: &str, dst: impl AsRef<Path>, sha256: Option<&str>, opts: &DownloadOptions) -> io::Result<()> {
+    resume_download(url, dst.as_ref(), sha256, opts).await
+}
+
+/// Partial file of the download. It is named after the expected checksum, or the ETag of the blob,
+/// so that a partial file of another blob is never resumed. Without either, there is nothing to resume.
+fn partial_path(dst: &Path, key: Option<&str>) -> std::path::PathBuf {
+    let name = dst.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    match key {
+        Some(key) => dst.with_file_name(format!("{name}.{}.part", &format!("{:x}", Sha256::digest(key.as_bytes()))[..16])),
+        None => dst.with_file_name(format!("{name}.part")),
+    }
+}
 
+async fn sha256_of(path: &Path) -> io::Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || get_sha256(&path)).await.map_err(io::Error::other)?
+}
+
+async fn resume_download(url: &str, dst: &Path, sha256: Option<&str>, opts: &DownloadOptions) -> io::Result<()> {
     if let Some(parent) = dst.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
 
-    let client = reqwest::Client::new();
-    let resp = client.get(url).send().await.map_err(|e| io::Error::other(e.to_string()))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(io::Error::other(format!("HTTP {status}: {body}")));
+    let mut builder =
+        reqwest::Client::builder().danger_accept_invalid_certs(opts.accept_invalid_certs).read_timeout(opts.read_timeout.unwrap_or(READ_TIMEOUT));
+    if let Some(timeout) = opts.connect_timeout {
+        builder = builder.connect_timeout(timeout);
     }
+    let client = builder.build().map_err(|e| io::Error::other(e.to_string()))?;
+    let request = |method: reqwest::Method| match &opts.token {
+        Some(token) => client.request(method, url).bearer_auth(token),
+        None => client.request(method, url),
+    };
+    let etag_of = |resp: &reqwest::Response| resp.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_string);
 
-    let mut file = tokio::fs::File::create(&tmp).await?;
-    let mut stream = resp.bytes_stream();
+    // Blobs are served on GET only, so without a checksum the ETag of the first response names the partial file
+    let mut pending = None;
+    let key = match sha256 {
+        Some(sha256) => Some(sha256.to_lowercase()),
+        None => {
+            let resp = request(reqwest::Method::GET).send().await.map_err(|e| io::Error::other(e.to_string()))?;
+            let etag = etag_of(&resp);
+            pending = Some(resp);
+            etag
+        }
+    };
+    let mut etag = if sha256.is_none() { key.clone() } else { None };
+    let tmp = partial_path(dst, key.as_deref());
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| io::Error::other(e.to_string()))?;
-        file.write_all(&chunk).await?;
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&tmp).await?;
+    if key.is_none() {
+        file.set_len(0).await?;
     }
+    let mut attempt = 0;
+    loop {
+        let offset = file.metadata().await?.len();
+        let resp = match pending.take() {
+            Some(resp) if offset == 0 => resp,
+            _ => {
+                let mut rq = request(reqwest::Method::GET);
+                if offset > 0 {
+                    rq = rq.header(RANGE, format!("bytes={offset}-"));
+                    // A changed blob is sent whole instead of the rest of the old one
+                    if let Some(etag) = &etag {
+                        rq = rq.header(IF_RANGE, etag);
+                    }
+                }
+                rq.send().await.map_err(|e| io::Error::other(e.to_string()))?
+            }
+        };
 
-    file.flush().await?;
+        match resp.status() {
+            // Nothing past the partial file: it is either complete or not this blob at all
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                if let Some(want) = sha256
+                    && sha256_of(&tmp).await?.eq_ignore_ascii_case(want)
+                {
+                    break;
+                }
+                if attempt >= RESUME_ATTEMPTS {
+                    return Err(io::Error::other(format!("HTTP {}: partial download of {url} cannot be resumed", resp.status())));
+                }
+                log::debug!("Partial download of {url} cannot be resumed at {offset}, restarting");
+                attempt += 1;
+                file.set_len(0).await?;
+                continue;
+            }
+            StatusCode::PARTIAL_CONTENT => {}
+            status if status.is_success() => file.set_len(0).await?,
+            status => {
+                let body = resp.text().await.unwrap_or_default();
+                return Err(io::Error::other(format!("HTTP {status}: {body}")));
+            }
+        }
+        if let Some(tag) = etag_of(&resp) {
+            etag = Some(tag);
+        }
+
+        let mut stream = resp.bytes_stream();
+        let mut broken = None;
+        let mut received = 0;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    file.write_all(&chunk).await?;
+                    received += chunk.len();
+                }
+                Err(e) => {
+                    broken = Some(e);
+                    break;
+                }
+            }
+        }
+        file.flush().await?;
+
+        // Only the attempts that made no progress count, a slow link resumes for as long as data arrives
+        if received > 0 {
+            attempt = 0;
+        }
+
+        match broken {
+            None => break,
+            Some(e) if attempt < RESUME_ATTEMPTS => {
+                log::debug!("Download of {url} broke: {e}, resuming");
+                attempt += 1;
+            }
+            Some(e) => return Err(io::Error::other(e.to_string())),
+        }
+    }
     drop(file);
 
+    if let Some(want) = sha256 {
+        let have = sha256_of(&tmp).await?;
+        if !have.eq_ignore_ascii_case(want) {
+            tokio::fs::remove_file(&tmp).await?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("checksum mismatch: got {have}, expected {want}")));
+        }
+    }
+
     tokio::fs::rename(&tmp, dst).await?;
     Ok(())
 }
 
 /// Download a blob by its original filename. This is a convenience function that first resolves the filename to a SHA256 hash, then downloads the blob by hash.
-/// This is not atomic by itself, but relies on the underlying `atomic_download` to ensure atomicity of the file write.
+/// This is not atomic by itself, but relies on the underlying `atomic_download_verified` to ensure atomicity of the file write.
 /// Example usage:
 /// ```
 /// let meta = download_by_name("http://localhost:8080", "myfile.txt").await?;
@@ -130,7 +325,7 @@ pub async fn download_by_name(master: &str, fname: &str) -> io::Result<DataItemM
 
     let blob_url = format!("{master}/store/{}/blob", meta.sha256);
 
-    atomic_download(&blob_url, fname).await?;
+    atomic_download_verified(&blob_url, fname, &meta.sha256).await?;
     set_file_attrs(&meta, fname)?;
 
     Ok(meta)
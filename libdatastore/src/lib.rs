@@ -1,4 +1,6 @@
 pub mod cfg;
 pub mod client;
 pub mod resources;
+pub mod tags;
+pub mod uploads;
 pub mod util;
@@ -6,16 +6,21 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::os::unix::fs::MetadataExt;
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
     io::{self},
     path::{Path, PathBuf},
     time::Duration,
 };
 
+/// Directory of the namespace metadata under the datastore root. Data is shared by all namespaces.
+const NAMESPACES_DIR: &str = "ns";
+
 #[derive(Debug, Clone)]
 pub struct DataStorage {
     cfg: DataStorageConfig,
     root: PathBuf,
+    pins: DataPins,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +31,89 @@ pub struct DataItemMeta {
     pub expires_unix: Option<u64>,
     pub fname: Option<String>,
     pub fmode: u32,
+    /// Namespace of the item, `None` for the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Items the garbage collection must keep, because active models still pull them.
+///
+/// An item is pinned by its namespace and exact name, or by a name prefix.
+/// Models taking the namespace from the context pin the name in every
+/// namespace that starts with the fixed part of it.
+#[derive(Debug, Clone, Default)]
+pub struct DataPins {
+    names: HashSet<(Option<String>, String)>,
+    prefixes: Vec<(Option<String>, String)>,
+    spread: Vec<(String, String, bool)>,
+}
+
+impl DataPins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pin the item with this name
+    pub fn name(&mut self, namespace: Option<&str>, fname: &str) {
+        self.names.insert((namespace.map(str::to_string), fname.to_string()));
+    }
+
+    /// Pin all items with names starting with this prefix
+    pub fn prefix(&mut self, namespace: Option<&str>, prefix: &str) {
+        self.prefixes.push((namespace.map(str::to_string), prefix.to_string()));
+    }
+
+    /// Pin the item with this name, or all items under this name prefix, in every namespace starting with
+    /// `namespace_prefix`. An empty prefix covers the default namespace as well.
+    pub fn spread(&mut self, namespace_prefix: &str, src: &str, prefix: bool) {
+        self.spread.push((namespace_prefix.to_string(), src.to_string(), prefix));
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len() + self.prefixes.len() + self.spread.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Is the item pinned?
+    pub fn pins(&self, meta: &DataItemMeta) -> bool {
+        let Some(fname) = meta.fname.as_deref() else {
+            return false;
+        };
+        self.names.contains(&(meta.namespace.clone(), fname.to_string()))
+            || self.prefixes.iter().any(|(ns, prefix)| *ns == meta.namespace && fname.starts_with(prefix.as_str()))
+            || self.spread.iter().any(|(ns, src, prefix)| {
+                meta.namespace.as_deref().unwrap_or_default().starts_with(ns.as_str())
+                    && if *prefix { fname.starts_with(src.as_str()) } else { fname == src }
+            })
+    }
+}
+
+/// What one garbage collection run removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataGcReport {
+    /// Expired items removed
+    pub expired: usize,
+    /// Items removed to get back under a size limit
+    pub evicted: usize,
+    /// Data files removed because no item refers to them anymore
+    pub orphans: usize,
+    /// Expired items kept because they are pinned
+    pub kept: usize,
+    /// Abandoned staged uploads removed
+    pub uploads: usize,
+}
+
+/// Check a namespace name: letters, digits, `.`, `_` and `-`, not starting with a dot.
+pub fn check_namespace(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.starts_with('.') || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid namespace \"{name}\"")));
+    }
+    Ok(())
 }
 
 /// Format a byte count into a short human-readable string.
@@ -87,13 +175,53 @@ impl DataStorage {
             format_size_limit(cfg.get_max_item_size()).bright_yellow(),
             format_size_limit(cfg.get_max_overall_size()).bright_yellow(),
         );
+        for (name, ns) in cfg.get_namespaces() {
+            check_namespace(name)?;
+            log::info!(
+                "Datastore namespace {}. Expiration: {}, max size: {}",
+                name.bright_yellow(),
+                format_expiration(ns.get_expiration().or(cfg.get_expiration())).bright_yellow(),
+                format_size_limit(ns.get_max_size()).bright_yellow(),
+            );
+        }
         fs::create_dir_all(&root)?;
-        Ok(Self { cfg, root })
+        Ok(Self { cfg, root, pins: DataPins::default() })
+    }
+
+    /// Replace the items the garbage collection must keep
+    pub fn set_pins(&mut self, pins: DataPins) {
+        self.pins = pins;
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn cfg(&self) -> &DataStorageConfig {
+        &self.cfg
     }
 
     /// Add a file to the store (copy). Returns metadata.
     pub fn add(&self, src: impl AsRef<Path>) -> io::Result<DataItemMeta> {
-        let src = src.as_ref();
+        self.add_to(src, None, None, &BTreeMap::new())
+    }
+
+    /// Add a file to a namespace (`None` for the default one) under a name with tags.
+    /// The name defaults to the file name. Returns metadata.
+    pub fn add_to(
+        &self, src: impl AsRef<Path>, namespace: Option<&str>, fname: Option<&str>, tags: &BTreeMap<String, String>,
+    ) -> io::Result<DataItemMeta> {
+        self.put(src.as_ref(), None, namespace, fname, tags)
+    }
+
+    /// Store the file as an item. A staged upload comes with its verified `sha256`: it is moved into the
+    /// store instead of copied, and its bytes, already counted as staged, are not counted twice.
+    pub(crate) fn put(
+        &self, src: &Path, staged: Option<&str>, namespace: Option<&str>, fname: Option<&str>, tags: &BTreeMap<String, String>,
+    ) -> io::Result<DataItemMeta> {
+        if let Some(ns) = namespace {
+            check_namespace(ns)?;
+        }
         let md = fs::metadata(src)?;
 
         let unix_mode = md.mode() & 0o7777;
@@ -108,24 +236,38 @@ impl DataStorage {
             return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("item too big: {size} > {max} bytes")));
         }
 
-        // Ensure overall limit BEFORE writing (best-effort).
+        // Ensure overall limit BEFORE writing (best-effort). Space is freed by the periodic garbage collection only.
+        let own = if staged.is_some() { size } else { 0 };
         if let Some(max_total) = self.cfg.get_max_overall_size() {
-            let total = self.total()?;
+            let total = self.total()?.saturating_sub(own);
             if total.saturating_add(size) > max_total {
-                // try GC once; if still too big, reject.
-                self.gc()?;
-                let total2 = self.total()?;
-                if total2.saturating_add(size) > max_total {
-                    return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("storage full: {total2}+{size} > {max_total} bytes")));
-                }
+                return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("storage full: {total}+{size} > {max_total} bytes")));
             }
         }
 
         // Hash streaming
-        let sha256 = get_sha256(src)?;
-        let (dir, data_path, meta_path) = self.shardpath(&sha256);
+        let sha256 = match staged {
+            Some(sha256) => sha256.to_string(),
+            None => get_sha256(src)?,
+        };
+
+        if let Some(ns) = namespace
+            && let Some(max_ns) = self.cfg.get_namespace(ns).and_then(|c| c.get_max_size())
+        {
+            let stored = self.items_in(Some(ns))?.iter().filter(|m| m.sha256 != sha256).map(|m| m.size_bytes).sum::<u64>();
+            let used = stored.saturating_add(self.staged_in(ns)?).saturating_sub(own);
+            if used.saturating_add(size) > max_ns {
+                return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("namespace {ns} full: {used}+{size} > {max_ns} bytes")));
+            }
+        }
+
+        let (dir, data_path, _) = self.shardpath(&sha256);
+        let meta_path = self.meta_path(namespace, &sha256);
 
         fs::create_dir_all(&dir)?;
+        if let Some(parent) = meta_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
 
         // If data already exists, do not rewrite it
         if data_path.exists() {
@@ -133,20 +275,25 @@ impl DataStorage {
             if have != sha256 {
                 return Err(io::Error::new(io::ErrorKind::InvalidData, format!("store corruption: expected {sha256}, got {have} at {data_path:?}")));
             }
+        } else if staged.is_some() {
+            fs::rename(src, &data_path)?;
         } else {
             copy(src, &data_path)?;
         }
 
         let now = unix_now();
-        let expires = self.cfg.get_expiration().map(|d| now.saturating_add(d.as_secs()));
+        let expiration = namespace.and_then(|ns| self.cfg.get_namespace(ns)).and_then(|c| c.get_expiration()).or(self.cfg.get_expiration());
+        let expires = expiration.map(|d| now.saturating_add(d.as_secs()));
 
         let meta = DataItemMeta {
             sha256: sha256.clone(),
             size_bytes: size,
             created_unix: now,
             expires_unix: expires,
-            fname: src.file_name().map(|s| s.to_string_lossy().to_string()),
+            fname: fname.map(str::to_string).or_else(|| src.file_name().map(|s| s.to_string_lossy().to_string())),
             fmode: unix_mode,
+            namespace: namespace.map(str::to_string),
+            tags: tags.clone(),
         };
 
         // Write meta last (so presence of meta implies object is ready).
@@ -155,9 +302,14 @@ impl DataStorage {
         Ok(meta)
     }
 
-    /// Read metadata for an object, if it exists.
+    /// Read metadata for an object, if it exists. The default namespace comes first, then the others by name.
     pub fn meta(&self, sha256: &str) -> io::Result<Option<DataItemMeta>> {
-        let (_dir, _data_path, meta_path) = self.shardpath(sha256);
+        Ok(self.metas(sha256)?.into_iter().next())
+    }
+
+    /// Read metadata for an object in one namespace, if it exists.
+    pub fn meta_in(&self, namespace: Option<&str>, sha256: &str) -> io::Result<Option<DataItemMeta>> {
+        let meta_path = self.meta_path(namespace, sha256);
         if !meta_path.exists() {
             return Ok(None);
         }
@@ -166,38 +318,153 @@ impl DataStorage {
         Ok(Some(meta))
     }
 
+    /// Metadata for an object in every namespace holding it, default namespace first.
+    pub fn metas(&self, sha256: &str) -> io::Result<Vec<DataItemMeta>> {
+        let mut out = vec![];
+        if let Some(meta) = self.meta_in(None, sha256)? {
+            out.push(meta);
+        }
+        for ns in self.namespaces()? {
+            if let Some(meta) = self.meta_in(Some(&ns), sha256)? {
+                out.push(meta);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Namespaces holding items, by name
+    pub fn namespaces(&self) -> io::Result<Vec<String>> {
+        let dir = self.root.join(NAMESPACES_DIR);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut out = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|name| check_namespace(name).is_ok())
+            .collect::<Vec<_>>();
+        out.sort();
+        Ok(out)
+    }
+
+    /// Metadata of all items of all namespaces
+    pub fn items(&self) -> io::Result<Vec<DataItemMeta>> {
+        Ok(meta_tree(&self.root)?.iter().filter_map(|p| fs::read(p).ok()).filter_map(|b| serde_json::from_slice(&b).ok()).collect())
+    }
+
+    /// Metadata of all items of one namespace
+    pub fn items_in(&self, namespace: Option<&str>) -> io::Result<Vec<DataItemMeta>> {
+        Ok(self.items()?.into_iter().filter(|m| m.namespace.as_deref() == namespace).collect())
+    }
+
     /// Return the on-disk path for download/serve.
     pub fn uri(&self, sha256: &str) -> PathBuf {
         let (_dir, data_path, _meta_path) = self.shardpath(sha256);
         data_path
     }
 
-    /// Garbage-collect expired items, then enforce max_overall_size by oldest-first.
-    pub fn gc(&self) -> io::Result<()> {
-        self.expire()?;
+    /// Garbage-collect expired items and abandoned uploads, remove data no item refers to anymore, then enforce
+    /// the namespace quotas and max_overall_size by oldest-first. Pinned items are never removed.
+    pub fn gc(&self) -> io::Result<DataGcReport> {
+        let mut report = DataGcReport::default();
+        (report.expired, report.kept) = self.expire()?;
+        report.uploads = self.sweep_uploads()?;
 
-        if let Some(max_total) = self.cfg.get_max_overall_size() {
-            loop {
-                let total = self.total()?;
-                if total <= max_total {
+        // Metadata that cannot be read still refers to its data, the file name is the sha256
+        let mut refs = HashMap::<String, usize>::new();
+        for meta_path in meta_tree(&self.root)? {
+            if let Some(sha256) = meta_path.file_name().and_then(|s| s.to_str()).and_then(|s| s.strip_suffix(".meta.json")) {
+                *refs.entry(sha256.to_string()).or_default() += 1;
+            }
+        }
+        let mut sizes = HashMap::new();
+        for data_path in data_tree(&self.root)? {
+            let Some(sha256) = data_path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if refs.contains_key(sha256) {
+                sizes.insert(sha256.to_string(), fs::metadata(&data_path).map(|md| md.len()).unwrap_or_default());
+                continue;
+            }
+            match fs::remove_file(&data_path) {
+                Ok(_) => report.orphans += 1,
+                Err(e) => log::error!("Failed to remove data file {data_path:?}: {e}"),
+            }
+        }
+
+        // Sizes are known now, eviction walks the items once, oldest first
+        let mut items = self.items()?;
+        items.sort_by_key(|m| m.created_unix);
+        let mut evicted = vec![false; items.len()];
+        let mut quotas = self.cfg.get_namespaces().iter().filter_map(|(ns, c)| c.get_max_size().map(|max| (ns, max))).collect::<Vec<_>>();
+        quotas.sort();
+        for (ns, max) in quotas {
+            let in_ns = |m: &DataItemMeta| m.namespace.as_deref() == Some(ns.as_str());
+            let mut used = items.iter().filter(|m| in_ns(m)).map(|m| m.size_bytes).sum::<u64>();
+            for (i, meta) in items.iter().enumerate() {
+                if used <= max {
                     break;
                 }
+                if !in_ns(meta) || self.pins.pins(meta) {
+                    continue;
+                }
+                self.evict(meta, &mut refs, &mut sizes)?;
+                used = used.saturating_sub(meta.size_bytes);
+                evicted[i] = true;
+                report.evicted += 1;
+            }
+        }
 
-                // Delete oldest meta+data pair.
-                if let Some(oldest) = self.oldest()? {
-                    self.del(&oldest.sha256)?;
-                } else {
+        if let Some(max_total) = self.cfg.get_max_overall_size() {
+            let mut total = sizes.values().sum::<u64>();
+            for (i, meta) in items.iter().enumerate() {
+                if total <= max_total {
                     break;
                 }
+                if evicted[i] || self.pins.pins(meta) {
+                    continue;
+                }
+                total = total.saturating_sub(self.evict(meta, &mut refs, &mut sizes)?);
+                report.evicted += 1;
             }
         }
 
-        Ok(())
+        Ok(report)
     }
 
-    /// Deletes an object and its metadata from the storage. Best-effort: if files are missing, ignore and continue.
+    /// Deletes an object and its metadata in all namespaces from the storage. Best-effort: if files are missing, ignore and continue.
     pub fn del(&self, sha256: &str) -> io::Result<()> {
-        let (_dir, data_path, meta_path) = self.shardpath(sha256);
+        let mut namespaces = vec![None];
+        namespaces.extend(self.namespaces()?.into_iter().map(Some));
+        for ns in namespaces {
+            let meta_path = self.meta_path(ns.as_deref(), sha256);
+            if ns.is_some() && !meta_path.exists() {
+                continue;
+            }
+            if let Err(e) = fs::remove_file(&meta_path) {
+                // only log error, best effort.
+                log::error!("Failed to remove meta file {meta_path:?}: {e}");
+            }
+        }
+        self.del_data(sha256);
+        Ok(())
+    }
+
+    /// Remove an item to get under a size limit. Returns the bytes freed, which is the data size once the last item
+    /// referring to the data is gone.
+    fn evict(&self, meta: &DataItemMeta, refs: &mut HashMap<String, usize>, sizes: &mut HashMap<String, u64>) -> io::Result<u64> {
+        self.del_in(meta.namespace.as_deref(), &meta.sha256)?;
+        let left = refs.get_mut(&meta.sha256).map(|n| {
+            *n = n.saturating_sub(1);
+            *n
+        });
+        Ok(if left.unwrap_or_default() == 0 { sizes.remove(&meta.sha256).unwrap_or_default() } else { 0 })
+    }
+
+    /// Deletes the metadata of an object in one namespace. The data goes too when no other namespace refers to it.
+    pub fn del_in(&self, namespace: Option<&str>, sha256: &str) -> io::Result<()> {
+        let meta_path = self.meta_path(namespace, sha256);
         match fs::remove_file(&meta_path) {
             Ok(_) => (),
             Err(e) => {
@@ -205,6 +472,14 @@ impl DataStorage {
                 log::error!("Failed to remove meta file {meta_path:?}: {e}");
             }
         }
+        if self.metas(sha256)?.is_empty() {
+            self.del_data(sha256);
+        }
+        Ok(())
+    }
+
+    fn del_data(&self, sha256: &str) {
+        let (_dir, data_path, _meta_path) = self.shardpath(sha256);
         match fs::remove_file(&data_path) {
             Ok(_) => (),
             Err(e) => {
@@ -212,7 +487,17 @@ impl DataStorage {
                 log::error!("Failed to remove data file {data_path:?}: {e}");
             }
         }
-        Ok(())
+    }
+
+    /// Metadata path of an object: next to the data for the default namespace, `ns/<namespace>/aa/bb/` otherwise
+    fn meta_path(&self, namespace: Option<&str>, sha256: &str) -> PathBuf {
+        let (dir, _data_path, meta_path) = self.shardpath(sha256);
+        match namespace {
+            None => meta_path,
+            Some(ns) => {
+                self.root.join(NAMESPACES_DIR).join(ns).join(dir.strip_prefix(&self.root).unwrap_or(&dir)).join(format!("{sha256}.meta.json"))
+            }
+        }
     }
 
     // Shard: aa/bb/<fullsha>.(bin|json)
@@ -226,51 +511,27 @@ impl DataStorage {
         (dir, data_path, meta_path)
     }
 
-    fn expire(&self) -> io::Result<()> {
+    /// Removes expired items, except the pinned ones. Returns how many were removed and kept.
+    fn expire(&self) -> io::Result<(usize, usize)> {
         let now = unix_now();
-        for meta_path in meta_tree(&self.root)? {
-            if let Ok(bytes) = fs::read(&meta_path)
-                && let Ok(meta) = serde_json::from_slice::<DataItemMeta>(&bytes)
-                && let Some(exp) = meta.expires_unix
-                && exp <= now
-            {
-                self.del(&meta.sha256)?;
+        let (mut expired, mut kept) = (0, 0);
+        for meta in self.items()? {
+            if meta.expires_unix.is_none_or(|exp| exp > now) {
+                continue;
             }
+            if self.pins.pins(&meta) {
+                kept += 1;
+                continue;
+            }
+            self.del_in(meta.namespace.as_deref(), &meta.sha256)?;
+            expired += 1;
         }
-        Ok(())
-    }
-
-    fn oldest(&self) -> io::Result<Option<DataItemMeta>> {
-        let mut best: Option<DataItemMeta> = None;
-
-        for meta_path in meta_tree(&self.root)? {
-            let bytes = match fs::read(&meta_path) {
-                Ok(b) => b,
-                Err(_) => continue,
-            };
-            let meta: DataItemMeta = match serde_json::from_slice(&bytes) {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-
-            best = match best {
-                None => Some(meta),
-                Some(cur) => {
-                    if meta.created_unix < cur.created_unix {
-                        Some(meta)
-                    } else {
-                        Some(cur)
-                    }
-                }
-            };
-        }
-
-        Ok(best)
+        Ok((expired, kept))
     }
 
-    /// Computes the total size of all data files in bytes.
-    fn total(&self) -> io::Result<u64> {
-        let mut total = 0u64;
+    /// Computes the total size of all data files and staged uploads in bytes.
+    pub(crate) fn total(&self) -> io::Result<u64> {
+        let mut total = self.staged_total()?;
         for data_path in data_tree(&self.root)? {
             if let Ok(md) = fs::metadata(&data_path) {
                 total = total.saturating_add(md.len());
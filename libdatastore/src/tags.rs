@@ -0,0 +1,47 @@
+use std::{collections::BTreeMap, io};
+
+/// Parse comma-separated `key=value` tags, e.g. `team=web,env=prod`.
+pub fn parse_tags(input: &str) -> io::Result<BTreeMap<String, String>> {
+    let mut tags = BTreeMap::new();
+    for item in input.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("tag \"{item}\" must be key=value")));
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("tag \"{item}\" has no key")));
+        }
+        tags.insert(key.to_string(), value.trim().to_string());
+    }
+    Ok(tags)
+}
+
+/// Tag search: all terms must match. A term is `key=value`, or only `key` for any value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    terms: Vec<(String, Option<String>)>,
+}
+
+impl TagFilter {
+    /// Parse comma-separated terms, e.g. `team=web,release`
+    pub fn parse(input: &str) -> Self {
+        let terms = input
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(|term| match term.split_once('=') {
+                Some((key, value)) => (key.trim().to_string(), Some(value.trim().to_string())),
+                None => (term.to_string(), None),
+            })
+            .collect();
+        Self { terms }
+    }
+
+    pub fn matches(&self, tags: &BTreeMap<String, String>) -> bool {
+        self.terms.iter().all(|(key, value)| match (tags.get(key), value) {
+            (Some(have), Some(want)) => have == want,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+    }
+}
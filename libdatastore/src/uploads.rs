@@ -0,0 +1,275 @@
+//! Staged uploads.
+//!
+//! Big objects arrive in chunks, each with its own checksum. The upload id is
+//! the resume token: after a broken connection the client asks how much was
+//! received and continues from there. The object is added to the datastore
+//! once all bytes are in and the sha256 of the whole object is verified.
+//! Upload ids are random, and an upload belongs to the user that started it.
+//! Staged bytes count towards the size limits of the datastore and of the
+//! namespace, a chunk that would exceed them is rejected.
+
+use crate::{
+    resources::{DataItemMeta, DataStorage, check_namespace},
+    util::{get_sha256, json_write, unix_now},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Seek, SeekFrom, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Directory of the staged uploads under the datastore root
+const UPLOADS_DIR: &str = "uploads";
+
+/// Chunk size suggested to the clients
+pub const UPLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Uploads without a new chunk for this long are dropped by the garbage collection
+pub const UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// State of a staged upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataUpload {
+    /// Upload id, also the resume token
+    pub id: String,
+    pub size_bytes: u64,
+    /// Bytes received so far, the offset of the next chunk
+    pub received_bytes: u64,
+    /// Expected sha256 of the whole object, if the client knows it upfront
+    pub sha256: Option<String>,
+    pub fname: Option<String>,
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Session user that started the upload
+    #[serde(default)]
+    pub owner: String,
+    pub created_unix: u64,
+    pub updated_unix: u64,
+}
+
+impl DataUpload {
+    pub fn is_complete(&self) -> bool {
+        self.received_bytes == self.size_bytes
+    }
+
+    /// Only the user that started the upload can continue it
+    pub fn is_owned_by(&self, uid: &str) -> bool {
+        !self.owner.is_empty() && self.owner == uid
+    }
+}
+
+/// Upload ids are hex only, so they are safe as file names
+fn check_upload_id(id: &str) -> io::Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid upload id \"{id}\"")));
+    }
+    Ok(())
+}
+
+/// Upload id is the resume token, so it must not be guessable
+fn new_upload_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+impl DataStorage {
+    fn upload_paths(&self, id: &str) -> (PathBuf, PathBuf) {
+        let dir = self.root().join(UPLOADS_DIR);
+        (dir.join(format!("{id}.json")), dir.join(format!("{id}.part")))
+    }
+
+    /// Start a staged upload of `size_bytes` for the `owner`. The metadata is the same as for [`DataStorage::add_to`].
+    pub fn upload_start(
+        &self, owner: &str, size_bytes: u64, sha256: Option<&str>, namespace: Option<&str>, fname: Option<&str>, tags: &BTreeMap<String, String>,
+    ) -> io::Result<DataUpload> {
+        if let Some(ns) = namespace {
+            check_namespace(ns)?;
+        }
+        if let Some(max) = self.cfg().get_max_item_size()
+            && size_bytes > max
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("item too big: {size_bytes} > {max} bytes")));
+        }
+        if let Some(sha256) = sha256
+            && (sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid sha256 \"{sha256}\"")));
+        }
+
+        let now = unix_now();
+        let upload = DataUpload {
+            id: new_upload_id(),
+            size_bytes,
+            received_bytes: 0,
+            sha256: sha256.map(str::to_lowercase),
+            fname: fname.map(str::to_string),
+            namespace: namespace.map(str::to_string),
+            tags: tags.clone(),
+            owner: owner.to_string(),
+            created_unix: now,
+            updated_unix: now,
+        };
+
+        let (state_path, part_path) = self.upload_paths(&upload.id);
+        fs::create_dir_all(self.root().join(UPLOADS_DIR))?;
+        fs::File::create(part_path)?;
+        json_write(&state_path, &upload)?;
+        Ok(upload)
+    }
+
+    /// State of a staged upload, `None` if it is unknown
+    pub fn upload(&self, id: &str) -> io::Result<Option<DataUpload>> {
+        check_upload_id(id)?;
+        let (state_path, _) = self.upload_paths(id);
+        match fs::read(&state_path) {
+            Ok(b) => serde_json::from_slice(&b).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Append a chunk at `offset`, which must be the number of bytes received so far.
+    /// `sha256` is the checksum of the chunk.
+    pub fn upload_chunk(&self, id: &str, offset: u64, data: &[u8], sha256: &str) -> io::Result<DataUpload> {
+        let mut upload = self.upload(id)?.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("unknown upload {id}")))?;
+        if offset != upload.received_bytes {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("chunk at offset {offset}, expected offset {}", upload.received_bytes)));
+        }
+        if offset.saturating_add(data.len() as u64) > upload.size_bytes {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("chunk goes past the upload size of {} bytes", upload.size_bytes)));
+        }
+        let have = format!("{:x}", Sha256::digest(data));
+        if !have.eq_ignore_ascii_case(sha256) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("chunk checksum mismatch: got {have}, expected {sha256}")));
+        }
+
+        let len = data.len() as u64;
+        if let Some(max) = self.cfg().get_max_overall_size() {
+            let total = self.total()?;
+            if total.saturating_add(len) > max {
+                return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("storage full: {total}+{len} > {max} bytes")));
+            }
+        }
+        if let Some(ns) = upload.namespace.as_deref()
+            && let Some(max) = self.cfg().get_namespace(ns).and_then(|c| c.get_max_size())
+        {
+            let used = self.items_in(Some(ns))?.iter().map(|m| m.size_bytes).sum::<u64>().saturating_add(self.staged_in(ns)?);
+            if used.saturating_add(len) > max {
+                return Err(io::Error::new(io::ErrorKind::OutOfMemory, format!("namespace {ns} full: {used}+{len} > {max} bytes")));
+            }
+        }
+
+        // Drop whatever a previous, unacknowledged write left behind
+        let (state_path, part_path) = self.upload_paths(id);
+        let mut part = fs::OpenOptions::new().write(true).open(&part_path)?;
+        part.set_len(offset)?;
+        part.seek(SeekFrom::Start(offset))?;
+        part.write_all(data)?;
+        part.sync_all()?;
+
+        upload.received_bytes += data.len() as u64;
+        upload.updated_unix = unix_now();
+        json_write(&state_path, &upload)?;
+        Ok(upload)
+    }
+
+    /// Verify the complete upload and add it to the datastore. The staged upload is removed,
+    /// also when its content does not match the expected sha256.
+    pub fn upload_finish(&self, id: &str) -> io::Result<DataItemMeta> {
+        let upload = self.upload(id)?.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("unknown upload {id}")))?;
+        if !upload.is_complete() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("upload is incomplete: {} of {} bytes received", upload.received_bytes, upload.size_bytes),
+            ));
+        }
+
+        // Hashed once here, the staged file is then moved into the store as it is
+        let (_, part_path) = self.upload_paths(id);
+        let have = get_sha256(&part_path)?;
+        if let Some(want) = &upload.sha256
+            && &have != want
+        {
+            self.upload_abort(id)?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("checksum mismatch: got {have}, expected {want}")));
+        }
+
+        let meta = self.put(&part_path, Some(&have), upload.namespace.as_deref(), upload.fname.as_deref(), &upload.tags)?;
+        self.upload_abort(id)?;
+        Ok(meta)
+    }
+
+    /// Drop a staged upload. Returns `false` if it is unknown.
+    pub fn upload_abort(&self, id: &str) -> io::Result<bool> {
+        check_upload_id(id)?;
+        let (state_path, part_path) = self.upload_paths(id);
+        let known = state_path.exists();
+        for p in [part_path, state_path] {
+            match fs::remove_file(&p) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(known)
+    }
+
+    /// Staged uploads, oldest first
+    pub fn uploads(&self) -> io::Result<Vec<DataUpload>> {
+        let dir = self.root().join(UPLOADS_DIR);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut out = vec![];
+        for ent in fs::read_dir(dir)? {
+            let p = ent?.path();
+            if p.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(upload) = fs::read(&p).ok().and_then(|b| serde_json::from_slice::<DataUpload>(&b).ok()) {
+                out.push(upload);
+            }
+        }
+        out.sort_by_key(|u| u.created_unix);
+        Ok(out)
+    }
+
+    /// Bytes received by the staged uploads of the namespace
+    pub(crate) fn staged_in(&self, namespace: &str) -> io::Result<u64> {
+        Ok(self.uploads()?.iter().filter(|u| u.namespace.as_deref() == Some(namespace)).map(|u| u.received_bytes).sum())
+    }
+
+    /// Size of all staged upload files
+    pub(crate) fn staged_total(&self) -> io::Result<u64> {
+        let dir = self.root().join(UPLOADS_DIR);
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut total = 0u64;
+        for ent in fs::read_dir(dir)? {
+            let p = ent?.path();
+            if p.extension().and_then(|s| s.to_str()) == Some("part")
+                && let Ok(md) = fs::metadata(&p)
+            {
+                total = total.saturating_add(md.len());
+            }
+        }
+        Ok(total)
+    }
+
+    /// Drop uploads without a new chunk within the [`UPLOAD_TTL`]. Returns how many were dropped.
+    pub(crate) fn sweep_uploads(&self) -> io::Result<usize> {
+        let cutoff = unix_now().saturating_sub(UPLOAD_TTL.as_secs());
+        let mut dropped = 0;
+        for upload in self.uploads()?.into_iter().filter(|u| u.updated_unix < cutoff) {
+            if self.upload_abort(&upload.id)? {
+                dropped += 1;
+            }
+        }
+        Ok(dropped)
+    }
+}
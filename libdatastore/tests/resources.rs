@@ -1,5 +1,10 @@
-use libdatastore::{cfg::DataStorageConfig, resources::DataStorage};
+use libdatastore::{
+    cfg::{DataNamespaceConfig, DataStorageConfig},
+    resources::{DataGcReport, DataPins, DataStorage},
+    tags::{TagFilter, parse_tags},
+};
 use std::{
+    collections::BTreeMap,
     fs,
     io::{self, Write},
     os::unix::fs::PermissionsExt,
@@ -145,3 +150,221 @@ fn gc_oldest_prefers_older_timestamp() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn namespaces_share_data_but_not_metadata() -> anyhow::Result<()> {
+    let root = store_root();
+    let src = root.path().join("src.bin");
+    write_file(&src, 32, 0o644)?;
+
+    let ds = DataStorage::new(DataStorageConfig::new(), root.path().join("store"))?;
+    let tags = parse_tags("team=web, env=prod")?;
+    let web = ds.add_to(&src, Some("web"), Some("/web/key"), &tags)?;
+    let ops = ds.add_to(&src, Some("ops"), Some("/ops/key"), &BTreeMap::new())?;
+    assert_eq!(web.sha256, ops.sha256);
+    assert!(ds.add_to(&src, Some("../escape"), None, &BTreeMap::new()).is_err());
+
+    assert_eq!(ds.namespaces()?, ["ops", "web"]);
+    assert!(ds.meta_in(None, &web.sha256)?.is_none());
+    let meta = ds.meta_in(Some("web"), &web.sha256)?.expect("web meta");
+    assert_eq!((meta.fname.as_deref(), meta.tags.get("team").map(String::as_str)), (Some("/web/key"), Some("web")));
+    assert_eq!(ds.metas(&web.sha256)?.len(), 2);
+
+    ds.del_in(Some("web"), &web.sha256)?;
+    assert!(ds.uri(&web.sha256).exists(), "data is still used by the ops namespace");
+    ds.del_in(Some("ops"), &web.sha256)?;
+    assert!(!ds.uri(&web.sha256).exists());
+
+    Ok(())
+}
+
+#[test]
+fn tag_filter_matches_all_terms() -> anyhow::Result<()> {
+    let tags = parse_tags("team=web,env=prod,release=")?;
+    assert!(TagFilter::parse("team=web").matches(&tags));
+    assert!(TagFilter::parse("team=web,release").matches(&tags));
+    assert!(TagFilter::parse("").matches(&tags));
+    assert!(!TagFilter::parse("team=web,env=dev").matches(&tags));
+    assert!(!TagFilter::parse("owner").matches(&tags));
+    assert!(parse_tags("team").is_err());
+    assert!(parse_tags("=web").is_err());
+    Ok(())
+}
+
+#[test]
+fn namespace_quota_and_retention() -> anyhow::Result<()> {
+    let root = store_root();
+    let cfg = DataStorageConfig::new()
+        .namespace("small", DataNamespaceConfig::new().max_size(150))
+        .namespace("brief", DataNamespaceConfig::new().expiration(Duration::from_secs(0)));
+    let ds = DataStorage::new(cfg, root.path().join("store"))?;
+
+    let (f1, f2) = (root.path().join("f1.bin"), root.path().join("f2.bin"));
+    write_file(&f1, 100, 0o644)?;
+    fs::write(&f2, vec![0xCDu8; 100])?;
+
+    ds.add_to(&f1, Some("small"), None, &BTreeMap::new())?;
+    assert_eq!(ds.add_to(&f2, Some("small"), None, &BTreeMap::new()).unwrap_err().kind(), io::ErrorKind::OutOfMemory);
+    assert!(ds.add_to(&f2, None, None, &BTreeMap::new()).is_ok(), "the default namespace has no quota");
+    assert!(ds.add_to(&f1, Some("small"), None, &BTreeMap::new()).is_ok(), "replacing an item does not count twice");
+
+    let brief = ds.add_to(&f2, Some("brief"), None, &BTreeMap::new())?;
+    assert!(brief.expires_unix.is_some());
+    assert_eq!(ds.gc()?.expired, 1);
+    assert!(ds.meta_in(Some("brief"), &brief.sha256)?.is_none());
+    assert!(ds.uri(&brief.sha256).exists(), "data is still used by the default namespace");
+
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_pinned_items_and_drops_orphans() -> anyhow::Result<()> {
+    let root = store_root();
+    let cfg = DataStorageConfig::new().expiration(Duration::from_secs(0));
+    let mut ds = DataStorage::new(cfg, root.path().join("store"))?;
+
+    let mut items = vec![];
+    for (i, name) in ["/keys/a", "/ring/b", "/tmp/c"].iter().enumerate() {
+        let src = root.path().join(format!("{i}.bin"));
+        fs::write(&src, format!("item {i}"))?;
+        items.push(ds.add_to(&src, None, Some(name), &BTreeMap::new())?);
+    }
+
+    let mut pins = DataPins::new();
+    pins.name(None, "/keys/a");
+    pins.prefix(None, "/ring/");
+    pins.prefix(Some("other"), "/tmp/");
+    ds.set_pins(pins);
+
+    let orphan = ds.uri(&"f".repeat(64));
+    fs::create_dir_all(orphan.parent().unwrap())?;
+    fs::write(&orphan, "nobody refers to me")?;
+
+    assert_eq!(ds.gc()?, DataGcReport { expired: 1, evicted: 0, orphans: 1, kept: 2, uploads: 0 });
+    assert!(ds.meta(&items[0].sha256)?.is_some());
+    assert!(ds.meta(&items[1].sha256)?.is_some());
+    assert!(ds.meta(&items[2].sha256)?.is_none());
+    assert!(!orphan.exists());
+
+    Ok(())
+}
+
+#[test]
+fn gc_evicts_oldest_unpinned_items_over_the_quota() -> anyhow::Result<()> {
+    let root = store_root();
+    let store_dir = root.path().join("store");
+    let ds = DataStorage::new(DataStorageConfig::new(), &store_dir)?;
+
+    let mut items = vec![];
+    for (i, name) in ["/a", "/b", "/c"].iter().enumerate() {
+        let src = root.path().join(format!("{i}.bin"));
+        fs::write(&src, vec![i as u8; 100])?;
+        let mut meta = ds.add_to(&src, Some("small"), Some(name), &BTreeMap::new())?;
+        meta.created_unix = i as u64 + 1;
+        let sha = &meta.sha256;
+        fs::write(store_dir.join("ns/small").join(&sha[0..2]).join(&sha[2..4]).join(format!("{sha}.meta.json")), serde_json::to_vec(&meta)?)?;
+        items.push(meta);
+    }
+
+    let mut ds = DataStorage::new(DataStorageConfig::new().namespace("small", DataNamespaceConfig::new().max_size(250)), &store_dir)?;
+    let mut pins = DataPins::new();
+    pins.spread("sm", "/a", false);
+    ds.set_pins(pins);
+
+    assert_eq!(ds.gc()?.evicted, 1);
+    assert!(ds.meta_in(Some("small"), &items[0].sha256)?.is_some(), "pinned through a namespace taken from the context");
+    assert!(ds.meta_in(Some("small"), &items[1].sha256)?.is_none());
+    assert!(!ds.uri(&items[1].sha256).exists());
+    assert!(ds.meta_in(Some("small"), &items[2].sha256)?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_data_of_unreadable_metadata() -> anyhow::Result<()> {
+    let root = store_root();
+    let ds = DataStorage::new(DataStorageConfig::new(), root.path().join("store"))?;
+    let src = root.path().join("item.bin");
+    write_file(&src, 64, 0o644)?;
+    let meta = ds.add(&src)?;
+
+    let meta_path = ds.uri(&meta.sha256).with_file_name(format!("{}.meta.json", meta.sha256));
+    fs::write(&meta_path, "{{{")?;
+
+    assert_eq!(ds.gc()?.orphans, 0);
+    assert!(ds.uri(&meta.sha256).exists());
+
+    Ok(())
+}
+
+#[test]
+fn staged_upload_resumes_and_verifies_checksum() -> anyhow::Result<()> {
+    use sha2::{Digest, Sha256};
+    let sha = |b: &[u8]| format!("{:x}", Sha256::digest(b));
+
+    let root = store_root();
+    let ds = DataStorage::new(DataStorageConfig::new(), root.path())?;
+    let data = (0..3000u32).map(|n| n as u8).collect::<Vec<_>>();
+    let tags = parse_tags("kind=iso")?;
+
+    let up = ds.upload_start("alice", data.len() as u64, Some(&sha(&data)), Some("web"), Some("/iso/big.img"), &tags)?;
+    assert_eq!(up.id.len(), 32);
+    assert!(up.is_owned_by("alice") && !up.is_owned_by("bob"));
+    let other = ds.upload_start("alice", 1, None, None, Some("/iso/big.img"), &tags)?;
+    assert_ne!(other.id, up.id);
+    assert!(ds.upload_abort(&other.id)?);
+    ds.upload_chunk(&up.id, 0, &data[..1000], &sha(&data[..1000]))?;
+
+    // A lost response: the client resends from a stale offset, or a corrupted chunk
+    assert_eq!(ds.upload_chunk(&up.id, 0, &data[..1000], &sha(&data[..1000])).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(ds.upload_chunk(&up.id, 1000, &data[1000..2000], &sha(b"other")).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(ds.upload_finish(&up.id).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let resumed = ds.upload(&up.id)?.unwrap();
+    assert_eq!(resumed.received_bytes, 1000);
+    ds.upload_chunk(&up.id, 1000, &data[1000..], &sha(&data[1000..]))?;
+
+    let meta = ds.upload_finish(&up.id)?;
+    assert_eq!(meta.sha256, sha(&data));
+    assert_eq!(meta.namespace.as_deref(), Some("web"));
+    assert_eq!(meta.tags, tags);
+    assert_eq!(fs::read(ds.uri(&meta.sha256))?, data);
+    assert!(ds.upload(&up.id)?.is_none());
+
+    // Content not matching the announced checksum is dropped
+    let bad = ds.upload_start("alice", 4, Some(&sha(b"abcd")), None, None, &BTreeMap::new())?;
+    ds.upload_chunk(&bad.id, 0, b"abce", &sha(b"abce"))?;
+    assert_eq!(ds.upload_finish(&bad.id).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(ds.uploads()?.is_empty());
+    assert!(ds.upload("../meta").is_err());
+    Ok(())
+}
+
+#[test]
+fn staged_uploads_count_towards_the_limits() -> anyhow::Result<()> {
+    use sha2::{Digest, Sha256};
+    let sha = |b: &[u8]| format!("{:x}", Sha256::digest(b));
+
+    let root = store_root();
+    let cfg = DataStorageConfig::new().max_overall_size(250).namespace("small", DataNamespaceConfig::new().max_size(150));
+    let ds = DataStorage::new(cfg, root.path())?;
+    let (a, b) = (vec![1u8; 100], vec![2u8; 100]);
+
+    let first = ds.upload_start("alice", 100, None, Some("small"), None, &BTreeMap::new())?;
+    let second = ds.upload_start("alice", 100, None, Some("small"), None, &BTreeMap::new())?;
+    ds.upload_chunk(&first.id, 0, &a, &sha(&a))?;
+    assert_eq!(ds.upload_chunk(&second.id, 0, &b, &sha(&b)).unwrap_err().kind(), io::ErrorKind::OutOfMemory);
+
+    let third = ds.upload_start("alice", 100, None, None, None, &BTreeMap::new())?;
+    ds.upload_chunk(&third.id, 0, &b, &sha(&b))?;
+    let c = vec![3u8; 100];
+    let fourth = ds.upload_start("alice", 100, None, None, None, &BTreeMap::new())?;
+    assert_eq!(ds.upload_chunk(&fourth.id, 0, &c, &sha(&c)).unwrap_err().kind(), io::ErrorKind::OutOfMemory);
+
+    // Completing moves the staged bytes into the store, they are not counted twice
+    let meta = ds.upload_finish(&first.id)?;
+    assert_eq!(fs::read(ds.uri(&meta.sha256))?, a);
+    ds.upload_finish(&third.id)?;
+
+    Ok(())
+}
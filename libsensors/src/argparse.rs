@@ -3,6 +3,7 @@ use crate::sspec::SensorConf;
 pub trait SensorArgs {
     fn arg_str(&self, key: &str) -> Option<String>;
     fn arg_u64(&self, key: &str) -> Option<u64>;
+    fn arg_f64(&self, key: &str) -> Option<f64>;
     fn arg_bool(&self, key: &str) -> Option<bool>;
     fn arg_str_array(&self, key: &str) -> Option<Vec<String>>;
     fn arg_duration(&self, key: &str) -> Option<std::time::Duration>;
@@ -17,6 +18,10 @@ impl SensorArgs for SensorConf {
         self.args().get(key).and_then(|v| v.as_i64()).map(|i| i as u64)
     }
 
+    fn arg_f64(&self, key: &str) -> Option<f64> {
+        self.args().get(key).and_then(|v| v.as_f64())
+    }
+
     fn arg_bool(&self, key: &str) -> Option<bool> {
         self.args().get(key).and_then(|v| v.as_bool())
     }
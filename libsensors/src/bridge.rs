@@ -1,28 +1,177 @@
+use crate::sspec::{OverflowPolicy, QueueConf};
 use libsysinspect::intp::actproc::response::ActionResponse;
 use libsysinspect::reactor::evtproc::EventProcessor;
 use serde_json::Value as JsonValue;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, Notify};
 
-/// Build an emitter closure that converts a sensor JSON event into an ActionResponse
-/// and registers it into the Sysinspect reactor.
+/// How long the forwarder waits for new events before it checks if the queue is still in use.
+const IDLE: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct Pending {
+    events: VecDeque<JsonValue>,
+    overflows: u64,
+}
+
+/// Bounded event queue of one sensor.
 ///
-/// This is the Sysinspect boundary. Everything "sensor runtime" should call this,
-/// not re-implement it.
-pub fn reactor_emitter(sid: String, reactor: Option<Arc<Mutex<EventProcessor>>>) -> impl Fn(JsonValue) + Send + Sync + Clone + 'static {
-    move |ev: JsonValue| {
-        log::debug!("Registering event from sensor to reactor: {ev}");
+/// Sensors push their events here without waiting for the reactor. A single
+/// forwarder task per queue takes the events out in batches, so the whole batch
+/// is registered under one lock of the reactor. While the reactor is busy, the
+/// events wait in the queue, and when it is full the overflow policy decides
+/// which events are lost.
+pub struct EventQueue {
+    sid: String,
+    cfg: QueueConf,
+    attached: bool,
+    pending: std::sync::Mutex<Pending>,
+    notify: Notify,
+    dropped: AtomicU64,
+    batched: AtomicU64,
+    /// Last time an event was queued or passed to the reactor
+    beat: std::sync::Mutex<Option<Instant>>,
+}
+
+impl EventQueue {
+    /// Creates a queue for the sensor and starts its forwarder into the reactor.
+    /// Without a reactor every event is dropped.
+    pub fn new(sid: &str, cfg: QueueConf, reactor: Option<Arc<Mutex<EventProcessor>>>) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            sid: sid.to_string(),
+            cfg,
+            attached: reactor.is_some(),
+            pending: std::sync::Mutex::new(Pending::default()),
+            notify: Notify::new(),
+            dropped: AtomicU64::new(0),
+            batched: AtomicU64::new(0),
+            beat: std::sync::Mutex::new(None),
+        });
+
+        if let Some(reactor) = reactor {
+            tokio::spawn(Self::forward(queue.clone(), reactor));
+        }
 
-        let Some(reactor) = reactor.clone() else {
-            log::warn!("No reactor attached for sensor '{}': {}", sid, ev);
+        queue
+    }
+
+    /// Queues an event, applying the overflow policy if the queue is full.
+    pub fn push(&self, ev: JsonValue) {
+        log::debug!("Queueing event from sensor '{}': {ev}", self.sid);
+        self.beat();
+
+        if !self.attached {
+            log::warn!("No reactor attached for sensor '{}': {}", self.sid, ev);
+            self.dropped.fetch_add(1, Ordering::Relaxed);
             return;
-        };
+        }
 
-        let eid = ev.get("eid").and_then(|v| v.as_str()).map(|s| s.to_string()).unwrap_or_else(|| sid.clone());
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            if pending.events.len() >= self.cfg.size.max(1) {
+                pending.overflows += 1;
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                match self.cfg.overflow {
+                    OverflowPolicy::DropNewest => return,
+                    OverflowPolicy::Sample if !pending.overflows.is_multiple_of(self.cfg.sample.max(1)) => return,
+                    OverflowPolicy::DropOldest | OverflowPolicy::Sample => {
+                        pending.events.pop_front();
+                    }
+                }
+            }
+            pending.events.push_back(ev);
+        }
 
-        tokio::spawn(async move {
-            let response = ActionResponse::from_sensor(ev);
-            reactor.lock().await.receiver().register(eid, response);
-        });
+        self.notify.notify_one();
+    }
+
+    /// Number of events waiting for the reactor.
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).events.len()
+    }
+
+    /// Returns true if no events are waiting for the reactor.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of events lost to the overflow policy or for the lack of a reactor.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of events registered together with other events in one batch.
+    pub fn batched(&self) -> u64 {
+        self.batched.load(Ordering::Relaxed)
+    }
+
+    /// Time since an event was last queued or passed to the reactor, i.e. since the sensor was last seen working.
+    pub fn heartbeat(&self) -> Option<Duration> {
+        self.beat.lock().unwrap_or_else(|e| e.into_inner()).map(|t| t.elapsed())
+    }
+
+    fn beat(&self) {
+        *self.beat.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    }
+
+    fn take(&self) -> Vec<JsonValue> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let n = pending.events.len().min(self.cfg.batch.max(1));
+        pending.events.drain(..n).collect()
+    }
+
+    /// Passes queued events to the reactor until the queue is no longer used elsewhere.
+    async fn forward(queue: Arc<Self>, reactor: Arc<Mutex<EventProcessor>>) {
+        loop {
+            let _ = tokio::time::timeout(IDLE, queue.notify.notified()).await;
+
+            // The reactor is locked only for a batch, not on every idle wakeup
+            while !queue.is_empty() {
+                // Events are taken only once the reactor is free, so they pile up in the queue meanwhile
+                let mut reactor = reactor.lock().await;
+                let batch = queue.take();
+                if batch.is_empty() {
+                    break;
+                }
+
+                let n = batch.len() as u64;
+                for ev in batch {
+                    let eid = ev.get("eid").and_then(|v| v.as_str()).map(|s| s.to_string()).unwrap_or_else(|| queue.sid.clone());
+                    reactor.receiver().register(eid, ActionResponse::from_sensor(ev));
+                }
+                drop(reactor);
+                queue.beat();
+
+                if n > 1 {
+                    queue.batched.fetch_add(n, Ordering::Relaxed);
+                }
+            }
+
+            if Arc::strong_count(&queue) == 1 {
+                log::debug!("Event queue of sensor '{}' is closed", queue.sid);
+                return;
+            }
+        }
     }
 }
+
+/// Build an emitter closure that converts a sensor JSON event into an ActionResponse
+/// and registers it into the Sysinspect reactor.
+///
+/// This is the Sysinspect boundary. Everything "sensor runtime" should call this,
+/// not re-implement it. Events go through an [`EventQueue`] with default settings.
+pub fn reactor_emitter(sid: String, reactor: Option<Arc<Mutex<EventProcessor>>>) -> impl Fn(JsonValue) + Send + Sync + Clone + 'static {
+    queue_emitter(EventQueue::new(&sid, QueueConf::default(), reactor))
+}
+
+/// Build an emitter closure that pushes sensor events into the given queue.
+pub fn queue_emitter(queue: Arc<EventQueue>) -> impl Fn(JsonValue) + Send + Sync + Clone + 'static {
+    move |ev: JsonValue| queue.push(ev)
+}
@@ -1,3 +1,5 @@
+#[cfg(target_os = "linux")]
+use super::inotify::{Excludes, FileHashes, FsChange, Inotify, coalesce};
 use super::sensor::{Sensor, SensorEvent};
 use crate::argparse::SensorArgs;
 use crate::sspec::SensorConf;
@@ -6,6 +8,17 @@ use colored::Colorize;
 use filescream::{FileScream, FileScreamConfig, events::FileScreamEvent};
 use omnitrace_core::callbacks::Callback;
 use std::{fmt, time::Duration};
+#[cfg(target_os = "linux")]
+use std::{fs, io, path::PathBuf};
+
+/// Default coalescing window of the kernel backend
+#[cfg(target_os = "linux")]
+const DEFAULT_COALESCE: Duration = Duration::from_millis(200);
+
+/// Files larger than this are not hashed by default
+#[cfg(target_os = "linux")]
+const DEFAULT_HASH_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
 #[derive(Clone)]
 pub struct FsNotifySensor {
     sid: String,
@@ -18,31 +31,21 @@ impl fmt::Debug for FsNotifySensor {
     }
 }
 
-#[async_trait]
-impl Sensor for FsNotifySensor {
-    fn new(id: String, cfg: SensorConf) -> Self {
-        Self { sid: id, cfg }
-    }
-
-    fn id() -> String {
-        "sys.filesystem".to_string()
+impl FsNotifySensor {
+    fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", FsNotifySensor::id(), if self.cfg.tag().is_none() { "" } else { "@" }, self.cfg.tag().unwrap_or(""))
     }
 
-    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
-        // args
-        let Some(path) = self.cfg.arg_str("path") else {
-            log::warn!("[{}] '{}' missing args.path; not starting", Self::id().bright_magenta(), self.sid);
-            return;
-        };
-
+    /// Watches with the `FileScream` poller, which knows only created, changed and deleted files.
+    async fn run_poll(&self, path: &str, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
         let locked = self.cfg.arg_bool("locked").unwrap_or(false);
         let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(3));
 
-        log::info!("[{}] '{}' watching '{}' with pulse {:?} and opts {:?}", Self::id().bright_magenta(), self.sid, path, pulse, self.cfg.opts());
+        log::info!("[{}] '{}' polling '{}' with pulse {:?} and opts {:?}", Self::id().bright_magenta(), self.sid, path, pulse, self.cfg.opts());
 
         // build sensor
         let mut fs = FileScream::new(Some(FileScreamConfig::default().pulse(pulse)));
-        fs.watch(&path);
+        fs.watch(path);
 
         // build mask
         let mut mask = filescream::events::FileScreamMask::empty();
@@ -56,16 +59,16 @@ impl Sensor for FsNotifySensor {
                     "created" => mask |= filescream::events::FileScreamMask::CREATED,
                     "changed" => mask |= filescream::events::FileScreamMask::CHANGED,
                     "deleted" => mask |= filescream::events::FileScreamMask::REMOVED,
+                    "attrib" | "renamed" => log::warn!("sys.filesystem '{}' opt '{}' needs the inotify backend", self.sid, o),
                     _ => log::warn!("sys.filesystem '{}' unknown opt '{}'", self.sid, o),
                 }
             }
         }
 
         let (tx, mut rx) = tokio::sync::mpsc::channel::<serde_json::Value>(0xfff);
-        let lstid = format!("{}{}{}", FsNotifySensor::id(), if self.cfg.tag().is_none() { "" } else { "@" }, self.cfg.tag().unwrap_or(""));
         let mut hub = omnitrace_core::callbacks::CallbackHub::<FileScreamEvent>::new();
         hub.set_result_channel(tx);
-        hub.add(BridgeCb { mask: mask.bits(), sid: self.sid.clone(), lstid, locked });
+        hub.add(BridgeCb { mask: mask.bits(), sid: self.sid.clone(), lstid: self.listener_id_with_tag(), locked });
         let hub = std::sync::Arc::new(hub);
 
         let (ctx, _handle) = omnitrace_core::sensor::SensorCtx::new(hub);
@@ -75,6 +78,193 @@ impl Sensor for FsNotifySensor {
             (emit)(v);
         }
     }
+
+    /// Watches with inotify, merging the changes of every coalescing window.
+    #[cfg(target_os = "linux")]
+    async fn run_inotify(&self, path: &str, mut w: Inotify, excludes: &Excludes, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let window = self.cfg.arg_duration("coalesce").unwrap_or(DEFAULT_COALESCE);
+        let recursive = self.cfg.arg_bool("recursive").unwrap_or(false);
+        let mut hashes = None;
+        if self.cfg.arg_bool("hash").unwrap_or(false) {
+            // Hashing reads whole files, so it stays off the async workers
+            let (root, max_bytes, excludes) =
+                (PathBuf::from(path), self.cfg.arg_u64("hash-max-bytes").unwrap_or(DEFAULT_HASH_MAX_BYTES), excludes.clone());
+            match tokio::task::spawn_blocking(move || FileHashes::scan(&root, recursive, max_bytes, |p| excludes.matches(p))).await {
+                Ok(known) => hashes = Some(known),
+                Err(err) => {
+                    log::error!("[{}] '{}' unable to hash '{}': {err}; stopping", Self::id().bright_magenta(), self.sid, path);
+                    return;
+                }
+            }
+        }
+
+        log::info!(
+            "[{}] '{}' watching '{}' with inotify ({} directories), coalescing {:?}, opts {:?}",
+            Self::id().bright_magenta(),
+            self.sid,
+            path,
+            w.watched(),
+            window,
+            self.cfg.opts()
+        );
+
+        let mut pending = vec![];
+        let mut deadline: Option<tokio::time::Instant> = None;
+        loop {
+            // `None` when the coalescing window is over
+            let events = tokio::select! {
+                r = w.read() => Some(r),
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => None,
+            };
+
+            match events {
+                Some(Ok(events)) => {
+                    // New directories are walked and watched while the events are turned into changes
+                    let (back, changes) = match tokio::task::spawn_blocking(move || {
+                        let changes = w.changes(events);
+                        (w, changes)
+                    })
+                    .await
+                    {
+                        Ok(r) => r,
+                        Err(err) => {
+                            log::error!("[{}] '{}' inotify stopped: {err}", Self::id().bright_magenta(), self.sid);
+                            return;
+                        }
+                    };
+                    w = back;
+                    if !changes.is_empty() {
+                        deadline.get_or_insert_with(|| tokio::time::Instant::now() + window);
+                        pending.extend(changes);
+                    }
+                }
+                Some(Err(err)) => {
+                    log::error!("[{}] '{}' inotify read failed: {err}", Self::id().bright_magenta(), self.sid);
+                    return;
+                }
+                None => {
+                    deadline = None;
+                    pending.extend(w.take_moved_out());
+                    for change in coalesce(std::mem::take(&mut pending)) {
+                        match self.change_event(&change, &mut hashes).await {
+                            Ok(Some(ev)) => (emit)(ev),
+                            Ok(None) => {}
+                            Err(err) => {
+                                log::error!("[{}] '{}' hashing failed: {err}; stopping", Self::id().bright_magenta(), self.sid);
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Packages one change, updating the known hashes. Returns `None` if its
+    /// action is not wanted or the event is locked, and an error if hashing broke.
+    /// The hashes are not silently dropped then, as every later event would lack them.
+    #[cfg(target_os = "linux")]
+    async fn change_event(&self, change: &FsChange, hashes: &mut Option<FileHashes>) -> Result<Option<serde_json::Value>, tokio::task::JoinError> {
+        let (action, mut data) = match change {
+            FsChange::Created(p) => ("created", serde_json::json!({"file": p.to_string_lossy()})),
+            FsChange::Changed(p) => ("changed", serde_json::json!({"file": p.to_string_lossy()})),
+            FsChange::Deleted(p) => ("deleted", serde_json::json!({"file": p.to_string_lossy()})),
+            FsChange::Attrib(p) => {
+                let mut data = serde_json::json!({"file": p.to_string_lossy()});
+                if let Ok(m) = fs::symlink_metadata(p) {
+                    use std::os::unix::fs::MetadataExt;
+                    data["mode"] = serde_json::json!(format!("{:04o}", m.mode() & 0o7777));
+                    data["uid"] = serde_json::json!(m.uid());
+                    data["gid"] = serde_json::json!(m.gid());
+                }
+                ("attrib", data)
+            }
+            FsChange::Renamed { from, to } => {
+                ("renamed", serde_json::json!({"file": to.to_string_lossy(), "from": from.to_string_lossy(), "to": to.to_string_lossy()}))
+            }
+        };
+
+        // Hashes are kept up to date also for the actions that are not emitted
+        if let Some(mut known) = hashes.take() {
+            let owned = change.clone();
+            let (known, (before, after)) = tokio::task::spawn_blocking(move || {
+                let diff = known.update(&owned);
+                (known, diff)
+            })
+            .await?;
+            *hashes = Some(known);
+            if !matches!(change, FsChange::Attrib(_)) {
+                data["sha256-before"] = serde_json::json!(before);
+                data["sha256-after"] = serde_json::json!(after);
+            }
+        }
+
+        if !self.cfg.opts().is_empty() && !self.cfg.opts().iter().any(|o| o == action) {
+            return Ok(None);
+        }
+
+        let eid = format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, change.path().to_string_lossy(), 0);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.filesystem", &eid).await {
+            return Ok(None);
+        }
+
+        data["action"] = serde_json::json!(action);
+        Ok(Some(serde_json::json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.filesystem",
+            "data": data,
+        })))
+    }
+}
+
+#[async_trait]
+impl Sensor for FsNotifySensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self { sid: id, cfg }
+    }
+
+    fn id() -> String {
+        "sys.filesystem".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        // args
+        let Some(path) = self.cfg.arg_str("path") else {
+            log::warn!("[{}] '{}' missing args.path; not starting", Self::id().bright_magenta(), self.sid);
+            return;
+        };
+
+        let backend = self.cfg.arg_str("backend").unwrap_or_else(|| "auto".to_string());
+        if !["auto", "inotify", "poll"].contains(&backend.as_str()) {
+            log::warn!("[{}] '{}' unknown backend '{}'; not starting", Self::id().bright_magenta(), self.sid, backend);
+            return;
+        }
+
+        #[cfg(target_os = "linux")]
+        if backend != "poll" {
+            let excludes = match Excludes::new(&self.cfg.arg_str_array("exclude").unwrap_or_default()) {
+                Ok(excludes) => excludes,
+                Err(err) => {
+                    log::warn!("[{}] '{}' invalid args.exclude: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                    return;
+                }
+            };
+            // Watching a recursive tree walks all of it
+            let (root, recursive, tree) = (PathBuf::from(&path), self.cfg.arg_bool("recursive").unwrap_or(false), excludes.clone());
+            match tokio::task::spawn_blocking(move || Inotify::new(&root, recursive, tree)).await.map_err(io::Error::other).and_then(|r| r) {
+                Ok(w) => return self.run_inotify(&path, w, &excludes, emit).await,
+                Err(err) => log::warn!("[{}] '{}' inotify unavailable for '{}': {err}; polling instead", Self::id().bright_magenta(), self.sid, path),
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if backend == "inotify" {
+            log::warn!("[{}] '{}' inotify is only available on Linux; polling instead", Self::id().bright_magenta(), self.sid);
+        }
+
+        self.run_poll(&path, emit).await;
+    }
 }
 
 struct BridgeCb {
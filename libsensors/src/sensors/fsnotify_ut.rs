@@ -0,0 +1,207 @@
+use crate::{
+    sensors::{
+        fsnotify::FsNotifySensor,
+        inotify::{Excludes, FsChange, Inotify, coalesce, parse_events},
+        sensor::Sensor,
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a `sys.filesystem` sensor configuration for tests.
+fn mk_cfg(opts: &[&str], args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.filesystem",
+        "opts": opts,
+        "args": args
+    }))
+    .unwrap()
+}
+
+/// Builds one raw `struct inotify_event` with a NUL-padded name.
+fn raw_event(wd: i32, mask: u32, cookie: u32, name: &str) -> Vec<u8> {
+    let len = if name.is_empty() { 0 } else { (name.len() + 1).next_multiple_of(16) };
+    let mut b = vec![];
+    b.extend_from_slice(&wd.to_ne_bytes());
+    b.extend_from_slice(&mask.to_ne_bytes());
+    b.extend_from_slice(&cookie.to_ne_bytes());
+    b.extend_from_slice(&(len as u32).to_ne_bytes());
+    b.extend_from_slice(name.as_bytes());
+    b.resize(16 + len, 0);
+    b
+}
+
+/// Collects the changes of a watcher until it stays quiet for a moment.
+async fn drain(w: &mut Inotify) -> Vec<FsChange> {
+    let mut out = vec![];
+    while let Ok(Ok(changes)) = tokio::time::timeout(Duration::from_millis(100), w.next()).await {
+        out.extend(changes);
+    }
+    out.extend(w.take_moved_out());
+    coalesce(out)
+}
+
+fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[test]
+fn parse_events_splits_padded_names() {
+    let mut buf = raw_event(1, libc::IN_CREATE, 0, "hello.txt");
+    buf.extend(raw_event(2, libc::IN_MOVED_TO, 7, "b"));
+    buf.extend(raw_event(3, libc::IN_IGNORED, 0, ""));
+
+    let evs = parse_events(&buf);
+    assert_eq!(evs.len(), 3);
+    assert_eq!((evs[0].wd, evs[0].mask, evs[0].name.to_str()), (1, libc::IN_CREATE, Some("hello.txt")));
+    assert_eq!((evs[1].cookie, evs[1].name.to_str()), (7, Some("b")));
+    assert!(evs[2].name.is_empty());
+
+    // A truncated event is not read past the buffer
+    assert_eq!(parse_events(&buf[..buf.len() - 4]).len(), 2);
+}
+
+#[test]
+fn coalesce_merges_repeats_but_keeps_short_lived_files() {
+    let (a, b) = (PathBuf::from("/w/a"), PathBuf::from("/w/b"));
+    let merged = coalesce(vec![
+        FsChange::Created(a.clone()),
+        FsChange::Changed(a.clone()),
+        FsChange::Attrib(a.clone()),
+        FsChange::Changed(b.clone()),
+        FsChange::Changed(b.clone()),
+        FsChange::Attrib(b.clone()),
+        FsChange::Deleted(a.clone()),
+    ]);
+    assert_eq!(merged, vec![FsChange::Created(a.clone()), FsChange::Changed(b.clone()), FsChange::Attrib(b), FsChange::Deleted(a)]);
+}
+
+#[test]
+fn excludes_match_names_and_paths() {
+    let ex = Excludes::new(&["*.swp".to_string(), "/srv/app/cache/*".to_string()]).unwrap();
+    assert!(ex.matches(Path::new("/etc/.sshd_config.swp")));
+    assert!(ex.matches(Path::new("/srv/app/cache/deep/item")));
+    assert!(!ex.matches(Path::new("/srv/app/config.yaml")));
+    assert!(Excludes::new(&["[".to_string()]).is_err());
+}
+
+#[tokio::test]
+async fn inotify_follows_tree_with_renames_attributes_and_excludes() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::write(root.join("sub/a.conf"), "a").unwrap();
+
+    let mut w = Inotify::new(root, true, Excludes::new(&[".git".to_string()]).unwrap()).unwrap();
+    assert_eq!(w.watched(), 2);
+
+    fs::rename(root.join("sub/a.conf"), root.join("sub/b.conf")).unwrap();
+    fs::set_permissions(root.join("sub/b.conf"), fs::Permissions::from_mode(0o600)).unwrap();
+    fs::write(root.join(".git/index"), "x").unwrap();
+    let got = drain(&mut w).await;
+    assert_eq!(
+        got,
+        vec![FsChange::Renamed { from: root.join("sub/a.conf"), to: root.join("sub/b.conf") }, FsChange::Attrib(root.join("sub/b.conf"))]
+    );
+
+    // A new directory is watched, and what landed in it before that is reported
+    fs::create_dir_all(root.join("new/deeper")).unwrap();
+    fs::write(root.join("new/deeper/c"), "c").unwrap();
+    let got = drain(&mut w).await;
+    assert!(got.contains(&FsChange::Created(root.join("new"))));
+    assert!(got.contains(&FsChange::Created(root.join("new/deeper/c"))));
+
+    fs::write(root.join("new/deeper/c"), "cc").unwrap();
+    assert_eq!(drain(&mut w).await, vec![FsChange::Changed(root.join("new/deeper/c"))]);
+
+    // Moved out of the tree
+    fs::rename(root.join("sub/b.conf"), root.join(".git/b.conf")).unwrap();
+    assert_eq!(drain(&mut w).await, vec![FsChange::Deleted(root.join("sub/b.conf"))]);
+}
+
+#[tokio::test]
+async fn inotify_single_file_sees_atomic_replace() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("sshd_config");
+    fs::write(&file, "Port 22\n").unwrap();
+
+    let mut w = Inotify::new(&file, false, Excludes::default()).unwrap();
+    fs::write(dir.path().join("other"), "x").unwrap();
+    fs::write(dir.path().join(".sshd_config.tmp"), "Port 2222\n").unwrap();
+    fs::rename(dir.path().join(".sshd_config.tmp"), &file).unwrap();
+
+    assert_eq!(drain(&mut w).await, vec![FsChange::Changed(file)]);
+}
+
+#[tokio::test]
+async fn run_reports_content_hash_before_and_after() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("passwd");
+    fs::write(&file, "root:x:0:0\n").unwrap();
+
+    let s = FsNotifySensor::new(
+        "sid".to_string(),
+        mk_cfg(&["changed"], json!({"path": dir.path().to_str().unwrap(), "backend": "inotify", "hash": true, "coalesce": "20ms"})),
+    );
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let writer = {
+        let file = file.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            fs::write(&file, "root:x:0:0\nevil:x:0:0\n").unwrap();
+            fs::write(file.with_extension("new"), "ignored, not a wanted action").unwrap();
+        })
+    };
+    let _ = tokio::time::timeout(Duration::from_millis(300), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+    writer.await.unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["eid"], format!("sid|sys.filesystem|changed@{}|0", file.display()));
+    assert_eq!(events[0]["data"]["action"], "changed");
+    assert_eq!(events[0]["data"]["sha256-before"], sha256(b"root:x:0:0\n"));
+    assert_eq!(events[0]["data"]["sha256-after"], sha256(b"root:x:0:0\nevil:x:0:0\n"));
+}
+
+#[tokio::test]
+async fn run_defaults_to_inotify() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("app.conf");
+    fs::write(&file, "a").unwrap();
+
+    let s = FsNotifySensor::new(
+        "sid".to_string(),
+        mk_cfg(&["renamed", "created"], json!({"path": dir.path().to_str().unwrap(), "recursive": true, "coalesce": "20ms"})),
+    );
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let writer = {
+        let root = dir.path().to_path_buf();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            fs::rename(root.join("app.conf"), root.join("app.conf.old")).unwrap();
+            fs::create_dir_all(root.join("conf.d/extra")).unwrap();
+        })
+    };
+    let _ = tokio::time::timeout(Duration::from_millis(300), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+    writer.await.unwrap();
+
+    // Renames are seen by inotify only, the poller reports them as a deletion and a creation
+    let events = events.lock().unwrap();
+    let actions = events
+        .iter()
+        .map(|e| (e["data"]["action"].as_str().unwrap().to_string(), e["data"]["file"].as_str().unwrap().to_string()))
+        .collect::<Vec<_>>();
+    assert!(actions.contains(&("renamed".to_string(), dir.path().join("app.conf.old").display().to_string())), "{actions:?}");
+    assert!(actions.contains(&("created".to_string(), dir.path().join("conf.d").display().to_string())), "{actions:?}");
+}
@@ -0,0 +1,374 @@
+//! Kernel-notified filesystem watcher for `sys.filesystem`.
+//!
+//! Directories are watched with inotify, recursively if asked. A single file
+//! is watched through its directory, so it is still seen after an editor
+//! replaces it by renaming a new file over it.
+
+use glob::Pattern;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr, OsString},
+    fs, io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+};
+use tokio::io::unix::AsyncFd;
+use walkdir::WalkDir;
+
+const WATCH_MASK: u32 = libc::IN_CREATE | libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+
+/// Size of the fixed part of `struct inotify_event`
+const EVENT_HEADER: usize = 16;
+
+/// A filesystem change, after rename pairing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FsChange {
+    Created(PathBuf),
+    Changed(PathBuf),
+    Deleted(PathBuf),
+    /// Permissions, ownership or other metadata changed
+    Attrib(PathBuf),
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+impl FsChange {
+    /// Path the change is reported on; the new name for a rename
+    pub(crate) fn path(&self) -> &Path {
+        match self {
+            FsChange::Created(p) | FsChange::Changed(p) | FsChange::Deleted(p) | FsChange::Attrib(p) => p,
+            FsChange::Renamed { to, .. } => to,
+        }
+    }
+}
+
+/// One raw `struct inotify_event`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RawEvent {
+    pub(crate) wd: i32,
+    pub(crate) mask: u32,
+    pub(crate) cookie: u32,
+    pub(crate) name: OsString,
+}
+
+/// Splits a buffer read from an inotify descriptor into its events.
+pub(crate) fn parse_events(buf: &[u8]) -> Vec<RawEvent> {
+    let mut out = vec![];
+    let mut at = 0;
+    while at + EVENT_HEADER <= buf.len() {
+        let u32_at = |i: usize| u32::from_ne_bytes([buf[at + i], buf[at + i + 1], buf[at + i + 2], buf[at + i + 3]]);
+        let len = u32_at(12) as usize;
+        let Some(name) = buf.get(at + EVENT_HEADER..at + EVENT_HEADER + len) else {
+            break;
+        };
+        let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+        out.push(RawEvent { wd: u32_at(0) as i32, mask: u32_at(4), cookie: u32_at(8), name: OsStr::from_bytes(name).to_os_string() });
+        at += EVENT_HEADER + len;
+    }
+    out
+}
+
+/// Merges changes that happened within one coalescing window.
+///
+/// Repeated changes of a path are reported once, and a change right after the
+/// path was created is part of the creation. Creations and deletions are all
+/// kept, so a short-lived file is still reported.
+pub(crate) fn coalesce(changes: Vec<FsChange>) -> Vec<FsChange> {
+    let mut out: Vec<FsChange> = vec![];
+    let mut last: HashMap<PathBuf, usize> = HashMap::new();
+    for change in changes {
+        let prev = last.get(change.path()).map(|i| &out[*i]);
+        let absorbed = matches!(
+            (&change, prev),
+            (FsChange::Changed(_), Some(FsChange::Created(_) | FsChange::Changed(_)))
+                | (FsChange::Attrib(_), Some(FsChange::Created(_) | FsChange::Attrib(_)))
+        );
+        if !absorbed {
+            last.insert(change.path().to_path_buf(), out.len());
+            out.push(change);
+        }
+    }
+    out
+}
+
+/// Exclude globs, matched against the full path and the file name.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Excludes(Vec<Pattern>);
+
+impl Excludes {
+    pub(crate) fn new(globs: &[String]) -> Result<Self, glob::PatternError> {
+        globs.iter().map(|g| Pattern::new(g)).collect::<Result<Vec<_>, _>>().map(Self)
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        self.0.iter().any(|p| p.matches_path(path) || p.matches(&name))
+    }
+}
+
+/// Watches a file or a directory tree with inotify.
+pub(crate) struct Inotify {
+    fd: AsyncFd<OwnedFd>,
+    wds: HashMap<i32, PathBuf>,
+    /// Set when a single file is watched through its directory
+    file: Option<PathBuf>,
+    recursive: bool,
+    excludes: Excludes,
+    /// A rename source waiting for its target: cookie, path and if it is a directory
+    moved_from: Option<(u32, PathBuf, bool)>,
+}
+
+impl Inotify {
+    pub(crate) fn new(path: &Path, recursive: bool, excludes: Excludes) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = AsyncFd::new(unsafe { OwnedFd::from_raw_fd(fd) })?;
+
+        let mut w = Self { fd, wds: HashMap::new(), file: None, recursive, excludes, moved_from: None };
+        if path.is_dir() {
+            w.add_tree(path)?;
+        } else {
+            let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            w.add_watch(dir)?;
+            w.file = path.file_name().map(|name| dir.join(name));
+        }
+        Ok(w)
+    }
+
+    /// Directories currently watched
+    pub(crate) fn watched(&self) -> usize {
+        self.wds.len()
+    }
+
+    fn add_watch(&mut self, dir: &Path) -> io::Result<()> {
+        let cpath = CString::new(dir.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.get_ref().as_raw_fd(), cpath.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.wds.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Watches `dir`, and with recursion all directories below it.
+    /// Returns the entries found below `dir`.
+    fn add_tree(&mut self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        self.add_watch(dir)?;
+        let mut found = vec![];
+        if !self.recursive {
+            return Ok(found);
+        }
+
+        let excludes = self.excludes.clone();
+        for entry in WalkDir::new(dir).min_depth(1).into_iter().filter_entry(|e| !excludes.matches(e.path())).filter_map(Result::ok) {
+            if entry.file_type().is_dir()
+                && let Err(err) = self.add_watch(entry.path())
+            {
+                log::warn!("Unable to watch {}: {err}", entry.path().display());
+            }
+            found.push(entry.into_path());
+        }
+        Ok(found)
+    }
+
+    /// Waits for the next events and returns them as changes.
+    pub(crate) async fn next(&mut self) -> io::Result<Vec<FsChange>> {
+        let events = self.read().await?;
+        Ok(self.changes(events))
+    }
+
+    /// Waits for the next events.
+    pub(crate) async fn read(&mut self) -> io::Result<Vec<RawEvent>> {
+        let mut buf = vec![0u8; 64 * 1024];
+        let n = loop {
+            let mut guard = self.fd.readable().await?;
+            match guard.try_io(|fd| {
+                let n = unsafe { libc::read(fd.get_ref().as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+                if n < 0 { Err(io::Error::last_os_error()) } else { Ok(n as usize) }
+            }) {
+                Ok(r) => break r?,
+                Err(_would_block) => continue,
+            }
+        };
+
+        Ok(parse_events(&buf[..n]))
+    }
+
+    /// Turns events into changes. With recursion, a new directory tree is walked and watched here,
+    /// so a large tree is better handled off the async workers.
+    pub(crate) fn changes(&mut self, events: Vec<RawEvent>) -> Vec<FsChange> {
+        let mut out = vec![];
+        for ev in events {
+            self.translate(ev, &mut out);
+        }
+        out
+    }
+
+    /// Returns a rename source still waiting for its target: it was moved out of the watch.
+    pub(crate) fn take_moved_out(&mut self) -> Option<FsChange> {
+        let (_, from, is_dir) = self.moved_from.take()?;
+        self.moved_out(from, is_dir)
+    }
+
+    fn moved_out(&mut self, from: PathBuf, is_dir: bool) -> Option<FsChange> {
+        if is_dir {
+            self.unwatch_tree(&from);
+        }
+        self.scoped(FsChange::Deleted(from))
+    }
+
+    fn translate(&mut self, ev: RawEvent, out: &mut Vec<FsChange>) {
+        if ev.mask & libc::IN_Q_OVERFLOW != 0 {
+            log::warn!("inotify queue overflow, filesystem events were lost");
+            return;
+        }
+        if ev.mask & libc::IN_IGNORED != 0 {
+            self.wds.remove(&ev.wd);
+            return;
+        }
+        let Some(dir) = self.wds.get(&ev.wd) else {
+            return;
+        };
+        let path = if ev.name.is_empty() { dir.clone() } else { dir.join(&ev.name) };
+        if self.excludes.matches(&path) {
+            return;
+        }
+        let is_dir = ev.mask & libc::IN_ISDIR != 0;
+
+        // A rename source is followed by its target right away, otherwise it left the watch
+        if let Some((cookie, from, from_dir)) = self.moved_from.take() {
+            if ev.mask & libc::IN_MOVED_TO != 0 && ev.cookie == cookie {
+                if is_dir {
+                    self.rename_watches(&from, &path);
+                }
+                out.extend(self.scoped(FsChange::Renamed { from, to: path }));
+                return;
+            }
+            out.extend(self.moved_out(from, from_dir));
+        }
+
+        let change = if ev.mask & libc::IN_CREATE != 0 || ev.mask & libc::IN_MOVED_TO != 0 {
+            if is_dir && self.recursive && self.file.is_none() {
+                // Entries made before the watch was in place are reported as created
+                match self.add_tree(&path) {
+                    Ok(found) => {
+                        out.extend(self.scoped(FsChange::Created(path)));
+                        out.extend(found.into_iter().filter_map(|p| self.scoped(FsChange::Created(p))));
+                        return;
+                    }
+                    Err(err) => log::warn!("Unable to watch {}: {err}", path.display()),
+                }
+            }
+            FsChange::Created(path)
+        } else if ev.mask & libc::IN_MODIFY != 0 {
+            if is_dir {
+                return;
+            }
+            FsChange::Changed(path)
+        } else if ev.mask & libc::IN_ATTRIB != 0 {
+            FsChange::Attrib(path)
+        } else if ev.mask & libc::IN_DELETE != 0 {
+            FsChange::Deleted(path)
+        } else if ev.mask & libc::IN_MOVED_FROM != 0 {
+            self.moved_from = Some((ev.cookie, path, is_dir));
+            return;
+        } else {
+            return;
+        };
+        out.extend(self.scoped(change));
+    }
+
+    /// Keeps the watch paths right after a watched directory was renamed.
+    fn rename_watches(&mut self, from: &Path, to: &Path) {
+        for dir in self.wds.values_mut() {
+            if let Ok(rest) = dir.strip_prefix(from) {
+                *dir = to.join(rest);
+            }
+        }
+    }
+
+    /// Drops the watches of a directory tree that left the watch.
+    fn unwatch_tree(&mut self, dir: &Path) {
+        let fd = self.fd.get_ref().as_raw_fd();
+        self.wds.retain(|wd, path| {
+            let gone = path.starts_with(dir);
+            if gone {
+                unsafe { libc::inotify_rm_watch(fd, *wd) };
+            }
+            !gone
+        });
+    }
+
+    /// Limits a change to the watched file, if only a file is watched.
+    ///
+    /// A file renamed over the watched one replaces its content, and renaming
+    /// the watched file away removes it.
+    fn scoped(&self, change: FsChange) -> Option<FsChange> {
+        let Some(file) = &self.file else {
+            return Some(change);
+        };
+        match change {
+            FsChange::Renamed { to, .. } if &to == file => Some(FsChange::Changed(to)),
+            FsChange::Renamed { from, .. } if &from == file => Some(FsChange::Deleted(from)),
+            FsChange::Created(p) | FsChange::Changed(p) | FsChange::Deleted(p) | FsChange::Attrib(p) if &p != file => None,
+            FsChange::Renamed { .. } => None,
+            change => Some(change),
+        }
+    }
+}
+
+/// Last known sha256 of the watched files, to report content before and after a change.
+#[derive(Debug, Default)]
+pub(crate) struct FileHashes {
+    known: HashMap<PathBuf, String>,
+    max_bytes: u64,
+}
+
+impl FileHashes {
+    /// Hashes the files that exist when the watch starts.
+    pub(crate) fn scan(path: &Path, recursive: bool, max_bytes: u64, excluded: impl Fn(&Path) -> bool) -> Self {
+        let mut hashes = Self { known: HashMap::new(), max_bytes };
+        let walk = WalkDir::new(path).max_depth(if recursive { usize::MAX } else { 1 });
+        for entry in walk.into_iter().filter_entry(|e| e.depth() == 0 || !excluded(e.path())).filter_map(Result::ok) {
+            if let Some(h) = hashes.hash(entry.path()) {
+                hashes.known.insert(entry.into_path(), h);
+            }
+        }
+        hashes
+    }
+
+    fn hash(&self, path: &Path) -> Option<String> {
+        fs::metadata(path).ok().filter(|m| m.is_file() && m.len() <= self.max_bytes)?;
+        let mut file = fs::File::open(path).ok()?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).ok()?;
+        Some(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Applies a change and returns the hash before and after it.
+    pub(crate) fn update(&mut self, change: &FsChange) -> (Option<String>, Option<String>) {
+        let (before, path) = match change {
+            FsChange::Created(p) => (None, Some(p)),
+            FsChange::Changed(p) | FsChange::Attrib(p) => (self.known.get(p).cloned(), Some(p)),
+            FsChange::Deleted(p) => (self.known.remove(p), None),
+            FsChange::Renamed { from, to } => (self.known.remove(from), Some(to)),
+        };
+        let after = path.and_then(|p| {
+            let h = self.hash(p);
+            match &h {
+                Some(h) => self.known.insert(p.clone(), h.clone()),
+                None => self.known.remove(p),
+            };
+            h
+        });
+        (before, after)
+    }
+}
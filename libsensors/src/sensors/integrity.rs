@@ -0,0 +1,771 @@
+#[cfg(target_os = "linux")]
+use super::inotify::{Excludes, FsChange, Inotify};
+use crate::{
+    argparse::SensorArgs,
+    sensors::{
+        SensorCtx,
+        sensor::{Sensor, SensorEvent},
+    },
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use dashmap::DashMap;
+use glob::Pattern;
+use lazy_static::lazy_static;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{Notify, mpsc};
+use walkdir::WalkDir;
+
+/// Default time between two full verifications
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Time to let a burst of filesystem events settle before the touched paths are verified
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// Keys of the baseline metadata tree
+const META_DIGEST: &[u8] = b"digest";
+const META_SIGNATURE: &[u8] = b"signature";
+const META_CREATED: &[u8] = b"created";
+const META_FILES: &[u8] = b"files";
+
+lazy_static! {
+    /// Rebaseline triggers of the running integrity sensors, by sensor id
+    static ref REBASELINE: DashMap<String, Arc<Notify>> = DashMap::new();
+}
+
+/// Asks the running `sys.integrity` sensors to take a new baseline of their paths.
+///
+/// Only the sensor `sid` is asked if given, otherwise all of them.
+/// Returns the ids of the sensors that were asked.
+pub fn request_rebaseline(sid: Option<&str>) -> Vec<String> {
+    let mut asked = REBASELINE
+        .iter()
+        .filter(|e| sid.is_none_or(|sid| sid == e.key()))
+        .map(|e| {
+            e.value().notify_one();
+            e.key().clone()
+        })
+        .collect::<Vec<_>>();
+    asked.sort();
+    asked
+}
+
+/// Registration of a running sensor for rebaseline requests, dropped when it stops.
+pub(crate) struct RebaselineTrigger {
+    sid: String,
+    notify: Arc<Notify>,
+}
+
+impl RebaselineTrigger {
+    pub(crate) fn register(sid: &str) -> Self {
+        let notify = Arc::new(Notify::new());
+        REBASELINE.insert(sid.to_string(), notify.clone());
+        Self { sid: sid.to_string(), notify }
+    }
+
+    pub(crate) async fn requested(&self) {
+        self.notify.notified().await
+    }
+}
+
+impl Drop for RebaselineTrigger {
+    fn drop(&mut self) {
+        REBASELINE.remove_if(&self.sid, |_, n| Arc::ptr_eq(n, &self.notify));
+    }
+}
+
+/// Recorded state of one path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileRecord {
+    /// `file`, `dir`, `symlink` or `other`
+    pub(crate) kind: String,
+    /// Content hash of a regular file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sha256: Option<String>,
+    /// Target of a symbolic link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target: Option<String>,
+    pub(crate) mode: u32,
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+    /// Size of a regular file or a link; directories are always 0, their size follows their entries
+    pub(crate) size: u64,
+    /// Extended attributes, values hex-encoded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) xattrs: BTreeMap<String, String>,
+}
+
+impl FileRecord {
+    /// Reads the state of `path` without following a symbolic link.
+    pub(crate) fn read(path: &Path, xattrs: bool) -> io::Result<Self> {
+        let meta = fs::symlink_metadata(path)?;
+        let ft = meta.file_type();
+        let mut rec = Self {
+            kind: if ft.is_file() {
+                "file"
+            } else if ft.is_dir() {
+                "dir"
+            } else if ft.is_symlink() {
+                "symlink"
+            } else {
+                "other"
+            }
+            .to_string(),
+            mode: meta.mode() & 0o7777,
+            uid: meta.uid(),
+            gid: meta.gid(),
+            size: if ft.is_dir() { 0 } else { meta.len() },
+            ..Default::default()
+        };
+        if ft.is_file() {
+            let mut hasher = Sha256::new();
+            io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+            rec.sha256 = Some(format!("{:x}", hasher.finalize()));
+        } else if ft.is_symlink() {
+            rec.target = Some(fs::read_link(path)?.to_string_lossy().into_owned());
+        }
+        if xattrs {
+            rec.xattrs = read_xattrs(path);
+        }
+        Ok(rec)
+    }
+
+    /// Names of the properties that differ from `other`.
+    pub(crate) fn diff(&self, other: &Self) -> Vec<&'static str> {
+        let mut changes = vec![];
+        if self.kind != other.kind {
+            changes.push("kind");
+        }
+        if self.sha256 != other.sha256 {
+            changes.push("sha256");
+        }
+        if self.target != other.target {
+            changes.push("target");
+        }
+        if self.mode != other.mode {
+            changes.push("mode");
+        }
+        if self.uid != other.uid || self.gid != other.gid {
+            changes.push("owner");
+        }
+        if self.size != other.size {
+            changes.push("size");
+        }
+        if self.xattrs != other.xattrs {
+            changes.push("xattrs");
+        }
+        changes
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_xattrs(path: &Path) -> BTreeMap<String, String> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let mut out = BTreeMap::new();
+    let Ok(cpath) = CString::new(path.as_os_str().as_bytes()) else {
+        return out;
+    };
+    let len = unsafe { libc::llistxattr(cpath.as_ptr(), std::ptr::null_mut(), 0) };
+    if len <= 0 {
+        return out;
+    }
+    let mut names = vec![0u8; len as usize];
+    let len = unsafe { libc::llistxattr(cpath.as_ptr(), names.as_mut_ptr().cast(), names.len()) };
+    if len <= 0 {
+        return out;
+    }
+
+    for name in names[..len as usize].split(|b| *b == 0).filter(|n| !n.is_empty()) {
+        let Ok(cname) = CString::new(name) else {
+            continue;
+        };
+        let vlen = unsafe { libc::lgetxattr(cpath.as_ptr(), cname.as_ptr(), std::ptr::null_mut(), 0) };
+        if vlen < 0 {
+            continue;
+        }
+        let mut value = vec![0u8; vlen as usize];
+        let vlen = unsafe { libc::lgetxattr(cpath.as_ptr(), cname.as_ptr(), value.as_mut_ptr().cast(), value.len()) };
+        if vlen < 0 {
+            continue;
+        }
+        out.insert(String::from_utf8_lossy(name).into_owned(), value[..vlen as usize].iter().map(|b| format!("{b:02x}")).collect());
+    }
+    out
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_xattrs(_path: &Path) -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
+/// The monitored paths, minus the excluded ones.
+#[derive(Debug, Clone)]
+pub(crate) struct PathSet {
+    roots: Vec<PathBuf>,
+    excludes: Vec<Pattern>,
+    xattrs: bool,
+}
+
+impl PathSet {
+    pub(crate) fn new(roots: &[String], excludes: &[String], xattrs: bool) -> Result<Self, glob::PatternError> {
+        Ok(Self {
+            roots: roots.iter().map(PathBuf::from).collect(),
+            excludes: excludes.iter().map(|g| Pattern::new(g)).collect::<Result<_, _>>()?,
+            xattrs,
+        })
+    }
+
+    /// Exclude globs are matched against the full path and the file name.
+    fn excluded(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        self.excludes.iter().any(|p| p.matches_path(path) || p.matches(&name))
+    }
+
+    /// Whether `path` is monitored.
+    pub(crate) fn covers(&self, path: &Path) -> bool {
+        self.roots.iter().any(|r| path.starts_with(r)) && !path.ancestors().any(|p| self.excluded(p))
+    }
+
+    /// Records everything under `root`, which must itself be covered.
+    fn scan_under(&self, root: &Path, out: &mut BTreeMap<String, FileRecord>) {
+        let walk = WalkDir::new(root).follow_links(false).into_iter().filter_entry(|e| !self.excluded(e.path()));
+        for entry in walk {
+            match entry {
+                Ok(entry) => match FileRecord::read(entry.path(), self.xattrs) {
+                    Ok(rec) => {
+                        out.insert(entry.path().to_string_lossy().into_owned(), rec);
+                    }
+                    Err(err) => log::debug!("Unable to read {}: {err}", entry.path().display()),
+                },
+                Err(err) => log::debug!("Unable to walk {}: {err}", root.display()),
+            }
+        }
+    }
+
+    /// Records every monitored path.
+    pub(crate) fn scan(&self) -> BTreeMap<String, FileRecord> {
+        let mut out = BTreeMap::new();
+        for root in &self.roots {
+            self.scan_under(root, &mut out);
+        }
+        out
+    }
+
+    /// Records the monitored paths at and below `path`.
+    pub(crate) fn scan_at(&self, path: &Path) -> BTreeMap<String, FileRecord> {
+        let mut out = BTreeMap::new();
+        if self.covers(path) {
+            self.scan_under(path, &mut out);
+        }
+        out
+    }
+}
+
+/// Baseline kept in sled: one record per path, and the digest of all of them signed with the minion key.
+///
+/// The records of each baseline are in a tree of their own, named in the metadata,
+/// so a new baseline replaces the previous one at once or not at all.
+#[derive(Debug, Clone)]
+pub(crate) struct Baseline {
+    db: sled::Db,
+    files: sled::Tree,
+    meta: sled::Tree,
+}
+
+impl Baseline {
+    pub(crate) fn open(db: sled::Db) -> Result<Self, sled::Error> {
+        let meta = db.open_tree("meta")?;
+        let name = meta.get(META_FILES)?.map(|v| v.to_vec()).unwrap_or_else(|| b"files".to_vec());
+        let files = db.open_tree(&name)?;
+
+        // Records of a baseline that was never completed
+        for stale in db.tree_names().into_iter().filter(|t| t.starts_with(b"files") && t.as_ref() != name.as_slice()) {
+            db.drop_tree(stale)?;
+        }
+        Ok(Self { db, files, meta })
+    }
+
+    /// Whether no baseline was taken yet.
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.meta.contains_key(META_DIGEST).unwrap_or(false)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Replaces the baseline with `records` and signs it if a key is given.
+    ///
+    /// The records are written to a new tree first, and the metadata is switched over to it
+    /// in one batch, so the previous baseline stays in place if this fails half-way.
+    pub(crate) fn replace(&mut self, records: &BTreeMap<String, FileRecord>, key: Option<&RsaPrivateKey>) -> Result<(), String> {
+        let name = format!("files.{}", self.db.generate_id().map_err(|err| err.to_string())?);
+        let files = self.db.open_tree(&name).map_err(|err| err.to_string())?;
+        for (path, rec) in records {
+            files.insert(path.as_bytes(), serde_json::to_vec(rec).map_err(|err| err.to_string())?).map_err(|err| err.to_string())?;
+        }
+        files.flush().map_err(|err| err.to_string())?;
+
+        let digest = digest(&files)?;
+        let mut batch = sled::Batch::default();
+        match key {
+            Some(key) => batch.insert(
+                META_SIGNATURE,
+                libsysinspect::rsa::keys::sign_data(key.clone(), &digest).map_err(|err| format!("unable to sign baseline: {err}"))?,
+            ),
+            None => batch.remove(META_SIGNATURE),
+        }
+        batch.insert(META_CREATED, humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string().as_bytes());
+        batch.insert(META_DIGEST, digest);
+        batch.insert(META_FILES, name.as_bytes());
+        self.meta.apply_batch(batch).map_err(|err| err.to_string())?;
+        self.meta.flush().map_err(|err| err.to_string())?;
+
+        let previous = std::mem::replace(&mut self.files, files);
+        if let Err(err) = self.db.drop_tree(previous.name()) {
+            log::warn!("Unable to drop the previous baseline records: {err}");
+        }
+        Ok(())
+    }
+
+    /// Checks that the baseline is unchanged since it was taken.
+    ///
+    /// With a key the signature must match it, so a baseline rewritten without
+    /// the minion key is refused. Without one only the digest is checked.
+    pub(crate) fn verify(&self, key: Option<&RsaPrivateKey>) -> Result<(), String> {
+        let digest = digest(&self.files)?;
+        if self.meta.get(META_DIGEST).map_err(|err| err.to_string())?.as_deref() != Some(digest.as_slice()) {
+            return Err("baseline records do not match their digest".to_string());
+        }
+        let Some(key) = key else {
+            return Ok(());
+        };
+        let Some(sig) = self.meta.get(META_SIGNATURE).map_err(|err| err.to_string())? else {
+            return Err("baseline is not signed".to_string());
+        };
+        match libsysinspect::rsa::keys::verify_sign(&RsaPublicKey::from(key), &digest, sig.to_vec()) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("baseline signature does not match the minion key".to_string()),
+            Err(err) => Err(format!("invalid baseline signature: {err}")),
+        }
+    }
+
+    /// Time the baseline was taken, RFC 3339.
+    pub(crate) fn created(&self) -> Option<String> {
+        self.meta.get(META_CREATED).ok().flatten().map(|v| String::from_utf8_lossy(&v).into_owned())
+    }
+
+    pub(crate) fn get(&self, path: &str) -> Option<FileRecord> {
+        self.files.get(path.as_bytes()).ok().flatten().and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    /// Baseline records at and below `path`.
+    fn under(&self, path: &Path) -> BTreeMap<String, FileRecord> {
+        let prefix = path.to_string_lossy();
+        self.files
+            .scan_prefix(prefix.as_bytes())
+            .filter_map(Result::ok)
+            .map(|(k, v)| (String::from_utf8_lossy(&k).into_owned(), v))
+            .filter(|(k, _)| Path::new(k).starts_with(path))
+            .filter_map(|(k, v)| serde_json::from_slice(&v).ok().map(|rec| (k, rec)))
+            .collect()
+    }
+
+    fn all(&self) -> BTreeMap<String, FileRecord> {
+        self.files
+            .iter()
+            .filter_map(Result::ok)
+            .filter_map(|(k, v)| serde_json::from_slice(&v).ok().map(|rec| (String::from_utf8_lossy(&k).into_owned(), rec)))
+            .collect()
+    }
+}
+
+/// sha256 over all records, in key order
+fn digest(files: &sled::Tree) -> Result<Vec<u8>, String> {
+    let mut hasher = Sha256::new();
+    for kv in files.iter() {
+        let (k, v) = kv.map_err(|err| err.to_string())?;
+        hasher.update(&k);
+        hasher.update([0]);
+        hasher.update(&v);
+        hasher.update([b'\n']);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Where to compare the monitored paths with the baseline.
+#[derive(Debug, Clone)]
+pub(crate) enum Scope {
+    All,
+    Paths(Vec<PathBuf>),
+}
+
+/// A path whose state differs from the baseline or from what was reported before
+pub(crate) type Mismatch = (String, Option<FileRecord>, Option<FileRecord>);
+
+/// Compares the current state of the paths in `scope` with the baseline.
+///
+/// Returns the paths that differ from it, and the `reported` paths even if they
+/// match it again, with the expected and the actual record of each.
+pub(crate) fn compare(paths: &PathSet, baseline: &Baseline, scope: &Scope, reported: &HashSet<String>) -> Vec<Mismatch> {
+    let (expected, actual) = match scope {
+        Scope::All => (baseline.all(), paths.scan()),
+        Scope::Paths(changed) => {
+            let mut expected = BTreeMap::new();
+            let mut actual = BTreeMap::new();
+            for p in changed {
+                expected.extend(baseline.under(p));
+                actual.extend(paths.scan_at(p));
+            }
+            (expected, actual)
+        }
+    };
+
+    let keys = expected.keys().chain(actual.keys()).cloned().collect::<BTreeSet<_>>();
+    keys.into_iter()
+        .filter_map(|k| {
+            let (e, a) = (expected.get(&k), actual.get(&k));
+            (e != a || reported.contains(&k)).then(|| (k, e.cloned(), a.cloned()))
+        })
+        .collect()
+}
+
+/// A difference to the baseline, as reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Finding {
+    Added(FileRecord),
+    Removed(FileRecord),
+    Modified {
+        expected: FileRecord,
+        actual: FileRecord,
+        changes: Vec<&'static str>,
+    },
+    /// Back to its baseline state after a drift was reported
+    Restored,
+}
+
+impl Finding {
+    pub(crate) fn action(&self) -> &'static str {
+        match self {
+            Finding::Added(_) => "added",
+            Finding::Removed(_) => "removed",
+            Finding::Modified { .. } => "modified",
+            Finding::Restored => "restored",
+        }
+    }
+}
+
+/// Drift reported so far, so the same drift is reported once and not on every verification.
+#[derive(Debug, Default)]
+pub(crate) struct Drift {
+    reported: HashMap<String, Option<FileRecord>>,
+}
+
+impl Drift {
+    pub(crate) fn reported(&self) -> HashSet<String> {
+        self.reported.keys().cloned().collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.reported.clear();
+    }
+
+    /// Returns what is new about `path` since the last report, if anything.
+    pub(crate) fn observe(&mut self, path: &str, expected: Option<FileRecord>, actual: Option<FileRecord>) -> Option<Finding> {
+        if expected == actual {
+            return self.reported.remove(path).map(|_| Finding::Restored);
+        }
+        if self.reported.get(path) == Some(&actual) {
+            return None;
+        }
+        self.reported.insert(path.to_string(), actual.clone());
+        match (expected, actual) {
+            (None, Some(actual)) => Some(Finding::Added(actual)),
+            (Some(expected), None) => Some(Finding::Removed(expected)),
+            (Some(expected), Some(actual)) => {
+                let changes = expected.diff(&actual);
+                Some(Finding::Modified { expected, actual, changes })
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Verifies the monitored paths against a signed baseline and emits an event per drifted file.
+pub struct IntegritySensor {
+    sid: String,
+    cfg: SensorConf,
+    state: Option<PathBuf>,
+    key: Option<RsaPrivateKey>,
+}
+
+impl fmt::Debug for IntegritySensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntegritySensor").field("sid", &self.sid).field("listener", &self.cfg.listener()).finish()
+    }
+}
+
+impl IntegritySensor {
+    /// Creates an integrity sensor that keeps its baseline under the context state root,
+    /// signed with the context signing key.
+    pub fn with_ctx(id: String, cfg: SensorConf, ctx: SensorCtx) -> Self {
+        let state = ctx.state_root().map(|root| root.join("integrity").join(state_name(&id)));
+        Self { sid: id, cfg, state, key: ctx.signing_key().cloned() }
+    }
+
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Builds a stable event id for an action on a subject, usually a file.
+    pub(crate) fn make_eid(&self, action: &str, subject: &str) -> String {
+        format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, subject, 0)
+    }
+
+    /// Opens the baseline database, and tells if it is new, i.e. its state directory did not exist before.
+    fn open_db(&self) -> Result<(sled::Db, bool), sled::Error> {
+        match &self.state {
+            Some(path) => {
+                let fresh = !path.exists();
+                Ok((sled::open(path)?, fresh))
+            }
+            None => {
+                log::warn!("[{}] '{}' has no state directory, the baseline is not kept across restarts", Self::id().bright_magenta(), self.sid);
+                Ok((sled::Config::new().temporary(true).open()?, true))
+            }
+        }
+    }
+
+    /// Takes a new baseline of the monitored paths.
+    async fn rebaseline(&self, paths: &PathSet, baseline: &mut Baseline) -> Result<usize, String> {
+        let (paths, mut next, key) = (paths.clone(), baseline.clone(), self.key.clone());
+        let (next, n) = tokio::task::spawn_blocking(move || {
+            let records = paths.scan();
+            next.replace(&records, key.as_ref()).map(|_| (next, records.len()))
+        })
+        .await
+        .map_err(|err| err.to_string())??;
+        *baseline = next;
+        Ok(n)
+    }
+
+    /// Compares `scope` with the baseline and emits the drift not reported yet.
+    async fn verify(&self, paths: &PathSet, baseline: &Baseline, scope: Scope, drift: &mut Drift, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let reported = drift.reported();
+        let (p, b) = (paths.clone(), baseline.clone());
+        let mismatches = match tokio::task::spawn_blocking(move || compare(&p, &b, &scope, &reported)).await {
+            Ok(mismatches) => mismatches,
+            Err(err) => {
+                log::error!("[{}] '{}' verification failed: {err}", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+
+        for (path, expected, actual) in mismatches {
+            if let Some(finding) = drift.observe(&path, expected, actual)
+                && let Some(ev) = self.drift_event(&path, &finding).await
+            {
+                (emit)(ev);
+            }
+        }
+    }
+
+    /// Packages one finding, honouring optional lock-based duplicate suppression.
+    async fn drift_event(&self, path: &str, finding: &Finding) -> Option<SensorEvent> {
+        let mut data = json!({"action": finding.action(), "file": path});
+        match finding {
+            Finding::Added(actual) => data["actual"] = json!(actual),
+            Finding::Removed(expected) => data["expected"] = json!(expected),
+            Finding::Modified { expected, actual, changes } => {
+                data["changes"] = json!(changes);
+                data["expected"] = json!(expected);
+                data["actual"] = json!(actual);
+            }
+            Finding::Restored => {}
+        }
+        self.event(finding.action(), path, data).await
+    }
+
+    async fn event(&self, action: &str, subject: &str, data: serde_json::Value) -> Option<SensorEvent> {
+        let eid = self.make_eid(action, subject);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.integrity", &eid).await {
+            return None;
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.integrity",
+            "data": data,
+        }))
+    }
+
+    /// Forwards the paths touched on disk, one watcher task per monitored path.
+    #[cfg(target_os = "linux")]
+    fn watch(&self, paths: &PathSet, tasks: &mut tokio::task::JoinSet<()>) -> mpsc::Receiver<PathBuf> {
+        let (tx, rx) = mpsc::channel(0xfff);
+        let excludes = Excludes::new(&paths.excludes.iter().map(|p| p.as_str().to_string()).collect::<Vec<_>>()).unwrap_or_default();
+        for root in &paths.roots {
+            let mut w = match Inotify::new(root, true, excludes.clone()) {
+                Ok(w) => w,
+                Err(err) => {
+                    log::warn!(
+                        "[{}] '{}' unable to watch '{}': {err}; verifying it on schedule only",
+                        Self::id().bright_magenta(),
+                        self.sid,
+                        root.display()
+                    );
+                    continue;
+                }
+            };
+            let tx = tx.clone();
+            tasks.spawn(async move {
+                while let Ok(changes) = w.next().await {
+                    for change in changes.into_iter().chain(w.take_moved_out()) {
+                        let touched = match change {
+                            FsChange::Renamed { from, to } => vec![from, to],
+                            change => vec![change.path().to_path_buf()],
+                        };
+                        for p in touched {
+                            if tx.send(p).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+        rx
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn watch(&self, _paths: &PathSet, _tasks: &mut tokio::task::JoinSet<()>) -> mpsc::Receiver<PathBuf> {
+        log::info!("[{}] '{}' filesystem events are only watched on Linux; verifying on schedule only", Self::id().bright_magenta(), self.sid);
+        mpsc::channel(1).1
+    }
+}
+
+#[async_trait]
+impl Sensor for IntegritySensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self::with_ctx(id, cfg, SensorCtx::default())
+    }
+
+    fn id() -> String {
+        "sys.integrity".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let Some(roots) = self.cfg.arg_str_array("paths") else {
+            log::warn!("[{}] '{}' missing/invalid args.paths (expected array of strings); not starting", Self::id().bright_magenta(), self.sid);
+            return;
+        };
+        let paths = match PathSet::new(&roots, &self.cfg.arg_str_array("exclude").unwrap_or_default(), self.cfg.arg_bool("xattrs").unwrap_or(true)) {
+            Ok(paths) => paths,
+            Err(err) => {
+                log::warn!("[{}] '{}' invalid args.exclude: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let (mut baseline, fresh) = match self.open_db().and_then(|(db, fresh)| Ok((Baseline::open(db)?, fresh))) {
+            Ok(opened) => opened,
+            Err(err) => {
+                log::error!("[{}] '{}' unable to open the baseline: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        if self.key.is_none() {
+            log::warn!("[{}] '{}' has no signing key, the baseline is not signed", Self::id().bright_magenta(), self.sid);
+        }
+
+        let trigger = RebaselineTrigger::register(&self.sid);
+        let pulse = self.cfg.arg_duration("verify-every").unwrap_or(DEFAULT_INTERVAL);
+
+        // Only a new state directory gets a baseline on its own. One that lost its baseline
+        // may have been wiped to hide a change, so it waits for an operator like a forged one.
+        if baseline.is_empty() && fresh {
+            match self.rebaseline(&paths, &mut baseline).await {
+                Ok(n) => log::info!("[{}] '{}' took the initial baseline of {n} paths", Self::id().bright_magenta(), self.sid),
+                Err(err) => {
+                    log::error!("[{}] '{}' unable to take the baseline: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                    return;
+                }
+            }
+        }
+
+        // A baseline that fails verification is not compared against until an operator rebaselines
+        let check = if baseline.is_empty() { Err("the state directory has no baseline".to_string()) } else { baseline.verify(self.key.as_ref()) };
+        let mut valid = match check {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("[{}] '{}' {err}; waiting for a rebaseline", Self::id().bright_magenta(), self.sid);
+                if let Some(ev) = self.event("invalid", "baseline", json!({"action": "invalid", "error": err})).await {
+                    (emit)(ev);
+                }
+                false
+            }
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut touched_rx = if self.cfg.arg_bool("watch").unwrap_or(true) { self.watch(&paths, &mut tasks) } else { mpsc::channel(1).1 };
+
+        log::info!(
+            "[{}] '{}' verifying {} paths from the baseline of {}, every {:?}",
+            Self::id().bright_magenta(),
+            self.sid,
+            baseline.len(),
+            baseline.created().unwrap_or_default(),
+            pulse
+        );
+
+        let mut drift = Drift::default();
+        let mut touched: Vec<PathBuf> = vec![];
+        let mut deadline: Option<tokio::time::Instant> = None;
+        let mut tick = tokio::time::interval(pulse);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    if valid {
+                        self.verify(&paths, &baseline, Scope::All, &mut drift, emit).await;
+                    }
+                }
+                Some(p) = touched_rx.recv() => {
+                    deadline.get_or_insert_with(|| tokio::time::Instant::now() + SETTLE);
+                    touched.push(p);
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                    deadline = None;
+                    let scope = Scope::Paths(std::mem::take(&mut touched));
+                    if valid {
+                        self.verify(&paths, &baseline, scope, &mut drift, emit).await;
+                    }
+                }
+                _ = trigger.requested() => match self.rebaseline(&paths, &mut baseline).await {
+                    Ok(n) => {
+                        log::info!("[{}] '{}' rebaselined {n} paths", Self::id().bright_magenta(), self.sid);
+                        drift.clear();
+                        valid = true;
+                        if let Some(ev) = self.event("rebaselined", "baseline", json!({"action": "rebaselined", "files": n})).await {
+                            (emit)(ev);
+                        }
+                    }
+                    Err(err) => log::error!("[{}] '{}' unable to rebaseline: {err}", Self::id().bright_magenta(), self.sid),
+                },
+            }
+        }
+    }
+}
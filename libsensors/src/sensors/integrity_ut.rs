@@ -0,0 +1,224 @@
+use crate::{
+    sensors::{
+        integrity::{Baseline, Drift, FileRecord, Finding, IntegritySensor, PathSet, RebaselineTrigger, Scope, compare, request_rebaseline},
+        sensor::Sensor,
+        state_name,
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, fs, os::unix::fs::PermissionsExt, path::Path};
+
+/// Returns a `sys.integrity` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.integrity",
+        "tag": tag,
+        "args": args
+    }))
+    .unwrap()
+}
+
+fn mk_baseline() -> Baseline {
+    Baseline::open(sled::Config::new().temporary(true).open().unwrap()).unwrap()
+}
+
+fn mk_paths(root: &Path, excludes: &[&str]) -> PathSet {
+    PathSet::new(&[root.to_string_lossy().into_owned()], &excludes.iter().map(|e| e.to_string()).collect::<Vec<_>>(), false).unwrap()
+}
+
+#[test]
+fn make_eid_uses_action_and_file() {
+    let s = IntegritySensor::new("sid".to_string(), mk_cfg(Some("etc"), json!({})));
+    assert_eq!(s.make_eid("modified", "/etc/passwd"), "sid|sys.integrity@etc|modified@/etc/passwd|0");
+}
+
+#[test]
+fn state_name_stays_in_the_state_directory() {
+    assert_eq!(state_name("etc-files@v2.1"), "etc-files@v2.1");
+    for sid in ["../../etc", "/etc/cron.d", "..", ".hidden", ""] {
+        let name = state_name(sid);
+        assert!(!name.contains('/') && !name.starts_with('.'), "{sid:?} -> {name:?}");
+    }
+
+    // Made safe, different ids still get different state
+    assert_ne!(state_name("a/b"), state_name("a_b"));
+    assert_ne!(state_name("a/b"), state_name("a:b"));
+}
+
+#[test]
+fn record_diff_names_changed_properties() {
+    let a = FileRecord { kind: "file".into(), sha256: Some("aa".into()), mode: 0o644, uid: 0, gid: 0, size: 3, ..Default::default() };
+    let b = FileRecord { sha256: Some("bb".into()), mode: 0o600, gid: 1, ..a.clone() };
+
+    assert!(a.diff(&a).is_empty());
+    assert_eq!(a.diff(&b), vec!["sha256", "mode", "owner"]);
+}
+
+#[test]
+fn scan_records_kinds_and_skips_excludes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.conf"), "a=1").unwrap();
+    fs::write(dir.path().join("app.conf.swp"), "junk").unwrap();
+    fs::create_dir(dir.path().join("cache")).unwrap();
+    fs::write(dir.path().join("cache").join("blob"), "x").unwrap();
+    std::os::unix::fs::symlink("app.conf", dir.path().join("current")).unwrap();
+
+    let records = mk_paths(dir.path(), &["*.swp", "cache"]).scan();
+    let get = |p: &Path| records.get(&p.to_string_lossy().into_owned()).cloned();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(get(dir.path()).unwrap().kind, "dir");
+    assert_eq!(get(dir.path()).unwrap().size, 0);
+    let conf = get(&dir.path().join("app.conf")).unwrap();
+    assert_eq!(conf.kind, "file");
+    assert_eq!(conf.size, 3);
+    assert_eq!(conf.sha256, Some(format!("{:x}", Sha256::digest(b"a=1"))));
+    assert_eq!(get(&dir.path().join("current")).unwrap().target.as_deref(), Some("app.conf"));
+    assert!(get(&dir.path().join("app.conf.swp")).is_none());
+    assert!(get(&dir.path().join("cache").join("blob")).is_none());
+}
+
+#[test]
+fn signed_baseline_verifies_and_refuses_tampering() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("passwd"), "root:x:0:0").unwrap();
+    let (key, _) = libsysinspect::rsa::keys::keygen(1024).unwrap();
+    let (other, _) = libsysinspect::rsa::keys::keygen(1024).unwrap();
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let mut b = Baseline::open(db).unwrap();
+    assert!(b.is_empty());
+    b.replace(&mk_paths(dir.path(), &[]).scan(), Some(&key)).unwrap();
+    assert!(!b.is_empty());
+    assert!(b.verify(Some(&key)).is_ok());
+    assert!(b.verify(Some(&other)).is_err());
+
+    // A record rewritten behind the sensor's back no longer matches the signed digest
+    let path = dir.path().join("passwd").to_string_lossy().into_owned();
+    let mut rec = b.get(&path).unwrap();
+    rec.sha256 = Some("forged".into());
+    b.files.insert(path.as_bytes(), serde_json::to_vec(&rec).unwrap()).unwrap();
+    assert!(b.verify(Some(&key)).is_err());
+}
+
+#[test]
+fn unsigned_baseline_is_refused_once_a_key_is_known() {
+    let dir = tempfile::tempdir().unwrap();
+    let (key, _) = libsysinspect::rsa::keys::keygen(1024).unwrap();
+
+    let mut b = mk_baseline();
+    b.replace(&mk_paths(dir.path(), &[]).scan(), None).unwrap();
+    assert!(b.verify(None).is_ok());
+    assert_eq!(b.verify(Some(&key)).unwrap_err(), "baseline is not signed");
+}
+
+#[test]
+fn new_baseline_replaces_the_previous_one_at_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let paths = mk_paths(dir.path(), &[]);
+    fs::write(dir.path().join("a"), "1").unwrap();
+
+    let mut b = Baseline::open(sled::open(state.path()).unwrap()).unwrap();
+    b.replace(&paths.scan(), None).unwrap();
+    let first = b.files.name();
+    fs::write(dir.path().join("b"), "2").unwrap();
+    b.replace(&paths.scan(), None).unwrap();
+    assert_ne!(b.files.name(), first);
+    assert!(!b.db.tree_names().contains(&first));
+
+    // Records of a baseline that was never switched over to are dropped on open
+    b.db.open_tree("files.unfinished").unwrap().insert(b"x", b"y".to_vec()).unwrap();
+    let current = b.files.name();
+    drop(b);
+    let b = Baseline::open(sled::open(state.path()).unwrap()).unwrap();
+    assert_eq!(b.files.name(), current);
+    assert_eq!(b.len(), 3);
+    assert!(b.verify(None).is_ok());
+    assert!(!b.db.tree_names().iter().any(|t| t.as_ref() == b"files.unfinished"));
+}
+
+#[test]
+fn compare_finds_added_removed_and_modified_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = mk_paths(dir.path(), &[]);
+    fs::write(dir.path().join("keep"), "same").unwrap();
+    fs::write(dir.path().join("edit"), "old").unwrap();
+    fs::write(dir.path().join("gone"), "bye").unwrap();
+
+    let mut b = mk_baseline();
+    b.replace(&paths.scan(), None).unwrap();
+    assert!(compare(&paths, &b, &Scope::All, &HashSet::new()).is_empty());
+
+    fs::write(dir.path().join("edit"), "new content").unwrap();
+    fs::remove_file(dir.path().join("gone")).unwrap();
+    fs::write(dir.path().join("new"), "hi").unwrap();
+
+    let found = compare(&paths, &b, &Scope::All, &HashSet::new());
+    let name = |p: &str| Path::new(p).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut changed = found.iter().map(|(p, e, a)| (name(p), e.is_some(), a.is_some())).collect::<Vec<_>>();
+    changed.sort();
+    // The directory itself changed its modification only, which is not recorded
+    assert_eq!(changed, vec![("edit".to_string(), true, true), ("gone".to_string(), true, false), ("new".to_string(), false, true)]);
+
+    // Only the touched paths are compared for filesystem events
+    let touched = compare(&paths, &b, &Scope::Paths(vec![dir.path().join("gone")]), &HashSet::new());
+    assert_eq!(touched.len(), 1);
+    assert_eq!(name(&touched[0].0), "gone");
+}
+
+#[test]
+fn compare_sees_permission_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths = mk_paths(dir.path(), &[]);
+    let file = dir.path().join("shadow");
+    fs::write(&file, "secret").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let mut b = mk_baseline();
+    b.replace(&paths.scan(), None).unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let found = compare(&paths, &b, &Scope::Paths(vec![file.clone()]), &HashSet::new());
+    assert_eq!(found.len(), 1);
+    let (_, expected, actual) = &found[0];
+    assert_eq!(expected.as_ref().unwrap().diff(actual.as_ref().unwrap()), vec!["mode"]);
+}
+
+#[test]
+fn drift_is_reported_once_until_it_changes_or_is_restored() {
+    let base = FileRecord { kind: "file".into(), sha256: Some("aa".into()), mode: 0o644, size: 1, ..Default::default() };
+    let edited = FileRecord { sha256: Some("bb".into()), ..base.clone() };
+    let edited_again = FileRecord { sha256: Some("cc".into()), ..base.clone() };
+    let mut drift = Drift::default();
+
+    assert!(
+        matches!(drift.observe("/etc/a", Some(base.clone()), Some(edited.clone())), Some(Finding::Modified { ref changes, .. }) if changes == &vec!["sha256"])
+    );
+    assert_eq!(drift.observe("/etc/a", Some(base.clone()), Some(edited.clone())), None);
+    assert!(matches!(drift.observe("/etc/a", Some(base.clone()), Some(edited_again)), Some(Finding::Modified { .. })));
+    assert_eq!(drift.observe("/etc/a", Some(base.clone()), Some(base.clone())), Some(Finding::Restored));
+    assert_eq!(drift.observe("/etc/a", Some(base.clone()), Some(base.clone())), None);
+
+    assert_eq!(drift.observe("/etc/b", None, Some(base.clone())), Some(Finding::Added(base.clone())));
+    assert_eq!(drift.observe("/etc/c", Some(base.clone()), None), Some(Finding::Removed(base)));
+    assert_eq!(drift.reported().len(), 2);
+    drift.clear();
+    assert!(drift.reported().is_empty());
+}
+
+#[tokio::test]
+async fn rebaseline_reaches_registered_sensors_only() {
+    let a = RebaselineTrigger::register("ut-integrity-a");
+    let b = RebaselineTrigger::register("ut-integrity-b");
+
+    assert_eq!(request_rebaseline(Some("ut-integrity-a")), vec!["ut-integrity-a".to_string()]);
+    tokio::time::timeout(std::time::Duration::from_secs(1), a.requested()).await.unwrap();
+    assert!(tokio::time::timeout(std::time::Duration::from_millis(50), b.requested()).await.is_err());
+
+    drop(b);
+    assert!(request_rebaseline(Some("ut-integrity-b")).is_empty());
+    assert!(request_rebaseline(None).contains(&"ut-integrity-a".to_string()));
+}
@@ -0,0 +1,414 @@
+use crate::{
+    argparse::SensorArgs,
+    sensors::{
+        logtail::Patterns,
+        sensor::{Sensor, SensorEvent},
+    },
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value, json};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::PathBuf,
+    process::Command,
+    time::Duration,
+};
+use tokio::{sync::mpsc, time};
+
+/// Default kernel log device on Linux
+const KMSG: &str = "/dev/kmsg";
+
+/// Syslog level names, by the level number in `/dev/kmsg`
+const LEVELS: &[&str] = &["emerg", "alert", "crit", "err", "warning", "notice", "info", "debug"];
+
+/// Event data keys that custom pattern captures cannot override
+const RESERVED: &[&str] = &["action", "pattern", "message", "level", "uptime"];
+
+/// Builds the event fields of one message family from the regex captures
+type Fields = fn(&Captures) -> Map<String, Value>;
+
+lazy_static! {
+    /// Known message families: the action name, the pattern and its fields
+    static ref FAMILIES: Vec<(&'static str, Regex, Fields)> = vec![
+        (
+            "oom",
+            Regex::new(r"(?:(Memory cgroup )?[Oo]ut of memory.*?: )?Killed process (\d+) \(([^)]*)\)(?:.*?anon-rss:(\d+)kB)?").unwrap(),
+            |c| fields(&[("pid", num(c, 2)), ("comm", text(c, 3)), ("anon-rss-kb", num(c, 4)), ("cgroup", json!(c.get(1).is_some()))]),
+        ),
+        (
+            "oom",
+            Regex::new(r"^pid (\d+) \(([^)]*)\),.*? was killed: (.+)$").unwrap(),
+            |c| fields(&[("pid", num(c, 1)), ("comm", text(c, 2)), ("reason", text(c, 3))]),
+        ),
+        (
+            "segfault",
+            Regex::new(r"^(\S+)\[(\d+)\]: segfault at (\S+) ip (\S+) sp \S+ error (\d+)(?: in ([^\[\s]+))?").unwrap(),
+            |c| fields(&[("comm", text(c, 1)), ("pid", num(c, 2)), ("address", text(c, 3)), ("ip", text(c, 4)), ("error", num(c, 5)), ("object", text(c, 6))]),
+        ),
+        (
+            "segfault",
+            Regex::new(r"^traps: (\S+)\[(\d+)\] (.+?) ip:(\S+)").unwrap(),
+            |c| fields(&[("comm", text(c, 1)), ("pid", num(c, 2)), ("fault", text(c, 3)), ("ip", text(c, 4))]),
+        ),
+        (
+            "segfault",
+            Regex::new(r"^pid (\d+) \(([^)]*)\),(?: jid \d+,)? uid (\d+): exited on signal (\d+)").unwrap(),
+            |c| fields(&[("pid", num(c, 1)), ("comm", text(c, 2)), ("uid", num(c, 3)), ("signal", num(c, 4))]),
+        ),
+        (
+            "fs-readonly",
+            Regex::new(r"^(\w+?)(?:-fs)?:?(?: \w+)? \((?:device )?([^)]+)\).*?(?:[Rr]emounting filesystem read-only|forced readonly|[Ss]hutting down filesystem)").unwrap(),
+            |c| fields(&[("fs", text(c, 1)), ("device", text(c, 2))]),
+        ),
+        (
+            "hung-task",
+            Regex::new(r"^INFO: task (.+):(\d+) blocked for more than (\d+) seconds").unwrap(),
+            |c| fields(&[("comm", text(c, 1)), ("pid", num(c, 2)), ("seconds", num(c, 3))]),
+        ),
+        (
+            "nic-reset",
+            Regex::new(r"^NETDEV WATCHDOG: (\S+) \(([^)]+)\): transmit queue (\d+) timed out").unwrap(),
+            |c| fields(&[("interface", text(c, 1)), ("driver", text(c, 2)), ("queue", num(c, 3))]),
+        ),
+        (
+            "nic-reset",
+            Regex::new(r"^(\S+) \S+ (\S+): (?:Reset adapter|Detected (?:Tx |Hardware )?Unit Hang|.*\b[Tt][Xx] timeout)").unwrap(),
+            |c| fields(&[("driver", text(c, 1)), ("interface", text(c, 2))]),
+        ),
+        (
+            "io-error",
+            Regex::new(r"I/O error,? (?:on )?dev (\w+)(?:, (?:sector|logical block) (\d+))?(?:.*? op \S+:\((\w+)\))?").unwrap(),
+            |c| fields(&[("device", text(c, 1)), ("sector", num(c, 2)), ("op", text(c, 3))]),
+        ),
+        (
+            "io-error",
+            Regex::new(r"^nvme (nvme\d+): I/O \d+ QID \d+ timeout").unwrap(),
+            |c| fields(&[("device", text(c, 1))]),
+        ),
+        (
+            "io-error",
+            Regex::new(r"^(ata\d+(?:\.\d+)?): (?:failed command|exception Emask)").unwrap(),
+            |c| fields(&[("device", text(c, 1))]),
+        ),
+        (
+            "hardware-error",
+            Regex::new(r"\[Hardware Error\]|^EDAC \S+: \d+ [CU]E |^mce: |Machine Check").unwrap(),
+            |_| Map::new(),
+        ),
+    ];
+}
+
+fn fields(kv: &[(&str, Value)]) -> Map<String, Value> {
+    kv.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+}
+
+fn text(c: &Captures, i: usize) -> Value {
+    c.get(i).map(|m| json!(m.as_str())).unwrap_or(Value::Null)
+}
+
+fn num(c: &Captures, i: usize) -> Value {
+    c.get(i).and_then(|m| m.as_str().parse::<u64>().ok()).map(|n| json!(n)).unwrap_or(Value::Null)
+}
+
+/// One kernel log message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct KernelRecord {
+    /// Syslog level, `None` if the source does not tell
+    pub(crate) level: Option<u8>,
+    /// Seconds since boot
+    pub(crate) uptime: Option<f64>,
+    pub(crate) message: String,
+}
+
+impl KernelRecord {
+    /// Decodes one `/dev/kmsg` record: `<prio>,<seq>,<usec>,<flags>[,...];<message>`.
+    ///
+    /// Continuation lines, which carry the device properties and start with a
+    /// space, are not records.
+    pub(crate) fn from_kmsg(line: &str) -> Option<Self> {
+        if line.starts_with(' ') {
+            return None;
+        }
+        let (head, msg) = line.split_once(';')?;
+        let mut head = head.split(',');
+        let prio = head.next()?.parse::<u32>().ok()?;
+        let usec = head.nth(1).and_then(|u| u.parse::<u64>().ok());
+        Some(Self { level: Some((prio & 7) as u8), uptime: usec.map(|u| u as f64 / 1_000_000.0), message: unescape(msg) })
+    }
+
+    /// Decodes one `dmesg` line, with or without a `[ uptime]` prefix.
+    pub(crate) fn from_dmesg(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(rest) = line.strip_prefix('[')
+            && let Some((ts, msg)) = rest.split_once(']')
+            && let Ok(uptime) = ts.trim().parse::<f64>()
+        {
+            return Some(Self { level: None, uptime: Some(uptime), message: msg.trim_start().to_string() });
+        }
+        Some(Self { level: None, uptime: None, message: line.to_string() })
+    }
+
+    pub(crate) fn level_name(&self) -> Option<&'static str> {
+        self.level.and_then(|l| LEVELS.get(l as usize).copied())
+    }
+}
+
+/// Decodes the `\xNN` escapes `/dev/kmsg` uses for non-printable bytes.
+fn unescape(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'\\'
+            && b.get(i + 1) == Some(&b'x')
+            && let Some(c) = b.get(i + 2..i + 4).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            out.push(c);
+            i += 4;
+            continue;
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Returns the family and the fields of a known kernel message.
+pub(crate) fn classify(message: &str) -> Option<(&'static str, Map<String, Value>)> {
+    FAMILIES.iter().find_map(|(action, re, f)| re.captures(message).map(|c| (*action, f(&c))))
+}
+
+/// Returns the lines of a `dmesg` output that were not in the previous one.
+///
+/// The message buffer is a ring, so old lines drop off its start. New lines
+/// follow the last line seen before; if that is gone, all lines are new.
+pub(crate) fn new_dmesg_lines(last: Option<&str>, now: &[String]) -> Vec<String> {
+    match last.and_then(|l| now.iter().rposition(|n| n == l)) {
+        Some(i) => now[i + 1..].to_vec(),
+        None => now.to_vec(),
+    }
+}
+
+/// Follows the kernel ring buffer from its end and sends every message.
+///
+/// `/dev/kmsg` is read without blocking, and waited on with `poll(2)` for at
+/// most one pulse at a time, so this blocking thread stops within a pulse once
+/// the receiver is gone, even if the kernel logs nothing.
+pub(crate) fn follow_kmsg(path: PathBuf, pulse: Duration, tx: mpsc::UnboundedSender<KernelRecord>) {
+    let mut file = match OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            log::warn!("Unable to open {}: {err}", path.display());
+            return;
+        }
+    };
+    if let Err(err) = file.seek(SeekFrom::End(0)) {
+        log::warn!("Unable to seek {}: {err}", path.display());
+        return;
+    }
+
+    let mut buf = vec![0u8; 16 * 1024];
+    let mut partial = String::new();
+    loop {
+        if tx.is_closed() {
+            return;
+        }
+        match file.read(&mut buf) {
+            // A regular file, e.g. a copy of the kernel log: wait for more
+            Ok(0) => std::thread::sleep(pulse),
+            Ok(n) => {
+                partial.push_str(&String::from_utf8_lossy(&buf[..n]));
+                while let Some(nl) = partial.find('\n') {
+                    let line = partial.drain(..=nl).collect::<String>();
+                    if let Some(rec) = KernelRecord::from_kmsg(line.trim_end_matches('\n'))
+                        && tx.send(rec).is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            // No new message yet
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => wait_readable(&file, pulse),
+            // The reader fell behind and messages were overwritten
+            Err(err) if err.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(err) => {
+                log::warn!("Unable to read {}: {err}", path.display());
+                return;
+            }
+        }
+    }
+}
+
+/// Waits until `file` has something to read, or `timeout` passed.
+fn wait_readable(file: &File, timeout: Duration) {
+    let mut pfd = libc::pollfd { fd: file.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    // Interrupted or timed out, the caller reads again either way
+    unsafe { libc::poll(&mut pfd, 1, timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int) };
+}
+
+/// Polls `dmesg` and sends the messages that are new since the previous poll.
+async fn follow_dmesg(pulse: Duration, tx: mpsc::UnboundedSender<KernelRecord>) {
+    let mut last: Option<String> = None;
+    let mut primed = false;
+    let mut tick = time::interval(pulse);
+    loop {
+        tick.tick().await;
+        let Ok(out) = tokio::task::spawn_blocking(|| Command::new("dmesg").output()).await else {
+            continue;
+        };
+        let lines = match out {
+            Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).lines().map(str::to_string).collect::<Vec<_>>(),
+            Ok(_) | Err(_) => {
+                log::debug!("Unable to run dmesg");
+                continue;
+            }
+        };
+
+        let new = new_dmesg_lines(last.as_deref(), &lines);
+        last = lines.last().cloned().or(last);
+        if !std::mem::replace(&mut primed, true) {
+            continue;
+        }
+        for rec in new.iter().filter_map(|l| KernelRecord::from_dmesg(l)) {
+            if tx.send(rec).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Emits an event for kernel messages of known families, such as OOM kills,
+/// segfaults, filesystems remounted read-only, hung tasks, NIC resets, I/O
+/// and hardware errors, and for messages matching custom patterns.
+#[derive(Debug, Clone)]
+pub struct KernelSensor {
+    sid: String,
+    cfg: SensorConf,
+}
+
+impl KernelSensor {
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Returns `true` if the action is enabled by the sensor options.
+    /// Without options every family and every custom pattern is emitted.
+    pub(crate) fn wants(&self, action: &str) -> bool {
+        self.cfg.opts().is_empty() || self.cfg.opts().iter().any(|o| o == action)
+    }
+
+    /// Builds a stable event id for a kernel message.
+    pub(crate) fn make_eid(&self, action: &str, subject: &str) -> String {
+        format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, subject, 0)
+    }
+
+    /// Custom patterns from `args.patterns`, if any.
+    pub(crate) fn patterns(&self) -> Result<Option<Patterns>, String> {
+        if self.cfg.args().get("patterns").is_none() {
+            return Ok(None);
+        }
+        Patterns::from_cfg(&self.cfg).map(Some)
+    }
+
+    /// Turns one kernel message into events: one for its family, if known,
+    /// and one for every custom pattern it matches.
+    pub(crate) fn matches(&self, rec: &KernelRecord, patterns: Option<&Patterns>) -> Vec<(String, String, Map<String, Value>)> {
+        let mut out = vec![];
+        if let Some((action, data)) = classify(&rec.message)
+            && self.wants(action)
+        {
+            let subject = ["device", "interface", "comm"].iter().find_map(|k| data.get(*k).and_then(|v| v.as_str())).unwrap_or("kernel").to_string();
+            out.push((action.to_string(), subject, data));
+        }
+
+        for (pattern, captures) in patterns.map(|p| p.matches(&rec.message)).unwrap_or_default() {
+            if !self.wants("matched") {
+                break;
+            }
+            let mut data = Map::new();
+            data.insert("pattern".to_string(), json!(pattern));
+            for (k, v) in captures {
+                if RESERVED.contains(&k.as_str()) {
+                    log::debug!("[{}] '{}' capture '{k}' clashes with an event field, dropped", Self::id().bright_magenta(), self.sid);
+                    continue;
+                }
+                data.insert(k, v);
+            }
+            out.push(("matched".to_string(), pattern, data));
+        }
+        out
+    }
+
+    /// Packages one classified message, honouring optional lock-based duplicate suppression.
+    async fn event(&self, rec: &KernelRecord, action: &str, subject: &str, fields: Map<String, Value>) -> Option<SensorEvent> {
+        let eid = self.make_eid(action, subject);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.kernel", &eid).await {
+            return None;
+        }
+
+        let mut data = Map::new();
+        data.insert("action".to_string(), json!(action));
+        data.insert("message".to_string(), json!(rec.message));
+        data.insert("level".to_string(), json!(rec.level_name()));
+        data.insert("uptime".to_string(), json!(rec.uptime));
+        data.extend(fields);
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.kernel",
+            "data": data,
+        }))
+    }
+}
+
+#[async_trait]
+impl Sensor for KernelSensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self { sid: id, cfg }
+    }
+
+    fn id() -> String {
+        "sys.kernel".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let patterns = match self.patterns() {
+            Ok(patterns) => patterns,
+            Err(err) => {
+                log::warn!("[{}] '{}' {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(1));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _reader = if cfg!(target_os = "linux") || self.cfg.arg_str("kmsg").is_some() {
+            let path = PathBuf::from(self.cfg.arg_str("kmsg").unwrap_or_else(|| KMSG.to_string()));
+            log::info!("[{}] '{}' following {}", Self::id().bright_magenta(), self.sid, path.display());
+            tokio::task::spawn_blocking(move || follow_kmsg(path, pulse, tx))
+        } else {
+            log::info!("[{}] '{}' polling dmesg with pulse {:?}", Self::id().bright_magenta(), self.sid, pulse);
+            tokio::spawn(follow_dmesg(pulse, tx))
+        };
+
+        while let Some(rec) = rx.recv().await {
+            for (action, subject, fields) in self.matches(&rec, patterns.as_ref()) {
+                if let Some(ev) = self.event(&rec, &action, &subject, fields).await {
+                    (emit)(ev);
+                }
+            }
+        }
+        log::warn!("[{}] '{}' lost the kernel log", Self::id().bright_magenta(), self.sid);
+    }
+}
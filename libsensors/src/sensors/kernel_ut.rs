@@ -0,0 +1,189 @@
+use crate::{
+    sensors::{
+        kernel::{KernelRecord, KernelSensor, classify, follow_kmsg, new_dmesg_lines},
+        sensor::Sensor,
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use std::{
+    fs,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a `sys.kernel` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, opts: &[&str], args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.kernel",
+        "tag": tag,
+        "opts": opts,
+        "interval": {"secs": 0, "nanos": 10_000_000},
+        "args": args
+    }))
+    .unwrap()
+}
+
+fn family(message: &str) -> (&'static str, serde_json::Value) {
+    let (action, data) = classify(message).unwrap();
+    (action, serde_json::Value::Object(data))
+}
+
+fn rec(message: &str) -> KernelRecord {
+    KernelRecord { message: message.to_string(), ..Default::default() }
+}
+
+#[test]
+fn make_eid_uses_action_and_subject() {
+    let s = KernelSensor::new("sid".to_string(), mk_cfg(Some("hw"), &[], json!({})));
+    assert_eq!(s.make_eid("io-error", "sda"), "sid|sys.kernel@hw|io-error@sda|0");
+}
+
+#[test]
+fn kmsg_records_are_decoded() {
+    let r = KernelRecord::from_kmsg("3,1234,5678901234,-;Out of memory: Killed process 812 (java)\\x0a").unwrap();
+    assert_eq!(r.level_name(), Some("err"));
+    assert_eq!(r.uptime, Some(5678.901234));
+    assert_eq!(r.message, "Out of memory: Killed process 812 (java)\n");
+
+    // Facility bits are not part of the level
+    assert_eq!(KernelRecord::from_kmsg("30,1,2,-;systemd[1]: started").unwrap().level_name(), Some("info"));
+    assert!(KernelRecord::from_kmsg(" SUBSYSTEM=block").is_none());
+    assert!(KernelRecord::from_kmsg("no header").is_none());
+}
+
+#[test]
+fn dmesg_lines_are_decoded() {
+    let r = KernelRecord::from_dmesg("[  12.500000] nvme nvme0: I/O 12 QID 3 timeout, aborting").unwrap();
+    assert_eq!((r.uptime, r.message.as_str()), (Some(12.5), "nvme nvme0: I/O 12 QID 3 timeout, aborting"));
+    assert_eq!(KernelRecord::from_dmesg("pid 77 (sh), jid 0, uid 0: exited on signal 11").unwrap().uptime, None);
+    assert!(KernelRecord::from_dmesg("  ").is_none());
+}
+
+#[test]
+fn oom_kills_name_the_victim() {
+    let (action, data) = family("Out of memory: Killed process 4242 (java) total-vm:9000000kB, anon-rss:4123456kB, file-rss:0kB");
+    assert_eq!(action, "oom");
+    assert_eq!(data, json!({"pid": 4242, "comm": "java", "anon-rss-kb": 4123456, "cgroup": false}));
+
+    assert_eq!(family("Memory cgroup out of memory: Killed process 99 (php-fpm) total-vm:1kB").1["cgroup"], true);
+    assert_eq!(family("pid 812 (postgres), jid 0, uid 70, was killed: out of swap space").1["reason"], "out of swap space");
+}
+
+#[test]
+fn segfaults_name_the_process() {
+    let (action, data) = family("nginx[1312]: segfault at 0 ip 00007f1c2a3b sp 00007ffd5e6f error 4 in libc.so.6[7f1c2a000+195000] likely on CPU 1");
+    assert_eq!(action, "segfault");
+    assert_eq!((data["comm"].as_str(), data["pid"].as_u64(), data["object"].as_str()), (Some("nginx"), Some(1312), Some("libc.so.6")));
+
+    assert_eq!(
+        family("traps: a.out[77] general protection fault ip:401136 sp:7ffc error:0 in a.out[401000+1000]").1["fault"],
+        "general protection fault"
+    );
+    assert_eq!(family("pid 77 (sh), jid 0, uid 1001: exited on signal 11 (core dumped)").1["signal"], 11);
+}
+
+#[test]
+fn storage_and_network_failures_are_classified() {
+    assert_eq!(family("EXT4-fs (sda1): Remounting filesystem read-only"), ("fs-readonly", json!({"fs": "EXT4", "device": "sda1"})));
+    assert_eq!(family("BTRFS info (device nvme0n1p2): forced readonly").1["device"], "nvme0n1p2");
+    assert_eq!(family("XFS (dm-0): Corruption of in-memory data detected.  Shutting down filesystem").1["fs"], "XFS");
+
+    assert_eq!(
+        family("INFO: task kworker/u8:2:123 blocked for more than 120 seconds."),
+        ("hung-task", json!({"comm": "kworker/u8:2", "pid": 123, "seconds": 120}))
+    );
+
+    assert_eq!(
+        family("NETDEV WATCHDOG: eth0 (e1000e): transmit queue 0 timed out 5000 ms"),
+        ("nic-reset", json!({"interface": "eth0", "driver": "e1000e", "queue": 0}))
+    );
+    assert_eq!(family("e1000e 0000:00:19.0 eno1: Reset adapter unexpectedly").1["interface"], "eno1");
+
+    assert_eq!(
+        family("I/O error, dev sdb, sector 2048 op 0x1:(WRITE) flags 0x800 phys_seg 1 prio class 2"),
+        ("io-error", json!({"device": "sdb", "sector": 2048, "op": "WRITE"}))
+    );
+    assert_eq!(family("Buffer I/O error on dev sda1, logical block 0, async page read").1["device"], "sda1");
+    assert_eq!(family("ata3.00: failed command: READ FPDMA QUEUED").1["device"], "ata3.00");
+
+    assert_eq!(family("mce: [Hardware Error]: Machine check events logged").0, "hardware-error");
+    assert!(classify("usb 1-1: new high-speed USB device number 2 using xhci_hcd").is_none());
+}
+
+#[test]
+fn custom_patterns_and_options_select_events() {
+    let s = KernelSensor::new(
+        "sid".to_string(),
+        mk_cfg(None, &["oom", "matched"], json!({"patterns": {"thermal": r"CPU(?P<cpu>\d+): .*temperature above threshold"}})),
+    );
+    let patterns = s.patterns().unwrap();
+
+    let out = s.matches(&rec("CPU3: Core temperature above threshold, cpu clock throttled"), patterns.as_ref());
+    assert_eq!(out.len(), 1);
+    assert_eq!((out[0].0.as_str(), out[0].1.as_str()), ("matched", "thermal"));
+    assert_eq!(out[0].2["cpu"], "3");
+
+    let oom = s.matches(&rec("Out of memory: Killed process 1 (x)"), patterns.as_ref());
+    assert_eq!((oom[0].0.as_str(), oom[0].1.as_str()), ("oom", "x"));
+    // Not enabled in the options
+    assert!(s.matches(&rec("ata1: exception Emask 0x0 SAct 0x0"), patterns.as_ref()).is_empty());
+
+    let broken = KernelSensor::new("sid".to_string(), mk_cfg(None, &[], json!({"patterns": {"broken": "("}})));
+    assert!(broken.patterns().is_err());
+    assert!(KernelSensor::new("sid".to_string(), mk_cfg(None, &[], json!({}))).patterns().unwrap().is_none());
+}
+
+#[test]
+fn dmesg_polls_return_new_lines_only() {
+    let lines = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(new_dmesg_lines(Some("b"), &lines(&["a", "b", "c", "d"])), lines(&["c", "d"]));
+    assert!(new_dmesg_lines(Some("d"), &lines(&["a", "b", "c", "d"])).is_empty());
+    // The ring wrapped past the last line seen
+    assert_eq!(new_dmesg_lines(Some("x"), &lines(&["e", "f"])), lines(&["e", "f"]));
+}
+
+#[tokio::test]
+async fn run_follows_new_kernel_messages() {
+    let dir = tempfile::tempdir().unwrap();
+    let kmsg = dir.path().join("kmsg");
+    fs::write(&kmsg, "3,1,100,-;Out of memory: Killed process 1 (old)\n").unwrap();
+
+    let s = KernelSensor::new("sid".to_string(), mk_cfg(None, &[], json!({"kmsg": kmsg.to_string_lossy()})));
+    let writer = {
+        let kmsg = kmsg.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut f = fs::OpenOptions::new().append(true).open(&kmsg).unwrap();
+            writeln!(f, "6,2,200,-;usb 1-1: new device").unwrap();
+            writeln!(f, "3,3,3000000,-;Out of memory: Killed process 4242 (java) total-vm:1kB, anon-rss:2048kB").unwrap();
+            writeln!(f, " SUBSYSTEM=memory").unwrap();
+        })
+    };
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let _ = tokio::time::timeout(Duration::from_millis(300), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+    writer.await.unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["eid"], "sid|sys.kernel|oom@java|0");
+    assert_eq!(events[0]["data"]["pid"], 4242);
+    assert_eq!(events[0]["data"]["level"], "err");
+    assert_eq!(events[0]["data"]["uptime"], 3.0);
+}
+
+#[tokio::test]
+async fn kmsg_reader_stops_once_the_receiver_is_gone() {
+    let dir = tempfile::tempdir().unwrap();
+    let kmsg = dir.path().join("kmsg");
+    fs::write(&kmsg, "").unwrap();
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let reader = tokio::task::spawn_blocking(move || follow_kmsg(kmsg, Duration::from_millis(20), tx));
+    drop(rx);
+    assert!(tokio::time::timeout(Duration::from_secs(1), reader).await.is_ok());
+}
@@ -0,0 +1,617 @@
+use crate::{
+    argparse::SensorArgs,
+    sensors::{
+        logtail::Tail,
+        sensor::{Sensor, SensorEvent},
+    },
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use glob::Pattern;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::json;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, Lines},
+    process::{ChildStdout, Command},
+    time,
+};
+
+/// Auth logs looked up when `authlog` is not set, in this order
+const AUTH_LOGS: &[&str] = &["/var/log/auth.log", "/var/log/secure"];
+
+/// Default login records file
+const WTMP: &str = "/var/log/wtmp";
+
+/// Default time window of the brute-force threshold
+const DEFAULT_BRUTE_FORCE_WINDOW: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    /// `<timestamp> <host> <program>[<pid>]: <message>`, with a BSD or an RFC 3339 timestamp
+    static ref SYSLOG: Regex = Regex::new(r"^(?:\w{3}\s+\d+\s+[\d:]+|\d{4}-\d\d-\d\dT\S+)\s+\S+\s+([^\s\[:]+)(?:\[\d+\])?:\s*(.*)$").unwrap();
+    /// The user name is chosen by the client and may itself read `from <address> port <port>`,
+    /// so the address is the last one on the line
+    static ref SSH_ACCEPTED: Regex = Regex::new(r"^Accepted (\S+) for (.+) from (\S+) port (\d+)(?: ssh2)?(?:: .*)?$").unwrap();
+    static ref SSH_FAILED: Regex = Regex::new(r"^Failed (\S+) for (invalid user )?(.+) from (\S+) port (\d+)(?: ssh2)?(?:: .*)?$").unwrap();
+    static ref PAM_FAILED: Regex = Regex::new(r"^pam_unix\(([^:]+):auth\): authentication failure;(.*)$").unwrap();
+    static ref SUDO: Regex = Regex::new(r"^\s*(\S+) : (.*?)TTY=(\S+) ; PWD=.*? ; USER=(\S+) ;.*?COMMAND=(.*)$").unwrap();
+}
+
+/// One interactive access, as read from an auth log, the journal or wtmp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct LoginEvent {
+    /// `login`, `logout`, `ssh`, `failed` or `sudo`
+    pub(crate) action: &'static str,
+    pub(crate) user: Option<String>,
+    /// Source address, or host name if the source logs no address
+    pub(crate) address: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) tty: Option<String>,
+    /// SSH authentication method, PAM service, `sudo`, or `local`/`remote` for wtmp records
+    pub(crate) method: Option<String>,
+    /// Why a failed authentication failed, for sudo
+    pub(crate) reason: Option<String>,
+    /// User a sudo command runs as
+    pub(crate) target: Option<String>,
+    pub(crate) command: Option<String>,
+}
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `10.0.0.0/8`.
+/// A plain address is a network of one host.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = s.split_once('/').map(|(a, p)| (a, Some(p))).unwrap_or((s, None));
+        let addr = addr.parse::<IpAddr>().map_err(|err| format!("invalid network '{s}': {err}"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("invalid network '{s}': bad prefix length"))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl Cidr {
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// User and source network filters.
+///
+/// Events without a user or without a source address, e.g. a local console
+/// login, are not filtered by the respective patterns.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LoginFilter {
+    users: Vec<Pattern>,
+    exclude_users: Vec<Pattern>,
+    networks: Vec<Cidr>,
+    exclude_networks: Vec<Cidr>,
+}
+
+impl LoginFilter {
+    pub(crate) fn from_cfg(cfg: &SensorConf) -> Result<Self, String> {
+        let globs = |key: &str| {
+            cfg.arg_str_array(key)
+                .unwrap_or_default()
+                .iter()
+                .map(|g| Pattern::new(g).map_err(|err| format!("invalid args.{key} pattern '{g}': {err}")))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let nets = |key: &str| cfg.arg_str_array(key).unwrap_or_default().iter().map(|n| n.parse()).collect::<Result<Vec<Cidr>, _>>();
+
+        Ok(Self {
+            users: globs("users")?,
+            exclude_users: globs("exclude-users")?,
+            networks: nets("networks")?,
+            exclude_networks: nets("exclude-networks")?,
+        })
+    }
+
+    pub(crate) fn matches(&self, ev: &LoginEvent) -> bool {
+        if let Some(user) = &ev.user
+            && ((!self.users.is_empty() && !self.users.iter().any(|p| p.matches(user))) || self.exclude_users.iter().any(|p| p.matches(user)))
+        {
+            return false;
+        }
+        if let Some(ip) = ev.address.as_deref().and_then(|a| a.parse::<IpAddr>().ok())
+            && ((!self.networks.is_empty() && !self.networks.iter().any(|n| n.contains(&ip)))
+                || self.exclude_networks.iter().any(|n| n.contains(&ip)))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Counts failed authentications per source and trips once a source reaches
+/// the threshold within the window.
+///
+/// A tripped source starts counting from zero again, so a running attack is
+/// reported once per threshold reached.
+#[derive(Debug)]
+pub(crate) struct BruteForce {
+    failures: usize,
+    window: Duration,
+    seen: HashMap<String, VecDeque<Instant>>,
+}
+
+impl BruteForce {
+    pub(crate) fn new(failures: usize, window: Duration) -> Self {
+        Self { failures: failures.max(1), window, seen: HashMap::new() }
+    }
+
+    pub(crate) fn from_cfg(cfg: &SensorConf) -> Option<Self> {
+        let bf = cfg.args().get("brute-force")?;
+        let failures = bf.get("failures").and_then(|v| v.as_u64())?;
+        let window = bf.get("window").and_then(|v| v.as_str()).and_then(|w| humantime::parse_duration(w).ok()).unwrap_or(DEFAULT_BRUTE_FORCE_WINDOW);
+        Some(Self::new(failures as usize, window))
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records one failure of `source` and returns the number of failures
+    /// within the window if that trips the threshold.
+    pub(crate) fn observe(&mut self, source: &str, now: Instant) -> Option<usize> {
+        let hits = self.seen.entry(source.to_string()).or_default();
+        hits.push_back(now);
+        while hits.front().is_some_and(|t| now.duration_since(*t) > self.window) {
+            hits.pop_front();
+        }
+        if hits.len() < self.failures {
+            return None;
+        }
+        let n = hits.len();
+        self.seen.remove(source);
+        Some(n)
+    }
+
+    /// Forgets sources without a failure within the window
+    pub(crate) fn expire(&mut self, now: Instant) {
+        self.seen.retain(|_, hits| hits.back().is_some_and(|t| now.duration_since(*t) <= self.window));
+    }
+}
+
+/// Splits a syslog line into the program name and the message.
+pub(crate) fn parse_syslog(line: &str) -> Option<(&str, &str)> {
+    let c = SYSLOG.captures(line)?;
+    Some((c.get(1)?.as_str(), c.get(2)?.as_str()))
+}
+
+/// Recognises the interactive access in one message of `program`.
+pub(crate) fn parse_auth(program: &str, msg: &str) -> Option<LoginEvent> {
+    let msg = msg.trim_end();
+    match program {
+        "sshd" | "sshd-session" => {
+            if let Some(c) = SSH_ACCEPTED.captures(msg) {
+                return Some(LoginEvent {
+                    action: "ssh",
+                    method: Some(c[1].to_string()),
+                    user: Some(c[2].to_string()),
+                    address: Some(c[3].to_string()),
+                    port: c[4].parse().ok(),
+                    tty: Some("ssh".to_string()),
+                    ..Default::default()
+                });
+            }
+            let c = SSH_FAILED.captures(msg)?;
+            Some(LoginEvent {
+                action: "failed",
+                method: Some(c[1].to_string()),
+                user: Some(c[3].to_string()),
+                address: Some(c[4].to_string()),
+                port: c[5].parse().ok(),
+                tty: Some("ssh".to_string()),
+                reason: Some(if c.get(2).is_some() { "invalid user" } else { "bad credentials" }.to_string()),
+                ..Default::default()
+            })
+        }
+        "sudo" => {
+            let c = SUDO.captures(msg)?;
+            let problem = c[2].trim().trim_end_matches(';').trim();
+            Some(LoginEvent {
+                action: if problem.is_empty() { "sudo" } else { "failed" },
+                user: Some(c[1].to_string()),
+                tty: Some(c[3].to_string()),
+                target: Some(c[4].to_string()),
+                command: Some(c[5].trim().to_string()),
+                method: Some("sudo".to_string()),
+                reason: (!problem.is_empty()).then(|| problem.to_string()),
+                ..Default::default()
+            })
+        }
+        _ => {
+            // sshd reports its own failures, with the method and port
+            let c = PAM_FAILED.captures(msg).filter(|c| &c[1] != "sshd")?;
+            let fields = c[2].split_whitespace().filter_map(|kv| kv.split_once('=')).filter(|(_, v)| !v.is_empty()).collect::<HashMap<_, _>>();
+            Some(LoginEvent {
+                action: "failed",
+                method: Some(c[1].to_string()),
+                user: fields.get("user").or_else(|| fields.get("ruser")).map(|u| u.to_string()),
+                address: fields.get("rhost").map(|h| h.to_string()),
+                tty: fields.get("tty").map(|t| t.to_string()),
+                reason: Some("authentication failure".to_string()),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Size of one `struct utmp` record on Linux
+pub(crate) const UTMP_SIZE: usize = 384;
+
+const USER_PROCESS: i16 = 7;
+const DEAD_PROCESS: i16 = 8;
+
+/// One login or logout record of Linux wtmp.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Utmp {
+    pub(crate) login: bool,
+    pub(crate) line: String,
+    pub(crate) user: String,
+    pub(crate) host: String,
+    pub(crate) addr: Option<IpAddr>,
+}
+
+/// Decodes a Linux `struct utmp` record. Other record types than user
+/// logins and logouts, e.g. reboots and runlevel changes, are skipped.
+pub(crate) fn parse_utmp(b: &[u8]) -> Option<Utmp> {
+    if b.len() < UTMP_SIZE {
+        return None;
+    }
+    let kind = i16::from_ne_bytes([b[0], b[1]]);
+    if kind != USER_PROCESS && kind != DEAD_PROCESS {
+        return None;
+    }
+    let text = |r: std::ops::Range<usize>| {
+        let f = &b[r];
+        String::from_utf8_lossy(&f[..f.iter().position(|c| *c == 0).unwrap_or(f.len())]).into_owned()
+    };
+
+    let raw = &b[348..364];
+    let addr = if raw.iter().all(|c| *c == 0) {
+        None
+    } else if raw[4..].iter().all(|c| *c == 0) {
+        Some(IpAddr::from([raw[0], raw[1], raw[2], raw[3]]))
+    } else {
+        Some(IpAddr::from(<[u8; 16]>::try_from(raw).ok()?))
+    };
+
+    Some(Utmp { login: kind == USER_PROCESS, line: text(8..40), user: text(44..76), host: text(76..332), addr })
+}
+
+/// Follows wtmp from its end and turns new records into logins and logouts.
+#[derive(Debug)]
+pub(crate) struct Wtmp {
+    path: PathBuf,
+    /// Inode and offset of the next record
+    pos: Option<(u64, u64)>,
+    /// Logged in users by terminal, as logout records carry no user
+    ttys: HashMap<String, String>,
+}
+
+impl Wtmp {
+    pub(crate) fn open(path: &Path) -> Self {
+        let mut wtmp = Self { path: path.to_path_buf(), pos: None, ttys: HashMap::new() };
+        wtmp.pos = wtmp.stat().map(|(ino, len)| (ino, len - len % UTMP_SIZE as u64));
+        wtmp
+    }
+
+    fn stat(&self) -> Option<(u64, u64)> {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(&self.path).ok().map(|m| (m.ino(), m.len()))
+    }
+
+    /// Returns the logins and logouts recorded since the last poll.
+    pub(crate) fn poll(&mut self) -> Vec<LoginEvent> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let Some((ino, len)) = self.stat() else {
+            return vec![];
+        };
+        // Rotated or truncated: the new file is unread
+        let offset = match self.pos {
+            Some((i, o)) if i == ino && o <= len => o,
+            _ => 0,
+        };
+        let whole = (len - offset) - (len - offset) % UTMP_SIZE as u64;
+        let mut buf = vec![];
+        let read = std::fs::File::open(&self.path).and_then(|mut f| {
+            f.seek(SeekFrom::Start(offset))?;
+            f.take(whole).read_to_end(&mut buf)
+        });
+        if let Err(err) = read {
+            log::warn!("Unable to read {}: {err}", self.path.display());
+            return vec![];
+        }
+        self.pos = Some((ino, offset + buf.len() as u64));
+
+        buf.chunks_exact(UTMP_SIZE).filter_map(parse_utmp).map(|u| self.event(u)).collect()
+    }
+
+    fn event(&mut self, u: Utmp) -> LoginEvent {
+        let user = if u.login {
+            self.ttys.insert(u.line.clone(), u.user.clone());
+            Some(u.user)
+        } else {
+            self.ttys.remove(&u.line).or_else(|| (!u.user.is_empty()).then_some(u.user))
+        };
+        let address = u.addr.map(|a| a.to_string()).or_else(|| (!u.host.is_empty()).then_some(u.host));
+        LoginEvent {
+            action: if u.login { "login" } else { "logout" },
+            user,
+            method: Some(if address.is_some() { "remote" } else { "local" }.to_string()),
+            address,
+            tty: (!u.line.is_empty()).then_some(u.line),
+            ..Default::default()
+        }
+    }
+}
+
+/// Emits an event for interactive access: logins and logouts, new SSH
+/// sessions, failed authentications and sudo use, and brute-force attempts.
+#[derive(Debug, Clone)]
+pub struct LoginSensor {
+    sid: String,
+    cfg: SensorConf,
+}
+
+impl LoginSensor {
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Returns `true` if the action is enabled by the sensor options.
+    /// Without options every action is emitted.
+    pub(crate) fn wants(&self, action: &str) -> bool {
+        self.cfg.opts().is_empty() || self.cfg.opts().iter().any(|o| o == action)
+    }
+
+    /// Builds a stable event id for an access of a user or, for brute-force, of a source.
+    pub(crate) fn make_eid(&self, action: &str, subject: &str) -> String {
+        format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, subject, 0)
+    }
+
+    /// Backends from `args.backends`, or the defaults of the system.
+    ///
+    /// By default the auth log is read if it exists and the journal otherwise;
+    /// wtmp is read on Linux.
+    pub(crate) fn backends(&self, authlog: Option<&Path>) -> Result<Vec<String>, String> {
+        let backends = match self.cfg.arg_str_array("backends") {
+            Some(b) => b,
+            None => {
+                let mut b = vec![if authlog.is_some_and(Path::exists) || !cfg!(target_os = "linux") { "authlog" } else { "journal" }.to_string()];
+                if cfg!(target_os = "linux") {
+                    b.push("wtmp".to_string());
+                }
+                b
+            }
+        };
+        if let Some(b) = backends.iter().find(|b| !["authlog", "journal", "wtmp"].contains(&b.as_str())) {
+            return Err(format!("unknown backend '{b}'"));
+        }
+        Ok(backends)
+    }
+
+    /// Packages one access, honouring optional lock-based duplicate suppression.
+    async fn event(&self, ev: &LoginEvent, source: &str) -> Option<SensorEvent> {
+        let eid = self.make_eid(ev.action, ev.user.as_deref().or(ev.address.as_deref()).unwrap_or("unknown"));
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.login", &eid).await {
+            return None;
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.login",
+            "data": {
+                "action": ev.action,
+                "user": ev.user,
+                "address": ev.address,
+                "port": ev.port,
+                "tty": ev.tty,
+                "method": ev.method,
+                "reason": ev.reason,
+                "target": ev.target,
+                "command": ev.command,
+                "source": source,
+            },
+        }))
+    }
+
+    /// Packages a tripped brute-force threshold of one source.
+    async fn brute_force_event(&self, ev: &LoginEvent, source: &str, failures: usize, window: Duration) -> Option<SensorEvent> {
+        let eid = self.make_eid("brute-force", source);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.login", &eid).await {
+            return None;
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.login",
+            "data": {
+                "action": "brute-force",
+                "user": ev.user,
+                "address": ev.address,
+                "failures": failures,
+                "window": window.as_secs(),
+            },
+        }))
+    }
+
+    /// Filters one access, feeds the brute-force counter and emits what is wanted.
+    async fn handle(
+        &self, ev: LoginEvent, source: &str, filter: &LoginFilter, bf: &mut Option<BruteForce>, emit: &(dyn Fn(SensorEvent) + Send + Sync),
+    ) {
+        if !filter.matches(&ev) {
+            return;
+        }
+        if self.wants(ev.action)
+            && let Some(out) = self.event(&ev, source).await
+        {
+            (emit)(out);
+        }
+
+        if ev.action != "failed" || !self.wants("brute-force") {
+            return;
+        }
+        let Some(bf) = bf.as_mut() else {
+            return;
+        };
+        let Some(key) = ev.address.as_deref().or(ev.user.as_deref()) else {
+            return;
+        };
+        if let Some(failures) = bf.observe(key, Instant::now())
+            && let Some(out) = self.brute_force_event(&ev, key, failures, bf.window()).await
+        {
+            (emit)(out);
+        }
+    }
+}
+
+/// Follows the auth and authpriv facilities of the systemd journal.
+fn follow_journal() -> std::io::Result<Lines<BufReader<ChildStdout>>> {
+    let mut child = Command::new("journalctl")
+        .args(["-f", "-n", "0", "-o", "json", "SYSLOG_FACILITY=4", "SYSLOG_FACILITY=10"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("no stdout"))?;
+    // The child is killed once its output is dropped with the sensor
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(BufReader::new(stdout).lines())
+}
+
+/// Program name and message of one journal entry in JSON.
+pub(crate) fn parse_journal(line: &str) -> Option<(String, String)> {
+    let v = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    Some((v.get("SYSLOG_IDENTIFIER")?.as_str()?.to_string(), v.get("MESSAGE")?.as_str()?.to_string()))
+}
+
+#[async_trait]
+impl Sensor for LoginSensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self { sid: id, cfg }
+    }
+
+    fn id() -> String {
+        "sys.login".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let filter = match LoginFilter::from_cfg(&self.cfg) {
+            Ok(filter) => filter,
+            Err(err) => {
+                log::warn!("[{}] '{}' {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let authlog = self.cfg.arg_str("authlog").map(PathBuf::from).or_else(|| AUTH_LOGS.iter().map(PathBuf::from).find(|p| p.exists()));
+        let backends = match self.backends(authlog.as_deref()) {
+            Ok(backends) => backends,
+            Err(err) => {
+                log::warn!("[{}] '{}' {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let mut bf = BruteForce::from_cfg(&self.cfg);
+        let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(1));
+
+        let has = |b: &str| backends.iter().any(|x| x == b);
+        let mut tail = match authlog.as_deref() {
+            Some(path) if has("authlog") => Some(Tail::open(path, None, false)),
+            None if has("authlog") => {
+                log::warn!("[{}] '{}' found no auth log, set args.authlog", Self::id().bright_magenta(), self.sid);
+                None
+            }
+            _ => None,
+        };
+        let mut wtmp = if has("wtmp") {
+            if cfg!(target_os = "linux") {
+                Some(Wtmp::open(Path::new(&self.cfg.arg_str("wtmp").unwrap_or_else(|| WTMP.to_string()))))
+            } else {
+                log::warn!("[{}] '{}' reads wtmp on Linux only", Self::id().bright_magenta(), self.sid);
+                None
+            }
+        } else {
+            None
+        };
+        let mut journal = if has("journal") {
+            follow_journal()
+                .inspect_err(|err| log::warn!("[{}] '{}' unable to follow the journal: {err}", Self::id().bright_magenta(), self.sid))
+                .ok()
+        } else {
+            None
+        };
+        log::info!("[{}] '{}' watching logins via {} with pulse {:?}", Self::id().bright_magenta(), self.sid, backends.join(", "), pulse);
+
+        let mut tick = time::interval(pulse);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    if let Some(tail) = tail.as_mut() {
+                        for (_, line) in tail.poll() {
+                            if let Some(ev) = parse_syslog(&line).and_then(|(program, msg)| parse_auth(program, msg)) {
+                                self.handle(ev, "authlog", &filter, &mut bf, emit).await;
+                            }
+                        }
+                    }
+                    if let Some(wtmp) = wtmp.as_mut() {
+                        for ev in wtmp.poll() {
+                            self.handle(ev, "wtmp", &filter, &mut bf, emit).await;
+                        }
+                    }
+                    if let Some(bf) = bf.as_mut() {
+                        bf.expire(Instant::now());
+                    }
+                }
+                line = async { journal.as_mut().unwrap().next_line().await }, if journal.is_some() => {
+                    match line {
+                        Ok(Some(line)) => {
+                            if let Some(ev) = parse_journal(&line).and_then(|(program, msg)| parse_auth(&program, &msg)) {
+                                self.handle(ev, "journal", &filter, &mut bf, emit).await;
+                            }
+                        }
+                        Ok(None) | Err(_) => {
+                            log::warn!("[{}] '{}' lost the journal", Self::id().bright_magenta(), self.sid);
+                            journal = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
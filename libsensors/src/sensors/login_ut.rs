@@ -0,0 +1,253 @@
+use crate::{
+    sensors::{
+        login::{BruteForce, Cidr, LoginEvent, LoginFilter, LoginSensor, UTMP_SIZE, Wtmp, parse_auth, parse_journal, parse_syslog, parse_utmp},
+        sensor::Sensor,
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use std::{
+    fs,
+    io::Write,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Returns a `sys.login` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, opts: &[&str], args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.login",
+        "tag": tag,
+        "opts": opts,
+        "interval": {"secs": 0, "nanos": 10_000_000},
+        "args": args
+    }))
+    .unwrap()
+}
+
+/// Builds a Linux `struct utmp` record.
+fn utmp(kind: i16, line: &str, user: &str, host: &str, addr: [u8; 4]) -> Vec<u8> {
+    let mut b = vec![0u8; UTMP_SIZE];
+    b[0..2].copy_from_slice(&kind.to_ne_bytes());
+    b[8..8 + line.len()].copy_from_slice(line.as_bytes());
+    b[44..44 + user.len()].copy_from_slice(user.as_bytes());
+    b[76..76 + host.len()].copy_from_slice(host.as_bytes());
+    b[348..352].copy_from_slice(&addr);
+    b
+}
+
+fn from(user: &str, address: Option<&str>) -> LoginEvent {
+    LoginEvent { action: "ssh", user: Some(user.to_string()), address: address.map(str::to_string), ..Default::default() }
+}
+
+#[test]
+fn make_eid_uses_action_and_user() {
+    let s = LoginSensor::new("sid".to_string(), mk_cfg(Some("sec"), &[], json!({})));
+    assert_eq!(s.make_eid("ssh", "alice"), "sid|sys.login@sec|ssh@alice|0");
+}
+
+#[test]
+fn backends_are_checked() {
+    let s = LoginSensor::new("sid".to_string(), mk_cfg(None, &[], json!({"backends": ["authlog", "utmpx"]})));
+    assert_eq!(s.backends(None).unwrap_err(), "unknown backend 'utmpx'");
+
+    let s = LoginSensor::new("sid".to_string(), mk_cfg(None, &[], json!({"backends": ["journal"]})));
+    assert_eq!(s.backends(None).unwrap(), vec!["journal".to_string()]);
+}
+
+#[test]
+fn syslog_lines_are_split() {
+    assert_eq!(
+        parse_syslog("Oct 18 10:00:01 web1 sshd[812]: Accepted publickey for alice from 10.0.0.5 port 52144 ssh2"),
+        Some(("sshd", "Accepted publickey for alice from 10.0.0.5 port 52144 ssh2"))
+    );
+    assert_eq!(
+        parse_syslog("2026-10-18T10:00:01.123456+00:00 web1 sudo:    alice : TTY=pts/0 ; PWD=/ ; USER=root ; COMMAND=/usr/bin/id"),
+        Some(("sudo", "alice : TTY=pts/0 ; PWD=/ ; USER=root ; COMMAND=/usr/bin/id"))
+    );
+    assert_eq!(parse_syslog("garbage"), None);
+}
+
+#[test]
+fn ssh_sessions_and_failures_are_recognised() {
+    let ok = parse_auth("sshd", "Accepted publickey for alice from 10.0.0.5 port 52144 ssh2: ED25519 SHA256:abc").unwrap();
+    assert_eq!((ok.action, ok.user.as_deref(), ok.address.as_deref(), ok.port), ("ssh", Some("alice"), Some("10.0.0.5"), Some(52144)));
+    assert_eq!(ok.method.as_deref(), Some("publickey"));
+
+    let bad = parse_auth("sshd", "Failed password for invalid user admin from 203.0.113.9 port 40022 ssh2").unwrap();
+    assert_eq!((bad.action, bad.user.as_deref(), bad.method.as_deref()), ("failed", Some("admin"), Some("password")));
+    assert_eq!(bad.reason.as_deref(), Some("invalid user"));
+
+    let plain = parse_auth("sshd", "Failed password for root from 203.0.113.9 port 40023 ssh2").unwrap();
+    assert_eq!(plain.reason.as_deref(), Some("bad credentials"));
+
+    assert!(parse_auth("sshd", "Connection closed by 203.0.113.9 port 40022 [preauth]").is_none());
+    // sshd reports its own failures; the PAM duplicate is skipped
+    assert!(parse_auth("sshd", "pam_unix(sshd:auth): authentication failure; logname= uid=0 euid=0 tty=ssh ruser= rhost=203.0.113.9").is_none());
+}
+
+#[test]
+fn hostile_user_names_do_not_forge_the_source() {
+    // A client may log in as "x from 10.0.0.1 port 22" to pose as an allowed network
+    let bad = parse_auth("sshd", "Failed password for invalid user x from 10.0.0.1 port 22 from 203.0.113.9 port 40022 ssh2").unwrap();
+    assert_eq!(bad.user.as_deref(), Some("x from 10.0.0.1 port 22"));
+    assert_eq!((bad.address.as_deref(), bad.port), (Some("203.0.113.9"), Some(40022)));
+    assert_eq!(bad.reason.as_deref(), Some("invalid user"));
+
+    let bad = parse_auth("sshd", "Failed password for invalid user  invalid user root from 203.0.113.9 port 40022 ssh2").unwrap();
+    assert_eq!(bad.user.as_deref(), Some(" invalid user root"));
+
+    let ok = parse_auth("sshd", "Accepted password for a from 10.0.0.1 port 22 from 203.0.113.9 port 40022 ssh2").unwrap();
+    assert_eq!((ok.user.as_deref(), ok.address.as_deref(), ok.port), (Some("a from 10.0.0.1 port 22"), Some("203.0.113.9"), Some(40022)));
+    let ok = parse_auth("sshd", "Accepted publickey for b from 10.0.0.1 port 22: from 203.0.113.9 port 40022 ssh2: ED25519 SHA256:abc").unwrap();
+    assert_eq!((ok.address.as_deref(), ok.port), (Some("203.0.113.9"), Some(40022)));
+}
+
+#[test]
+fn sudo_use_and_pam_failures_are_recognised() {
+    let ok = parse_auth("sudo", "alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/usr/bin/systemctl restart nginx").unwrap();
+    assert_eq!((ok.action, ok.user.as_deref(), ok.tty.as_deref(), ok.target.as_deref()), ("sudo", Some("alice"), Some("pts/0"), Some("root")));
+    assert_eq!(ok.command.as_deref(), Some("/usr/bin/systemctl restart nginx"));
+
+    let bad = parse_auth("sudo", "bob : 3 incorrect password attempts ; TTY=pts/1 ; PWD=/tmp ; USER=root ; COMMAND=/bin/sh").unwrap();
+    assert_eq!((bad.action, bad.reason.as_deref()), ("failed", Some("3 incorrect password attempts")));
+
+    let su = parse_auth("su", "pam_unix(su:auth): authentication failure; logname=bob uid=1001 euid=0 tty=/dev/pts/1 ruser=bob rhost=  user=root")
+        .unwrap();
+    assert_eq!((su.action, su.method.as_deref(), su.user.as_deref(), su.address), ("failed", Some("su"), Some("root"), None));
+}
+
+#[test]
+fn journal_entries_are_read() {
+    let line = r#"{"SYSLOG_IDENTIFIER":"sshd","MESSAGE":"Accepted password for bob from ::1 port 1022 ssh2","_PID":"77"}"#;
+    assert_eq!(parse_journal(line), Some(("sshd".to_string(), "Accepted password for bob from ::1 port 1022 ssh2".to_string())));
+    assert_eq!(parse_journal(r#"{"MESSAGE":[1,2,3]}"#), None);
+}
+
+#[test]
+fn cidr_contains_addresses() {
+    let net = "10.0.0.0/8".parse::<Cidr>().unwrap();
+    assert!(net.contains(&"10.20.30.40".parse::<IpAddr>().unwrap()));
+    assert!(net.contains(&"::ffff:10.1.1.1".parse::<IpAddr>().unwrap()));
+    assert!(!net.contains(&"192.168.1.1".parse::<IpAddr>().unwrap()));
+    assert!("2001:db8::/32".parse::<Cidr>().unwrap().contains(&"2001:db8::1".parse::<IpAddr>().unwrap()));
+    assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(&"1.2.3.4".parse::<IpAddr>().unwrap()));
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn filter_selects_users_and_networks() {
+    let f = LoginFilter::from_cfg(&mk_cfg(None, &[], json!({"exclude-users": ["nagios"], "exclude-networks": ["10.0.0.0/8"]}))).unwrap();
+    assert!(f.matches(&from("alice", Some("203.0.113.9"))));
+    assert!(!f.matches(&from("alice", Some("10.1.2.3"))));
+    assert!(!f.matches(&from("nagios", Some("203.0.113.9"))));
+    assert!(f.matches(&from("alice", None)));
+
+    let f = LoginFilter::from_cfg(&mk_cfg(None, &[], json!({"users": ["adm*"], "networks": ["192.168.0.0/16"]}))).unwrap();
+    assert!(f.matches(&from("admin", Some("192.168.1.10"))));
+    assert!(!f.matches(&from("admin", Some("203.0.113.9"))));
+    assert!(!f.matches(&from("alice", Some("192.168.1.10"))));
+
+    assert!(LoginFilter::from_cfg(&mk_cfg(None, &[], json!({"networks": ["nonsense"]}))).is_err());
+}
+
+#[test]
+fn brute_force_trips_within_the_window() {
+    let mut bf = BruteForce::new(3, Duration::from_secs(60));
+    let t0 = Instant::now();
+
+    assert_eq!(bf.observe("203.0.113.9", t0), None);
+    assert_eq!(bf.observe("203.0.113.9", t0 + Duration::from_secs(10)), None);
+    assert_eq!(bf.observe("198.51.100.1", t0 + Duration::from_secs(10)), None);
+    assert_eq!(bf.observe("203.0.113.9", t0 + Duration::from_secs(20)), Some(3));
+    // Counting starts over after tripping
+    assert_eq!(bf.observe("203.0.113.9", t0 + Duration::from_secs(21)), None);
+
+    // Failures older than the window do not count
+    assert_eq!(bf.observe("198.51.100.1", t0 + Duration::from_secs(100)), None);
+    assert_eq!(bf.observe("198.51.100.1", t0 + Duration::from_secs(101)), None);
+
+    bf.expire(t0 + Duration::from_secs(500));
+    assert_eq!(bf.observe("198.51.100.1", t0 + Duration::from_secs(501)), None);
+}
+
+#[test]
+fn utmp_records_are_decoded() {
+    let u = parse_utmp(&utmp(7, "pts/3", "alice", "10.0.0.5", [10, 0, 0, 5])).unwrap();
+    assert!(u.login);
+    assert_eq!((u.line.as_str(), u.user.as_str(), u.host.as_str()), ("pts/3", "alice", "10.0.0.5"));
+    assert_eq!(u.addr, Some("10.0.0.5".parse().unwrap()));
+
+    // Boot records are not logins
+    assert!(parse_utmp(&utmp(2, "~", "reboot", "6.1.0", [0; 4])).is_none());
+    assert!(parse_utmp(&[0u8; 10]).is_none());
+}
+
+#[test]
+fn wtmp_follows_new_logins_and_logouts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wtmp");
+    fs::write(&path, utmp(7, "pts/0", "old", "", [0; 4])).unwrap();
+
+    let mut w = Wtmp::open(&path);
+    assert!(w.poll().is_empty());
+
+    let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    f.write_all(&utmp(7, "pts/1", "alice", "10.0.0.5", [10, 0, 0, 5])).unwrap();
+    f.write_all(&utmp(8, "pts/1", "", "", [0; 4])).unwrap();
+    f.write_all(&utmp(7, "tty1", "root", "", [0; 4])[..100]).unwrap();
+
+    let evs = w.poll();
+    assert_eq!(evs.len(), 2);
+    assert_eq!((evs[0].action, evs[0].user.as_deref(), evs[0].address.as_deref()), ("login", Some("alice"), Some("10.0.0.5")));
+    assert_eq!(evs[0].method.as_deref(), Some("remote"));
+    assert_eq!((evs[1].action, evs[1].user.as_deref(), evs[1].tty.as_deref()), ("logout", Some("alice"), Some("pts/1")));
+
+    // The rest of a partly written record is picked up on the next poll
+    f.write_all(&utmp(7, "tty1", "root", "", [0; 4])[100..]).unwrap();
+    let evs = w.poll();
+    assert_eq!((evs[0].action, evs[0].user.as_deref(), evs[0].method.as_deref()), ("login", Some("root"), Some("local")));
+}
+
+#[tokio::test]
+async fn run_reports_auth_log_access_and_brute_force() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("auth.log");
+    fs::write(&log, "Oct 18 09:00:00 web1 sshd[1]: Failed password for root from 203.0.113.9 port 1 ssh2\n").unwrap();
+
+    let s = LoginSensor::new(
+        "sid".to_string(),
+        mk_cfg(
+            None,
+            &["ssh", "brute-force"],
+            json!({"backends": ["authlog"], "authlog": log.to_string_lossy(), "brute-force": {"failures": 2, "window": "1m"}}),
+        ),
+    );
+
+    let writer = {
+        let log = log.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut f = fs::OpenOptions::new().append(true).open(&log).unwrap();
+            for port in [2, 3] {
+                writeln!(f, "Oct 18 10:00:0{port} web1 sshd[2]: Failed password for root from 203.0.113.9 port {port} ssh2").unwrap();
+            }
+            writeln!(f, "Oct 18 10:00:05 web1 sshd[3]: Accepted publickey for alice from 10.0.0.5 port 4 ssh2").unwrap();
+        })
+    };
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let _ = tokio::time::timeout(Duration::from_millis(300), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+    writer.await.unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["eid"], "sid|sys.login|brute-force@203.0.113.9|0");
+    assert_eq!(events[0]["data"]["failures"], 2);
+    assert_eq!(events[0]["data"]["user"], "root");
+    assert_eq!(events[1]["eid"], "sid|sys.login|ssh@alice|0");
+    assert_eq!(events[1]["data"]["method"], "publickey");
+    assert_eq!(events[1]["data"]["source"], "authlog");
+}
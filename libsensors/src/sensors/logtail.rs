@@ -0,0 +1,412 @@
+use crate::{
+    argparse::SensorArgs,
+    sensors::{
+        SensorCtx,
+        sensor::{Sensor, SensorEvent},
+        state_name,
+    },
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::time;
+
+/// Most bytes read from one file per poll, so a huge backlog is consumed in steps
+const MAX_READ: u64 = 4 * 1024 * 1024;
+
+/// Longest line kept in memory; longer ones are cut here
+const MAX_LINE: usize = 64 * 1024;
+
+/// Default maximum number of lines in one multiline record
+const DEFAULT_MAX_LINES: usize = 500;
+
+/// Event data keys that captures cannot override
+const RESERVED: &[&str] = &["action", "pattern", "file", "line"];
+
+/// Read position of one followed file, as persisted across restarts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TailOffset {
+    pub(crate) inode: u64,
+    /// Bytes consumed up to the last complete line
+    pub(crate) offset: u64,
+}
+
+/// Follows one file through rotation and truncation.
+///
+/// The open handle stays on the file it was opened on, so after a rename
+/// (logrotate `create`) the rest of the old file is read before the new one.
+/// A file that shrinks under the handle (logrotate `copytruncate`) is read
+/// again from the start.
+#[derive(Debug)]
+pub(crate) struct Tail {
+    path: PathBuf,
+    file: Option<File>,
+    pos: TailOffset,
+    /// Bytes after the last complete line
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    /// Opens `path` at the saved position if it still refers to the same file,
+    /// otherwise at its start or end.
+    pub(crate) fn open(path: &Path, saved: Option<TailOffset>, from_start: bool) -> Self {
+        let mut tail = Self { path: path.to_path_buf(), file: None, pos: TailOffset::default(), partial: vec![] };
+        let Ok((file, meta)) = File::open(path).and_then(|f| f.metadata().map(|m| (f, m))) else {
+            return tail;
+        };
+
+        let offset = match saved {
+            Some(saved) if saved.inode == meta.ino() && saved.offset <= meta.len() => saved.offset,
+            // Rotated while we were away: the whole new file is unread
+            Some(_) => 0,
+            None if from_start => 0,
+            None => meta.len(),
+        };
+        tail.file = Some(file);
+        tail.pos = TailOffset { inode: meta.ino(), offset };
+        tail
+    }
+
+    pub(crate) fn position(&self) -> TailOffset {
+        self.pos
+    }
+
+    /// Returns the complete lines written since the last poll, each with the position it starts at.
+    pub(crate) fn poll(&mut self) -> Vec<(TailOffset, String)> {
+        let mut lines = vec![];
+        if self.file.is_none() {
+            // The file did not exist so far: everything in it is new
+            let Ok(file) = File::open(&self.path) else {
+                return lines;
+            };
+            self.pos = TailOffset { inode: file.metadata().map(|m| m.ino()).unwrap_or_default(), offset: 0 };
+            self.file = Some(file);
+        }
+
+        if let Err(err) = self.read(&mut lines) {
+            log::warn!("Unable to read {}: {err}", self.path.display());
+        }
+
+        // Rotated: the old file is drained, continue with the new one from its start
+        if let Ok(meta) = fs::metadata(&self.path)
+            && meta.ino() != self.pos.inode
+        {
+            self.flush_partial(&mut lines);
+            match File::open(&self.path) {
+                Ok(file) => {
+                    log::debug!("{} was rotated, following the new file", self.path.display());
+                    self.file = Some(file);
+                    self.pos = TailOffset { inode: meta.ino(), offset: 0 };
+                    if let Err(err) = self.read(&mut lines) {
+                        log::warn!("Unable to read {}: {err}", self.path.display());
+                    }
+                }
+                Err(err) => log::warn!("Unable to open rotated {}: {err}", self.path.display()),
+            }
+        }
+
+        lines
+    }
+
+    fn read(&mut self, lines: &mut Vec<(TailOffset, String)>) -> io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.pos.offset + self.partial.len() as u64 {
+            log::debug!("{} was truncated, reading from the start", self.path.display());
+            self.pos.offset = 0;
+            self.partial.clear();
+        }
+
+        file.seek(SeekFrom::Start(self.pos.offset + self.partial.len() as u64))?;
+        let mut buf = vec![];
+        file.take(MAX_READ).read_to_end(&mut buf)?;
+        self.partial.extend_from_slice(&buf);
+
+        let mut start = 0;
+        while let Some(nl) = self.partial[start..].iter().position(|b| *b == b'\n') {
+            lines.push((TailOffset { inode: self.pos.inode, offset: self.pos.offset + start as u64 }, decode_line(&self.partial[start..start + nl])));
+            start += nl + 1;
+        }
+        self.partial.drain(..start);
+        self.pos.offset += start as u64;
+
+        if self.partial.len() > MAX_LINE {
+            self.flush_partial(lines);
+        }
+        Ok(())
+    }
+
+    /// Takes an unterminated line as complete, e.g. the last line of a rotated file
+    fn flush_partial(&mut self, lines: &mut Vec<(TailOffset, String)>) {
+        if !self.partial.is_empty() {
+            lines.push((self.pos, decode_line(&self.partial)));
+            self.pos.offset += self.partial.len() as u64;
+            self.partial.clear();
+        }
+    }
+}
+
+fn decode_line(b: &[u8]) -> String {
+    let b = &b[..b.len().min(MAX_LINE)];
+    String::from_utf8_lossy(b.strip_suffix(b"\r").unwrap_or(b)).into_owned()
+}
+
+/// Joins lines into records.
+///
+/// Without a start pattern every line is a record. With one, a line matching
+/// it starts a new record and the other lines, e.g. a stack trace, belong to
+/// the record before them.
+#[derive(Debug)]
+pub(crate) struct Records {
+    start: Option<Regex>,
+    max_lines: usize,
+    pending: Vec<String>,
+    /// Where the first pending line starts
+    pending_at: Option<TailOffset>,
+}
+
+impl Records {
+    pub(crate) fn new(start: Option<Regex>, max_lines: usize) -> Self {
+        Self { start, max_lines: max_lines.max(1), pending: vec![], pending_at: None }
+    }
+
+    /// Position of the record still open. It is what is persisted, so that
+    /// after a restart the record is read again whole instead of losing its head.
+    pub(crate) fn pending_at(&self) -> Option<TailOffset> {
+        self.pending_at
+    }
+
+    /// Adds one line starting at `at` and returns the records it completes.
+    pub(crate) fn push(&mut self, at: TailOffset, line: String) -> Vec<String> {
+        let Some(start) = &self.start else {
+            return vec![line];
+        };
+
+        let mut out = vec![];
+        if start.is_match(&line) {
+            out.extend(self.flush());
+        }
+        if self.pending.is_empty() {
+            self.pending_at = Some(at);
+        }
+        self.pending.push(line);
+        if self.pending.len() >= self.max_lines {
+            out.extend(self.flush());
+        }
+        out
+    }
+
+    /// Completes the pending record, e.g. when no line came for a while.
+    pub(crate) fn flush(&mut self) -> Option<String> {
+        self.pending_at = None;
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending).join("\n"))
+    }
+}
+
+/// Named patterns, with the named captures of a match.
+#[derive(Debug, Clone)]
+pub(crate) struct Patterns(Vec<(String, Regex)>);
+
+impl Patterns {
+    /// Reads `args.patterns`, a mapping of a pattern name to a regular expression.
+    pub(crate) fn from_cfg(cfg: &SensorConf) -> Result<Self, String> {
+        let Some(map) = cfg.args().get("patterns").and_then(|v| v.as_mapping()) else {
+            return Err("missing/invalid args.patterns (expected mapping of name to regex)".to_string());
+        };
+
+        let mut out = vec![];
+        for (name, re) in map {
+            let (Some(name), Some(re)) = (name.as_str(), re.as_str()) else {
+                return Err(format!("invalid pattern {name:?}"));
+            };
+            out.push((name.to_string(), Regex::new(re).map_err(|err| format!("invalid pattern '{name}': {err}"))?));
+        }
+        if out.is_empty() {
+            return Err("args.patterns is empty".to_string());
+        }
+        Ok(Self(out))
+    }
+
+    /// Returns the name and the named captures of every pattern matching the record.
+    pub(crate) fn matches(&self, record: &str) -> Vec<(String, serde_json::Map<String, serde_json::Value>)> {
+        let mut out = vec![];
+        for (name, re) in &self.0 {
+            let Some(caps) = re.captures(record) else {
+                continue;
+            };
+            let fields = re
+                .capture_names()
+                .flatten()
+                .filter_map(|n| caps.name(n).map(|m| (n.to_string(), json!(m.as_str()))))
+                .collect::<serde_json::Map<_, _>>();
+            out.push((name.clone(), fields));
+        }
+        out
+    }
+}
+
+/// Emits an event for every log record matching one of the configured patterns.
+pub struct LogTailSensor {
+    sid: String,
+    cfg: SensorConf,
+    state: Option<PathBuf>,
+}
+
+impl fmt::Debug for LogTailSensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogTailSensor").field("sid", &self.sid).field("listener", &self.cfg.listener()).finish()
+    }
+}
+
+impl LogTailSensor {
+    /// Creates a log tail sensor that keeps its offsets under the context state root.
+    pub fn with_ctx(id: String, cfg: SensorConf, ctx: SensorCtx) -> Self {
+        let state = ctx.state_root().map(|root| root.join("logtail").join(format!("{}.json", state_name(&id))));
+        Self { sid: id, cfg, state }
+    }
+
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Builds a stable event id for a pattern match.
+    pub(crate) fn make_eid(&self, pattern: &str) -> String {
+        format!("{}|{}|matched@{}|{}", self.sid, self.listener_id_with_tag(), pattern, 0)
+    }
+
+    /// Saved offsets by file path
+    pub(crate) fn load_offsets(&self) -> HashMap<String, TailOffset> {
+        self.state.as_ref().and_then(|p| fs::read(p).ok()).and_then(|b| serde_json::from_slice(&b).ok()).unwrap_or_default()
+    }
+
+    fn save_offsets(&self, offsets: &HashMap<String, TailOffset>) {
+        let Some(path) = &self.state else {
+            return;
+        };
+        let tmp = path.with_extension("json.tmp");
+        let r = path
+            .parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| fs::write(&tmp, serde_json::to_vec(offsets).unwrap_or_default()))
+            .and_then(|_| fs::rename(&tmp, path));
+        if let Err(err) = r {
+            log::warn!("[{}] '{}' unable to save offsets to {}: {err}", Self::id().bright_magenta(), self.sid, path.display());
+        }
+    }
+
+    /// Packages one match, honouring optional lock-based duplicate suppression.
+    async fn event(&self, file: &str, record: &str, pattern: &str, captures: serde_json::Map<String, serde_json::Value>) -> Option<SensorEvent> {
+        let eid = self.make_eid(pattern);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.logtail", &eid).await {
+            return None;
+        }
+
+        let mut data = serde_json::Map::new();
+        data.insert("action".to_string(), json!("matched"));
+        data.insert("pattern".to_string(), json!(pattern));
+        data.insert("file".to_string(), json!(file));
+        data.insert("line".to_string(), json!(record));
+        for (k, v) in captures {
+            if RESERVED.contains(&k.as_str()) {
+                log::debug!("[{}] '{}' capture '{k}' clashes with an event field, dropped", Self::id().bright_magenta(), self.sid);
+                continue;
+            }
+            data.insert(k, v);
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.logtail",
+            "data": data,
+        }))
+    }
+}
+
+#[async_trait]
+impl Sensor for LogTailSensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self::with_ctx(id, cfg, SensorCtx::default())
+    }
+
+    fn id() -> String {
+        "sys.logtail".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let Some(files) = self.cfg.arg_str_array("files") else {
+            log::warn!("[{}] '{}' missing/invalid args.files (expected array of strings); not starting", Self::id().bright_magenta(), self.sid);
+            return;
+        };
+        let patterns = match Patterns::from_cfg(&self.cfg) {
+            Ok(patterns) => patterns,
+            Err(err) => {
+                log::warn!("[{}] '{}' {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let multiline = self.cfg.args().get("multiline");
+        let start = match multiline.and_then(|m| m.get("start")).and_then(|v| v.as_str()).map(Regex::new).transpose() {
+            Ok(start) => start,
+            Err(err) => {
+                log::warn!("[{}] '{}' invalid args.multiline.start: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                return;
+            }
+        };
+        let max_lines = multiline.and_then(|m| m.get("max-lines")).and_then(|v| v.as_u64()).map(|v| v as usize).unwrap_or(DEFAULT_MAX_LINES);
+        let from_start = self.cfg.arg_str("from").is_some_and(|v| v == "start");
+        let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(1));
+
+        let mut offsets = self.load_offsets();
+        let mut tails = files
+            .iter()
+            .map(|f| {
+                log::info!("[{}] '{}' following '{}' with pulse {:?}", Self::id().bright_magenta(), self.sid, f, pulse);
+                (f.clone(), Tail::open(Path::new(f), offsets.get(f).copied(), from_start), Records::new(start.clone(), max_lines))
+            })
+            .collect::<Vec<_>>();
+
+        let mut tick = time::interval(pulse);
+        loop {
+            tick.tick().await;
+            for (file, tail, records) in tails.iter_mut() {
+                let lines = tail.poll();
+                // A record is complete once no line came for a whole interval
+                let mut done = if lines.is_empty() { records.flush().into_iter().collect() } else { vec![] };
+                for (at, line) in lines {
+                    done.extend(records.push(at, line));
+                }
+
+                for record in done {
+                    for (pattern, captures) in patterns.matches(&record) {
+                        if let Some(ev) = self.event(file, &record, &pattern, captures).await {
+                            (emit)(ev);
+                        }
+                    }
+                }
+            }
+
+            let current = tails.iter().map(|(f, t, r)| (f.clone(), r.pending_at().unwrap_or_else(|| t.position()))).collect::<HashMap<_, _>>();
+            if current != offsets {
+                self.save_offsets(&current);
+                offsets = current;
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{
+    sensors::{
+        SensorCtx,
+        logtail::{LogTailSensor, Patterns, Records, Tail, TailOffset},
+        sensor::Sensor,
+    },
+    sspec::SensorConf,
+};
+use regex::Regex;
+use serde_json::{from_value, json};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a `sys.logtail` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.logtail",
+        "tag": tag,
+        "interval": {"secs": 0, "nanos": 10_000_000},
+        "args": args
+    }))
+    .unwrap()
+}
+
+fn append(path: &Path, data: &str) {
+    OpenOptions::new().create(true).append(true).open(path).unwrap().write_all(data.as_bytes()).unwrap();
+}
+
+/// Polled lines without their positions
+fn text(lines: Vec<(TailOffset, String)>) -> Vec<String> {
+    lines.into_iter().map(|(_, line)| line).collect()
+}
+
+#[test]
+fn make_eid_uses_pattern_name() {
+    let s = LogTailSensor::new("sid".to_string(), mk_cfg(Some("app"), json!({})));
+    assert_eq!(s.make_eid("oom"), "sid|sys.logtail@app|matched@oom|0");
+}
+
+#[test]
+fn tail_starts_at_end_and_keeps_partial_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "old line\n");
+
+    let mut t = Tail::open(&log, None, false);
+    assert!(t.poll().is_empty());
+
+    append(&log, "one\ntw");
+    assert_eq!(text(t.poll()), vec!["one"]);
+    append(&log, "o\r\n");
+    assert_eq!(text(t.poll()), vec!["two"]);
+    assert_eq!(t.position().offset, fs::metadata(&log).unwrap().len());
+}
+
+#[test]
+fn tail_resumes_from_saved_offset_of_same_file_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "one\n");
+
+    let mut t = Tail::open(&log, None, true);
+    assert_eq!(text(t.poll()), vec!["one"]);
+    let saved = t.position();
+
+    append(&log, "two\n");
+    assert_eq!(text(Tail::open(&log, Some(saved), false).poll()), vec!["two"]);
+
+    // Replaced while not watching: the new file is read from its start
+    fs::rename(&log, dir.path().join("app.log.1")).unwrap();
+    append(&log, "three\n");
+    assert_eq!(text(Tail::open(&log, Some(saved), false).poll()), vec!["three"]);
+}
+
+#[test]
+fn tail_drains_rotated_file_then_follows_new_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "");
+    let mut t = Tail::open(&log, None, false);
+
+    append(&log, "before\nlast");
+    fs::rename(&log, dir.path().join("app.log.1")).unwrap();
+    assert_eq!(text(t.poll()), vec!["before"]);
+
+    append(&log, "after\n");
+    assert_eq!(text(t.poll()), vec!["last", "after"]);
+}
+
+#[test]
+fn tail_rereads_truncated_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "");
+    let mut t = Tail::open(&log, None, false);
+
+    append(&log, "a long line before copytruncate\n");
+    assert_eq!(t.poll().len(), 1);
+
+    fs::write(&log, "new\n").unwrap();
+    assert_eq!(text(t.poll()), vec!["new"]);
+}
+
+#[test]
+fn records_join_continuation_lines() {
+    let mut r = Records::new(Some(Regex::new(r"^\d{4}-").unwrap()), 10);
+
+    assert!(r.push(TailOffset::default(), "2026-01-01 ERROR boom".to_string()).is_empty());
+    assert!(r.push(TailOffset::default(), "  at foo()".to_string()).is_empty());
+    assert!(r.push(TailOffset::default(), "  at bar()".to_string()).is_empty());
+    assert_eq!(r.push(TailOffset::default(), "2026-01-01 INFO ok".to_string()), vec!["2026-01-01 ERROR boom\n  at foo()\n  at bar()"]);
+    assert_eq!(r.flush().as_deref(), Some("2026-01-01 INFO ok"));
+    assert!(r.flush().is_none());
+
+    let mut r = Records::new(Some(Regex::new(r"^\d{4}-").unwrap()), 2);
+    assert!(r.push(TailOffset::default(), "2026-01-01 x".to_string()).is_empty());
+    assert_eq!(r.push(TailOffset::default(), " y".to_string()), vec!["2026-01-01 x\n y"]);
+}
+
+#[test]
+fn open_record_is_read_again_from_its_first_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "");
+    let mut t = Tail::open(&log, None, false);
+    let mut r = Records::new(Some(Regex::new(r"^\d{4}-").unwrap()), 10);
+
+    append(&log, "2026-01-01 INFO ok\n2026-01-01 ERROR boom\n  at foo()\n");
+    let mut done = vec![];
+    for (at, line) in t.poll() {
+        done.extend(r.push(at, line));
+    }
+    assert_eq!(done, vec!["2026-01-01 INFO ok"]);
+
+    // Stopped with the error record still open: the saved position is its head, not the end of the file
+    let saved = r.pending_at().unwrap();
+    assert_eq!(saved.offset, "2026-01-01 INFO ok\n".len() as u64);
+    assert!(saved.offset < t.position().offset);
+
+    append(&log, "  at bar()\n");
+    assert_eq!(text(Tail::open(&log, Some(saved), false).poll()), vec!["2026-01-01 ERROR boom", "  at foo()", "  at bar()"]);
+
+    assert_eq!(r.flush().as_deref(), Some("2026-01-01 ERROR boom\n  at foo()"));
+    assert!(r.pending_at().is_none());
+}
+
+#[test]
+fn patterns_report_named_captures() {
+    let p =
+        Patterns::from_cfg(&mk_cfg(None, json!({"patterns": {"oom": r"Out of memory: Killed process (?P<pid>\d+) \((?P<comm>[^)]+)\)"}}))).unwrap();
+    let m = p.matches("kernel: Out of memory: Killed process 4242 (java) total-vm:1kB");
+    assert_eq!(m.len(), 1);
+    assert_eq!(m[0].0, "oom");
+    assert_eq!(m[0].1["pid"], "4242");
+    assert_eq!(m[0].1["comm"], "java");
+
+    assert!(Patterns::from_cfg(&mk_cfg(None, json!({"patterns": {"bad": "("}}))).is_err());
+    assert!(Patterns::from_cfg(&mk_cfg(None, json!({}))).is_err());
+}
+
+#[tokio::test]
+async fn run_emits_captures_as_fields_and_persists_offsets() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    append(&log, "2026-01-01 INFO started\n");
+
+    let cfg = mk_cfg(
+        Some("app"),
+        json!({
+            "files": [log.to_str().unwrap()],
+            "patterns": {"error": r"ERROR (?P<code>E\d+)"},
+            "multiline": {"start": r"^\d{4}-"},
+        }),
+    );
+    let ctx = SensorCtx::default().with_state_root(dir.path().join("state"));
+    let s = LogTailSensor::with_ctx("sid".to_string(), cfg.clone(), ctx.clone());
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let writer = {
+        let log = log.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            append(&log, "2026-01-01 ERROR E42 failed\n  at main()\n");
+        })
+    };
+    let _ = tokio::time::timeout(Duration::from_millis(300), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+    writer.await.unwrap();
+
+    {
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["eid"], "sid|sys.logtail@app|matched@error|0");
+        assert_eq!(events[0]["data"]["action"], "matched");
+        assert_eq!(events[0]["data"]["code"], "E42");
+        assert_eq!(events[0]["data"]["file"], log.to_str().unwrap());
+        assert_eq!(events[0]["data"]["line"], "2026-01-01 ERROR E42 failed\n  at main()");
+    }
+
+    let saved = s.load_offsets()[log.to_str().unwrap()];
+    assert_eq!(saved.offset, fs::metadata(&log).unwrap().len());
+
+    // A restarted sensor continues where the previous one stopped
+    append(&log, "2026-01-01 ERROR E7 again\n");
+    let s = LogTailSensor::with_ctx("sid".to_string(), cfg, ctx);
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let _ = tokio::time::timeout(Duration::from_millis(100), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["data"]["code"], "E7");
+}
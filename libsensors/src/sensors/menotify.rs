@@ -86,6 +86,13 @@ impl MeNotifySensor {
         }
     }
 
+    /// Stops the running instance of one sensor, leaving the others alone.
+    pub fn invalidate(sid: &str) {
+        if let Some(generation) = GENERATIONS.lock().unwrap_or_else(|e| e.into_inner()).get_mut(sid) {
+            *generation += 1;
+        }
+    }
+
     /// Returns the polling interval used for `tick(ctx)` execution.
     ///
     /// # Returns
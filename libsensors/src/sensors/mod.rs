@@ -1,5 +1,11 @@
 pub mod fsnotify;
 pub mod ifacenotify;
+#[cfg(target_os = "linux")]
+pub(crate) mod inotify;
+pub mod integrity;
+pub mod kernel;
+pub mod login;
+pub mod logtail;
 pub mod menotify;
 pub mod mountnotify;
 pub mod net_health;
@@ -9,12 +15,24 @@ pub mod net_throughput;
 pub mod net_wifi;
 pub mod netnotify;
 pub mod procnotify;
+pub mod resource;
 pub mod sensor;
+pub mod servicenotify;
 pub mod socknotify;
 
+#[cfg(all(test, target_os = "linux"))]
+mod fsnotify_ut;
 #[cfg(test)]
 mod ifacenotify_ut;
 #[cfg(test)]
+mod integrity_ut;
+#[cfg(test)]
+mod kernel_ut;
+#[cfg(test)]
+mod login_ut;
+#[cfg(test)]
+mod logtail_ut;
+#[cfg(test)]
 mod net_health_ut;
 #[cfg(test)]
 mod net_hostname_ut;
@@ -28,17 +46,25 @@ mod net_wifi_ut;
 #[cfg(test)]
 mod proc_ut;
 #[cfg(test)]
+mod resource_ut;
+#[cfg(test)]
+mod servicenotify_ut;
+#[cfg(test)]
 mod socknotify_ut;
 
 use crate::{sensors::sensor::Sensor, sspec::SensorConf};
 use dashmap::DashMap;
 use lazy_static::lazy_static;
+use rsa::RsaPrivateKey;
+use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 
 /// Runtime context passed to sensor constructors.
 #[derive(Debug, Clone, Default)]
 pub struct SensorCtx {
     sharelib_root: Option<PathBuf>,
+    state_root: Option<PathBuf>,
+    signing_key: Option<RsaPrivateKey>,
 }
 
 impl SensorCtx {
@@ -52,6 +78,40 @@ impl SensorCtx {
     pub fn sharelib_root(&self) -> Option<&std::path::Path> {
         self.sharelib_root.as_deref()
     }
+
+    /// Returns a context with a directory where sensors keep state across restarts.
+    pub fn with_state_root(mut self, root: PathBuf) -> Self {
+        self.state_root = Some(root);
+        self
+    }
+
+    /// Returns the configured sensor state directory, if any.
+    pub fn state_root(&self) -> Option<&std::path::Path> {
+        self.state_root.as_deref()
+    }
+
+    /// Returns a context with the key sensors sign their persisted state with, usually the minion key.
+    pub fn with_signing_key(mut self, key: RsaPrivateKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    /// Returns the configured signing key, if any.
+    pub fn signing_key(&self) -> Option<&RsaPrivateKey> {
+        self.signing_key.as_ref()
+    }
+}
+
+/// File name of the state of a sensor under the state root.
+///
+/// The sensor id comes from the configuration and may contain path separators or be `..`.
+/// Such an id is made safe and suffixed with its hash, so that different ids stay apart.
+pub(crate) fn state_name(sid: &str) -> String {
+    let safe = sid.chars().map(|c| if c.is_ascii_alphanumeric() || "-_.@".contains(c) { c } else { '_' }).collect::<String>();
+    if safe == sid && !sid.starts_with('.') && !sid.is_empty() {
+        return safe;
+    }
+    format!("{}-{}", safe.trim_start_matches('.'), &format!("{:x}", Sha256::digest(sid.as_bytes()))[..16])
 }
 
 pub type SensorFactory = fn(String, SensorConf, SensorCtx) -> Box<dyn Sensor>;
@@ -95,9 +155,22 @@ pub fn init_registry() {
     });
     REGISTRY
         .insert(ifacenotify::IfaceSensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| Box::new(ifacenotify::IfaceSensor::new(sid, cfg)));
+    REGISTRY.insert(integrity::IntegritySensor::id(), |sid: String, cfg: SensorConf, ctx: SensorCtx| {
+        Box::new(integrity::IntegritySensor::with_ctx(sid, cfg, ctx))
+    });
+    REGISTRY.insert(kernel::KernelSensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| Box::new(kernel::KernelSensor::new(sid, cfg)));
+    REGISTRY.insert(login::LoginSensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| Box::new(login::LoginSensor::new(sid, cfg)));
+    REGISTRY.insert(logtail::LogTailSensor::id(), |sid: String, cfg: SensorConf, ctx: SensorCtx| {
+        Box::new(logtail::LogTailSensor::with_ctx(sid, cfg, ctx))
+    });
     REGISTRY.insert(menotify::MeNotifySensor::id(), |sid: String, cfg: SensorConf, ctx: SensorCtx| {
         Box::new(menotify::MeNotifySensor::with_ctx(sid, cfg, ctx))
     });
+    REGISTRY
+        .insert(resource::ResourceSensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| Box::new(resource::ResourceSensor::new(sid, cfg)));
+    REGISTRY.insert(servicenotify::ServiceSensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| {
+        Box::new(servicenotify::ServiceSensor::new(sid, cfg))
+    });
     REGISTRY.insert(socknotify::SockTraySensor::id(), |sid: String, cfg: SensorConf, _ctx: SensorCtx| {
         Box::new(socknotify::SockTraySensor::new(sid, cfg))
     });
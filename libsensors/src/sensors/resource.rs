@@ -0,0 +1,425 @@
+use crate::{
+    argparse::SensorArgs,
+    sensors::sensor::{Sensor, SensorEvent},
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    fmt, fs,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time;
+
+/// Metrics with a configurable threshold. The `pressure.*` thresholds are set
+/// under the nested `pressure` argument.
+pub(crate) const METRICS: &[&str] = &["disk", "inodes", "memory", "swap", "load", "pressure.cpu", "pressure.memory", "pressure.io"];
+
+/// Default hysteresis, in percent of the threshold
+const DEFAULT_HYSTERESIS: f64 = 10.0;
+
+/// Changes smaller than this are reported as a steady trend
+const TREND_EPSILON: f64 = 0.01;
+
+/// Measures the requested metrics, limited to the given mountpoints if any.
+///
+/// The probe stays injectable so tests can feed deterministic samples.
+type ResourceProbe = Arc<dyn Fn(&[String], &[String]) -> Vec<ResourceSample> + Send + Sync>;
+
+/// One measured value, e.g. the disk usage of `/var` in percent.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResourceSample {
+    pub(crate) metric: String,
+    /// Mountpoint for `disk` and `inodes`, none otherwise
+    pub(crate) target: Option<String>,
+    pub(crate) value: f64,
+}
+
+impl ResourceSample {
+    pub(crate) fn new(metric: &str, target: Option<&str>, value: f64) -> Self {
+        Self { metric: metric.to_string(), target: target.map(str::to_string), value }
+    }
+
+    /// Event target, e.g. `disk:/var` or `memory`
+    pub(crate) fn key(&self) -> String {
+        match &self.target {
+            Some(target) => format!("{}:{}", self.metric, target),
+            None => self.metric.clone(),
+        }
+    }
+}
+
+/// A metric crossing its threshold, in either direction.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResourceCrossing {
+    /// `exceeded` or `recovered`
+    pub(crate) action: &'static str,
+    pub(crate) sample: ResourceSample,
+    pub(crate) threshold: f64,
+    /// Level the value must drop below to recover
+    pub(crate) recover_below: f64,
+    /// `rising`, `falling` or `steady`, compared to the previous sample
+    pub(crate) trend: &'static str,
+    pub(crate) delta: f64,
+}
+
+/// Last known state of one metric target
+#[derive(Debug, Clone, Copy)]
+struct Track {
+    exceeded: bool,
+    last: f64,
+}
+
+/// Thresholds and the state of every watched metric target.
+///
+/// A metric is `exceeded` once it reaches its threshold and `recovered` only
+/// after it drops below the threshold minus the hysteresis, so a value
+/// wobbling around the threshold does not fire on every sample.
+#[derive(Debug, Default)]
+pub(crate) struct ResourceWatch {
+    thresholds: HashMap<String, f64>,
+    /// Hysteresis in percent of the threshold
+    hysteresis: f64,
+    tracks: HashMap<String, Track>,
+}
+
+impl ResourceWatch {
+    pub(crate) fn from_cfg(cfg: &SensorConf) -> Self {
+        let mut thresholds = HashMap::new();
+        for metric in METRICS {
+            let value = match metric.split_once('.') {
+                Some((group, kind)) => cfg.args().get(group).and_then(|g| g.get(kind)).and_then(|v| v.as_f64()),
+                None => cfg.arg_f64(metric),
+            };
+            if let Some(value) = value.filter(|v| *v > 0.0) {
+                thresholds.insert(metric.to_string(), value);
+            }
+        }
+
+        Self { thresholds, hysteresis: cfg.arg_f64("hysteresis").unwrap_or(DEFAULT_HYSTERESIS).clamp(0.0, 100.0), tracks: HashMap::new() }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.thresholds.is_empty()
+    }
+
+    /// Metrics with a threshold, in a stable order
+    pub(crate) fn metrics(&self) -> Vec<String> {
+        METRICS.iter().filter(|m| self.thresholds.contains_key(**m)).map(|m| m.to_string()).collect()
+    }
+
+    /// Feed one round of samples and return the threshold crossings.
+    ///
+    /// Targets missing from the round, e.g. unmounted filesystems, are forgotten.
+    pub(crate) fn update(&mut self, samples: &[ResourceSample]) -> Vec<ResourceCrossing> {
+        let mut crossings = vec![];
+        let mut seen = HashSet::new();
+        for sample in samples {
+            let Some(threshold) = self.thresholds.get(&sample.metric).copied() else {
+                continue;
+            };
+            let key = sample.key();
+            let recover_below = threshold * (1.0 - self.hysteresis / 100.0);
+            let prev = self.tracks.get(&key).copied();
+            let delta = prev.map(|t| sample.value - t.last).unwrap_or_default();
+            let trend = if delta >= TREND_EPSILON {
+                "rising"
+            } else if delta <= -TREND_EPSILON {
+                "falling"
+            } else {
+                "steady"
+            };
+
+            let was_exceeded = prev.is_some_and(|t| t.exceeded);
+            let exceeded = if was_exceeded { sample.value >= recover_below } else { sample.value >= threshold };
+            if exceeded != was_exceeded {
+                crossings.push(ResourceCrossing {
+                    action: if exceeded { "exceeded" } else { "recovered" },
+                    sample: sample.clone(),
+                    threshold,
+                    recover_below,
+                    trend,
+                    delta,
+                });
+            }
+
+            self.tracks.insert(key.clone(), Track { exceeded, last: sample.value });
+            seen.insert(key);
+        }
+        self.tracks.retain(|key, _| seen.contains(key));
+        crossings
+    }
+}
+
+/// Emits an event when disk, inode, memory, swap, load or pressure stall
+/// usage crosses its configured threshold.
+#[derive(Clone)]
+pub struct ResourceSensor {
+    sid: String,
+    cfg: SensorConf,
+    probe: ResourceProbe,
+}
+
+impl fmt::Debug for ResourceSensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceSensor").field("sid", &self.sid).field("listener", &self.cfg.listener()).finish()
+    }
+}
+
+impl ResourceSensor {
+    /// Builds a sensor instance with a custom probe for tests.
+    #[cfg(test)]
+    pub(crate) fn with_probe(id: String, cfg: SensorConf, probe: ResourceProbe) -> Self {
+        Self { sid: id, cfg, probe }
+    }
+
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Returns `true` if the action is enabled by the sensor options.
+    /// Without options both `exceeded` and `recovered` are emitted.
+    pub(crate) fn wants(&self, action: &str) -> bool {
+        self.cfg.opts().is_empty() || self.cfg.opts().iter().any(|o| o == action)
+    }
+
+    /// Builds a stable event id for a threshold crossing.
+    pub(crate) fn make_eid(&self, action: &str, target: &str) -> String {
+        format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, target, 0)
+    }
+
+    /// Packages one crossing, honouring optional lock-based duplicate suppression.
+    async fn event(&self, c: &ResourceCrossing) -> Option<SensorEvent> {
+        let eid = self.make_eid(c.action, &c.sample.key());
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.resource", &eid).await {
+            return None;
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.resource",
+            "data": {
+                "action": c.action,
+                "metric": c.sample.metric,
+                "target": c.sample.target,
+                "value": round2(c.sample.value),
+                "threshold": c.threshold,
+                "recover-below": round2(c.recover_below),
+                "trend": c.trend,
+                "delta": round2(c.delta),
+            },
+        }))
+    }
+}
+
+#[async_trait]
+impl Sensor for ResourceSensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self { sid: id, cfg, probe: Arc::new(probe_live) }
+    }
+
+    fn id() -> String {
+        "sys.resource".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let mut watch = ResourceWatch::from_cfg(&self.cfg);
+        if watch.is_empty() {
+            log::warn!("[{}] '{}' has no thresholds in args; not starting", Self::id().bright_magenta(), self.sid);
+            return;
+        }
+
+        let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(10));
+        let metrics = Arc::new(watch.metrics());
+        let mountpoints = Arc::new(self.cfg.arg_str_array("mountpoints").unwrap_or_default());
+        log::info!("[{}] '{}' watching {} with pulse {:?}", Self::id().bright_magenta(), self.sid, metrics.join(", "), pulse);
+
+        let mut tick = time::interval(pulse);
+        loop {
+            tick.tick().await;
+
+            // statvfs on a stale network mount may hang
+            let (probe, m, mp) = (self.probe.clone(), metrics.clone(), mountpoints.clone());
+            let samples = match tokio::task::spawn_blocking(move || probe(&m, &mp)).await {
+                Ok(samples) => samples,
+                Err(err) => {
+                    log::error!("[{}] '{}' failed to sample resources: {err}", Self::id().bright_magenta(), self.sid);
+                    continue;
+                }
+            };
+
+            for c in watch.update(&samples).iter().filter(|c| self.wants(c.action)) {
+                if let Some(ev) = self.event(c).await {
+                    (emit)(ev);
+                }
+            }
+        }
+    }
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Measures the requested metrics on the running system.
+///
+/// Memory, swap and pressure stall information are read from `/proc` and
+/// are only available on Linux.
+fn probe_live(metrics: &[String], mountpoints: &[String]) -> Vec<ResourceSample> {
+    let want = |metric: &str| metrics.iter().any(|m| m == metric);
+    let mut out = vec![];
+
+    if want("disk") || want("inodes") {
+        let mountpoints = if mountpoints.is_empty() {
+            fs::read_to_string("/proc/mounts").map(|s| parse_mounts(&s)).unwrap_or_default()
+        } else {
+            mountpoints.to_vec()
+        };
+        for mp in &mountpoints {
+            let Some((disk, inodes)) = fs_usage(mp) else {
+                continue;
+            };
+            if want("disk") {
+                out.push(ResourceSample::new("disk", Some(mp), disk));
+            }
+            if want("inodes")
+                && let Some(inodes) = inodes
+            {
+                out.push(ResourceSample::new("inodes", Some(mp), inodes));
+            }
+        }
+    }
+
+    if (want("memory") || want("swap"))
+        && let Ok(meminfo) = fs::read_to_string("/proc/meminfo")
+    {
+        let (memory, swap) = parse_meminfo(&meminfo);
+        for (metric, value) in [("memory", memory), ("swap", swap)] {
+            if want(metric)
+                && let Some(value) = value
+            {
+                out.push(ResourceSample::new(metric, None, value));
+            }
+        }
+    }
+
+    if want("load")
+        && let Some(load) = load_per_core()
+    {
+        out.push(ResourceSample::new("load", None, load));
+    }
+
+    for kind in ["cpu", "memory", "io"] {
+        let metric = format!("pressure.{kind}");
+        if want(&metric)
+            && let Some(value) = fs::read_to_string(format!("/proc/pressure/{kind}")).ok().and_then(|s| parse_pressure(&s))
+        {
+            out.push(ResourceSample::new(&metric, None, value));
+        }
+    }
+
+    out
+}
+
+/// Used space and used inodes of a filesystem, in percent.
+///
+/// Space is counted as `df` does: blocks reserved for root are neither used
+/// nor available. Inodes are `None` on filesystems without an inode limit.
+fn fs_usage(mountpoint: &str) -> Option<(f64, Option<f64>)> {
+    let path = CString::new(mountpoint).ok()?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+
+    let used = st.f_blocks as f64 - st.f_bfree as f64;
+    let total = used + st.f_bavail as f64;
+    if total <= 0.0 {
+        return None;
+    }
+    let inodes = (st.f_files > 0).then(|| (st.f_files as f64 - st.f_ffree as f64) / st.f_files as f64 * 100.0);
+    Some((used / total * 100.0, inodes))
+}
+
+/// One minute load average divided by the number of cores
+fn load_per_core() -> Option<f64> {
+    let mut load = [0f64; 1];
+    if unsafe { libc::getloadavg(load.as_mut_ptr(), 1) } != 1 {
+        return None;
+    }
+    Some(load[0] / std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64)
+}
+
+/// Writable, device-backed mountpoints from `/proc/mounts`.
+///
+/// Pseudo filesystems and read-only mounts (e.g. squashfs images, which are
+/// always full) are skipped.
+pub(crate) fn parse_mounts(mounts: &str) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    for line in mounts.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 4 || !fields[0].starts_with('/') || fields[3].split(',').any(|o| o == "ro") {
+            continue;
+        }
+        let mp = unescape_mount(fields[1]);
+        if !out.contains(&mp) {
+            out.push(mp);
+        }
+    }
+    out
+}
+
+/// Decodes the octal escapes of `/proc/mounts`, e.g. `\040` for a space.
+fn unescape_mount(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'\\'
+            && let Some(c) = b.get(i + 1..i + 4).and_then(|o| std::str::from_utf8(o).ok()).and_then(|o| u8::from_str_radix(o, 8).ok())
+        {
+            out.push(c);
+            i += 4;
+            continue;
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Used memory and used swap from `/proc/meminfo`, in percent.
+///
+/// Memory counts everything that is not available, so page cache is not
+/// taken as used. Swap is `None` without swap.
+pub(crate) fn parse_meminfo(meminfo: &str) -> (Option<f64>, Option<f64>) {
+    let kb = meminfo
+        .lines()
+        .filter_map(|line| {
+            let (key, rest) = line.split_once(':')?;
+            Some((key.trim(), rest.split_whitespace().next()?.parse::<f64>().ok()?))
+        })
+        .collect::<HashMap<_, _>>();
+    let used = |total: &str, free: &str| match (kb.get(total), kb.get(free)) {
+        (Some(total), Some(free)) if *total > 0.0 => Some((total - free) / total * 100.0),
+        _ => None,
+    };
+    (used("MemTotal", "MemAvailable"), used("SwapTotal", "SwapFree"))
+}
+
+/// The `some avg10` share of a `/proc/pressure/*` file: the percentage of the
+/// last ten seconds in which at least one task was stalled on the resource.
+pub(crate) fn parse_pressure(pressure: &str) -> Option<f64> {
+    pressure
+        .lines()
+        .find(|line| line.starts_with("some "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))
+        .and_then(|v| v.parse().ok())
+}
@@ -0,0 +1,147 @@
+use crate::{
+    sensors::{
+        resource::{ResourceSample, ResourceSensor, ResourceWatch, parse_meminfo, parse_mounts, parse_pressure},
+        sensor::Sensor,
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a `sys.resource` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, opts: &[&str], args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.resource",
+        "tag": tag,
+        "opts": opts,
+        "interval": {"secs": 0, "nanos": 10_000_000},
+        "args": args
+    }))
+    .unwrap()
+}
+
+fn disk(mp: &str, value: f64) -> ResourceSample {
+    ResourceSample::new("disk", Some(mp), value)
+}
+
+#[test]
+fn listener_id_with_tag() {
+    assert_eq!(ResourceSensor::new("sid".to_string(), mk_cfg(Some("fs"), &[], json!({}))).listener_id_with_tag(), "sys.resource@fs");
+}
+
+#[test]
+fn make_eid_uses_metric_and_target() {
+    let s = ResourceSensor::new("sid".to_string(), mk_cfg(None, &[], json!({})));
+    assert_eq!(s.make_eid("exceeded", &disk("/var", 95.0).key()), "sid|sys.resource|exceeded@disk:/var|0");
+    assert_eq!(s.make_eid("recovered", &ResourceSample::new("pressure.io", None, 1.0).key()), "sid|sys.resource|recovered@pressure.io|0");
+}
+
+#[test]
+fn thresholds_are_read_from_args() {
+    let w = ResourceWatch::from_cfg(&mk_cfg(None, &[], json!({"disk": 90, "load": 1.5, "pressure": {"io": 20, "cpu": 0}})));
+    assert_eq!(w.metrics(), vec!["disk", "load", "pressure.io"]);
+    assert!(ResourceWatch::from_cfg(&mk_cfg(None, &[], json!({"mountpoints": ["/"]}))).is_empty());
+}
+
+#[test]
+fn watch_fires_once_and_recovers_below_hysteresis() {
+    let mut w = ResourceWatch::from_cfg(&mk_cfg(None, &[], json!({"disk": 90, "hysteresis": 10})));
+
+    assert!(w.update(&[disk("/", 80.0)]).is_empty());
+
+    let c = w.update(&[disk("/", 91.5)]);
+    assert_eq!(c.len(), 1);
+    assert_eq!(c[0].action, "exceeded");
+    assert_eq!(c[0].trend, "rising");
+    assert_eq!(c[0].sample.value, 91.5);
+
+    // Still above the recovery level of 81
+    assert!(w.update(&[disk("/", 95.0)]).is_empty());
+    assert!(w.update(&[disk("/", 85.0)]).is_empty());
+    assert!(w.update(&[disk("/", 92.0)]).is_empty());
+
+    let c = w.update(&[disk("/", 70.0)]);
+    assert_eq!(c.len(), 1);
+    assert_eq!(c[0].action, "recovered");
+    assert_eq!(c[0].trend, "falling");
+    assert_eq!(c[0].recover_below, 81.0);
+}
+
+#[test]
+fn watch_tracks_targets_separately_and_forgets_vanished_ones() {
+    let mut w = ResourceWatch::from_cfg(&mk_cfg(None, &[], json!({"disk": 90})));
+
+    let c = w.update(&[disk("/", 95.0), disk("/var", 50.0)]);
+    assert_eq!(c.len(), 1);
+    assert_eq!(c[0].sample.key(), "disk:/");
+
+    // "/" is gone: when it comes back full, it fires again
+    assert!(w.update(&[disk("/var", 50.0)]).is_empty());
+    assert_eq!(w.update(&[disk("/", 95.0), disk("/var", 50.0)]).len(), 1);
+}
+
+#[test]
+fn meminfo_counts_available_memory_as_free() {
+    let (mem, swap) = parse_meminfo(
+        "MemTotal:       1000 kB\nMemFree:         100 kB\nMemAvailable:    250 kB\nSwapTotal:      2000 kB\nSwapFree:       1500 kB\n",
+    );
+    assert_eq!(mem, Some(75.0));
+    assert_eq!(swap, Some(25.0));
+
+    let (_, swap) = parse_meminfo("MemTotal: 1000 kB\nMemAvailable: 250 kB\nSwapTotal: 0 kB\nSwapFree: 0 kB\n");
+    assert!(swap.is_none());
+}
+
+#[test]
+fn pressure_reads_some_avg10() {
+    let psi = "some avg10=12.34 avg60=5.00 avg300=1.00 total=123456\nfull avg10=3.00 avg60=1.00 avg300=0.50 total=6543\n";
+    assert_eq!(parse_pressure(psi), Some(12.34));
+    assert!(parse_pressure("full avg10=3.00 avg60=1.00 avg300=0.50 total=6543\n").is_none());
+}
+
+#[test]
+fn mounts_skip_pseudo_and_readonly_filesystems() {
+    let mounts = "\
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+/dev/sda1 / ext4 rw,relatime 0 0
+tmpfs /run tmpfs rw,nosuid,nodev 0 0
+/dev/loop3 /snap/core/1 squashfs ro,nodev,relatime 0 0
+/dev/sdb1 /mnt/my\\040data xfs rw,relatime 0 0
+/dev/sda1 / ext4 rw,relatime 0 0
+";
+    assert_eq!(parse_mounts(mounts), vec!["/".to_string(), "/mnt/my data".to_string()]);
+}
+
+#[tokio::test]
+async fn run_emits_enabled_crossings_with_value_and_trend() {
+    let rounds = Arc::new(Mutex::new(VecDeque::from(vec![
+        vec![ResourceSample::new("memory", None, 50.0)],
+        vec![ResourceSample::new("memory", None, 96.0)],
+        vec![ResourceSample::new("memory", None, 40.0)],
+    ])));
+    let s = ResourceSensor::with_probe(
+        "sid".to_string(),
+        mk_cfg(Some("mem"), &["recovered"], json!({"memory": 90})),
+        Arc::new(move |metrics, _| {
+            assert_eq!(metrics, ["memory".to_string()]);
+            rounds.lock().unwrap().pop_front().unwrap_or_default()
+        }),
+    );
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let _ = tokio::time::timeout(Duration::from_millis(200), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["eid"], "sid|sys.resource@mem|recovered@memory|0");
+    assert_eq!(events[0]["data"]["action"], "recovered");
+    assert_eq!(events[0]["data"]["value"], 40.0);
+    assert_eq!(events[0]["data"]["threshold"], 90.0);
+    assert_eq!(events[0]["data"]["trend"], "falling");
+    assert_eq!(events[0]["data"]["delta"], -56.0);
+}
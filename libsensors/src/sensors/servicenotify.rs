@@ -0,0 +1,343 @@
+use crate::{
+    argparse::SensorArgs,
+    sensors::sensor::{Sensor, SensorEvent},
+    sspec::SensorConf,
+};
+use async_trait::async_trait;
+use colored::Colorize;
+use glob::Pattern;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    process::Command,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time;
+
+/// Properties requested from `systemctl show`
+const SYSTEMD_PROPS: &str = "Id,ActiveState,SubState,Result,ExecMainStatus,NRestarts";
+
+/// Lists the state of every service unit selected by the filter.
+///
+/// Returns `None` if the service manager could not be queried at all, so a
+/// failed poll is not mistaken for every service being stopped. The probe
+/// stays injectable so tests can feed deterministic states.
+type ServiceProbe = Arc<dyn Fn(&UnitFilter) -> Option<Vec<ServiceStatus>> + Send + Sync>;
+
+/// Include and exclude glob patterns for unit names.
+///
+/// A pattern matches the full unit name as well as the name without the
+/// `.service` suffix, so both `nginx` and `nginx.service` select the same unit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UnitFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl UnitFilter {
+    pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Self, glob::PatternError> {
+        Ok(Self {
+            include: include.iter().map(|g| Pattern::new(g)).collect::<Result<_, _>>()?,
+            exclude: exclude.iter().map(|g| Pattern::new(g)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns `true` if the unit is selected. Without include patterns every unit is.
+    pub(crate) fn matches(&self, unit: &str) -> bool {
+        let short = unit.strip_suffix(".service").unwrap_or(unit);
+        let hit = |p: &Pattern| p.matches(unit) || p.matches(short);
+        (self.include.is_empty() || self.include.iter().any(hit)) && !self.exclude.iter().any(hit)
+    }
+}
+
+/// State of one service, as reported by the service manager.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ServiceStatus {
+    pub(crate) unit: String,
+    /// `active`, `inactive`, `failed`, `restarting`, `starting` or `stopping`
+    pub(crate) state: String,
+    /// Manager specific detail, e.g. the systemd sub-state `auto-restart`
+    pub(crate) sub: Option<String>,
+    /// Result of the last run, e.g. `exit-code` or `signal` on systemd
+    pub(crate) result: Option<String>,
+    pub(crate) exit_code: Option<i64>,
+    pub(crate) restarts: Option<u64>,
+}
+
+impl ServiceStatus {
+    /// Maps systemd's active and sub-state onto the sensor states.
+    ///
+    /// A unit waiting to be restarted after a crash is `activating` with the
+    /// `auto-restart` sub-state on systemd, which is reported as `restarting`.
+    pub(crate) fn systemd_state(active: &str, sub: &str) -> &'static str {
+        match (active, sub) {
+            (_, "auto-restart") => "restarting",
+            ("active" | "reloading" | "refreshing", _) => "active",
+            ("failed", _) => "failed",
+            ("activating", _) => "starting",
+            ("deactivating", _) => "stopping",
+            _ => "inactive",
+        }
+    }
+}
+
+/// A service changing its state, or restarted between two polls.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ServiceTransition {
+    /// The new state, or `restarting` if only the restart counter moved
+    pub(crate) action: String,
+    pub(crate) old: String,
+    pub(crate) status: ServiceStatus,
+}
+
+/// The last known state of every watched service.
+///
+/// The first poll only records the states. Services that are not listed by
+/// the service manager are `inactive`, as systemd unloads stopped units.
+#[derive(Debug, Default)]
+pub(crate) struct ServiceWatch {
+    tracks: HashMap<String, ServiceStatus>,
+    primed: bool,
+}
+
+impl ServiceWatch {
+    /// Feed one poll and return the transitions since the previous one.
+    pub(crate) fn update(&mut self, services: &[ServiceStatus]) -> Vec<ServiceTransition> {
+        let mut out = vec![];
+        let mut seen = HashSet::new();
+        for status in services {
+            seen.insert(status.unit.clone());
+            let prev = self.tracks.insert(status.unit.clone(), status.clone());
+            if !self.primed {
+                continue;
+            }
+
+            let old = prev.as_ref().map(|p| p.state.as_str()).unwrap_or("inactive");
+            let restarted = matches!((prev.as_ref().and_then(|p| p.restarts), status.restarts), (Some(a), Some(b)) if b > a);
+            if old != status.state {
+                out.push(ServiceTransition { action: status.state.clone(), old: old.to_string(), status: status.clone() });
+            } else if restarted {
+                out.push(ServiceTransition { action: "restarting".to_string(), old: old.to_string(), status: status.clone() });
+            }
+        }
+
+        for track in self.tracks.values().filter(|t| !seen.contains(&t.unit) && t.state != "inactive") {
+            let status = ServiceStatus { unit: track.unit.clone(), state: "inactive".to_string(), ..Default::default() };
+            out.push(ServiceTransition { action: status.state.clone(), old: track.state.clone(), status });
+        }
+        self.tracks.retain(|unit, _| seen.contains(unit));
+        self.primed = true;
+
+        out.sort_by(|a, b| a.status.unit.cmp(&b.status.unit));
+        out
+    }
+}
+
+/// Emits an event when a systemd unit or an rc.d service changes its state,
+/// e.g. crashes, is restarted or flaps.
+#[derive(Clone)]
+pub struct ServiceSensor {
+    sid: String,
+    cfg: SensorConf,
+    probe: ServiceProbe,
+}
+
+impl fmt::Debug for ServiceSensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceSensor").field("sid", &self.sid).field("listener", &self.cfg.listener()).finish()
+    }
+}
+
+impl ServiceSensor {
+    /// Builds a sensor instance with a custom probe for tests.
+    #[cfg(test)]
+    pub(crate) fn with_probe(id: String, cfg: SensorConf, probe: ServiceProbe) -> Self {
+        Self { sid: id, cfg, probe }
+    }
+
+    /// Returns the listener id, including an optional `@tag` suffix.
+    pub(crate) fn listener_id_with_tag(&self) -> String {
+        format!("{}{}{}", Self::id(), if self.cfg.tag().is_some() { "@" } else { "" }, self.cfg.tag().unwrap_or(""))
+    }
+
+    /// Returns `true` if the action is enabled by the sensor options.
+    /// Without options every transition is emitted.
+    pub(crate) fn wants(&self, action: &str) -> bool {
+        self.cfg.opts().is_empty() || self.cfg.opts().iter().any(|o| o == action)
+    }
+
+    /// Builds a stable event id for a service transition.
+    pub(crate) fn make_eid(&self, action: &str, unit: &str) -> String {
+        format!("{}|{}|{}@{}|{}", self.sid, self.listener_id_with_tag(), action, unit, 0)
+    }
+
+    /// Packages one transition, honouring optional lock-based duplicate suppression.
+    async fn event(&self, t: &ServiceTransition) -> Option<SensorEvent> {
+        let eid = self.make_eid(&t.action, &t.status.unit);
+        if self.cfg.arg_bool("locked").unwrap_or(false) && !libcommon::eidhub::get_eidhub().add("sys.service", &eid).await {
+            return None;
+        }
+
+        Some(json!({
+            "eid": eid,
+            "sensor": self.sid,
+            "listener": "sys.service",
+            "data": {
+                "action": t.action,
+                "unit": t.status.unit,
+                "old": t.old,
+                "new": t.status.state,
+                "sub": t.status.sub,
+                "result": t.status.result,
+                "exit-code": t.status.exit_code,
+                "restarts": t.status.restarts,
+            },
+        }))
+    }
+}
+
+#[async_trait]
+impl Sensor for ServiceSensor {
+    fn new(id: String, cfg: SensorConf) -> Self {
+        Self { sid: id, cfg, probe: Arc::new(probe_live) }
+    }
+
+    fn id() -> String {
+        "sys.service".to_string()
+    }
+
+    async fn run(&self, emit: &(dyn Fn(SensorEvent) + Send + Sync)) {
+        let filter =
+            match UnitFilter::new(&self.cfg.arg_str_array("include").unwrap_or_default(), &self.cfg.arg_str_array("exclude").unwrap_or_default()) {
+                Ok(filter) => Arc::new(filter),
+                Err(err) => {
+                    log::warn!("[{}] '{}' has an invalid unit pattern: {err}; not starting", Self::id().bright_magenta(), self.sid);
+                    return;
+                }
+            };
+
+        let pulse = self.cfg.interval().unwrap_or_else(|| Duration::from_secs(10));
+        log::info!("[{}] '{}' watching services with pulse {:?}", Self::id().bright_magenta(), self.sid, pulse);
+
+        let mut watch = ServiceWatch::default();
+        let mut tick = time::interval(pulse);
+        loop {
+            tick.tick().await;
+
+            let (probe, f) = (self.probe.clone(), filter.clone());
+            let services = match tokio::task::spawn_blocking(move || probe(&f)).await {
+                Ok(Some(services)) => services,
+                Ok(None) => {
+                    log::debug!("[{}] '{}' could not query the service manager", Self::id().bright_magenta(), self.sid);
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("[{}] '{}' failed to poll services: {err}", Self::id().bright_magenta(), self.sid);
+                    continue;
+                }
+            };
+
+            for t in watch.update(&services).iter().filter(|t| self.wants(&t.action)) {
+                if let Some(ev) = self.event(t).await {
+                    (emit)(ev);
+                }
+            }
+        }
+    }
+}
+
+/// Polls the service manager of the running system: systemd on Linux,
+/// `rcctl` on OpenBSD and rc.d `service` elsewhere.
+fn probe_live(filter: &UnitFilter) -> Option<Vec<ServiceStatus>> {
+    if cfg!(target_os = "linux") {
+        probe_systemd(filter)
+    } else if cfg!(target_os = "openbsd") {
+        probe_rc(filter, &["rcctl", "ls", "on"], |name| Command::new("rcctl").args(["check", name]).output())
+    } else {
+        probe_rc(filter, &["service", "-e"], |name| Command::new("service").args([name, "status"]).output())
+    }
+}
+
+/// Runs a command and returns its stdout if it succeeded.
+fn stdout_of(cmd: &str, args: &[&str]) -> Option<String> {
+    Command::new(cmd)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn probe_systemd(filter: &UnitFilter) -> Option<Vec<ServiceStatus>> {
+    let units = parse_list_units(&stdout_of("systemctl", &["list-units", "--type=service", "--all", "--plain", "--no-legend", "--no-pager"])?)
+        .into_iter()
+        .filter(|u| filter.matches(u))
+        .collect::<Vec<_>>();
+    if units.is_empty() {
+        return Some(vec![]);
+    }
+
+    let mut args = vec!["show", "--no-pager", "-p", SYSTEMD_PROPS];
+    args.extend(units.iter().map(String::as_str));
+    Some(parse_systemd_show(&stdout_of("systemctl", &args)?))
+}
+
+/// Enabled rc.d services, each checked with its `status` command.
+///
+/// An enabled service that is not running is reported as `failed`, with the
+/// exit code of the status command. rc.d keeps no restart counter.
+fn probe_rc(filter: &UnitFilter, list: &[&str], status: impl Fn(&str) -> std::io::Result<std::process::Output>) -> Option<Vec<ServiceStatus>> {
+    let names = stdout_of(list[0], &list[1..])?
+        .lines()
+        .filter_map(|l| l.trim().rsplit('/').next().map(str::to_string))
+        .filter(|n| !n.is_empty() && filter.matches(n))
+        .collect::<Vec<_>>();
+
+    let mut out = vec![];
+    for name in names {
+        let Ok(output) = status(&name) else {
+            continue;
+        };
+        let running = output.status.success();
+        out.push(ServiceStatus {
+            unit: name,
+            state: if running { "active" } else { "failed" }.to_string(),
+            exit_code: output.status.code().map(i64::from),
+            ..Default::default()
+        });
+    }
+    Some(out)
+}
+
+/// Unit names from `systemctl list-units --plain --no-legend`.
+pub(crate) fn parse_list_units(out: &str) -> Vec<String> {
+    out.lines()
+        .filter_map(|l| l.trim_start_matches(|c: char| c == '●' || c == '*' || c.is_whitespace()).split_whitespace().next())
+        .filter(|u| u.ends_with(".service"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Service states from `systemctl show`, one blank-line separated block per unit.
+pub(crate) fn parse_systemd_show(out: &str) -> Vec<ServiceStatus> {
+    let mut services = vec![];
+    for block in out.split("\n\n") {
+        let props = block.lines().filter_map(|l| l.split_once('=')).collect::<HashMap<_, _>>();
+        let Some(unit) = props.get("Id").filter(|u| !u.is_empty()) else {
+            continue;
+        };
+        let active = props.get("ActiveState").copied().unwrap_or_default();
+        let sub = props.get("SubState").copied().unwrap_or_default();
+        services.push(ServiceStatus {
+            unit: unit.to_string(),
+            state: ServiceStatus::systemd_state(active, sub).to_string(),
+            sub: (!sub.is_empty()).then(|| sub.to_string()),
+            result: props.get("Result").filter(|r| !r.is_empty()).map(|r| r.to_string()),
+            exit_code: props.get("ExecMainStatus").and_then(|c| c.parse().ok()),
+            restarts: props.get("NRestarts").and_then(|c| c.parse().ok()),
+        });
+    }
+    services
+}
@@ -0,0 +1,165 @@
+use crate::{
+    sensors::{
+        sensor::Sensor,
+        servicenotify::{ServiceSensor, ServiceStatus, ServiceWatch, UnitFilter, parse_list_units, parse_systemd_show},
+    },
+    sspec::SensorConf,
+};
+use serde_json::{from_value, json};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Returns a `sys.service` sensor configuration for tests.
+fn mk_cfg(tag: Option<&str>, opts: &[&str], args: serde_json::Value) -> SensorConf {
+    from_value(json!({
+        "listener": "sys.service",
+        "tag": tag,
+        "opts": opts,
+        "interval": {"secs": 0, "nanos": 10_000_000},
+        "args": args
+    }))
+    .unwrap()
+}
+
+fn svc(unit: &str, state: &str, restarts: Option<u64>) -> ServiceStatus {
+    ServiceStatus { unit: unit.to_string(), state: state.to_string(), restarts, ..Default::default() }
+}
+
+fn filter(include: &[&str], exclude: &[&str]) -> UnitFilter {
+    let v = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    UnitFilter::new(&v(include), &v(exclude)).unwrap()
+}
+
+#[test]
+fn make_eid_uses_action_and_unit() {
+    let s = ServiceSensor::new("sid".to_string(), mk_cfg(Some("web"), &[], json!({})));
+    assert_eq!(s.make_eid("failed", "nginx.service"), "sid|sys.service@web|failed@nginx.service|0");
+}
+
+#[test]
+fn filter_matches_with_and_without_suffix() {
+    let f = filter(&["nginx", "ssh*"], &["*@*"]);
+    assert!(f.matches("nginx.service"));
+    assert!(f.matches("sshd.service"));
+    assert!(f.matches("sshd"));
+    assert!(!f.matches("cron.service"));
+    assert!(!f.matches("ssh@1.service"));
+
+    let all = filter(&[], &["systemd-*"]);
+    assert!(all.matches("cron.service"));
+    assert!(!all.matches("systemd-journald.service"));
+}
+
+#[test]
+fn systemd_states_are_normalised() {
+    assert_eq!(ServiceStatus::systemd_state("active", "running"), "active");
+    assert_eq!(ServiceStatus::systemd_state("reloading", "reload"), "active");
+    assert_eq!(ServiceStatus::systemd_state("activating", "auto-restart"), "restarting");
+    assert_eq!(ServiceStatus::systemd_state("activating", "start-pre"), "starting");
+    assert_eq!(ServiceStatus::systemd_state("failed", "failed"), "failed");
+    assert_eq!(ServiceStatus::systemd_state("inactive", "dead"), "inactive");
+}
+
+#[test]
+fn parses_systemctl_output() {
+    let list = "\
+cron.service      loaded active   running Regular background program processing daemon
+● nginx.service   loaded failed   failed  A high performance web server
+dev-sda1.device   loaded active   plugged /dev/sda1
+";
+    assert_eq!(parse_list_units(list), vec!["cron.service".to_string(), "nginx.service".to_string()]);
+
+    let show = "\
+Id=nginx.service
+ActiveState=failed
+SubState=failed
+Result=exit-code
+ExecMainStatus=1
+NRestarts=5
+
+Id=cron.service
+ActiveState=active
+SubState=running
+Result=success
+ExecMainStatus=0
+NRestarts=0
+";
+    let services = parse_systemd_show(show);
+    assert_eq!(services.len(), 2);
+    assert_eq!(services[0].unit, "nginx.service");
+    assert_eq!(services[0].state, "failed");
+    assert_eq!(services[0].result.as_deref(), Some("exit-code"));
+    assert_eq!(services[0].exit_code, Some(1));
+    assert_eq!(services[0].restarts, Some(5));
+    assert_eq!(services[1].state, "active");
+}
+
+#[test]
+fn watch_reports_transitions_after_the_first_poll() {
+    let mut w = ServiceWatch::default();
+    assert!(w.update(&[svc("a.service", "failed", Some(0)), svc("b.service", "active", Some(0))]).is_empty());
+    assert!(w.update(&[svc("a.service", "failed", Some(0)), svc("b.service", "active", Some(0))]).is_empty());
+
+    let t = w.update(&[svc("a.service", "active", Some(0)), svc("b.service", "restarting", Some(1))]);
+    assert_eq!(t.iter().map(|t| (t.action.as_str(), t.old.as_str())).collect::<Vec<_>>(), vec![("active", "failed"), ("restarting", "active")]);
+}
+
+#[test]
+fn watch_sees_restarts_between_polls() {
+    let mut w = ServiceWatch::default();
+    w.update(&[svc("a.service", "active", Some(2))]);
+
+    let t = w.update(&[svc("a.service", "active", Some(3))]);
+    assert_eq!(t.len(), 1);
+    assert_eq!(t[0].action, "restarting");
+    assert_eq!(t[0].status.state, "active");
+    assert_eq!(t[0].status.restarts, Some(3));
+}
+
+#[test]
+fn watch_treats_unlisted_units_as_inactive() {
+    let mut w = ServiceWatch::default();
+    w.update(&[svc("a.service", "active", None)]);
+
+    let gone = w.update(&[]);
+    assert_eq!(gone.len(), 1);
+    assert_eq!((gone[0].action.as_str(), gone[0].old.as_str()), ("inactive", "active"));
+    assert!(w.update(&[]).is_empty());
+
+    let back = w.update(&[svc("a.service", "active", None)]);
+    assert_eq!((back[0].action.as_str(), back[0].old.as_str()), ("active", "inactive"));
+}
+
+#[tokio::test]
+async fn run_emits_enabled_transitions_with_exit_code() {
+    let failed = ServiceStatus { result: Some("exit-code".into()), exit_code: Some(2), ..svc("nginx.service", "failed", Some(1)) };
+    let rounds = Arc::new(Mutex::new(VecDeque::from(vec![
+        Some(vec![svc("nginx.service", "active", Some(0))]),
+        None,
+        Some(vec![svc("nginx.service", "restarting", Some(1))]),
+        Some(vec![failed]),
+    ])));
+    let s = ServiceSensor::with_probe(
+        "sid".to_string(),
+        mk_cfg(None, &["failed"], json!({"include": ["nginx"]})),
+        Arc::new(move |f| {
+            assert!(f.matches("nginx.service"));
+            rounds.lock().unwrap().pop_front().unwrap_or(None)
+        }),
+    );
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    let _ = tokio::time::timeout(Duration::from_millis(200), s.run(&move |ev| sink.lock().unwrap().push(ev))).await;
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["eid"], "sid|sys.service|failed@nginx.service|0");
+    assert_eq!(events[0]["data"]["old"], "restarting");
+    assert_eq!(events[0]["data"]["new"], "failed");
+    assert_eq!(events[0]["data"]["exit-code"], 2);
+    assert_eq!(events[0]["data"]["restarts"], 1);
+}
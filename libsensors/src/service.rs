@@ -1,16 +1,282 @@
-use crate::bridge::reactor_emitter;
+use crate::bridge::EventQueue;
+use crate::sensors::menotify::MeNotifySensor;
+use crate::sensors::sensor::{Sensor, SensorEvent};
 use crate::sensors::{self, SensorCtx};
-use crate::sspec::SensorSpec;
+use crate::sspec::{SensorConf, SensorSpec};
 use colored::Colorize;
+use indexmap::IndexMap;
 use libsysinspect::reactor::evtproc::EventProcessor;
+use serde::Serialize;
+use std::any::Any;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Notify, oneshot};
 use tokio::task::{AbortHandle, JoinHandle, JoinSet};
 
+/// Longest delay between restarts of a failing sensor. A sensor that ran
+/// longer than that is restarted after the initial backoff again.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Longest wait for a stopped sensor to let go of its resources before its replacement starts
+const HALT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lifecycle state of a sensor, as reported to the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorState {
+    /// Sensor was started
+    Started,
+
+    /// Sensor was stopped or has finished on its own
+    Stopped,
+
+    /// Sensor has panicked
+    Crashed,
+
+    /// Sensor was restarted after it has finished, or with a changed configuration
+    Restarted,
+}
+
+impl SensorState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensorState::Started => "started",
+            SensorState::Stopped => "stopped",
+            SensorState::Crashed => "crashed",
+            SensorState::Restarted => "restarted",
+        }
+    }
+}
+
+/// Lifecycle change of one sensor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SensorNotice {
+    pub sid: String,
+    pub listener: String,
+    pub state: SensorState,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SensorNotice {
+    fn new(sid: &str, cfg: &SensorConf, state: SensorState, error: Option<String>) -> Self {
+        Self { sid: sid.to_string(), listener: cfg.listener().to_string(), state, error }
+    }
+}
+
+/// Callback receiving sensor lifecycle changes.
+pub type SensorNotifier = Arc<dyn Fn(SensorNotice) + Send + Sync>;
+
+/// Health of one sensor, as reported to the master.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SensorStats {
+    pub sid: String,
+    pub listener: String,
+    pub state: SensorState,
+    pub restarts: u64,
+    pub emitted: u64,
+    pub dropped: u64,
+
+    /// Events registered to the reactor in batches
+    pub batched: u64,
+
+    /// Events waiting for the reactor
+    pub queued: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    /// Seconds since the last heartbeat, i.e. since an event was last queued or passed to the reactor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat: Option<u64>,
+
+    /// Seconds since the sensor was last started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<u64>,
+}
+
+struct Health {
+    state: SensorState,
+    last_error: Option<String>,
+    started: Option<Instant>,
+}
+
+/// Counters of one sensor, shared by its supervisor, its emitter and the service.
+/// Drop and batch counters and the heartbeat are kept by the event queue of the sensor.
+struct SensorCounters {
+    emitted: AtomicU64,
+    restarts: AtomicU64,
+    health: std::sync::Mutex<Health>,
+    queue: Arc<EventQueue>,
+}
+
+impl SensorCounters {
+    fn new(queue: Arc<EventQueue>) -> Self {
+        Self {
+            emitted: AtomicU64::new(0),
+            queue,
+            restarts: AtomicU64::new(0),
+            health: std::sync::Mutex::new(Health { state: SensorState::Started, last_error: None, started: None }),
+        }
+    }
+
+    fn health(&self) -> std::sync::MutexGuard<'_, Health> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn started(&self) {
+        self.health().started = Some(Instant::now());
+    }
+
+    fn set_state(&self, state: SensorState, error: Option<String>) {
+        let mut health = self.health();
+        health.state = state;
+        if error.is_some() {
+            health.last_error = error;
+        }
+    }
+
+    fn stats(&self, sid: &str, cfg: &SensorConf) -> SensorStats {
+        let health = self.health();
+        SensorStats {
+            sid: sid.to_string(),
+            listener: cfg.listener().to_string(),
+            state: health.state,
+            restarts: self.restarts.load(Ordering::Relaxed),
+            emitted: self.emitted.load(Ordering::Relaxed),
+            dropped: self.queue.dropped(),
+            batched: self.queue.batched(),
+            queued: self.queue.len() as u64,
+            last_error: health.last_error.clone(),
+            heartbeat: self.queue.heartbeat().map(|t| t.as_secs()),
+            uptime: health.started.map(|t| t.elapsed().as_secs()),
+        }
+    }
+}
+
+/// Sensor task that is currently managed by the service.
+struct RunningSensor {
+    cfg: SensorConf,
+    task: AbortHandle,
+    /// Tells the supervisor to stop the sensor and finish
+    stop: Arc<Notify>,
+    /// Closed once the supervisor and its sensor are gone
+    done: oneshot::Receiver<()>,
+    counters: Arc<SensorCounters>,
+}
+
+/// Keeps one sensor running according to its restart policy.
+struct Supervisor {
+    sid: String,
+    cfg: SensorConf,
+    ctx: SensorCtx,
+    notifier: Option<SensorNotifier>,
+    counters: Arc<SensorCounters>,
+    stop: Arc<Notify>,
+    /// Dropped with the supervisor, which closes [`RunningSensor::done`]
+    _done: oneshot::Sender<()>,
+}
+
+impl Supervisor {
+    fn label(&self) -> String {
+        format!("{}/{}", self.sid, self.cfg.listener())
+    }
+
+    /// Run the sensor, restart it with a growing backoff as long as the policy wants it.
+    ///
+    /// The sensor runs in its own task, so a panic is caught and reported. Aborting the
+    /// supervisor aborts the sensor as well. When told to stop, the supervisor aborts the
+    /// sensor and finishes only after the sensor task is gone.
+    async fn run(self, first: Box<dyn Sensor>) {
+        let mut sensor = Some(first);
+        let mut failures = 0u32;
+
+        loop {
+            let Some(current) =
+                sensor.take().or_else(|| sensors::init_sensor(self.cfg.listener(), self.sid.clone(), self.cfg.clone(), self.ctx.clone()))
+            else {
+                log::error!("Sensor '{}' cannot be initialised again, giving up", self.label().bright_red());
+                self.report(SensorState::Stopped, Some("sensor cannot be initialised again".to_string()));
+                return;
+            };
+
+            let emit = self.emitter();
+            self.counters.started();
+            let mut run = tokio::spawn(async move {
+                current.run(&emit).await;
+            });
+            let _abort = AbortOnDropGuard(vec![run.abort_handle()]);
+
+            let since = Instant::now();
+            let outcome = tokio::select! {
+                outcome = &mut run => outcome,
+                _ = self.stop.notified() => {
+                    run.abort();
+                    let _ = run.await;
+                    return;
+                }
+            };
+
+            let crashed = match outcome {
+                Ok(()) => {
+                    log::info!("Sensor '{}' has finished", self.label().bright_yellow());
+                    self.report(SensorState::Stopped, None);
+                    false
+                }
+                Err(err) if err.is_panic() => {
+                    let msg = panic_message(err.into_panic());
+                    log::error!("Sensor '{}' has crashed: {msg}", self.label().bright_red());
+                    self.report(SensorState::Crashed, Some(msg));
+                    true
+                }
+                Err(_) => return,
+            };
+
+            if !self.cfg.restart().restarts(crashed) {
+                return;
+            }
+
+            if since.elapsed() >= MAX_BACKOFF {
+                failures = 0;
+            }
+            let delay = self.cfg.backoff().saturating_mul(1 << failures.min(16)).min(MAX_BACKOFF);
+            failures += 1;
+
+            log::info!("Restarting sensor '{}' in {}", self.label().bright_yellow(), humantime::format_duration(delay));
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.stop.notified() => return,
+            }
+            self.counters.restarts.fetch_add(1, Ordering::Relaxed);
+            self.report(SensorState::Restarted, None);
+        }
+    }
+
+    /// Emitter that counts events before queueing them for the reactor. The queue beats the heartbeat.
+    fn emitter(&self) -> impl Fn(SensorEvent) + Send + Sync + 'static {
+        let counters = self.counters.clone();
+        move |ev| {
+            counters.emitted.fetch_add(1, Ordering::Relaxed);
+            counters.queue.push(ev);
+        }
+    }
+
+    fn report(&self, state: SensorState, error: Option<String>) {
+        self.counters.set_state(state, error.clone());
+        if let Some(notifier) = &self.notifier {
+            notifier(SensorNotice::new(&self.sid, &self.cfg, state, error));
+        }
+    }
+}
+
 pub struct SensorService {
     spec: SensorSpec,
     reactor: Option<Arc<Mutex<EventProcessor>>>,
     ctx: SensorCtx,
+    notifier: Option<SensorNotifier>,
+    running: IndexMap<String, RunningSensor>,
 }
 
 struct AbortOnDropGuard(Vec<AbortHandle>);
@@ -29,11 +295,22 @@ impl Drop for AbortOnDropGuard {
     }
 }
 
+/// Extracts a readable message from a task panic payload.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
 impl SensorService {
     /// Creates a new sensor service with default runtime context.
     pub fn new(spec: SensorSpec) -> Self {
         sensors::init_registry();
-        Self { spec, reactor: None, ctx: SensorCtx::default() }
+        Self { spec, reactor: None, ctx: SensorCtx::default(), notifier: None, running: IndexMap::new() }
     }
 
     /// Returns a sensor service with explicit runtime context.
@@ -42,31 +319,111 @@ impl SensorService {
         self
     }
 
+    /// Returns a sensor service that reports sensor lifecycle changes to the notifier.
+    pub fn with_notifier(mut self, notifier: SensorNotifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
     /// Start all sensors in the service spec, returning a list of JoinHandles for the running tasks.
     pub fn start(&mut self) -> Vec<JoinHandle<()>> {
-        let reactor = self.reactor.clone();
         let mut handles = Vec::new();
 
         for (sid, cfg) in self.spec.items() {
-            log::debug!("Starting sensor '{}' with listener '{}'", sid, cfg.listener());
+            if let Some(h) = self.launch(&sid, &cfg) {
+                self.notify(SensorNotice::new(&sid, &cfg, SensorState::Started, None));
+                handles.push(h);
+            }
+        }
+
+        handles
+    }
+
+    /// Apply a new sensor spec to the running sensors.
+    ///
+    /// Sensors that are not in the new spec are stopped, new ones are started and
+    /// only those with a changed configuration are restarted. Sensors that have
+    /// finished on their own are started again. Everything else keeps running.
+    /// A restarted sensor is started only after its old instance is gone, so the
+    /// two never hold the same files, sockets or watches at once.
+    ///
+    /// # Returns
+    ///
+    /// Returns the lifecycle changes, which are also passed to the notifier.
+    pub async fn reload(&mut self, mut spec: SensorSpec) -> Vec<SensorNotice> {
+        let items = spec.items();
+        let mut changes = Vec::new();
+
+        let removed: Vec<String> = self.running.keys().filter(|sid| !items.contains_key(*sid)).cloned().collect();
+        for sid in removed {
+            if let Some(rs) = self.running.shift_remove(&sid) {
+                let cfg = rs.cfg.clone();
+                if Self::retire(&sid, rs).await {
+                    changes.push(SensorNotice::new(&sid, &cfg, SensorState::Stopped, None));
+                }
+            }
+        }
+
+        for (sid, cfg) in items.iter() {
+            let state = match self.running.get(sid) {
+                Some(rs) if !rs.task.is_finished() && rs.cfg.same_as(cfg) => continue,
+                Some(rs) if !rs.task.is_finished() => SensorState::Restarted,
+                _ => SensorState::Started,
+            };
 
-            let Some(sensor) = sensors::init_sensor(cfg.listener(), sid.to_string(), cfg.clone(), self.ctx.clone()) else {
-                log::error!("Unknown sensor listener '{}' for '{}'", cfg.listener(), sid);
-                continue;
+            let old = match self.running.shift_remove(sid) {
+                Some(rs) => {
+                    let cfg = rs.cfg.clone();
+                    Self::retire(sid, rs).await;
+                    Some(cfg)
+                }
+                None => None,
             };
 
-            log::info!("Initialized sensor '{}'", format!("{}/{}", sid, cfg.listener()).bright_yellow());
+            if self.launch(sid, cfg).is_some() {
+                changes.push(SensorNotice::new(sid, cfg, state, None));
+            } else if let Some(old) = old.filter(|_| state == SensorState::Restarted) {
+                changes.push(SensorNotice::new(sid, &old, SensorState::Stopped, None));
+            }
+        }
+
+        self.spec = spec;
+        for n in &changes {
+            log::info!("Sensor '{}' {}", format!("{}/{}", n.sid, n.listener).bright_yellow(), n.state.as_str());
+            self.notify(n.clone());
+        }
+
+        changes
+    }
 
-            let sid = sid.to_string();
-            let reactor = reactor.clone();
+    /// Stop all running sensors.
+    ///
+    /// # Returns
+    ///
+    /// Returns the lifecycle changes, which are also passed to the notifier.
+    pub fn stop(&mut self) -> Vec<SensorNotice> {
+        let mut changes = Vec::new();
+        for (sid, rs) in std::mem::take(&mut self.running) {
+            if Self::halt(&sid, &rs) {
+                changes.push(SensorNotice::new(&sid, &rs.cfg, SensorState::Stopped, None));
+            }
+        }
 
-            let emit = reactor_emitter(sid.clone(), reactor.clone());
-            handles.push(tokio::spawn(async move {
-                sensor.run(&emit).await;
-            }));
+        for n in &changes {
+            self.notify(n.clone());
         }
 
-        handles
+        changes
+    }
+
+    /// Returns the health of all sensors, including those that have finished or crashed.
+    pub fn stats(&self) -> Vec<SensorStats> {
+        self.running.iter().map(|(sid, rs)| rs.counters.stats(sid, &rs.cfg)).collect()
+    }
+
+    /// Returns IDs of the sensors that are currently running.
+    pub fn running(&self) -> Vec<String> {
+        self.running.iter().filter(|(_, rs)| !rs.task.is_finished()).map(|(sid, _)| sid.clone()).collect()
     }
 
     /// Start all sensors under one supervisor task so aborting the returned
@@ -96,4 +453,59 @@ impl SensorService {
     pub fn set_event_processor(&mut self, events: Arc<Mutex<EventProcessor>>) {
         self.reactor = Some(events);
     }
+
+    /// Spawn one sensor under its supervisor and track it.
+    fn launch(&mut self, sid: &str, cfg: &SensorConf) -> Option<JoinHandle<()>> {
+        log::debug!("Starting sensor '{}' with listener '{}'", sid, cfg.listener());
+
+        let Some(sensor) = sensors::init_sensor(cfg.listener(), sid.to_string(), cfg.clone(), self.ctx.clone()) else {
+            log::error!("Unknown sensor listener '{}' for '{}'", cfg.listener(), sid);
+            return None;
+        };
+
+        log::info!("Initialized sensor '{}'", format!("{}/{}", sid, cfg.listener()).bright_yellow());
+
+        // The queue outlives restarts of the sensor, and its forwarder ends once the sensor is dropped
+        let counters = Arc::new(SensorCounters::new(EventQueue::new(sid, cfg.queue().clone(), self.reactor.clone())));
+        let (stop, (tx, done)) = (Arc::new(Notify::new()), oneshot::channel());
+        let supervisor = Supervisor {
+            sid: sid.to_string(),
+            cfg: cfg.clone(),
+            ctx: self.ctx.clone(),
+            notifier: self.notifier.clone(),
+            counters: counters.clone(),
+            stop: stop.clone(),
+            _done: tx,
+        };
+        let task = tokio::spawn(supervisor.run(sensor));
+
+        self.running.insert(sid.to_string(), RunningSensor { cfg: cfg.clone(), task: task.abort_handle(), stop, done, counters });
+        Some(task)
+    }
+
+    /// Stop a sensor and wait until it is gone. Returns `true` if it was still running.
+    async fn retire(sid: &str, rs: RunningSensor) -> bool {
+        let running = !rs.task.is_finished();
+        rs.stop.notify_one();
+        if tokio::time::timeout(HALT_TIMEOUT, rs.done).await.is_err() {
+            log::warn!("Sensor '{}' does not stop, aborting it", format!("{}/{}", sid, rs.cfg.listener()).bright_yellow());
+            rs.task.abort();
+        }
+        MeNotifySensor::invalidate(sid);
+        running
+    }
+
+    /// Abort a sensor task. Returns `true` if it was still running.
+    fn halt(sid: &str, rs: &RunningSensor) -> bool {
+        let running = !rs.task.is_finished();
+        rs.task.abort();
+        MeNotifySensor::invalidate(sid);
+        running
+    }
+
+    fn notify(&self, notice: SensorNotice) {
+        if let Some(notifier) = &self.notifier {
+            notifier(notice);
+        }
+    }
 }
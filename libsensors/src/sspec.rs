@@ -156,6 +156,7 @@ impl SensorSpec {
             if config.interval().is_none() {
                 let mut c = config.clone();
                 c.interval = Some(Self::u2d(Self::pick_range(range.min, range.max), &range.unit));
+                c.picked = true;
                 *config = c;
             }
         }
@@ -181,6 +182,70 @@ impl SensorSpec {
     }
 }
 
+/// What the service does when a sensor finishes.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never start the sensor again
+    Never,
+
+    /// Start the sensor again if it has crashed
+    #[default]
+    OnFailure,
+
+    /// Start the sensor again whenever it finishes
+    Always,
+}
+
+impl RestartPolicy {
+    /// Returns `true` if a sensor that finished, or crashed, should be started again.
+    pub fn restarts(&self, crashed: bool) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => crashed,
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// What the event queue of a sensor does with a new event when it is full.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued event to make room
+    #[default]
+    DropOldest,
+
+    /// Drop the new event
+    DropNewest,
+
+    /// Keep every n-th new event in place of the oldest one, drop the others
+    Sample,
+}
+
+/// Event queue of a sensor, which holds the events while the reactor is busy.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct QueueConf {
+    /// Maximum number of queued events
+    pub size: usize,
+
+    /// What to do with new events when the queue is full
+    pub overflow: OverflowPolicy,
+
+    /// Every n-th event is kept with the `sample` policy
+    pub sample: u64,
+
+    /// Maximum number of events passed to the reactor at once
+    pub batch: usize,
+}
+
+impl Default for QueueConf {
+    fn default() -> Self {
+        Self { size: 1024, overflow: OverflowPolicy::default(), sample: 10, batch: 256 }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct SensorConf {
     #[serde(default)]
@@ -202,6 +267,18 @@ pub struct SensorConf {
 
     #[serde(default)]
     interval: Option<Duration>,
+
+    #[serde(default)]
+    restart: RestartPolicy,
+
+    #[serde(default)]
+    backoff: Option<String>,
+
+    #[serde(default)]
+    queue: QueueConf,
+
+    #[serde(skip)]
+    picked: bool, // Marker that the interval was picked from the global range
 }
 
 impl SensorConf {
@@ -254,6 +331,41 @@ impl SensorConf {
     pub fn interval(&self) -> Option<Duration> {
         self.interval
     }
+
+    /// Returns the restart policy of this sensor, `on-failure` by default.
+    pub fn restart(&self) -> RestartPolicy {
+        self.restart
+    }
+
+    /// Returns the delay before the first restart, one second by default.
+    /// It doubles with every restart of a sensor that keeps failing.
+    pub fn backoff(&self) -> Duration {
+        self.backoff.as_deref().and_then(|b| humantime::parse_duration(b).ok()).unwrap_or(Duration::from_secs(1))
+    }
+
+    /// Returns the event queue settings of this sensor.
+    pub fn queue(&self) -> &QueueConf {
+        &self.queue
+    }
+
+    /// Checks if both configurations describe the same sensor.
+    ///
+    /// Intervals picked at random from the global range are not part of the
+    /// configuration, so they are ignored. A sensor is restarted on reload
+    /// only if this returns `false`.
+    pub fn same_as(&self, other: &SensorConf) -> bool {
+        let explicit = |c: &SensorConf| if c.picked { None } else { c.interval };
+        self.profile == other.profile
+            && self.description == other.description
+            && self.listener == other.listener
+            && self.opts == other.opts
+            && self.args == other.args
+            && self.tag == other.tag
+            && self.restart == other.restart
+            && self.backoff == other.backoff
+            && self.queue == other.queue
+            && explicit(self) == explicit(other)
+    }
 }
 
 impl FromStr for SensorSpec {
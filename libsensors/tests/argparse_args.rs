@@ -36,4 +36,12 @@ mod argparse_args_test {
         let cfg = mk_cfg(json!({ "n": -1 }));
         assert_eq!(cfg.arg_u64("n"), Some(u64::MAX));
     }
+
+    #[test]
+    fn arg_f64_accepts_integers_and_floats() {
+        let cfg = mk_cfg(json!({ "load": 1.5, "disk": 90, "name": "x" }));
+        assert_eq!(cfg.arg_f64("load"), Some(1.5));
+        assert_eq!(cfg.arg_f64("disk"), Some(90.0));
+        assert!(cfg.arg_f64("name").is_none());
+    }
 }
@@ -1,5 +1,6 @@
 mod bridge_emitter_test {
-    use libsensors::bridge::reactor_emitter;
+    use libsensors::bridge::{EventQueue, reactor_emitter};
+    use libsensors::sspec::{OverflowPolicy, QueueConf};
     use libsysinspect::reactor::evtproc::EventProcessor;
     use serde_json::json;
     use std::sync::Arc;
@@ -34,4 +35,65 @@ mod bridge_emitter_test {
         let got = reactor.lock().await.receiver().get_by_eid("sid-b".to_string());
         assert!(got.is_some());
     }
+
+    /// Push five events into a queue of three while the reactor is busy, return the registered eids.
+    async fn overflow(overflow: OverflowPolicy) -> (Vec<String>, u64) {
+        let reactor = Arc::new(Mutex::new(EventProcessor::new()));
+        let queue = EventQueue::new("sid-q", QueueConf { size: 3, overflow, sample: 2, batch: 16 }, Some(reactor.clone()));
+
+        let busy = reactor.lock().await;
+        for i in 1..=5 {
+            queue.push(json!({"eid": format!("e{i}"), "data": {}}));
+        }
+        assert_eq!(queue.len(), 3);
+        drop(busy);
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+
+        let mut rx = reactor.lock().await;
+        let eids = (1..=5).map(|i| format!("e{i}")).filter(|e| rx.receiver().get_by_eid(e.clone()).is_some()).collect();
+        (eids, queue.dropped())
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_latest_events() {
+        assert_eq!(overflow(OverflowPolicy::DropOldest).await, (vec!["e3".into(), "e4".into(), "e5".into()], 2));
+    }
+
+    #[tokio::test]
+    async fn drop_newest_keeps_first_events() {
+        assert_eq!(overflow(OverflowPolicy::DropNewest).await, (vec!["e1".into(), "e2".into(), "e3".into()], 2));
+    }
+
+    #[tokio::test]
+    async fn sample_keeps_every_nth_overflowing_event() {
+        assert_eq!(overflow(OverflowPolicy::Sample).await, (vec!["e2".into(), "e3".into(), "e5".into()], 2));
+    }
+
+    #[tokio::test]
+    async fn queued_events_are_registered_in_one_batch() {
+        let reactor = Arc::new(Mutex::new(EventProcessor::new()));
+        let queue = EventQueue::new("sid-q", QueueConf::default(), Some(reactor.clone()));
+
+        let busy = reactor.lock().await;
+        for i in 0..10 {
+            queue.push(json!({"eid": format!("e{i}"), "data": {}}));
+        }
+        drop(busy);
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+
+        assert!(queue.is_empty());
+        assert_eq!((queue.dropped(), queue.batched()), (0, 10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_queue_does_not_wait_for_a_busy_reactor() {
+        let reactor = Arc::new(Mutex::new(EventProcessor::new()));
+        let queue = EventQueue::new("sid-q", QueueConf::default(), Some(reactor.clone()));
+
+        // With nothing queued the forwarder never needs the reactor, so it closes even while the reactor is busy
+        let _busy = reactor.lock().await;
+        drop(queue);
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        assert_eq!(Arc::strong_count(&reactor), 1);
+    }
 }
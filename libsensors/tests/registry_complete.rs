@@ -81,4 +81,52 @@ sensors:
         let listener = cfg.listener().to_string();
         assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
     }
+
+    #[test]
+    fn sys_resource_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.resource");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
+
+    #[test]
+    fn sys_logtail_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.logtail");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
+
+    #[test]
+    fn sys_integrity_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.integrity");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
+
+    #[test]
+    fn sys_service_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.service");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
+
+    #[test]
+    fn sys_login_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.login");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
+
+    #[test]
+    fn sys_kernel_is_registered() {
+        sensors::init_registry();
+        let (sid, cfg) = cfg_for("sys.kernel");
+        let listener = cfg.listener().to_string();
+        assert!(sensors::init_sensor(&listener, sid, cfg, SensorCtx::default()).is_some());
+    }
 }
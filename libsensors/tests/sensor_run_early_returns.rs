@@ -1,7 +1,13 @@
 mod sensor_run_early_returns_test {
     use libsensors::sensors::fsnotify::FsNotifySensor;
+    use libsensors::sensors::integrity::IntegritySensor;
+    use libsensors::sensors::kernel::KernelSensor;
+    use libsensors::sensors::login::LoginSensor;
+    use libsensors::sensors::logtail::LogTailSensor;
     use libsensors::sensors::mountnotify::MountSensor;
+    use libsensors::sensors::resource::ResourceSensor;
     use libsensors::sensors::sensor::Sensor;
+    use libsensors::sensors::servicenotify::ServiceSensor;
     use libsensors::sspec::SensorConf;
     use serde_json::{from_value, json};
     use std::sync::{
@@ -18,6 +24,14 @@ mod sensor_run_early_returns_test {
         .unwrap()
     }
 
+    fn fs_cfg_unknown_backend() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.filesystem",
+            "args": { "path": "/tmp", "backend": "fanotify" }
+        }))
+        .unwrap()
+    }
+
     fn mount_cfg_missing_mountpoints() -> SensorConf {
         from_value(json!({
             "listener": "sys.mount",
@@ -26,6 +40,54 @@ mod sensor_run_early_returns_test {
         .unwrap()
     }
 
+    fn integrity_cfg_missing_paths() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.integrity",
+            "args": { "exclude": ["*.swp"] }
+        }))
+        .unwrap()
+    }
+
+    fn service_cfg_with_broken_pattern() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.service",
+            "args": { "include": ["nginx["] }
+        }))
+        .unwrap()
+    }
+
+    fn kernel_cfg_with_broken_pattern() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.kernel",
+            "args": { "patterns": { "broken": "(" } }
+        }))
+        .unwrap()
+    }
+
+    fn login_cfg_unknown_backend() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.login",
+            "args": { "backends": ["lastlog"] }
+        }))
+        .unwrap()
+    }
+
+    fn logtail_cfg_with_broken_pattern() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.logtail",
+            "args": { "files": ["/var/log/syslog"], "patterns": { "broken": "(" } }
+        }))
+        .unwrap()
+    }
+
+    fn resource_cfg_without_thresholds() -> SensorConf {
+        from_value(json!({
+            "listener": "sys.resource",
+            "args": { "mountpoints": ["/"] }
+        }))
+        .unwrap()
+    }
+
     #[tokio::test]
     async fn fsnotify_run_returns_early_when_path_missing() {
         let s = FsNotifySensor::new("SID".into(), fs_cfg_missing_path());
@@ -44,6 +106,24 @@ mod sensor_run_early_returns_test {
         assert_eq!(hits.load(Ordering::SeqCst), 0);
     }
 
+    #[tokio::test]
+    async fn fsnotify_run_returns_early_on_unknown_backend() {
+        let s = FsNotifySensor::new("SID".into(), fs_cfg_unknown_backend());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
     #[tokio::test]
     async fn mountnotify_run_returns_early_when_mountpoints_missing() {
         let s = MountSensor::new("SID".into(), mount_cfg_missing_mountpoints());
@@ -61,4 +141,112 @@ mod sensor_run_early_returns_test {
 
         assert_eq!(hits.load(Ordering::SeqCst), 0);
     }
+
+    #[tokio::test]
+    async fn resource_run_returns_early_without_thresholds() {
+        let s = ResourceSensor::new("SID".into(), resource_cfg_without_thresholds());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn logtail_run_returns_early_on_invalid_pattern() {
+        let s = LogTailSensor::new("SID".into(), logtail_cfg_with_broken_pattern());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn integrity_run_returns_early_when_paths_missing() {
+        let s = IntegritySensor::new("SID".into(), integrity_cfg_missing_paths());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn service_run_returns_early_when_pattern_is_invalid() {
+        let s = ServiceSensor::new("SID".into(), service_cfg_with_broken_pattern());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn login_run_returns_early_when_backend_unknown() {
+        let s = LoginSensor::new("SID".into(), login_cfg_unknown_backend());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn kernel_run_returns_early_when_pattern_is_invalid() {
+        let s = KernelSensor::new("SID".into(), kernel_cfg_with_broken_pattern());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = hits.clone();
+
+        timeout(
+            Duration::from_secs(1),
+            s.run(&move |_evt| {
+                hits2.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
 }
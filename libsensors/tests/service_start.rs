@@ -1,7 +1,26 @@
 mod service_start_test {
-    use libsensors::service::SensorService;
+    use libsensors::service::{SensorNotice, SensorService, SensorState};
     use libsensors::sspec::SensorSpec;
     use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    /// Returns a spec of kernel sensors following a plain file, so they keep running.
+    fn kernel_spec(kmsg: &std::path::Path, sensors: &[(&str, &str)]) -> SensorSpec {
+        let mut y = "sensors:\n".to_string();
+        for (sid, opt) in sensors {
+            y.push_str(&format!(
+                "  {sid}:\n    listener: sys.kernel\n    opts: [{opt}]\n    interval: {{ secs: 0, nanos: 10000000 }}\n    args: {{ kmsg: {} }}\n",
+                kmsg.display()
+            ));
+        }
+        SensorSpec::from_str(&y).unwrap()
+    }
+
+    fn states(notices: &[SensorNotice]) -> Vec<(String, SensorState)> {
+        let mut out: Vec<_> = notices.iter().map(|n| (n.sid.clone(), n.state)).collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    }
 
     #[tokio::test]
     async fn start_skips_unknown_listener_and_starts_known_one() {
@@ -56,4 +75,54 @@ sensors:
         h.abort();
         let _ = h.await;
     }
+
+    #[tokio::test]
+    async fn reload_applies_only_the_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let kmsg = tmp.path().join("kmsg");
+        std::fs::write(&kmsg, "").unwrap();
+
+        let mut svc = SensorService::new(kernel_spec(&kmsg, &[("same", "oom"), ("changed", "oom"), ("gone", "oom")]));
+        assert_eq!(svc.start().len(), 3);
+        tokio::task::yield_now().await;
+
+        let changes = svc.reload(kernel_spec(&kmsg, &[("same", "oom"), ("changed", "segfault"), ("added", "oom")])).await;
+        assert_eq!(
+            states(&changes),
+            vec![
+                ("added".to_string(), SensorState::Started),
+                ("changed".to_string(), SensorState::Restarted),
+                ("gone".to_string(), SensorState::Stopped),
+            ]
+        );
+
+        let mut running = svc.running();
+        running.sort();
+        assert_eq!(running, vec!["added", "changed", "same"]);
+
+        // Nothing changed, nothing is touched
+        assert!(svc.reload(kernel_spec(&kmsg, &[("same", "oom"), ("changed", "segfault"), ("added", "oom")])).await.is_empty());
+        svc.stop();
+    }
+
+    #[tokio::test]
+    async fn notifier_receives_lifecycle_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let kmsg = tmp.path().join("kmsg");
+        std::fs::write(&kmsg, "").unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut svc =
+            SensorService::new(kernel_spec(&kmsg, &[("k", "oom")])).with_notifier(Arc::new(move |n: SensorNotice| sink.lock().unwrap().push(n)));
+
+        svc.start();
+        let stopped = svc.stop();
+        assert_eq!(states(&stopped), vec![("k".to_string(), SensorState::Stopped)]);
+        assert!(svc.running().is_empty());
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(states(&seen), vec![("k".to_string(), SensorState::Started), ("k".to_string(), SensorState::Stopped)]);
+        assert_eq!(seen[0].listener, "sys.kernel");
+    }
 }
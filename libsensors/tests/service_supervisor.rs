@@ -0,0 +1,94 @@
+mod service_supervisor_test {
+    use libsensors::service::{SensorNotice, SensorService, SensorState};
+    use libsensors::sspec::SensorSpec;
+    use std::io::Write;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn always_policy_restarts_finished_sensor() {
+        let y = r#"
+sensors:
+  p:
+    listener: sys.proc
+    restart: always
+    backoff: 10ms
+"#;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut svc =
+            SensorService::new(SensorSpec::from_str(y).unwrap()).with_notifier(Arc::new(move |n: SensorNotice| sink.lock().unwrap().push(n.state)));
+
+        svc.start();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let stats = svc.stats();
+        assert_eq!(stats.len(), 1);
+        assert!(stats[0].restarts >= 2);
+        assert_eq!(svc.running(), vec!["p"]);
+        svc.stop();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[..4], [SensorState::Started, SensorState::Stopped, SensorState::Restarted, SensorState::Stopped]);
+    }
+
+    #[tokio::test]
+    async fn finished_sensor_is_not_restarted_by_default() {
+        let y = r#"
+sensors:
+  p:
+    listener: sys.proc
+    backoff: 10ms
+"#;
+        let mut svc = SensorService::new(SensorSpec::from_str(y).unwrap());
+        for h in svc.start() {
+            h.await.unwrap();
+        }
+
+        let stats = svc.stats();
+        assert_eq!((stats[0].state, stats[0].restarts), (SensorState::Stopped, 0));
+        assert!(svc.running().is_empty());
+    }
+
+    #[tokio::test]
+    async fn events_without_reactor_are_counted_as_dropped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let kmsg = tmp.path().join("kmsg");
+        std::fs::write(&kmsg, "").unwrap();
+
+        let y = format!(
+            "sensors:\n  k:\n    listener: sys.kernel\n    interval: {{ secs: 0, nanos: 10000000 }}\n    args: {{ kmsg: {} }}\n",
+            kmsg.display()
+        );
+        let mut svc = SensorService::new(SensorSpec::from_str(&y).unwrap());
+        svc.start();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut f = std::fs::OpenOptions::new().append(true).open(&kmsg).unwrap();
+        writeln!(f, "3,1,100,-;Out of memory: Killed process 1 (java)").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stats = svc.stats();
+        assert_eq!((stats[0].emitted, stats[0].dropped), (1, 1));
+        assert_eq!(stats[0].state, SensorState::Started);
+        assert!(stats[0].heartbeat.is_some());
+        svc.stop();
+    }
+
+    #[tokio::test]
+    async fn quiet_sensor_has_an_uptime_but_no_heartbeat() {
+        let tmp = tempfile::tempdir().unwrap();
+        let kmsg = tmp.path().join("kmsg");
+        std::fs::write(&kmsg, "").unwrap();
+
+        let y = format!("sensors:\n  k:\n    listener: sys.kernel\n    args: {{ kmsg: {} }}\n", kmsg.display());
+        let mut svc = SensorService::new(SensorSpec::from_str(&y).unwrap());
+        svc.start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stats = svc.stats();
+        assert_eq!((stats[0].emitted, stats[0].heartbeat, stats[0].uptime), (0, None, Some(0)));
+        svc.stop();
+    }
+}
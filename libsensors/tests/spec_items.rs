@@ -30,4 +30,26 @@ not_sensors:
         let r = SensorSpec::from_str(y);
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_same_as_ignores_picked_intervals() {
+        let y = r#"
+sensors:
+  a:
+    listener: sys.filesystem
+    args: { path: /tmp }
+  b:
+    listener: sys.filesystem
+    interval: { secs: 5, nanos: 0 }
+"#;
+
+        let a = SensorSpec::from_str(y).unwrap().items();
+        let b = SensorSpec::from_str(y).unwrap().items();
+        assert!(a["a"].same_as(&b["a"]));
+        assert!(a["b"].same_as(&b["b"]));
+        assert!(!a["a"].same_as(&b["b"]));
+
+        let changed = SensorSpec::from_str(&y.replace("/tmp", "/var")).unwrap().items();
+        assert!(!a["a"].same_as(&changed["a"]));
+    }
 }
@@ -0,0 +1,167 @@
+use super::{AUDIT_GENESIS, AUDIT_RECOVERED, AuditChannel, AuditEntry, AuditFilter, AuditJournal, AuditOutcome, minion_target, parse_time};
+use chrono::{Duration, Utc};
+use std::fs;
+
+fn entry(actor: &str, action: &str) -> AuditEntry {
+    AuditEntry::new(actor, AuditChannel::Console, action, "*", AuditOutcome::Success)
+}
+
+#[test]
+fn records_are_chained() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+
+    let first = journal.record(entry("ops", "cluster/sync")).unwrap();
+    let second = journal.record(entry("alice", "POST /api/v1/query").detail("400 Bad Request")).unwrap();
+
+    assert_eq!((first.seq, first.prev.as_str()), (1, AUDIT_GENESIS));
+    assert_eq!((second.seq, second.prev.as_str()), (2, first.hash.as_str()));
+    assert_eq!(second.detail.as_deref(), Some("400 Bad Request"));
+
+    let verified = journal.verify().unwrap();
+    assert_eq!((verified.records, verified.head), (2, second.hash));
+}
+
+#[test]
+fn empty_journal_verifies() {
+    let dir = tempfile::tempdir().unwrap();
+    let verified = AuditJournal::new(dir.path()).verify().unwrap();
+
+    assert_eq!((verified.records, verified.head.as_str()), (0, AUDIT_GENESIS));
+}
+
+#[test]
+fn tampering_breaks_the_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    for action in ["cluster/sync", "cluster/rotate", "cluster/shutdown"] {
+        journal.record(entry("ops", action)).unwrap();
+    }
+    let original = fs::read_to_string(journal.path()).unwrap();
+
+    fs::write(journal.path(), original.replace("cluster/rotate", "cluster/online")).unwrap();
+    assert!(journal.verify().unwrap_err().to_string().contains("record 2"));
+
+    let lines = original.lines().collect::<Vec<_>>();
+    fs::write(journal.path(), format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+    assert!(journal.verify().is_err());
+
+    fs::write(journal.path(), format!("{}\n{}\n", lines[1], lines[2])).unwrap();
+    assert!(journal.verify().is_err());
+}
+
+#[test]
+fn chain_continues_after_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let long = "x".repeat(10_000);
+    AuditJournal::new(dir.path()).record(entry("ops", &long)).unwrap();
+    AuditJournal::new(dir.path()).record(entry("ops", "cluster/sync")).unwrap();
+
+    assert_eq!(AuditJournal::new(dir.path()).verify().unwrap().records, 2);
+}
+
+#[test]
+fn torn_final_record_is_cut_off_and_marked() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    let first = journal.record(entry("ops", "cluster/sync")).unwrap();
+    let mut raw = fs::read(journal.path()).unwrap();
+    raw.extend_from_slice(br#"{"seq":2,"timestamp":"2026-"#);
+    fs::write(journal.path(), raw).unwrap();
+
+    let next = journal.record(entry("ops", "cluster/rotate")).unwrap();
+    let records = journal.query(&AuditFilter::default()).unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        (records[1].action.as_str(), records[1].channel, records[1].prev.as_str()),
+        (AUDIT_RECOVERED, AuditChannel::Journal, first.hash.as_str())
+    );
+    assert_eq!((next.seq, next.prev.as_str()), (3, records[1].hash.as_str()));
+    assert_eq!(journal.verify().unwrap().records, 3);
+}
+
+#[test]
+fn intact_journal_is_not_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    journal.record(entry("ops", "cluster/sync")).unwrap();
+    journal.record(entry("ops", "cluster/rotate")).unwrap();
+
+    assert!(journal.query(&AuditFilter::default()).unwrap().iter().all(|r| r.action != AUDIT_RECOVERED));
+}
+
+#[test]
+fn corrupt_complete_record_is_kept_and_appends_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    journal.record(entry("ops", "cluster/sync")).unwrap();
+    let mut raw = fs::read(journal.path()).unwrap();
+    raw.extend_from_slice(b"{\"seq\":2,\"actor\":\"ops\"}\n");
+    fs::write(journal.path(), &raw).unwrap();
+
+    assert!(journal.record(entry("ops", "cluster/rotate")).unwrap_err().to_string().contains("refusing to append"));
+    assert_eq!(fs::read(journal.path()).unwrap(), raw);
+}
+
+#[test]
+fn pages_follow_the_previous_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    for action in ["cluster/sync", "cluster/rotate", "cluster/shutdown"] {
+        journal.record(entry("ops", action)).unwrap();
+    }
+
+    let first = journal.page(&AuditFilter::default(), 0, 1).unwrap();
+    assert_eq!(first.iter().map(|r| r.seq).collect::<Vec<_>>(), [1]);
+    let rest = journal.page(&AuditFilter::default(), first[0].seq, usize::MAX).unwrap();
+    assert_eq!(rest.iter().map(|r| r.seq).collect::<Vec<_>>(), [2, 3]);
+    assert!(journal.page(&AuditFilter::default(), 3, usize::MAX).unwrap().is_empty());
+}
+
+#[test]
+fn query_filters_by_actor_and_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    for actor in ["ops", "token:ci", "alice", "token:deploy"] {
+        journal.record(entry(actor, "cluster/sync")).unwrap();
+    }
+
+    let actors = |filter: AuditFilter| journal.query(&filter).unwrap().into_iter().map(|r| r.actor).collect::<Vec<_>>();
+    assert_eq!(actors(AuditFilter::default()).len(), 4);
+    assert_eq!(actors(AuditFilter { actor: Some("token:*".to_string()), ..Default::default() }), ["token:ci", "token:deploy"]);
+    assert_eq!(actors(AuditFilter { limit: Some(1), ..Default::default() }), ["token:deploy"]);
+    assert!(actors(AuditFilter { since: Some(Utc::now() + Duration::minutes(1)), ..Default::default() }).is_empty());
+    assert!(actors(AuditFilter { until: Some(Utc::now() - Duration::minutes(1)), ..Default::default() }).is_empty());
+    assert!(journal.query(&AuditFilter { actor: Some("[".to_string()), ..Default::default() }).is_err());
+}
+
+#[test]
+fn export_is_jsonl() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = AuditJournal::new(dir.path());
+    journal.record(entry("ops", "cluster/sync")).unwrap();
+    journal.record(entry("alice", "cluster/rotate")).unwrap();
+
+    let export = journal.export(&AuditFilter::default()).unwrap();
+    assert_eq!(export, fs::read_to_string(journal.path()).unwrap());
+    for line in export.lines() {
+        assert!(serde_json::from_str::<serde_json::Value>(line).unwrap().get("hash").is_some());
+    }
+}
+
+#[test]
+fn times_are_absolute_or_relative() {
+    assert_eq!(parse_time("2026-01-02T03:04:05Z").unwrap().to_rfc3339(), "2026-01-02T03:04:05+00:00");
+
+    let day_ago = parse_time("24h").unwrap();
+    assert!((Utc::now() - Duration::hours(24) - day_ago).num_seconds().abs() < 5);
+    assert!(parse_time("yesterday-ish").is_err());
+}
+
+#[test]
+fn minion_targets_are_described() {
+    assert_eq!(minion_target("", "", ""), "*");
+    assert_eq!(minion_target("web*", "os.family:linux", ""), "web* traits:os.family:linux");
+    assert_eq!(minion_target("web*", "", "30ab"), "id:30ab");
+}
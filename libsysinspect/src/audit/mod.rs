@@ -0,0 +1,440 @@
+//! Audit journal of operator actions on the master.
+//!
+//! Console commands, Web API calls and scheduled queries are appended to a
+//! JSONL file in the audit directory of the master, one record per line. Each
+//! record carries the hash of its predecessor and its own SHA-256 hash over
+//! the record content, so removing, reordering or editing a line breaks the
+//! chain and is reported by [`AuditJournal::verify`].
+//!
+//! The journal is append-only: records are never rewritten, and the file is
+//! itself the JSONL export for SIEM ingestion. The only exception is a final
+//! line torn by a crash during an append, which never got its newline. It is
+//! cut off when the journal is opened for the next append, and a `recovered`
+//! record marks the place. A complete line that cannot be read back is not
+//! touched: appends are refused until the journal is repaired.
+
+#[cfg(test)]
+#[path = "audit_ut.rs"]
+mod audit_ut;
+
+use chrono::{DateTime, Utc};
+use libcommon::SysinspectError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// File name of the journal within the audit directory
+pub const AUDIT_JOURNAL: &str = "journal.jsonl";
+
+/// Previous hash of the very first record
+pub const AUDIT_GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Actor of scheduled queries
+pub const AUDIT_SCHEDULER: &str = "scheduler";
+
+/// Actor and action of the record appended after a torn final line was cut off
+pub const AUDIT_RECOVERED: &str = "recovered";
+
+/// Serialised size of the records in one page of an export, well within a console response
+pub const AUDIT_PAGE_SIZE: usize = 128 * 1024;
+
+/// Console, Web API and scheduler append from different tasks of the same process
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+/// Where the action came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditChannel {
+    Console,
+    #[serde(rename = "webapi")]
+    WebApi,
+    Scheduler,
+
+    /// The journal itself
+    Journal,
+}
+
+impl Display for AuditChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditChannel::Console => write!(f, "console"),
+            AuditChannel::WebApi => write!(f, "webapi"),
+            AuditChannel::Scheduler => write!(f, "scheduler"),
+            AuditChannel::Journal => write!(f, "journal"),
+        }
+    }
+}
+
+/// How the action ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+
+    /// Rejected by authentication or access control
+    Denied,
+
+    /// Accepted, but failed
+    Failure,
+}
+
+impl Display for AuditOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditOutcome::Success => write!(f, "success"),
+            AuditOutcome::Denied => write!(f, "denied"),
+            AuditOutcome::Failure => write!(f, "failure"),
+        }
+    }
+}
+
+/// Action to be recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Who: console client name, Web API user, `token:<name>` or `scheduler`
+    pub actor: String,
+    pub channel: AuditChannel,
+
+    /// What: console command, Web API method and path, or scheduled task name
+    pub action: String,
+
+    /// What the action was applied on, e.g. minion selector or object
+    pub target: String,
+    pub outcome: AuditOutcome,
+
+    /// Error message or other result details
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(actor: &str, channel: AuditChannel, action: &str, target: &str, outcome: AuditOutcome) -> Self {
+        AuditEntry { actor: actor.to_string(), channel, action: action.to_string(), target: target.to_string(), outcome, detail: None }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        self.detail = if detail.is_empty() { None } else { Some(detail) };
+        self
+    }
+}
+
+/// Record of the journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the journal, starting at 1
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub channel: AuditChannel,
+    pub action: String,
+    pub target: String,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// Hash of the previous record, hex encoded
+    pub prev: String,
+
+    /// SHA-256 over the record with an empty hash, hex encoded
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// Hash of the record content, which is the record serialised with an empty hash
+    pub fn digest(&self) -> Result<String, SysinspectError> {
+        let mut unsealed = self.clone();
+        unsealed.hash.clear();
+        Ok(hex::encode(Sha256::digest(serde_json::to_vec(&unsealed)?)))
+    }
+}
+
+/// Selection of journal records. Empty filter selects everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+
+    /// Actor name or glob, e.g. `token:*`
+    pub actor: Option<String>,
+
+    /// Keep only the newest records
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, record: &AuditRecord, actor: Option<&glob::Pattern>) -> bool {
+        self.since.is_none_or(|t| record.timestamp >= t)
+            && self.until.is_none_or(|t| record.timestamp < t)
+            && actor.is_none_or(|p| p.matches(&record.actor))
+    }
+}
+
+/// Parse a point in time: RFC 3339 or a duration back from now, e.g. `24h` or `7d`
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, SysinspectError> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+
+    humantime::parse_duration(value)
+        .ok()
+        .and_then(|ago| chrono::Duration::from_std(ago).ok())
+        .and_then(|ago| Utc::now().checked_sub_signed(ago))
+        .ok_or_else(|| SysinspectError::InvalidQuery(format!("Invalid time \"{value}\", expected RFC 3339 or a duration such as 24h")))
+}
+
+/// Target of an action on minions: the minion id, or the hostname query narrowed by traits
+pub fn minion_target(query: &str, traits: &str, mid: &str) -> String {
+    if !mid.trim().is_empty() {
+        return format!("id:{}", mid.trim());
+    }
+
+    let query = if query.trim().is_empty() { "*" } else { query.trim() };
+    if traits.trim().is_empty() { query.to_string() } else { format!("{query} traits:{}", traits.trim()) }
+}
+
+/// Outcome of the chain verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditVerification {
+    /// Number of verified records
+    pub records: u64,
+
+    /// Hash of the last record, or the genesis hash of an empty journal
+    pub head: String,
+}
+
+/// Hash-chained journal stored in a directory
+pub struct AuditJournal {
+    root: PathBuf,
+}
+
+impl AuditJournal {
+    pub fn new(root: &Path) -> Self {
+        AuditJournal { root: root.to_path_buf() }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.root.join(AUDIT_JOURNAL)
+    }
+
+    /// Append the action to the journal
+    pub fn record(&self, entry: AuditEntry) -> Result<AuditRecord, SysinspectError> {
+        let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        fs::create_dir_all(&self.root)?;
+        let mut options = OpenOptions::new();
+        options.create(true).read(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(self.path())?;
+
+        let torn = Self::recover(&mut file)?;
+        let mut last = self.last().map_err(|err| {
+            SysinspectError::MasterGeneralError(format!(
+                "Audit journal {} ends with a broken record, refusing to append: {err}",
+                self.path().display()
+            ))
+        })?;
+        if let Some(torn) = torn {
+            log::warn!("Audit journal {} ended with a torn record, cut off {torn} bytes", self.path().display());
+            let entry = AuditEntry::new(AUDIT_RECOVERED, AuditChannel::Journal, AUDIT_RECOVERED, AUDIT_JOURNAL, AuditOutcome::Success)
+                .detail(format!("Cut off a torn record of {torn} bytes"));
+            last = Some(Self::append(&mut file, last, entry)?);
+        }
+
+        Self::append(&mut file, last, entry)
+    }
+
+    /// Append the action from async code: the lock and the sync of the journal run on the blocking pool
+    pub async fn submit(&self, entry: AuditEntry) -> Result<AuditRecord, SysinspectError> {
+        let journal = AuditJournal::new(&self.root);
+        tokio::task::spawn_blocking(move || journal.record(entry))
+            .await
+            .map_err(|err| SysinspectError::MasterGeneralError(format!("Audit journal append did not finish: {err}")))?
+    }
+
+    /// Chain the entry to the last record and write it out
+    fn append(file: &mut File, last: Option<AuditRecord>, entry: AuditEntry) -> Result<AuditRecord, SysinspectError> {
+        let mut record = AuditRecord {
+            seq: last.as_ref().map(|r| r.seq + 1).unwrap_or(1),
+            timestamp: Utc::now(),
+            actor: entry.actor,
+            channel: entry.channel,
+            action: entry.action,
+            target: entry.target,
+            outcome: entry.outcome,
+            detail: entry.detail,
+            prev: last.map(|r| r.hash).unwrap_or_else(|| AUDIT_GENESIS.to_string()),
+            hash: String::new(),
+        };
+        record.hash = record.digest()?;
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+
+        Ok(record)
+    }
+
+    /// Truncate a final line that is not terminated back to the last complete line.
+    /// Complete lines are left alone, even if they cannot be parsed. Returns the number of bytes cut off, if any.
+    fn recover(file: &mut File) -> Result<Option<u64>, SysinspectError> {
+        let len = file.metadata()?.len();
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut window = 4096.min(len);
+        let keep = loop {
+            let mut tail = vec![0; window as usize];
+            file.seek(SeekFrom::Start(len - window))?;
+            file.read_exact(&mut tail)?;
+            if tail.ends_with(b"\n") {
+                return Ok(None);
+            }
+            match tail.iter().rposition(|b| *b == b'\n') {
+                Some(pos) => break len - window + pos as u64 + 1,
+                None if window == len => break 0,
+                None => window = (window * 2).min(len),
+            }
+        };
+
+        file.set_len(keep)?;
+        file.sync_data()?;
+        Ok(Some(len - keep))
+    }
+
+    /// Last record, read from the end of the journal
+    fn last(&self) -> Result<Option<AuditRecord>, SysinspectError> {
+        let mut file = match File::open(self.path()) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let len = file.metadata()?.len();
+        let mut window = 4096.min(len);
+        loop {
+            let mut tail = vec![0; window as usize];
+            file.seek(SeekFrom::Start(len - window))?;
+            file.read_exact(&mut tail)?;
+            let body = tail.strip_suffix(b"\n").unwrap_or(&tail);
+            match body.iter().rposition(|b| *b == b'\n') {
+                Some(pos) => return Self::parse(&body[pos + 1..]),
+                None if window == len => return Self::parse(body),
+                None => window = (window * 2).min(len),
+            }
+        }
+    }
+
+    fn parse(line: &[u8]) -> Result<Option<AuditRecord>, SysinspectError> {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+
+        serde_json::from_slice(line).map(Some).map_err(|err| SysinspectError::DeserializationError(format!("Corrupt audit record: {err}")))
+    }
+
+    /// Iterate over all records, oldest first
+    fn scan(&self, mut visit: impl FnMut(AuditRecord) -> Result<(), SysinspectError>) -> Result<(), SysinspectError> {
+        let file = match File::open(self.path()) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        for line in BufReader::new(file).lines() {
+            if let Some(record) = Self::parse(line?.as_bytes())? {
+                visit(record)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records matching the filter, oldest first
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>, SysinspectError> {
+        let actor = match filter.actor.as_deref().filter(|a| !a.is_empty()) {
+            Some(actor) => {
+                Some(glob::Pattern::new(actor).map_err(|err| SysinspectError::InvalidQuery(format!("Invalid actor pattern \"{actor}\": {err}")))?)
+            }
+            None => None,
+        };
+
+        let mut out = Vec::new();
+        self.scan(|record| {
+            if filter.matches(&record, actor.as_ref()) {
+                out.push(record);
+            }
+            Ok(())
+        })?;
+        if let Some(limit) = filter.limit
+            && out.len() > limit
+        {
+            out.drain(..out.len() - limit);
+        }
+
+        Ok(out)
+    }
+
+    /// Records matching the filter that follow the record `after`, oldest first.
+    /// The page ends before the records exceed `size` bytes as JSON, but has at least one record.
+    pub fn page(&self, filter: &AuditFilter, after: u64, size: usize) -> Result<Vec<AuditRecord>, SysinspectError> {
+        let mut used = 0;
+        let mut out = Vec::new();
+        for record in self.query(filter)?.into_iter().filter(|r| r.seq > after) {
+            used += serde_json::to_vec(&record)?.len() + 1;
+            if used > size && !out.is_empty() {
+                break;
+            }
+            out.push(record);
+        }
+
+        Ok(out)
+    }
+
+    /// Records matching the filter as JSONL, one record per line
+    pub fn export(&self, filter: &AuditFilter) -> Result<String, SysinspectError> {
+        let mut out = String::new();
+        for record in self.query(filter)? {
+            out.push_str(&serde_json::to_string(&record)?);
+            out.push('\n');
+        }
+
+        Ok(out)
+    }
+
+    /// Walk the whole chain and check every link and hash
+    pub fn verify(&self) -> Result<AuditVerification, SysinspectError> {
+        let mut state = AuditVerification { records: 0, head: AUDIT_GENESIS.to_string() };
+        self.scan(|record| {
+            if record.seq != state.records + 1 {
+                return Err(SysinspectError::MasterGeneralError(format!(
+                    "Audit journal is broken at record {}: expected sequence {}",
+                    record.seq,
+                    state.records + 1
+                )));
+            }
+            if record.prev != state.head {
+                return Err(SysinspectError::MasterGeneralError(format!(
+                    "Audit journal is broken at record {}: previous record does not match",
+                    record.seq
+                )));
+            }
+            if record.digest()? != record.hash {
+                return Err(SysinspectError::MasterGeneralError(format!("Audit journal is broken at record {}: record was modified", record.seq)));
+            }
+            state.records = record.seq;
+            state.head = record.hash;
+            Ok(())
+        })?;
+
+        Ok(state)
+    }
+}
@@ -8,7 +8,7 @@ use libcommon::SysinspectError;
 use nix::libc;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Value, from_str, from_value};
-use std::{env, fs, os::unix::fs::PermissionsExt, path::PathBuf, time::Duration};
+use std::{collections::HashMap, env, fs, os::unix::fs::PermissionsExt, path::PathBuf, time::Duration};
 
 // Network
 // -------
@@ -88,6 +88,7 @@ pub static DEFAULT_MINION_MACHINE_ID_REL: &str = "machine-id";
 pub static CFG_PENDING_TASKS_ROOT: &str = "pending-tasks";
 pub static CFG_PENDING_COMMANDS_ROOT: &str = "pending-commands";
 pub static CFG_INBOUND_COMMANDS_ROOT: &str = "inbound-commands";
+pub static CFG_SENSORS_STATE_ROOT: &str = "sensors-state";
 
 pub static DEFAULT_DATASTORE_ROOT: &str = "/var/lib/sysinspect/datastore";
 
@@ -105,6 +106,10 @@ pub static DEFAULT_DATASTORE_ITEM_MAX_SIZE: u64 = 0x64 * 0x400 * 0x400;
 pub static CFG_MINION_KEYS: &str = "minion-keys";
 pub static CFG_MINION_REGISTRY: &str = "minion-registry";
 pub static CFG_API_KEYS: &str = "webapi-keys";
+pub static CFG_RBAC_POLICY: &str = "rbac.yaml";
+pub static CFG_AUDIT: &str = "audit";
+pub static CFG_MODEL_VERSIONS: &str = "model-versions";
+pub static CFG_PLACEMENT_LOG: &str = "placement.log";
 pub static CFG_FILESERVER_ROOT: &str = "data";
 pub static CFG_DB: &str = "registry";
 
@@ -174,6 +179,14 @@ pub static CFG_HISTORY_AGE: usize = 86400; // 1 day in seconds
 // CMDB refresh age
 pub static DEFAULT_CMDB_UPDATE_AGE: u64 = 7 * 24 * 60 * 60;
 
+// Master high availability
+pub static DEFAULT_HA_LEASE_TTL: u64 = 15; // seconds
+
+// Queued commands for offline minions
+pub static DEFAULT_COMMANDS_TTL: u64 = 24 * 60 * 60; // seconds
+pub static DEFAULT_COMMANDS_HISTORY: u64 = 7 * 24 * 60 * 60; // seconds
+pub static CFG_HA_STATE: &str = "ha-state";
+
 /// Get a default location of a logfiles
 fn _logfile_path() -> PathBuf {
     let mut home = String::from("");
@@ -471,6 +484,14 @@ pub struct MinionConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     modules_autosync_startup: Option<bool>,
 
+    /// Interval of recomputing traits and pushing changed keys to the master.
+    /// Refreshes can also be requested by the `traits-refresh` event handler.
+    ///
+    /// Default: none (traits are sent only on connect and sync)
+    #[serde(rename = "traits.refresh", default, with = "humantime_serde::option")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    traits_refresh: Option<Duration>,
+
     /// IP address of Master
     #[serde(rename = "master.ip")]
     #[serde(skip_serializing_if = "String::is_empty")]
@@ -486,6 +507,12 @@ pub struct MinionConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     master_fileserver_port: Option<u32>,
 
+    /// IP addresses of standby masters, tried in order when the current master
+    /// is unreachable. They use the same ports as the primary master.
+    #[serde(rename = "master.standby")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    master_standby: Option<Vec<String>>,
+
     /// Offline mode determines whether the minion continues local execution
     /// when the master is unreachable.
     ///
@@ -644,6 +671,11 @@ impl MinionConfig {
         self.tmp_path = Some(p.to_string());
     }
 
+    /// Set standby master IPs
+    pub fn set_master_standby(&mut self, ips: Vec<String>) {
+        self.master_standby = Some(ips);
+    }
+
     /// Return master addr
     pub fn master(&self) -> String {
         format!("{}:{}", self.master_ip, self.master_port.unwrap_or(DEFAULT_PORT))
@@ -654,6 +686,27 @@ impl MinionConfig {
         format!("{}:{}", self.master_ip, self.master_fileserver_port.unwrap_or(DEFAULT_FILESERVER_PORT))
     }
 
+    /// Return IPs of all configured masters: the primary first, then standby ones
+    pub fn masters(&self) -> Vec<String> {
+        let mut ips = vec![self.master_ip.clone()];
+        for ip in self.master_standby.clone().unwrap_or_default() {
+            let ip = ip.trim().to_string();
+            if !ip.is_empty() && !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+        ips
+    }
+
+    /// Return a copy of the configuration, pointing to the Nth configured master
+    /// (see `masters()`). Index wraps around.
+    pub fn with_master(&self, idx: usize) -> MinionConfig {
+        let mut cfg = self.clone();
+        let ips = self.masters();
+        cfg.master_ip = ips[idx % ips.len()].clone();
+        cfg
+    }
+
     /// Get minion root directory
     pub fn root_dir(&self) -> PathBuf {
         PathBuf::from(self.root.clone().unwrap_or(DEFAULT_SYSINSPECT_ROOT.to_string()))
@@ -759,6 +812,11 @@ impl MinionConfig {
         self.root_dir().join(CFG_PENDING_TASKS_ROOT)
     }
 
+    /// Root directory for sensor state kept across restarts, e.g. log offsets.
+    pub fn sensors_state_dir(&self) -> PathBuf {
+        self.root_dir().join(CFG_SENSORS_STATE_ROOT)
+    }
+
     /// Root directory for persisted inbound command dedup/acceptance state.
     pub fn inbound_commands_dir(&self) -> PathBuf {
         self.managed_db_dir().join(CFG_INBOUND_COMMANDS_ROOT)
@@ -846,6 +904,16 @@ impl MinionConfig {
         self.modules_autosync_startup.unwrap_or(true)
     }
 
+    /// Return traits refresh interval, if periodic refresh is enabled
+    pub fn traits_refresh(&self) -> Option<Duration> {
+        self.traits_refresh.filter(|d| !d.is_zero())
+    }
+
+    /// Set traits refresh interval
+    pub fn set_traits_refresh(&mut self, interval: Option<Duration>) {
+        self.traits_refresh = interval;
+    }
+
     /// Set autosync mode
     pub fn set_autosync(&mut self, mode: &str) {
         self.modules_check = Some(mode.to_string());
@@ -978,6 +1046,8 @@ pub struct ClusteredMinion {
     hostname: String,
     traits: Option<IndexMap<String, Value>>,
     nodes: Vec<ClusteredMinionScope>,
+    #[serde(default)]
+    placement: ClusterPlacement,
 }
 
 impl ClusteredMinion {
@@ -1008,6 +1078,123 @@ impl ClusteredMinion {
     pub fn traits(&self) -> Option<&IndexMap<String, Value>> {
         self.traits.as_ref()
     }
+
+    /// Get clustered minion placement policy
+    pub fn placement(&self) -> &ClusterPlacement {
+        &self.placement
+    }
+}
+
+/// Strategy used to pick physical member(s) of a clustered minion for a task
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClusterPlacementStrategy {
+    /// Lowest disk write rate, then fewest running tasks
+    #[default]
+    LeastIo,
+    /// Rotate through the alive members
+    RoundRobin,
+    /// Lowest CPU usage or load average (see `metric`)
+    LeastLoaded,
+    /// Consistent hashing of a context key, so the same key sticks to the same member
+    Hashed,
+    /// Prefer members matching the affinity traits
+    Affinity,
+    /// Every alive member
+    All,
+}
+
+impl std::fmt::Display for ClusterPlacementStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ClusterPlacementStrategy::LeastIo => "least-io",
+            ClusterPlacementStrategy::RoundRobin => "round-robin",
+            ClusterPlacementStrategy::LeastLoaded => "least-loaded",
+            ClusterPlacementStrategy::Hashed => "hashed",
+            ClusterPlacementStrategy::Affinity => "affinity",
+            ClusterPlacementStrategy::All => "all",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Load metric used by the `least-loaded` placement strategy
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterLoadMetric {
+    #[default]
+    Cpu,
+    Load,
+}
+
+/// Placement policy of a clustered minion
+///
+/// Example:
+///
+/// ```yaml
+/// placement:
+///   strategy: hashed
+///   key: tenant
+/// ```
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ClusterPlacement {
+    #[serde(default)]
+    strategy: ClusterPlacementStrategy,
+
+    // Metric for the "least-loaded" strategy
+    #[serde(default)]
+    metric: ClusterLoadMetric,
+
+    // Context key for the "hashed" strategy
+    key: Option<String>,
+
+    // Preferred member traits for the "affinity" strategy
+    traits: Option<IndexMap<String, Value>>,
+}
+
+impl ClusterPlacement {
+    /// Create a placement policy with the given strategy
+    pub fn new(strategy: ClusterPlacementStrategy) -> Self {
+        ClusterPlacement { strategy, ..Default::default() }
+    }
+
+    /// Get placement strategy
+    pub fn strategy(&self) -> ClusterPlacementStrategy {
+        self.strategy
+    }
+
+    /// Get load metric for the least-loaded strategy
+    pub fn metric(&self) -> ClusterLoadMetric {
+        self.metric
+    }
+
+    /// Get context key for the hashed strategy
+    pub fn key(&self) -> Option<&String> {
+        self.key.as_ref()
+    }
+
+    /// Get affinity traits
+    pub fn traits(&self) -> Option<&IndexMap<String, Value>> {
+        self.traits.as_ref()
+    }
+
+    /// Set load metric
+    pub fn set_metric(mut self, metric: ClusterLoadMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Set context key
+    pub fn set_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Set affinity traits
+    pub fn set_traits(mut self, traits: IndexMap<String, Value>) -> Self {
+        self.traits = Some(traits);
+        self
+    }
 }
 
 /// Definition of a clustered minion scope
@@ -1278,6 +1465,30 @@ pub struct MasterConfig {
     // Clustered minions configuration
     cluster: Option<Vec<ClusteredMinion>>,
 
+    // Time to live of commands queued for offline minions. Default: 24h
+    #[serde(rename = "commands.ttl", default, with = "humantime_serde::option")]
+    commands_ttl: Option<Duration>,
+
+    // How long receipts of finished commands are kept. Default: 7d
+    #[serde(rename = "commands.history", default, with = "humantime_serde::option")]
+    commands_history: Option<Duration>,
+
+    // Active/standby: lease file shared by all masters of the pair. Enables HA when set.
+    #[serde(rename = "ha.lease")]
+    ha_lease: Option<String>,
+
+    // Active/standby: lease time to live. Default: 15s
+    #[serde(rename = "ha.lease.ttl", default, with = "humantime_serde::option")]
+    ha_lease_ttl: Option<Duration>,
+
+    // Active/standby: shared directory of the replicated state. Default: "ha-state" next to the lease file.
+    #[serde(rename = "ha.state")]
+    ha_state: Option<String>,
+
+    // Active/standby: name of this master in the lease. Default: random per process.
+    #[serde(rename = "ha.node")]
+    ha_node: Option<String>,
+
     // Datastore configuration for storing artifacts, usually files
     #[serde(rename = "datastore.path")]
     datastore_path: Option<String>,
@@ -1293,6 +1504,10 @@ pub struct MasterConfig {
     // Max size of a single item in the datastore in bytes. Default: unlimited
     #[serde(rename = "datastore.item-max-size", default, deserialize_with = "libcommon::humaninput::h2bytes")]
     datastore_item_max_size: Option<u64>,
+
+    // Datastore namespaces with own quota, retention and minion read access
+    #[serde(rename = "datastore.namespaces", default)]
+    datastore_namespaces: IndexMap<String, DatastoreNamespace>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -1320,6 +1535,64 @@ impl HopstartConfig {
     }
 }
 
+/// Datastore namespace: own quota and retention, and which minions may read it
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct DatastoreNamespace {
+    #[serde(rename = "max-size", default, deserialize_with = "libcommon::humaninput::h2bytes")]
+    max_size: Option<u64>,
+
+    #[serde(rename = "max-age", default, with = "humantime_serde::option")]
+    max_age: Option<Duration>,
+
+    read: Option<DatastoreReadAcl>,
+}
+
+impl DatastoreNamespace {
+    /// Max total size of the namespace in bytes. Default: only the datastore limit applies
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Max age of the items of the namespace. Default: the datastore max age
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Can a minion with these traits read the namespace? Without a `read` rule every minion can.
+    pub fn readable_by(&self, traits: &HashMap<String, serde_json::Value>) -> bool {
+        self.read.as_ref().is_none_or(|acl| acl.allows(traits))
+    }
+}
+
+/// Minions allowed to read a datastore namespace: those matching the traits query or having one of the profiles
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct DatastoreReadAcl {
+    traits: Option<String>,
+
+    #[serde(default)]
+    profiles: Vec<String>,
+}
+
+impl DatastoreReadAcl {
+    pub fn allows(&self, traits: &HashMap<String, serde_json::Value>) -> bool {
+        let profiles = traits.get("minion.profile").map(crate::traits::normalized_profiles).unwrap_or_default();
+        if self.profiles.iter().any(|p| profiles.contains(p)) {
+            return true;
+        }
+
+        let Some(query) = self.traits.as_deref().filter(|q| !q.trim().is_empty()) else {
+            return false;
+        };
+        match crate::traits::parse_traits_query(query).and_then(crate::traits::to_typed_query) {
+            Ok(query) => query.into_iter().any(|group| group.into_iter().flatten().all(|(key, expected)| traits.get(&key) == Some(&expected))),
+            Err(err) => {
+                log::error!("Unable to parse datastore read traits query '{query}': {err}");
+                false
+            }
+        }
+    }
+}
+
 impl MasterConfig {
     pub fn new(p: PathBuf) -> Result<MasterConfig, SysinspectError> {
         let cp = p.as_os_str().to_str().unwrap_or_default();
@@ -1380,6 +1653,39 @@ impl MasterConfig {
         HopstartConfig { batch: self.hopstart_batch, network_forward: self.hopstart_network_forward, on_start: self.hopstart_on_start }
     }
 
+    /// Get HA lease file. If not set, the master runs standalone.
+    pub fn ha_lease(&self) -> Option<PathBuf> {
+        self.ha_lease.as_ref().map(PathBuf::from)
+    }
+
+    /// Get default time to live of commands queued for offline minions
+    pub fn commands_ttl(&self) -> Duration {
+        self.commands_ttl.unwrap_or_else(|| Duration::from_secs(DEFAULT_COMMANDS_TTL))
+    }
+
+    /// Get how long receipts of finished commands are kept
+    pub fn commands_history(&self) -> Duration {
+        self.commands_history.unwrap_or_else(|| Duration::from_secs(DEFAULT_COMMANDS_HISTORY))
+    }
+
+    /// Get HA lease time to live
+    pub fn ha_lease_ttl(&self) -> Duration {
+        self.ha_lease_ttl.unwrap_or_else(|| Duration::from_secs(DEFAULT_HA_LEASE_TTL))
+    }
+
+    /// Get HA replicated state directory
+    pub fn ha_state(&self) -> Option<PathBuf> {
+        if let Some(p) = &self.ha_state {
+            return Some(PathBuf::from(p));
+        }
+        self.ha_lease().map(|p| p.parent().map(|d| d.to_path_buf()).unwrap_or_default().join(CFG_HA_STATE))
+    }
+
+    /// Get HA node name of this master
+    pub fn ha_node(&self) -> Option<String> {
+        self.ha_node.clone()
+    }
+
     /// Get OTLP configuration
     pub fn otlp_cfg(&self) -> TelemetryConfig {
         if let Some(cfg) = &self.telemetry {
@@ -1595,6 +1901,26 @@ impl MasterConfig {
         self.root_dir().join(CFG_CONSOLE_KEYS)
     }
 
+    /// Access control policy of the Web API and console
+    pub fn rbac_policy(&self) -> PathBuf {
+        self.root_dir().join(CFG_RBAC_POLICY)
+    }
+
+    /// Audit journal of operator actions
+    pub fn audit_root(&self) -> PathBuf {
+        self.root_dir().join(CFG_AUDIT)
+    }
+
+    /// Uploaded versions of the models, see [`crate::mdescr::versions`]
+    pub fn model_versions_root(&self) -> PathBuf {
+        self.root_dir().join(CFG_MODEL_VERSIONS)
+    }
+
+    /// Recent placement decisions of the virtual minions
+    pub fn placement_log(&self) -> PathBuf {
+        self.root_dir().join(CFG_PLACEMENT_LOG)
+    }
+
     /// Root for managed secure transport metadata on the master.
     pub fn transport_root(&self) -> PathBuf {
         self.root_dir().join(CFG_TRANSPORT_ROOT)
@@ -1685,6 +2011,17 @@ impl MasterConfig {
     pub fn datastore_item_max_size(&self) -> u64 {
         self.datastore_item_max_size.unwrap_or(DEFAULT_DATASTORE_ITEM_MAX_SIZE)
     }
+
+    /// Get datastore namespaces with own quota, retention or read access
+    pub fn datastore_namespaces(&self) -> &IndexMap<String, DatastoreNamespace> {
+        &self.datastore_namespaces
+    }
+
+    /// Can a minion with these traits read items of the namespace? The default namespace and
+    /// namespaces without configuration are readable by every minion.
+    pub fn datastore_readable(&self, namespace: Option<&str>, traits: &HashMap<String, serde_json::Value>) -> bool {
+        namespace.and_then(|ns| self.datastore_namespaces.get(ns)).is_none_or(|ns| ns.readable_by(traits))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
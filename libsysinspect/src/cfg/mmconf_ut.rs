@@ -1,6 +1,7 @@
 use super::mmconf::{
-    CFG_TRANSPORT_MASTER, CFG_TRANSPORT_MINIONS, CFG_TRANSPORT_ROOT, CFG_TRANSPORT_STATE, DEFAULT_CMDB_UPDATE_AGE, DEFAULT_CONSOLE_PORT,
-    MasterConfig, MinionConfig, MinionOfflineMode, MinionPerformanceProfile, OfflineBacklogPolicy,
+    CFG_TRANSPORT_MASTER, CFG_TRANSPORT_MINIONS, CFG_TRANSPORT_ROOT, CFG_TRANSPORT_STATE, ClusterLoadMetric, ClusterPlacementStrategy,
+    DEFAULT_CMDB_UPDATE_AGE, DEFAULT_CONSOLE_PORT, DEFAULT_HA_LEASE_TTL, MasterConfig, MinionConfig, MinionOfflineMode, MinionPerformanceProfile,
+    OfflineBacklogPolicy,
 };
 use std::{
     fs,
@@ -84,6 +85,28 @@ fn master_hopstart_accepts_config_override() {
     assert!(cfg.hopstart().on_start());
 }
 
+#[test]
+fn master_ha_is_disabled_when_not_configured() {
+    let cfg = MasterConfig::new(write_master_cfg("config:\n  master:\n    fileserver.models: []\n")).unwrap();
+
+    assert!(cfg.ha_lease().is_none());
+    assert!(cfg.ha_state().is_none());
+    assert_eq!(cfg.ha_lease_ttl().as_secs(), DEFAULT_HA_LEASE_TTL);
+}
+
+#[test]
+fn master_ha_state_defaults_next_to_lease() {
+    let cfg = MasterConfig::new(write_master_cfg(
+        "config:\n  master:\n    fileserver.models: []\n    ha.lease: /mnt/shared/sysmaster.lease\n    ha.lease.ttl: 5s\n    ha.node: master-a\n",
+    ))
+    .unwrap();
+
+    assert_eq!(cfg.ha_lease(), Some(std::path::PathBuf::from("/mnt/shared/sysmaster.lease")));
+    assert_eq!(cfg.ha_state(), Some(std::path::PathBuf::from("/mnt/shared/ha-state")));
+    assert_eq!(cfg.ha_lease_ttl().as_secs(), 5);
+    assert_eq!(cfg.ha_node().as_deref(), Some("master-a"));
+}
+
 #[test]
 fn master_transport_paths_are_under_managed_transport_root() {
     let cfg = MasterConfig::new(write_master_cfg("config:\n  master:\n    fileserver.models: []\n")).unwrap();
@@ -196,6 +219,29 @@ fn minion_custom_layout_marker_and_pending_tasks_follow_root() {
 
     assert_eq!(cfg.local_marker_path(), std::path::PathBuf::from("/srv/sysinspect/.local"));
     assert_eq!(cfg.pending_tasks_dir(), std::path::PathBuf::from("/srv/sysinspect/pending-tasks"));
+    assert_eq!(cfg.sensors_state_dir(), std::path::PathBuf::from("/srv/sysinspect/sensors-state"));
+}
+
+#[test]
+fn minion_masters_lists_primary_then_standby() {
+    let mut cfg = MinionConfig::default();
+    cfg.set_master_ip("10.0.0.1");
+    cfg.set_master_standby(vec!["10.0.0.2".to_string(), "10.0.0.1".to_string(), " ".to_string()]);
+
+    assert_eq!(cfg.masters(), vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()]);
+    assert!(cfg.with_master(1).master().starts_with("10.0.0.2:"));
+    assert!(cfg.with_master(2).master().starts_with("10.0.0.1:"));
+}
+
+#[test]
+fn minion_traits_refresh_is_disabled_unless_configured() {
+    assert!(MinionConfig::default().traits_refresh().is_none());
+
+    let cfg = MinionConfig::new(write_master_cfg("config:\n  minion:\n    master.ip: ''\n    traits.refresh: 15m\n")).unwrap();
+    assert_eq!(cfg.traits_refresh(), Some(std::time::Duration::from_secs(900)));
+
+    let cfg = MinionConfig::new(write_master_cfg("config:\n  minion:\n    master.ip: ''\n    traits.refresh: 0s\n")).unwrap();
+    assert!(cfg.traits_refresh().is_none());
 }
 
 #[test]
@@ -333,3 +379,54 @@ fn minion_backlog_policy_roundtrips_through_setter() {
     cfg.set_backlog_policy(OfflineBacklogPolicy::Evict);
     assert_eq!(cfg.backlog_policy(), OfflineBacklogPolicy::Evict);
 }
+
+#[test]
+fn master_cluster_placement_defaults_to_least_io() {
+    let cfg = MasterConfig::new(write_master_cfg(
+        "config:\n  master:\n    fileserver.models: []\n    cluster:\n      - id: vm1\n        hostname: vm1\n        nodes:\n          - query: \"a*\"\n          - query: \"b*\"\n",
+    ))
+    .unwrap();
+
+    assert_eq!(cfg.cluster().len(), 1);
+    assert_eq!(cfg.cluster()[0].placement().strategy(), ClusterPlacementStrategy::LeastIo);
+    assert!(cfg.cluster()[0].placement().key().is_none());
+}
+
+#[test]
+fn master_cluster_placement_accepts_strategy_override() {
+    let cfg = MasterConfig::new(write_master_cfg(
+        "config:\n  master:\n    fileserver.models: []\n    cluster:\n      - id: vm1\n        hostname: vm1\n        placement:\n          strategy: least-loaded\n          metric: load\n        nodes:\n          - query: \"a*\"\n          - query: \"b*\"\n      - id: vm2\n        hostname: vm2\n        placement:\n          strategy: hashed\n          key: tenant\n        nodes:\n          - query: \"c*\"\n          - query: \"d*\"\n",
+    ))
+    .unwrap();
+
+    let cluster = cfg.cluster();
+    assert_eq!(cluster[0].placement().strategy(), ClusterPlacementStrategy::LeastLoaded);
+    assert_eq!(cluster[0].placement().metric(), ClusterLoadMetric::Load);
+    assert_eq!(cluster[1].placement().strategy(), ClusterPlacementStrategy::Hashed);
+    assert_eq!(cluster[1].placement().key().map(String::as_str), Some("tenant"));
+}
+
+#[test]
+fn master_datastore_namespaces_limit_minion_reads() {
+    let cfg = MasterConfig::new(write_master_cfg(
+        "config:\n  master:\n    fileserver.models: []\n    datastore.namespaces:\n      web:\n        max-size: 2MB\n        max-age: 7d\n        read:\n          traits: \"system.os.name:Ubuntu and rack:r12\"\n          profiles: [edge]\n      shared:\n        max-size: 1KB\n",
+    ))
+    .unwrap();
+
+    let web = &cfg.datastore_namespaces()["web"];
+    assert_eq!(web.max_size(), Some(2_000_000));
+    assert_eq!(web.max_age().map(|d| d.as_secs()), Some(7 * 86_400));
+
+    let traits =
+        |pairs: &[(&str, serde_json::Value)]| pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect::<std::collections::HashMap<_, _>>();
+    let ubuntu = traits(&[("system.os.name", "Ubuntu".into()), ("rack", "r12".into())]);
+    let edge = traits(&[("system.os.name", "Debian".into()), ("minion.profile", serde_json::json!(["default", "edge"]))]);
+    let other = traits(&[("system.os.name", "Ubuntu".into()), ("rack", "r13".into())]);
+
+    assert!(cfg.datastore_readable(Some("web"), &ubuntu));
+    assert!(cfg.datastore_readable(Some("web"), &edge));
+    assert!(!cfg.datastore_readable(Some("web"), &other));
+    assert!(cfg.datastore_readable(Some("shared"), &other));
+    assert!(cfg.datastore_readable(Some("unknown"), &other));
+    assert!(cfg.datastore_readable(None, &other));
+}
@@ -33,6 +33,8 @@ fn console_sealed_roundtrips_payload() {
         traits: "".to_string(),
         mid: "".to_string(),
         context: "{\"op\":\"reset\",\"traits\":{}}".to_string(),
+        ttl: None,
+        supersede: None,
     };
     let key = secretbox::gen_key();
     let sealed = ConsoleSealed::seal(&payload, &key).unwrap();
@@ -3,6 +3,7 @@
 use base64::{Engine, engine::general_purpose::STANDARD};
 use chrono::{DateTime, Utc};
 use libcommon::SysinspectError;
+use libsysproto::payload::SensorHealth;
 use rsa::{RsaPrivateKey, RsaPublicKey};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
@@ -14,7 +15,10 @@ use std::{
 };
 
 use crate::{
+    audit::{AuditRecord, AuditVerification},
     cfg::mmconf::{CFG_CONSOLE_KEY_PRI, CFG_CONSOLE_KEY_PUB, MasterConfig},
+    mdescr::versions::{ModelDiff, ModelVersion, ModelVersions},
+    rbac::tokens::ApiToken,
     rsa::keys::{
         RsaKey::{Private, Public},
         decrypt, encrypt, key_from_file, key_to_file, keygen, sign_data, to_pem, verify_sign,
@@ -71,6 +75,12 @@ pub struct ConsoleQuery {
     pub mid: String,
     /// Optional JSON-encoded context payload.
     pub context: String,
+    /// Seconds a command queued for offline minions stays deliverable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+    /// Queued commands with the same key are replaced by this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersede: Option<String>,
 }
 
 /// Structured console response returned by `sysmaster`.
@@ -119,6 +129,14 @@ pub struct ConsoleMinionProcessSignalRequest {
     pub signal: i32,
 }
 
+/// Request parameters for taking a new file integrity baseline on a minion.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ConsoleMinionIntegrityRebaselineRequest {
+    /// Id of the `sys.integrity` sensor to rebaseline; all of them if not set.
+    #[serde(default)]
+    pub sensor: Option<String>,
+}
+
 /// Request parameters for a minion self-upgrade via the master fileserver.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsoleMinionUpgradeSelfRequest {
@@ -130,10 +148,22 @@ pub struct ConsoleMinionUpgradeSelfRequest {
     pub version: String,
 }
 
+/// Request parameters for an orchestrated minion reboot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsoleMinionRebootRequest {
+    /// Seconds to wait for running cycles to finish and the journal to be delivered.
+    #[serde(default = "default_reboot_drain_timeout")]
+    pub drain_timeout: u64,
+}
+
 fn default_top_process_limit() -> usize {
     24
 }
 
+fn default_reboot_drain_timeout() -> u64 {
+    60
+}
+
 /// Snapshot of one selected raw minion logfile.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ConsoleMinionLogSnapshot {
@@ -373,6 +403,133 @@ pub enum ConsolePayload {
         #[serde(default)]
         items: Vec<String>,
     },
+    /// Commands queued for minions with their delivery and execution receipts.
+    QueuedCommands {
+        /// One row per queued command and minion.
+        rows: Vec<ConsoleQueuedCommandRow>,
+    },
+    /// Time-stamped trait changes recorded in the master CMDB.
+    TraitHistory {
+        /// One row per changed trait, oldest first.
+        rows: Vec<ConsoleTraitChangeRow>,
+    },
+    /// Web API tokens known to the master.
+    ApiTokens {
+        /// One row per token, sorted by name.
+        rows: Vec<ConsoleApiTokenRow>,
+    },
+    /// Newly created Web API token.
+    ApiTokenCreated {
+        /// Stored token record.
+        row: ConsoleApiTokenRow,
+        /// The token itself. The master keeps only its hash.
+        token: String,
+    },
+    /// Records of the audit journal, oldest first.
+    AuditRecords {
+        /// One row per recorded operator action.
+        rows: Vec<AuditRecord>,
+    },
+    /// Result of the audit journal chain verification.
+    AuditVerified {
+        /// Number of verified records and the hash of the last one.
+        verification: AuditVerification,
+    },
+    /// Stored versions of a model.
+    ModelVersions {
+        /// Versions, oldest first, and the active one.
+        versions: ModelVersions,
+    },
+    /// Model version activated by an upload or a rollback.
+    ModelVersionActivated {
+        /// Model name.
+        model: String,
+        /// The now active version.
+        version: ModelVersion,
+    },
+    /// Changes between two versions of a model.
+    ModelDiff {
+        /// Changed files with their diff lines.
+        diff: ModelDiff,
+    },
+}
+
+/// One Web API token as stored by the master, without its secret.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsoleApiTokenRow {
+    /// Token name.
+    pub name: String,
+    /// Who created the token, e.g. `user alice` or `console ci`.
+    pub owner: String,
+    /// Granted scopes.
+    pub scopes: Vec<String>,
+    /// When the token was created.
+    pub created: DateTime<Utc>,
+    /// When the token expires, if ever.
+    pub expires: Option<DateTime<Utc>>,
+    /// When the token was last used.
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl From<ApiToken> for ConsoleApiTokenRow {
+    fn from(token: ApiToken) -> Self {
+        ConsoleApiTokenRow {
+            name: token.name,
+            owner: token.owner.to_string(),
+            scopes: token.scopes.iter().map(|s| s.to_string()).collect(),
+            created: token.created,
+            expires: token.expires,
+            last_used: token.last_used,
+        }
+    }
+}
+
+/// One trait change of a minion as recorded by the master.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsoleTraitChangeRow {
+    /// Minion system id.
+    pub minion_id: String,
+    /// Preferred host label of the minion.
+    pub host: String,
+    /// Trait key.
+    pub key: String,
+    /// Value before the change, missing if the trait appeared.
+    pub old: Option<Value>,
+    /// Value after the change, missing if the trait disappeared.
+    pub new: Option<Value>,
+    /// When the master received the change.
+    pub changed_at: DateTime<Utc>,
+}
+
+/// One command queued by the master for a minion.
+///
+/// Commands are queued for minions that were offline when the command was
+/// issued and replayed once they connect. The row carries the receipts the
+/// master collected for it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsoleQueuedCommandRow {
+    /// Queue entry id.
+    pub id: u64,
+    /// Target minion system id.
+    pub minion_id: String,
+    /// Cycle id of the command.
+    pub cycle_id: String,
+    /// Model or command URI.
+    pub scheme: String,
+    /// Queue state: pending, replayed, delivered, executed, failed, expired or superseded.
+    pub state: String,
+    /// Supersede key, if any.
+    pub supersede: Option<String>,
+    /// When the command was queued.
+    pub enqueued_at: DateTime<Utc>,
+    /// When the command stops being deliverable, if ever.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Delivery receipt.
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Execution receipt, or when the command was expired or superseded.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Execution error reported by the minion.
+    pub error: Option<String>,
 }
 
 /// One online-minion summary row returned by the master.
@@ -418,6 +575,9 @@ pub struct ConsoleOnlineMinionRow {
     /// Kernel release reported by minion traits, if present.
     #[serde(default)]
     pub kernel: String,
+    /// Sensor health reported with the last pong of the minion.
+    #[serde(default)]
+    pub sensors: Vec<SensorHealth>,
 }
 
 /// One transport-status row returned by the master.
@@ -723,24 +883,32 @@ impl ConsoleSealed {
 /// client key configured in the master config or one of the extra keys stored
 /// in the console keys directory.
 pub fn authorised_console_client(cfg: &MasterConfig, client_pem: &str) -> Result<bool, SysinspectError> {
+    Ok(console_client_name(cfg, client_pem)?.is_some())
+}
+
+/// Name of an authorised console client, as used by the access control bindings.
+///
+/// The master's own console key is named `console`, keys from the console keys
+/// directory are named after their file, without the extension.
+pub fn console_client_name(cfg: &MasterConfig, client_pem: &str) -> Result<Option<String>, SysinspectError> {
     let client_pem = client_pem.trim();
     if cfg.console_pubkey().exists() && fs::read_to_string(cfg.console_pubkey()).map_err(SysinspectError::IoErr)?.trim() == client_pem {
-        return Ok(true);
+        return Ok(Some("console".to_string()));
     }
 
     let root = cfg.console_keys_root();
     if !root.exists() {
-        return Ok(false);
+        return Ok(None);
     }
 
     for entry in fs::read_dir(root).map_err(SysinspectError::IoErr)? {
         let path = entry.map_err(SysinspectError::IoErr)?.path();
         if path.is_file() && fs::read_to_string(&path).map_err(SysinspectError::IoErr)?.trim() == client_pem {
-            return Ok(true);
+            return Ok(Some(path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()));
         }
     }
 
-    Ok(false)
+    Ok(None)
 }
 
 /// Build a fully bootstrapped encrypted console request envelope for the given query.
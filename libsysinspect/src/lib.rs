@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod cfg;
 pub mod console;
 pub mod context;
@@ -6,6 +7,7 @@ pub mod intp;
 pub mod journal;
 pub mod logger;
 pub mod mdescr;
+pub mod rbac;
 pub mod reactor;
 pub mod rsa;
 pub mod tmpl;
@@ -0,0 +1,67 @@
+//! Datastore items the models still need.
+//!
+//! `cfg.resource` actions pull items from the master datastore by their logical
+//! name (`src`), or all items under a name prefix with `sync-dir`. The datastore
+//! garbage collection must not remove these while the models are active.
+
+use super::browser::ModelBrowser;
+use crate::cfg::mmconf::MinionConfig;
+use libcommon::SysinspectError;
+use std::{collections::BTreeSet, path::Path, sync::Arc};
+
+/// Module of the actions referring to datastore items
+const RESOURCE_MODULE: &str = "cfg.resource";
+
+/// A datastore item, or all items under a name prefix, pulled by a `cfg.resource` action
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DataRef {
+    /// Namespace, `None` for the default one
+    pub namespace: Option<String>,
+    /// The namespace comes from the context, so it is the part before the first variable,
+    /// and the item may be in any namespace starting with it
+    pub namespace_prefix: bool,
+    /// Logical name of the item, or the name prefix
+    pub src: String,
+    pub prefix: bool,
+}
+
+/// Collect the datastore items pulled by the `cfg.resource` actions of the models.
+///
+/// A name with context variables, e.g. `/$(host)/authorized_keys`, refers to
+/// everything under the part before the first variable, the same goes for the
+/// namespace. A model that cannot be read is an error: its items are unknown,
+/// so nothing may be collected.
+pub fn data_refs(models_root: &Path, models: &[String]) -> Result<BTreeSet<DataRef>, SysinspectError> {
+    let mut refs = BTreeSet::new();
+    for name in models {
+        let model = ModelBrowser::load(Arc::new(MinionConfig::default()), &models_root.join(name))
+            .and_then(|m| m.summarize())
+            .map_err(|err| SysinspectError::ModelDSLError(format!("Unable to read datastore references of model {name}: {err}")))?;
+
+        for action in model.actions.iter().filter(|a| a.module == RESOURCE_MODULE) {
+            for state in &action.states {
+                if !state.opts.iter().any(|o| o == "pull" || o == "sync-dir") {
+                    continue;
+                }
+                let arg = |key: &str| state.args.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+                let Some(src) = arg("src").filter(|s| !s.is_empty()) else {
+                    continue;
+                };
+                let (namespace, namespace_prefix) = match arg("namespace").filter(|ns| !ns.is_empty()) {
+                    Some(ns) => match ns.split_once("$(") {
+                        Some((head, _)) => (Some(head.to_string()), true),
+                        None => (Some(ns), false),
+                    },
+                    None => (None, false),
+                };
+
+                let sync_dir = state.opts.iter().any(|o| o == "sync-dir");
+                refs.insert(match src.split_once("$(") {
+                    Some((head, _)) => DataRef { namespace, namespace_prefix, src: head.to_string(), prefix: true },
+                    None => DataRef { namespace, namespace_prefix, src, prefix: sync_dir },
+                });
+            }
+        }
+    }
+    Ok(refs)
+}
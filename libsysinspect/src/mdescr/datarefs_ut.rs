@@ -0,0 +1,96 @@
+use crate::mdescr::datarefs::{DataRef, data_refs};
+use std::fs;
+
+const MODEL: &str = r#"
+name: Keys
+version: "1.0"
+description: Distributes keys.
+maintainer: tester <t@t.t>
+
+entities:
+  keys:
+    descr: Keys
+
+actions:
+  pull-keys:
+    module: cfg.resource
+    bind: [keys]
+    state:
+      $:
+        opts: [pull]
+        args:
+          src: /shared/authorized_keys
+          file: /root/.ssh/authorized_keys
+      per-host:
+        opts: [pull]
+        args:
+          src: /hosts/$(system.hostname)/authorized_keys
+          namespace: ops
+  sync-ring:
+    module: cfg.resource
+    bind: [keys]
+    state:
+      $:
+        opts: [sync-dir]
+        args:
+          src: /keyring/
+          namespace: ops
+          dst: /tmp/ring
+      per-team:
+        opts: [pull]
+        args:
+          src: /team/key
+          namespace: team-$(team)
+  publish:
+    module: cfg.resource
+    bind: [keys]
+    state:
+      $:
+        opts: [push]
+        args:
+          src: /published/key
+  run:
+    module: sys.run
+    bind: [keys]
+    state:
+      $:
+        opts: [pull]
+        args:
+          src: /not/a/resource
+"#;
+
+fn data_ref(namespace: Option<&str>, src: &str, prefix: bool) -> DataRef {
+    DataRef { namespace: namespace.map(str::to_string), namespace_prefix: false, src: src.to_string(), prefix }
+}
+
+#[test]
+fn pulled_resources_are_referenced() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("keys")).unwrap();
+    fs::write(root.path().join("keys/model.cfg"), MODEL).unwrap();
+    fs::create_dir_all(root.path().join("broken")).unwrap();
+    fs::write(root.path().join("broken/model.cfg"), "{{{").unwrap();
+
+    let refs = data_refs(root.path(), &["keys".to_string()]).unwrap();
+    assert_eq!(
+        refs.into_iter().collect::<Vec<_>>(),
+        [
+            data_ref(None, "/shared/authorized_keys", false),
+            data_ref(Some("ops"), "/hosts/", true),
+            data_ref(Some("ops"), "/keyring/", true),
+            DataRef { namespace_prefix: true, ..data_ref(Some("team-"), "/team/key", false) },
+        ]
+    );
+}
+
+#[test]
+fn unreadable_models_fail_the_collection() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("keys")).unwrap();
+    fs::write(root.path().join("keys/model.cfg"), MODEL).unwrap();
+    fs::create_dir_all(root.path().join("broken")).unwrap();
+    fs::write(root.path().join("broken/model.cfg"), "{{{").unwrap();
+
+    assert!(data_refs(root.path(), &["keys".to_string(), "broken".to_string()]).is_err());
+    assert!(data_refs(root.path(), &["keys".to_string(), "missing".to_string()]).is_err());
+}
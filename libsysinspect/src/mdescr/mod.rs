@@ -2,14 +2,20 @@ pub mod browse_types;
 pub mod browser;
 pub mod catalog;
 pub mod datapatch;
+pub mod datarefs;
 pub mod mspec;
 pub mod mspecdef;
 pub mod telemetry;
+pub mod versions;
 
 #[cfg(test)]
 mod browser_ut;
 #[cfg(test)]
 mod catalog_ut;
+#[cfg(test)]
+mod datarefs_ut;
+#[cfg(test)]
+mod versions_ut;
 
 /// DSL directives
 pub static DSL_DIR_ENTITIES: &str = "entities";
@@ -0,0 +1,588 @@
+//! Versioned models of the master.
+//!
+//! Uploaded model bundles are validated by the model loader, kept as numbered
+//! versions with their content hash and then activated in the models directory
+//! of the file server, where the master and the minions pick them up. A model
+//! that was edited by hand is kept as a version of its own before it is
+//! replaced, so every active model can be restored.
+//!
+//! Activation swaps the whole model directory at once, minions never see a
+//! half-copied model.
+
+use super::{browser::ModelBrowser, mspec::MODEL_INDEX};
+use crate::cfg::mmconf::MinionConfig;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use libcommon::SysinspectError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+/// Version index of one model
+pub const MODEL_VERSIONS_INDEX: &str = "versions.json";
+
+/// Which model version every dispatched query ran
+pub const MODEL_RUNS: &str = "runs.jsonl";
+
+/// The run log is compacted to its newer half above this size
+const MODEL_RUNS_MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Lines of unchanged context around the changes of a file diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Files too large to compare line by line are shown as replaced
+const DIFF_MAX_CELLS: usize = 4_000_000;
+
+/// Console and Web API change models from different tasks of the same process
+static MODEL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Model files as uploaded: path relative to the model directory and base64 encoded content
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelBundle {
+    pub files: BTreeMap<String, String>,
+}
+
+impl ModelBundle {
+    /// Bundle all files of a model directory
+    pub fn from_dir(root: &Path) -> Result<Self, SysinspectError> {
+        if !root.join(MODEL_INDEX).is_file() {
+            return Err(SysinspectError::ModelDSLError(format!("{} does not contain {MODEL_INDEX}", root.display())));
+        }
+
+        let mut files = BTreeMap::new();
+        for (path, data) in read_files(root)? {
+            files.insert(path, STANDARD.encode(data));
+        }
+
+        Ok(ModelBundle { files })
+    }
+
+    /// Decoded files. Paths must stay within the model directory.
+    fn decode(&self) -> Result<BTreeMap<String, Vec<u8>>, SysinspectError> {
+        let mut out = BTreeMap::new();
+        for (path, data) in &self.files {
+            let clean = Path::new(path);
+            if path.is_empty() || !clean.components().all(|c| matches!(c, Component::Normal(_))) {
+                return Err(SysinspectError::InvalidQuery(format!("Invalid model file path \"{path}\"")));
+            }
+            let data = STANDARD.decode(data).map_err(|err| SysinspectError::InvalidQuery(format!("Invalid content of model file {path}: {err}")))?;
+            out.insert(path.to_string(), data);
+        }
+        if !out.contains_key(MODEL_INDEX) {
+            return Err(SysinspectError::InvalidQuery(format!("Model bundle has no {MODEL_INDEX}")));
+        }
+
+        Ok(out)
+    }
+}
+
+/// Stored version of a model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelVersion {
+    /// Version number, starting at 1
+    pub version: u64,
+
+    /// SHA-256 over the paths and contents of all model files, hex encoded
+    pub hash: String,
+    pub created: DateTime<Utc>,
+
+    /// Who uploaded the version, empty for models taken over from the models directory
+    #[serde(default)]
+    pub author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub files: usize,
+}
+
+/// All versions of a model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelVersions {
+    pub model: String,
+
+    /// Version in the models directory, if it was not changed by hand since
+    #[serde(default)]
+    pub active: Option<u64>,
+
+    /// Oldest first
+    #[serde(default)]
+    pub versions: Vec<ModelVersion>,
+}
+
+impl ModelVersions {
+    pub fn get(&self, version: u64) -> Option<&ModelVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    fn by_hash(&self, hash: &str) -> Option<&ModelVersion> {
+        self.versions.iter().find(|v| v.hash == hash)
+    }
+
+    fn next(&self) -> u64 {
+        self.versions.iter().map(|v| v.version).max().unwrap_or_default() + 1
+    }
+}
+
+/// How a file changed between two versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Display for ModelFileChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelFileChange::Added => write!(f, "added"),
+            ModelFileChange::Removed => write!(f, "removed"),
+            ModelFileChange::Modified => write!(f, "modified"),
+        }
+    }
+}
+
+/// Changed file with its unified diff lines
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelFileDiff {
+    pub path: String,
+    pub change: ModelFileChange,
+    pub lines: Vec<String>,
+}
+
+/// Changes between two versions of a model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelDiff {
+    pub model: String,
+    pub from: u64,
+    pub to: u64,
+
+    /// Changed files only, sorted by path
+    pub files: Vec<ModelFileDiff>,
+}
+
+/// Model version a query cycle ran
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRun {
+    pub cycle_id: String,
+    pub model: String,
+
+    /// Unknown if the model was changed by hand and not uploaded since
+    #[serde(default)]
+    pub version: Option<u64>,
+    pub hash: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Versions of all models, stored next to the models directory of the file server
+pub struct ModelStore {
+    root: PathBuf,
+    models: PathBuf,
+}
+
+impl ModelStore {
+    /// Versions are kept in `root`, active models in `models`
+    pub fn new(root: &Path, models: &Path) -> Self {
+        ModelStore { root: root.to_path_buf(), models: models.to_path_buf() }
+    }
+
+    /// Validate the bundle, store it as a new version and activate it.
+    /// Uploading the content of an existing version activates that version.
+    pub fn upload(&self, model: &str, bundle: &ModelBundle, author: &str, note: Option<&str>) -> Result<ModelVersion, SysinspectError> {
+        Self::check_name(model)?;
+        let files = bundle.decode()?;
+        let _guard = MODEL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let staging = self.root.join(model).join(format!(".upload-{}", uuid::Uuid::new_v4()));
+        let stored = Self::write_files(&staging, &files).and_then(|_| Self::validate(&staging)).and_then(|_| {
+            let mut index = self.snapshot_live(model)?;
+            let hash = content_hash(&staging)?;
+            if let Some(existing) = index.by_hash(&hash).cloned() {
+                fs::remove_dir_all(&staging)?;
+                return Ok((index, existing));
+            }
+
+            let version = ModelVersion {
+                version: index.next(),
+                hash,
+                created: Utc::now(),
+                author: author.to_string(),
+                note: note.map(str::trim).filter(|n| !n.is_empty()).map(str::to_string),
+                files: files.len(),
+            };
+            fs::rename(&staging, self.version_dir(model, version.version))?;
+            index.versions.push(version.clone());
+            self.save_index(&index)?;
+            Ok((index, version))
+        });
+        let (mut index, version) = match stored {
+            Ok(stored) => stored,
+            Err(err) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(err);
+            }
+        };
+
+        self.activate(&mut index, version.version)?;
+        Ok(version)
+    }
+
+    /// Restore an earlier version. Without a version, the one before the active version is restored.
+    pub fn rollback(&self, model: &str, version: Option<u64>) -> Result<ModelVersion, SysinspectError> {
+        Self::check_name(model)?;
+        let _guard = MODEL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.snapshot_live(model)?;
+        let target = match version {
+            Some(version) => index.get(version).cloned(),
+            None => {
+                let active = index.active.ok_or_else(|| SysinspectError::InvalidQuery(format!("Model {model} has no active version")))?;
+                index.versions.iter().filter(|v| v.version < active).max_by_key(|v| v.version).cloned()
+            }
+        }
+        .ok_or_else(|| SysinspectError::ObjectNotFound(format!("Model {model} has no version to roll back to")))?;
+
+        self.activate(&mut index, target.version)?;
+        Ok(target)
+    }
+
+    /// Versions of the model, oldest first
+    pub fn versions(&self, model: &str) -> Result<ModelVersions, SysinspectError> {
+        Self::check_name(model)?;
+        let mut index = self.load_index(model)?;
+        if index.versions.is_empty() && !self.models.join(model).join(MODEL_INDEX).is_file() {
+            return Err(SysinspectError::ObjectNotFound(format!("Model {model} not found")));
+        }
+
+        index.active = match self.live_hash(model)? {
+            Some(hash) => index.by_hash(&hash).map(|v| v.version),
+            None => None,
+        };
+        Ok(index)
+    }
+
+    /// Changes from one version to another. Without `to`, the active version is compared.
+    pub fn diff(&self, model: &str, from: u64, to: Option<u64>) -> Result<ModelDiff, SysinspectError> {
+        let index = self.versions(model)?;
+        let to = to.or(index.active).ok_or_else(|| SysinspectError::InvalidQuery(format!("Model {model} has no active version to compare with")))?;
+        for version in [from, to] {
+            if index.get(version).is_none() {
+                return Err(SysinspectError::ObjectNotFound(format!("Model {model} has no version {version}")));
+            }
+        }
+
+        let old = read_files(&self.version_dir(model, from))?;
+        let new = read_files(&self.version_dir(model, to))?;
+        let mut paths = old.keys().chain(new.keys()).cloned().collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+
+        let mut files = Vec::new();
+        for path in paths {
+            let (change, lines) = match (old.get(&path), new.get(&path)) {
+                (Some(a), Some(b)) if a == b => continue,
+                (Some(a), Some(b)) => (ModelFileChange::Modified, diff_files(a, b)),
+                (None, Some(b)) => (ModelFileChange::Added, diff_files(&[], b)),
+                (Some(a), None) => (ModelFileChange::Removed, diff_files(a, &[])),
+                (None, None) => continue,
+            };
+            files.push(ModelFileDiff { path, change, lines });
+        }
+
+        Ok(ModelDiff { model: model.to_string(), from, to, files })
+    }
+
+    /// Remember which version of the model the query cycle runs.
+    /// The active model is read under the model lock, so an activation cannot swap it in between.
+    pub fn record_run(&self, cycle_id: &str, model: &str) -> Result<Option<ModelRun>, SysinspectError> {
+        Self::check_name(model)?;
+        let _guard = MODEL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(hash) = self.live_hash(model)? else {
+            return Ok(None);
+        };
+        let run = ModelRun {
+            cycle_id: cycle_id.to_string(),
+            model: model.to_string(),
+            version: self.load_index(model)?.by_hash(&hash).map(|v| v.version),
+            hash,
+            timestamp: Utc::now(),
+        };
+
+        fs::create_dir_all(&self.root)?;
+        let path = self.root.join(MODEL_RUNS);
+        let mut line = serde_json::to_vec(&run)?;
+        line.push(b'\n');
+        OpenOptions::new().create(true).append(true).open(&path)?.write_all(&line)?;
+
+        if fs::metadata(&path)?.len() > MODEL_RUNS_MAX_SIZE {
+            let lines = fs::read_to_string(&path)?.lines().map(str::to_string).collect::<Vec<_>>();
+            let kept = lines[lines.len() / 2..].join("\n");
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, format!("{kept}\n"))?;
+            fs::rename(tmp, &path)?;
+        }
+
+        Ok(Some(run))
+    }
+
+    /// Recorded model versions by query cycle
+    pub fn runs(&self) -> Result<HashMap<String, ModelRun>, SysinspectError> {
+        let file = match fs::File::open(self.root.join(MODEL_RUNS)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut out = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            // A line cut by a crash must not hide the other runs
+            if let Ok(run) = serde_json::from_str::<ModelRun>(&line?) {
+                out.insert(run.cycle_id.clone(), run);
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn check_name(model: &str) -> Result<(), SysinspectError> {
+        let mut components = Path::new(model).components();
+        if model.starts_with('.') || !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return Err(SysinspectError::InvalidQuery(format!("Invalid model name \"{model}\"")));
+        }
+
+        Ok(())
+    }
+
+    /// Load the model the same way minions do, so a broken model is never activated
+    fn validate(path: &Path) -> Result<(), SysinspectError> {
+        ModelBrowser::load(Arc::new(MinionConfig::default()), path)
+            .and_then(|browser| browser.summarize())
+            .map(|_| ())
+            .map_err(|err| SysinspectError::ModelDSLError(format!("Model is not valid: {err}")))
+    }
+
+    fn version_dir(&self, model: &str, version: u64) -> PathBuf {
+        self.root.join(model).join(version.to_string())
+    }
+
+    fn load_index(&self, model: &str) -> Result<ModelVersions, SysinspectError> {
+        match fs::read(self.root.join(model).join(MODEL_VERSIONS_INDEX)) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ModelVersions { model: model.to_string(), ..Default::default() }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save_index(&self, index: &ModelVersions) -> Result<(), SysinspectError> {
+        let dir = self.root.join(&index.model);
+        fs::create_dir_all(&dir)?;
+        let tmp = dir.join(format!("{MODEL_VERSIONS_INDEX}.tmp"));
+        fs::write(&tmp, serde_json::to_vec_pretty(index)?)?;
+        fs::rename(tmp, dir.join(MODEL_VERSIONS_INDEX))?;
+        Ok(())
+    }
+
+    fn live_hash(&self, model: &str) -> Result<Option<String>, SysinspectError> {
+        let live = self.models.join(model);
+        if !live.join(MODEL_INDEX).is_file() {
+            return Ok(None);
+        }
+
+        content_hash(&live).map(Some)
+    }
+
+    /// Keep the model in the models directory as a version, unless it is one already.
+    /// The returned index has the version of the models directory active.
+    fn snapshot_live(&self, model: &str) -> Result<ModelVersions, SysinspectError> {
+        let mut index = self.load_index(model)?;
+        let Some(hash) = self.live_hash(model)? else {
+            return Ok(index);
+        };
+        if let Some(version) = index.by_hash(&hash).map(|v| v.version) {
+            index.active = Some(version);
+            return Ok(index);
+        }
+
+        let version = ModelVersion {
+            version: index.next(),
+            hash,
+            created: Utc::now(),
+            author: String::new(),
+            note: Some("Taken over from the models directory".to_string()),
+            files: read_files(&self.models.join(model))?.len(),
+        };
+        copy_dir(&self.models.join(model), &self.version_dir(model, version.version))?;
+        log::info!("Model {model} was changed outside of version control, kept it as version {}", version.version);
+        index.active = Some(version.version);
+        index.versions.push(version);
+        self.save_index(&index)?;
+        Ok(index)
+    }
+
+    /// Replace the model in the models directory with the version
+    fn activate(&self, index: &mut ModelVersions, version: u64) -> Result<(), SysinspectError> {
+        let model = index.model.clone();
+        let staging = self.models.join(format!(".{model}.staging"));
+        let live = self.models.join(&model);
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        copy_dir(&self.version_dir(&model, version), &staging)?;
+
+        if live.exists() {
+            swap_dirs(&staging, &live)?;
+            fs::remove_dir_all(&staging)?;
+        } else {
+            fs::rename(&staging, &live)?;
+        }
+
+        index.active = Some(version);
+        self.save_index(index)?;
+        log::info!("Activated version {version} of model {model}");
+        Ok(())
+    }
+
+    fn write_files(root: &Path, files: &BTreeMap<String, Vec<u8>>) -> Result<(), SysinspectError> {
+        for (path, data) in files {
+            let dst = root.join(path);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(dst, data)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// All files below the directory by their relative path
+fn read_files(root: &Path) -> Result<BTreeMap<String, Vec<u8>>, SysinspectError> {
+    let mut out = BTreeMap::new();
+    for entry in WalkDir::new(root).follow_links(true) {
+        let entry = entry.map_err(|err| SysinspectError::IoErr(err.into()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        out.insert(path.to_string_lossy().replace('\\', "/"), fs::read(entry.path())?);
+    }
+
+    Ok(out)
+}
+
+/// Hash over the relative paths and contents of all files of the model directory
+pub fn content_hash(root: &Path) -> Result<String, SysinspectError> {
+    let mut hasher = Sha256::new();
+    for (path, data) in read_files(root)? {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update(Sha256::digest(&data));
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), SysinspectError> {
+    for (path, data) in read_files(src)? {
+        let target = dst.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, data)?;
+    }
+    fs::create_dir_all(dst)?;
+
+    Ok(())
+}
+
+/// Exchange two directories in one step where the platform supports it
+fn swap_dirs(a: &Path, b: &Path) -> Result<(), SysinspectError> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        let (ca, cb) = (CString::new(a.as_os_str().as_bytes()), CString::new(b.as_os_str().as_bytes()));
+        if let (Ok(ca), Ok(cb)) = (ca, cb) {
+            // SAFETY: both paths are valid NUL-terminated strings that outlive the call
+            if unsafe { libc::renameat2(libc::AT_FDCWD, ca.as_ptr(), libc::AT_FDCWD, cb.as_ptr(), libc::RENAME_EXCHANGE) } == 0 {
+                return Ok(());
+            }
+            log::debug!("Unable to exchange {} and {} at once: {}", a.display(), b.display(), std::io::Error::last_os_error());
+        }
+    }
+
+    let old = b.with_file_name(format!(".{}.old", b.file_name().unwrap_or_default().to_string_lossy()));
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    fs::rename(b, &old)?;
+    fs::rename(a, b)?;
+    fs::rename(old, a)?;
+    Ok(())
+}
+
+/// Unified diff lines of two file contents
+fn diff_files(old: &[u8], new: &[u8]) -> Vec<String> {
+    let (Ok(old), Ok(new)) = (std::str::from_utf8(old), std::str::from_utf8(new)) else {
+        return vec!["Binary files differ".to_string()];
+    };
+
+    diff_lines(&old.lines().collect::<Vec<_>>(), &new.lines().collect::<Vec<_>>())
+}
+
+/// Hunks of a line diff, based on the longest common subsequence of both sides
+pub(crate) fn diff_lines(old: &[&str], new: &[&str]) -> Vec<String> {
+    let (n, m) = (old.len(), new.len());
+    let mut ops: Vec<(char, &str)> = Vec::new();
+    if n.saturating_mul(m) > DIFF_MAX_CELLS {
+        ops.extend(old.iter().map(|l| ('-', *l)));
+        ops.extend(new.iter().map(|l| ('+', *l)));
+    } else {
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old[i] == new[j] {
+                ops.push((' ', old[i]));
+                (i, j) = (i + 1, j + 1);
+            } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                ops.push(('-', old[i]));
+                i += 1;
+            } else {
+                ops.push(('+', new[j]));
+                j += 1;
+            }
+        }
+    }
+
+    // Group the changes with their context into hunks
+    let mut out = Vec::new();
+    let changed = ops.iter().enumerate().filter(|(_, (op, _))| *op != ' ').map(|(at, _)| at).collect::<Vec<_>>();
+    let mut at = 0;
+    while at < changed.len() {
+        let start = changed[at].saturating_sub(DIFF_CONTEXT);
+        let mut end = changed[at];
+        while at < changed.len() && changed[at] <= end + 2 * DIFF_CONTEXT + 1 {
+            end = changed[at];
+            at += 1;
+        }
+        let end = (end + DIFF_CONTEXT + 1).min(ops.len());
+
+        let line = |side: char| ops[..start].iter().filter(|(op, _)| *op == ' ' || *op == side).count() + 1;
+        let count = |side: char| ops[start..end].iter().filter(|(op, _)| *op == ' ' || *op == side).count();
+        out.push(format!("@@ -{},{} +{},{} @@", line('-'), count('-'), line('+'), count('+')));
+        out.extend(ops[start..end].iter().map(|(op, text)| format!("{op}{text}")));
+    }
+
+    out
+}
@@ -0,0 +1,135 @@
+use crate::mdescr::versions::{ModelBundle, ModelFileChange, ModelStore, content_hash, diff_lines};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{fs, path::Path};
+
+fn model(version: &str) -> String {
+    format!("name: Alpha\nversion: \"{version}\"\ndescription: First model.\nmaintainer: a <a@a.a>\n")
+}
+
+fn bundle(files: &[(&str, &str)]) -> ModelBundle {
+    ModelBundle { files: files.iter().map(|(path, body)| (path.to_string(), STANDARD.encode(body))).collect() }
+}
+
+fn store(root: &Path) -> ModelStore {
+    ModelStore::new(&root.join("model-versions"), &root.join("models"))
+}
+
+#[test]
+fn upload_validates_and_activates() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+
+    let first = store.upload("alpha", &bundle(&[("model.cfg", &model("1.0"))]), "ops", Some("initial")).unwrap();
+    assert_eq!((first.version, first.author.as_str(), first.note.as_deref()), (1, "ops", Some("initial")));
+    assert_eq!(fs::read_to_string(root.path().join("models/alpha/model.cfg")).unwrap(), model("1.0"));
+
+    assert!(store.upload("alpha", &bundle(&[("model.cfg", "{{{ bad yaml")]), "ops", None).is_err());
+    assert!(store.upload("alpha", &bundle(&[("entities.cfg", "entities: {}")]), "ops", None).is_err());
+    assert_eq!(fs::read_to_string(root.path().join("models/alpha/model.cfg")).unwrap(), model("1.0"));
+
+    let versions = store.versions("alpha").unwrap();
+    assert_eq!((versions.active, versions.versions.len()), (Some(1), 1));
+    assert_eq!(versions.versions[0].hash, content_hash(&root.path().join("models/alpha")).unwrap());
+    assert_eq!(fs::read_dir(root.path().join("model-versions/alpha")).unwrap().count(), 2, "only the version and the index are left");
+}
+
+#[test]
+fn same_content_reuses_the_version() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    store.upload("alpha", &bundle(&[("model.cfg", &model("1.0"))]), "ops", None).unwrap();
+    store.upload("alpha", &bundle(&[("model.cfg", &model("2.0"))]), "ops", None).unwrap();
+
+    assert_eq!(store.upload("alpha", &bundle(&[("model.cfg", &model("1.0"))]), "alice", None).unwrap().version, 1);
+    let versions = store.versions("alpha").unwrap();
+    assert_eq!((versions.active, versions.versions.len()), (Some(1), 2));
+}
+
+#[test]
+fn hand_made_model_is_kept_and_restored() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("models/alpha")).unwrap();
+    fs::write(root.path().join("models/alpha/model.cfg"), model("0.1")).unwrap();
+    let store = store(root.path());
+
+    let versions = store.versions("alpha").unwrap();
+    assert_eq!((versions.active, versions.versions.len()), (None, 0));
+
+    assert_eq!(store.upload("alpha", &bundle(&[("model.cfg", &model("1.0"))]), "ops", None).unwrap().version, 2);
+    let versions = store.versions("alpha").unwrap();
+    assert_eq!((versions.active, versions.versions[0].author.as_str()), (Some(2), ""));
+
+    assert_eq!(store.rollback("alpha", None).unwrap().version, 1);
+    assert_eq!(fs::read_to_string(root.path().join("models/alpha/model.cfg")).unwrap(), model("0.1"));
+    assert!(store.rollback("alpha", None).is_err());
+
+    assert_eq!(store.rollback("alpha", Some(2)).unwrap().version, 2);
+    assert_eq!(fs::read_to_string(root.path().join("models/alpha/model.cfg")).unwrap(), model("1.0"));
+    assert!(store.rollback("alpha", Some(7)).is_err());
+    assert!(!root.path().join("models/.alpha.staging").exists());
+}
+
+#[test]
+fn versions_are_compared() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    store.upload("alpha", &bundle(&[("model.cfg", &model("1.0")), ("old.cfg", "a: 1\n")]), "ops", None).unwrap();
+    store.upload("alpha", &bundle(&[("model.cfg", &model("2.0")), ("new/entities.cfg", "b: 2\n")]), "ops", None).unwrap();
+
+    let diff = store.diff("alpha", 1, None).unwrap();
+    assert_eq!(diff.to, 2);
+    let changes = diff.files.iter().map(|f| (f.path.as_str(), f.change)).collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [("model.cfg", ModelFileChange::Modified), ("new/entities.cfg", ModelFileChange::Added), ("old.cfg", ModelFileChange::Removed)]
+    );
+    assert!(diff.files[0].lines.contains(&"-version: \"1.0\"".to_string()));
+    assert!(diff.files[0].lines.contains(&"+version: \"2.0\"".to_string()));
+    assert!(store.diff("alpha", 1, Some(3)).is_err());
+}
+
+#[test]
+fn bundles_stay_in_the_model() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+
+    for path in ["../escape.cfg", "/etc/passwd", "a/../../b.cfg", ""] {
+        assert!(store.upload("alpha", &bundle(&[("model.cfg", &model("1.0")), (path, "x")]), "ops", None).is_err(), "{path}");
+    }
+    for name in ["..", ".hidden", "a/b", ""] {
+        assert!(store.upload(name, &bundle(&[("model.cfg", &model("1.0"))]), "ops", None).is_err(), "{name}");
+    }
+    assert!(!root.path().join("escape.cfg").exists());
+}
+
+#[test]
+fn runs_record_the_version() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    assert!(store.record_run("c0", "alpha").unwrap().is_none());
+
+    store.upload("alpha", &bundle(&[("model.cfg", &model("1.0"))]), "ops", None).unwrap();
+    store.record_run("c1", "alpha").unwrap();
+    fs::write(root.path().join("models/alpha/extra.cfg"), "c: 3\n").unwrap();
+    store.record_run("c2", "alpha").unwrap();
+
+    let runs = store.runs().unwrap();
+    assert_eq!(runs["c1"].version, Some(1));
+    assert_eq!(runs["c2"].version, None);
+    assert_ne!(runs["c1"].hash, runs["c2"].hash);
+}
+
+#[test]
+fn line_diff_has_hunks() {
+    let old = (1..=20).map(|n| n.to_string()).collect::<Vec<_>>();
+    let mut new = old.clone();
+    new[1] = "two".to_string();
+    new.remove(17);
+    let (old, new) = (old.iter().map(String::as_str).collect::<Vec<_>>(), new.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let lines = diff_lines(&old, &new);
+    assert_eq!(lines.iter().filter(|l| l.starts_with("@@")).count(), 2);
+    assert_eq!(lines[0], "@@ -1,5 +1,5 @@");
+    assert_eq!(&lines[1..7], [" 1", "-2", "+two", " 3", " 4", " 5"]);
+    assert!(diff_lines(&old, &old).is_empty());
+}
@@ -0,0 +1,389 @@
+//! Role-based access control of the operator interfaces (Web API and console).
+//!
+//! The policy lives on the master in `rbac.yaml` next to the other master data.
+//! Roles grant permissions on models and their states, target scopes, cluster
+//! commands and datastore writes. Bindings map PAM users, their groups, console
+//! client keys and API tokens to the roles.
+//!
+//! If the policy file is not present, access control is disabled and every
+//! authenticated principal has full access, as before. API tokens are still
+//! limited to their scopes.
+
+#[cfg(test)]
+#[path = "rbac_ut.rs"]
+mod rbac_ut;
+pub mod tokens;
+#[cfg(test)]
+#[path = "tokens_ut.rs"]
+mod tokens_ut;
+
+use crate::cfg::mmconf::MasterConfig;
+use glob::Pattern;
+use libcommon::SysinspectError;
+use libsysproto::query::{
+    SCHEME_COMMAND,
+    commands::{
+        CLUSTER_API_TOKENS, CLUSTER_AUDIT, CLUSTER_CONFIG_RELOAD, CLUSTER_LIBRARY_INDEX, CLUSTER_MARK_UPGRADE_REQUIRED, CLUSTER_MASTER_LOGS,
+        CLUSTER_MODEL_VERSIONS, CLUSTER_MODELS, CLUSTER_MODULE_INDEX, CLUSTER_PLACEMENT, CLUSTER_REBOOT_STATUS, CLUSTER_UPGRADE_STATUS,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, fs, path::Path};
+use tokens::{TokenOwner, TokenStore};
+
+/// Who is calling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal {
+    /// PAM user of the Web API
+    User(String),
+
+    /// Console client, named after its key file in the console keys directory
+    Console(String),
+
+    /// Named API token
+    Token(String),
+
+    /// Minion with a datastore session. Minions can only read the datastore.
+    Minion(String),
+}
+
+impl Display for Principal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Principal::User(name) => write!(f, "user {name}"),
+            Principal::Console(name) => write!(f, "console {name}"),
+            Principal::Token(name) => write!(f, "token {name}"),
+            Principal::Minion(id) => write!(f, "minion {id}"),
+        }
+    }
+}
+
+/// Minions selected by a call: hostname globs, traits query and minion Id.
+/// Without hostnames and a minion Id the call goes to every minion.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Target<'a> {
+    pub query: &'a str,
+    pub traits: &'a str,
+    pub mid: &'a str,
+}
+
+impl<'a> Target<'a> {
+    pub fn new(query: &'a str, traits: &'a str, mid: &'a str) -> Self {
+        Self { query, traits, mid }
+    }
+
+    /// Every minion of the cluster
+    pub fn everyone() -> Self {
+        Self::new("*", "", "")
+    }
+}
+
+/// What is being done
+#[derive(Debug, Clone)]
+pub enum Access<'a> {
+    /// Read-only listing: models, results, queued commands etc
+    Read,
+
+    /// Read-only view of the targeted minions: their traits, trait history and keys
+    ReadOn(Target<'a>),
+
+    /// Read from the datastore
+    DatastoreRead,
+
+    /// Model call on the targeted minions
+    Query { model: &'a str, target: Target<'a> },
+
+    /// Cluster command of the master itself, e.g. `cluster/audit`
+    Command(&'a str),
+
+    /// Cluster command sent to the targeted minions, e.g. `cluster/shutdown`
+    CommandOn { command: &'a str, target: Target<'a> },
+
+    /// Upload to the datastore
+    DatastoreWrite,
+}
+
+/// Cluster commands served by the master itself. Their query is not a minion selection.
+const MASTER_COMMANDS: &[&str] = &[
+    CLUSTER_API_TOKENS,
+    CLUSTER_AUDIT,
+    CLUSTER_CONFIG_RELOAD,
+    CLUSTER_LIBRARY_INDEX,
+    CLUSTER_MARK_UPGRADE_REQUIRED,
+    CLUSTER_MASTER_LOGS,
+    CLUSTER_MODEL_VERSIONS,
+    CLUSTER_MODELS,
+    CLUSTER_MODULE_INDEX,
+    CLUSTER_PLACEMENT,
+    CLUSTER_REBOOT_STATUS,
+    CLUSTER_UPGRADE_STATUS,
+];
+
+impl<'a> Access<'a> {
+    /// Access of a console or Web API query. Queries of the command scheme are cluster commands.
+    pub fn of_query(model: &'a str, query: &'a str, traits: &'a str, mid: &'a str) -> Self {
+        let target = Target::new(query, traits, mid);
+        match model.strip_prefix(SCHEME_COMMAND) {
+            Some(command) if MASTER_COMMANDS.contains(&command) => Access::Command(command),
+            Some(command) => Access::CommandOn { command, target },
+            None => Access::Query { model, target },
+        }
+    }
+
+    /// Cluster command of the master itself, not sent to any minion
+    pub fn command(command: &'a str) -> Self {
+        Access::Command(command)
+    }
+
+    /// Cluster command sent to the targeted minions
+    pub fn command_on(command: &'a str, target: Target<'a>) -> Self {
+        Access::CommandOn { command, target }
+    }
+}
+
+impl Display for Access<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Read => write!(f, "read access"),
+            Access::ReadOn(_) => write!(f, "read access to minions"),
+            Access::DatastoreRead => write!(f, "datastore read"),
+            Access::Query { model, .. } => write!(f, "query of {model}"),
+            Access::Command(command) | Access::CommandOn { command, .. } => write!(f, "command {command}"),
+            Access::DatastoreWrite => write!(f, "datastore write"),
+        }
+    }
+}
+
+/// Model permission. Both model and states are glob patterns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelGrant {
+    pub model: String,
+
+    /// States the model can be called with. `$` stands for a call without a state (all states).
+    #[serde(default = "any")]
+    pub states: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Role {
+    #[serde(default)]
+    pub models: Vec<ModelGrant>,
+
+    /// Hostnames, minion Ids or virtual groups (`v:<name>`) the role can target
+    #[serde(default = "any")]
+    pub targets: Vec<String>,
+
+    /// Traits queries the role can target with
+    #[serde(default = "any")]
+    pub traits: Vec<String>,
+
+    /// Cluster commands, e.g. `cluster/minion/info` or `cluster/*`
+    #[serde(default)]
+    pub commands: Vec<String>,
+
+    #[serde(default, rename = "datastore-write")]
+    pub datastore_write: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bindings {
+    #[serde(default)]
+    pub users: HashMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub consoles: HashMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub tokens: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RbacPolicy {
+    #[serde(default)]
+    pub roles: HashMap<String, Role>,
+
+    #[serde(default)]
+    pub bindings: Bindings,
+}
+
+fn any() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn matches(patterns: &[String], value: &str) -> bool {
+    patterns.iter().any(|p| p == value || Pattern::new(p).is_ok_and(|p| p.matches(value)))
+}
+
+/// Split the model call path `/<model>/[entities]/[states]` or `<model>:[labels]` into the model and its states.
+fn model_states(path: &str) -> (&str, Vec<&str>) {
+    let path = path.trim().trim_matches('/');
+    if let Some((model, _)) = path.split_once(':') {
+        return (model, vec!["$"]);
+    }
+
+    let mut parts = path.split('/');
+    let model = parts.next().unwrap_or_default();
+    let states = parts.nth(1).map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).collect::<Vec<_>>()).unwrap_or_default();
+    (model, if states.is_empty() { vec!["$"] } else { states })
+}
+
+/// Trait term `key:value` without the whitespace the query grammar allows around the colon.
+fn trait_term(term: &str) -> String {
+    match term.split_once(':') {
+        Some((key, value)) => format!("{}:{}", key.trim(), value.trim()),
+        None => term.trim().to_string(),
+    }
+}
+
+impl Role {
+    fn allows(&self, access: &Access) -> bool {
+        match access {
+            Access::Read | Access::DatastoreRead => true,
+            Access::ReadOn(target) => self.allows_target(target),
+            Access::DatastoreWrite => self.datastore_write,
+            Access::Command(command) => matches(&self.commands, command.trim_matches('/')),
+            Access::CommandOn { command, target } => matches(&self.commands, command.trim_matches('/')) && self.allows_target(target),
+            Access::Query { model, target } => {
+                let (model, states) = model_states(model);
+                let model_ok =
+                    self.models.iter().any(|g| matches(std::slice::from_ref(&g.model), model) && states.iter().all(|s| matches(&g.states, s)));
+
+                model_ok && self.allows_target(target)
+            }
+        }
+    }
+
+    /// Every hostname, the minion Id and every term of the traits query must be within the role scope.
+    /// Terms are checked in all `or` groups, as any of them widens the selection.
+    fn allows_target(&self, target: &Target) -> bool {
+        let mut targets = target.query.split(',').map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect::<Vec<_>>();
+        if !target.mid.trim().is_empty() {
+            targets.push(target.mid.trim().to_string());
+        }
+        if targets.is_empty() {
+            targets.push("*".to_string());
+        }
+        if !targets.iter().all(|t| matches(&self.targets, t)) {
+            return false;
+        }
+
+        let traits = target.traits.trim();
+        if traits.is_empty() {
+            return true;
+        }
+        match crate::traits::parse_traits_query(traits) {
+            Ok(groups) if !groups.is_empty() => groups.iter().flatten().all(|term| matches(&self.traits, &trait_term(term))),
+            _ => false,
+        }
+    }
+}
+
+impl RbacPolicy {
+    /// Load the policy of the master. Returns `None` if access control is not configured.
+    pub fn load(cfg: &MasterConfig) -> Result<Option<Self>, SysinspectError> {
+        Self::from_file(&cfg.rbac_policy())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Option<Self>, SysinspectError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let policy = serde_yaml::from_str::<Self>(&fs::read_to_string(path)?)
+            .map_err(|e| SysinspectError::ConfigError(format!("Invalid access control policy {}: {e}", path.display())))?;
+        for (binding, roles) in
+            policy.bindings.users.iter().chain(&policy.bindings.groups).chain(&policy.bindings.consoles).chain(&policy.bindings.tokens)
+        {
+            if let Some(role) = roles.iter().find(|r| !policy.roles.contains_key(*r)) {
+                return Err(SysinspectError::ConfigError(format!("Access control binding {binding} refers to unknown role {role}")));
+            }
+        }
+
+        Ok(Some(policy))
+    }
+
+    /// Names of the roles bound to the principal. Users also get the roles of their groups.
+    pub fn roles_of(&self, principal: &Principal, groups: &[String]) -> Vec<String> {
+        let mut roles = match principal {
+            Principal::User(name) => {
+                let mut roles = self.bindings.users.get(name).cloned().unwrap_or_default();
+                for group in groups {
+                    roles.extend(self.bindings.groups.get(group).cloned().unwrap_or_default());
+                }
+                roles
+            }
+            Principal::Console(name) => self.bindings.consoles.get(name).cloned().unwrap_or_default(),
+            Principal::Token(name) => self.bindings.tokens.get(name).cloned().unwrap_or_default(),
+            Principal::Minion(_) => vec![],
+        };
+        roles.sort();
+        roles.dedup();
+        roles
+    }
+
+    /// Check the access against the roles of the principal. Any single role has to grant the entire access.
+    pub fn check(&self, principal: &Principal, groups: &[String], access: &Access) -> Result<(), SysinspectError> {
+        if self.roles_of(principal, groups).iter().filter_map(|r| self.roles.get(r)).any(|r| r.allows(access)) {
+            return Ok(());
+        }
+
+        Err(SysinspectError::AccessDenied(format!("{access} is not permitted for {principal}")))
+    }
+}
+
+/// Unix groups of the user, to resolve group bindings of PAM users.
+pub fn user_groups(name: &str) -> Vec<String> {
+    use nix::unistd::{Group, User, getgrouplist};
+    use std::ffi::CString;
+
+    let Ok(Some(user)) = User::from_name(name) else {
+        return vec![];
+    };
+    let Ok(cname) = CString::new(name) else {
+        return vec![];
+    };
+
+    getgrouplist(&cname, user.gid)
+        .unwrap_or_else(|_| vec![user.gid])
+        .into_iter()
+        .filter_map(|gid| Group::from_gid(gid).ok().flatten().map(|g| g.name))
+        .collect()
+}
+
+/// Check the access of a principal against the master policy. Passes if access control is not configured.
+///
+/// Tokens are always checked against their scopes first. Tokens of Web API users are then
+/// checked as their owner. Minions can read the datastore and nothing else, with or without a policy.
+pub fn authorise(cfg: &MasterConfig, principal: &Principal, access: &Access) -> Result<(), SysinspectError> {
+    if let Principal::Minion(_) = principal {
+        return match access {
+            Access::DatastoreRead => Ok(()),
+            _ => Err(SysinspectError::AccessDenied(format!("{access} is not permitted for {principal}"))),
+        };
+    }
+
+    let mut principal = principal.clone();
+    if let Principal::Token(name) = &principal {
+        let token = TokenStore::new(&cfg.api_keys_root())
+            .get(name)?
+            .filter(|t| !t.is_expired())
+            .ok_or_else(|| SysinspectError::AccessDenied(format!("Token {name} is revoked or expired")))?;
+        token.permits(access)?;
+        if let TokenOwner::User(owner) = token.owner {
+            principal = Principal::User(owner);
+        }
+    }
+
+    let Some(policy) = RbacPolicy::load(cfg)? else {
+        return Ok(());
+    };
+
+    let groups = match &principal {
+        Principal::User(name) => user_groups(name),
+        _ => vec![],
+    };
+    policy.check(&principal, &groups, access)
+}
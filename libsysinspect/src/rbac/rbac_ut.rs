@@ -0,0 +1,158 @@
+use super::{Access, Principal, RbacPolicy, Target, model_states};
+use std::fs;
+
+const POLICY: &str = r#"
+roles:
+  viewer: {}
+  operator:
+    models:
+      - model: "cm"
+        states: ["$", "check", "dry-*"]
+    targets: ["web*", "db01"]
+    traits: ["system.os.distribution:*"]
+    commands: ["cluster/minion/info", "cluster/audit"]
+  admin:
+    models:
+      - model: "*"
+    commands: ["cluster/*"]
+    datastore-write: true
+bindings:
+  users:
+    alice: [operator]
+  groups:
+    wheel: [admin]
+  consoles:
+    ci: [viewer]
+  tokens:
+    deploy: [operator]
+"#;
+
+fn policy() -> RbacPolicy {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rbac.yaml");
+    fs::write(&path, POLICY).unwrap();
+    RbacPolicy::from_file(&path).unwrap().unwrap()
+}
+
+fn query<'a>(model: &'a str, query: &'a str) -> Access<'a> {
+    Access::of_query(model, query, "", "")
+}
+
+#[test]
+fn model_states_are_split_from_the_call_path() {
+    assert_eq!(model_states("cm/files"), ("cm", vec!["$"]));
+    assert_eq!(model_states("/cm/files/check,apply"), ("cm", vec!["check", "apply"]));
+    assert_eq!(model_states("cm:web"), ("cm", vec!["$"]));
+}
+
+#[test]
+fn missing_policy_disables_access_control() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(RbacPolicy::from_file(&dir.path().join("rbac.yaml")).unwrap().is_none());
+}
+
+#[test]
+fn binding_to_unknown_role_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rbac.yaml");
+    fs::write(&path, "roles:\n  viewer: {}\nbindings:\n  users:\n    bob: [root]\n").unwrap();
+    assert!(RbacPolicy::from_file(&path).is_err());
+}
+
+#[test]
+fn model_and_state_grants_are_enforced() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &query("/cm/files/check", "web01")).is_ok());
+    assert!(p.check(&alice, &[], &query("/cm/files/dry-run", "web01")).is_ok());
+    assert!(p.check(&alice, &[], &query("cm/files", "web01")).is_ok());
+    assert!(p.check(&alice, &[], &query("/cm/files/apply", "web01")).is_err());
+    assert!(p.check(&alice, &[], &query("/net/ifaces", "web01")).is_err());
+}
+
+#[test]
+fn targets_and_traits_are_enforced() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &query("cm/files", "WEB01,db01")).is_ok());
+    assert!(p.check(&alice, &[], &query("cm/files", "web01,db02")).is_err());
+    assert!(p.check(&alice, &[], &query("cm/files", "*")).is_err());
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution:Ubuntu", "")).is_ok());
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.hostname:x", "")).is_err());
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "", "", "30a1f")).is_err());
+    assert!(p.check(&alice, &[], &query("cm/files", "")).is_err());
+}
+
+#[test]
+fn every_term_of_a_traits_query_is_enforced() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution : Ubuntu", "")).is_ok());
+    assert!(
+        p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution:Ubuntu or system.os.distribution:Debian", "")).is_ok()
+    );
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution:Ubuntu or system.hostname:db02", "")).is_err());
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution:Ubuntu and system.arch:x86_64", "")).is_err());
+    assert!(p.check(&alice, &[], &Access::of_query("cm/files", "web01", "system.os.distribution:*", "")).is_err());
+}
+
+#[test]
+fn commands_and_datastore_writes_are_enforced() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+    let root = Principal::User("root".to_string());
+
+    assert!(p.check(&alice, &[], &query("cmd://cluster/minion/info", "web01")).is_ok());
+    assert!(p.check(&alice, &[], &query("cmd://cluster/shutdown", "web01")).is_err());
+    assert!(p.check(&alice, &[], &Access::DatastoreWrite).is_err());
+    assert!(p.check(&root, &["wheel".to_string()], &query("cmd://cluster/shutdown", "*")).is_ok());
+    assert!(p.check(&root, &["wheel".to_string()], &Access::DatastoreWrite).is_ok());
+}
+
+#[test]
+fn command_targets_are_enforced() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &query("cmd://cluster/minion/info", "*")).is_err());
+    assert!(p.check(&alice, &[], &query("cmd://cluster/minion/info", "")).is_err());
+    assert!(p.check(&alice, &[], &Access::command_on("cluster/minion/info", Target::new("web01", "system.hostname:db02", ""))).is_err());
+    assert!(p.check(&alice, &[], &Access::command_on("cluster/minion/info", Target::everyone())).is_err());
+    assert!(p.check(&alice, &[], &Access::command("cluster/minion/info")).is_ok());
+}
+
+#[test]
+fn master_commands_have_no_targets() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &query("cmd://cluster/audit", "")).is_ok());
+    assert!(p.check(&alice, &[], &query("cmd://cluster/audit", "*")).is_ok());
+    assert!(p.check(&alice, &[], &query("cmd://cluster/placement", "")).is_err());
+}
+
+#[test]
+fn minion_reads_are_scoped_to_the_role_targets() {
+    let p = policy();
+    let alice = Principal::User("alice".to_string());
+
+    assert!(p.check(&alice, &[], &Access::ReadOn(Target::new("web01", "", ""))).is_ok());
+    assert!(p.check(&alice, &[], &Access::ReadOn(Target::new("", "", "db01"))).is_ok());
+    assert!(p.check(&alice, &[], &Access::ReadOn(Target::new("", "", "db02"))).is_err());
+    assert!(p.check(&alice, &[], &Access::ReadOn(Target::default())).is_err());
+    assert!(p.check(&alice, &[], &Access::Read).is_ok());
+}
+
+#[test]
+fn principals_without_roles_are_denied() {
+    let p = policy();
+
+    assert!(p.check(&Principal::Console("ci".to_string()), &[], &Access::Read).is_ok());
+    assert!(p.check(&Principal::Console("ci".to_string()), &[], &query("cm/files", "web01")).is_err());
+    assert!(p.check(&Principal::Console("laptop".to_string()), &[], &Access::Read).is_err());
+    assert!(p.check(&Principal::User("bob".to_string()), &["users".to_string()], &Access::Read).is_err());
+    assert!(p.check(&Principal::Token("deploy".to_string()), &[], &query("cm/files", "web02")).is_ok());
+}
@@ -0,0 +1,357 @@
+//! Long-lived API tokens for automation.
+//!
+//! Tokens are persisted in the Web API keys directory of the master, one JSON
+//! file per token. Only the SHA-256 hash of a token is stored, the token itself
+//! is shown once, when it is created. Presented tokens are looked up by their
+//! hash in an index directory, where each file names the token of that hash.
+//!
+//! Tokens created by the operator on the console get their roles through the
+//! `tokens` bindings of the access control policy. Tokens created by a Web API
+//! user act on behalf of that user. Scopes narrow down what a token can do
+//! regardless of its roles.
+
+use super::Access;
+use chrono::{DateTime, Duration, Utc};
+use libcommon::SysinspectError;
+use rand::{RngCore, rngs::OsRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Display,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Prefix of every issued token, to tell them apart from session ids
+pub const TOKEN_PREFIX: &str = "sit_";
+
+/// Last-used timestamp is written at most this often per token
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Index of the token names by token hash
+const HASH_INDEX_DIR: &str = "by-hash";
+
+/// What a token can be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenScope {
+    /// Read-only listings
+    Read,
+
+    /// Model calls
+    Query,
+
+    /// Cluster commands
+    Command,
+
+    /// Uploads to the datastore
+    DatastoreWrite,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 4] = [TokenScope::Read, TokenScope::Query, TokenScope::Command, TokenScope::DatastoreWrite];
+
+    fn permits(&self, access: &Access) -> bool {
+        matches!(
+            (self, access),
+            (TokenScope::Read, Access::Read | Access::ReadOn(_) | Access::DatastoreRead)
+                | (TokenScope::Query, Access::Query { .. })
+                | (TokenScope::Command, Access::Command(_) | Access::CommandOn { .. })
+                | (TokenScope::DatastoreWrite, Access::DatastoreWrite)
+        )
+    }
+
+    /// Parse comma-separated scopes. `all` stands for every scope, empty input for read-only.
+    pub fn parse_list(scopes: &str) -> Result<Vec<TokenScope>, SysinspectError> {
+        let mut out = Vec::new();
+        for scope in scopes.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if scope.eq_ignore_ascii_case("all") {
+                return Ok(Self::ALL.to_vec());
+            }
+            let scope = scope.parse::<TokenScope>()?;
+            if !out.contains(&scope) {
+                out.push(scope);
+            }
+        }
+
+        Ok(if out.is_empty() { vec![TokenScope::Read] } else { out })
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = SysinspectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Ok(TokenScope::Read),
+            "query" => Ok(TokenScope::Query),
+            "command" => Ok(TokenScope::Command),
+            "datastore-write" => Ok(TokenScope::DatastoreWrite),
+            _ => Err(SysinspectError::InvalidQuery(format!("Unknown token scope \"{s}\", expected read, query, command or datastore-write"))),
+        }
+    }
+}
+
+impl Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenScope::Read => write!(f, "read"),
+            TokenScope::Query => write!(f, "query"),
+            TokenScope::Command => write!(f, "command"),
+            TokenScope::DatastoreWrite => write!(f, "datastore-write"),
+        }
+    }
+}
+
+/// Who created the token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum TokenOwner {
+    /// Web API user. The token acts on behalf of the user.
+    User(String),
+
+    /// Console client of the operator. The token has roles of its own.
+    Console(String),
+}
+
+impl Display for TokenOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenOwner::User(name) => write!(f, "user {name}"),
+            TokenOwner::Console(name) => write!(f, "console {name}"),
+        }
+    }
+}
+
+/// Persisted API token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub owner: TokenOwner,
+    pub scopes: Vec<TokenScope>,
+
+    /// SHA-256 of the token, hex encoded
+    pub hash: String,
+    pub created: DateTime<Utc>,
+    pub expires: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// Check the access against the token scopes
+    pub fn permits(&self, access: &Access) -> Result<(), SysinspectError> {
+        if self.scopes.iter().any(|s| s.permits(access)) {
+            return Ok(());
+        }
+
+        Err(SysinspectError::AccessDenied(format!("{access} is outside of the scopes of token {}", self.name)))
+    }
+}
+
+/// Parse a human-readable token lifetime, e.g. `90d` or `12h`. Empty input means the token never expires.
+pub fn parse_lifetime(lifetime: &str) -> Result<Option<std::time::Duration>, SysinspectError> {
+    if lifetime.trim().is_empty() {
+        return Ok(None);
+    }
+
+    humantime::parse_duration(lifetime.trim())
+        .map(Some)
+        .map_err(|err| SysinspectError::InvalidQuery(format!("Invalid token lifetime \"{lifetime}\": {err}")))
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Token names are used as file names and in the access control bindings
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && !matches!(name, "." | "..")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Write a file readable only by the owner. The file is replaced at once, readers never see a partial one.
+fn write_private(path: &Path, data: &[u8]) -> Result<(), SysinspectError> {
+    let tmp = path.with_extension(format!("{:016x}.tmp", OsRng.next_u64()));
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+
+    let written = opts.open(&tmp).and_then(|mut f| {
+        f.write_all(data)?;
+        f.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+/// API tokens stored in a directory
+pub struct TokenStore {
+    root: PathBuf,
+}
+
+impl TokenStore {
+    pub fn new(root: &Path) -> Self {
+        TokenStore { root: root.to_path_buf() }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{name}.json"))
+    }
+
+    fn save(&self, token: &ApiToken) -> Result<(), SysinspectError> {
+        fs::create_dir_all(&self.root)?;
+        write_private(&self.path(&token.name), serde_json::to_string_pretty(token)?.as_bytes())
+    }
+
+    fn index_path(&self, hash: &str) -> PathBuf {
+        self.root.join(HASH_INDEX_DIR).join(hash)
+    }
+
+    /// Build the hash index of the tokens, issued before the index existed
+    fn ensure_index(&self) -> Result<(), SysinspectError> {
+        let index = self.root.join(HASH_INDEX_DIR);
+        if index.exists() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.root)?;
+        let tmp = self.root.join(format!("{HASH_INDEX_DIR}.{:016x}.tmp", OsRng.next_u64()));
+        fs::create_dir(&tmp)?;
+        for token in self.list()? {
+            write_private(&tmp.join(&token.hash), token.name.as_bytes())?;
+        }
+        if let Err(err) = fs::rename(&tmp, &index) {
+            let _ = fs::remove_dir_all(&tmp);
+            // Built meanwhile by another caller
+            if !index.exists() {
+                return Err(err.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Issue a new token. Returns the stored record and the token itself, which cannot be recovered later.
+    pub fn create(
+        &self, name: &str, owner: TokenOwner, scopes: Vec<TokenScope>, ttl: Option<std::time::Duration>,
+    ) -> Result<(ApiToken, String), SysinspectError> {
+        if !valid_name(name) {
+            return Err(SysinspectError::InvalidQuery(format!("Invalid token name \"{name}\": use up to 64 letters, digits, '.', '_' or '-'")));
+        }
+        if self.path(name).exists() {
+            return Err(SysinspectError::InvalidQuery(format!("Token {name} already exists")));
+        }
+        if scopes.is_empty() {
+            return Err(SysinspectError::InvalidQuery("Token needs at least one scope".to_string()));
+        }
+
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let secret = format!("{TOKEN_PREFIX}{}", hex::encode(secret));
+
+        let created = Utc::now();
+        let expires = match ttl {
+            Some(ttl) => Some(created + Duration::from_std(ttl).map_err(|e| SysinspectError::InvalidQuery(format!("Invalid token lifetime: {e}")))?),
+            None => None,
+        };
+        let token = ApiToken { name: name.to_string(), owner, scopes, hash: hash_token(&secret), created, expires, last_used: None };
+        self.ensure_index()?;
+        write_private(&self.index_path(&token.hash), token.name.as_bytes())?;
+        self.save(&token)?;
+
+        Ok((token, secret))
+    }
+
+    /// All tokens, sorted by name. Expired tokens are included.
+    pub fn list(&self) -> Result<Vec<ApiToken>, SysinspectError> {
+        if !self.root.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut tokens = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            match serde_json::from_str::<ApiToken>(&fs::read_to_string(&path)?) {
+                Ok(token) => tokens.push(token),
+                Err(err) => log::warn!("Skipping unreadable API token {}: {err}", path.display()),
+            }
+        }
+        tokens.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(tokens)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<ApiToken>, SysinspectError> {
+        if !valid_name(name) || !self.path(name).exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&fs::read_to_string(self.path(name))?)?))
+    }
+
+    /// Remove the token. Returns `false` if there was no such token.
+    pub fn revoke(&self, name: &str) -> Result<bool, SysinspectError> {
+        if !valid_name(name) || !self.path(name).exists() {
+            return Ok(false);
+        }
+
+        if let Ok(Some(token)) = self.get(name)
+            && let Err(err) = fs::remove_file(self.index_path(&token.hash))
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(err.into());
+        }
+        fs::remove_file(self.path(name))?;
+        Ok(true)
+    }
+
+    /// Find the valid token record of a presented token and record its use.
+    pub fn verify(&self, secret: &str) -> Result<Option<ApiToken>, SysinspectError> {
+        if !secret.starts_with(TOKEN_PREFIX) {
+            return Ok(None);
+        }
+
+        self.ensure_index()?;
+        let hash = hash_token(secret);
+        let name = match fs::read_to_string(self.index_path(&hash)) {
+            Ok(name) => name,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let Some(mut token) = self.get(&name)?.filter(|t| t.hash == hash) else {
+            return Ok(None);
+        };
+        if token.is_expired() {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        if token.last_used.is_none_or(|at| (now - at).num_seconds() >= LAST_USED_RESOLUTION_SECS) {
+            token.last_used = Some(now);
+            if let Err(err) = self.save(&token) {
+                log::warn!("Unable to record the use of API token {}: {err}", token.name);
+            }
+        }
+
+        Ok(Some(token))
+    }
+}
@@ -0,0 +1,113 @@
+use super::Access;
+use super::tokens::{TOKEN_PREFIX, TokenOwner, TokenScope, TokenStore, parse_lifetime};
+use std::{fs, time::Duration};
+
+fn console() -> TokenOwner {
+    TokenOwner::Console("console".to_string())
+}
+
+#[test]
+fn created_token_is_verified_and_only_its_hash_is_stored() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+    let (token, secret) = store.create("ci", console(), vec![TokenScope::Read], None).unwrap();
+
+    assert!(secret.starts_with(TOKEN_PREFIX));
+    assert!(!fs::read_to_string(dir.path().join("ci.json")).unwrap().contains(&secret));
+    assert_eq!(token.last_used, None);
+
+    let verified = store.verify(&secret).unwrap().unwrap();
+    assert_eq!(verified.name, "ci");
+    assert!(verified.last_used.is_some());
+    assert!(store.get("ci").unwrap().unwrap().last_used.is_some());
+
+    assert!(store.verify(&format!("{TOKEN_PREFIX}00")).unwrap().is_none());
+    assert!(store.verify("session-id").unwrap().is_none());
+}
+
+#[test]
+fn tokens_are_found_by_their_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+    let (_, ci) = store.create("ci", console(), vec![TokenScope::Read], None).unwrap();
+    let (_, deploy) = store.create("deploy", console(), vec![TokenScope::Query], None).unwrap();
+
+    // Tokens issued before the index are indexed on the first use
+    fs::remove_dir_all(dir.path().join("by-hash")).unwrap();
+    assert_eq!(store.verify(&deploy).unwrap().unwrap().name, "deploy");
+    assert_eq!(store.verify(&ci).unwrap().unwrap().name, "ci");
+
+    assert!(store.revoke("ci").unwrap());
+    assert!(store.verify(&ci).unwrap().is_none());
+    assert_eq!(fs::read_dir(dir.path().join("by-hash")).unwrap().count(), 1);
+    assert!(fs::read_dir(dir.path()).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().ends_with(".tmp")));
+}
+
+#[cfg(unix)]
+#[test]
+fn token_files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+    let (_, secret) = store.create("ci", console(), vec![TokenScope::Read], None).unwrap();
+    store.verify(&secret).unwrap().unwrap();
+
+    assert_eq!(fs::metadata(dir.path().join("ci.json")).unwrap().permissions().mode() & 0o777, 0o600);
+}
+
+#[test]
+fn token_names_are_unique_and_safe() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+
+    assert!(store.create("ci", console(), vec![TokenScope::Read], None).is_ok());
+    assert!(store.create("ci", console(), vec![TokenScope::Read], None).is_err());
+    assert!(store.create("../ci", console(), vec![TokenScope::Read], None).is_err());
+    assert!(store.create("", console(), vec![TokenScope::Read], None).is_err());
+    assert!(store.create("deploy", console(), vec![], None).is_err());
+}
+
+#[test]
+fn revoked_and_expired_tokens_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+    let (_, revoked) = store.create("ci", console(), vec![TokenScope::Read], None).unwrap();
+    let (_, expired) = store.create("old", TokenOwner::User("alice".to_string()), vec![TokenScope::Read], Some(Duration::ZERO)).unwrap();
+
+    assert!(store.revoke("ci").unwrap());
+    assert!(!store.revoke("ci").unwrap());
+    assert!(store.verify(&revoked).unwrap().is_none());
+    assert!(store.verify(&expired).unwrap().is_none());
+
+    let listed = store.list().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].is_expired());
+}
+
+#[test]
+fn scopes_limit_the_access() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = TokenStore::new(dir.path());
+    let (token, _) = store.create("ci", console(), TokenScope::parse_list("read, query").unwrap(), None).unwrap();
+
+    assert!(token.permits(&Access::Read).is_ok());
+    assert!(token.permits(&Access::of_query("cm/files", "*", "", "")).is_ok());
+    assert!(token.permits(&Access::of_query("cmd://cluster/shutdown", "*", "", "")).is_err());
+    assert!(token.permits(&Access::DatastoreWrite).is_err());
+}
+
+#[test]
+fn scope_lists_are_parsed() {
+    assert_eq!(TokenScope::parse_list("").unwrap(), vec![TokenScope::Read]);
+    assert_eq!(TokenScope::parse_list("all").unwrap(), TokenScope::ALL.to_vec());
+    assert_eq!(TokenScope::parse_list("query,Query,datastore-write").unwrap(), vec![TokenScope::Query, TokenScope::DatastoreWrite]);
+    assert!(TokenScope::parse_list("read,admin").is_err());
+}
+
+#[test]
+fn lifetimes_are_parsed() {
+    assert_eq!(parse_lifetime("").unwrap(), None);
+    assert_eq!(parse_lifetime("90d").unwrap(), Some(Duration::from_secs(90 * 86400)));
+    assert!(parse_lifetime("forever").is_err());
+}
@@ -4,6 +4,7 @@ pub mod evthandler;
 pub mod pipeline;
 pub mod pipescript;
 pub mod stdhdl;
+pub mod traitsrefresh;
 
 use lazy_static::lazy_static;
 
@@ -18,7 +19,7 @@ pub mod registry {
     use super::*;
     use crate::{
         intp::conf::EventConfig,
-        reactor::handlers::{chainstop::ChainStopEventHandler, pipeline::PipelineHandler},
+        reactor::handlers::{chainstop::ChainStopEventHandler, pipeline::PipelineHandler, traitsrefresh::TraitsRefreshEventHandler},
     };
     use cstr_stdhdl::ConstraintHandler;
     use dashmap::DashMap;
@@ -50,6 +51,7 @@ pub mod registry {
         REGISTRY_MAP.insert(PipeScriptHandler::id(), |eid, cfg| Box::new(PipeScriptHandler::new(eid, cfg)));
         REGISTRY_MAP.insert(PipelineHandler::id(), |eid, cfg| Box::new(PipelineHandler::new(eid, cfg)));
         REGISTRY_MAP.insert(ChainStopEventHandler::id(), |eid, cfg| Box::new(ChainStopEventHandler::new(eid, cfg)));
+        REGISTRY_MAP.insert(TraitsRefreshEventHandler::id(), |eid, cfg| Box::new(TraitsRefreshEventHandler::new(eid, cfg)));
     }
 
     /// Get all registered handlers.
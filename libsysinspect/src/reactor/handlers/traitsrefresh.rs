@@ -0,0 +1,48 @@
+use super::evthandler::EventHandler;
+use crate::{
+    intp::{
+        actproc::response::ActionResponse,
+        conf::{EventConfig, EventConfigOption},
+    },
+    traits::request_traits_refresh,
+};
+use colored::Colorize;
+
+/// Asks the minion to recompute its traits and push the changed keys to the
+/// master. Typically routed from sensors watching hostname or interfaces.
+#[derive(Default, Debug)]
+pub struct TraitsRefreshEventHandler {
+    eid: String,
+    config: EventConfig,
+}
+
+impl EventHandler for TraitsRefreshEventHandler {
+    fn new(eid: String, cfg: EventConfig) -> Self
+    where
+        Self: Sized,
+    {
+        Self { eid, config: cfg }
+    }
+
+    fn id() -> String
+    where
+        Self: Sized,
+    {
+        "traits-refresh".to_string()
+    }
+
+    fn config(&self) -> Option<EventConfigOption> {
+        self.config.cfg(&Self::id())
+    }
+
+    fn handle(&self, evt: &ActionResponse) {
+        if !evt.match_eid(&self.eid) {
+            return;
+        }
+
+        if self.config().and_then(|cfg| cfg.as_bool("verbose")).unwrap_or(false) {
+            log::info!("[{}] Traits refresh requested by {}", Self::id().bright_blue(), evt.eid());
+        }
+        request_traits_refresh();
+    }
+}
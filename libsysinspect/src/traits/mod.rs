@@ -6,13 +6,14 @@ pub mod systraits;
 use crate::cfg::mmconf::MinionConfig;
 use indexmap::{IndexMap, IndexSet};
 use libcommon::SysinspectError;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use pest::Parser;
 use pest_derive::Parser;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use systraits::SystemTraits;
+use tokio::sync::Notify;
 
 #[cfg(test)]
 mod osinfo_ut;
@@ -85,6 +86,12 @@ pub struct TraitsTransportPayload {
     /// Keys that originated from executable trait functions.
     #[serde(default)]
     pub fn_keys: Vec<String>,
+    /// Set if `traits` carries only the keys changed since the last sync.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delta: bool,
+    /// Keys that disappeared since the last sync. Only used in delta payloads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
 }
 
 impl TraitsTransportPayload {
@@ -97,11 +104,41 @@ impl TraitsTransportPayload {
             }
             Value::Object(map) => {
                 let traits = serde_json::from_value(Value::Object(map))?;
-                Ok(Self { traits, static_keys: vec![], fn_keys: vec![] })
+                Ok(Self { traits, static_keys: vec![], fn_keys: vec![], ..Default::default() })
             }
             _ => Err(SysinspectError::SerializationError("Traits payload must be a JSON object".to_string())),
         }
     }
+
+    /// Build a delta payload with only the keys that changed since `previous`.
+    ///
+    /// Source key lists are always sent in full, as they are small. Returns
+    /// `None` if nothing changed.
+    pub fn delta_from(&self, previous: &TraitsTransportPayload) -> Option<TraitsTransportPayload> {
+        let traits: IndexMap<String, Value> =
+            self.traits.iter().filter(|(k, v)| previous.traits.get(*k) != Some(*v)).map(|(k, v)| (k.clone(), v.clone())).collect();
+        let removed: Vec<String> = previous.traits.keys().filter(|k| !self.traits.contains_key(*k)).cloned().collect();
+        if traits.is_empty() && removed.is_empty() && self.static_keys == previous.static_keys && self.fn_keys == previous.fn_keys {
+            return None;
+        }
+
+        Some(TraitsTransportPayload { traits, static_keys: self.static_keys.clone(), fn_keys: self.fn_keys.clone(), delta: true, removed })
+    }
+}
+
+static _TRAITS_REFRESH: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Ask the running minion to recompute its traits and push what changed.
+///
+/// Used by the `traits-refresh` event handler, so sensors watching hostname,
+/// interfaces etc can trigger a refresh.
+pub fn request_traits_refresh() {
+    _TRAITS_REFRESH.notify_one();
+}
+
+/// Wait until a traits refresh is requested.
+pub async fn traits_refresh_requested() {
+    _TRAITS_REFRESH.notified().await;
 }
 
 #[derive(Parser)]
@@ -213,7 +250,7 @@ pub fn os_display_name(os: &str) -> &str {
     }
 }
 
-fn normalized_profiles(value: &Value) -> Vec<String> {
+pub(crate) fn normalized_profiles(value: &Value) -> Vec<String> {
     let mut names = IndexSet::new();
     match value {
         Value::String(name) if !name.trim().is_empty() => {
@@ -349,7 +349,12 @@ impl SystemTraits {
 
     /// Convert traits into the structured transport payload used for sync with the master.
     pub fn to_transport_value(&self) -> Result<Value, SysinspectError> {
-        Ok(serde_json::to_value(TraitsTransportPayload { traits: self.data.clone(), static_keys: self.yaml_keys(), fn_keys: self.function_keys() })?)
+        Ok(serde_json::to_value(self.to_transport_payload())?)
+    }
+
+    /// Convert traits into the structured transport payload used for sync with the master.
+    pub fn to_transport_payload(&self) -> TraitsTransportPayload {
+        TraitsTransportPayload { traits: self.data.clone(), static_keys: self.yaml_keys(), fn_keys: self.function_keys(), ..Default::default() }
     }
 
     /// Return the origin category for one trait key.
@@ -1,6 +1,9 @@
 use crate::cfg::mmconf::MinionConfig;
 use crate::traits::systraits::SystemTraits;
-use crate::traits::{MASTER_TRAITS_FILE, TraitUpdateRequest, current_os_type, effective_profiles, ensure_master_traits_file, os_display_name};
+use crate::traits::{
+    MASTER_TRAITS_FILE, TraitUpdateRequest, TraitsTransportPayload, current_os_type, effective_profiles, ensure_master_traits_file, os_display_name,
+};
+use serde_json::json;
 use std::fs;
 
 #[test]
@@ -177,3 +180,27 @@ fn os_display_name_keeps_android_distinct_from_linux() {
     assert_eq!(os_display_name("linux"), "Linux");
     assert!(!current_os_type().is_empty(), "current os type should be available");
 }
+
+#[test]
+fn transport_delta_carries_only_changed_and_removed_keys() {
+    let previous = TraitsTransportPayload {
+        traits: [("system.kernel".to_string(), json!("6.1")), ("system.hostname".to_string(), json!("web1")), ("gone".to_string(), json!(1))]
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    let mut current = previous.clone();
+    assert!(current.delta_from(&previous).is_none());
+
+    current.traits.insert("system.kernel".to_string(), json!("6.8"));
+    current.traits.shift_remove("gone");
+    let delta = current.delta_from(&previous).unwrap_or_else(|| panic!("delta expected"));
+    assert!(delta.delta);
+    assert_eq!(delta.traits.len(), 1);
+    assert_eq!(delta.traits.get("system.kernel"), Some(&json!("6.8")));
+    assert_eq!(delta.removed, vec!["gone".to_string()]);
+
+    // Delta flag survives the wire
+    let parsed = TraitsTransportPayload::from_json_str(&serde_json::to_string(&delta).unwrap()).unwrap();
+    assert_eq!(parsed, delta);
+}
@@ -5,6 +5,9 @@ pub mod replay;
 pub mod rqtypes;
 pub mod secure;
 
+#[cfg(test)]
+mod payload_ut;
+
 #[cfg(test)]
 mod replay_ut;
 
@@ -147,6 +147,9 @@ pub struct PingPayload {
 
     #[serde(rename = "cpu")]
     cpu_usage: f32, // CPU usage percentage
+
+    #[serde(rename = "sn", default)]
+    sensors: Vec<SensorHealth>, // health of the running sensors
 }
 
 impl PingPayload {
@@ -169,6 +172,76 @@ impl PingPayload {
     pub fn cpu_usage(&self) -> f32 {
         self.cpu_usage
     }
+
+    /// Get health of the sensors
+    pub fn sensors(&self) -> &Vec<SensorHealth> {
+        &self.sensors
+    }
+}
+
+/// Health of one sensor on a minion, sent with every pong.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct SensorHealth {
+    /// Sensor Id
+    pub sid: String,
+
+    /// Listener of the sensor, e.g. `sys.proc`
+    pub listener: String,
+
+    /// Last lifecycle state: started, stopped, restarted or crashed
+    pub state: String,
+
+    /// How many times the sensor was restarted
+    #[serde(default)]
+    pub restarts: u64,
+
+    /// Events emitted by the sensor
+    #[serde(default)]
+    pub emitted: u64,
+
+    /// Events that were lost
+    #[serde(default)]
+    pub dropped: u64,
+
+    /// Events registered to the reactor in batches
+    #[serde(default, alias = "coalesced")]
+    pub batched: u64,
+
+    /// Events waiting for the reactor
+    #[serde(default)]
+    pub queued: u64,
+
+    /// Message of the last crash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    /// Seconds since the last heartbeat, i.e. since the sensor last queued an event or had it passed to the reactor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat: Option<u64>,
+
+    /// Seconds since the sensor was last started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<u64>,
+}
+
+impl SensorHealth {
+    /// Seconds without a heartbeat after which a running sensor is considered stale.
+    pub const STALE_AFTER: u64 = 60;
+
+    /// Returns `true` if the sensor is running.
+    pub fn is_running(&self) -> bool {
+        matches!(self.state.as_str(), "started" | "restarted")
+    }
+
+    /// Returns `true` if the sensor is running, but had no heartbeat for too long since it was started.
+    /// A heartbeat from before a restart does not count.
+    pub fn is_stale(&self) -> bool {
+        let quiet = match (self.heartbeat, self.uptime) {
+            (Some(heartbeat), Some(uptime)) => Some(heartbeat.min(uptime)),
+            (heartbeat, uptime) => heartbeat.or(uptime),
+        };
+        self.is_running() && quiet.is_some_and(|q| q > Self::STALE_AFTER)
+    }
 }
 
 /// Master reply sent after a minion registration attempt.
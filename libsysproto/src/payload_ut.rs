@@ -0,0 +1,23 @@
+use crate::payload::SensorHealth;
+
+fn health(state: &str, heartbeat: Option<u64>, uptime: Option<u64>) -> SensorHealth {
+    SensorHealth { sid: "k".to_string(), listener: "sys.kernel".to_string(), state: state.to_string(), heartbeat, uptime, ..Default::default() }
+}
+
+#[test]
+fn sensor_without_heartbeat_is_stale_once_it_ran_long_enough() {
+    assert!(!health("started", None, Some(SensorHealth::STALE_AFTER)).is_stale());
+    assert!(health("started", None, Some(SensorHealth::STALE_AFTER + 1)).is_stale());
+    assert!(health("restarted", Some(600), Some(600)).is_stale());
+    assert!(!health("stopped", None, Some(600)).is_stale());
+}
+
+#[test]
+fn heartbeat_before_a_restart_does_not_make_the_sensor_stale() {
+    assert!(!health("restarted", Some(600), Some(5)).is_stale());
+    assert!(!health("started", Some(5), Some(600)).is_stale());
+
+    // Older minions send no uptime
+    assert!(health("started", Some(600), None).is_stale());
+    assert!(!health("started", None, None).is_stale());
+}
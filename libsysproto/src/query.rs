@@ -16,13 +16,21 @@ pub mod commands {
     // Sync the entire cluster
     pub const CLUSTER_SYNC: &str = "cluster/sync";
 
-    // Restart the entire cluster
-    // TODO: Not implemented yet
+    // Reboot selected minion hosts in rolling batches
     pub const CLUSTER_REBOOT: &str = "cluster/reboot";
 
+    // Show the progress of the current rolling reboot
+    pub const CLUSTER_REBOOT_STATUS: &str = "cluster/reboot/status";
+
+    // Show recorded placement decisions of virtual minions
+    pub const CLUSTER_PLACEMENT: &str = "cluster/placement";
+
     // Rotate RSA/AES on the entire cluster
     pub const CLUSTER_ROTATE: &str = "cluster/rotate";
 
+    // List commands queued for minions with their receipts
+    pub const CLUSTER_COMMANDS: &str = "cluster/commands";
+
     // Report transport status for one or more minions
     pub const CLUSTER_TRANSPORT_STATUS: &str = "cluster/transport/status";
 
@@ -44,15 +52,27 @@ pub mod commands {
     // Update master-managed static traits on minions
     pub const CLUSTER_TRAITS_UPDATE: &str = "cluster/traits/update";
 
+    // List time-stamped trait changes of minions
+    pub const CLUSTER_TRAITS_HISTORY: &str = "cluster/traits/history";
+
     // Manage deployment profiles on the master
     pub const CLUSTER_PROFILE: &str = "cluster/profile";
 
+    // Create, list or revoke Web API tokens
+    pub const CLUSTER_API_TOKENS: &str = "cluster/api-tokens";
+
+    // Query, export or verify the audit journal of operator actions
+    pub const CLUSTER_AUDIT: &str = "cluster/audit";
+
     // Upsert startup inventory / CMDB information for one registered minion
     pub const CLUSTER_CMDB_UPSERT: &str = "cluster/cmdb/upsert";
 
     // List available models on the master
     pub const CLUSTER_MODELS: &str = "cluster/models";
 
+    // Upload, list, compare or roll back model versions
+    pub const CLUSTER_MODEL_VERSIONS: &str = "cluster/model/versions";
+
     // Reload master configuration from disk
     pub const CLUSTER_CONFIG_RELOAD: &str = "cluster/config/reload";
 
@@ -68,6 +88,9 @@ pub mod commands {
     // Send a Unix signal to one process on one specific minion
     pub const CLUSTER_MINION_PROCESS_SIGNAL: &str = "cluster/minion/process/signal";
 
+    // Take a new file integrity baseline on one specific minion
+    pub const CLUSTER_MINION_INTEGRITY_REBASELINE: &str = "cluster/minion/integrity/rebaseline";
+
     // Mark all selected minions as requiring a cluster upgrade/sync
     pub const CLUSTER_MARK_UPGRADE_REQUIRED: &str = "cluster/upgrade/mark";
 
@@ -127,6 +127,10 @@ pub enum RequestType {
     #[serde(rename = "ssp")]
     SensorsSyncResponse,
 
+    /// Minion→Master: a sensor was started, stopped, restarted or has crashed.
+    #[serde(rename = "snt")]
+    SensorNotice,
+
     /// Minion→Master: model cycle completed.
     #[serde(rename = "mack")]
     ModelAck,
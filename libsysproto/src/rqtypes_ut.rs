@@ -89,6 +89,11 @@ fn agent_unknown_is_session_control() {
     assert_eq!(RequestType::AgentUnknown.message_class(), OutboundMessageClass::SessionControl);
 }
 
+#[test]
+fn sensor_notice_is_session_control() {
+    assert_eq!(RequestType::SensorNotice.message_class(), OutboundMessageClass::SessionControl);
+}
+
 #[test]
 fn cycle_ack_is_session_control() {
     assert_eq!(RequestType::CycleAck.message_class(), OutboundMessageClass::SessionControl);
@@ -113,6 +118,7 @@ fn only_the_three_journaled_types_are_durable_data() {
         RequestType::Pong,
         RequestType::SensorsSyncRequest,
         RequestType::SensorsSyncResponse,
+        RequestType::SensorNotice,
         RequestType::CycleAck,
     ] {
         assert_eq!(
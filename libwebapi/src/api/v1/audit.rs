@@ -0,0 +1,235 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{TAG_SYSTEM, minions::authorise_access},
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    http::StatusCode,
+    web::{Data, Query},
+};
+use libcommon::SysinspectError;
+use libsysinspect::{
+    audit::{self, AuditFilter, AuditJournal, AuditRecord},
+    rbac::Access,
+};
+use libsysproto::query::commands::CLUSTER_AUDIT;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Content type of the JSONL export
+pub const AUDIT_EXPORT_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Selection of audit records. Without any filter the whole journal is selected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct AuditQuery {
+    /// RFC 3339 time or a duration back from now, e.g. `24h`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+
+    /// RFC 3339 time or a duration back from now, exclusive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+
+    /// Actor name or glob, e.g. `token:*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+
+    /// Only the newest records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn filter(&self) -> Result<AuditFilter, SysinspectError> {
+        Ok(AuditFilter {
+            since: self.since.as_deref().map(audit::parse_time).transpose()?,
+            until: self.until.as_deref().map(audit::parse_time).transpose()?,
+            actor: self.actor.clone(),
+            limit: self.limit,
+        })
+    }
+}
+
+/// Recorded operator action
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditRecordInfo {
+    pub seq: u64,
+
+    /// RFC 3339 timestamp
+    pub timestamp: String,
+
+    /// Console client, Web API user, `token:<name>` or `scheduler`
+    pub actor: String,
+
+    /// console, webapi or scheduler
+    pub channel: String,
+    pub action: String,
+    pub target: String,
+
+    /// success, denied or failure
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// Hash of the previous record
+    pub prev: String,
+    pub hash: String,
+}
+
+impl From<AuditRecord> for AuditRecordInfo {
+    fn from(record: AuditRecord) -> Self {
+        AuditRecordInfo {
+            seq: record.seq,
+            timestamp: record.timestamp.to_rfc3339(),
+            actor: record.actor,
+            channel: record.channel.to_string(),
+            action: record.action,
+            target: record.target,
+            outcome: record.outcome.to_string(),
+            detail: record.detail,
+            prev: record.prev,
+            hash: record.hash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditListResponse {
+    /// Oldest first
+    pub records: Vec<AuditRecordInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditVerifyResponse {
+    /// Whether every record is intact and linked to its predecessor
+    pub valid: bool,
+
+    /// Number of verified records
+    pub records: u64,
+
+    /// Hash of the last record
+    pub head: String,
+
+    /// Where the chain is broken
+    #[serde(default)]
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditErrorResponse {
+    pub error: String,
+}
+
+fn audit_error(status: StatusCode, err: impl ToString) -> HttpResponse {
+    HttpResponse::build(status).json(AuditErrorResponse { error: err.to_string() })
+}
+
+/// Authorise reading the audit journal. It needs the permission of the `cluster/audit` command.
+async fn audit_journal(req: &HttpRequest, master: &MasterInterfaceType) -> Result<AuditJournal, HttpResponse> {
+    authorise_access(req, master, &Access::Command(CLUSTER_AUDIT)).await.map_err(|err| audit_error(err.status(), err))?;
+    Ok(AuditJournal::new(&master.lock().await.cfg().await.audit_root()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("since" = Option<String>, Query, description = "RFC 3339 time or a duration back from now, e.g. 24h"),
+        ("until" = Option<String>, Query, description = "RFC 3339 time or a duration back from now, exclusive"),
+        ("actor" = Option<String>, Query, description = "Actor name or glob, e.g. token:*"),
+        ("limit" = Option<usize>, Query, description = "Only the newest records")
+    ),
+    responses(
+        (status = 200, description = "Audit records, oldest first", body = AuditListResponse),
+        (status = 400, description = "Invalid filter", body = AuditErrorResponse),
+        (status = 401, description = "Unauthorized", body = AuditErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = AuditErrorResponse),
+        (status = 500, description = "Unreadable journal", body = AuditErrorResponse)
+    )
+)]
+#[get("/api/v1/audit")]
+pub async fn audit_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<AuditQuery>) -> impl Responder {
+    let journal = match audit_journal(&req, &master).await {
+        Ok(journal) => journal,
+        Err(response) => return response,
+    };
+    let filter = match q.filter() {
+        Ok(filter) => filter,
+        Err(err) => return audit_error(StatusCode::BAD_REQUEST, err),
+    };
+
+    match journal.query(&filter) {
+        Ok(records) => HttpResponse::Ok().json(AuditListResponse { records: records.into_iter().map(AuditRecordInfo::from).collect() }),
+        Err(SysinspectError::InvalidQuery(err)) => audit_error(StatusCode::BAD_REQUEST, err),
+        Err(err) => audit_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/export",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("since" = Option<String>, Query, description = "RFC 3339 time or a duration back from now, e.g. 24h"),
+        ("until" = Option<String>, Query, description = "RFC 3339 time or a duration back from now, exclusive"),
+        ("actor" = Option<String>, Query, description = "Actor name or glob, e.g. token:*"),
+        ("limit" = Option<usize>, Query, description = "Only the newest records")
+    ),
+    responses(
+        (status = 200, description = "Audit records as JSONL, one record per line, oldest first", body = String, content_type = "application/x-ndjson"),
+        (status = 400, description = "Invalid filter", body = AuditErrorResponse),
+        (status = 401, description = "Unauthorized", body = AuditErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = AuditErrorResponse),
+        (status = 500, description = "Unreadable journal", body = AuditErrorResponse)
+    )
+)]
+#[get("/api/v1/audit/export")]
+pub async fn audit_export_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<AuditQuery>) -> impl Responder {
+    let journal = match audit_journal(&req, &master).await {
+        Ok(journal) => journal,
+        Err(response) => return response,
+    };
+    let filter = match q.filter() {
+        Ok(filter) => filter,
+        Err(err) => return audit_error(StatusCode::BAD_REQUEST, err),
+    };
+
+    match journal.export(&filter) {
+        Ok(jsonl) => HttpResponse::Ok().content_type(AUDIT_EXPORT_CONTENT_TYPE).body(jsonl),
+        Err(SysinspectError::InvalidQuery(err)) => audit_error(StatusCode::BAD_REQUEST, err),
+        Err(err) => audit_error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit/verify",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Result of the hash chain verification", body = AuditVerifyResponse),
+        (status = 401, description = "Unauthorized", body = AuditErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = AuditErrorResponse)
+    )
+)]
+#[get("/api/v1/audit/verify")]
+pub async fn audit_verify_handler(req: HttpRequest, master: Data<MasterInterfaceType>) -> impl Responder {
+    let journal = match audit_journal(&req, &master).await {
+        Ok(journal) => journal,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok().json(match journal.verify() {
+        Ok(v) => AuditVerifyResponse { valid: true, records: v.records, head: v.head, error: String::new() },
+        Err(err) => AuditVerifyResponse { valid: false, records: 0, head: String::new(), error: err.to_string() },
+    })
+}
@@ -0,0 +1,105 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{TAG_MINIONS, minions::authorise_access},
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    web::{Data, Query},
+};
+use libsysinspect::{console::ConsoleQueuedCommandRow, rbac::Access};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CommandListQuery {
+    /// Only commands of this minion
+    pub mid: Option<String>,
+
+    /// Only commands in this state
+    pub state: Option<String>,
+}
+
+/// Command queued by the master for a minion that was offline
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QueuedCommandInfo {
+    pub id: u64,
+    pub minion_id: String,
+    pub cycle_id: String,
+    pub scheme: String,
+
+    /// pending, replayed, delivered, executed, failed, expired or superseded
+    pub state: String,
+    pub supersede: Option<String>,
+
+    /// RFC 3339 timestamps
+    pub enqueued_at: String,
+    pub expires_at: Option<String>,
+    pub delivered_at: Option<String>,
+    pub finished_at: Option<String>,
+
+    pub error: Option<String>,
+}
+
+impl From<ConsoleQueuedCommandRow> for QueuedCommandInfo {
+    fn from(row: ConsoleQueuedCommandRow) -> Self {
+        QueuedCommandInfo {
+            id: row.id,
+            minion_id: row.minion_id,
+            cycle_id: row.cycle_id,
+            scheme: row.scheme,
+            state: row.state,
+            supersede: row.supersede,
+            enqueued_at: row.enqueued_at.to_rfc3339(),
+            expires_at: row.expires_at.map(|t| t.to_rfc3339()),
+            delivered_at: row.delivered_at.map(|t| t.to_rfc3339()),
+            finished_at: row.finished_at.map(|t| t.to_rfc3339()),
+            error: row.error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommandListResponse {
+    pub commands: Vec<QueuedCommandInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CommandErrorResponse {
+    pub error: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/commands",
+    tag = TAG_MINIONS,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("mid" = Option<String>, Query, description = "Only commands of this minion System Id"),
+        ("state" = Option<String>, Query, description = "Only commands in this state: pending, replayed, delivered, executed, failed, expired or superseded")
+    ),
+    responses(
+        (status = 200, description = "Queued commands with their receipts", body = CommandListResponse),
+        (status = 401, description = "Unauthorized", body = CommandErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = CommandErrorResponse),
+        (status = 500, description = "Error", body = CommandErrorResponse)
+    )
+)]
+#[get("/api/v1/commands")]
+pub async fn command_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<CommandListQuery>) -> impl Responder {
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return HttpResponse::build(err.status()).json(CommandErrorResponse { error: err.to_string() });
+    }
+
+    match master.lock().await.commands(q.mid.clone()).await {
+        Ok(rows) => HttpResponse::Ok().json(CommandListResponse {
+            commands: rows
+                .into_iter()
+                .filter(|row| q.state.as_deref().is_none_or(|state| row.state.eq_ignore_ascii_case(state)))
+                .map(QueuedCommandInfo::from)
+                .collect(),
+        }),
+        Err(err) => HttpResponse::InternalServerError().json(CommandErrorResponse { error: err.to_string() }),
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{TAG_MINIONS, minions::authorise_access},
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    web::{Data, Path, Query},
+};
+use libsysinspect::rbac::Access;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+#[cfg(test)]
+#[path = "cycles_ut.rs"]
+mod cycles_ut;
+
+/// Page size when the client does not ask for one
+pub const CYCLES_PAGE_LIMIT: usize = 50;
+
+/// Largest page a client can ask for
+pub const CYCLES_PAGE_LIMIT_MAX: usize = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CyclePageQuery {
+    /// Number of records to skip
+    pub offset: Option<usize>,
+
+    /// Number of records to return
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CycleMinionsQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+
+    /// Only minions that have at least one event with this outcome
+    pub outcome: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CycleEventsQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+
+    /// Only events with this outcome: success, error or not_applicable
+    pub outcome: Option<String>,
+
+    /// Only events of this entity
+    pub entity: Option<String>,
+}
+
+/// Recorded query cycle (call session)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleInfo {
+    pub cycle_id: String,
+    pub query: String,
+
+    /// RFC 3339 timestamp
+    pub started_at: String,
+
+    /// Version of the model the cycle ran, unknown if the model was changed by hand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<u64>,
+
+    /// Content hash of the model the cycle ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_hash: Option<String>,
+}
+
+/// Minion that answered within a cycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleMinionInfo {
+    pub minion_id: String,
+    pub hostname: String,
+    pub ipaddr: String,
+
+    /// Number of events the minion returned
+    pub events: usize,
+
+    /// Number of events that ended with an error
+    pub errors: usize,
+
+    /// Distinct event outcomes of this minion
+    pub outcomes: Vec<String>,
+}
+
+/// Event returned by a minion within a cycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleEventInfo {
+    pub cycle_id: String,
+    pub entity_id: String,
+    pub action_id: String,
+    pub state_id: String,
+
+    /// success, error or not_applicable
+    pub outcome: String,
+    pub retcode: i64,
+    pub message: String,
+    pub timestamp: String,
+
+    #[schema(value_type = Object)]
+    pub constraints: HashMap<String, Value>,
+
+    #[schema(value_type = Object)]
+    pub response: HashMap<String, Value>,
+}
+
+impl CycleEventInfo {
+    pub fn new(
+        cycle_id: String, entity_id: String, action_id: String, state_id: String, timestamp: String, constraints: HashMap<String, Value>,
+        response: HashMap<String, Value>,
+    ) -> Self {
+        let retcode = response.get("retcode").and_then(Value::as_i64).unwrap_or_default();
+        let outcome = response
+            .get("outcome")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| if retcode == 0 { "success".to_string() } else { "error".to_string() });
+        let message = response.get("message").and_then(Value::as_str).unwrap_or_default().to_string();
+
+        CycleEventInfo { cycle_id, entity_id, action_id, state_id, outcome, retcode, message, timestamp, constraints, response }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.outcome == "error"
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleListResponse {
+    pub cycles: Vec<CycleInfo>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleMinionsResponse {
+    pub cycle_id: String,
+    pub minions: Vec<CycleMinionInfo>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CycleEventsResponse {
+    pub cycle_id: String,
+    pub minion_id: String,
+    pub events: Vec<CycleEventInfo>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CycleErrorResponse {
+    pub error: String,
+}
+
+/// Cut a page out of the records. Returns the page, total amount of records, offset and the effective limit.
+pub(crate) fn paginate<T>(items: Vec<T>, offset: Option<usize>, limit: Option<usize>) -> (Vec<T>, usize, usize, usize) {
+    let total = items.len();
+    let offset = offset.unwrap_or_default();
+    let limit = limit.unwrap_or(CYCLES_PAGE_LIMIT).clamp(1, CYCLES_PAGE_LIMIT_MAX);
+    (items.into_iter().skip(offset).take(limit).collect(), total, offset, limit)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/cycles",
+    tag = TAG_MINIONS,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("offset" = Option<usize>, Query, description = "Number of cycles to skip"),
+        ("limit" = Option<usize>, Query, description = "Number of cycles to return, 50 by default, 500 at most")
+    ),
+    responses(
+        (status = 200, description = "Recorded query cycles, newest first", body = CycleListResponse),
+        (status = 401, description = "Unauthorized", body = CycleErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = CycleErrorResponse),
+        (status = 500, description = "Error", body = CycleErrorResponse)
+    )
+)]
+#[get("/api/v1/cycles")]
+pub async fn cycle_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<CyclePageQuery>) -> impl Responder {
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return HttpResponse::build(err.status()).json(CycleErrorResponse { error: err.to_string() });
+    }
+
+    match master.lock().await.cycles().await {
+        Ok(mut cycles) => {
+            cycles.reverse();
+            let (cycles, total, offset, limit) = paginate(cycles, q.offset, q.limit);
+            HttpResponse::Ok().json(CycleListResponse { cycles, total, offset, limit })
+        }
+        Err(err) => HttpResponse::InternalServerError().json(CycleErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/cycles/{cycle_id}/minions",
+    tag = TAG_MINIONS,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("cycle_id" = String, Path, description = "Cycle Id, as returned by the query endpoint"),
+        ("offset" = Option<usize>, Query, description = "Number of minions to skip"),
+        ("limit" = Option<usize>, Query, description = "Number of minions to return, 50 by default, 500 at most"),
+        ("outcome" = Option<String>, Query, description = "Only minions with at least one event of this outcome")
+    ),
+    responses(
+        (status = 200, description = "Minions that answered within the cycle", body = CycleMinionsResponse),
+        (status = 401, description = "Unauthorized", body = CycleErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = CycleErrorResponse),
+        (status = 404, description = "Cycle not found", body = CycleErrorResponse),
+        (status = 500, description = "Error", body = CycleErrorResponse)
+    )
+)]
+#[get("/api/v1/cycles/{cycle_id}/minions")]
+pub async fn cycle_minions_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, cycle_id: Path<String>, q: Query<CycleMinionsQuery>,
+) -> impl Responder {
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return HttpResponse::build(err.status()).json(CycleErrorResponse { error: err.to_string() });
+    }
+
+    let cycle_id = cycle_id.into_inner();
+    match master.lock().await.cycle_minions(&cycle_id).await {
+        Ok(Some(minions)) => {
+            let minions = minions
+                .into_iter()
+                .filter(|m| q.outcome.as_deref().is_none_or(|outcome| m.outcomes.iter().any(|o| o.eq_ignore_ascii_case(outcome))))
+                .collect();
+            let (minions, total, offset, limit) = paginate(minions, q.offset, q.limit);
+            HttpResponse::Ok().json(CycleMinionsResponse { cycle_id, minions, total, offset, limit })
+        }
+        Ok(None) => HttpResponse::NotFound().json(CycleErrorResponse { error: format!("Cycle {cycle_id} not found") }),
+        Err(err) => HttpResponse::InternalServerError().json(CycleErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/cycles/{cycle_id}/minions/{mid}/events",
+    tag = TAG_MINIONS,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("cycle_id" = String, Path, description = "Cycle Id, as returned by the query endpoint"),
+        ("mid" = String, Path, description = "Minion System Id"),
+        ("offset" = Option<usize>, Query, description = "Number of events to skip"),
+        ("limit" = Option<usize>, Query, description = "Number of events to return, 50 by default, 500 at most"),
+        ("outcome" = Option<String>, Query, description = "Only events of this outcome: success, error or not_applicable"),
+        ("entity" = Option<String>, Query, description = "Only events of this entity")
+    ),
+    responses(
+        (status = 200, description = "Events the minion returned within the cycle", body = CycleEventsResponse),
+        (status = 401, description = "Unauthorized", body = CycleErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = CycleErrorResponse),
+        (status = 404, description = "Cycle or minion not found", body = CycleErrorResponse),
+        (status = 500, description = "Error", body = CycleErrorResponse)
+    )
+)]
+#[get("/api/v1/cycles/{cycle_id}/minions/{mid}/events")]
+pub async fn cycle_events_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, path: Path<(String, String)>, q: Query<CycleEventsQuery>,
+) -> impl Responder {
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return HttpResponse::build(err.status()).json(CycleErrorResponse { error: err.to_string() });
+    }
+
+    let (cycle_id, mid) = path.into_inner();
+    match master.lock().await.cycle_events(&cycle_id, &mid).await {
+        Ok(Some(events)) => {
+            let events = events
+                .into_iter()
+                .filter(|e| q.outcome.as_deref().is_none_or(|outcome| e.outcome.eq_ignore_ascii_case(outcome)))
+                .filter(|e| q.entity.as_deref().is_none_or(|entity| e.entity_id == entity))
+                .collect();
+            let (events, total, offset, limit) = paginate(events, q.offset, q.limit);
+            HttpResponse::Ok().json(CycleEventsResponse { cycle_id, minion_id: mid, events, total, offset, limit })
+        }
+        Ok(None) => HttpResponse::NotFound().json(CycleErrorResponse { error: format!("Minion {mid} has no results in cycle {cycle_id}") }),
+        Err(err) => HttpResponse::InternalServerError().json(CycleErrorResponse { error: err.to_string() }),
+    }
+}
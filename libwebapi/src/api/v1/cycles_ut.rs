@@ -0,0 +1,38 @@
+use super::{CYCLES_PAGE_LIMIT, CYCLES_PAGE_LIMIT_MAX, CycleEventInfo, paginate};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn event(response: serde_json::Value) -> CycleEventInfo {
+    CycleEventInfo::new(
+        "cycle".to_string(),
+        "entity".to_string(),
+        "action".to_string(),
+        "$".to_string(),
+        String::new(),
+        HashMap::new(),
+        serde_json::from_value(response).unwrap(),
+    )
+}
+
+#[test]
+fn paginate_uses_default_limit_and_reports_total() {
+    let (page, total, offset, limit) = paginate((0..120).collect::<Vec<_>>(), None, None);
+    assert_eq!((page.len(), total, offset, limit), (CYCLES_PAGE_LIMIT, 120, 0, CYCLES_PAGE_LIMIT));
+}
+
+#[test]
+fn paginate_clamps_limit_and_skips_offset() {
+    let (page, total, offset, limit) = paginate((0..1000).collect::<Vec<_>>(), Some(990), Some(10_000));
+    assert_eq!((page, total, offset, limit), ((990..1000).collect::<Vec<_>>(), 1000, 990, CYCLES_PAGE_LIMIT_MAX));
+    assert!(paginate(vec![1, 2, 3], Some(5), None).0.is_empty());
+}
+
+#[test]
+fn event_outcome_falls_back_to_retcode() {
+    assert_eq!(event(json!({"retcode": 0, "message": "ok"})).outcome, "success");
+    assert_eq!(event(json!({"retcode": 1})).outcome, "error");
+    assert!(event(json!({"retcode": 1})).is_error());
+
+    let na = event(json!({"retcode": 0, "outcome": "not_applicable", "message": "skipped"}));
+    assert_eq!((na.outcome.as_str(), na.message.as_str()), ("not_applicable", "skipped"));
+}
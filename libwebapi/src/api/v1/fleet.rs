@@ -0,0 +1,929 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{TAG_FLEET, minions::authorise_access},
+    audit::audit_target,
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, delete, get,
+    http::StatusCode,
+    patch, post, put,
+    web::{Data, Json, Path, Query},
+};
+use libsysinspect::{
+    audit,
+    console::{ConsoleMinionInfoRow, ConsoleOnlineMinionRow, ConsolePayload, ConsoleQuery, ConsoleTraitChangeRow, ConsoleTransportStatusRow},
+    rbac::{Access, Target},
+};
+use libsysproto::query::{
+    SCHEME_COMMAND,
+    commands::{
+        CLUSTER_MARK_UPGRADE_REQUIRED, CLUSTER_MINION_INFO, CLUSTER_ONLINE_MINIONS, CLUSTER_PROFILE, CLUSTER_REMOVE_MINION, CLUSTER_ROTATE,
+        CLUSTER_TRAITS_HISTORY, CLUSTER_TRAITS_UPDATE, CLUSTER_TRANSPORT_STATUS, CLUSTER_UPGRADE_STATUS,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use utoipa::ToSchema;
+
+/// Minions targeted by a fleet request. Without any selector all minions are targeted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct MinionSelector {
+    /// Hostname glob or comma-separated hostnames
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+
+    /// Traits query, e.g. `system.os.name:Ubuntu`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub traits: String,
+
+    /// Minion System Id
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub mid: String,
+}
+
+impl MinionSelector {
+    fn of_minion(mid: &str) -> Self {
+        MinionSelector { mid: mid.to_string(), ..Default::default() }
+    }
+
+    fn target(&self) -> Target<'_> {
+        Target::new(&self.query, &self.traits, &self.mid)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TraitHistoryQuery {
+    #[serde(flatten)]
+    pub selector: MinionSelector,
+
+    /// Only changes of this trait
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct KeyStatusQuery {
+    #[serde(flatten)]
+    pub selector: MinionSelector,
+
+    /// all, pending or idle
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct MinionRemoveQuery {
+    /// Also stop the minion and remove its files over SSH, if it was started by hopstart
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ProfileListQuery {
+    /// Glob of profile names
+    pub name: Option<String>,
+
+    /// List library selectors instead of module selectors
+    #[serde(default)]
+    pub library: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TraitsSetRequest {
+    #[serde(flatten)]
+    pub selector: MinionSelector,
+
+    /// Master-managed traits to set
+    #[schema(value_type = Object)]
+    pub traits: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TraitsUnsetRequest {
+    #[serde(flatten)]
+    pub selector: MinionSelector,
+
+    /// Master-managed traits to remove
+    #[serde(default)]
+    pub keys: Vec<String>,
+
+    /// Remove all master-managed traits instead
+    #[serde(default)]
+    pub reset: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProfileCreateRequest {
+    /// Profile name: letters, digits, '.', '_' or '-'
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ProfileUpdateRequest {
+    /// Selectors to add, exact names or glob patterns
+    #[serde(default)]
+    pub add: Vec<String>,
+
+    /// Selectors to remove
+    #[serde(default)]
+    pub remove: Vec<String>,
+
+    /// Update library selectors instead of module selectors
+    #[serde(default)]
+    pub library: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct KeyRotateRequest {
+    #[serde(flatten)]
+    pub selector: MinionSelector,
+
+    /// Reason recorded with the rotation, `manual` by default
+    pub reason: Option<String>,
+
+    /// Seconds the previous key stays valid, 900 by default
+    pub grace_seconds: Option<u64>,
+}
+
+/// Registered minion
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MinionInfo {
+    pub minion_id: String,
+    pub fqdn: String,
+    pub hostname: String,
+    pub ip: String,
+    pub alive: bool,
+    pub version: String,
+
+    /// Newest version in the repository for the minion platform, if known
+    pub target_version: String,
+    pub outdated: bool,
+    pub upgrade_required: bool,
+    pub upgrade_unreachable: bool,
+    pub os_distribution: String,
+    pub os_name: String,
+    pub os_version: String,
+    pub kernel: String,
+}
+
+impl From<ConsoleOnlineMinionRow> for MinionInfo {
+    fn from(row: ConsoleOnlineMinionRow) -> Self {
+        MinionInfo {
+            minion_id: row.minion_id,
+            fqdn: row.fqdn,
+            hostname: row.hostname,
+            ip: row.ip,
+            alive: row.alive,
+            version: row.version,
+            target_version: row.target_version,
+            outdated: row.outdated,
+            upgrade_required: row.upgrade_required,
+            upgrade_unreachable: row.upgrade_unreachable,
+            os_distribution: row.os_distribution,
+            os_name: row.os_name,
+            os_version: row.os_version,
+            kernel: row.kernel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MinionListResponse {
+    pub minions: Vec<MinionInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MinionTraitInfo {
+    pub key: String,
+    #[schema(value_type = Object)]
+    pub value: Value,
+
+    /// preset, static or function
+    pub source: String,
+}
+
+impl From<ConsoleMinionInfoRow> for MinionTraitInfo {
+    fn from(row: ConsoleMinionInfoRow) -> Self {
+        MinionTraitInfo { key: row.key, value: row.value, source: serde_name(&row.source) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MinionDetailsResponse {
+    pub minion_id: String,
+    pub traits: Vec<MinionTraitInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TraitChangeInfo {
+    pub minion_id: String,
+    pub host: String,
+    pub key: String,
+
+    /// Missing if the trait appeared
+    #[schema(value_type = Option<Object>)]
+    pub old: Option<Value>,
+
+    /// Missing if the trait disappeared
+    #[schema(value_type = Option<Object>)]
+    pub new: Option<Value>,
+
+    /// RFC 3339 timestamp
+    pub changed_at: String,
+}
+
+impl From<ConsoleTraitChangeRow> for TraitChangeInfo {
+    fn from(row: ConsoleTraitChangeRow) -> Self {
+        TraitChangeInfo {
+            minion_id: row.minion_id,
+            host: row.host,
+            key: row.key,
+            old: row.old,
+            new: row.new,
+            changed_at: row.changed_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TraitHistoryResponse {
+    pub changes: Vec<TraitChangeInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProfileListResponse {
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProfileResponse {
+    pub name: String,
+
+    /// Profile definition, as stored on the master
+    pub definition: String,
+}
+
+/// Transport key state of a minion
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KeyInfo {
+    pub minion_id: String,
+    pub fqdn: String,
+    pub hostname: String,
+    pub active_key_id: Option<String>,
+
+    /// RFC 3339 timestamps
+    pub last_handshake_at: Option<String>,
+    pub last_rotated_at: Option<String>,
+
+    /// idle, pending, in_progress or rollback_ready. Missing if the minion has no managed transport state.
+    pub rotation: Option<String>,
+}
+
+impl From<ConsoleTransportStatusRow> for KeyInfo {
+    fn from(row: ConsoleTransportStatusRow) -> Self {
+        KeyInfo {
+            minion_id: row.minion_id,
+            fqdn: row.fqdn,
+            hostname: row.hostname,
+            active_key_id: row.active_key_id,
+            last_handshake_at: row.last_handshake_at.map(|t| t.to_rfc3339()),
+            last_rotated_at: row.last_rotated_at.map(|t| t.to_rfc3339()),
+            rotation: row.rotation.as_ref().map(serde_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KeyListResponse {
+    pub keys: Vec<KeyInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeStatusResponse {
+    /// Minions marked as requiring an upgrade
+    pub required: usize,
+
+    /// Marked minions that were unreachable during the last upgrade
+    pub unreachable: usize,
+
+    /// Minions waiting for the post-upgrade restart
+    pub pending_post_upgrade: usize,
+}
+
+/// Result of a cluster command
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FleetAckResponse {
+    pub action: String,
+    pub target: String,
+    pub count: usize,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FleetErrorResponse {
+    pub error: String,
+}
+
+/// Serialised name of a unit enum variant, e.g. `in_progress`
+fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn fleet_error(status: StatusCode, error: impl ToString) -> HttpResponse {
+    HttpResponse::build(status).json(FleetErrorResponse { error: error.to_string() })
+}
+
+async fn authorise_fleet(req: &HttpRequest, master: &MasterInterfaceType, access: &Access<'_>) -> Result<(), HttpResponse> {
+    authorise_access(req, master, access).await.map(|_| ()).map_err(|err| fleet_error(err.status(), err))
+}
+
+/// Run the cluster command on the master and return its payload. Failed commands are bad requests.
+async fn cluster_command(
+    master: &MasterInterfaceType, command: &str, selector: &MinionSelector, context: Option<Value>,
+) -> Result<ConsolePayload, HttpResponse> {
+    let query = ConsoleQuery {
+        model: format!("{SCHEME_COMMAND}{command}"),
+        query: if selector.query.trim().is_empty() { "*".to_string() } else { selector.query.clone() },
+        traits: selector.traits.clone(),
+        mid: selector.mid.clone(),
+        context: context.map(|c| c.to_string()).unwrap_or_default(),
+        ttl: None,
+        supersede: None,
+    };
+
+    // The master lock must be released before waiting, the command needs it
+    let rx = master.lock().await.cluster_command(query).await.map_err(|err| fleet_error(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+    let response = rx.await.map_err(|_| fleet_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Master did not finish {command}")))?;
+    if !response.ok {
+        return Err(fleet_error(StatusCode::BAD_REQUEST, response.error));
+    }
+
+    Ok(response.payload)
+}
+
+fn unexpected(payload: ConsolePayload) -> HttpResponse {
+    log::error!("Unexpected cluster command payload: {payload:?}");
+    fleet_error(StatusCode::INTERNAL_SERVER_ERROR, "Unexpected response from the master")
+}
+
+fn ack(payload: ConsolePayload) -> HttpResponse {
+    match payload {
+        ConsolePayload::Ack { action, target, count, items } => HttpResponse::Ok().json(FleetAckResponse { action, target, count, items }),
+        ConsolePayload::StringList { items } => {
+            HttpResponse::Ok().json(FleetAckResponse { action: String::new(), target: String::new(), count: items.len(), items })
+        }
+        payload => unexpected(payload),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/minions",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("query" = Option<String>, Query, description = "Hostname glob or comma-separated hostnames"),
+        ("traits" = Option<String>, Query, description = "Traits query, e.g. system.os.name:Ubuntu"),
+        ("mid" = Option<String>, Query, description = "Minion System Id")
+    ),
+    responses(
+        (status = 200, description = "Registered minions with their online state", body = MinionListResponse),
+        (status = 400, description = "Invalid selector", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/minions")]
+pub async fn minion_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<MinionSelector>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::ReadOn(q.target())).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_ONLINE_MINIONS, &q, None).await {
+        Ok(ConsolePayload::OnlineMinions { rows }) => {
+            HttpResponse::Ok().json(MinionListResponse { minions: rows.into_iter().map(MinionInfo::from).collect() })
+        }
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/minions/{mid}",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("mid" = String, Path, description = "Minion System Id or hostname")
+    ),
+    responses(
+        (status = 200, description = "Minion with all its traits", body = MinionDetailsResponse),
+        (status = 400, description = "No or more than one minion matched", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/minions/{mid}")]
+pub async fn minion_info_handler(req: HttpRequest, master: Data<MasterInterfaceType>, mid: Path<String>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::ReadOn(Target::new("", "", &mid))).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_MINION_INFO, &MinionSelector::of_minion(&mid), None).await {
+        Ok(ConsolePayload::MinionInfo { rows }) => {
+            let minion_id = rows.iter().find(|row| row.key == "minion.id").and_then(|row| row.value.as_str()).unwrap_or(mid.as_str()).to_string();
+            HttpResponse::Ok().json(MinionDetailsResponse { minion_id, traits: rows.into_iter().map(MinionTraitInfo::from).collect() })
+        }
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/minions/{mid}",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("mid" = String, Path, description = "Minion System Id"),
+        ("force" = Option<bool>, Query, description = "Also stop the minion and remove its files over SSH, if it was started by hopstart")
+    ),
+    responses(
+        (status = 200, description = "Minion unregistered and its key removed", body = FleetAckResponse),
+        (status = 400, description = "Unknown minion", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[delete("/api/v1/minions/{mid}")]
+pub async fn minion_remove_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, mid: Path<String>, q: Query<MinionRemoveQuery>,
+) -> impl Responder {
+    audit_target(&req, format!("id:{mid}"));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_REMOVE_MINION, Target::new("", "", &mid))).await {
+        return response;
+    }
+
+    let context = q.force.then(|| json!({"force": true}));
+    match cluster_command(&master, CLUSTER_REMOVE_MINION, &MinionSelector::of_minion(&mid), context).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/traits",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = TraitsSetRequest,
+    responses(
+        (status = 200, description = "Traits update sent to the targeted minions", body = FleetAckResponse),
+        (status = 400, description = "Invalid request", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[put("/api/v1/traits")]
+pub async fn traits_set_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<TraitsSetRequest>) -> impl Responder {
+    audit_target(&req, audit::minion_target(&body.selector.query, &body.selector.traits, &body.selector.mid));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_TRAITS_UPDATE, body.selector.target())).await {
+        return response;
+    }
+    if body.traits.is_empty() {
+        return fleet_error(StatusCode::BAD_REQUEST, "No traits to set");
+    }
+
+    match cluster_command(&master, CLUSTER_TRAITS_UPDATE, &body.selector, Some(json!({"op": "set", "traits": body.traits}))).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/traits",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = TraitsUnsetRequest,
+    responses(
+        (status = 200, description = "Traits removal sent to the targeted minions", body = FleetAckResponse),
+        (status = 400, description = "Invalid request", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[delete("/api/v1/traits")]
+pub async fn traits_unset_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<TraitsUnsetRequest>) -> impl Responder {
+    audit_target(&req, audit::minion_target(&body.selector.query, &body.selector.traits, &body.selector.mid));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_TRAITS_UPDATE, body.selector.target())).await {
+        return response;
+    }
+
+    let context = if body.reset {
+        json!({"op": "reset", "traits": {}})
+    } else if body.keys.is_empty() {
+        return fleet_error(StatusCode::BAD_REQUEST, "Specify the keys to remove or reset");
+    } else {
+        json!({"op": "unset", "traits": body.keys.iter().map(|key| (key.clone(), Value::Null)).collect::<Map<_, _>>()})
+    };
+
+    match cluster_command(&master, CLUSTER_TRAITS_UPDATE, &body.selector, Some(context)).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/traits/history",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("query" = Option<String>, Query, description = "Hostname glob or comma-separated hostnames"),
+        ("traits" = Option<String>, Query, description = "Traits query, e.g. system.os.name:Ubuntu"),
+        ("mid" = Option<String>, Query, description = "Minion System Id"),
+        ("key" = Option<String>, Query, description = "Only changes of this trait")
+    ),
+    responses(
+        (status = 200, description = "Trait changes of the targeted minions, oldest first", body = TraitHistoryResponse),
+        (status = 400, description = "Invalid selector", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/traits/history")]
+pub async fn traits_history_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<TraitHistoryQuery>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::ReadOn(q.selector.target())).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_TRAITS_HISTORY, &q.selector, Some(json!({"key": q.key}))).await {
+        Ok(ConsolePayload::TraitHistory { rows }) => {
+            HttpResponse::Ok().json(TraitHistoryResponse { changes: rows.into_iter().map(TraitChangeInfo::from).collect() })
+        }
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profiles",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = Option<String>, Query, description = "Glob of profile names"),
+        ("library" = Option<bool>, Query, description = "With a name, list library selectors instead of module selectors")
+    ),
+    responses(
+        (status = 200, description = "Deployment profiles", body = ProfileListResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/profiles")]
+pub async fn profile_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<ProfileListQuery>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Read).await {
+        return response;
+    }
+
+    let context = json!({"op": "list", "name": q.name.clone().unwrap_or_default(), "library": q.library});
+    match cluster_command(&master, CLUSTER_PROFILE, &MinionSelector::default(), Some(context)).await {
+        Ok(ConsolePayload::StringList { items }) => HttpResponse::Ok().json(ProfileListResponse { profiles: items }),
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profiles/{name}",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    responses(
+        (status = 200, description = "Profile definition", body = ProfileResponse),
+        (status = 400, description = "Unknown profile", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/profiles/{name}")]
+pub async fn profile_show_handler(req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Read).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_PROFILE, &MinionSelector::default(), Some(json!({"op": "show", "name": name.as_str()}))).await {
+        Ok(ConsolePayload::Text { value }) => HttpResponse::Ok().json(ProfileResponse { name: name.into_inner(), definition: value }),
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/profiles",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = ProfileCreateRequest,
+    responses(
+        (status = 200, description = "Profile created", body = FleetAckResponse),
+        (status = 400, description = "Invalid name or the profile already exists", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[post("/api/v1/profiles")]
+pub async fn profile_create_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<ProfileCreateRequest>) -> impl Responder {
+    audit_target(&req, format!("profile:{}", body.name));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Command(CLUSTER_PROFILE)).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_PROFILE, &MinionSelector::default(), Some(json!({"op": "new", "name": body.name}))).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/profiles/{name}",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    request_body = ProfileUpdateRequest,
+    responses(
+        (status = 200, description = "Profile selectors updated", body = FleetAckResponse),
+        (status = 400, description = "Unknown profile or no selectors", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[patch("/api/v1/profiles/{name}")]
+pub async fn profile_update_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>, body: Json<ProfileUpdateRequest>,
+) -> impl Responder {
+    audit_target(&req, format!("profile:{name}"));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Command(CLUSTER_PROFILE)).await {
+        return response;
+    }
+    if body.add.is_empty() && body.remove.is_empty() {
+        return fleet_error(StatusCode::BAD_REQUEST, "No selectors to add or remove");
+    }
+
+    let mut last = None;
+    for (op, matches) in [("add", &body.add), ("remove", &body.remove)] {
+        if matches.is_empty() {
+            continue;
+        }
+        let context = json!({"op": op, "name": name.as_str(), "matches": matches, "library": body.library});
+        match cluster_command(&master, CLUSTER_PROFILE, &MinionSelector::default(), Some(context)).await {
+            Ok(payload) => last = Some(payload),
+            Err(response) => return response,
+        }
+    }
+
+    match last {
+        Some(payload) => ack(payload),
+        None => unexpected(ConsolePayload::Empty),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/profiles/{name}",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    responses(
+        (status = 200, description = "Profile deleted", body = FleetAckResponse),
+        (status = 400, description = "Unknown profile", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[delete("/api/v1/profiles/{name}")]
+pub async fn profile_delete_handler(req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>) -> impl Responder {
+    audit_target(&req, format!("profile:{name}"));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Command(CLUSTER_PROFILE)).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_PROFILE, &MinionSelector::default(), Some(json!({"op": "delete", "name": name.as_str()}))).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/profiles/{name}/minions",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    request_body = MinionSelector,
+    responses(
+        (status = 200, description = "Profile assigned to the targeted minions", body = FleetAckResponse),
+        (status = 400, description = "Unknown profile or invalid selector", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[post("/api/v1/profiles/{name}/minions")]
+pub async fn profile_tag_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>, body: Json<MinionSelector>,
+) -> impl Responder {
+    audit_target(&req, format!("profile:{name} on {}", audit::minion_target(&body.query, &body.traits, &body.mid)));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_PROFILE, body.target())).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_PROFILE, &body, Some(json!({"op": "tag", "profiles": [name.as_str()]}))).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/profiles/{name}/minions",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Profile name")
+    ),
+    request_body = MinionSelector,
+    responses(
+        (status = 200, description = "Profile removed from the targeted minions", body = FleetAckResponse),
+        (status = 400, description = "Unknown profile or invalid selector", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[delete("/api/v1/profiles/{name}/minions")]
+pub async fn profile_untag_handler(
+    req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>, body: Json<MinionSelector>,
+) -> impl Responder {
+    audit_target(&req, format!("profile:{name} on {}", audit::minion_target(&body.query, &body.traits, &body.mid)));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_PROFILE, body.target())).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_PROFILE, &body, Some(json!({"op": "untag", "profiles": [name.as_str()]}))).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("query" = Option<String>, Query, description = "Hostname glob or comma-separated hostnames"),
+        ("traits" = Option<String>, Query, description = "Traits query, e.g. system.os.name:Ubuntu"),
+        ("mid" = Option<String>, Query, description = "Minion System Id"),
+        ("filter" = Option<String>, Query, description = "all, pending or idle")
+    ),
+    responses(
+        (status = 200, description = "Transport keys of the targeted minions", body = KeyListResponse),
+        (status = 400, description = "Invalid selector or filter", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/keys")]
+pub async fn key_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<KeyStatusQuery>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::ReadOn(q.selector.target())).await {
+        return response;
+    }
+
+    let context = json!({"filter": q.filter.clone().unwrap_or_else(|| "all".to_string())});
+    match cluster_command(&master, CLUSTER_TRANSPORT_STATUS, &q.selector, Some(context)).await {
+        Ok(ConsolePayload::TransportStatus { rows }) => {
+            HttpResponse::Ok().json(KeyListResponse { keys: rows.into_iter().map(KeyInfo::from).collect() })
+        }
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys/rotate",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = KeyRotateRequest,
+    responses(
+        (status = 200, description = "Rotation sent to online minions and staged for offline ones", body = FleetAckResponse),
+        (status = 400, description = "Invalid selector", body = FleetErrorResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[post("/api/v1/keys/rotate")]
+pub async fn key_rotate_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<KeyRotateRequest>) -> impl Responder {
+    audit_target(&req, audit::minion_target(&body.selector.query, &body.selector.traits, &body.selector.mid));
+    if let Err(response) = authorise_fleet(&req, &master, &Access::command_on(CLUSTER_ROTATE, body.selector.target())).await {
+        return response;
+    }
+
+    let context = json!({"op": "rotate", "reason": body.reason, "grace_seconds": body.grace_seconds});
+    match cluster_command(&master, CLUSTER_ROTATE, &body.selector, Some(context)).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/upgrade",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Cluster upgrade state", body = UpgradeStatusResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[get("/api/v1/upgrade")]
+pub async fn upgrade_status_handler(req: HttpRequest, master: Data<MasterInterfaceType>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Read).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_UPGRADE_STATUS, &MinionSelector::default(), None).await {
+        Ok(ConsolePayload::UpgradeStatus { required, unreachable, pending_post_upgrade }) => {
+            HttpResponse::Ok().json(UpgradeStatusResponse { required, unreachable, pending_post_upgrade })
+        }
+        Ok(payload) => unexpected(payload),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/upgrade/required",
+    tag = TAG_FLEET,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Minions behind the repository build marked as requiring an upgrade", body = FleetAckResponse),
+        (status = 401, description = "Unauthorized", body = FleetErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = FleetErrorResponse)
+    )
+)]
+#[post("/api/v1/upgrade/required")]
+pub async fn upgrade_mark_handler(req: HttpRequest, master: Data<MasterInterfaceType>) -> impl Responder {
+    if let Err(response) = authorise_fleet(&req, &master, &Access::Command(CLUSTER_MARK_UPGRADE_REQUIRED)).await {
+        return response;
+    }
+
+    match cluster_command(&master, CLUSTER_MARK_UPGRADE_REQUIRED, &MinionSelector::default(), None).await {
+        Ok(payload) => ack(payload),
+        Err(response) => response,
+    }
+}
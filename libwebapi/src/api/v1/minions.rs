@@ -2,23 +2,45 @@ pub use crate::api::v1::system::health_handler;
 #[cfg(test)]
 #[path = "minions_ut.rs"]
 mod minions_ut;
-use crate::{MasterInterfaceType, api::v1::TAG_MINIONS, sessions::get_session_store};
+use crate::{
+    MasterInterfaceType,
+    api::v1::TAG_MINIONS,
+    audit::{audit_actor, audit_failure, audit_target},
+    sessions::get_session_store,
+};
 use actix_web::{
-    HttpRequest, Result, post,
+    HttpRequest, Result,
+    http::StatusCode,
+    post,
     web::{Data, Json},
 };
 use libcommon::SysinspectError;
+use libsysinspect::{
+    audit,
+    rbac::{
+        self, Access, Principal,
+        tokens::{TOKEN_PREFIX, TokenStore},
+    },
+};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::Display};
 use utoipa::ToSchema;
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct QueryRequest {
     pub model: String,
     pub query: String,
     pub traits: String,
     pub mid: String,
     pub context: HashMap<String, String>,
+
+    /// Seconds the command stays deliverable to offline minions
+    #[serde(default)]
+    pub ttl: Option<u64>,
+
+    /// Commands queued with the same key are replaced by this one
+    #[serde(default)]
+    pub supersede: Option<String>,
 }
 
 impl QueryRequest {
@@ -33,10 +55,14 @@ impl QueryRequest {
         ))
     }
 }
-#[derive(Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct QueryResponse {
     pub status: String,
     pub message: String,
+
+    /// Cycle Id of the dispatched query, to look up its results under /api/v1/cycles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -51,6 +77,10 @@ impl Display for QueryError {
     }
 }
 
+/// User Id prefix of requests authenticated with an API token
+pub(crate) const TOKEN_UID_PREFIX: &str = "token:";
+
+/// Authenticate the bearer credentials of the request: a session or an API token. Returns the user Id.
 pub(crate) async fn authorise_request(req: &HttpRequest) -> Result<String, SysinspectError> {
     let header = req
         .headers()
@@ -65,16 +95,78 @@ pub(crate) async fn authorise_request(req: &HttpRequest) -> Result<String, Sysin
         return Err(SysinspectError::WebAPIError("Bearer token cannot be empty".to_string()));
     }
 
-    let mut sessions = get_session_store().lock().await;
-    match sessions.uid(token) {
-        Some(uid) => {
+    {
+        let mut sessions = get_session_store().lock().await;
+        if let Some(uid) = sessions.uid(token) {
             sessions.ping(token);
-            Ok(uid)
+            audit_actor(req, &uid);
+            return Ok(uid);
+        }
+    }
+
+    if token.starts_with(TOKEN_PREFIX)
+        && let Some(master) = req.app_data::<Data<MasterInterfaceType>>()
+    {
+        let root = master.lock().await.cfg().await.api_keys_root();
+        if let Some(api_token) = TokenStore::new(&root).verify(token)? {
+            let uid = format!("{TOKEN_UID_PREFIX}{}", api_token.name);
+            audit_actor(req, &uid);
+            return Ok(uid);
+        }
+    }
+
+    Err(SysinspectError::WebAPIError("Invalid or expired bearer token".to_string()))
+}
+
+/// Authentication or authorisation failure of a request
+#[derive(Debug)]
+pub(crate) enum AccessError {
+    Unauthorized(SysinspectError),
+    Forbidden(SysinspectError),
+}
+
+impl AccessError {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            AccessError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AccessError::Forbidden(_) => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+impl Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessError::Unauthorized(err) | AccessError::Forbidden(err) => write!(f, "{err}"),
         }
-        None => Err(SysinspectError::WebAPIError("Invalid or expired bearer token".to_string())),
     }
 }
 
+/// Access control principal of a session user. Minion datastore sessions can only read the datastore.
+pub(crate) fn principal_of(uid: &str) -> Principal {
+    if let Some(mid) = uid.strip_prefix("minion:") {
+        return Principal::Minion(mid.to_string());
+    }
+    if let Some(name) = uid.strip_prefix(TOKEN_UID_PREFIX) {
+        return Principal::Token(name.to_string());
+    }
+
+    Principal::User(uid.to_string())
+}
+
+/// Authenticate the request and check its access against the master access control policy.
+pub(crate) async fn authorise_access(req: &HttpRequest, master: &MasterInterfaceType, access: &Access<'_>) -> Result<String, AccessError> {
+    let uid = authorise_request(req).await.map_err(AccessError::Unauthorized)?;
+    let principal = principal_of(&uid);
+    let cfg = master.lock().await.cfg().await.clone();
+    if let Err(err) = rbac::authorise(&cfg, &principal, access) {
+        log::warn!("Web API request {} {} denied: {err}", req.method(), req.path());
+        return Err(AccessError::Forbidden(err));
+    }
+
+    Ok(uid)
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/query",
@@ -86,29 +178,34 @@ pub(crate) async fn authorise_request(req: &HttpRequest) -> Result<String, Sysin
     responses(
         (status = 200, description = "Success", body = QueryResponse),
         (status = 400, description = "Bad Request", body = QueryError),
-        (status = 401, description = "Unauthorized", body = QueryError)
+        (status = 401, description = "Unauthorized", body = QueryError),
+        (status = 403, description = "Forbidden by the access control policy", body = QueryError)
     )
 )]
 #[post("/api/v1/query")]
 async fn query_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<QueryRequest>) -> Result<Json<QueryResponse>> {
-    if let Err(e) = authorise_request(&req).await {
-        use actix_web::http::StatusCode;
+    let access = Access::of_query(&body.model, &body.query, &body.traits, &body.mid);
+    audit_target(&req, format!("{} on {}", body.model, audit::minion_target(&body.query, &body.traits, &body.mid)));
+    if let Err(e) = authorise_access(&req, &master, &access).await {
+        let status = e.status();
         let err_body = Json(QueryError { status: "error".to_string(), error: e.to_string() });
-        return Err(actix_web::error::InternalError::new(err_body, StatusCode::UNAUTHORIZED).into());
+        return Err(actix_web::error::InternalError::new(err_body, status).into());
     }
 
     let mut master = master.lock().await;
     let query = match body.to_query() {
         Ok(q) => q,
         Err(e) => {
-            use actix_web::http::StatusCode;
             let err_body = Json(QueryError { status: "error".to_string(), error: e.to_string() });
             return Err(actix_web::error::InternalError::new(err_body, StatusCode::BAD_REQUEST).into());
         }
     };
 
-    match master.query(query).await {
-        Ok(()) => Ok(Json(QueryResponse { status: "success".to_string(), message: "Query executed successfully".to_string() })),
-        Err(err) => Ok(Json(QueryResponse { status: "error".to_string(), message: err.to_string() })),
+    match master.query(query, body.ttl, body.supersede.clone()).await {
+        Ok(cycle_id) => Ok(Json(QueryResponse { status: "success".to_string(), message: "Query dispatched".to_string(), cycle_id: Some(cycle_id) })),
+        Err(err) => {
+            audit_failure(&req, err.to_string());
+            Ok(Json(QueryResponse { status: "error".to_string(), message: err.to_string(), cycle_id: None }))
+        }
     }
 }
@@ -1,6 +1,7 @@
-use super::authorise_request;
+use super::{authorise_request, principal_of};
 use crate::sessions::get_session_store;
 use actix_web::{http::header::AUTHORIZATION, test::TestRequest};
+use libsysinspect::rbac::Principal;
 
 #[tokio::test]
 async fn authorise_request_accepts_lowercase_bearer_scheme() {
@@ -20,3 +21,15 @@ async fn authorise_request_accepts_mixed_case_bearer_scheme() {
 async fn authorise_request_rejects_non_bearer_scheme() {
     assert!(authorise_request(&TestRequest::default().insert_header((AUTHORIZATION, "Basic dev-token")).to_http_request()).await.is_err());
 }
+
+#[tokio::test]
+async fn authorise_request_rejects_unknown_api_token() {
+    assert!(authorise_request(&TestRequest::default().insert_header((AUTHORIZATION, "Bearer sit_00ff")).to_http_request()).await.is_err());
+}
+
+#[test]
+fn principal_of_maps_users_tokens_and_minions() {
+    assert_eq!(principal_of("alice"), Principal::User("alice".to_string()));
+    assert_eq!(principal_of("token:ci"), Principal::Token("ci".to_string()));
+    assert_eq!(principal_of("minion:30a1f"), Principal::Minion("30a1f".to_string()));
+}
@@ -1,13 +1,42 @@
 pub use crate::api::v1::system::health_handler;
 use crate::api::v1::{
+    audit::{
+        AuditErrorResponse, AuditListResponse, AuditQuery, AuditRecordInfo, AuditVerifyResponse, audit_export_handler, audit_list_handler,
+        audit_verify_handler,
+    },
+    commands::{CommandErrorResponse, CommandListQuery, CommandListResponse, QueuedCommandInfo, command_list_handler},
+    cycles::{
+        CycleErrorResponse, CycleEventInfo, CycleEventsQuery, CycleEventsResponse, CycleInfo, CycleListResponse, CycleMinionInfo, CycleMinionsQuery,
+        CycleMinionsResponse, CyclePageQuery, cycle_events_handler, cycle_list_handler, cycle_minions_handler,
+    },
+    fleet::{
+        FleetAckResponse, FleetErrorResponse, KeyInfo, KeyListResponse, KeyRotateRequest, KeyStatusQuery, MinionDetailsResponse, MinionInfo,
+        MinionListResponse, MinionRemoveQuery, MinionSelector, MinionTraitInfo, ProfileCreateRequest, ProfileListQuery, ProfileListResponse,
+        ProfileResponse, ProfileUpdateRequest, TraitChangeInfo, TraitHistoryQuery, TraitHistoryResponse, TraitsSetRequest, TraitsUnsetRequest,
+        UpgradeStatusResponse, key_list_handler, key_rotate_handler, minion_info_handler, minion_list_handler, minion_remove_handler,
+        profile_create_handler, profile_delete_handler, profile_list_handler, profile_show_handler, profile_tag_handler, profile_untag_handler,
+        profile_update_handler, traits_history_handler, traits_set_handler, traits_unset_handler, upgrade_mark_handler, upgrade_status_handler,
+    },
     minions::{QueryError, QueryRequest, QueryResponse, query_handler},
-    model::{ModelNameResponse, model_descr_handler, model_names_handler},
+    model::{
+        ModelDiffQuery, ModelDiffResponse, ModelFileDiffInfo, ModelNameResponse, ModelResponseError, ModelRollbackRequest, ModelUploadRequest,
+        ModelVersionInfo, ModelVersionResponse, ModelVersionsQuery, ModelVersionsResponse, model_descr_handler, model_diff_handler,
+        model_names_handler, model_rollback_handler, model_upload_handler, model_versions_handler,
+    },
     store::{
-        StoreListQuery, StoreMetaResponse, StoreMinionAuthResponse, StoreResolveQuery, store_blob_handler, store_list_handler, store_meta_handler,
-        store_minion_auth_handler, store_resolve_handler, store_upload_handler,
+        StoreChunkQuery, StoreListQuery, StoreMetaQuery, StoreMetaResponse, StoreMinionAuthResponse, StoreResolveQuery, StoreUploadResponse,
+        StoreUploadStartRequest, store_blob_handler, store_list_handler, store_meta_handler, store_minion_auth_handler, store_resolve_handler,
+        store_upload_abort_handler, store_upload_chunk_handler, store_upload_complete_handler, store_upload_handler, store_upload_start_handler,
+        store_upload_state_handler,
     },
+    stream::{StreamErrorResponse, event_stream_handler},
     system::{AuthRequest, AuthResponse, HealthInfo, HealthResponse, authenticate_handler},
+    tokens::{
+        TokenCreateRequest, TokenCreateResponse, TokenErrorResponse, TokenInfo, TokenListResponse, token_create_handler, token_list_handler,
+        token_revoke_handler,
+    },
 };
+use crate::stream::{StreamEvent, StreamEventKind, StreamFilter};
 use actix_web::Scope;
 use utoipa::Modify;
 use utoipa::OpenApi;
@@ -17,10 +46,16 @@ use utoipa_swagger_ui::SwaggerUi;
 #[cfg(test)]
 mod mod_ut;
 
+pub mod audit;
+pub mod commands;
+pub mod cycles;
+pub mod fleet;
 pub mod minions;
 pub mod model;
 pub mod store;
+pub mod stream;
 pub mod system;
+pub mod tokens;
 
 const API_VERSION: &str = "0.1.1";
 const API_DOC_DESCRIPTION: &str = "SysInspect Web API for interacting with the master interface. Use HTTPS/TLS for all requests. Documentation is exposed only when api.doc is enabled. Authenticate with POST /api/v1/authenticate to obtain a bearer token for protected operations. If the Web API is configured with client certificates, the same TLS client-certificate requirement also applies to Swagger UI and the OpenAPI document.";
@@ -30,6 +65,7 @@ const API_DOC_DEV_DESCRIPTION: &str = "SysInspect Web API for interacting with t
 pub static TAG_MINIONS: &str = "Minions";
 pub static TAG_SYSTEM: &str = "System";
 pub static TAG_MODELS: &str = "Models";
+pub static TAG_FLEET: &str = "Fleet";
 
 struct SecurityAddon;
 
@@ -63,12 +99,49 @@ impl V1 {
             .service(authenticate_handler)
             .service(model_names_handler)
             .service(model_descr_handler)
+            .service(model_versions_handler)
+            .service(model_upload_handler)
+            .service(model_diff_handler)
+            .service(model_rollback_handler)
             .service(store_minion_auth_handler)
             .service(store_resolve_handler)
             .service(store_list_handler)
             .service(store_meta_handler)
             .service(store_blob_handler)
             .service(store_upload_handler)
+            .service(store_upload_start_handler)
+            .service(store_upload_state_handler)
+            .service(store_upload_chunk_handler)
+            .service(store_upload_complete_handler)
+            .service(store_upload_abort_handler)
+            .service(command_list_handler)
+            .service(cycle_list_handler)
+            .service(cycle_minions_handler)
+            .service(cycle_events_handler)
+            .service(event_stream_handler)
+            .service(token_create_handler)
+            .service(token_list_handler)
+            .service(token_revoke_handler)
+            .service(audit_list_handler)
+            .service(audit_export_handler)
+            .service(audit_verify_handler)
+            .service(minion_list_handler)
+            .service(minion_info_handler)
+            .service(minion_remove_handler)
+            .service(traits_set_handler)
+            .service(traits_unset_handler)
+            .service(traits_history_handler)
+            .service(profile_list_handler)
+            .service(profile_show_handler)
+            .service(profile_create_handler)
+            .service(profile_update_handler)
+            .service(profile_delete_handler)
+            .service(profile_tag_handler)
+            .service(profile_untag_handler)
+            .service(key_list_handler)
+            .service(key_rotate_handler)
+            .service(upgrade_status_handler)
+            .service(upgrade_mark_handler)
     }
 
     fn doc_service(&self) -> SwaggerUi {
@@ -106,16 +179,67 @@ impl super::ApiVersion for V1 {
     crate::api::v1::system::authenticate_handler,
     crate::api::v1::model::model_names_handler,
     crate::api::v1::model::model_descr_handler,
+    crate::api::v1::model::model_versions_handler,
+    crate::api::v1::model::model_upload_handler,
+    crate::api::v1::model::model_diff_handler,
+    crate::api::v1::model::model_rollback_handler,
     crate::api::v1::store::store_meta_handler,
     crate::api::v1::store::store_blob_handler,
     crate::api::v1::store::store_upload_handler,
+    crate::api::v1::store::store_upload_start_handler,
+    crate::api::v1::store::store_upload_state_handler,
+    crate::api::v1::store::store_upload_chunk_handler,
+    crate::api::v1::store::store_upload_complete_handler,
+    crate::api::v1::store::store_upload_abort_handler,
     crate::api::v1::store::store_minion_auth_handler,
     crate::api::v1::store::store_resolve_handler,
     crate::api::v1::store::store_list_handler,
+    crate::api::v1::commands::command_list_handler,
+    crate::api::v1::cycles::cycle_list_handler,
+    crate::api::v1::cycles::cycle_minions_handler,
+    crate::api::v1::cycles::cycle_events_handler,
+    crate::api::v1::stream::event_stream_handler,
+    crate::api::v1::tokens::token_create_handler,
+    crate::api::v1::tokens::token_list_handler,
+    crate::api::v1::tokens::token_revoke_handler,
+    crate::api::v1::audit::audit_list_handler,
+    crate::api::v1::audit::audit_export_handler,
+    crate::api::v1::audit::audit_verify_handler,
+    crate::api::v1::fleet::minion_list_handler,
+    crate::api::v1::fleet::minion_info_handler,
+    crate::api::v1::fleet::minion_remove_handler,
+    crate::api::v1::fleet::traits_set_handler,
+    crate::api::v1::fleet::traits_unset_handler,
+    crate::api::v1::fleet::traits_history_handler,
+    crate::api::v1::fleet::profile_list_handler,
+    crate::api::v1::fleet::profile_show_handler,
+    crate::api::v1::fleet::profile_create_handler,
+    crate::api::v1::fleet::profile_update_handler,
+    crate::api::v1::fleet::profile_delete_handler,
+    crate::api::v1::fleet::profile_tag_handler,
+    crate::api::v1::fleet::profile_untag_handler,
+    crate::api::v1::fleet::key_list_handler,
+    crate::api::v1::fleet::key_rotate_handler,
+    crate::api::v1::fleet::upgrade_status_handler,
+    crate::api::v1::fleet::upgrade_mark_handler,
 ),
           components(schemas(QueryRequest, QueryResponse, QueryError,
                               HealthInfo, HealthResponse, AuthRequest, AuthResponse,
-                             ModelNameResponse, StoreMetaResponse, StoreMinionAuthResponse, StoreResolveQuery, StoreListQuery)),
+                             ModelNameResponse, ModelResponseError, ModelVersionsQuery, ModelVersionInfo, ModelVersionsResponse,
+                             ModelUploadRequest, ModelRollbackRequest, ModelVersionResponse, ModelDiffQuery, ModelFileDiffInfo, ModelDiffResponse,
+                             StoreMetaResponse, StoreMetaQuery, StoreMinionAuthResponse, StoreResolveQuery, StoreListQuery,
+                             StoreUploadStartRequest, StoreUploadResponse, StoreChunkQuery,
+                             CommandListQuery, CommandListResponse, QueuedCommandInfo, CommandErrorResponse,
+                             CyclePageQuery, CycleMinionsQuery, CycleEventsQuery, CycleInfo, CycleMinionInfo, CycleEventInfo,
+                             CycleListResponse, CycleMinionsResponse, CycleEventsResponse, CycleErrorResponse,
+                             StreamFilter, StreamEvent, StreamEventKind, StreamErrorResponse,
+                             TokenCreateRequest, TokenCreateResponse, TokenInfo, TokenListResponse, TokenErrorResponse,
+                             AuditQuery, AuditRecordInfo, AuditListResponse, AuditVerifyResponse, AuditErrorResponse,
+                             MinionSelector, MinionRemoveQuery, TraitHistoryQuery, KeyStatusQuery, ProfileListQuery,
+                             TraitsSetRequest, TraitsUnsetRequest, ProfileCreateRequest, ProfileUpdateRequest, KeyRotateRequest,
+                             MinionInfo, MinionListResponse, MinionTraitInfo, MinionDetailsResponse, TraitChangeInfo, TraitHistoryResponse,
+                             ProfileListResponse, ProfileResponse, KeyInfo, KeyListResponse, UpgradeStatusResponse,
+                             FleetAckResponse, FleetErrorResponse)),
 modifiers(&SecurityAddon),
 info(title = "SysInspect API", version = API_VERSION, description = API_DOC_DESCRIPTION))]
 pub struct ApiDoc;
@@ -127,16 +251,67 @@ pub struct ApiDoc;
     crate::api::v1::system::authenticate_handler,
     crate::api::v1::model::model_names_handler,
     crate::api::v1::model::model_descr_handler,
+    crate::api::v1::model::model_versions_handler,
+    crate::api::v1::model::model_upload_handler,
+    crate::api::v1::model::model_diff_handler,
+    crate::api::v1::model::model_rollback_handler,
     crate::api::v1::store::store_meta_handler,
     crate::api::v1::store::store_blob_handler,
     crate::api::v1::store::store_upload_handler,
+    crate::api::v1::store::store_upload_start_handler,
+    crate::api::v1::store::store_upload_state_handler,
+    crate::api::v1::store::store_upload_chunk_handler,
+    crate::api::v1::store::store_upload_complete_handler,
+    crate::api::v1::store::store_upload_abort_handler,
     crate::api::v1::store::store_minion_auth_handler,
     crate::api::v1::store::store_resolve_handler,
     crate::api::v1::store::store_list_handler,
+    crate::api::v1::commands::command_list_handler,
+    crate::api::v1::cycles::cycle_list_handler,
+    crate::api::v1::cycles::cycle_minions_handler,
+    crate::api::v1::cycles::cycle_events_handler,
+    crate::api::v1::stream::event_stream_handler,
+    crate::api::v1::tokens::token_create_handler,
+    crate::api::v1::tokens::token_list_handler,
+    crate::api::v1::tokens::token_revoke_handler,
+    crate::api::v1::audit::audit_list_handler,
+    crate::api::v1::audit::audit_export_handler,
+    crate::api::v1::audit::audit_verify_handler,
+    crate::api::v1::fleet::minion_list_handler,
+    crate::api::v1::fleet::minion_info_handler,
+    crate::api::v1::fleet::minion_remove_handler,
+    crate::api::v1::fleet::traits_set_handler,
+    crate::api::v1::fleet::traits_unset_handler,
+    crate::api::v1::fleet::traits_history_handler,
+    crate::api::v1::fleet::profile_list_handler,
+    crate::api::v1::fleet::profile_show_handler,
+    crate::api::v1::fleet::profile_create_handler,
+    crate::api::v1::fleet::profile_update_handler,
+    crate::api::v1::fleet::profile_delete_handler,
+    crate::api::v1::fleet::profile_tag_handler,
+    crate::api::v1::fleet::profile_untag_handler,
+    crate::api::v1::fleet::key_list_handler,
+    crate::api::v1::fleet::key_rotate_handler,
+    crate::api::v1::fleet::upgrade_status_handler,
+    crate::api::v1::fleet::upgrade_mark_handler,
 ),
           components(schemas(QueryRequest, QueryResponse, QueryError,
                               HealthInfo, HealthResponse, AuthRequest, AuthResponse,
-                             ModelNameResponse, StoreMetaResponse, StoreMinionAuthResponse, StoreResolveQuery, StoreListQuery)),
+                             ModelNameResponse, ModelResponseError, ModelVersionsQuery, ModelVersionInfo, ModelVersionsResponse,
+                             ModelUploadRequest, ModelRollbackRequest, ModelVersionResponse, ModelDiffQuery, ModelFileDiffInfo, ModelDiffResponse,
+                             StoreMetaResponse, StoreMetaQuery, StoreMinionAuthResponse, StoreResolveQuery, StoreListQuery,
+                             StoreUploadStartRequest, StoreUploadResponse, StoreChunkQuery,
+                             CommandListQuery, CommandListResponse, QueuedCommandInfo, CommandErrorResponse,
+                             CyclePageQuery, CycleMinionsQuery, CycleEventsQuery, CycleInfo, CycleMinionInfo, CycleEventInfo,
+                             CycleListResponse, CycleMinionsResponse, CycleEventsResponse, CycleErrorResponse,
+                             StreamFilter, StreamEvent, StreamEventKind, StreamErrorResponse,
+                             TokenCreateRequest, TokenCreateResponse, TokenInfo, TokenListResponse, TokenErrorResponse,
+                             AuditQuery, AuditRecordInfo, AuditListResponse, AuditVerifyResponse, AuditErrorResponse,
+                             MinionSelector, MinionRemoveQuery, TraitHistoryQuery, KeyStatusQuery, ProfileListQuery,
+                             TraitsSetRequest, TraitsUnsetRequest, ProfileCreateRequest, ProfileUpdateRequest, KeyRotateRequest,
+                             MinionInfo, MinionListResponse, MinionTraitInfo, MinionDetailsResponse, TraitChangeInfo, TraitHistoryResponse,
+                             ProfileListResponse, ProfileResponse, KeyInfo, KeyListResponse, UpgradeStatusResponse,
+                             FleetAckResponse, FleetErrorResponse)),
 modifiers(&SecurityAddon),
 info(title = "SysInspect API", version = API_VERSION, description = API_DOC_DEV_DESCRIPTION))]
 pub struct ApiDocDev;
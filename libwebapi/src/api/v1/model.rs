@@ -1,44 +1,54 @@
 use crate::{
     MasterInterfaceType,
-    api::v1::{TAG_MODELS, minions::authorise_request},
+    api::v1::{TAG_MODELS, minions::authorise_access},
+    audit::audit_target,
 };
 use actix_web::{
     HttpRequest, HttpResponse, Result, get,
-    web::{Data, Query},
+    http::StatusCode,
+    post,
+    web::{Data, Json, Query},
 };
 use indexmap::IndexMap;
 use libcommon::SysinspectError;
 use libsysinspect::{
-    cfg::mmconf::MinionConfig,
+    cfg::mmconf::{MasterConfig, MinionConfig},
     intp::inspector::SysInspector,
-    mdescr::{mspec, mspecdef::ModelSpec},
+    mdescr::{
+        mspec,
+        mspecdef::ModelSpec,
+        versions::{ModelBundle, ModelDiff, ModelStore, ModelVersion, ModelVersions},
+    },
+    rbac::Access,
 };
+use libsysproto::query::commands::CLUSTER_MODEL_VERSIONS;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use tokio::task;
 use utoipa::ToSchema;
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ModelInfo {
     /// The unique identifier of the model (Id)
-    id: String,
+    pub id: String,
 
     /// The name of the model
-    name: String,
+    pub name: String,
 
     /// A brief description of the model
-    description: String,
+    pub description: String,
 
     /// The version of the model
-    version: String,
+    pub version: String,
 
     /// The author of the model
-    maintainer: String,
+    pub maintainer: String,
 
     /// Entity to a vector of bound actions
     #[allow(clippy::type_complexity)]
     #[serde(rename = "entity-states")]
-    entities: BTreeMap<String, Vec<(String, BTreeMap<String, String>)>>, // Entity -> States
+    pub entities: BTreeMap<String, Vec<(String, BTreeMap<String, String>)>>, // Entity -> States
 }
 
 impl ModelInfo {
@@ -73,19 +83,19 @@ impl ModelInfo {
     }
 }
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ModelResponse {
-    model: ModelInfo,
+    pub model: ModelInfo,
 }
 
 #[derive(Deserialize, Serialize, ToSchema)]
 pub struct ModelResponseError {
-    error: String,
+    pub error: String,
 }
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ModelNameResponse {
-    models: Vec<String>,
+    pub models: Vec<String>,
 }
 
 #[utoipa::path(
@@ -99,14 +109,15 @@ pub struct ModelNameResponse {
     ),
     responses(
         (status = 200, description = "List of available models", body = ModelNameResponse),
-        (status = 401, description = "Unauthorized", body = ModelResponseError)
+        (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError)
     )
 )]
 #[allow(unused)]
 #[get("/api/v1/model/names")]
 pub async fn model_names_handler(req: HttpRequest, master: Data<MasterInterfaceType>) -> Result<HttpResponse> {
-    if let Err(err) = authorise_request(&req).await {
-        return Ok(HttpResponse::Unauthorized().json(ModelResponseError { error: err.to_string() }));
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return Ok(HttpResponse::build(err.status()).json(ModelResponseError { error: err.to_string() }));
     }
     let mut master = master.lock().await;
     Ok(HttpResponse::Ok().json(ModelNameResponse { models: master.cfg().await.fileserver_models().to_owned() }))
@@ -127,6 +138,7 @@ pub async fn model_names_handler(req: HttpRequest, master: Data<MasterInterfaceT
         (status = 200, description = "Detailed information about the model", body = ModelResponse),
         (status = 400, description = "Bad request", body = ModelResponseError),
         (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError),
         (status = 404, description = "Model not found", body = ModelResponseError),
         (status = 500, description = "Failed to load model information", body = ModelResponseError)
     )
@@ -136,8 +148,8 @@ pub async fn model_names_handler(req: HttpRequest, master: Data<MasterInterfaceT
 pub async fn model_descr_handler(
     req: HttpRequest, master: Data<MasterInterfaceType>, query: Query<IndexMap<String, String>>,
 ) -> Result<HttpResponse> {
-    if let Err(err) = authorise_request(&req).await {
-        return Ok(HttpResponse::Unauthorized().json(ModelResponseError { error: err.to_string() }));
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return Ok(HttpResponse::build(err.status()).json(ModelResponseError { error: err.to_string() }));
     }
     let mid = query.get("name").cloned().unwrap_or_default(); // Model Id
     if mid.is_empty() {
@@ -167,3 +179,297 @@ pub async fn model_descr_handler(
         },
     }
 }
+
+/// Stored version of a model
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ModelVersionInfo {
+    pub version: u64,
+
+    /// SHA-256 over the paths and contents of all model files
+    pub hash: String,
+
+    /// RFC 3339 timestamp
+    pub created: String,
+
+    /// Who uploaded the version, empty for models taken over from the models directory
+    pub author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+
+    /// Number of model files
+    pub files: usize,
+}
+
+impl From<ModelVersion> for ModelVersionInfo {
+    fn from(v: ModelVersion) -> Self {
+        ModelVersionInfo { version: v.version, hash: v.hash, created: v.created.to_rfc3339(), author: v.author, note: v.note, files: v.files }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ModelVersionsResponse {
+    pub model: String,
+
+    /// Version in the models directory, none if the model was changed by hand since
+    pub active: Option<u64>,
+
+    /// Oldest first
+    pub versions: Vec<ModelVersionInfo>,
+}
+
+impl From<ModelVersions> for ModelVersionsResponse {
+    fn from(v: ModelVersions) -> Self {
+        ModelVersionsResponse { model: v.model, active: v.active, versions: v.versions.into_iter().map(ModelVersionInfo::from).collect() }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct ModelVersionsQuery {
+    /// Model name
+    pub name: String,
+}
+
+/// Model bundle to validate, store as a new version and activate
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct ModelUploadRequest {
+    /// Model name, the directory of the model on the master
+    pub name: String,
+
+    /// What changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+
+    /// Model files: path relative to the model directory and base64 encoded content.
+    /// `model.cfg` is required.
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct ModelRollbackRequest {
+    pub name: String,
+
+    /// Version to restore, the one before the active version if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Version activated by an upload or a rollback
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ModelVersionResponse {
+    pub model: String,
+    pub version: ModelVersionInfo,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct ModelDiffQuery {
+    pub name: String,
+
+    /// Version to compare from
+    pub from: u64,
+
+    /// Version to compare to, the active version if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+}
+
+/// Changed file
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ModelFileDiffInfo {
+    pub path: String,
+
+    /// added, removed or modified
+    pub change: String,
+
+    /// Unified diff hunks
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ModelDiffResponse {
+    pub model: String,
+    pub from: u64,
+    pub to: u64,
+
+    /// Changed files, sorted by path
+    pub files: Vec<ModelFileDiffInfo>,
+}
+
+impl From<ModelDiff> for ModelDiffResponse {
+    fn from(d: ModelDiff) -> Self {
+        ModelDiffResponse {
+            model: d.model,
+            from: d.from,
+            to: d.to,
+            files: d.files.into_iter().map(|f| ModelFileDiffInfo { path: f.path, change: f.change.to_string(), lines: f.lines }).collect(),
+        }
+    }
+}
+
+fn model_error(err: SysinspectError) -> HttpResponse {
+    let status = match err {
+        SysinspectError::InvalidQuery(_) | SysinspectError::ModelDSLError(_) => StatusCode::BAD_REQUEST,
+        SysinspectError::ObjectNotFound(_) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    HttpResponse::build(status).json(ModelResponseError { error: err.to_string() })
+}
+
+/// Authorise the request and open the model versions. Returns the caller and the store.
+async fn model_store(req: &HttpRequest, master: &MasterInterfaceType, access: &Access<'_>) -> Result<(String, ModelStore), HttpResponse> {
+    let uid = authorise_access(req, master, access)
+        .await
+        .map_err(|err| HttpResponse::build(err.status()).json(ModelResponseError { error: err.to_string() }))?;
+    let cfg: MasterConfig = master.lock().await.cfg().await.clone();
+    Ok((uid, ModelStore::new(&cfg.model_versions_root(), &cfg.fileserver_models_root(false))))
+}
+
+/// Run the store operation on the blocking pool: it copies and hashes whole model directories under the model lock
+async fn blocking<T: Send + 'static>(
+    store: ModelStore, op: impl FnOnce(ModelStore) -> Result<T, SysinspectError> + Send + 'static,
+) -> Result<T, SysinspectError> {
+    task::spawn_blocking(move || op(store))
+        .await
+        .map_err(|err| SysinspectError::MasterGeneralError(format!("Model store operation did not finish: {err}")))?
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/model/versions",
+    tag = TAG_MODELS,
+    operation_id = "listModelVersions",
+    description = "Lists the stored versions of a model with their content hashes, and which of them is active.",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Query, description = "Model name")
+    ),
+    responses(
+        (status = 200, description = "Versions of the model, oldest first", body = ModelVersionsResponse),
+        (status = 400, description = "Invalid model name", body = ModelResponseError),
+        (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError),
+        (status = 404, description = "Model not found", body = ModelResponseError)
+    )
+)]
+#[get("/api/v1/model/versions")]
+pub async fn model_versions_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<ModelVersionsQuery>) -> HttpResponse {
+    let (_, store) = match model_store(&req, &master, &Access::Read).await {
+        Ok(opened) => opened,
+        Err(response) => return response,
+    };
+
+    let name = q.name.clone();
+    match blocking(store, move |store| store.versions(&name)).await {
+        Ok(versions) => HttpResponse::Ok().json(ModelVersionsResponse::from(versions)),
+        Err(err) => model_error(err),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/model/versions",
+    tag = TAG_MODELS,
+    operation_id = "uploadModelVersion",
+    description = "Uploads a model bundle. The model is validated by the model loader, stored as a new version and activated. Uploading the content of an existing version activates that version again.",
+    request_body = ModelUploadRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Activated version", body = ModelVersionResponse),
+        (status = 400, description = "Invalid bundle or model", body = ModelResponseError),
+        (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError),
+        (status = 413, description = "Payload too large")
+    )
+)]
+#[post("/api/v1/model/versions")]
+pub async fn model_upload_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<ModelUploadRequest>) -> HttpResponse {
+    audit_target(&req, format!("model:{}", body.name));
+    let (uid, store) = match model_store(&req, &master, &Access::Command(CLUSTER_MODEL_VERSIONS)).await {
+        Ok(opened) => opened,
+        Err(response) => return response,
+    };
+
+    let (name, bundle, author, note) = (body.name.clone(), ModelBundle { files: body.files.clone() }, uid.clone(), body.note.clone());
+    match blocking(store, move |store| store.upload(&name, &bundle, &author, note.as_deref())).await {
+        Ok(version) => {
+            log::info!("Web API user {uid} activated version {} of model {}", version.version, body.name);
+            HttpResponse::Ok().json(ModelVersionResponse { model: body.name.clone(), version: version.into() })
+        }
+        Err(err) => model_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/model/versions/diff",
+    tag = TAG_MODELS,
+    operation_id = "diffModelVersions",
+    description = "Compares two versions of a model file by file.",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Query, description = "Model name"),
+        ("from" = u64, Query, description = "Version to compare from"),
+        ("to" = Option<u64>, Query, description = "Version to compare to, the active version if omitted")
+    ),
+    responses(
+        (status = 200, description = "Changed files with unified diff hunks", body = ModelDiffResponse),
+        (status = 400, description = "Bad request", body = ModelResponseError),
+        (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError),
+        (status = 404, description = "Model or version not found", body = ModelResponseError)
+    )
+)]
+#[get("/api/v1/model/versions/diff")]
+pub async fn model_diff_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<ModelDiffQuery>) -> HttpResponse {
+    let (_, store) = match model_store(&req, &master, &Access::Read).await {
+        Ok(opened) => opened,
+        Err(response) => return response,
+    };
+
+    let (name, from, to) = (q.name.clone(), q.from, q.to);
+    match blocking(store, move |store| store.diff(&name, from, to)).await {
+        Ok(diff) => HttpResponse::Ok().json(ModelDiffResponse::from(diff)),
+        Err(err) => model_error(err),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/model/versions/rollback",
+    tag = TAG_MODELS,
+    operation_id = "rollbackModelVersion",
+    description = "Activates an earlier version of a model. The model directory is replaced at once.",
+    request_body = ModelRollbackRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Activated version", body = ModelVersionResponse),
+        (status = 400, description = "Bad request", body = ModelResponseError),
+        (status = 401, description = "Unauthorized", body = ModelResponseError),
+        (status = 403, description = "Forbidden by the access control policy", body = ModelResponseError),
+        (status = 404, description = "No version to roll back to", body = ModelResponseError)
+    )
+)]
+#[post("/api/v1/model/versions/rollback")]
+pub async fn model_rollback_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<ModelRollbackRequest>) -> HttpResponse {
+    audit_target(&req, format!("model:{}", body.name));
+    let (uid, store) = match model_store(&req, &master, &Access::Command(CLUSTER_MODEL_VERSIONS)).await {
+        Ok(opened) => opened,
+        Err(response) => return response,
+    };
+
+    let (name, version) = (body.name.clone(), body.version);
+    match blocking(store, move |store| store.rollback(&name, version)).await {
+        Ok(version) => {
+            log::info!("Web API user {uid} rolled model {} back to version {}", body.name, version.version);
+            HttpResponse::Ok().json(ModelVersionResponse { model: body.name.clone(), version: version.into() })
+        }
+        Err(err) => model_error(err),
+    }
+}
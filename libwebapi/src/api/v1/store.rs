@@ -1,13 +1,30 @@
-use std::path::{Path, PathBuf};
-
-use crate::{MasterInterfaceType, api::v1::minions::authorise_request, sessions::get_session_store};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    MasterInterfaceType,
+    api::v1::minions::{AccessError, authorise_access},
+    audit::audit_target,
+    sessions::get_session_store,
+};
 use actix_files::NamedFile;
 use actix_web::Result as ActixResult;
-use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put, web};
 use base64::{Engine, engine::general_purpose::STANDARD};
 use futures_util::StreamExt;
-use libdatastore::resources::DataItemMeta;
-use libsysinspect::rsa::keys::{RsaKey, key_from_file, verify_sign};
+use libcommon::SysinspectError;
+use libdatastore::{
+    resources::{DataItemMeta, DataStorage, check_namespace},
+    tags::{TagFilter, parse_tags},
+    uploads::{DataUpload, UPLOAD_CHUNK_SIZE},
+};
+use libsysinspect::{
+    cfg::mmconf::MasterConfig,
+    rbac::Access,
+    rsa::keys::{RsaKey, key_from_file, verify_sign},
+};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use tokio::task;
@@ -15,7 +32,10 @@ use utoipa::ToSchema;
 
 const MINION_AUTH_SKEW_SECS: u64 = 300;
 
-#[derive(Debug, Serialize, ToSchema)]
+/// Largest chunk of a staged upload the master accepts
+const MAX_UPLOAD_CHUNK_SIZE: usize = 4 * UPLOAD_CHUNK_SIZE as usize;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StoreMetaResponse {
     pub sha256: String,
     pub size_bytes: u64,
@@ -23,16 +43,102 @@ pub struct StoreMetaResponse {
     pub created_unix: u64,
     pub expires_unix: Option<u64>,
     pub fname: Option<String>,
+    /// Namespace of the object, missing for the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl From<DataItemMeta> for StoreMetaResponse {
+    fn from(meta: DataItemMeta) -> Self {
+        Self {
+            sha256: meta.sha256,
+            size_bytes: meta.size_bytes,
+            fmode: meta.fmode,
+            created_unix: meta.created_unix,
+            expires_unix: meta.expires_unix,
+            fname: meta.fname,
+            namespace: meta.namespace,
+            tags: meta.tags,
+        }
+    }
 }
-#[derive(Debug, Deserialize, ToSchema)]
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct StoreResolveQuery {
     pub fname: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StoreMetaQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct StoreListQuery {
     pub prefix: Option<String>,
     pub limit: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Comma-separated `key=value` terms, or only `key` for any value. All of them must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+}
+
+/// Start of a staged upload. The metadata is the same as for `POST /store`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StoreUploadStartRequest {
+    pub size_bytes: u64,
+    /// Expected sha256 of the whole object, verified when the upload completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// State of a staged upload. `upload_id` is the token to resume it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoreUploadResponse {
+    pub upload_id: String,
+    pub size_bytes: u64,
+    /// Offset of the next chunk
+    pub received_bytes: u64,
+    /// Suggested chunk size
+    pub chunk_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl From<DataUpload> for StoreUploadResponse {
+    fn from(upload: DataUpload) -> Self {
+        Self {
+            upload_id: upload.id,
+            size_bytes: upload.size_bytes,
+            received_bytes: upload.received_bytes,
+            chunk_size: UPLOAD_CHUNK_SIZE,
+            sha256: upload.sha256,
+            fname: upload.fname,
+            namespace: upload.namespace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoreChunkQuery {
+    /// Offset of the chunk, which must be the bytes received so far
+    pub offset: u64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -48,9 +154,42 @@ pub struct StoreMinionAuthResponse {
     pub error: String,
 }
 
-fn unauthorised_store_error(err: libcommon::SysinspectError) -> actix_web::Error {
+fn store_access_error(err: AccessError) -> actix_web::Error {
     let msg = err.to_string();
-    actix_web::error::InternalError::from_response(err, HttpResponse::Unauthorized().json(StoreErrorResponse { error: msg })).into()
+    let status = err.status();
+    actix_web::error::InternalError::from_response(msg.clone(), HttpResponse::build(status).json(StoreErrorResponse { error: msg })).into()
+}
+
+/// Who reads the datastore. Operators read every namespace, a minion only those its traits allow.
+enum StoreReader {
+    Operator,
+    Minion { cfg: Box<MasterConfig>, traits: HashMap<String, serde_json::Value> },
+}
+
+impl StoreReader {
+    async fn of(master: &web::Data<MasterInterfaceType>, uid: &str) -> Result<Self, SysinspectError> {
+        let Some(mid) = uid.strip_prefix("minion:") else {
+            return Ok(Self::Operator);
+        };
+        let master = master.lock().await;
+        let traits = master.minion_traits(mid).await?.unwrap_or_default();
+        Ok(Self::Minion { cfg: Box::new(master.cfg().await.clone()), traits })
+    }
+
+    fn may_read(&self, meta: &DataItemMeta) -> bool {
+        match self {
+            Self::Operator => true,
+            Self::Minion { cfg, traits } => cfg.datastore_readable(meta.namespace.as_deref(), traits),
+        }
+    }
+}
+
+/// Namespace from a request parameter or header, empty meaning the default namespace
+fn namespace_param(ns: Option<&str>) -> Result<Option<String>, std::io::Error> {
+    match ns.map(str::trim).filter(|ns| !ns.is_empty()) {
+        Some(ns) => check_namespace(ns).map(|_| Some(ns.to_string())),
+        None => Ok(None),
+    }
 }
 
 fn minion_auth_material(method: &str, path: &str, query: &str, timestamp: &str, body_sha256: &str) -> String {
@@ -169,36 +308,46 @@ fn get_meta_files(root: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
         ("bearer_auth" = [])
     ),
     params(
-        ("sha256" = String, Path, description = "SHA256 of the stored object")
+        ("sha256" = String, Path, description = "SHA256 of the stored object"),
+        ("namespace" = Option<String>, Query, description = "Namespace of the object (default: the default namespace, then any readable one)")
     ),
     responses(
         (status = 200, description = "Metadata for object", body = StoreMetaResponse),
         (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
         (status = 404, description = "Not found"),
         (status = 500, description = "Datastore error")
     )
 )]
 #[get("/store/{sha256:[0-9a-fA-F]{64}}")]
-pub async fn store_meta_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, sha256: web::Path<String>) -> impl Responder {
-    if let Err(err) = authorise_request(&req).await {
-        return HttpResponse::Unauthorized().json(StoreErrorResponse { error: err.to_string() });
-    }
+pub async fn store_meta_handler(
+    req: HttpRequest, master: web::Data<MasterInterfaceType>, sha256: web::Path<String>, q: web::Query<StoreMetaQuery>,
+) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreRead).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let namespace = match namespace_param(q.namespace.as_deref()) {
+        Ok(ns) => ns,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let reader = match StoreReader::of(&master, &uid).await {
+        Ok(reader) => reader,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
     let ds = {
         let m = master.lock().await;
         m.datastore().await
     };
 
     let ds = ds.lock().await;
+    let metas = match namespace {
+        Some(ns) => ds.meta_in(Some(&ns), &sha256).map(|m| m.into_iter().collect::<Vec<_>>()),
+        None => ds.metas(&sha256),
+    };
 
-    match ds.meta(&sha256) {
-        Ok(Some(meta)) => HttpResponse::Ok().json(StoreMetaResponse {
-            sha256: meta.sha256,
-            size_bytes: meta.size_bytes,
-            fmode: meta.fmode,
-            created_unix: meta.created_unix,
-            expires_unix: meta.expires_unix,
-            fname: meta.fname,
-        }),
+    match metas.map(|metas| metas.into_iter().find(|m| reader.may_read(m))) {
+        Ok(Some(meta)) => HttpResponse::Ok().json(StoreMetaResponse::from(meta)),
         Ok(None) => HttpResponse::NotFound().finish(),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
@@ -212,18 +361,23 @@ pub async fn store_meta_handler(req: HttpRequest, master: web::Data<MasterInterf
         ("bearer_auth" = [])
     ),
     params(
-        ("sha256" = String, Path, description = "SHA256 of the stored object")
+        ("sha256" = String, Path, description = "SHA256 of the stored object"),
+        ("Range" = Option<String>, Header, description = "Byte range to resume a download, e.g. bytes=1048576-")
     ),
     responses(
         (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
         (status = 200, description = "Binary blob"),
+        (status = 206, description = "Requested range of the binary blob"),
         (status = 404, description = "Not found"),
+        (status = 416, description = "Range not satisfiable"),
         (status = 500, description = "Datastore error")
     )
 )]
 #[get("/store/{sha256:[0-9a-fA-F]{64}}/blob")]
 pub async fn store_blob_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, sha256: web::Path<String>) -> ActixResult<NamedFile> {
-    authorise_request(&req).await.map_err(unauthorised_store_error)?;
+    let uid = authorise_access(&req, &master, &Access::DatastoreRead).await.map_err(store_access_error)?;
+    let reader = StoreReader::of(&master, &uid).await.map_err(actix_web::error::ErrorInternalServerError)?;
     let ds = {
         let m = master.lock().await;
         m.datastore().await
@@ -232,10 +386,12 @@ pub async fn store_blob_handler(req: HttpRequest, master: web::Data<MasterInterf
     let ds = ds.lock().await;
     let path = ds.uri(&sha256);
 
-    if !path.exists() {
+    // A minion gets the blob only through an object in a namespace it may read
+    if !path.exists() || !ds.metas(&sha256)?.iter().any(|m| reader.may_read(m)) {
         return Err(actix_web::error::ErrorNotFound("blob not found"));
     }
 
+    // Range requests are answered by NamedFile with 206 Partial Content
     Ok(NamedFile::open(path)?)
 }
 
@@ -246,6 +402,11 @@ pub async fn store_blob_handler(req: HttpRequest, master: web::Data<MasterInterf
     security(
         ("bearer_auth" = [])
     ),
+    params(
+        ("X-Filename" = Option<String>, Header, description = "Logical name of the object"),
+        ("X-Namespace" = Option<String>, Header, description = "Namespace of the object (default: the default namespace)"),
+        ("X-Tags" = Option<String>, Header, description = "Comma-separated key=value tags")
+    ),
     request_body(
         content = Vec<u8>,
         content_type = "application/octet-stream",
@@ -253,18 +414,29 @@ pub async fn store_blob_handler(req: HttpRequest, master: web::Data<MasterInterf
     ),
     responses(
         (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
         (status = 200, description = "Stored successfully", body = StoreMetaResponse),
+        (status = 400, description = "Invalid namespace or tags", body = StoreErrorResponse),
         (status = 413, description = "Payload too large"),
         (status = 500, description = "Datastore error")
     )
 )]
 #[post("/store")]
 pub async fn store_upload_handler(req: actix_web::HttpRequest, master: web::Data<MasterInterfaceType>, mut payload: web::Payload) -> impl Responder {
-    if let Err(err) = authorise_request(&req).await {
-        return HttpResponse::Unauthorized().json(StoreErrorResponse { error: err.to_string() });
+    if let Err(err) = authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() });
     }
     // full path goes into fname (as you demanded)
     let origin = req.headers().get("X-Filename").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+    audit_target(&req, origin.clone().unwrap_or_default());
+    let namespace = match namespace_param(req.headers().get("X-Namespace").and_then(|v| v.to_str().ok())) {
+        Ok(ns) => ns,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let tags = match parse_tags(req.headers().get("X-Tags").and_then(|v| v.to_str().ok()).unwrap_or_default()) {
+        Ok(tags) => tags,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
 
     let ds = {
         let m = master.lock().await;
@@ -298,9 +470,9 @@ pub async fn store_upload_handler(req: actix_web::HttpRequest, master: web::Data
     drop(f);
 
     // store
-    let mut meta = {
+    let meta = {
         let ds = ds.lock().await;
-        match ds.add(&tmp_path) {
+        match ds.add_to(&tmp_path, namespace.as_deref(), origin.as_deref(), &tags) {
             Ok(m) => m,
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::InvalidInput || e.kind() == std::io::ErrorKind::OutOfMemory {
@@ -311,41 +483,19 @@ pub async fn store_upload_handler(req: actix_web::HttpRequest, master: web::Data
         }
     };
 
-    // overwrite fname with full path + persist to meta sidecar
-    if let Some(origin) = origin {
-        if origin.contains('\0') {
-            return HttpResponse::BadRequest().body("invalid X-Filename");
-        }
-
-        meta.fname = Some(origin);
-
-        // compute sidecar path: <shard>/<sha>.meta.json
-        let meta_path = {
-            let ds = ds.lock().await;
-            let data_path = ds.uri(&meta.sha256);
-            data_path.parent().unwrap().join(format!("{}.meta.json", meta.sha256))
-        };
-
-        // atomic rewrite
-        let tmp_meta = meta_path.with_extension("meta.json.tmp");
-        if let Err(e) = std::fs::write(&tmp_meta, serde_json::to_vec(&meta).unwrap()) {
-            return HttpResponse::InternalServerError().body(e.to_string());
-        }
-        if let Err(e) = std::fs::rename(&tmp_meta, &meta_path) {
-            return HttpResponse::InternalServerError().body(e.to_string());
-        }
-    }
-
     drop(tmp);
+    let target = format!("{} {}", meta.sha256, meta.fname.as_deref().unwrap_or_default());
+    audit_target(
+        &req,
+        match &meta.namespace {
+            Some(ns) => format!("{ns}:{target}"),
+            None => target,
+        }
+        .trim_end()
+        .to_string(),
+    );
 
-    HttpResponse::Ok().json(StoreMetaResponse {
-        sha256: meta.sha256,
-        size_bytes: meta.size_bytes,
-        fmode: meta.fmode,
-        created_unix: meta.created_unix,
-        expires_unix: meta.expires_unix,
-        fname: meta.fname,
-    })
+    HttpResponse::Ok().json(StoreMetaResponse::from(meta))
 }
 
 #[utoipa::path(
@@ -356,20 +506,31 @@ pub async fn store_upload_handler(req: actix_web::HttpRequest, master: web::Data
         ("bearer_auth" = [])
     ),
     params(
-        ("fname" = String, Query, description = "Full path stored in metadata (meta.fname)")
+        ("fname" = String, Query, description = "Full path stored in metadata (meta.fname)"),
+        ("namespace" = Option<String>, Query, description = "Namespace to resolve in (default: the default namespace)")
     ),
     responses(
         (status = 200, description = "Resolved metadata", body = StoreMetaResponse),
         (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
         (status = 404, description = "Not found"),
         (status = 500, description = "Error")
     )
 )]
 #[get("/store/resolve")]
 pub async fn store_resolve_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, q: web::Query<StoreResolveQuery>) -> impl Responder {
-    if let Err(err) = authorise_request(&req).await {
-        return HttpResponse::Unauthorized().json(StoreErrorResponse { error: err.to_string() });
-    }
+    let uid = match authorise_access(&req, &master, &Access::DatastoreRead).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let namespace = match namespace_param(q.namespace.as_deref()) {
+        Ok(ns) => ns,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let reader = match StoreReader::of(&master, &uid).await {
+        Ok(reader) => reader,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
     let (root, want) = {
         let m = master.lock().await;
         (m.cfg().await.datastore_path(), q.fname.clone())
@@ -390,7 +551,7 @@ pub async fn store_resolve_handler(req: HttpRequest, master: web::Data<MasterInt
                 Err(_) => continue,
             };
 
-            if meta.fname.as_deref() != Some(want.as_str()) {
+            if meta.fname.as_deref() != Some(want.as_str()) || meta.namespace != namespace || !reader.may_read(&meta) {
                 continue;
             }
 
@@ -417,14 +578,7 @@ pub async fn store_resolve_handler(req: HttpRequest, master: web::Data<MasterInt
     };
 
     match meta {
-        Some(meta) => HttpResponse::Ok().json(StoreMetaResponse {
-            sha256: meta.sha256,
-            size_bytes: meta.size_bytes,
-            fmode: meta.fmode,
-            created_unix: meta.created_unix,
-            expires_unix: meta.expires_unix,
-            fname: meta.fname,
-        }),
+        Some(meta) => HttpResponse::Ok().json(StoreMetaResponse::from(meta)),
         None => HttpResponse::NotFound().finish(),
     }
 }
@@ -438,19 +592,32 @@ pub async fn store_resolve_handler(req: HttpRequest, master: web::Data<MasterInt
     ),
     params(
         ("prefix" = Option<String>, Query, description = "Only return items where meta.fname starts with this prefix"),
-        ("limit" = Option<usize>, Query, description = "Max items to return (default 200)")
+        ("limit" = Option<usize>, Query, description = "Max items to return (default 200)"),
+        ("namespace" = Option<String>, Query, description = "Only return items of this namespace (default: the default namespace)"),
+        ("tags" = Option<String>, Query, description = "Only return items with all these tags: comma-separated key=value, or key for any value")
     ),
     responses(
         (status = 200, description = "List of metadata", body = Vec<StoreMetaResponse>),
         (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
         (status = 500, description = "Error")
     )
 )]
 #[get("/store/list")]
 pub async fn store_list_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, q: web::Query<StoreListQuery>) -> impl Responder {
-    if let Err(err) = authorise_request(&req).await {
-        return HttpResponse::Unauthorized().json(StoreErrorResponse { error: err.to_string() });
-    }
+    let uid = match authorise_access(&req, &master, &Access::DatastoreRead).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let namespace = match namespace_param(q.namespace.as_deref()) {
+        Ok(ns) => ns,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let reader = match StoreReader::of(&master, &uid).await {
+        Ok(reader) => reader,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+    let tags = TagFilter::parse(q.tags.as_deref().unwrap_or_default());
     let (root, prefix, limit) = {
         let m = master.lock().await;
         (m.cfg().await.datastore_path(), q.prefix.clone(), q.limit.unwrap_or(200).min(5000))
@@ -479,6 +646,9 @@ pub async fn store_list_handler(req: HttpRequest, master: web::Data<MasterInterf
             {
                 continue;
             }
+            if meta.namespace != namespace || !tags.matches(&meta.tags) || !reader.may_read(&meta) {
+                continue;
+            }
 
             out.push(meta);
         }
@@ -499,17 +669,273 @@ pub async fn store_list_handler(req: HttpRequest, master: web::Data<MasterInterf
         Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
     };
 
-    HttpResponse::Ok().json(
-        metas
-            .into_iter()
-            .map(|meta| StoreMetaResponse {
-                sha256: meta.sha256,
-                size_bytes: meta.size_bytes,
-                fmode: meta.fmode,
-                created_unix: meta.created_unix,
-                expires_unix: meta.expires_unix,
-                fname: meta.fname,
-            })
-            .collect::<Vec<_>>(),
+    HttpResponse::Ok().json(metas.into_iter().map(StoreMetaResponse::from).collect::<Vec<_>>())
+}
+
+/// Map a staged upload error to the response
+fn upload_error(err: std::io::Error) -> HttpResponse {
+    let body = StoreErrorResponse { error: err.to_string() };
+    match err.kind() {
+        std::io::ErrorKind::NotFound => HttpResponse::NotFound().json(body),
+        std::io::ErrorKind::InvalidInput => HttpResponse::BadRequest().json(body),
+        std::io::ErrorKind::InvalidData => HttpResponse::UnprocessableEntity().json(body),
+        std::io::ErrorKind::OutOfMemory => HttpResponse::PayloadTooLarge().json(body),
+        _ => HttpResponse::InternalServerError().json(body),
+    }
+}
+
+/// Staged upload of the caller. Uploads started by another user are rejected.
+fn own_upload(ds: &DataStorage, id: &str, uid: &str) -> Result<DataUpload, HttpResponse> {
+    match ds.upload(id) {
+        Ok(Some(upload)) if upload.is_owned_by(uid) => Ok(upload),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(StoreErrorResponse { error: format!("upload {id} was started by another user") })),
+        Ok(None) => Err(HttpResponse::NotFound().json(StoreErrorResponse { error: format!("unknown upload {id}") })),
+        Err(err) => Err(upload_error(err)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/store/uploads",
+    tag = "Datastore",
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = StoreUploadStartRequest,
+    responses(
+        (status = 200, description = "Upload started", body = StoreUploadResponse),
+        (status = 400, description = "Invalid request or the object is too big", body = StoreErrorResponse),
+        (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StoreErrorResponse),
+        (status = 500, description = "Datastore error", body = StoreErrorResponse)
+    )
+)]
+#[post("/store/uploads")]
+pub async fn store_upload_start_handler(
+    req: HttpRequest, master: web::Data<MasterInterfaceType>, body: web::Json<StoreUploadStartRequest>,
+) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    audit_target(&req, body.fname.clone().unwrap_or_default());
+    let namespace = match namespace_param(body.namespace.as_deref()) {
+        Ok(ns) => ns,
+        Err(err) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: err.to_string() }),
+    };
+
+    let ds = {
+        let m = master.lock().await;
+        m.datastore().await
+    };
+    let ds = ds.lock().await;
+    match ds.upload_start(&uid, body.size_bytes, body.sha256.as_deref(), namespace.as_deref(), body.fname.as_deref(), &body.tags) {
+        Ok(upload) => {
+            audit_target(&req, format!("{} {}", upload.id, upload.fname.as_deref().unwrap_or_default()).trim_end().to_string());
+            HttpResponse::Ok().json(StoreUploadResponse::from(upload))
+        }
+        Err(err) => upload_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/store/uploads/{upload_id}",
+    tag = "Datastore",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("upload_id" = String, Path, description = "Upload id, returned when the upload started")
+    ),
+    responses(
+        (status = 200, description = "State of the upload, to resume it", body = StoreUploadResponse),
+        (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy, or the upload was started by another user", body = StoreErrorResponse),
+        (status = 404, description = "Unknown upload", body = StoreErrorResponse)
+    )
+)]
+#[get("/store/uploads/{upload_id}")]
+pub async fn store_upload_state_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, upload_id: web::Path<String>) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let ds = {
+        let m = master.lock().await;
+        m.datastore().await
+    };
+    let ds = ds.lock().await;
+    match own_upload(&ds, &upload_id, &uid) {
+        Ok(upload) => HttpResponse::Ok().json(StoreUploadResponse::from(upload)),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/store/uploads/{upload_id}",
+    tag = "Datastore",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("upload_id" = String, Path, description = "Upload id, returned when the upload started"),
+        ("offset" = u64, Query, description = "Offset of the chunk, which must be the bytes received so far"),
+        ("X-Chunk-Sha256" = String, Header, description = "SHA256 of the chunk")
+    ),
+    request_body(
+        content = Vec<u8>,
+        content_type = "application/octet-stream",
+        description = "Chunk of the object"
+    ),
+    responses(
+        (status = 200, description = "Chunk stored", body = StoreUploadResponse),
+        (status = 400, description = "Invalid chunk", body = StoreErrorResponse),
+        (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy, or the upload was started by another user", body = StoreErrorResponse),
+        (status = 404, description = "Unknown upload", body = StoreErrorResponse),
+        (status = 409, description = "Offset is not the bytes received so far, resume from the upload state", body = StoreErrorResponse),
+        (status = 413, description = "Chunk too large, or the datastore or namespace is full", body = StoreErrorResponse),
+        (status = 422, description = "Chunk checksum mismatch", body = StoreErrorResponse)
     )
+)]
+#[put("/store/uploads/{upload_id}")]
+pub async fn store_upload_chunk_handler(
+    req: HttpRequest, master: web::Data<MasterInterfaceType>, upload_id: web::Path<String>, q: web::Query<StoreChunkQuery>, mut payload: web::Payload,
+) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    let Some(sha256) = req.headers().get("X-Chunk-Sha256").and_then(|v| v.to_str().ok()).map(str::to_string) else {
+        return HttpResponse::BadRequest().json(StoreErrorResponse { error: "missing X-Chunk-Sha256 header".to_string() });
+    };
+
+    let mut data = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => return HttpResponse::BadRequest().json(StoreErrorResponse { error: e.to_string() }),
+        };
+        if data.len() + chunk.len() > MAX_UPLOAD_CHUNK_SIZE {
+            return HttpResponse::PayloadTooLarge().json(StoreErrorResponse { error: format!("chunk is larger than {MAX_UPLOAD_CHUNK_SIZE} bytes") });
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let ds = {
+        let m = master.lock().await;
+        m.datastore().await
+    };
+    let ds = ds.lock_owned().await;
+    match own_upload(&ds, &upload_id, &uid) {
+        Ok(upload) if upload.received_bytes != q.offset => {
+            return HttpResponse::Conflict()
+                .json(StoreErrorResponse { error: format!("chunk at offset {}, expected offset {}", q.offset, upload.received_bytes) });
+        }
+        Ok(_) => {}
+        Err(response) => return response,
+    }
+
+    let offset = q.offset;
+    match task::spawn_blocking(move || ds.upload_chunk(&upload_id, offset, &data, &sha256)).await {
+        Ok(Ok(upload)) => HttpResponse::Ok().json(StoreUploadResponse::from(upload)),
+        Ok(Err(err)) => upload_error(err),
+        Err(err) => HttpResponse::InternalServerError().json(StoreErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/store/uploads/{upload_id}/complete",
+    tag = "Datastore",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("upload_id" = String, Path, description = "Upload id, returned when the upload started")
+    ),
+    responses(
+        (status = 200, description = "Object verified and stored", body = StoreMetaResponse),
+        (status = 400, description = "Upload is incomplete", body = StoreErrorResponse),
+        (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy, or the upload was started by another user", body = StoreErrorResponse),
+        (status = 404, description = "Unknown upload", body = StoreErrorResponse),
+        (status = 413, description = "Datastore or namespace full", body = StoreErrorResponse),
+        (status = 422, description = "Object checksum mismatch, the upload is dropped", body = StoreErrorResponse)
+    )
+)]
+#[post("/store/uploads/{upload_id}/complete")]
+pub async fn store_upload_complete_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, upload_id: web::Path<String>) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    audit_target(&req, upload_id.to_string());
+    let ds = {
+        let m = master.lock().await;
+        m.datastore().await
+    };
+    let ds = ds.lock_owned().await;
+    if let Err(response) = own_upload(&ds, &upload_id, &uid) {
+        return response;
+    }
+    let id = upload_id.to_string();
+    match task::spawn_blocking(move || ds.upload_finish(&id)).await {
+        Ok(Ok(meta)) => {
+            let target = format!("{} {}", meta.sha256, meta.fname.as_deref().unwrap_or_default());
+            audit_target(
+                &req,
+                match &meta.namespace {
+                    Some(ns) => format!("{ns}:{target}"),
+                    None => target,
+                }
+                .trim_end()
+                .to_string(),
+            );
+            HttpResponse::Ok().json(StoreMetaResponse::from(meta))
+        }
+        Ok(Err(err)) => upload_error(err),
+        Err(err) => HttpResponse::InternalServerError().json(StoreErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/store/uploads/{upload_id}",
+    tag = "Datastore",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("upload_id" = String, Path, description = "Upload id, returned when the upload started")
+    ),
+    responses(
+        (status = 200, description = "Upload dropped"),
+        (status = 401, description = "Unauthorized", body = StoreErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy, or the upload was started by another user", body = StoreErrorResponse),
+        (status = 404, description = "Unknown upload", body = StoreErrorResponse)
+    )
+)]
+#[delete("/store/uploads/{upload_id}")]
+pub async fn store_upload_abort_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, upload_id: web::Path<String>) -> impl Responder {
+    let uid = match authorise_access(&req, &master, &Access::DatastoreWrite).await {
+        Ok(uid) => uid,
+        Err(err) => return HttpResponse::build(err.status()).json(StoreErrorResponse { error: err.to_string() }),
+    };
+    audit_target(&req, upload_id.to_string());
+    let ds = {
+        let m = master.lock().await;
+        m.datastore().await
+    };
+    let ds = ds.lock().await;
+    if let Err(response) = own_upload(&ds, &upload_id, &uid) {
+        return response;
+    }
+    match ds.upload_abort(&upload_id) {
+        Ok(true) => HttpResponse::Ok().finish(),
+        Ok(false) => HttpResponse::NotFound().json(StoreErrorResponse { error: format!("unknown upload {upload_id}") }),
+        Err(err) => upload_error(err),
+    }
 }
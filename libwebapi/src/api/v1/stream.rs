@@ -0,0 +1,89 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{
+        TAG_MINIONS,
+        minions::{authorise_access, authorise_request},
+    },
+    stream::StreamFilter,
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    web::{Bytes, Data, Query},
+};
+use futures_util::stream;
+use libsysinspect::rbac::Access;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::{sync::broadcast::error::RecvError, time};
+use utoipa::ToSchema;
+
+/// Interval of the keep-alive comments. The bearer token is re-checked on each of them.
+const STREAM_KEEPALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StreamErrorResponse {
+    pub error: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/events/stream",
+    tag = TAG_MINIONS,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("cycle_id" = Option<String>, Query, description = "Only events of this cycle"),
+        ("mid" = Option<String>, Query, description = "Only events of this minion System Id"),
+        ("model" = Option<String>, Query, description = "Only events of cycles started by this model")
+    ),
+    responses(
+        (status = 200, description = "Server-Sent Events stream: minion_accepted, action_response, constraint_failure, cycle_complete, minion_online, minion_offline, sensor_lifecycle", content_type = "text/event-stream", body = String),
+        (status = 401, description = "Unauthorized", body = StreamErrorResponse),
+        (status = 403, description = "Forbidden by the access control policy", body = StreamErrorResponse)
+    )
+)]
+#[get("/api/v1/events/stream")]
+pub async fn event_stream_handler(req: HttpRequest, master: Data<MasterInterfaceType>, q: Query<StreamFilter>) -> impl Responder {
+    if let Err(err) = authorise_access(&req, &master, &Access::Read).await {
+        return HttpResponse::build(err.status()).json(StreamErrorResponse { error: err.to_string() });
+    }
+
+    let rx = master.lock().await.events().await;
+    let filter = q.into_inner();
+    let mut keepalive = time::interval(STREAM_KEEPALIVE);
+    keepalive.reset();
+
+    let events = stream::unfold(Some((rx, keepalive, filter, req)), |state| async move {
+        let (mut rx, mut keepalive, filter, req) = state?;
+        loop {
+            tokio::select! {
+                evt = rx.recv() => match evt {
+                    Ok(evt) if filter.matches(&evt) => {
+                        return Some((Ok::<Bytes, actix_web::Error>(Bytes::from(evt.to_sse())), Some((rx, keepalive, filter, req))));
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        let frame = format!("event: lagged\ndata: {{\"skipped\":{skipped}}}\n\n");
+                        return Some((Ok(Bytes::from(frame)), Some((rx, keepalive, filter, req))));
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = keepalive.tick() => {
+                    // Session expired or closed: tell the client and end the stream
+                    if let Err(err) = authorise_request(&req).await {
+                        let frame = format!("event: unauthorized\ndata: {}\n\n", serde_json::json!({"error": err.to_string()}));
+                        return Some((Ok(Bytes::from(frame)), None));
+                    }
+                    return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), Some((rx, keepalive, filter, req))));
+                }
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events)
+}
@@ -1,17 +1,22 @@
-use crate::{MasterInterfaceType, api::v1::TAG_SYSTEM, sessions::get_session_store};
-use actix_web::{HttpResponse, Responder, post, web};
+use crate::{
+    MasterInterfaceType,
+    api::v1::TAG_SYSTEM,
+    audit::{AUDIT_ANONYMOUS, audit_actor},
+    sessions::get_session_store,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
 use libsysinspect::cfg::mmconf::AuthMethod::Pam;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(ToSchema, Serialize)]
+#[derive(Debug, Clone, ToSchema, Deserialize, Serialize)]
 pub struct HealthInfo {
     pub telemetry_enabled: bool,
     pub scheduler_tasks: usize,
     pub api_version: String,
 }
 
-#[derive(ToSchema, Serialize)]
+#[derive(Debug, Clone, ToSchema, Deserialize, Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub info: HealthInfo,
@@ -49,14 +54,14 @@ pub async fn health_handler(master: web::Data<MasterInterfaceType>, _body: ()) -
     let lock = master.lock().await;
     let cfg = lock.cfg().await;
 
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": "healthy",
-        "info": {
-            "telemetry.enabled": cfg.telemetry_enabled(),
-            "scheduler.tasks": cfg.scheduler().len(),
-            "api.version": cfg.api_version(),
-        }
-    }))
+    HttpResponse::Ok().json(HealthResponse {
+        status: "healthy".to_string(),
+        info: HealthInfo {
+            telemetry_enabled: cfg.telemetry_enabled(),
+            scheduler_tasks: cfg.scheduler().len(),
+            api_version: cfg.api_version().to_string(),
+        },
+    })
 }
 
 #[derive(ToSchema, Deserialize, Serialize)]
@@ -107,7 +112,8 @@ impl AuthResponse {
     description = "Authenticates a user using configured authentication method and returns a bearer token for subsequent HTTPS JSON requests.",
 )]
 #[post("/api/v1/authenticate")]
-pub async fn authenticate_handler(master: web::Data<MasterInterfaceType>, body: web::Json<AuthRequest>) -> impl Responder {
+pub async fn authenticate_handler(req: HttpRequest, master: web::Data<MasterInterfaceType>, body: web::Json<AuthRequest>) -> impl Responder {
+    audit_actor(&req, if body.username.trim().is_empty() { AUDIT_ANONYMOUS } else { body.username.trim() });
     let master = master.lock().await;
     let cfg = master.cfg().await;
     if cfg.api_devmode() {
@@ -0,0 +1,199 @@
+use crate::{
+    MasterInterfaceType,
+    api::v1::{
+        TAG_SYSTEM,
+        minions::{TOKEN_UID_PREFIX, authorise_access},
+    },
+    audit::audit_target,
+};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, delete, get, post,
+    web::{Data, Json, Path},
+};
+use libsysinspect::rbac::{
+    Access,
+    tokens::{ApiToken, TokenOwner, TokenScope, TokenStore},
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenCreateRequest {
+    /// Token name: letters, digits, '.', '_' or '-'
+    pub name: String,
+
+    /// read, query, command, datastore-write or all. Read-only if empty.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// Seconds the token stays valid. Never expires if omitted.
+    #[serde(default)]
+    pub ttl: Option<u64>,
+}
+
+/// API token, without its secret
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenInfo {
+    pub name: String,
+    pub owner: String,
+    pub scopes: Vec<String>,
+
+    /// RFC 3339 timestamps
+    pub created: String,
+    pub expires: Option<String>,
+    pub last_used: Option<String>,
+    pub expired: bool,
+}
+
+impl From<ApiToken> for TokenInfo {
+    fn from(token: ApiToken) -> Self {
+        TokenInfo {
+            expired: token.is_expired(),
+            name: token.name,
+            owner: token.owner.to_string(),
+            scopes: token.scopes.iter().map(|s| s.to_string()).collect(),
+            created: token.created.to_rfc3339(),
+            expires: token.expires.map(|t| t.to_rfc3339()),
+            last_used: token.last_used.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenCreateResponse {
+    /// The token itself. It is shown only once and cannot be recovered.
+    pub token: String,
+    pub info: TokenInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenListResponse {
+    pub tokens: Vec<TokenInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenErrorResponse {
+    pub error: String,
+}
+
+/// Authenticate a Web API user who manages own tokens. Tokens cannot manage tokens.
+async fn token_user(req: &HttpRequest, master: &MasterInterfaceType) -> Result<(String, TokenStore), HttpResponse> {
+    let uid = authorise_access(req, master, &Access::Read)
+        .await
+        .map_err(|err| HttpResponse::build(err.status()).json(TokenErrorResponse { error: err.to_string() }))?;
+    if uid.starts_with(TOKEN_UID_PREFIX) || uid.starts_with("minion:") {
+        return Err(HttpResponse::Forbidden().json(TokenErrorResponse { error: "API tokens can be managed only by Web API users".to_string() }));
+    }
+
+    let root = master.lock().await.cfg().await.api_keys_root();
+    Ok((uid, TokenStore::new(&root)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tokens",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body = TokenCreateRequest,
+    responses(
+        (status = 200, description = "Token created. The token is returned only once.", body = TokenCreateResponse),
+        (status = 400, description = "Invalid name, scopes or the token already exists", body = TokenErrorResponse),
+        (status = 401, description = "Unauthorized", body = TokenErrorResponse),
+        (status = 403, description = "Forbidden, e.g. for requests authenticated with a token", body = TokenErrorResponse)
+    )
+)]
+#[post("/api/v1/tokens")]
+pub async fn token_create_handler(req: HttpRequest, master: Data<MasterInterfaceType>, body: Json<TokenCreateRequest>) -> impl Responder {
+    audit_target(&req, format!("token:{}", body.name));
+    let (uid, store) = match token_user(&req, &master).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let scopes = match TokenScope::parse_list(&body.scopes.join(",")) {
+        Ok(scopes) => scopes,
+        Err(err) => return HttpResponse::BadRequest().json(TokenErrorResponse { error: err.to_string() }),
+    };
+
+    match store.create(&body.name, TokenOwner::User(uid.clone()), scopes, body.ttl.map(Duration::from_secs)) {
+        Ok((token, secret)) => {
+            log::info!("Web API user {uid} created API token {}", token.name);
+            HttpResponse::Ok().json(TokenCreateResponse { token: secret, info: token.into() })
+        }
+        Err(err) => HttpResponse::BadRequest().json(TokenErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tokens",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "API tokens of the user", body = TokenListResponse),
+        (status = 401, description = "Unauthorized", body = TokenErrorResponse),
+        (status = 403, description = "Forbidden, e.g. for requests authenticated with a token", body = TokenErrorResponse),
+        (status = 500, description = "Error", body = TokenErrorResponse)
+    )
+)]
+#[get("/api/v1/tokens")]
+pub async fn token_list_handler(req: HttpRequest, master: Data<MasterInterfaceType>) -> impl Responder {
+    let (uid, store) = match token_user(&req, &master).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    match store.list() {
+        Ok(tokens) => HttpResponse::Ok().json(TokenListResponse {
+            tokens: tokens.into_iter().filter(|t| t.owner == TokenOwner::User(uid.clone())).map(TokenInfo::from).collect(),
+        }),
+        Err(err) => HttpResponse::InternalServerError().json(TokenErrorResponse { error: err.to_string() }),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tokens/{name}",
+    tag = TAG_SYSTEM,
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("name" = String, Path, description = "Token name")
+    ),
+    responses(
+        (status = 200, description = "Token revoked", body = TokenInfo),
+        (status = 401, description = "Unauthorized", body = TokenErrorResponse),
+        (status = 403, description = "Forbidden, e.g. for requests authenticated with a token", body = TokenErrorResponse),
+        (status = 404, description = "The user has no such token", body = TokenErrorResponse),
+        (status = 500, description = "Error", body = TokenErrorResponse)
+    )
+)]
+#[delete("/api/v1/tokens/{name}")]
+pub async fn token_revoke_handler(req: HttpRequest, master: Data<MasterInterfaceType>, name: Path<String>) -> impl Responder {
+    audit_target(&req, format!("token:{name}"));
+    let (uid, store) = match token_user(&req, &master).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let name = name.into_inner();
+    let token = match store.get(&name) {
+        Ok(Some(token)) if token.owner == TokenOwner::User(uid.clone()) => token,
+        Ok(_) => return HttpResponse::NotFound().json(TokenErrorResponse { error: format!("Token {name} not found") }),
+        Err(err) => return HttpResponse::InternalServerError().json(TokenErrorResponse { error: err.to_string() }),
+    };
+
+    match store.revoke(&name) {
+        Ok(_) => {
+            log::info!("Web API user {uid} revoked API token {name}");
+            HttpResponse::Ok().json(TokenInfo::from(token))
+        }
+        Err(err) => HttpResponse::InternalServerError().json(TokenErrorResponse { error: err.to_string() }),
+    }
+}
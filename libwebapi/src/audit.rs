@@ -0,0 +1,137 @@
+//! Audit trail of Web API calls.
+//!
+//! The middleware records every call of a route that changes something, every
+//! denied call and every read of the audit journal itself. Plain reads are not
+//! recorded, dashboards poll them continuously.
+//!
+//! Authentication stores the caller in the request extensions, handlers may
+//! add the target of the call and a failure that the status code does not
+//! tell, e.g. a query the master could not dispatch.
+
+use crate::MasterInterfaceType;
+use actix_web::{
+    Error, HttpMessage, HttpRequest,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, StatusCode},
+    middleware::Next,
+    web::Data,
+};
+use libsysinspect::audit::{AuditChannel, AuditEntry, AuditJournal, AuditOutcome};
+
+/// Calls under this path read the audit journal and are always recorded
+pub const AUDIT_PATH: &str = "/api/v1/audit";
+
+/// Minions authenticate here to fetch from the datastore, that is no operator action
+const MINION_AUTH_PATH: &str = "/store/auth/minion";
+
+/// Routes that change something, by method and path pattern. Chunks of an upload are not listed:
+/// the upload is recorded when it starts, completes or is aborted.
+const MUTATING_ROUTES: &[(&str, &str)] = &[
+    ("POST", "/api/v1/query"),
+    ("POST", "/api/v1/model/versions"),
+    ("POST", "/api/v1/model/versions/rollback"),
+    ("DELETE", "/api/v1/minions/{mid}"),
+    ("PUT", "/api/v1/traits"),
+    ("DELETE", "/api/v1/traits"),
+    ("POST", "/api/v1/profiles"),
+    ("PATCH", "/api/v1/profiles/{name}"),
+    ("DELETE", "/api/v1/profiles/{name}"),
+    ("POST", "/api/v1/profiles/{name}/minions"),
+    ("DELETE", "/api/v1/profiles/{name}/minions"),
+    ("POST", "/api/v1/keys/rotate"),
+    ("POST", "/api/v1/upgrade/required"),
+    ("POST", "/api/v1/tokens"),
+    ("DELETE", "/api/v1/tokens/{name}"),
+    ("POST", "/store"),
+    ("POST", "/store/uploads"),
+    ("POST", "/store/uploads/{upload_id}/complete"),
+    ("DELETE", "/store/uploads/{upload_id}"),
+];
+
+/// Actor of calls that never authenticated
+pub const AUDIT_ANONYMOUS: &str = "anonymous";
+
+/// Who made the request
+#[derive(Debug, Clone)]
+struct AuditActor(String);
+
+/// What the request acted on and how it ended, as told by the handler
+#[derive(Debug, Clone, Default)]
+struct AuditNote {
+    target: String,
+    failure: Option<String>,
+}
+
+/// Remember the caller of the request
+pub(crate) fn audit_actor(req: &HttpRequest, actor: &str) {
+    req.extensions_mut().insert(AuditActor(actor.to_string()));
+}
+
+/// Remember what the request acts on
+pub(crate) fn audit_target(req: &HttpRequest, target: impl Into<String>) {
+    let mut ext = req.extensions_mut();
+    match ext.get_mut::<AuditNote>() {
+        Some(note) => note.target = target.into(),
+        None => {
+            ext.insert(AuditNote { target: target.into(), failure: None });
+        }
+    }
+}
+
+/// Remember that the request failed although it is answered with a success status
+pub(crate) fn audit_failure(req: &HttpRequest, detail: impl Into<String>) {
+    let mut ext = req.extensions_mut();
+    match ext.get_mut::<AuditNote>() {
+        Some(note) => note.failure = Some(detail.into()),
+        None => {
+            ext.insert(AuditNote { target: String::new(), failure: Some(detail.into()) });
+        }
+    }
+}
+
+/// Calls of mutating routes, denied calls and reads of the journal are recorded.
+/// `pattern` is the matched route, e.g. `/api/v1/minions/{mid}`, none for unknown paths.
+fn is_audited(method: &Method, path: &str, pattern: Option<&str>, status: StatusCode) -> bool {
+    if path == MINION_AUTH_PATH {
+        return false;
+    }
+
+    let mutating = pattern.is_some_and(|pattern| MUTATING_ROUTES.contains(&(method.as_str(), pattern)));
+    mutating || matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) || path.starts_with(AUDIT_PATH)
+}
+
+/// Record the Web API call in the audit journal of the master
+pub async fn audit_middleware(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let (method, path) = (req.method().clone(), req.path().to_string());
+    let master = req.app_data::<Data<MasterInterfaceType>>().cloned();
+    let response = next.call(req).await?;
+
+    let status = response.status();
+    let pattern = response.request().match_pattern();
+    let Some(master) = master.filter(|_| is_audited(&method, &path, pattern.as_deref(), status)) else {
+        return Ok(response);
+    };
+
+    let (actor, note) = {
+        let ext = response.request().extensions();
+        (
+            ext.get::<AuditActor>().map(|a| a.0.clone()).unwrap_or_else(|| AUDIT_ANONYMOUS.to_string()),
+            ext.get::<AuditNote>().cloned().unwrap_or_default(),
+        )
+    };
+    let (outcome, detail) = match (status, note.failure) {
+        (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN, _) => (AuditOutcome::Denied, status.to_string()),
+        (_, Some(failure)) => (AuditOutcome::Failure, failure),
+        (status, None) if status.is_client_error() || status.is_server_error() => (AuditOutcome::Failure, status.to_string()),
+        _ => (AuditOutcome::Success, String::new()),
+    };
+
+    let root = master.lock().await.cfg().await.audit_root();
+    let entry = AuditEntry::new(&actor, AuditChannel::WebApi, &format!("{method} {path}"), &note.target, outcome).detail(detail);
+    if let Err(err) = AuditJournal::new(&root).submit(entry).await {
+        log::error!("Unable to record Web API call of {actor} in the audit journal: {err}");
+    }
+
+    Ok(response)
+}
@@ -1,29 +1,62 @@
-use crate::api::ApiVersions;
-use actix_web::{App, HttpServer, web};
+use crate::{
+    api::{
+        ApiVersions,
+        v1::cycles::{CycleEventInfo, CycleInfo, CycleMinionInfo},
+    },
+    stream::StreamEvent,
+};
+use actix_web::{App, HttpServer, middleware::from_fn, web};
 use colored::Colorize;
 use libcommon::SysinspectError;
 use libdatastore::resources::DataStorage;
-use libsysinspect::cfg::mmconf::MasterConfig;
+use libsysinspect::{
+    cfg::mmconf::MasterConfig,
+    console::{ConsoleQuery, ConsoleQueuedCommandRow, ConsoleResponse},
+};
 use once_cell::sync::OnceCell;
 use rustls::RootCertStore;
 use rustls::ServerConfig;
 use rustls::server::WebPkiClientVerifier;
-use std::{fs::File, io::BufReader, sync::Arc, thread};
-use tokio::sync::Mutex;
+use std::{collections::HashMap, fs::File, io::BufReader, sync::Arc, thread};
+use tokio::sync::{Mutex, broadcast, oneshot};
 use x509_parser::prelude::parse_x509_certificate;
 
 pub mod api;
+pub mod audit;
 #[cfg(test)]
 mod lib_ut;
 #[cfg(feature = "pam")]
 pub mod pamauth;
 pub mod sessions;
+pub mod stream;
 
 #[async_trait::async_trait]
 pub trait MasterInterface: Send + Sync {
     async fn cfg(&self) -> &MasterConfig;
-    async fn query(&mut self, query: String) -> Result<(), SysinspectError>;
+
+    /// Dispatch a query and return the cycle Id of it
+    async fn query(&mut self, query: String, ttl: Option<u64>, supersede: Option<String>) -> Result<String, SysinspectError>;
     async fn datastore(&self) -> Arc<Mutex<DataStorage>>;
+    async fn commands(&self, mid: Option<String>) -> Result<Vec<ConsoleQueuedCommandRow>, SysinspectError>;
+
+    /// Recorded query cycles, oldest first
+    async fn cycles(&self) -> Result<Vec<CycleInfo>, SysinspectError>;
+
+    /// Minions that answered within the cycle. `None` if the cycle is unknown.
+    async fn cycle_minions(&self, cycle_id: &str) -> Result<Option<Vec<CycleMinionInfo>>, SysinspectError>;
+
+    /// Events of the minion within the cycle. `None` if the cycle or the minion is unknown.
+    async fn cycle_events(&self, cycle_id: &str, mid: &str) -> Result<Option<Vec<CycleEventInfo>>, SysinspectError>;
+
+    /// Run a cluster command the same way the console does. The command runs in the background
+    /// and may need the master itself, so await the response only after releasing the master lock.
+    async fn cluster_command(&self, query: ConsoleQuery) -> Result<oneshot::Receiver<ConsoleResponse>, SysinspectError>;
+
+    /// Traits of a registered minion. `None` if the minion is unknown.
+    async fn minion_traits(&self, mid: &str) -> Result<Option<HashMap<String, serde_json::Value>>, SysinspectError>;
+
+    /// Subscribe to the live feed of cycle and minion events, published from now on
+    async fn events(&self) -> broadcast::Receiver<StreamEvent>;
 }
 
 pub type MasterInterfaceType = Arc<Mutex<dyn MasterInterface + Send + Sync + 'static>>;
@@ -249,7 +282,7 @@ pub fn start_embedded_webapi(cfg: MasterConfig, master: MasterInterfaceType) ->
                 if let Some(ver) = api::get(devmode, ccfg.api_doc_enabled(), version) {
                     scope = ver.load(scope);
                 }
-                App::new().app_data(web::Data::new(cmaster.clone())).service(scope)
+                App::new().app_data(web::Data::new(cmaster.clone())).wrap(from_fn(audit::audit_middleware)).service(scope)
             });
 
             let server = server.bind_rustls_0_23((bind_addr.as_str(), bind_port as u16), tls_config).map_err(SysinspectError::from)?;
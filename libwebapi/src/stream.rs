@@ -0,0 +1,168 @@
+//! Live feed of cycle and minion events for Web API subscribers.
+//!
+//! The master publishes events here as they happen, the streaming endpoint
+//! fans them out to connected clients.
+
+#[cfg(test)]
+#[path = "stream_ut.rs"]
+mod stream_ut;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Events kept for slow subscribers before they start lagging
+const STREAM_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamEventKind {
+    /// First result of a cycle arrived from the minion
+    MinionAccepted,
+
+    /// Minion returned an action response
+    ActionResponse,
+
+    /// Action response has failed constraints
+    ConstraintFailure,
+
+    /// Minion finished the cycle
+    CycleComplete,
+
+    MinionOnline,
+    MinionOffline,
+
+    /// Sensor on the minion was started, stopped, restarted or has crashed
+    SensorLifecycle,
+}
+
+impl StreamEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamEventKind::MinionAccepted => "minion_accepted",
+            StreamEventKind::ActionResponse => "action_response",
+            StreamEventKind::ConstraintFailure => "constraint_failure",
+            StreamEventKind::CycleComplete => "cycle_complete",
+            StreamEventKind::MinionOnline => "minion_online",
+            StreamEventKind::MinionOffline => "minion_offline",
+            StreamEventKind::SensorLifecycle => "sensor_lifecycle",
+        }
+    }
+}
+
+/// Event pushed to the streaming subscribers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StreamEvent {
+    pub kind: StreamEventKind,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minion_id: Option<String>,
+
+    /// Query that started the cycle, model first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+
+    /// RFC 3339 timestamp
+    pub timestamp: String,
+
+    /// Raw event data, such as the action response
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    #[schema(value_type = Object)]
+    pub data: Value,
+}
+
+impl StreamEvent {
+    pub fn new(kind: StreamEventKind, timestamp: String) -> Self {
+        StreamEvent {
+            kind,
+            cycle_id: None,
+            minion_id: None,
+            model: None,
+            entity_id: None,
+            action_id: None,
+            outcome: None,
+            timestamp,
+            data: Value::Null,
+        }
+    }
+
+    pub fn cycle(mut self, cycle_id: &str) -> Self {
+        self.cycle_id = Some(cycle_id.to_string()).filter(|c| !c.is_empty());
+        self
+    }
+
+    pub fn minion(mut self, mid: &str) -> Self {
+        self.minion_id = Some(mid.to_string()).filter(|m| !m.is_empty());
+        self
+    }
+
+    pub fn model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string()).filter(|m| !m.is_empty());
+        self
+    }
+
+    pub fn action(mut self, entity_id: &str, action_id: &str, outcome: &str) -> Self {
+        self.entity_id = Some(entity_id.to_string());
+        self.action_id = Some(action_id.to_string());
+        self.outcome = Some(outcome.to_string());
+        self
+    }
+
+    pub fn data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Render the event as a Server-Sent Events frame
+    pub fn to_sse(&self) -> String {
+        format!("event: {}\ndata: {}\n\n", self.kind.as_str(), serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// Subscriber side selection of the events
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StreamFilter {
+    /// Only events of this cycle
+    pub cycle_id: Option<String>,
+
+    /// Only events of this minion
+    pub mid: Option<String>,
+
+    /// Only events of cycles started by this model, e.g. "cm/file-ops"
+    pub model: Option<String>,
+}
+
+impl StreamFilter {
+    /// Events without the filtered attribute (e.g. minion online for a cycle filter) are dropped.
+    pub fn matches(&self, evt: &StreamEvent) -> bool {
+        let cycle = self.cycle_id.as_deref().is_none_or(|cid| evt.cycle_id.as_deref() == Some(cid));
+        let minion = self.mid.as_deref().is_none_or(|mid| evt.minion_id.as_deref() == Some(mid));
+        let model = self.model.as_deref().is_none_or(|model| {
+            evt.model.as_deref().is_some_and(|m| m == model || m.strip_prefix(model).is_some_and(|rest| rest.starts_with(['/', ';'])))
+        });
+
+        cycle && minion && model
+    }
+}
+
+/// Sender of the live feed. The master owns it and publishes the events, the streaming
+/// endpoint subscribes to it through the master interface.
+pub type StreamSender = broadcast::Sender<StreamEvent>;
+
+/// New live feed without subscribers. Events published without subscribers are dropped.
+pub fn channel() -> StreamSender {
+    broadcast::channel(STREAM_CAPACITY).0
+}
@@ -0,0 +1,50 @@
+use super::{StreamEvent, StreamEventKind, StreamFilter, channel};
+
+fn action_event() -> StreamEvent {
+    StreamEvent::new(StreamEventKind::ActionResponse, "2026-01-01T00:00:00+00:00".to_string())
+        .cycle("c1")
+        .minion("m1")
+        .model("cm/file-ops;*")
+        .action("file", "check", "success")
+}
+
+#[test]
+fn filter_matches_cycle_minion_and_model() {
+    let evt = action_event();
+
+    assert!(StreamFilter::default().matches(&evt));
+    assert!(StreamFilter { cycle_id: Some("c1".to_string()), mid: Some("m1".to_string()), model: Some("cm/file-ops".to_string()) }.matches(&evt));
+    assert!(StreamFilter { model: Some("cm".to_string()), ..Default::default() }.matches(&evt));
+    assert!(!StreamFilter { model: Some("cm/file".to_string()), ..Default::default() }.matches(&evt));
+    assert!(!StreamFilter { cycle_id: Some("c2".to_string()), ..Default::default() }.matches(&evt));
+    assert!(!StreamFilter { mid: Some("m2".to_string()), ..Default::default() }.matches(&evt));
+}
+
+#[test]
+fn filter_drops_events_without_the_filtered_attribute() {
+    let online = StreamEvent::new(StreamEventKind::MinionOnline, String::new()).minion("m1");
+
+    assert!(StreamFilter { mid: Some("m1".to_string()), ..Default::default() }.matches(&online));
+    assert!(!StreamFilter { cycle_id: Some("c1".to_string()), ..Default::default() }.matches(&online));
+}
+
+#[test]
+fn event_renders_as_sse_frame() {
+    let frame = action_event().to_sse();
+
+    assert!(frame.starts_with("event: action_response\ndata: {"));
+    assert!(frame.ends_with("}\n\n"));
+    assert!(frame.contains(r#""kind":"action_response""#));
+    assert!(!frame.contains(r#""data""#));
+}
+
+#[tokio::test]
+async fn subscribers_receive_published_events() {
+    let stream = channel();
+    let mut rx = stream.subscribe();
+    stream.send(action_event()).unwrap();
+
+    let evt = rx.recv().await.unwrap();
+    assert_eq!(evt.kind, StreamEventKind::ActionResponse);
+    assert_eq!(evt.cycle_id.as_deref(), Some("c1"));
+}
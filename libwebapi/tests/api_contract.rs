@@ -1,19 +1,33 @@
-use actix_web::{App, HttpServer, web};
+use actix_web::{App, HttpServer, middleware::from_fn, web};
 use async_trait::async_trait;
 use libdatastore::{cfg::DataStorageConfig, resources::DataStorage};
-use libsysinspect::cfg::mmconf::MasterConfig;
+use libsysinspect::{
+    cfg::mmconf::MasterConfig,
+    console::{ConsoleOnlineMinionRow, ConsolePayload, ConsoleQuery, ConsoleResponse},
+};
 use libwebapi::{
     MasterInterface, MasterInterfaceType,
-    api::{self, ApiVersions},
+    api::{
+        self, ApiVersions,
+        v1::cycles::{CycleEventInfo, CycleInfo, CycleMinionInfo},
+    },
+    audit::audit_middleware,
     ensure_rustls_crypto_provider,
+    stream::{self, StreamEvent, StreamEventKind, StreamSender},
 };
 use reqwest::{Certificate, Identity};
 use rustls::RootCertStore;
 use rustls::ServerConfig;
 use rustls::server::WebPkiClientVerifier;
-use std::{fs, io::BufReader, path::Path, sync::Arc};
+use std::{
+    fs,
+    io::BufReader,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+use tempfile::TempDir;
 use tokio::{
-    sync::Mutex,
+    sync::{Mutex, oneshot},
     task::JoinHandle,
     time::{Duration, sleep},
 };
@@ -24,10 +38,14 @@ const MTLS_SERVER_KEY_PEM: &str = include_str!("data/webapi-test-server.key");
 const MTLS_CLIENT_CERT_PEM: &str = include_str!("data/webapi-test-client.crt");
 const MTLS_CLIENT_KEY_PEM: &str = include_str!("data/webapi-test-client.key");
 
+/// Live feed of the test masters, the tests publish into it
+static STREAM: LazyLock<StreamSender> = LazyLock::new(stream::channel);
+
 struct TestMaster {
     cfg: MasterConfig,
     queries: Arc<Mutex<Vec<String>>>,
     datastore: Arc<Mutex<DataStorage>>,
+    _root: TempDir,
 }
 
 #[async_trait]
@@ -36,14 +54,106 @@ impl MasterInterface for TestMaster {
         &self.cfg
     }
 
-    async fn query(&mut self, query: String) -> Result<(), libcommon::SysinspectError> {
+    async fn query(&mut self, query: String, _ttl: Option<u64>, _supersede: Option<String>) -> Result<String, libcommon::SysinspectError> {
         self.queries.lock().await.push(query);
-        Ok(())
+        Ok("cycle-1".to_string())
     }
 
     async fn datastore(&self) -> Arc<Mutex<DataStorage>> {
         Arc::clone(&self.datastore)
     }
+
+    async fn commands(&self, _mid: Option<String>) -> Result<Vec<libsysinspect::console::ConsoleQueuedCommandRow>, libcommon::SysinspectError> {
+        Ok(vec![])
+    }
+
+    async fn cycles(&self) -> Result<Vec<CycleInfo>, libcommon::SysinspectError> {
+        Ok((1..=3)
+            .map(|n| CycleInfo {
+                cycle_id: format!("cycle-{n}"),
+                query: "cm/file-ops;*".to_string(),
+                started_at: String::new(),
+                model_version: None,
+                model_hash: None,
+            })
+            .collect())
+    }
+
+    async fn cycle_minions(&self, cycle_id: &str) -> Result<Option<Vec<CycleMinionInfo>>, libcommon::SysinspectError> {
+        if cycle_id != "cycle-1" {
+            return Ok(None);
+        }
+
+        Ok(Some(vec![CycleMinionInfo {
+            minion_id: "m1".to_string(),
+            hostname: "m1.example.com".to_string(),
+            ipaddr: "10.0.0.1".to_string(),
+            events: 2,
+            errors: 1,
+            outcomes: vec!["error".to_string(), "success".to_string()],
+        }]))
+    }
+
+    async fn cycle_events(&self, cycle_id: &str, mid: &str) -> Result<Option<Vec<CycleEventInfo>>, libcommon::SysinspectError> {
+        if cycle_id != "cycle-1" || mid != "m1" {
+            return Ok(None);
+        }
+
+        let event = |entity: &str, retcode: i64| {
+            CycleEventInfo::new(
+                cycle_id.to_string(),
+                entity.to_string(),
+                "check".to_string(),
+                "$".to_string(),
+                String::new(),
+                Default::default(),
+                [("retcode".to_string(), serde_json::json!(retcode))].into_iter().collect(),
+            )
+        };
+        Ok(Some(vec![event("file", 0), event("pkg", 1), event("file", 1)]))
+    }
+
+    async fn cluster_command(&self, query: ConsoleQuery) -> Result<oneshot::Receiver<ConsoleResponse>, libcommon::SysinspectError> {
+        self.queries.lock().await.push(format!("{};{};{};{}", query.model, query.query, query.mid, query.context));
+
+        let response = if query.mid == "unknown" {
+            ConsoleResponse::err("Unable to find minion unknown")
+        } else if query.model == "cmd://cluster/minion/online" {
+            ConsoleResponse::ok(ConsolePayload::OnlineMinions {
+                rows: vec![ConsoleOnlineMinionRow {
+                    fqdn: "m1.example.com".to_string(),
+                    hostname: "m1".to_string(),
+                    ip: "10.0.0.1".to_string(),
+                    minion_id: "m1".to_string(),
+                    alive: true,
+                    version: "0.4.0".to_string(),
+                    target_version: String::new(),
+                    outdated: false,
+                    upgrade_required: false,
+                    upgrade_unreachable: false,
+                    os_distribution: String::new(),
+                    os_name: "linux".to_string(),
+                    os_version: String::new(),
+                    kernel: String::new(),
+                    sensors: vec![],
+                }],
+            })
+        } else {
+            ConsoleResponse::ok(ConsolePayload::Ack { action: "accepted_console_command".to_string(), target: query.model, count: 0, items: vec![] })
+        };
+
+        let (tx, rx) = oneshot::channel();
+        let _ = tx.send(response);
+        Ok(rx)
+    }
+
+    async fn minion_traits(&self, mid: &str) -> Result<Option<std::collections::HashMap<String, serde_json::Value>>, libcommon::SysinspectError> {
+        Ok((mid == "m1").then(|| [("rack".to_string(), serde_json::json!("r12"))].into_iter().collect()))
+    }
+
+    async fn events(&self) -> tokio::sync::broadcast::Receiver<StreamEvent> {
+        STREAM.subscribe()
+    }
 }
 
 fn write_cfg(root: &Path, devmode: bool, doc_enabled: bool) -> MasterConfig {
@@ -51,7 +161,8 @@ fn write_cfg(root: &Path, devmode: bool, doc_enabled: bool) -> MasterConfig {
     fs::write(
         &cfg_path,
         format!(
-            "config:\n  master:\n    fileserver.models: [cm, net]\n    api.bind.ip: 127.0.0.1\n    api.bind.port: 4202\n    api.devmode: {}\n    api.doc: {}\n",
+            "config:\n  master:\n    root: {}\n    fileserver.models: [cm, net]\n    api.bind.ip: 127.0.0.1\n    api.bind.port: 4202\n    api.devmode: {}\n    api.doc: {}\n    datastore.namespaces:\n      web:\n        read:\n          traits: \"rack:r12\"\n      ops:\n        read:\n          traits: \"rack:r99\"\n",
+            root.display(),
             if devmode { "true" } else { "false" },
             if doc_enabled { "true" } else { "false" }
         ),
@@ -95,11 +206,11 @@ async fn spawn_https_server(
     let cfg = write_cfg(root.path(), devmode, doc_enabled);
     let queries = Arc::new(Mutex::new(Vec::new()));
     let datastore = Arc::new(Mutex::new(DataStorage::new(DataStorageConfig::new(), root.path().join("datastore")).unwrap()));
-    let master: MasterInterfaceType = Arc::new(Mutex::new(TestMaster { cfg, queries: Arc::clone(&queries), datastore }));
+    let master: MasterInterfaceType = Arc::new(Mutex::new(TestMaster { cfg, queries: Arc::clone(&queries), datastore, _root: root }));
 
     let server = HttpServer::new(move || {
         let scope = api::get(devmode, doc_enabled, ApiVersions::V1).unwrap().load(web::scope(""));
-        App::new().app_data(web::Data::new(master.clone())).service(scope)
+        App::new().app_data(web::Data::new(master.clone())).wrap(from_fn(audit_middleware)).service(scope)
     })
     .workers(1)
     .bind_rustls_0_23(("127.0.0.1", 0), tls_config(require_client_auth))
@@ -193,6 +304,7 @@ async fn https_auth_and_query_use_plain_json_and_bearer_token() {
         .unwrap();
 
     assert_eq!(query["status"], "success");
+    assert_eq!(query["cycle_id"], "cycle-1");
     assert_eq!(queries.lock().await.as_slice(), ["cm/file-ops;*;;;reason:test"]);
     handle.abort();
 }
@@ -249,6 +361,359 @@ async fn https_model_names_returns_plain_json_list() {
     handle.abort();
 }
 
+async fn dev_token(client: &reqwest::Client, base: &str) -> String {
+    let auth = client
+        .post(format!("{base}/api/v1/authenticate"))
+        .json(&serde_json::json!({"username":"dev","password":"dev"}))
+        .send()
+        .await
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    auth["access_token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn https_cycles_are_listed_newest_first_with_pagination() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let token = dev_token(&client, &base).await;
+
+    let response = client
+        .get(format!("{base}/api/v1/cycles?limit=2"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+
+    assert_eq!(response["total"], 3);
+    assert_eq!(response["limit"], 2);
+    assert_eq!(response["cycles"][0]["cycle_id"], "cycle-3");
+    assert_eq!(response["cycles"].as_array().unwrap().len(), 2);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_cycle_events_are_filtered_by_outcome_and_entity() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let token = dev_token(&client, &base).await;
+
+    let minions = client
+        .get(format!("{base}/api/v1/cycles/cycle-1/minions?outcome=error"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(minions["minions"][0]["minion_id"], "m1");
+    assert_eq!(minions["minions"][0]["errors"], 1);
+
+    let events = client
+        .get(format!("{base}/api/v1/cycles/cycle-1/minions/m1/events?outcome=error&entity=file"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(events["total"], 1);
+    assert_eq!(events["events"][0]["entity_id"], "file");
+    assert_eq!(events["events"][0]["outcome"], "error");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_unknown_cycle_returns_not_found() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let token = dev_token(&client, &base).await;
+
+    let response = client.get(format!("{base}/api/v1/cycles/nope/minions")).bearer_auth(&token).send().await.unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_event_stream_pushes_filtered_events() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let token = dev_token(&client, &base).await;
+
+    let mut response =
+        client.get(format!("{base}/api/v1/events/stream?cycle_id=sse-cycle")).bearer_auth(&token).send().await.unwrap().error_for_status().unwrap();
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    _ = STREAM.send(StreamEvent::new(StreamEventKind::MinionOnline, String::new()).minion("m1"));
+    _ = STREAM.send(StreamEvent::new(StreamEventKind::CycleComplete, String::new()).cycle("sse-cycle").minion("m1"));
+
+    let chunk = String::from_utf8(response.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+    assert!(chunk.starts_with("event: cycle_complete\n"), "{chunk}");
+    assert!(chunk.contains(r#""cycle_id":"sse-cycle""#));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_api_tokens_are_scoped_and_revocable() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let session = dev_token(&client, &base).await;
+
+    let created = client
+        .post(format!("{base}/api/v1/tokens"))
+        .bearer_auth(&session)
+        .json(&serde_json::json!({"name": "ci", "scopes": ["read"], "ttl": 3600}))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    let token = created["token"].as_str().unwrap().to_string();
+    assert!(token.starts_with("sit_"));
+    assert_eq!(created["info"]["owner"], "user dev");
+    assert!(created["info"]["expires"].is_string());
+
+    let listed = client.get(format!("{base}/api/v1/tokens")).bearer_auth(&session).send().await.unwrap().json::<serde_json::Value>().await.unwrap();
+    assert_eq!(listed["tokens"][0]["name"], "ci");
+    assert!(listed["tokens"][0].get("hash").is_none());
+
+    let cycles = client.get(format!("{base}/api/v1/cycles")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(cycles.status(), reqwest::StatusCode::OK);
+
+    let query = client
+        .post(format!("{base}/api/v1/query"))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"model": "cm/file-ops", "query": "*", "traits": "", "mid": "", "context": {}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(query.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let minted = client.post(format!("{base}/api/v1/tokens")).bearer_auth(&token).json(&serde_json::json!({"name": "ci2"})).send().await.unwrap();
+    assert_eq!(minted.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let revoked = client.delete(format!("{base}/api/v1/tokens/ci")).bearer_auth(&session).send().await.unwrap();
+    assert_eq!(revoked.status(), reqwest::StatusCode::OK);
+    let cycles = client.get(format!("{base}/api/v1/cycles")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(cycles.status(), reqwest::StatusCode::UNAUTHORIZED);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_store_namespaces_filter_by_tags_and_minion_traits() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let session = dev_token(&client, &base).await;
+
+    let upload = |ns: &str, fname: &str, tags: &str, body: &'static str| {
+        client
+            .post(format!("{base}/store"))
+            .bearer_auth(&session)
+            .header("X-Filename", fname)
+            .header("X-Namespace", ns)
+            .header("X-Tags", tags)
+            .body(body)
+            .send()
+    };
+    let web = upload("web", "/etc/motd", "team=web,env=prod", "hello").await.unwrap().json::<serde_json::Value>().await.unwrap();
+    assert_eq!(web["namespace"], "web");
+    assert_eq!(web["tags"]["team"], "web");
+    let ops = upload("ops", "/etc/motd", "team=ops", "secret").await.unwrap().json::<serde_json::Value>().await.unwrap();
+    assert_eq!(upload("../etc", "/etc/motd", "", "x").await.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(upload("web", "/etc/motd", "team", "x").await.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let list = |token: String, query: &'static str| {
+        let client = client.clone();
+        let url = format!("{base}/store/list?{query}");
+        async move { client.get(url).bearer_auth(token).send().await.unwrap().json::<Vec<serde_json::Value>>().await.unwrap() }
+    };
+    assert_eq!(list(session.clone(), "namespace=web&tags=env=prod").await.len(), 1);
+    assert!(list(session.clone(), "namespace=web&tags=team=ops").await.is_empty());
+    assert!(list(session.clone(), "prefix=/etc").await.is_empty());
+
+    let minion = libwebapi::sessions::get_session_store().lock().await.open("minion:m1".to_string()).unwrap();
+    assert_eq!(list(minion.clone(), "namespace=web").await.len(), 1);
+    assert!(list(minion.clone(), "namespace=ops").await.is_empty());
+
+    let ops_sha = ops["sha256"].as_str().unwrap();
+    let meta = client.get(format!("{base}/store/{ops_sha}?namespace=ops")).bearer_auth(&minion).send().await.unwrap();
+    assert_eq!(meta.status(), reqwest::StatusCode::NOT_FOUND);
+    let blob = client.get(format!("{base}/store/{ops_sha}/blob")).bearer_auth(&minion).send().await.unwrap();
+    assert_eq!(blob.status(), reqwest::StatusCode::NOT_FOUND);
+    let blob = client.get(format!("{base}/store/{ops_sha}/blob")).bearer_auth(&session).send().await.unwrap();
+    assert_eq!(blob.text().await.unwrap(), "secret");
+    let resolved = client.get(format!("{base}/store/resolve?fname=/etc/motd&namespace=web")).bearer_auth(&minion).send().await.unwrap();
+    assert_eq!(resolved.json::<serde_json::Value>().await.unwrap()["sha256"], web["sha256"]);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_store_chunked_upload_resumes_and_blob_serves_ranges() {
+    use sha2::{Digest, Sha256};
+    let sha = |b: &[u8]| format!("{:x}", Sha256::digest(b));
+
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let session = dev_token(&client, &base).await;
+    let data = (0..5000u32).map(|n| (n % 251) as u8).collect::<Vec<_>>();
+
+    let started = client
+        .post(format!("{base}/store/uploads"))
+        .bearer_auth(&session)
+        .json(&serde_json::json!({"size_bytes": data.len(), "sha256": sha(&data), "fname": "/iso/big.img"}))
+        .send()
+        .await
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    let upload = format!("{base}/store/uploads/{}", started["upload_id"].as_str().unwrap());
+    assert_eq!(started["received_bytes"], 0);
+
+    let put = |offset: usize, chunk: &[u8], checksum: String| {
+        client.put(&upload).bearer_auth(&session).query(&[("offset", offset)]).header("X-Chunk-Sha256", checksum).body(chunk.to_vec()).send()
+    };
+    assert_eq!(put(0, &data[..2000], sha(&data[..2000])).await.unwrap().status(), reqwest::StatusCode::OK);
+    assert_eq!(put(0, &data[..2000], sha(&data[..2000])).await.unwrap().status(), reqwest::StatusCode::CONFLICT);
+    assert_eq!(put(2000, &data[2000..], sha(b"corrupted")).await.unwrap().status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    let state = client.get(&upload).bearer_auth(&session).send().await.unwrap().json::<serde_json::Value>().await.unwrap();
+    assert_eq!(state["received_bytes"], 2000);
+    assert_eq!(put(2000, &data[2000..], sha(&data[2000..])).await.unwrap().status(), reqwest::StatusCode::OK);
+
+    let meta = client.post(format!("{upload}/complete")).bearer_auth(&session).send().await.unwrap().json::<serde_json::Value>().await.unwrap();
+    assert_eq!(meta["sha256"], sha(&data));
+    assert_eq!(client.get(&upload).bearer_auth(&session).send().await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+
+    let blob = client.get(format!("{base}/store/{}/blob", sha(&data))).bearer_auth(&session).header("Range", "bytes=4000-").send().await.unwrap();
+    assert_eq!(blob.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(blob.bytes().await.unwrap().as_ref(), &data[4000..]);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_fleet_endpoints_run_cluster_commands() {
+    let (base, queries, handle) = spawn_https_server(true, true, false).await;
+    let client = trusted_client();
+    let token = dev_token(&client, &base).await;
+
+    let minions = client
+        .get(format!("{base}/api/v1/minions?query=m*"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(minions["minions"][0]["minion_id"], "m1");
+    assert_eq!(minions["minions"][0]["alive"], true);
+
+    let updated = client
+        .put(format!("{base}/api/v1/traits"))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({"mid": "m1", "traits": {"rack": "r1"}}))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(updated["target"], "cmd://cluster/traits/update");
+
+    let removed = client.delete(format!("{base}/api/v1/minions/unknown")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(removed.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(removed.json::<serde_json::Value>().await.unwrap()["error"], "Unable to find minion unknown");
+
+    let unauthorised = client.get(format!("{base}/api/v1/minions")).send().await.unwrap();
+    assert_eq!(unauthorised.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // Not a change, so not recorded although it is a POST
+    let health = client.post(format!("{base}/api/v1/health")).send().await.unwrap();
+    assert!(health.status().is_success());
+
+    assert_eq!(
+        queries.lock().await.as_slice(),
+        [
+            "cmd://cluster/minion/online;m*;;",
+            r#"cmd://cluster/traits/update;*;m1;{"op":"set","traits":{"rack":"r1"}}"#,
+            "cmd://cluster/minion/remove;*;unknown;",
+        ]
+    );
+
+    let audit = client
+        .get(format!("{base}/api/v1/audit?limit=3"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    let recorded = audit["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            format!(
+                "{} {} {} {}",
+                r["actor"].as_str().unwrap(),
+                r["action"].as_str().unwrap(),
+                r["target"].as_str().unwrap(),
+                r["outcome"].as_str().unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        recorded,
+        ["dev PUT /api/v1/traits id:m1 success", "dev DELETE /api/v1/minions/unknown id:unknown failure", "anonymous GET /api/v1/minions  denied",]
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_event_stream_rejects_missing_bearer_token() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+
+    let response = trusted_client().get(format!("{base}/api/v1/events/stream")).send().await.unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn https_cycles_reject_missing_bearer_token() {
+    let (base, _, handle) = spawn_https_server(true, true, false).await;
+
+    let response = trusted_client().get(format!("{base}/api/v1/cycles")).send().await.unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    handle.abort();
+}
+
 #[tokio::test]
 async fn https_model_names_rejects_missing_bearer_token_with_json_error() {
     let (base, _, handle) = spawn_https_server(true, true, false).await;
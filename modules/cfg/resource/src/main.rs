@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     fs,
-    io::{self, Read, Write},
+    io::{self, Read},
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     time::Duration,
@@ -9,6 +9,7 @@ use std::{
 
 use base64::{Engine, engine::general_purpose::STANDARD};
 use clap::Parser;
+use libdatastore::client::{DownloadOptions, atomic_download_with};
 use libmodcore::{
     init_mod_doc,
     modcli::ModuleCli,
@@ -61,7 +62,9 @@ struct Ctx {
     f: Option<String>,
     d: Option<String>,
     m: Option<String>,
+    ns: Option<String>,
     force: bool,
+    insecure: bool,
 }
 
 struct MinionAuth {
@@ -201,9 +204,12 @@ fn bootstrap_datastore_token(client: &reqwest::blocking::Client, auth: &MinionAu
     Ok(auth.access_token)
 }
 
-fn resolve_meta(client: &reqwest::blocking::Client, token: &str, base: &str, src: &str) -> Result<Option<StoreMetaResponse>, String> {
-    let url = format!("{base}/store/resolve");
-    let req = bearer_request(client, token, reqwest::Method::GET, &url).query(&[("fname", src)]);
+fn resolve_meta(c: &Ctx) -> Result<Option<StoreMetaResponse>, String> {
+    let url = format!("{}/store/resolve", c.b);
+    let mut req = bearer_request(&c.cl, &c.t, reqwest::Method::GET, &url).query(&[("fname", &c.s)]);
+    if let Some(ns) = &c.ns {
+        req = req.query(&[("namespace", ns)]);
+    }
 
     let rsp = req.send().map_err(|e| format!("resolve request failed: {e}"))?;
     if rsp.status() == StatusCode::NOT_FOUND {
@@ -216,30 +222,24 @@ fn resolve_meta(client: &reqwest::blocking::Client, token: &str, base: &str, src
     rsp.json::<StoreMetaResponse>().map(Some).map_err(|e| format!("unable to parse resolve metadata: {e}"))
 }
 
-fn download_atomic(client: &reqwest::blocking::Client, token: &str, url: &str, dst: &Path) -> Result<(), String> {
-    if let Some(parent) = dst.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("failed to create destination parent '{}': {e}", parent.display()))?;
-    }
-
-    let req = bearer_request(client, token, reqwest::Method::GET, url);
-    let mut rsp = req.send().map_err(|e| format!("download request failed: {e}"))?;
-    if !rsp.status().is_success() {
-        return Err(format!("download request failed: HTTP {}", rsp.status()));
-    }
-
-    let tmp = dst.with_extension("tmp");
-    let mut f = fs::File::create(&tmp).map_err(|e| format!("failed to create temp file '{}': {e}", tmp.display()))?;
-    io::copy(&mut rsp, &mut f).map_err(|e| format!("failed to write temp file '{}': {e}", tmp.display()))?;
-    f.flush().map_err(|e| format!("failed to flush temp file '{}': {e}", tmp.display()))?;
-    drop(f);
-
-    fs::rename(&tmp, dst).map_err(|e| format!("failed to move temp file '{}' to '{}': {e}", tmp.display(), dst.display()))?;
-    Ok(())
+/// Download the blob through the resumable datastore client, which verifies its checksum before `dst` is replaced.
+fn download_atomic(c: &Ctx, url: &str, dst: &Path, sha256: &str) -> Result<(), String> {
+    let opts = DownloadOptions {
+        token: Some(c.t.clone()),
+        accept_invalid_certs: c.insecure,
+        connect_timeout: Some(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS)),
+        read_timeout: Some(Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS)),
+    };
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| format!("Unable to start download runtime: {e}"))?;
+    rt.block_on(atomic_download_with(url, dst, Some(sha256), &opts)).map_err(|e| format!("download of '{}' failed: {e}", dst.display()))
 }
 
-fn list_meta(client: &reqwest::blocking::Client, token: &str, base: &str, prefix: &str) -> Result<Vec<StoreListEntry>, String> {
-    let url = format!("{base}/store/list");
-    let req = bearer_request(client, token, reqwest::Method::GET, &url).query(&[("prefix", prefix)]);
+fn list_meta(c: &Ctx) -> Result<Vec<StoreListEntry>, String> {
+    let url = format!("{}/store/list", c.b);
+    let mut req = bearer_request(&c.cl, &c.t, reqwest::Method::GET, &url).query(&[("prefix", &c.s)]);
+    if let Some(ns) = &c.ns {
+        req = req.query(&[("namespace", ns)]);
+    }
     let rsp = req.send().map_err(|e| format!("list request failed: {e}"))?;
     if !rsp.status().is_success() {
         return Err(format!("list request failed: HTTP {}", rsp.status()));
@@ -257,10 +257,11 @@ fn mk_ctx(rq: &ModRequest) -> Result<Ctx, String> {
     if s.is_empty() {
         return Err("Argument \"src\" is required".to_string());
     }
+    let insecure = arg_bool(rq, "tls-accept-insecure").unwrap_or(false);
     let cl = reqwest::blocking::Client::builder()
         .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
         .timeout(Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
-        .danger_accept_invalid_certs(arg_bool(rq, "tls-accept-insecure").unwrap_or(false))
+        .danger_accept_invalid_certs(insecure)
         .build()
         .map_err(|e| format!("Unable to initialize HTTP client: {e}"))?;
 
@@ -268,7 +269,18 @@ fn mk_ctx(rq: &ModRequest) -> Result<Ctx, String> {
     let base = api_base(rq);
     let token = bootstrap_datastore_token(&cl, &auth, &base)?;
 
-    Ok(Ctx { cl, b: base, t: token, s, f: arg_str(rq, "file"), d: arg_str(rq, "dst"), m: arg_str(rq, "mode"), force: rq.has_option("force") })
+    Ok(Ctx {
+        cl,
+        b: base,
+        t: token,
+        s,
+        f: arg_str(rq, "file"),
+        d: arg_str(rq, "dst"),
+        m: arg_str(rq, "mode"),
+        ns: arg_str(rq, "namespace"),
+        force: rq.has_option("force"),
+        insecure,
+    })
 }
 
 fn local_p(c: &Ctx) -> PathBuf {
@@ -284,7 +296,7 @@ fn do_push(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
 
     let hs = file_sha256(&p).map_err(|e| format!("Unable to checksum local file '{}': {e}", p.display()))?;
     if !c.force {
-        match resolve_meta(&c.cl, &c.t, &c.b, &c.s)? {
+        match resolve_meta(c)? {
             Some(m) if m.sha256 == hs => {
                 d.insert("src".to_string(), json!(c.s));
                 d.insert("sha256".to_string(), json!(m.sha256));
@@ -297,11 +309,14 @@ fn do_push(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
 
     let b = fs::read(&p).map_err(|e| format!("Unable to read local file '{}': {e}", p.display()))?;
     let body_sha256 = bytes_sha256_hex(&b);
-    let req = bearer_request(&c.cl, &c.t, reqwest::Method::POST, &format!("{}/store", c.b))
+    let mut req = bearer_request(&c.cl, &c.t, reqwest::Method::POST, &format!("{}/store", c.b))
         .header("Content-Type", "application/octet-stream")
         .header("X-Filename", c.s.clone())
         .header("X-Sysinspect-Body-Sha256", body_sha256)
         .body(b);
+    if let Some(ns) = &c.ns {
+        req = req.header("X-Namespace", ns.clone());
+    }
     let rsp = req.send().map_err(|e| format!("Push request failed: {e}"))?;
     if !rsp.status().is_success() {
         let st = rsp.status();
@@ -322,7 +337,7 @@ fn do_push(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
 fn do_pull(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
     let mut d = JsonMap::new();
     let p = local_p(c);
-    let m = match resolve_meta(&c.cl, &c.t, &c.b, &c.s)? {
+    let m = match resolve_meta(c)? {
         Some(m) => m,
         None => return Err(format!("Resource '{}' was not found in datastore", c.s)),
     };
@@ -341,7 +356,7 @@ fn do_pull(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
     }
 
     let url = format!("{}/store/{}/blob", c.b, m.sha256);
-    download_atomic(&c.cl, &c.t, &url, &p)?;
+    download_atomic(c, &url, &p, &m.sha256)?;
     let md = c.m.as_deref().and_then(parse_mode).unwrap_or(m.fmode & 0o7777);
     fs::set_permissions(&p, fs::Permissions::from_mode(md))
         .map_err(|e| format!("Downloaded resource but failed to set mode on '{}': {e}", p.display()))?;
@@ -363,7 +378,7 @@ fn do_sync_dir(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
         c.f.clone().or_else(|| c.d.clone()).map(PathBuf::from).ok_or_else(|| "Argument \"file\" or \"dst\" is required for sync-dir".to_string())?;
     fs::create_dir_all(&dst_dir).map_err(|e| format!("Unable to create destination directory '{}': {e}", dst_dir.display()))?;
 
-    let metas = list_meta(&c.cl, &c.t, &c.b, &c.s)?;
+    let metas = list_meta(c)?;
     let mut changed = false;
     let mut synced = 0usize;
     for meta in metas {
@@ -384,7 +399,7 @@ fn do_sync_dir(c: &Ctx) -> Result<(bool, String, JsonMap), String> {
         }
 
         let url = format!("{}/store/{}/blob", c.b, meta.sha256);
-        download_atomic(&c.cl, &c.t, &url, &dst)?;
+        download_atomic(c, &url, &dst, &meta.sha256)?;
         let md = c.m.as_deref().and_then(parse_mode).unwrap_or(meta.fmode & 0o7777);
         fs::set_permissions(&dst, fs::Permissions::from_mode(md))
             .map_err(|e| format!("Downloaded resource but failed to set mode on '{}': {e}", dst.display()))?;
@@ -28,6 +28,8 @@ pub(crate) struct ManagerDef {
     pub(crate) disable: Option<String>,
     #[serde(default)]
     pub(crate) info: Option<String>,
+    #[serde(default)]
+    pub(crate) reboot: Option<String>,
 }
 
 /// Top-level YAML structure: a map of manager IDs to their definitions.
@@ -90,7 +92,8 @@ pub fn run(rt: &ModRequest) -> ModResponse {
     let name = runtime::get_arg(rt, "name");
     let dry_run = runtime::get_opt(rt, "dry-run");
 
-    if name.is_empty() {
+    // Reboot is the only host-wide operation, all others need a service
+    if name.is_empty() && !runtime::get_opt(rt, "reboot") {
         resp.set_retcode(1);
         resp.set_message("Argument \"name\" is required");
         return resp;
@@ -155,7 +158,8 @@ pub fn run(rt: &ModRequest) -> ModResponse {
     match exec_sh(&cmd) {
         Ok((code, stdout, stderr)) => {
             resp.set_retcode(code);
-            resp.set_message(&format!("Service '{}' {} {}", name, op, if code == 0 { "successful" } else { "failed" }));
+            let subject = if name.is_empty() { "Host".to_string() } else { format!("Service '{name}'") };
+            resp.set_message(&format!("{subject} {op} {}", if code == 0 { "successful" } else { "failed" }));
             let mut data = telemetry_base(&name, mgr_id);
             data.insert("exit_code".to_string(), serde_json::Value::Number(serde_json::Number::from(code)));
             if !stdout.is_empty() {
@@ -294,6 +298,7 @@ pub(crate) fn resolve_template<'a>(mgr: &'a ManagerDef, op: &str) -> Option<&'a
         "info" => mgr.info.as_deref().or(Some(mgr.status.as_str())),
         "enable" => mgr.enable.as_deref(),
         "disable" => mgr.disable.as_deref(),
+        "reboot" => mgr.reboot.as_deref(),
         _ => None,
     }
 }
@@ -318,9 +323,13 @@ pub(crate) fn parse_operation(rt: &ModRequest, resp: &mut ModResponse) -> Option
         Some("enable")
     } else if runtime::get_opt(rt, "disable") {
         Some("disable")
+    } else if runtime::get_opt(rt, "reboot") {
+        Some("reboot")
     } else {
         resp.set_retcode(1);
-        resp.set_message("No operation specified. Use --check, --info, --status, --start, --stop, --restart, --reload, --enable, or --disable");
+        resp.set_message(
+            "No operation specified. Use --check, --info, --status, --start, --stop, --restart, --reload, --enable, --disable or --reboot",
+        );
         None
     }
 }
@@ -38,6 +38,7 @@ mod tests {
         assert_eq!(parse_operation(&make_request(&["reload"]), &mut resp), Some("reload"));
         assert_eq!(parse_operation(&make_request(&["enable"]), &mut resp), Some("enable"));
         assert_eq!(parse_operation(&make_request(&["disable"]), &mut resp), Some("disable"));
+        assert_eq!(parse_operation(&make_request(&["reboot"]), &mut resp), Some("reboot"));
     }
 
     #[test]
@@ -88,6 +89,7 @@ mod tests {
             enable: Some("enable {name}".into()),
             disable: None,
             info: None,
+            reboot: Some("reboot".into()),
         }
     }
 
@@ -116,6 +118,18 @@ mod tests {
         assert_eq!(resolve_template(&mgr, "disable"), None);
     }
 
+    #[test]
+    fn resolve_template_reboot() {
+        let mgr = test_manager();
+        assert_eq!(resolve_template(&mgr, "reboot"), Some("reboot"));
+    }
+
+    #[test]
+    fn builtin_managers_can_reboot() {
+        let cfg = Config::from_merged(None).unwrap();
+        assert!(cfg.managers.values().all(|m| m.reboot.is_some()));
+    }
+
     #[test]
     fn resolve_template_unknown() {
         let mgr = test_manager();
@@ -37,6 +37,8 @@ pub fn cli(version: &'static str) -> Command {
             .arg(Arg::new("set").long("set").help("Set traits as comma-separated key:value pairs").conflicts_with_all(["unset", "reset"]))
             .arg(Arg::new("unset").long("unset").help("Unset traits as comma-separated keys").conflicts_with_all(["set", "reset"]))
             .arg(Arg::new("reset").long("reset").action(ArgAction::SetTrue).help("Reset all master-managed traits on targeted minions").conflicts_with_all(["set", "unset"]))
+            .arg(Arg::new("history").long("history").action(ArgAction::SetTrue).help("Show when traits of targeted minions changed").conflicts_with_all(["set", "unset", "reset"]))
+            .arg(Arg::new("key").long("key").help("Show the history of this trait only, e.g. system.kernel").requires("history"))
             .arg(Arg::new("id").long("id").help("Target a specific minion by its system id").conflicts_with_all(["query", "query-pos"]))
             .arg(Arg::new("query").long("query").help("Target minions by hostname glob or query").conflicts_with("query-pos"))
             .arg(Arg::new("select-traits").long("traits").help("Target minions by traits query"))
@@ -61,6 +63,34 @@ pub fn cli(version: &'static str) -> Command {
             .arg(Arg::new("query-pos").help("Target minions by hostname glob or query").required(false).index(1))
             .arg(Arg::new("help").short('h').long("help").action(ArgAction::SetTrue).help("Display help for this command"))
         )
+        .subcommand(Command::new("token").about("Manage Web API tokens for automation").styles(styles.clone()).disable_help_flag(true)
+            .arg(Arg::new("create").long("create").action(ArgAction::SetTrue).help("Create a token and print it once").conflicts_with_all(["list", "revoke"]))
+            .arg(Arg::new("list").long("list").action(ArgAction::SetTrue).help("List tokens with their scopes, expiry and last use").conflicts_with_all(["create", "revoke"]))
+            .arg(Arg::new("revoke").long("revoke").action(ArgAction::SetTrue).help("Revoke a token").conflicts_with_all(["create", "list"]))
+            .arg(Arg::new("name").short('n').long("name").help("Token name"))
+            .arg(Arg::new("scopes").long("scopes").help("Comma-separated scopes: read, query, command, datastore-write or all (default: read)").requires("create"))
+            .arg(Arg::new("expires").long("expires").help("Token lifetime, e.g. 90d or 12h (default: never expires)").requires("create"))
+            .arg(Arg::new("help").short('h').long("help").action(ArgAction::SetTrue).help("Display help for this command"))
+        )
+        .subcommand(Command::new("audit").about("Query, export or verify the audit journal of operator actions").styles(styles.clone()).disable_help_flag(true)
+            .arg(Arg::new("since").long("since").help("Only actions at or after this time: RFC 3339 or a duration back from now, e.g. 24h"))
+            .arg(Arg::new("until").long("until").help("Only actions before this time: RFC 3339 or a duration back from now"))
+            .arg(Arg::new("actor").long("actor").help("Only actions of this actor, glob patterns such as token:* are accepted"))
+            .arg(Arg::new("limit").long("limit").value_parser(clap::value_parser!(usize)).help("Only the newest actions"))
+            .arg(Arg::new("export").long("export").help("Write the selected actions as JSONL to the file, '-' for standard output").conflicts_with("verify"))
+            .arg(Arg::new("verify").long("verify").action(ArgAction::SetTrue).help("Verify the hash chain of the whole journal").conflicts_with_all(["since", "until", "actor", "limit"]))
+            .arg(Arg::new("help").short('h').long("help").action(ArgAction::SetTrue).help("Display help for this command"))
+        )
+        .subcommand(Command::new("model").about("Upload, list, compare or roll back model versions").styles(styles.clone()).disable_help_flag(true)
+            .arg(Arg::new("upload").long("upload").value_name("DIR").help("Validate the model directory and activate it as a new version").conflicts_with_all(["versions", "diff", "rollback"]))
+            .arg(Arg::new("versions").long("versions").action(ArgAction::SetTrue).help("List the versions of a model").conflicts_with_all(["upload", "diff", "rollback"]))
+            .arg(Arg::new("diff").long("diff").value_name("FROM[:TO]").help("Compare two versions, TO defaults to the active one").conflicts_with_all(["upload", "versions", "rollback"]))
+            .arg(Arg::new("rollback").long("rollback").action(ArgAction::SetTrue).help("Activate the previous version again").conflicts_with_all(["upload", "versions", "diff"]))
+            .arg(Arg::new("name").short('n').long("name").help("Model name (default for --upload: the directory name)"))
+            .arg(Arg::new("note").long("note").help("Note stored with the uploaded version").requires("upload"))
+            .arg(Arg::new("version").long("version").value_parser(clap::value_parser!(u64)).help("Roll back to this version instead of the previous one").requires("rollback"))
+            .arg(Arg::new("help").short('h').long("help").action(ArgAction::SetTrue).help("Display help for this command"))
+        )
         .subcommand(Command::new("network").about("Manage cluster transport state and rotation").styles(styles.clone()).disable_help_flag(true)
             .arg(Arg::new("add").short('A').long("add").action(ArgAction::SetTrue).help("Plan onboarding for one or more hosts").conflicts_with_all(["remove", "upgrade", "rotate", "status", "info"]))
             .arg(Arg::new("remove").short('R').long("remove").action(ArgAction::SetTrue).help("Remove one or more managed hosts").conflicts_with_all(["add", "upgrade", "rotate", "status", "info"]))
@@ -90,6 +120,16 @@ pub fn cli(version: &'static str) -> Command {
                 "entire cluster".bright_red()
             )).conflicts_with("hopstart"))
             .arg(Arg::new("hopstart").long("hopstart").action(ArgAction::SetTrue).help("Issue SSH-backed startup for selected offline hopstart minions").conflicts_with_all(["online", "shutdown"]))
+            .arg(Arg::new("placement").long("placement").action(ArgAction::SetTrue).help("Show recent placement decisions of virtual minions matching the query").conflicts_with_all(["online", "shutdown", "hopstart"]))
+            .arg(Arg::new("reboot").long("reboot").action(ArgAction::SetTrue).help("Reboot the selected minion hosts in rolling batches").conflicts_with_all(["online", "shutdown", "hopstart", "placement"]))
+            .arg(Arg::new("reboot-status").long("reboot-status").action(ArgAction::SetTrue).help("Show the progress of the current rolling reboot").conflicts_with_all(["online", "shutdown", "hopstart", "placement", "reboot"]))
+            .arg(Arg::new("batch").long("batch").value_parser(clap::value_parser!(usize)).help("Number of minions rebooted at once (default: 1)").requires("reboot"))
+            .arg(Arg::new("timeout").long("timeout").value_parser(clap::value_parser!(u64)).help("Seconds a rebooted minion has to come back (default: 600)").requires("reboot"))
+            .arg(Arg::new("drain").long("drain").value_parser(clap::value_parser!(u64)).help("Seconds a minion has to finish running cycles before reboot (default: 60)").requires("reboot"))
+            .arg(Arg::new("commands").long("commands").action(ArgAction::SetTrue).help("Show commands queued for offline minions and their delivery receipts").conflicts_with_all(["online", "shutdown", "hopstart", "placement", "reboot", "reboot-status"]))
+            .arg(Arg::new("state").long("state").help("Only queued commands in this state (pending, replayed, delivered, executed, failed, expired, superseded)").requires("commands"))
+            .arg(Arg::new("integrity-rebaseline").long("integrity-rebaseline").action(ArgAction::SetTrue).help("Take a new file integrity baseline on the minion selected by --id or name").conflicts_with_all(["online", "shutdown", "hopstart", "placement", "reboot", "reboot-status", "commands"]))
+            .arg(Arg::new("sensor").long("sensor").help("Only rebaseline this sys.integrity sensor (default: all of them)").requires("integrity-rebaseline"))
             .arg(Arg::new("hostnames").short('n').long("hostnames").visible_alias("hn").alias("names").help("Comma-separated hostnames or IPs").conflicts_with("query-pos"))
             .arg(Arg::new("id").long("id").help("Target a specific minion by its system id").conflicts_with_all(["query-pos", "hostnames"]))
             .arg(Arg::new("query-pos").help("Target minions by hostname glob or query").required(false).index(1).default_value("*"))
@@ -175,6 +215,17 @@ pub fn cli(version: &'static str) -> Command {
                 .action(ArgAction::SetTrue)
                 .help(format!("Sync the {} for all artefacts (modules, libraries, traits etc)", "entire cluster".bright_red()))
         )
+        .arg(
+            Arg::new("ttl")
+                .long("ttl")
+                .value_parser(clap::value_parser!(u64))
+                .help("Seconds a command queued for offline minions stays deliverable (default: commands.ttl)")
+        )
+        .arg(
+            Arg::new("supersede")
+                .long("supersede")
+                .help("Replace older queued commands with the same key by this one")
+        )
 
         .next_help_heading("Model")
         .arg(
@@ -1,7 +1,12 @@
 use chrono::{DateTime, Utc};
 use colored::Colorize;
 use libsysinspect::{
-    console::{ConsoleMinionInfoRow, ConsoleOnlineMinionRow, ConsolePayload, ConsoleTransportStatusRow},
+    audit::{AuditOutcome, AuditRecord},
+    console::{
+        ConsoleApiTokenRow, ConsoleMinionInfoRow, ConsoleOnlineMinionRow, ConsolePayload, ConsoleQueuedCommandRow, ConsoleTraitChangeRow,
+        ConsoleTransportStatusRow,
+    },
+    mdescr::versions::{ModelDiff, ModelVersions},
     traits::TraitSource,
     transport::TransportRotationStatus,
     util::pad_visible,
@@ -371,6 +376,276 @@ fn render_transport_status(rows: &[ConsoleTransportStatusRow]) -> String {
     out.join("\n")
 }
 
+/// Render the `ConsolePayload::QueuedCommands` rows as a CLI table.
+///
+/// Columns are minion id, cycle id, command, state, age since the command was
+/// queued and the execution error reported by the minion, if any.
+fn render_queued_commands(rows: &[ConsoleQueuedCommandRow]) -> String {
+    if rows.is_empty() {
+        return "No queued commands".to_string();
+    }
+
+    let now = Utc::now();
+    let widths = (
+        rows.iter().map(|row| shorten_middle(&row.minion_id, 4).chars().count()).max().unwrap_or(6).max("MINION".chars().count()),
+        rows.iter().map(|row| shorten_middle(&row.cycle_id, 4).chars().count()).max().unwrap_or(5).max("CYCLE".chars().count()),
+        rows.iter().map(|row| row.scheme.chars().count()).max().unwrap_or(7).max("COMMAND".chars().count()),
+        rows.iter().map(|row| row.state.chars().count()).max().unwrap_or(5).max("STATE".chars().count()),
+        rows.iter().map(|row| relative_label(Some(row.enqueued_at), now).chars().count()).max().unwrap_or(3).max("AGE".chars().count()),
+    );
+
+    let mut out = vec![
+        format!(
+            "{}  {}  {}  {}  {}  {}",
+            pad_visible(&"MINION".bright_yellow().to_string(), widths.0),
+            pad_visible(&"CYCLE".bright_yellow().to_string(), widths.1),
+            pad_visible(&"COMMAND".bright_yellow().to_string(), widths.2),
+            pad_visible(&"STATE".bright_yellow().to_string(), widths.3),
+            pad_visible(&"AGE".bright_yellow().to_string(), widths.4),
+            "ERROR".bright_yellow(),
+        ),
+        format!(
+            "{}  {}  {}  {}  {}  {}",
+            "─".repeat(widths.0),
+            "─".repeat(widths.1),
+            "─".repeat(widths.2),
+            "─".repeat(widths.3),
+            "─".repeat(widths.4),
+            "─".repeat("ERROR".len()),
+        ),
+    ];
+
+    for row in rows {
+        let state = match row.state.as_str() {
+            "executed" | "delivered" => row.state.bright_green().to_string(),
+            "failed" | "expired" => row.state.bright_red().to_string(),
+            "superseded" => row.state.dimmed().to_string(),
+            _ => row.state.yellow().to_string(),
+        };
+        out.push(format!(
+            "{}  {}  {}  {}  {}  {}",
+            pad_visible(&shorten_middle(&row.minion_id, 4).bright_green().to_string(), widths.0),
+            pad_visible(&shorten_middle(&row.cycle_id, 4), widths.1),
+            pad_visible(&row.scheme, widths.2),
+            pad_visible(&state, widths.3),
+            pad_visible(&relative_label(Some(row.enqueued_at), now), widths.4),
+            row.error.as_deref().unwrap_or("-"),
+        ));
+    }
+
+    out.join("\n")
+}
+
+/// Render the `ConsolePayload::TraitHistory` rows as a CLI table, oldest change first.
+fn render_trait_history(rows: &[ConsoleTraitChangeRow]) -> String {
+    if rows.is_empty() {
+        return "No trait changes recorded".to_string();
+    }
+
+    let value = |v: &Option<Value>| match v {
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => "-".to_string(),
+    };
+    let widths = (
+        "YYYY-MM-DD HH:MM:SS".len(),
+        rows.iter().map(|row| row.host.chars().count()).max().unwrap_or(4).max("HOST".chars().count()),
+        rows.iter().map(|row| row.key.chars().count()).max().unwrap_or(5).max("TRAIT".chars().count()),
+        rows.iter().map(|row| value(&row.old).chars().count()).max().unwrap_or(3).max("OLD".chars().count()),
+    );
+
+    let mut out = vec![
+        format!(
+            "{}  {}  {}  {}  {}",
+            pad_visible(&"CHANGED".bright_yellow().to_string(), widths.0),
+            pad_visible(&"HOST".bright_yellow().to_string(), widths.1),
+            pad_visible(&"TRAIT".bright_yellow().to_string(), widths.2),
+            pad_visible(&"OLD".bright_yellow().to_string(), widths.3),
+            "NEW".bright_yellow(),
+        ),
+        format!("{}  {}  {}  {}  {}", "─".repeat(widths.0), "─".repeat(widths.1), "─".repeat(widths.2), "─".repeat(widths.3), "─".repeat(3)),
+    ];
+
+    for row in rows {
+        out.push(format!(
+            "{}  {}  {}  {}  {}",
+            pad_visible(&row.changed_at.format("%Y-%m-%d %H:%M:%S").to_string(), widths.0),
+            pad_visible(&row.host.bright_green().to_string(), widths.1),
+            pad_visible(&row.key.yellow().to_string(), widths.2),
+            pad_visible(&value(&row.old), widths.3),
+            value(&row.new).bright_yellow(),
+        ));
+    }
+
+    out.join("\n")
+}
+
+/// Render the `ConsolePayload::ApiTokens` rows as a CLI table.
+///
+/// Expired tokens are kept in the listing until they are revoked, their
+/// expiry is shown in red.
+fn render_api_tokens(rows: &[ConsoleApiTokenRow]) -> String {
+    if rows.is_empty() {
+        return "No API tokens".to_string();
+    }
+
+    let now = Utc::now();
+    let expires = |row: &ConsoleApiTokenRow| row.expires.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "never".to_string());
+    let widths = (
+        rows.iter().map(|row| row.name.chars().count()).max().unwrap_or(4).max("NAME".chars().count()),
+        rows.iter().map(|row| row.owner.chars().count()).max().unwrap_or(5).max("OWNER".chars().count()),
+        rows.iter().map(|row| row.scopes.join(",").chars().count()).max().unwrap_or(6).max("SCOPES".chars().count()),
+        rows.iter().map(|row| expires(row).chars().count()).max().unwrap_or(7).max("EXPIRES".chars().count()),
+    );
+
+    let mut out = vec![
+        format!(
+            "{}  {}  {}  {}  {}",
+            pad_visible(&"NAME".bright_yellow().to_string(), widths.0),
+            pad_visible(&"OWNER".bright_yellow().to_string(), widths.1),
+            pad_visible(&"SCOPES".bright_yellow().to_string(), widths.2),
+            pad_visible(&"EXPIRES".bright_yellow().to_string(), widths.3),
+            "LAST USED".bright_yellow(),
+        ),
+        format!("{}  {}  {}  {}  {}", "─".repeat(widths.0), "─".repeat(widths.1), "─".repeat(widths.2), "─".repeat(widths.3), "─".repeat(9)),
+    ];
+
+    for row in rows {
+        let expiry = if row.expires.is_some_and(|t| t <= now) { expires(row).bright_red().to_string() } else { expires(row) };
+        out.push(format!(
+            "{}  {}  {}  {}  {}",
+            pad_visible(&row.name.bright_green().to_string(), widths.0),
+            pad_visible(&row.owner, widths.1),
+            pad_visible(&row.scopes.join(","), widths.2),
+            pad_visible(&expiry, widths.3),
+            relative_label(row.last_used, now),
+        ));
+    }
+
+    out.join("\n")
+}
+
+/// Render the `ConsolePayload::AuditRecords` rows as a CLI table, oldest action first.
+///
+/// Denied actions are shown in red, failed ones in yellow. The details and the
+/// hashes are left to the JSONL export.
+fn render_audit_records(rows: &[AuditRecord]) -> String {
+    if rows.is_empty() {
+        return "No audit records".to_string();
+    }
+
+    let widths = (
+        "YYYY-MM-DD HH:MM:SS".len(),
+        rows.iter().map(|row| row.actor.chars().count()).max().unwrap_or(5).max("ACTOR".chars().count()),
+        rows.iter().map(|row| row.channel.to_string().chars().count()).max().unwrap_or(7).max("CHANNEL".chars().count()),
+        rows.iter().map(|row| row.action.chars().count()).max().unwrap_or(6).max("ACTION".chars().count()),
+        rows.iter().map(|row| row.target.chars().count()).max().unwrap_or(6).max("TARGET".chars().count()),
+    );
+
+    let mut out = vec![
+        format!(
+            "{}  {}  {}  {}  {}  {}",
+            pad_visible(&"TIME".bright_yellow().to_string(), widths.0),
+            pad_visible(&"ACTOR".bright_yellow().to_string(), widths.1),
+            pad_visible(&"CHANNEL".bright_yellow().to_string(), widths.2),
+            pad_visible(&"ACTION".bright_yellow().to_string(), widths.3),
+            pad_visible(&"TARGET".bright_yellow().to_string(), widths.4),
+            "OUTCOME".bright_yellow(),
+        ),
+        format!(
+            "{}  {}  {}  {}  {}  {}",
+            "─".repeat(widths.0),
+            "─".repeat(widths.1),
+            "─".repeat(widths.2),
+            "─".repeat(widths.3),
+            "─".repeat(widths.4),
+            "─".repeat(7)
+        ),
+    ];
+
+    for row in rows {
+        let outcome = match row.outcome {
+            AuditOutcome::Success => row.outcome.to_string().bright_green(),
+            AuditOutcome::Denied => row.outcome.to_string().bright_red(),
+            AuditOutcome::Failure => row.outcome.to_string().yellow(),
+        };
+        out.push(format!(
+            "{}  {}  {}  {}  {}  {}",
+            pad_visible(&row.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(), widths.0),
+            pad_visible(&row.actor.bright_green().to_string(), widths.1),
+            pad_visible(&row.channel.to_string(), widths.2),
+            pad_visible(&row.action, widths.3),
+            pad_visible(&row.target, widths.4),
+            outcome,
+        ));
+    }
+
+    out.join("\n")
+}
+
+/// Render the `ConsolePayload::ModelVersions` list as a CLI table, oldest version first.
+///
+/// The active version is marked with an asterisk. When no version is marked, the
+/// model directory was edited by hand after the last upload or rollback.
+fn render_model_versions(versions: &ModelVersions) -> String {
+    if versions.versions.is_empty() {
+        return format!("Model {} has no uploaded versions", versions.model.bright_green());
+    }
+
+    let widths = (
+        versions.versions.iter().map(|v| v.version.to_string().chars().count() + 2).max().unwrap_or(7).max("VERSION".chars().count()),
+        versions.versions.iter().map(|v| v.author.chars().count()).max().unwrap_or(6).max("AUTHOR".chars().count()),
+    );
+
+    let mut out = vec![
+        format!(
+            "{}  {}  {}  {}  {}  {}",
+            pad_visible(&"VERSION".bright_yellow().to_string(), widths.0),
+            pad_visible(&"HASH".bright_yellow().to_string(), 12),
+            pad_visible(&"CREATED".bright_yellow().to_string(), 16),
+            pad_visible(&"AUTHOR".bright_yellow().to_string(), widths.1),
+            pad_visible(&"FILES".bright_yellow().to_string(), 5),
+            "NOTE".bright_yellow(),
+        ),
+        format!("{}  {}  {}  {}  {}  {}", "─".repeat(widths.0), "─".repeat(12), "─".repeat(16), "─".repeat(widths.1), "─".repeat(5), "─".repeat(4)),
+    ];
+
+    for v in &versions.versions {
+        let version = if versions.active == Some(v.version) { format!("{} *", v.version).bright_green().to_string() } else { v.version.to_string() };
+        out.push(format!(
+            "{}  {}  {}  {}  {:>5}  {}",
+            pad_visible(&version, widths.0),
+            pad_visible(&v.hash.chars().take(12).collect::<String>(), 12),
+            pad_visible(&v.created.format("%Y-%m-%d %H:%M").to_string(), 16),
+            pad_visible(&v.author, widths.1),
+            v.files,
+            v.note.as_deref().unwrap_or_default(),
+        ));
+    }
+
+    out.join("\n")
+}
+
+/// Render the `ConsolePayload::ModelDiff` as a colored unified diff per file.
+fn render_model_diff(diff: &ModelDiff) -> String {
+    if diff.files.is_empty() {
+        return format!("Model {}: versions {} and {} are the same", diff.model.bright_green(), diff.from, diff.to);
+    }
+
+    let mut out = vec![format!("Model {}: version {} → {}", diff.model.bright_green(), diff.from, diff.to)];
+    for file in &diff.files {
+        out.push(format!("{} {}", file.change.to_string().bright_yellow(), file.path.bold()));
+        out.extend(file.lines.iter().map(|line| match line.chars().next() {
+            Some('+') => line.bright_green().to_string(),
+            Some('-') => line.bright_red().to_string(),
+            Some('@') => line.cyan().to_string(),
+            _ => line.clone(),
+        }));
+    }
+
+    out.join("\n")
+}
+
 /// Render a structured console payload into the current stdout-oriented CLI
 /// representation.
 ///
@@ -394,17 +669,43 @@ pub fn render_console_payload(payload: &ConsolePayload) -> String {
             "delete_profile" => format!("Deleted profile {}", target.bright_yellow()),
             "update_profile" => format!("Updated profile {}", target.bright_yellow()),
             "remove_minion" => format!("Unregistered minion {}", target.bright_yellow()),
+            "revoke_api_token" => format!("Revoked API token {}", target.bright_yellow()),
             "apply_profiles" => {
                 format!("Applied profiles {} on {} minion{}", items.join(", ").bright_yellow(), count, if *count == 1 { "" } else { "s" })
             }
             "remove_profiles" => {
                 format!("Removed profiles {} on {} minion{}", items.join(", ").bright_yellow(), count, if *count == 1 { "" } else { "s" })
             }
+            "integrity_rebaseline" => {
+                format!("Rebaselining file integrity on {}: {}", target.bright_yellow(), items.join(", ").bright_yellow())
+            }
             "accepted_console_command" => String::new(),
             _ => action.clone(),
         },
         ConsolePayload::OnlineMinions { rows } => render_online_minions(rows),
         ConsolePayload::TransportStatus { rows } => render_transport_status(rows),
+        ConsolePayload::QueuedCommands { rows } => render_queued_commands(rows),
+        ConsolePayload::TraitHistory { rows } => render_trait_history(rows),
+        ConsolePayload::ApiTokens { rows } => render_api_tokens(rows),
+        ConsolePayload::ApiTokenCreated { row, token } => format!(
+            "Created API token {} with scopes {}\n{}\n{}",
+            row.name.bright_yellow(),
+            row.scopes.join(","),
+            token.bright_green(),
+            "Store the token now, it cannot be shown again.".yellow()
+        ),
+        ConsolePayload::AuditRecords { rows } => render_audit_records(rows),
+        ConsolePayload::AuditVerified { verification } => format!(
+            "Audit journal is intact: {} record{}, head {}",
+            verification.records,
+            if verification.records == 1 { "" } else { "s" },
+            verification.head.bright_yellow()
+        ),
+        ConsolePayload::ModelVersions { versions } => render_model_versions(versions),
+        ConsolePayload::ModelVersionActivated { model, version } => {
+            format!("Model {}: version {} is active", model.bright_green(), version.version.to_string().bright_yellow())
+        }
+        ConsolePayload::ModelDiff { diff } => render_model_diff(diff),
         ConsolePayload::MinionInfo { rows } => render_minion_info(rows),
         ConsolePayload::MinionLogs { snapshot } => {
             let mut out = vec![format!("{} ({})", snapshot.path, snapshot.source_kind)];
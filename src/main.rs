@@ -7,23 +7,26 @@ use libsysinspect::{
         mmconf::{MasterConfig, MinionConfig},
         select_config_path,
     },
-    console::{ConsoleQuery, ConsoleResponse, ConsoleSealed, build_console_query},
+    console::{ConsolePayload, ConsoleQuery, ConsoleResponse, ConsoleSealed, build_console_query},
     context,
     inspector::SysInspectRunner,
     logger::{self, MemoryLogger, STDOUTLogger},
+    mdescr::versions::ModelBundle,
     reactor::handlers,
     traits::get_minion_traits,
 };
 use libsysproto::query::SCHEME_COMMAND;
 use libsysproto::query::commands::{
-    CLUSTER_HOPSTART, CLUSTER_MINION_INFO, CLUSTER_ONLINE_MINIONS, CLUSTER_PROFILE, CLUSTER_REMOVE_MINION, CLUSTER_ROTATE, CLUSTER_SHUTDOWN,
-    CLUSTER_SYNC, CLUSTER_TRAITS_UPDATE, CLUSTER_TRANSPORT_STATUS,
+    CLUSTER_API_TOKENS, CLUSTER_AUDIT, CLUSTER_COMMANDS, CLUSTER_HOPSTART, CLUSTER_MINION_INFO, CLUSTER_MINION_INTEGRITY_REBASELINE,
+    CLUSTER_MODEL_VERSIONS, CLUSTER_ONLINE_MINIONS, CLUSTER_PLACEMENT, CLUSTER_PROFILE, CLUSTER_REBOOT, CLUSTER_REBOOT_STATUS, CLUSTER_REMOVE_MINION,
+    CLUSTER_ROTATE, CLUSTER_SHUTDOWN, CLUSTER_SYNC, CLUSTER_TRAITS_HISTORY, CLUSTER_TRAITS_UPDATE, CLUSTER_TRANSPORT_STATUS,
 };
 use log::LevelFilter;
 use serde_json::json;
 use std::{
     env,
-    io::ErrorKind,
+    fs::File,
+    io::{ErrorKind, Write},
     path::PathBuf,
     process::exit,
     sync::{Mutex, OnceLock},
@@ -64,14 +67,24 @@ fn print_event_handlers() {
 pub(crate) async fn call_master_console(
     cfg: &MasterConfig, model: &str, query: &str, traits: Option<&String>, mid: Option<&str>, context: Option<&String>,
 ) -> Result<ConsoleResponse, SysinspectError> {
-    let request = ConsoleQuery {
-        model: model.to_string(),
-        query: query.to_string(),
-        traits: traits.cloned().unwrap_or_default(),
-        mid: mid.unwrap_or_default().to_string(),
-        context: context.cloned().unwrap_or_default(),
-    };
-    let (envelope, key) = build_console_query(&cfg.root_dir(), cfg, &request)?;
+    send_console_query(
+        cfg,
+        &ConsoleQuery {
+            model: model.to_string(),
+            query: query.to_string(),
+            traits: traits.cloned().unwrap_or_default(),
+            mid: mid.unwrap_or_default().to_string(),
+            context: context.cloned().unwrap_or_default(),
+            ttl: None,
+            supersede: None,
+        },
+    )
+    .await
+}
+
+/// Send a prepared console query to the master and return its opened response.
+pub(crate) async fn send_console_query(cfg: &MasterConfig, request: &ConsoleQuery) -> Result<ConsoleResponse, SysinspectError> {
+    let (envelope, key) = build_console_query(&cfg.root_dir(), cfg, request)?;
     let mut stream = timeout(CONSOLE_CONNECT_TIMEOUT, TcpStream::connect(cfg.console_connect_addr()))
         .await
         .map_err(|_| std::io::Error::new(ErrorKind::TimedOut, "timeout while connecting to master console"))??;
@@ -134,6 +147,110 @@ fn cluster_selector(cluster: &ArgMatches) -> (String, Option<&str>) {
     }
 }
 
+fn api_token_context(am: &ArgMatches) -> Result<String, SysinspectError> {
+    if am.get_flag("list") {
+        return Ok(json!({"op": "list"}).to_string());
+    }
+
+    let name = am.get_one::<String>("name").cloned().unwrap_or_default();
+    if name.trim().is_empty() {
+        return Err(SysinspectError::InvalidQuery("Specify the token name with --name".to_string()));
+    }
+    if am.get_flag("revoke") {
+        return Ok(json!({"op": "revoke", "name": name}).to_string());
+    }
+
+    Ok(json!({
+        "op": "create",
+        "name": name,
+        "scopes": am.get_one::<String>("scopes").cloned().unwrap_or_default(),
+        "expires": am.get_one::<String>("expires"),
+    })
+    .to_string())
+}
+
+fn model_context(am: &ArgMatches) -> Result<String, SysinspectError> {
+    if let Some(dir) = am.get_one::<String>("upload") {
+        let dir = PathBuf::from(dir);
+        let name = match am.get_one::<String>("name") {
+            Some(name) => name.clone(),
+            None => dir
+                .canonicalize()
+                .ok()
+                .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                .ok_or_else(|| SysinspectError::InvalidQuery(format!("Cannot take the model name from {}, use --name", dir.display())))?,
+        };
+        return Ok(json!({
+            "op": "upload",
+            "name": name,
+            "note": am.get_one::<String>("note"),
+            "bundle": ModelBundle::from_dir(&dir)?,
+        })
+        .to_string());
+    }
+
+    let name = am.get_one::<String>("name").cloned().unwrap_or_default();
+    if name.trim().is_empty() {
+        return Err(SysinspectError::InvalidQuery("Specify the model with --name".to_string()));
+    }
+    if let Some(range) = am.get_one::<String>("diff") {
+        let (from, to) = match range.split_once(':') {
+            Some((from, to)) => (from, Some(to)),
+            None => (range.as_str(), None),
+        };
+        let version = |v: &str| v.trim().parse::<u64>().map_err(|_| SysinspectError::InvalidQuery(format!("Invalid model version: {v}")));
+        return Ok(json!({"op": "diff", "name": name, "from": version(from)?, "to": to.map(version).transpose()?}).to_string());
+    }
+    if am.get_flag("rollback") {
+        return Ok(json!({"op": "rollback", "name": name, "version": am.get_one::<u64>("version")}).to_string());
+    }
+
+    Ok(json!({"op": "list", "name": name}).to_string())
+}
+
+fn audit_context(am: &ArgMatches, after: Option<u64>) -> String {
+    json!({
+        "since": am.get_one::<String>("since"),
+        "until": am.get_one::<String>("until"),
+        "actor": am.get_one::<String>("actor"),
+        "limit": am.get_one::<usize>("limit"),
+        "after": after,
+        "verify": am.get_flag("verify"),
+    })
+    .to_string()
+}
+
+/// Write audit records as JSONL, one record per line, to the file or to standard output for `-`.
+/// A console response is capped in size, so the records are fetched in pages, each following the last record written.
+async fn export_audit(cfg: &MasterConfig, am: &ArgMatches, dst: &str) -> Result<usize, SysinspectError> {
+    let mut out: Box<dyn Write> = if dst == "-" { Box::new(std::io::stdout()) } else { Box::new(File::create(dst)?) };
+    let limit = am.get_one::<usize>("limit").copied().unwrap_or(usize::MAX);
+    let (mut after, mut count) = (0, 0);
+    while count < limit {
+        let context = audit_context(am, Some(after));
+        let resp = call_master_console(cfg, &format!("{SCHEME_COMMAND}{CLUSTER_AUDIT}"), "*", None, None, Some(&context)).await?;
+        if !resp.ok {
+            return Err(SysinspectError::MasterGeneralError(resp.error));
+        }
+        let ConsolePayload::AuditRecords { rows } = resp.payload else {
+            return Err(SysinspectError::MasterGeneralError("Master did not return audit records".to_string()));
+        };
+
+        let rows = rows.into_iter().filter(|r| r.seq > after).take(limit - count).collect::<Vec<_>>();
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.seq;
+        for row in &rows {
+            writeln!(out, "{}", serde_json::to_string(row)?)?;
+        }
+        count += rows.len();
+    }
+    out.flush()?;
+
+    Ok(count)
+}
+
 fn profile_update_context(am: &ArgMatches) -> Result<Option<String>, SysinspectError> {
     let invalid_name = |name: &str| {
         let name = name.trim();
@@ -279,6 +396,33 @@ fn help(cli: &mut Command, params: &ArgMatches) -> bool {
         }
         return false;
     }
+    if let Some(sub) = params.subcommand_matches("token")
+        && (sub.get_flag("help") || !(sub.get_flag("create") || sub.get_flag("list") || sub.get_flag("revoke")))
+    {
+        if let Some(s_cli) = cli.find_subcommand_mut("token") {
+            _ = s_cli.print_help();
+            return true;
+        }
+        return false;
+    }
+    if let Some(sub) = params.subcommand_matches("model")
+        && (sub.get_flag("help") || !(sub.contains_id("upload") || sub.get_flag("versions") || sub.contains_id("diff") || sub.get_flag("rollback")))
+    {
+        if let Some(s_cli) = cli.find_subcommand_mut("model") {
+            _ = s_cli.print_help();
+            return true;
+        }
+        return false;
+    }
+    if let Some(sub) = params.subcommand_matches("audit")
+        && sub.get_flag("help")
+    {
+        if let Some(s_cli) = cli.find_subcommand_mut("audit") {
+            _ = s_cli.print_help();
+            return true;
+        }
+        return false;
+    }
     if let Some(sub) = params.subcommand_matches("network")
         && (sub.get_flag("help")
             || !(sub.get_flag("add")
@@ -295,7 +439,10 @@ fn help(cli: &mut Command, params: &ArgMatches) -> bool {
         return false;
     }
     if let Some(sub) = params.subcommand_matches("cluster")
-        && (sub.get_flag("help") || !(sub.get_flag("shutdown") || sub.get_flag("online") || sub.get_flag("hopstart")))
+        && (sub.get_flag("help")
+            || !["shutdown", "online", "hopstart", "placement", "reboot", "reboot-status", "commands", "integrity-rebaseline"]
+                .iter()
+                .any(|flag| sub.get_flag(flag)))
     {
         if let Some(s_cli) = cli.find_subcommand_mut("cluster") {
             _ = s_cli.print_help();
@@ -476,6 +623,20 @@ async fn main() {
         let target_id = sub.get_one::<String>("id").map(String::as_str);
         let target_query = sub.get_one::<String>("query").or_else(|| sub.get_one::<String>("query-pos")).map(String::as_str).unwrap_or("*");
         let target_traits = sub.get_one::<String>("select-traits");
+        if sub.get_flag("history") {
+            let context = json!({ "key": sub.get_one::<String>("key") }).to_string();
+            let scheme = format!("{SCHEME_COMMAND}{CLUSTER_TRAITS_HISTORY}");
+            match call_master_console(&cfg, &scheme, target_query, target_traits, target_id, Some(&context)).await {
+                Ok(response) => {
+                    let rendered = clifmt::render_console_payload(&response.payload);
+                    if !rendered.is_empty() {
+                        println!("{}", rendered);
+                    }
+                }
+                Err(err) => log::error!("Cannot reach master: {err}"),
+            }
+            exit(0);
+        }
         let scheme = format!("{SCHEME_COMMAND}{CLUSTER_TRAITS_UPDATE}");
 
         let context = match traits_update_context(sub) {
@@ -517,6 +678,77 @@ async fn main() {
         exit(0);
     }
 
+    if let Some(sub) = params.subcommand_matches("token") {
+        let context = match api_token_context(sub) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                log::error!("{err}");
+                exit(1);
+            }
+        };
+
+        match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_API_TOKENS}"), "*", None, None, Some(&context)).await {
+            Ok(resp) => {
+                let rendered = clifmt::render_console_payload(&resp.payload);
+                if !rendered.is_empty() {
+                    println!("{}", rendered);
+                }
+            }
+            Err(err) => log::error!("Cannot reach master: {err}"),
+        }
+        exit(0);
+    }
+
+    if let Some(sub) = params.subcommand_matches("model") {
+        let context = match model_context(sub) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                log::error!("{err}");
+                exit(1);
+            }
+        };
+
+        match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_MODEL_VERSIONS}"), "*", None, None, Some(&context)).await {
+            Ok(resp) => {
+                let rendered = clifmt::render_console_payload(&resp.payload);
+                if !rendered.is_empty() {
+                    println!("{}", rendered);
+                }
+            }
+            Err(err) => log::error!("Cannot reach master: {err}"),
+        }
+        exit(0);
+    }
+
+    if let Some(sub) = params.subcommand_matches("audit") {
+        if let Some(dst) = sub.get_one::<String>("export") {
+            match export_audit(&cfg, sub, dst).await {
+                Ok(count) if dst != "-" => log::info!("Exported {count} audit records to {dst}"),
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!("Unable to export audit records: {err}");
+                    exit(1);
+                }
+            }
+            exit(0);
+        }
+
+        let context = audit_context(sub, None);
+        match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_AUDIT}"), "*", None, None, Some(&context)).await {
+            Ok(resp) => {
+                let rendered = clifmt::render_console_payload(&resp.payload);
+                if !rendered.is_empty() {
+                    println!("{}", rendered);
+                }
+            }
+            Err(err) => {
+                log::error!("Cannot reach master: {err}");
+                exit(1);
+            }
+        }
+        exit(0);
+    }
+
     if *params.get_one::<bool>("list-handlers").unwrap_or(&false) {
         print_event_handlers();
         return;
@@ -681,6 +913,80 @@ async fn main() {
             }
             return;
         }
+        if cluster.get_flag("placement") {
+            let (query, _) = cluster_selector(cluster);
+            match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_PLACEMENT}"), &query, None, None, None).await {
+                Ok(response) => {
+                    let rendered = clifmt::render_console_payload(&response.payload);
+                    if !rendered.is_empty() {
+                        println!("{}", rendered);
+                    }
+                }
+                Err(err) => log::error!("Cannot reach master: {err}"),
+            }
+            return;
+        }
+        if cluster.get_flag("reboot") || cluster.get_flag("reboot-status") {
+            let (model, query, direct_id, context) = if cluster.get_flag("reboot") {
+                let (query, direct_id) = cluster_selector(cluster);
+                let context = json!({
+                    "batch": cluster.get_one::<usize>("batch"),
+                    "timeout": cluster.get_one::<u64>("timeout"),
+                    "drain": cluster.get_one::<u64>("drain"),
+                })
+                .to_string();
+                (CLUSTER_REBOOT, query, direct_id, Some(context))
+            } else {
+                (CLUSTER_REBOOT_STATUS, String::new(), None, None)
+            };
+            match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{model}"), &query, None, direct_id, context.as_ref()).await {
+                Ok(response) => {
+                    let rendered = clifmt::render_console_payload(&response.payload);
+                    if !rendered.is_empty() {
+                        println!("{}", rendered);
+                    }
+                }
+                Err(err) => log::error!("Cannot reach master: {err}"),
+            }
+            return;
+        }
+        if cluster.get_flag("commands") {
+            let (query, direct_id) = cluster_selector(cluster);
+            let context = json!({ "state": cluster.get_one::<String>("state") }).to_string();
+            match call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_COMMANDS}"), &query, None, direct_id, Some(&context)).await {
+                Ok(response) => {
+                    let rendered = clifmt::render_console_payload(&response.payload);
+                    if !rendered.is_empty() {
+                        println!("{}", rendered);
+                    }
+                }
+                Err(err) => log::error!("Cannot reach master: {err}"),
+            }
+            return;
+        }
+        if cluster.get_flag("integrity-rebaseline") {
+            let (query, direct_id) = cluster_selector(cluster);
+            let context = json!({ "sensor": cluster.get_one::<String>("sensor") }).to_string();
+            match call_master_console(
+                &cfg,
+                &format!("{SCHEME_COMMAND}{CLUSTER_MINION_INTEGRITY_REBASELINE}"),
+                &query,
+                None,
+                direct_id,
+                Some(&context),
+            )
+            .await
+            {
+                Ok(response) => {
+                    let rendered = clifmt::render_console_payload(&response.payload);
+                    if !rendered.is_empty() {
+                        println!("{}", rendered);
+                    }
+                }
+                Err(err) => log::error!("Cannot reach master: {err}"),
+            }
+            return;
+        }
         if cluster.get_flag("online") {
             let (query, direct_id) = cluster_selector(cluster);
             let by_query = direct_id.is_none() && (query.contains('*') || query.contains(','));
@@ -710,11 +1016,29 @@ async fn main() {
         let query = params.get_one::<String>("query");
         let traits = params.get_one::<String>("traits");
         let context = params.get_one::<String>("context");
-        if let Err(err) = call_master_console(&cfg, model, query.unwrap_or(&"".to_string()), traits, None, context).await {
+        let request = ConsoleQuery {
+            model: model.to_string(),
+            query: query.cloned().unwrap_or_default(),
+            traits: traits.cloned().unwrap_or_default(),
+            mid: String::new(),
+            context: context.cloned().unwrap_or_default(),
+            ttl: params.get_one::<u64>("ttl").copied(),
+            supersede: params.get_one::<String>("supersede").cloned(),
+        };
+        if let Err(err) = send_console_query(&cfg, &request).await {
             log::error!("Cannot reach master: {err}");
         }
     } else if params.get_flag("sync") {
-        if let Err(err) = call_master_console(&cfg, &format!("{SCHEME_COMMAND}{CLUSTER_SYNC}"), "*", None, None, None).await {
+        let request = ConsoleQuery {
+            model: format!("{SCHEME_COMMAND}{CLUSTER_SYNC}"),
+            query: "*".to_string(),
+            traits: String::new(),
+            mid: String::new(),
+            context: String::new(),
+            ttl: params.get_one::<u64>("ttl").copied(),
+            supersede: params.get_one::<String>("supersede").cloned(),
+        };
+        if let Err(err) = send_console_query(&cfg, &request).await {
             log::error!("Cannot reach master: {err}");
         }
     } else if let Some(mid) = params.get_one::<String>("unregister") {
@@ -842,4 +1166,12 @@ mod main_ut {
 
         assert!(!help(&mut cli, &params));
     }
+
+    #[test]
+    fn model_diff_with_name_is_not_treated_as_help() {
+        let mut cli = clidef::cli("test");
+        let params = cli.to_owned().try_get_matches_from(["sysinspect", "model", "--diff", "3:4", "--name", "webserver"]).unwrap();
+
+        assert!(!help(&mut cli, &params));
+    }
 }
@@ -10,7 +10,7 @@ use libsysinspect::{
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     prelude::{Buffer, Rect},
-    style::{Modifier, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarState, StatefulWidget, Table, Widget},
 };
@@ -214,6 +214,8 @@ impl SysInspectUX {
             .max()
             .unwrap_or(2)
             .max(2);
+        let sens_data: Vec<(String, Color)> = filtered.iter().map(|r| Self::_fmt_sensors(r)).collect();
+        let sens_w = sens_data.iter().map(|(s, _)| UnicodeWidthStr::width(s.as_str()) as u16).max().unwrap_or(1).max(1);
         let dist_w = dist_name_data
             .iter()
             .zip(dist_ver_data.iter())
@@ -223,7 +225,7 @@ impl SysInspectUX {
             .max(3);
 
         let col_spacing: u16 = 1;
-        let fixed_w = ip_w + host_w + ver_w + dist_w + 5 * col_spacing + 1;
+        let fixed_w = ip_w + host_w + ver_w + dist_w + sens_w + 6 * col_spacing + 1;
         let ker_avail = inner.width.saturating_sub(fixed_w) as usize;
         let ker_data: Vec<String> = filtered.iter().map(|r| Self::_trunc_ellipsis(&r.kernel, ker_avail)).collect();
 
@@ -232,6 +234,7 @@ impl SysInspectUX {
             Constraint::Length(host_w),
             Constraint::Length(ver_w),
             Constraint::Length(dist_w),
+            Constraint::Length(sens_w),
             Constraint::Length(ker_avail as u16),
             Constraint::Length(1),
         ];
@@ -255,6 +258,7 @@ impl SysInspectUX {
                         Cell::from(host_data[idx].as_str()),
                         ver_cell,
                         Cell::from(Line::from(vec![Span::raw(dist_name_data[idx].as_str()), Span::raw("/"), Span::raw(dist_ver_data[idx].as_str())])),
+                        Cell::from(sens_data[idx].0.as_str()),
                         Cell::from(ker_data[idx].as_str()),
                         Cell::from(""),
                     ])
@@ -278,6 +282,7 @@ impl SysInspectUX {
                             Span::styled("/", Style::default().fg(palette::FG)),
                             Span::styled(dist_ver_data[idx].as_str(), Style::default().fg(palette::PRIMARY)),
                         ])),
+                        Cell::from(sens_data[idx].0.as_str()).style(Style::default().fg(sens_data[idx].1).bg(popup_bg)),
                         Cell::from(ker_data[idx].as_str()).style(Style::default().fg(palette::PROCESSING_GLOW).bg(popup_bg)),
                         Cell::from(""),
                     ])
@@ -372,6 +377,34 @@ impl SysInspectUX {
         }
     }
 
+    /// Summarize sensor health as healthy/total sensors, plus the stale and the crashed ones, colored by the worst state.
+    /// A running sensor with a stale heartbeat is not healthy.
+    fn _fmt_sensors(r: &ConsoleOnlineMinionRow) -> (String, Color) {
+        if r.sensors.is_empty() {
+            return ("-".to_string(), palette::MUTED);
+        }
+
+        let up = r.sensors.iter().filter(|s| s.is_running() && !s.is_stale()).count();
+        let stale = r.sensors.iter().filter(|s| s.is_stale()).count();
+        let crashed = r.sensors.iter().filter(|s| s.state == "crashed").count();
+        let mut out = format!("{up}/{}", r.sensors.len());
+        if stale > 0 {
+            out.push_str(&format!(" ⌛{stale}"));
+        }
+        if crashed > 0 {
+            out.push_str(&format!(" ✗{crashed}"));
+        }
+
+        let color = if stale + crashed > 0 {
+            palette::ERROR
+        } else if up == r.sensors.len() {
+            palette::SUCCESS
+        } else {
+            palette::WARNING
+        };
+        (out, color)
+    }
+
     fn _trunc_ellipsis(s: &str, max: usize) -> String {
         if s.chars().count() <= max { s.to_string() } else { format!("{}…", s.chars().take(max.saturating_sub(1)).collect::<String>()) }
     }
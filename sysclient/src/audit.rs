@@ -0,0 +1,26 @@
+//! Audit journal of operator actions on the master.
+
+use crate::{AuditListResponse, AuditQuery, AuditVerifyResponse, SysClient, endpoints};
+use libcommon::SysinspectError;
+
+impl SysClient {
+    /// Recorded operator actions, oldest first
+    pub async fn audit(&self, q: &AuditQuery) -> Result<AuditListResponse, SysinspectError> {
+        self.call(&endpoints::AUDIT_LIST, &[], "Failed to read audit journal", |r| r.query(q)).await
+    }
+
+    /// Recorded operator actions as JSONL, one record per line, for SIEM ingestion
+    pub async fn audit_export(&self, q: &AuditQuery) -> Result<String, SysinspectError> {
+        let response =
+            self.send(&endpoints::AUDIT_EXPORT, &[], "Failed to export audit journal", |r| r.query(q)).await?;
+        response
+            .text()
+            .await
+            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to export audit journal: {e}")))
+    }
+
+    /// Check that no record of the journal was modified, removed or reordered
+    pub async fn audit_verify(&self) -> Result<AuditVerifyResponse, SysinspectError> {
+        self.call(&endpoints::AUDIT_VERIFY, &[], "Failed to verify audit journal", |r| r).await
+    }
+}
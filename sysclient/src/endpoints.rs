@@ -0,0 +1,139 @@
+//! Web API endpoints used by the client.
+//!
+//! Every request of the client goes through one of these, so the table can be
+//! checked against the OpenAPI document of the master.
+
+use reqwest::Method;
+
+/// Web API operation: HTTP method and path template, e.g. `/api/v1/minions/{mid}`
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub method: Method,
+    pub path: &'static str,
+
+    /// Whether the endpoint needs a bearer token
+    pub auth: bool,
+}
+
+impl Endpoint {
+    const fn new(method: Method, path: &'static str) -> Self {
+        Endpoint { method, path, auth: true }
+    }
+
+    const fn public(method: Method, path: &'static str) -> Self {
+        Endpoint { method, path, auth: false }
+    }
+
+    /// Requests that can be repeated without side effects, after the master might have seen them.
+    /// `DELETE` is not among them: removing a minion or revoking a token again fails or reaches the minions twice.
+    pub fn is_idempotent(&self) -> bool {
+        [Method::GET, Method::PUT, Method::HEAD].contains(&self.method)
+    }
+}
+
+pub const AUTHENTICATE: Endpoint = Endpoint::public(Method::POST, "/api/v1/authenticate");
+pub const HEALTH: Endpoint = Endpoint::public(Method::POST, "/api/v1/health");
+pub const QUERY: Endpoint = Endpoint::new(Method::POST, "/api/v1/query");
+pub const MODEL_NAMES: Endpoint = Endpoint::new(Method::GET, "/api/v1/model/names");
+pub const MODEL_DESCR: Endpoint = Endpoint::new(Method::GET, "/api/v1/model/descr");
+pub const MODEL_VERSIONS: Endpoint = Endpoint::new(Method::GET, "/api/v1/model/versions");
+pub const MODEL_UPLOAD: Endpoint = Endpoint::new(Method::POST, "/api/v1/model/versions");
+pub const MODEL_DIFF: Endpoint = Endpoint::new(Method::GET, "/api/v1/model/versions/diff");
+pub const MODEL_ROLLBACK: Endpoint = Endpoint::new(Method::POST, "/api/v1/model/versions/rollback");
+pub const COMMANDS: Endpoint = Endpoint::new(Method::GET, "/api/v1/commands");
+pub const CYCLES: Endpoint = Endpoint::new(Method::GET, "/api/v1/cycles");
+pub const CYCLE_MINIONS: Endpoint = Endpoint::new(Method::GET, "/api/v1/cycles/{cycle_id}/minions");
+pub const CYCLE_EVENTS: Endpoint = Endpoint::new(Method::GET, "/api/v1/cycles/{cycle_id}/minions/{mid}/events");
+pub const EVENT_STREAM: Endpoint = Endpoint::new(Method::GET, "/api/v1/events/stream");
+
+pub const TOKEN_CREATE: Endpoint = Endpoint::new(Method::POST, "/api/v1/tokens");
+pub const TOKEN_LIST: Endpoint = Endpoint::new(Method::GET, "/api/v1/tokens");
+pub const TOKEN_REVOKE: Endpoint = Endpoint::new(Method::DELETE, "/api/v1/tokens/{name}");
+
+pub const AUDIT_LIST: Endpoint = Endpoint::new(Method::GET, "/api/v1/audit");
+pub const AUDIT_EXPORT: Endpoint = Endpoint::new(Method::GET, "/api/v1/audit/export");
+pub const AUDIT_VERIFY: Endpoint = Endpoint::new(Method::GET, "/api/v1/audit/verify");
+
+pub const MINION_LIST: Endpoint = Endpoint::new(Method::GET, "/api/v1/minions");
+pub const MINION_INFO: Endpoint = Endpoint::new(Method::GET, "/api/v1/minions/{mid}");
+pub const MINION_REMOVE: Endpoint = Endpoint::new(Method::DELETE, "/api/v1/minions/{mid}");
+pub const TRAITS_SET: Endpoint = Endpoint::new(Method::PUT, "/api/v1/traits");
+pub const TRAITS_UNSET: Endpoint = Endpoint::new(Method::DELETE, "/api/v1/traits");
+pub const TRAITS_HISTORY: Endpoint = Endpoint::new(Method::GET, "/api/v1/traits/history");
+pub const PROFILE_LIST: Endpoint = Endpoint::new(Method::GET, "/api/v1/profiles");
+pub const PROFILE_SHOW: Endpoint = Endpoint::new(Method::GET, "/api/v1/profiles/{name}");
+pub const PROFILE_CREATE: Endpoint = Endpoint::new(Method::POST, "/api/v1/profiles");
+pub const PROFILE_UPDATE: Endpoint = Endpoint::new(Method::PATCH, "/api/v1/profiles/{name}");
+pub const PROFILE_DELETE: Endpoint = Endpoint::new(Method::DELETE, "/api/v1/profiles/{name}");
+pub const PROFILE_TAG: Endpoint = Endpoint::new(Method::POST, "/api/v1/profiles/{name}/minions");
+pub const PROFILE_UNTAG: Endpoint = Endpoint::new(Method::DELETE, "/api/v1/profiles/{name}/minions");
+pub const KEY_LIST: Endpoint = Endpoint::new(Method::GET, "/api/v1/keys");
+pub const KEY_ROTATE: Endpoint = Endpoint::new(Method::POST, "/api/v1/keys/rotate");
+pub const UPGRADE_STATUS: Endpoint = Endpoint::new(Method::GET, "/api/v1/upgrade");
+pub const UPGRADE_MARK: Endpoint = Endpoint::new(Method::POST, "/api/v1/upgrade/required");
+
+pub const STORE_UPLOAD: Endpoint = Endpoint::new(Method::POST, "/store");
+pub const STORE_META: Endpoint = Endpoint::new(Method::GET, "/store/{sha256}");
+pub const STORE_BLOB: Endpoint = Endpoint::new(Method::GET, "/store/{sha256}/blob");
+pub const STORE_RESOLVE: Endpoint = Endpoint::new(Method::GET, "/store/resolve");
+pub const STORE_LIST: Endpoint = Endpoint::new(Method::GET, "/store/list");
+pub const STORE_UPLOAD_START: Endpoint = Endpoint::new(Method::POST, "/store/uploads");
+pub const STORE_UPLOAD_STATE: Endpoint = Endpoint::new(Method::GET, "/store/uploads/{upload_id}");
+pub const STORE_UPLOAD_CHUNK: Endpoint = Endpoint::new(Method::PUT, "/store/uploads/{upload_id}");
+pub const STORE_UPLOAD_COMPLETE: Endpoint = Endpoint::new(Method::POST, "/store/uploads/{upload_id}/complete");
+pub const STORE_UPLOAD_ABORT: Endpoint = Endpoint::new(Method::DELETE, "/store/uploads/{upload_id}");
+
+/// All endpoints the client implements
+pub const ALL: &[Endpoint] = &[
+    AUTHENTICATE,
+    HEALTH,
+    QUERY,
+    MODEL_NAMES,
+    MODEL_DESCR,
+    MODEL_VERSIONS,
+    MODEL_UPLOAD,
+    MODEL_DIFF,
+    MODEL_ROLLBACK,
+    COMMANDS,
+    CYCLES,
+    CYCLE_MINIONS,
+    CYCLE_EVENTS,
+    EVENT_STREAM,
+    TOKEN_CREATE,
+    TOKEN_LIST,
+    TOKEN_REVOKE,
+    AUDIT_LIST,
+    AUDIT_EXPORT,
+    AUDIT_VERIFY,
+    MINION_LIST,
+    MINION_INFO,
+    MINION_REMOVE,
+    TRAITS_SET,
+    TRAITS_UNSET,
+    TRAITS_HISTORY,
+    PROFILE_LIST,
+    PROFILE_SHOW,
+    PROFILE_CREATE,
+    PROFILE_UPDATE,
+    PROFILE_DELETE,
+    PROFILE_TAG,
+    PROFILE_UNTAG,
+    KEY_LIST,
+    KEY_ROTATE,
+    UPGRADE_STATUS,
+    UPGRADE_MARK,
+    STORE_UPLOAD,
+    STORE_META,
+    STORE_BLOB,
+    STORE_RESOLVE,
+    STORE_LIST,
+    STORE_UPLOAD_START,
+    STORE_UPLOAD_STATE,
+    STORE_UPLOAD_CHUNK,
+    STORE_UPLOAD_COMPLETE,
+    STORE_UPLOAD_ABORT,
+];
+
+/// Endpoints deliberately left out of the client.
+/// `/store/auth/minion` is signed with the RSA key of a minion and is used by minions only.
+pub const EXCLUDED: &[Endpoint] = &[Endpoint::public(Method::POST, "/store/auth/minion")];
@@ -0,0 +1,92 @@
+//! Live cycle and minion events, read from the Server-Sent Events stream of the master.
+
+#[cfg(test)]
+#[path = "events_ut.rs"]
+mod events_ut;
+
+use libcommon::SysinspectError;
+use libwebapi::stream::StreamEvent;
+use reqwest::Response;
+use serde_json::Value;
+
+/// Open event stream. Events are read as they arrive, see [`EventStream::next`].
+#[derive(Debug)]
+pub struct EventStream {
+    response: Response,
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    pub(crate) fn new(response: Response) -> Self {
+        EventStream { response, buffer: Vec::new() }
+    }
+
+    /// Wait for the next event. Returns `None` once the master closes the stream.
+    ///
+    /// # Errors
+    /// * Returns `SysinspectError::MasterGeneralError` if the connection breaks or the master ends the stream
+    ///   because the session has expired.
+    pub async fn next(&mut self) -> Result<Option<StreamEvent>, SysinspectError> {
+        loop {
+            while let Some(frame) = self.take_frame() {
+                if let Some(event) = parse_frame(&frame)? {
+                    return Ok(Some(event));
+                }
+            }
+
+            match self
+                .response
+                .chunk()
+                .await
+                .map_err(|e| SysinspectError::MasterGeneralError(format!("Event stream error: {e}")))?
+            {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Cut the next complete frame out of the buffer
+    fn take_frame(&mut self) -> Option<String> {
+        let end = self.buffer.windows(2).position(|w| w == b"\n\n")?;
+        let frame = self.buffer.drain(..end + 2).collect::<Vec<u8>>();
+
+        Some(String::from_utf8_lossy(&frame[..end]).into_owned())
+    }
+}
+
+/// Parse a Server-Sent Events frame. Keep-alive comments and lag notices yield no event.
+fn parse_frame(frame: &str) -> Result<Option<StreamEvent>, SysinspectError> {
+    let mut name = "message";
+    let mut data = Vec::new();
+    for line in frame.lines().map(|l| l.trim_end_matches('\r')) {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = value.trim();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    if data.is_empty() {
+        return Ok(None);
+    }
+
+    let data = data.join("\n");
+    match name {
+        "lagged" => {
+            let skipped =
+                serde_json::from_str::<Value>(&data).ok().and_then(|v| v["skipped"].as_u64()).unwrap_or_default();
+            log::warn!("Event stream is lagging behind, {skipped} events were skipped");
+            Ok(None)
+        }
+        "unauthorized" => {
+            let error = serde_json::from_str::<Value>(&data)
+                .ok()
+                .and_then(|v| v["error"].as_str().map(str::to_string))
+                .unwrap_or(data);
+            Err(SysinspectError::MasterGeneralError(format!("Event stream closed by the master: {error}")))
+        }
+        _ => serde_json::from_str::<StreamEvent>(&data)
+            .map(Some)
+            .map_err(|e| SysinspectError::SerializationError(format!("Invalid {name} event: {e}"))),
+    }
+}
@@ -0,0 +1,27 @@
+use super::parse_frame;
+use libwebapi::stream::{StreamEvent, StreamEventKind};
+
+#[test]
+fn event_frames_are_decoded() {
+    let frame = StreamEvent::new(StreamEventKind::CycleComplete, String::new()).cycle("c1").minion("m1").to_sse();
+    let event = parse_frame(frame.trim_end()).unwrap().unwrap();
+
+    assert_eq!(event.kind, StreamEventKind::CycleComplete);
+    assert_eq!(event.cycle_id.as_deref(), Some("c1"));
+    assert_eq!(event.minion_id.as_deref(), Some("m1"));
+}
+
+#[test]
+fn keep_alive_and_lag_frames_are_skipped() {
+    assert!(parse_frame(": keep-alive").unwrap().is_none());
+    assert!(parse_frame("event: lagged\ndata: {\"skipped\":3}").unwrap().is_none());
+}
+
+#[test]
+fn unauthorized_frame_ends_the_stream_with_error() {
+    let err = parse_frame("event: unauthorized\ndata: {\"error\":\"Invalid or expired bearer token\"}")
+        .unwrap_err()
+        .to_string();
+
+    assert!(err.contains("Invalid or expired bearer token"));
+}
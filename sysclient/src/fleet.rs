@@ -0,0 +1,102 @@
+//! Fleet management: minions, traits, profiles, transport keys and upgrades.
+
+use crate::{
+    FleetAckResponse, KeyListResponse, KeyRotateRequest, KeyStatusQuery, MinionDetailsResponse, MinionListResponse,
+    MinionSelector, ProfileListQuery, ProfileListResponse, ProfileResponse, ProfileUpdateRequest, SysClient,
+    TraitHistoryQuery, TraitHistoryResponse, TraitsSetRequest, TraitsUnsetRequest, UpgradeStatusResponse, endpoints,
+};
+use libcommon::SysinspectError;
+use libwebapi::api::v1::fleet::{MinionRemoveQuery, ProfileCreateRequest};
+
+impl SysClient {
+    /// Registered minions with their online state
+    pub async fn minions(&self, selector: &MinionSelector) -> Result<MinionListResponse, SysinspectError> {
+        self.call(&endpoints::MINION_LIST, &[], "Failed to list minions", |r| r.query(selector)).await
+    }
+
+    /// Minion with all its traits
+    pub async fn minion(&self, mid: &str) -> Result<MinionDetailsResponse, SysinspectError> {
+        self.call(&endpoints::MINION_INFO, &[mid], "Failed to get minion details", |r| r).await
+    }
+
+    /// Unregister the minion and remove its key. With `force` the minion is also stopped and its files removed
+    /// over SSH, if it was started by hopstart.
+    pub async fn remove_minion(&self, mid: &str, force: bool) -> Result<FleetAckResponse, SysinspectError> {
+        let q = MinionRemoveQuery { force };
+        self.call(&endpoints::MINION_REMOVE, &[mid], "Failed to remove minion", |r| r.query(&q)).await
+    }
+
+    /// Set master-managed traits on the targeted minions
+    pub async fn set_traits(&self, request: &TraitsSetRequest) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::TRAITS_SET, &[], "Failed to set traits", |r| r.json(request)).await
+    }
+
+    /// Remove master-managed traits from the targeted minions
+    pub async fn unset_traits(&self, request: &TraitsUnsetRequest) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::TRAITS_UNSET, &[], "Failed to unset traits", |r| r.json(request)).await
+    }
+
+    /// Trait changes of the targeted minions, oldest first
+    pub async fn trait_history(&self, q: &TraitHistoryQuery) -> Result<TraitHistoryResponse, SysinspectError> {
+        self.call(&endpoints::TRAITS_HISTORY, &[], "Failed to get trait history", |r| r.query(q)).await
+    }
+
+    /// Deployment profiles
+    pub async fn profiles(&self, q: &ProfileListQuery) -> Result<ProfileListResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_LIST, &[], "Failed to list profiles", |r| r.query(q)).await
+    }
+
+    /// Definition of the profile
+    pub async fn profile(&self, name: &str) -> Result<ProfileResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_SHOW, &[name], "Failed to get profile", |r| r).await
+    }
+
+    pub async fn create_profile(&self, name: &str) -> Result<FleetAckResponse, SysinspectError> {
+        let request = ProfileCreateRequest { name: name.to_string() };
+        self.call(&endpoints::PROFILE_CREATE, &[], "Failed to create profile", |r| r.json(&request)).await
+    }
+
+    /// Add and remove selectors of the profile
+    pub async fn update_profile(
+        &self, name: &str, request: &ProfileUpdateRequest,
+    ) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_UPDATE, &[name], "Failed to update profile", |r| r.json(request)).await
+    }
+
+    pub async fn delete_profile(&self, name: &str) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_DELETE, &[name], "Failed to delete profile", |r| r).await
+    }
+
+    /// Assign the profile to the targeted minions
+    pub async fn tag_profile(
+        &self, name: &str, selector: &MinionSelector,
+    ) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_TAG, &[name], "Failed to assign profile", |r| r.json(selector)).await
+    }
+
+    /// Remove the profile from the targeted minions
+    pub async fn untag_profile(
+        &self, name: &str, selector: &MinionSelector,
+    ) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::PROFILE_UNTAG, &[name], "Failed to unassign profile", |r| r.json(selector)).await
+    }
+
+    /// Transport keys of the targeted minions
+    pub async fn keys(&self, q: &KeyStatusQuery) -> Result<KeyListResponse, SysinspectError> {
+        self.call(&endpoints::KEY_LIST, &[], "Failed to list transport keys", |r| r.query(q)).await
+    }
+
+    /// Rotate transport keys of the targeted minions. Offline minions get the rotation when they reconnect.
+    pub async fn rotate_keys(&self, request: &KeyRotateRequest) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::KEY_ROTATE, &[], "Failed to rotate transport keys", |r| r.json(request)).await
+    }
+
+    pub async fn upgrade_status(&self) -> Result<UpgradeStatusResponse, SysinspectError> {
+        self.call(&endpoints::UPGRADE_STATUS, &[], "Failed to get upgrade status", |r| r).await
+    }
+
+    /// Mark minions behind the repository build as requiring an upgrade
+    pub async fn mark_upgrade_required(&self) -> Result<FleetAckResponse, SysinspectError> {
+        self.call(&endpoints::UPGRADE_MARK, &[], "Failed to mark minions for upgrade", |r| r).await
+    }
+}
@@ -1,21 +1,61 @@
+use endpoints::Endpoint;
 use libcommon::SysinspectError;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use reqwest::{Client, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::{collections::BTreeMap, collections::HashMap};
+use std::collections::HashMap;
+
+mod audit;
+pub mod endpoints;
+mod events;
+mod fleet;
+mod models;
+mod retry;
+mod store;
+mod tokens;
 
 #[cfg(test)]
 mod lib_ut;
 
+pub use events::EventStream;
+pub use libwebapi::{
+    api::v1::{
+        audit::{AuditListResponse, AuditQuery, AuditRecordInfo, AuditVerifyResponse},
+        commands::{CommandListQuery, CommandListResponse, QueuedCommandInfo},
+        cycles::{
+            CycleEventInfo, CycleEventsQuery, CycleEventsResponse, CycleInfo, CycleListResponse, CycleMinionInfo,
+            CycleMinionsQuery, CycleMinionsResponse, CyclePageQuery,
+        },
+        fleet::{
+            FleetAckResponse, KeyInfo, KeyListResponse, KeyRotateRequest, KeyStatusQuery, MinionDetailsResponse,
+            MinionInfo, MinionListResponse, MinionSelector, MinionTraitInfo, ProfileListQuery, ProfileListResponse,
+            ProfileResponse, ProfileUpdateRequest, TraitChangeInfo, TraitHistoryQuery, TraitHistoryResponse,
+            TraitsSetRequest, TraitsUnsetRequest, UpgradeStatusResponse,
+        },
+        minions::{QueryRequest, QueryResponse},
+        model::{
+            ModelDiffQuery, ModelDiffResponse, ModelFileDiffInfo, ModelInfo, ModelNameResponse, ModelResponse,
+            ModelRollbackRequest, ModelUploadRequest, ModelVersionInfo, ModelVersionResponse, ModelVersionsResponse,
+        },
+        store::{StoreListQuery, StoreMetaResponse, StoreUploadResponse, StoreUploadStartRequest},
+        system::{AuthRequest, AuthResponse, HealthInfo, HealthResponse},
+        tokens::{TokenCreateRequest, TokenCreateResponse, TokenInfo, TokenListResponse},
+    },
+    stream::{StreamEvent, StreamEventKind, StreamFilter},
+};
+pub use retry::RetryPolicy;
+
 /// SysClient Configuration
 /// This struct holds the configuration for the SysClient, including the root directory.
 /// It can be extended in the future to include more configuration options.
 ///
 /// # Fields
 /// * `master_url` - The URL of the SysInspect master server.
+/// * `retry` - How requests are retried when the master is unreachable or busy.
 #[derive(Debug, Clone)]
 pub struct SysClientConfiguration {
     pub master_url: String,
+    pub retry: RetryPolicy,
 }
 
 impl SysClientConfiguration {
@@ -26,76 +66,155 @@ impl SysClientConfiguration {
 
 impl Default for SysClientConfiguration {
     fn default() -> Self {
-        SysClientConfiguration { master_url: "https://localhost:4202".to_string() }
+        SysClientConfiguration { master_url: "https://localhost:4202".to_string(), retry: RetryPolicy::default() }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AuthRequest {
-    pub username: String,
-    pub password: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AuthResponse {
-    pub status: String,
-    pub access_token: String,
-    pub token_type: String,
-    pub error: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct QueryRequest {
-    pub model: String,
-    pub query: String,
-    pub traits: String,
-    pub mid: String,
-    pub context: HashMap<String, String>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct QueryResponse {
-    pub status: String,
-    pub message: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ModelNameResponse {
-    pub models: Vec<String>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ModelInfo {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub version: String,
-    pub maintainer: String,
-    #[allow(clippy::type_complexity)]
-    #[serde(rename = "entity-states")]
-    pub entity_states: BTreeMap<String, Vec<(String, BTreeMap<String, String>)>>,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ModelResponse {
-    pub model: ModelInfo,
-}
-
 /// SysClient is the main client for interacting with the SysInspect Web API.
-/// It handles authentication and plain JSON request/response flows.
+/// It handles authentication and typed JSON request/response flows for all the endpoints,
+/// see [`endpoints::ALL`]. Request and response types are shared with the Web API.
 ///
 /// # Fields
 /// * `cfg` - The configuration for the SysClient, which includes the master URL.
-/// * `access_token` - The bearer token for the authenticated user.
+/// * `http` - HTTP client, reused by all requests.
+/// * `access_token` - The bearer token: a session of the authenticated user or an API token.
 #[derive(Debug, Clone)]
 pub struct SysClient {
     cfg: SysClientConfiguration,
+    http: Client,
     access_token: String,
 }
 
 impl SysClient {
     pub fn new(cfg: SysClientConfiguration) -> Self {
-        SysClient { cfg, access_token: String::new() }
+        SysClient { http: cfg.client(), cfg, access_token: String::new() }
+    }
+
+    /// Use an existing bearer token, such as an API token created with `sysinspect token --create`,
+    /// instead of authenticating with username and password.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.access_token = token.trim().to_string();
+        self
+    }
+
+    /// Current bearer token, empty if the client is not authenticated
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// Build the URL of the endpoint, filling the path parameters in order.
+    fn url(&self, endpoint: &Endpoint, args: &[&str]) -> Result<Url, SysinspectError> {
+        let mut url = Url::parse(self.cfg.master_url.trim_end_matches('/')).map_err(|e| {
+            SysinspectError::MasterGeneralError(format!("Invalid master URL {}: {e}", self.cfg.master_url))
+        })?;
+        {
+            let mut segments = url.path_segments_mut().map_err(|_| {
+                SysinspectError::MasterGeneralError(format!("Invalid master URL {}", self.cfg.master_url))
+            })?;
+            segments.pop_if_empty();
+
+            let mut args = args.iter();
+            for segment in endpoint.path.trim_start_matches('/').split('/') {
+                if segment.starts_with('{') {
+                    let arg = args.next().ok_or_else(|| {
+                        SysinspectError::MasterGeneralError(format!("Missing {segment} for {}", endpoint.path))
+                    })?;
+                    segments.push(arg);
+                } else {
+                    segments.push(segment);
+                }
+            }
+        }
+
+        Ok(url)
+    }
+
+    /// Send a request to the endpoint and return the successful response.
+    /// Failed requests are retried according to the retry policy of the configuration.
+    ///
+    /// # Arguments
+    /// * `endpoint` - The endpoint to call.
+    /// * `args` - Values of the path parameters of the endpoint, in order.
+    /// * `what` - What the request does, used in the error messages.
+    /// * `build` - Adds query, headers and body to the request. Called again for each retry.
+    ///
+    /// # Errors
+    /// * Returns `SysinspectError::MasterGeneralError` if the client is not authenticated for a protected endpoint,
+    ///   if the master cannot be reached or if it answers with an error. The error message of the master is included.
+    async fn send(
+        &self, endpoint: &Endpoint, args: &[&str], what: &str, build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, SysinspectError> {
+        if endpoint.auth && self.access_token.is_empty() {
+            return Err(SysinspectError::MasterGeneralError("Client is not authenticated".to_string()));
+        }
+
+        let url = self.url(endpoint, args)?;
+        let mut attempt = 0;
+        loop {
+            let mut request = self.http.request(endpoint.method.clone(), url.clone());
+            if endpoint.auth {
+                request = request.bearer_auth(&self.access_token);
+            }
+
+            let delay = match build(request).send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => match self.cfg.retry.delay(attempt, &response).filter(|_| endpoint.is_idempotent()) {
+                    Some(delay) => {
+                        log::debug!(
+                            "{} {} returned {}, retrying in {delay:?}",
+                            endpoint.method,
+                            endpoint.path,
+                            response.status()
+                        );
+                        delay
+                    }
+                    None => return Err(Self::status_error(what, response).await),
+                },
+                Err(err) => {
+                    // A request that never reached the master is safe to repeat
+                    let retry = attempt < self.cfg.retry.max_retries
+                        && (err.is_connect() || (err.is_timeout() && endpoint.is_idempotent()));
+                    if !retry {
+                        return Err(SysinspectError::MasterGeneralError(format!("{what}: {err}")));
+                    }
+
+                    let delay = self.cfg.retry.backoff(attempt);
+                    log::debug!("{} {} failed: {err}, retrying in {delay:?}", endpoint.method, endpoint.path);
+                    delay
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send a request to the endpoint and decode the JSON response.
+    async fn call<T: DeserializeOwned>(
+        &self, endpoint: &Endpoint, args: &[&str], what: &str, build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<T, SysinspectError> {
+        self.send(endpoint, args, what, build)
+            .await?
+            .json::<T>()
+            .await
+            .map_err(|e| SysinspectError::MasterGeneralError(format!("{what}: cannot decode the response: {e}")))
+    }
+
+    /// Turn an error response into an error, with the message of the master if it has one.
+    async fn status_error(what: &str, response: Response) -> SysinspectError {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v.get("error").and_then(Value::as_str).map(str::to_string))
+            .filter(|e| !e.is_empty())
+            .unwrap_or_else(|| body.trim().to_string());
+
+        SysinspectError::MasterGeneralError(if message.is_empty() {
+            format!("{what}: {status}")
+        } else {
+            format!("{what}: {status}: {message}")
+        })
     }
 
     /// Authenticate a user with the SysInspect system.
@@ -106,25 +225,13 @@ impl SysClient {
     /// * `pwd` - The password for the user.
     ///
     ///  # Returns
-    /// A `Result` that is `Ok(true)` if authentication is successful,
-    /// or `Ok(false)` if authentication fails.
-    /// If there is an error during the setup or authentication process, it returns an `Err(SysinspectError)`.
+    /// A `Result` that is `Ok(String)` with the bearer token if authentication is successful.
+    /// If authentication fails or there is an error during the process, it returns an `Err(SysinspectError)`.
     pub async fn authenticate(&mut self, uid: &str, pwd: &str) -> Result<String, SysinspectError> {
         log::debug!("Authenticating user: {uid}");
-        let response = self
-            .cfg
-            .client()
-            .post(format!("{}/api/v1/authenticate", self.cfg.master_url.trim_end_matches('/')))
-            .json(&AuthRequest { username: uid.to_string(), password: pwd.to_string() })
-            .send()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Authentication error: {e}")))?;
-        let response = response
-            .error_for_status()
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Authentication error: {e}")))?
-            .json::<AuthResponse>()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Authentication decode error: {e}")))?;
+        let request = AuthRequest { username: uid.to_string(), password: pwd.to_string() };
+        let response: AuthResponse =
+            self.call(&endpoints::AUTHENTICATE, &[], "Authentication error", |r| r.json(&request)).await?;
 
         if response.status != "authenticated" || response.access_token.trim().is_empty() {
             return Err(SysinspectError::MasterGeneralError(if response.error.is_empty() {
@@ -140,6 +247,11 @@ impl SysClient {
         Ok(self.access_token.clone())
     }
 
+    /// Check the health of the Web API. Does not need authentication.
+    pub async fn health(&self) -> Result<HealthResponse, SysinspectError> {
+        self.call(&endpoints::HEALTH, &[], "Health check error", |r| r).await
+    }
+
     /// Query the SysInspect system with a given query string.
     /// This method requires the client to be authenticated.
     ///
@@ -147,7 +259,7 @@ impl SysClient {
     /// * `query` - The query string to send to the SysInspect system.
     ///
     /// # Returns
-    /// A `Result` that is `Ok(String)` containing the response from the SysInspect system,
+    /// A `Result` that is `Ok(QueryResponse)` containing the response from the SysInspect system,
     /// or an `Err(SysinspectError)` if there is an error during the query process.
     ///
     /// # Errors
@@ -159,34 +271,22 @@ impl SysClient {
     pub async fn query(
         &self, model: &str, query: &str, traits: &str, mid: &str, context: Value,
     ) -> Result<QueryResponse, SysinspectError> {
-        if self.access_token.is_empty() {
-            return Err(SysinspectError::MasterGeneralError("Client is not authenticated".to_string()));
-        }
-
-        let query_request = QueryRequest {
+        self.dispatch(&QueryRequest {
             model: model.to_string(),
             query: query.to_string(),
             traits: traits.to_string(),
             mid: mid.to_string(),
             context: Self::context_map(context)?,
-        };
-
-        let response = self
-            .cfg
-            .client()
-            .post(format!("{}/api/v1/query", self.cfg.master_url.trim_end_matches('/')))
-            .bearer_auth(&self.access_token)
-            .json(&query_request)
-            .send()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Query error: {e}")))?
-            .error_for_status()
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Query error: {e}")))?
-            .json::<QueryResponse>()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Query decode error: {e}")))?;
+            ttl: None,
+            supersede: None,
+        })
+        .await
+    }
 
-        Ok(response)
+    /// Send a prepared query, e.g. with a delivery deadline for offline minions (`ttl`) or a `supersede` key.
+    /// Queries are not retried once they reached the master, to not run them twice.
+    pub async fn dispatch(&self, request: &QueryRequest) -> Result<QueryResponse, SysinspectError> {
+        self.call(&endpoints::QUERY, &[], "Query error", |r| r.json(request)).await
     }
 
     /// Retrieve the list of available models from the SysInspect system.
@@ -196,47 +296,44 @@ impl SysClient {
     /// or an `Err(SysinspectError)` if there is an error during the retrieval process.
     /// # Errors
     /// * Returns `SysinspectError::MasterGeneralError` if there is an error during the retrieval process, such as network issues or server errors.
-    ///
-    /// Calls the `list_models` API to fetch available models from the SysInspect system.
-    /// Returns a `ModelNameResponse` containing the list of models on success, or a `SysinspectError` if the API call fails.
-    /// This enables the caller to access the models provided by the SysInspect system.
     pub async fn models(&self) -> Result<ModelNameResponse, SysinspectError> {
-        if self.access_token.is_empty() {
-            return Err(SysinspectError::MasterGeneralError("Client is not authenticated".to_string()));
-        }
-
-        self.cfg
-            .client()
-            .get(format!("{}/api/v1/model/names", self.cfg.master_url.trim_end_matches('/')))
-            .bearer_auth(&self.access_token)
-            .send()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to list models: {e}")))?
-            .error_for_status()
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to list models: {e}")))?
-            .json::<ModelNameResponse>()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to decode model list: {e}")))
+        self.call(&endpoints::MODEL_NAMES, &[], "Failed to list models", |r| r).await
     }
 
+    /// Retrieve the description of a model: its entities with their states.
     pub async fn model_descr(&self, name: &str) -> Result<ModelResponse, SysinspectError> {
-        if self.access_token.is_empty() {
-            return Err(SysinspectError::MasterGeneralError("Client is not authenticated".to_string()));
-        }
+        self.call(&endpoints::MODEL_DESCR, &[], "Failed to get model details", |r| r.query(&[("name", name)])).await
+    }
 
-        self.cfg
-            .client()
-            .get(format!("{}/api/v1/model/descr", self.cfg.master_url.trim_end_matches('/')))
-            .bearer_auth(&self.access_token)
-            .query(&[("name", name)])
-            .send()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to get model details: {e}")))?
-            .error_for_status()
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to get model details: {e}")))?
-            .json::<ModelResponse>()
-            .await
-            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to decode model details: {e}")))
+    /// Commands queued for offline minions, with their delivery receipts
+    pub async fn commands(&self, filter: &CommandListQuery) -> Result<CommandListResponse, SysinspectError> {
+        self.call(&endpoints::COMMANDS, &[], "Failed to list queued commands", |r| r.query(filter)).await
+    }
+
+    /// Recorded query cycles, newest first
+    pub async fn cycles(&self, page: &CyclePageQuery) -> Result<CycleListResponse, SysinspectError> {
+        self.call(&endpoints::CYCLES, &[], "Failed to list cycles", |r| r.query(page)).await
+    }
+
+    /// Minions that answered within the cycle
+    pub async fn cycle_minions(
+        &self, cycle_id: &str, filter: &CycleMinionsQuery,
+    ) -> Result<CycleMinionsResponse, SysinspectError> {
+        self.call(&endpoints::CYCLE_MINIONS, &[cycle_id], "Failed to list cycle minions", |r| r.query(filter)).await
+    }
+
+    /// Events a minion returned within the cycle
+    pub async fn cycle_events(
+        &self, cycle_id: &str, mid: &str, filter: &CycleEventsQuery,
+    ) -> Result<CycleEventsResponse, SysinspectError> {
+        self.call(&endpoints::CYCLE_EVENTS, &[cycle_id, mid], "Failed to list cycle events", |r| r.query(filter)).await
+    }
+
+    /// Subscribe to live cycle and minion events. Only events published after subscribing are received.
+    pub async fn events(&self, filter: &StreamFilter) -> Result<EventStream, SysinspectError> {
+        let response =
+            self.send(&endpoints::EVENT_STREAM, &[], "Failed to open event stream", |r| r.query(filter)).await?;
+        Ok(EventStream::new(response))
     }
 
     fn context_map(context: Value) -> Result<HashMap<String, String>, SysinspectError> {
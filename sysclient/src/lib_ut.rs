@@ -1,6 +1,7 @@
-use super::{SysClient, SysClientConfiguration};
+use super::{RetryPolicy, SysClient, SysClientConfiguration, endpoints};
 use libcommon::SysinspectError;
 use serde_json::json;
+use std::time::Duration;
 
 #[test]
 fn default_configuration_uses_https_localhost() {
@@ -9,7 +10,7 @@ fn default_configuration_uses_https_localhost() {
 
 #[tokio::test]
 async fn query_requires_authentication_first() {
-    let client = SysClient::new(SysClientConfiguration { master_url: "https://localhost:4202".to_string() });
+    let client = SysClient::new(SysClientConfiguration::default());
     let err = client.query("cm/file-ops", "*", "", "", json!({})).await.unwrap_err().to_string();
 
     assert!(err.contains("not authenticated"));
@@ -17,7 +18,7 @@ async fn query_requires_authentication_first() {
 
 #[tokio::test]
 async fn models_require_authentication_first() {
-    let client = SysClient::new(SysClientConfiguration { master_url: "https://localhost:4202".to_string() });
+    let client = SysClient::new(SysClientConfiguration::default());
     let err = client.models().await.unwrap_err().to_string();
 
     assert!(err.contains("not authenticated"));
@@ -25,7 +26,7 @@ async fn models_require_authentication_first() {
 
 #[tokio::test]
 async fn model_descr_requires_authentication_first() {
-    let client = SysClient::new(SysClientConfiguration { master_url: "https://localhost:4202".to_string() });
+    let client = SysClient::new(SysClientConfiguration::default());
     let err = client.model_descr("cm").await.unwrap_err().to_string();
 
     assert!(err.contains("not authenticated"));
@@ -55,3 +56,39 @@ fn query_context_stringifies_non_string_values() {
     assert_eq!(context.get("b"), Some(&"true".to_string()));
     assert_eq!(context.get("s"), Some(&"text".to_string()));
 }
+
+#[test]
+fn endpoint_urls_encode_path_parameters() {
+    let client =
+        SysClient::new(SysClientConfiguration { master_url: "https://master:4202/".to_string(), ..Default::default() });
+
+    assert_eq!(client.url(&endpoints::CYCLES, &[]).unwrap().as_str(), "https://master:4202/api/v1/cycles");
+    assert_eq!(
+        client.url(&endpoints::CYCLE_EVENTS, &["c1", "web/01"]).unwrap().as_str(),
+        "https://master:4202/api/v1/cycles/c1/minions/web%2F01/events"
+    );
+    assert!(client.url(&endpoints::MINION_INFO, &[]).is_err());
+}
+
+#[test]
+fn backoff_doubles_up_to_the_limit() {
+    let policy = RetryPolicy {
+        max_retries: 5,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(300),
+    };
+
+    assert_eq!(policy.backoff(0), Duration::from_millis(100));
+    assert_eq!(policy.backoff(1), Duration::from_millis(200));
+    assert_eq!(policy.backoff(2), Duration::from_millis(300));
+    assert_eq!(policy.backoff(40), Duration::from_millis(300));
+}
+
+#[test]
+fn only_writes_and_reads_without_side_effects_are_idempotent() {
+    assert!(endpoints::TRAITS_SET.is_idempotent());
+    assert!(!endpoints::MINION_REMOVE.is_idempotent());
+    assert!(!endpoints::TOKEN_REVOKE.is_idempotent());
+    assert!(!endpoints::QUERY.is_idempotent());
+    assert!(!endpoints::STORE_UPLOAD.is_idempotent());
+}
@@ -47,7 +47,7 @@ async fn main() -> Result<(), SysinspectError> {
 
     println!("Model id: {}", mdetails.model.id);
     println!("Model descr: {:?}", mdetails.model.description);
-    println!("States: {:#?}", mdetails.model.entity_states);
+    println!("States: {:#?}", mdetails.model.entities);
 
     Ok(())
 }
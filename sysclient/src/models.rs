@@ -0,0 +1,33 @@
+//! Model versions on the master.
+
+use crate::{
+    ModelDiffQuery, ModelDiffResponse, ModelRollbackRequest, ModelUploadRequest, ModelVersionResponse,
+    ModelVersionsResponse, SysClient, endpoints,
+};
+use libcommon::SysinspectError;
+
+impl SysClient {
+    /// Stored versions of the model, oldest first, and which of them is active
+    pub async fn model_versions(&self, name: &str) -> Result<ModelVersionsResponse, SysinspectError> {
+        self.call(&endpoints::MODEL_VERSIONS, &[], "Failed to list model versions", |r| r.query(&[("name", name)]))
+            .await
+    }
+
+    /// Upload a model bundle. The master validates it, stores it as a new version and activates it.
+    /// A bundle of a model directory is made with `libsysinspect::mdescr::versions::ModelBundle::from_dir`.
+    pub async fn upload_model(&self, request: &ModelUploadRequest) -> Result<ModelVersionResponse, SysinspectError> {
+        self.call(&endpoints::MODEL_UPLOAD, &[], "Failed to upload model", |r| r.json(request)).await
+    }
+
+    /// Changes between two versions of a model
+    pub async fn model_diff(&self, q: &ModelDiffQuery) -> Result<ModelDiffResponse, SysinspectError> {
+        self.call(&endpoints::MODEL_DIFF, &[], "Failed to compare model versions", |r| r.query(q)).await
+    }
+
+    /// Activate an earlier version of a model
+    pub async fn rollback_model(
+        &self, request: &ModelRollbackRequest,
+    ) -> Result<ModelVersionResponse, SysinspectError> {
+        self.call(&endpoints::MODEL_ROLLBACK, &[], "Failed to roll back model", |r| r.json(request)).await
+    }
+}
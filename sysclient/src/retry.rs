@@ -0,0 +1,66 @@
+//! Retry policy of the client.
+
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+use std::time::Duration;
+
+/// How failed requests are retried.
+///
+/// Requests are retried when the master could not be reached, or when it
+/// answers that it is temporarily unavailable (429, 502, 503 and 504).
+/// Requests that are not idempotent are retried only if they were never sent.
+///
+/// # Fields
+/// * `max_retries` - How many times a request is repeated, zero turns retries off.
+/// * `initial_backoff` - Delay before the first retry, doubled on every next one.
+/// * `max_backoff` - Upper bound of a single delay, also for the `Retry-After` header of the master.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Do not retry at all
+    pub fn none() -> Self {
+        RetryPolicy { max_retries: 0, ..Default::default() }
+    }
+
+    /// Delay before the retry number `attempt`, counted from zero
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_backoff)
+    }
+
+    /// Delay before retrying the response, if it is worth retrying
+    pub(crate) fn delay(&self, attempt: u32, response: &Response) -> Option<Duration> {
+        if attempt >= self.max_retries || !Self::is_transient(response.status()) {
+            return None;
+        }
+
+        let after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|secs| Duration::from_secs(secs).min(self.max_backoff));
+
+        Some(after.unwrap_or_else(|| self.backoff(attempt)))
+    }
+
+    /// Statuses of a master that is overloaded or restarting
+    pub fn is_transient(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_retries: 3, initial_backoff: Duration::from_millis(200), max_backoff: Duration::from_secs(5) }
+    }
+}
@@ -0,0 +1,257 @@
+//! Datastore: upload, lookup and download of stored objects.
+
+use crate::{StoreListQuery, StoreMetaResponse, StoreUploadResponse, StoreUploadStartRequest, SysClient, endpoints};
+use libcommon::SysinspectError;
+use reqwest::header::{CONTENT_TYPE, RANGE};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, path::Path};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// SHA256 of a local file, read in blocks
+async fn file_sha256(path: &Path) -> Result<String, SysinspectError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+impl SysClient {
+    /// Store the data. `fname` is the path recorded with the object, to find it later with [`SysClient::store_resolve`].
+    pub async fn store_upload(&self, data: &[u8], fname: Option<&str>) -> Result<StoreMetaResponse, SysinspectError> {
+        self.store_upload_to(data, fname, None, &BTreeMap::new()).await
+    }
+
+    /// Store the data in a namespace, with tags to search for it in [`SysClient::store_list`]
+    pub async fn store_upload_to(
+        &self, data: &[u8], fname: Option<&str>, namespace: Option<&str>, tags: &BTreeMap<String, String>,
+    ) -> Result<StoreMetaResponse, SysinspectError> {
+        let tags = tags.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join(",");
+        self.call(&endpoints::STORE_UPLOAD, &[], "Failed to upload to datastore", |r| {
+            let mut r = r.header(CONTENT_TYPE, "application/octet-stream").body(data.to_vec());
+            if let Some(fname) = fname {
+                r = r.header("X-Filename", fname);
+            }
+            if let Some(namespace) = namespace {
+                r = r.header("X-Namespace", namespace);
+            }
+            if !tags.is_empty() {
+                r = r.header("X-Tags", tags.as_str());
+            }
+            r
+        })
+        .await
+    }
+
+    /// Store a local file under its path
+    pub async fn store_upload_file(&self, path: &Path) -> Result<StoreMetaResponse, SysinspectError> {
+        let data = tokio::fs::read(path).await?;
+        self.store_upload(&data, Some(&path.to_string_lossy())).await
+    }
+
+    /// Metadata of a stored object
+    pub async fn store_meta(&self, sha256: &str) -> Result<StoreMetaResponse, SysinspectError> {
+        self.call(&endpoints::STORE_META, &[sha256], "Failed to get datastore object", |r| r).await
+    }
+
+    /// Newest object stored under the path
+    pub async fn store_resolve(&self, fname: &str) -> Result<StoreMetaResponse, SysinspectError> {
+        self.call(&endpoints::STORE_RESOLVE, &[], "Failed to resolve datastore object", |r| {
+            r.query(&[("fname", fname)])
+        })
+        .await
+    }
+
+    /// Newest object stored under the path in a namespace
+    pub async fn store_resolve_in(&self, namespace: &str, fname: &str) -> Result<StoreMetaResponse, SysinspectError> {
+        self.call(&endpoints::STORE_RESOLVE, &[], "Failed to resolve datastore object", |r| {
+            r.query(&[("fname", fname), ("namespace", namespace)])
+        })
+        .await
+    }
+
+    /// Stored objects, newest first
+    pub async fn store_list(&self, q: &StoreListQuery) -> Result<Vec<StoreMetaResponse>, SysinspectError> {
+        self.call(&endpoints::STORE_LIST, &[], "Failed to list datastore", |r| r.query(q)).await
+    }
+
+    /// Content of a stored object
+    pub async fn store_blob(&self, sha256: &str) -> Result<Vec<u8>, SysinspectError> {
+        let response =
+            self.send(&endpoints::STORE_BLOB, &[sha256], "Failed to download datastore object", |r| r).await?;
+        Ok(response
+            .bytes()
+            .await
+            .map_err(|e| SysinspectError::MasterGeneralError(format!("Failed to download datastore object: {e}")))?
+            .to_vec())
+    }
+
+    /// Download a stored object into a file, without keeping it in memory. Returns the number of bytes written.
+    ///
+    /// The object is downloaded to `<dst>.tmp` first. A partial download left there by an earlier
+    /// call is resumed with a range request, also when the connection breaks in the middle.
+    /// The file is renamed to `dst` only when its sha256 matches.
+    pub async fn store_download(&self, sha256: &str, dst: &Path) -> Result<u64, SysinspectError> {
+        let size = self.store_meta(sha256).await?.size_bytes;
+        let tmp = dst.with_extension("tmp");
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&tmp).await?;
+        let mut written = file.metadata().await?.len();
+        if written > size {
+            file.set_len(0).await?;
+            written = 0;
+        }
+
+        let mut attempt = 0;
+        while written < size {
+            let offset = written;
+            let mut response = self
+                .send(&endpoints::STORE_BLOB, &[sha256], "Failed to download datastore object", |r| {
+                    if offset > 0 { r.header(RANGE, format!("bytes={offset}-")) } else { r }
+                })
+                .await?;
+            if offset > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                // The master sent the whole object
+                file.set_len(0).await?;
+                written = 0;
+            }
+
+            let mut broken = None;
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        file.write_all(&chunk).await?;
+                        written += chunk.len() as u64;
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        broken = Some(err);
+                        break;
+                    }
+                }
+            }
+            file.flush().await?;
+
+            match broken {
+                None if written < size => {
+                    return Err(SysinspectError::MasterGeneralError(format!(
+                        "Failed to download datastore object: it ended after {written} of {size} bytes"
+                    )));
+                }
+                None => {}
+                Some(err) if attempt < self.cfg.retry.max_retries => {
+                    log::debug!("Download of {sha256} broke at {written} of {size} bytes: {err}, resuming");
+                    tokio::time::sleep(self.cfg.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Some(err) => {
+                    return Err(SysinspectError::MasterGeneralError(format!(
+                        "Failed to download datastore object: {err}, {written} of {size} bytes kept to resume"
+                    )));
+                }
+            }
+        }
+        drop(file);
+
+        let have = file_sha256(&tmp).await?;
+        if have != sha256.to_lowercase() {
+            tokio::fs::remove_file(&tmp).await?;
+            return Err(SysinspectError::MasterGeneralError(format!(
+                "Failed to download datastore object: checksum mismatch, got {have}"
+            )));
+        }
+        tokio::fs::rename(&tmp, dst).await?;
+
+        Ok(written)
+    }
+
+    /// Start a staged upload, to send a big object in chunks
+    pub async fn store_upload_start(
+        &self, rq: &StoreUploadStartRequest,
+    ) -> Result<StoreUploadResponse, SysinspectError> {
+        self.call(&endpoints::STORE_UPLOAD_START, &[], "Failed to start datastore upload", |r| r.json(rq)).await
+    }
+
+    /// State of a staged upload: how many bytes the master has
+    pub async fn store_upload_state(&self, upload_id: &str) -> Result<StoreUploadResponse, SysinspectError> {
+        self.call(&endpoints::STORE_UPLOAD_STATE, &[upload_id], "Failed to get datastore upload", |r| r).await
+    }
+
+    /// Send a chunk of a staged upload at `offset`, which must be the bytes the master has so far
+    pub async fn store_upload_chunk(
+        &self, upload_id: &str, offset: u64, data: &[u8],
+    ) -> Result<StoreUploadResponse, SysinspectError> {
+        let sha256 = format!("{:x}", Sha256::digest(data));
+        self.call(&endpoints::STORE_UPLOAD_CHUNK, &[upload_id], "Failed to upload datastore chunk", |r| {
+            r.query(&[("offset", offset)])
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .header("X-Chunk-Sha256", sha256.as_str())
+                .body(data.to_vec())
+        })
+        .await
+    }
+
+    /// Verify and store a staged upload, once all of it is sent
+    pub async fn store_upload_complete(&self, upload_id: &str) -> Result<StoreMetaResponse, SysinspectError> {
+        self.call(&endpoints::STORE_UPLOAD_COMPLETE, &[upload_id], "Failed to complete datastore upload", |r| r).await
+    }
+
+    /// Drop a staged upload
+    pub async fn store_upload_abort(&self, upload_id: &str) -> Result<(), SysinspectError> {
+        self.send(&endpoints::STORE_UPLOAD_ABORT, &[upload_id], "Failed to abort datastore upload", |r| r).await?;
+        Ok(())
+    }
+
+    /// Store a local file in chunks, under its path in the namespace. Use this for big files:
+    /// if the upload breaks, the error names the upload id to continue with [`SysClient::store_upload_resume`].
+    pub async fn store_upload_file_chunked(
+        &self, path: &Path, namespace: Option<&str>, tags: &BTreeMap<String, String>,
+    ) -> Result<StoreMetaResponse, SysinspectError> {
+        let rq = StoreUploadStartRequest {
+            size_bytes: tokio::fs::metadata(path).await?.len(),
+            sha256: Some(file_sha256(path).await?),
+            fname: Some(path.to_string_lossy().to_string()),
+            namespace: namespace.map(str::to_string),
+            tags: tags.clone(),
+        };
+        let upload = self.store_upload_start(&rq).await?;
+        self.store_upload_resume(&upload.upload_id, path).await
+    }
+
+    /// Send the rest of a staged upload from the local file, starting where the master left off, and complete it.
+    /// A failed chunk is sent again from the offset the master reports, up to the retries of the configuration.
+    pub async fn store_upload_resume(
+        &self, upload_id: &str, path: &Path,
+    ) -> Result<StoreMetaResponse, SysinspectError> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut state = self.store_upload_state(upload_id).await?;
+        let mut buf = vec![0u8; state.chunk_size.max(1) as usize];
+        let mut attempt = 0;
+
+        while state.received_bytes < state.size_bytes {
+            let len = (state.size_bytes - state.received_bytes).min(buf.len() as u64) as usize;
+            file.seek(std::io::SeekFrom::Start(state.received_bytes)).await?;
+            file.read_exact(&mut buf[..len]).await?;
+
+            state = match self.store_upload_chunk(upload_id, state.received_bytes, &buf[..len]).await {
+                Ok(state) => state,
+                Err(err) if attempt < self.cfg.retry.max_retries => {
+                    log::debug!("Chunk of upload {upload_id} at {} failed: {err}, resuming", state.received_bytes);
+                    tokio::time::sleep(self.cfg.retry.backoff(attempt)).await;
+                    attempt += 1;
+                    self.store_upload_state(upload_id).await?
+                }
+                Err(err) => {
+                    return Err(SysinspectError::MasterGeneralError(format!("{err}, resume upload {upload_id} later")));
+                }
+            };
+        }
+
+        self.store_upload_complete(upload_id).await
+    }
+}
@@ -0,0 +1,21 @@
+//! API tokens of the authenticated user.
+
+use crate::{SysClient, TokenCreateRequest, TokenCreateResponse, TokenInfo, TokenListResponse, endpoints};
+use libcommon::SysinspectError;
+
+impl SysClient {
+    /// Create an API token. The token is returned only once, use it with [`SysClient::with_token`].
+    /// Tokens can be managed only with a user session, not with another token.
+    pub async fn create_token(&self, request: &TokenCreateRequest) -> Result<TokenCreateResponse, SysinspectError> {
+        self.call(&endpoints::TOKEN_CREATE, &[], "Failed to create API token", |r| r.json(request)).await
+    }
+
+    /// API tokens of the user, without their secrets
+    pub async fn tokens(&self) -> Result<TokenListResponse, SysinspectError> {
+        self.call(&endpoints::TOKEN_LIST, &[], "Failed to list API tokens", |r| r).await
+    }
+
+    pub async fn revoke_token(&self, name: &str) -> Result<TokenInfo, SysinspectError> {
+        self.call(&endpoints::TOKEN_REVOKE, &[name], "Failed to revoke API token", |r| r).await
+    }
+}
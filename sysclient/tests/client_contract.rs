@@ -1,23 +1,48 @@
-use actix_web::{App, HttpServer, web};
+use actix_web::{App, HttpResponse, HttpServer, middleware::from_fn, web};
 use async_trait::async_trait;
 use libdatastore::{cfg::DataStorageConfig, resources::DataStorage};
-use libsysinspect::cfg::mmconf::MasterConfig;
+use libsysinspect::{
+    cfg::mmconf::MasterConfig,
+    console::{ConsoleOnlineMinionRow, ConsolePayload, ConsoleQuery, ConsoleResponse},
+    mdescr::versions::ModelBundle,
+};
 use libwebapi::{
     MasterInterface, MasterInterfaceType,
-    api::{self, ApiVersions},
+    api::{self, ApiVersions, v1::ApiDoc},
+    audit::audit_middleware,
+    stream::{self, StreamSender},
+};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::Path,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
-use std::{fs, path::Path, sync::Arc};
-use sysinspect_client::{ModelNameResponse, QueryResponse, SysClient, SysClientConfiguration};
+use sysinspect_client::{
+    AuditQuery, CycleEventInfo, CycleEventsQuery, CycleInfo, CycleMinionInfo, CycleMinionsQuery, CyclePageQuery,
+    MinionSelector, ModelDiffQuery, ModelNameResponse, ModelRollbackRequest, ModelUploadRequest, QueryResponse,
+    RetryPolicy, StoreListQuery, StreamEvent, StreamEventKind, StreamFilter, SysClient, SysClientConfiguration,
+    TokenCreateRequest, TraitsSetRequest, endpoints,
+};
+use tempfile::TempDir;
 use tokio::{
-    sync::Mutex,
+    sync::{Mutex, oneshot},
     task::JoinHandle,
     time::{Duration, sleep},
 };
+use utoipa::OpenApi;
+
+/// Live feed of the test masters, the tests publish into it
+static STREAM: LazyLock<StreamSender> = LazyLock::new(stream::channel);
 
 struct TestMaster {
     cfg: MasterConfig,
     queries: Arc<Mutex<Vec<String>>>,
     datastore: Arc<Mutex<DataStorage>>,
+    _root: TempDir,
 }
 
 #[async_trait]
@@ -26,21 +51,131 @@ impl MasterInterface for TestMaster {
         &self.cfg
     }
 
-    async fn query(&mut self, query: String) -> Result<(), libcommon::SysinspectError> {
+    async fn query(
+        &mut self, query: String, _ttl: Option<u64>, _supersede: Option<String>,
+    ) -> Result<String, libcommon::SysinspectError> {
         self.queries.lock().await.push(query);
-        Ok(())
+        Ok("cycle-1".to_string())
     }
 
     async fn datastore(&self) -> Arc<Mutex<DataStorage>> {
         Arc::clone(&self.datastore)
     }
+
+    async fn commands(
+        &self, _mid: Option<String>,
+    ) -> Result<Vec<libsysinspect::console::ConsoleQueuedCommandRow>, libcommon::SysinspectError> {
+        Ok(vec![])
+    }
+
+    async fn cycles(&self) -> Result<Vec<CycleInfo>, libcommon::SysinspectError> {
+        Ok((1..=3)
+            .map(|n| CycleInfo {
+                cycle_id: format!("cycle-{n}"),
+                query: "cm/file-ops;*".to_string(),
+                started_at: String::new(),
+                model_version: Some(n),
+                model_hash: None,
+            })
+            .collect())
+    }
+
+    async fn cycle_minions(&self, cycle_id: &str) -> Result<Option<Vec<CycleMinionInfo>>, libcommon::SysinspectError> {
+        if cycle_id != "cycle-1" {
+            return Ok(None);
+        }
+
+        Ok(Some(vec![CycleMinionInfo {
+            minion_id: "m1".to_string(),
+            hostname: "m1.example.com".to_string(),
+            ipaddr: "10.0.0.1".to_string(),
+            events: 2,
+            errors: 1,
+            outcomes: vec!["error".to_string(), "success".to_string()],
+        }]))
+    }
+
+    async fn cycle_events(
+        &self, cycle_id: &str, mid: &str,
+    ) -> Result<Option<Vec<CycleEventInfo>>, libcommon::SysinspectError> {
+        if cycle_id != "cycle-1" || mid != "m1" {
+            return Ok(None);
+        }
+
+        let event = |entity: &str, retcode: i64| {
+            CycleEventInfo::new(
+                cycle_id.to_string(),
+                entity.to_string(),
+                "check".to_string(),
+                "$".to_string(),
+                String::new(),
+                Default::default(),
+                [("retcode".to_string(), serde_json::json!(retcode))].into_iter().collect(),
+            )
+        };
+        Ok(Some(vec![event("file", 0), event("pkg", 1)]))
+    }
+
+    async fn cluster_command(
+        &self, query: ConsoleQuery,
+    ) -> Result<oneshot::Receiver<ConsoleResponse>, libcommon::SysinspectError> {
+        self.queries.lock().await.push(format!("{};{};{};{}", query.model, query.query, query.mid, query.context));
+
+        let response = if query.mid == "unknown" {
+            ConsoleResponse::err("Unable to find minion unknown")
+        } else if query.model == "cmd://cluster/minion/online" {
+            ConsoleResponse::ok(ConsolePayload::OnlineMinions {
+                rows: vec![ConsoleOnlineMinionRow {
+                    fqdn: "m1.example.com".to_string(),
+                    hostname: "m1".to_string(),
+                    ip: "10.0.0.1".to_string(),
+                    minion_id: "m1".to_string(),
+                    alive: true,
+                    version: "0.4.0".to_string(),
+                    target_version: String::new(),
+                    outdated: false,
+                    upgrade_required: false,
+                    upgrade_unreachable: false,
+                    os_distribution: String::new(),
+                    os_name: "linux".to_string(),
+                    os_version: String::new(),
+                    kernel: String::new(),
+                    sensors: vec![],
+                }],
+            })
+        } else {
+            ConsoleResponse::ok(ConsolePayload::Ack {
+                action: "accepted_console_command".to_string(),
+                target: query.model,
+                count: 1,
+                items: vec![],
+            })
+        };
+
+        let (tx, rx) = oneshot::channel();
+        let _ = tx.send(response);
+        Ok(rx)
+    }
+
+    async fn minion_traits(
+        &self, mid: &str,
+    ) -> Result<Option<std::collections::HashMap<String, serde_json::Value>>, libcommon::SysinspectError> {
+        Ok((mid == "m1").then(|| [("rack".to_string(), serde_json::json!("r12"))].into_iter().collect()))
+    }
+
+    async fn events(&self) -> tokio::sync::broadcast::Receiver<StreamEvent> {
+        STREAM.subscribe()
+    }
 }
 
 fn write_cfg(root: &Path) -> MasterConfig {
     let cfg_path = root.join("sysinspect.conf");
     fs::write(
         &cfg_path,
-        "config:\n  master:\n    fileserver.models: [cm, net]\n    api.bind.ip: 127.0.0.1\n    api.bind.port: 4202\n    api.devmode: true\n",
+        format!(
+            "config:\n  master:\n    root: {}\n    fileserver.models: [cm, net]\n    api.bind.ip: 127.0.0.1\n    api.bind.port: 4202\n    api.devmode: true\n",
+            root.display()
+        ),
     )
     .unwrap();
     MasterConfig::new(cfg_path).unwrap()
@@ -53,10 +188,10 @@ async fn spawn_http_server() -> (String, Arc<Mutex<Vec<String>>>, JoinHandle<std
     let datastore =
         Arc::new(Mutex::new(DataStorage::new(DataStorageConfig::new(), root.path().join("datastore")).unwrap()));
     let master: MasterInterfaceType =
-        Arc::new(Mutex::new(TestMaster { cfg, queries: Arc::clone(&queries), datastore }));
+        Arc::new(Mutex::new(TestMaster { cfg, queries: Arc::clone(&queries), datastore, _root: root }));
     let server = HttpServer::new(move || {
         let scope = api::get(true, true, ApiVersions::V1).unwrap().load(web::scope(""));
-        App::new().app_data(web::Data::new(master.clone())).service(scope)
+        App::new().app_data(web::Data::new(master.clone())).wrap(from_fn(audit_middleware)).service(scope)
     })
     .bind(("127.0.0.1", 0))
     .unwrap();
@@ -67,10 +202,50 @@ async fn spawn_http_server() -> (String, Arc<Mutex<Vec<String>>>, JoinHandle<std
     (format!("http://{}", addr), queries, handle)
 }
 
+fn config(base: String) -> SysClientConfiguration {
+    SysClientConfiguration { master_url: base, ..Default::default() }
+}
+
+async fn dev_client(base: String) -> SysClient {
+    let mut client = SysClient::new(config(base));
+    client.authenticate("dev", "dev").await.unwrap();
+    client
+}
+
+#[test]
+fn client_covers_every_documented_endpoint() {
+    let documented = ApiDoc::openapi()
+        .paths
+        .paths
+        .iter()
+        .flat_map(|(path, item)| {
+            [
+                ("GET", item.get.is_some()),
+                ("POST", item.post.is_some()),
+                ("PUT", item.put.is_some()),
+                ("PATCH", item.patch.is_some()),
+                ("DELETE", item.delete.is_some()),
+            ]
+            .into_iter()
+            .filter(|(_, present)| *present)
+            .map(|(method, _)| format!("{method} {path}"))
+            .collect::<Vec<_>>()
+        })
+        .collect::<BTreeSet<_>>();
+    let covered = endpoints::ALL
+        .iter()
+        .chain(endpoints::EXCLUDED)
+        .map(|e| format!("{} {}", e.method, e.path))
+        .collect::<BTreeSet<_>>();
+
+    assert_eq!(covered.len(), endpoints::ALL.len() + endpoints::EXCLUDED.len(), "endpoint listed twice");
+    assert_eq!(documented, covered);
+}
+
 #[tokio::test]
 async fn client_authenticates_and_executes_plain_json_query() {
     let (base, queries, handle) = spawn_http_server().await;
-    let mut client = SysClient::new(SysClientConfiguration { master_url: base });
+    let mut client = SysClient::new(config(base));
 
     let token = client.authenticate("dev", "dev").await.unwrap();
     let response: QueryResponse =
@@ -78,6 +253,7 @@ async fn client_authenticates_and_executes_plain_json_query() {
 
     assert_eq!(token, "dev-token");
     assert_eq!(response.status, "success");
+    assert_eq!(response.cycle_id.as_deref(), Some("cycle-1"));
     assert_eq!(queries.lock().await.as_slice(), ["cm/file-ops;*;;;reason:test"]);
     handle.abort();
 }
@@ -85,11 +261,308 @@ async fn client_authenticates_and_executes_plain_json_query() {
 #[tokio::test]
 async fn client_lists_models_using_bearer_auth() {
     let (base, _, handle) = spawn_http_server().await;
-    let mut client = SysClient::new(SysClientConfiguration { master_url: base });
-    client.authenticate("dev", "dev").await.unwrap();
+    let client = dev_client(base).await;
 
     let models: ModelNameResponse = client.models().await.unwrap();
 
     assert_eq!(models.models, vec!["cm".to_string(), "net".to_string()]);
     handle.abort();
 }
+
+#[tokio::test]
+async fn client_checks_health_without_authentication() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = SysClient::new(config(base));
+
+    let health = client.health().await.unwrap();
+
+    assert_eq!(health.status, "healthy");
+    assert_eq!(health.info.api_version, "1");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_reads_cycle_results() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+
+    let cycles = client.cycles(&CyclePageQuery { offset: None, limit: Some(2) }).await.unwrap();
+    assert_eq!(cycles.total, 3);
+    assert_eq!(cycles.cycles.len(), 2);
+    assert_eq!(cycles.cycles[0].model_version, Some(3));
+
+    let minions = client
+        .cycle_minions("cycle-1", &CycleMinionsQuery { outcome: Some("error".to_string()), ..Default::default() })
+        .await
+        .unwrap();
+    assert_eq!(minions.minions[0].minion_id, "m1");
+
+    let events = client
+        .cycle_events("cycle-1", "m1", &CycleEventsQuery { entity: Some("pkg".to_string()), ..Default::default() })
+        .await
+        .unwrap();
+    assert_eq!(events.total, 1);
+    assert_eq!(events.events[0].outcome, "error");
+
+    let err = client.cycle_minions("nope", &CycleMinionsQuery::default()).await.unwrap_err().to_string();
+    assert!(err.contains("404"), "{err}");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_streams_live_events() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+
+    let mut events =
+        client.events(&StreamFilter { cycle_id: Some("client-sse".to_string()), ..Default::default() }).await.unwrap();
+    _ = STREAM.send(StreamEvent::new(StreamEventKind::MinionOnline, String::new()).minion("m1"));
+    _ = STREAM.send(StreamEvent::new(StreamEventKind::CycleComplete, String::new()).cycle("client-sse").minion("m1"));
+
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(event.kind, StreamEventKind::CycleComplete);
+    assert_eq!(event.minion_id.as_deref(), Some("m1"));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_manages_the_fleet() {
+    let (base, queries, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+
+    let minions = client.minions(&MinionSelector { query: "m*".to_string(), ..Default::default() }).await.unwrap();
+    assert_eq!(minions.minions[0].minion_id, "m1");
+    assert!(minions.minions[0].alive);
+
+    let traits = serde_json::json!({"rack": "r1"}).as_object().unwrap().clone();
+    let ack = client
+        .set_traits(&TraitsSetRequest {
+            selector: MinionSelector { mid: "m1".to_string(), ..Default::default() },
+            traits,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ack.target, "cmd://cluster/traits/update");
+
+    let err = client.remove_minion("unknown", false).await.unwrap_err().to_string();
+    assert!(err.contains("Unable to find minion unknown"), "{err}");
+
+    assert_eq!(
+        queries.lock().await.as_slice(),
+        [
+            "cmd://cluster/minion/online;m*;;",
+            r#"cmd://cluster/traits/update;*;m1;{"op":"set","traits":{"rack":"r1"}}"#,
+            "cmd://cluster/minion/remove;*;unknown;",
+        ]
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_uploads_and_downloads_datastore_objects() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+    let dst = tempfile::tempdir().unwrap();
+
+    let stored = client.store_upload(b"hello datastore", Some("/etc/motd")).await.unwrap();
+    assert_eq!(stored.size_bytes, 15);
+    assert_eq!(stored.fname.as_deref(), Some("/etc/motd"));
+
+    assert_eq!(client.store_meta(&stored.sha256).await.unwrap().sha256, stored.sha256);
+    assert_eq!(client.store_resolve("/etc/motd").await.unwrap().sha256, stored.sha256);
+    assert_eq!(
+        client
+            .store_list(&StoreListQuery { prefix: Some("/etc".to_string()), ..Default::default() })
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(client.store_blob(&stored.sha256).await.unwrap(), b"hello datastore");
+
+    let tags = [("team".to_string(), "web".to_string())].into_iter().collect();
+    let web = client.store_upload_to(b"hello web", Some("/etc/motd"), Some("web"), &tags).await.unwrap();
+    assert_eq!(web.namespace.as_deref(), Some("web"));
+    assert_eq!(client.store_resolve_in("web", "/etc/motd").await.unwrap().sha256, web.sha256);
+    let query =
+        StoreListQuery { namespace: Some("web".to_string()), tags: Some("team=web".to_string()), ..Default::default() };
+    assert_eq!(client.store_list(&query).await.unwrap().len(), 1);
+
+    let path = dst.path().join("motd");
+    assert_eq!(client.store_download(&stored.sha256, &path).await.unwrap(), 15);
+    assert_eq!(fs::read(&path).unwrap(), b"hello datastore");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_uploads_in_chunks_and_resumes_downloads() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+    let dir = tempfile::tempdir().unwrap();
+    let data = (0..20_000u32).map(|n| (n % 251) as u8).collect::<Vec<_>>();
+    let src = dir.path().join("big.img");
+    fs::write(&src, &data).unwrap();
+
+    let stored = client.store_upload_file_chunked(&src, Some("web"), &Default::default()).await.unwrap();
+    assert_eq!(stored.size_bytes, 20_000);
+    assert_eq!(stored.namespace.as_deref(), Some("web"));
+
+    // An interrupted upload continues where the master left off
+    let rq = libwebapi::api::v1::store::StoreUploadStartRequest { size_bytes: 20_000, ..Default::default() };
+    let upload = client.store_upload_start(&rq).await.unwrap();
+    client.store_upload_chunk(&upload.upload_id, 0, &data[..7000]).await.unwrap();
+    assert!(client.store_upload_chunk(&upload.upload_id, 0, &data[..7000]).await.is_err());
+    assert_eq!(client.store_upload_resume(&upload.upload_id, &src).await.unwrap().sha256, stored.sha256);
+
+    // A partial download is completed with a range request and verified
+    let dst = dir.path().join("copy.img");
+    fs::write(dst.with_extension("tmp"), &data[..12_000]).unwrap();
+    assert_eq!(client.store_download(&stored.sha256, &dst).await.unwrap(), 20_000);
+    assert_eq!(fs::read(&dst).unwrap(), data);
+
+    // A corrupted partial download is not renamed into place
+    fs::write(dst.with_extension("tmp"), vec![0u8; 12_000]).unwrap();
+    assert!(client.store_download(&stored.sha256, &dst).await.is_err());
+    assert!(!dst.with_extension("tmp").exists());
+
+    let aborted = client.store_upload_start(&rq).await.unwrap();
+    client.store_upload_abort(&aborted.upload_id).await.unwrap();
+    assert!(client.store_upload_state(&aborted.upload_id).await.is_err());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_authenticates_with_api_token() {
+    let (base, _, handle) = spawn_http_server().await;
+    let session = dev_client(base.clone()).await;
+
+    let created = session
+        .create_token(&TokenCreateRequest { name: "ci".to_string(), scopes: vec!["read".to_string()], ttl: None })
+        .await
+        .unwrap();
+    assert_eq!(session.tokens().await.unwrap().tokens[0].name, "ci");
+
+    let client = SysClient::new(config(base)).with_token(&created.token);
+    assert_eq!(client.cycles(&CyclePageQuery::default()).await.unwrap().total, 3);
+    let err = client.query("cm/file-ops", "*", "", "", serde_json::json!({})).await.unwrap_err().to_string();
+    assert!(err.contains("403"), "{err}");
+
+    session.revoke_token("ci").await.unwrap();
+    let err = client.cycles(&CyclePageQuery::default()).await.unwrap_err().to_string();
+    assert!(err.contains("401"), "{err}");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_reads_the_audit_journal() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+    client.query("cm/file-ops", "web*", "", "", serde_json::json!({})).await.unwrap();
+    client.cycles(&CyclePageQuery::default()).await.unwrap();
+
+    let records = client.audit(&AuditQuery::default()).await.unwrap().records;
+    let actions = records.iter().map(|r| (r.actor.as_str(), r.action.as_str(), r.target.as_str())).collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [("dev", "POST /api/v1/authenticate", ""), ("dev", "POST /api/v1/query", "cm/file-ops on web*")]
+    );
+    assert!(records.iter().all(|r| r.channel == "webapi" && r.outcome == "success"));
+
+    let verified = client.audit_verify().await.unwrap();
+    assert!(verified.valid, "{}", verified.error);
+    assert_eq!(verified.records, 3);
+
+    let export = client.audit_export(&AuditQuery { limit: Some(2), ..Default::default() }).await.unwrap();
+    let exported = export.lines().map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()).collect::<Vec<_>>();
+    assert_eq!(exported.len(), 2);
+    assert_eq!(exported[1]["action"], "GET /api/v1/audit/verify");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_retries_idempotent_requests_while_master_is_unavailable() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    let server = HttpServer::new(move || {
+        let counter = Arc::clone(&counter);
+        App::new().default_service(web::to(move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    return HttpResponse::ServiceUnavailable().json(serde_json::json!({"error": "restarting"}));
+                }
+                HttpResponse::Ok().json(serde_json::json!({"models": ["cm"]}))
+            }
+        }))
+    })
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let base = format!("http://{}", server.addrs()[0]);
+    let handle = tokio::spawn(server.run());
+    sleep(Duration::from_millis(100)).await;
+
+    let retry = RetryPolicy {
+        max_retries: 3,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    };
+    let client =
+        SysClient::new(SysClientConfiguration { master_url: base.clone(), retry: retry.clone() }).with_token("t");
+    assert_eq!(client.models().await.unwrap().models, vec!["cm".to_string()]);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // Queries are not idempotent and must not be repeated
+    calls.store(0, Ordering::SeqCst);
+    let err = client.query("cm", "*", "", "", serde_json::json!({})).await.unwrap_err().to_string();
+    assert!(err.contains("restarting"), "{err}");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    calls.store(0, Ordering::SeqCst);
+    let client =
+        SysClient::new(SysClientConfiguration { master_url: base, retry: RetryPolicy::none() }).with_token("t");
+    assert!(client.models().await.is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn client_manages_model_versions() {
+    let (base, _, handle) = spawn_http_server().await;
+    let client = dev_client(base).await;
+    let src = tempfile::tempdir().unwrap();
+    let upload = |version: &str| {
+        fs::write(
+            src.path().join("model.cfg"),
+            format!("name: Net\nversion: \"{version}\"\ndescription: Network.\nmaintainer: ops\n"),
+        )
+        .unwrap();
+        ModelUploadRequest {
+            name: "net".to_string(),
+            note: Some(format!("release {version}")),
+            files: ModelBundle::from_dir(src.path()).unwrap().files,
+        }
+    };
+
+    assert_eq!(client.upload_model(&upload("1.0")).await.unwrap().version.version, 1);
+    let second = client.upload_model(&upload("2.0")).await.unwrap().version;
+    assert_eq!((second.version, second.author.as_str()), (2, "dev"));
+
+    let versions = client.model_versions("net").await.unwrap();
+    assert_eq!((versions.active, versions.versions.len()), (Some(2), 2));
+
+    let diff = client.model_diff(&ModelDiffQuery { name: "net".to_string(), from: 1, to: None }).await.unwrap();
+    assert_eq!((diff.to, diff.files[0].change.as_str()), (2, "modified"));
+    assert!(diff.files[0].lines.contains(&"+version: \"2.0\"".to_string()));
+
+    let restored =
+        client.rollback_model(&ModelRollbackRequest { name: "net".to_string(), version: None }).await.unwrap();
+    assert_eq!(restored.version.version, 1);
+    assert_eq!(client.model_versions("net").await.unwrap().active, Some(1));
+
+    let mut broken = upload("3.0");
+    broken.files.insert("model.cfg".to_string(), "e3t7IGJhZCB5YW1s".to_string());
+    let err = client.upload_model(&broken).await.unwrap_err().to_string();
+    assert!(err.contains("not valid"), "{err}");
+    assert!(client.model_versions("missing").await.is_err());
+    handle.abort();
+}
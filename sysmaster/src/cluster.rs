@@ -1,19 +1,34 @@
 // Cluster management for sysmaster
 use crate::registry::{mreg::MinionRegistry, session::SessionKeeper, taskreg::TaskRegistry};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use globset::Glob;
 use indexmap::IndexMap;
 use libcommon::SysinspectError;
 use libsysinspect::{
-    cfg::mmconf::ClusteredMinion,
+    cfg::mmconf::{ClusterLoadMetric, ClusterPlacement, ClusterPlacementStrategy, ClusteredMinion},
+    context,
     traits::{self, systraits::SystemTraits},
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::hash::Hash;
-use std::{collections::HashMap, sync::Arc, vec};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    vec,
+};
 use tokio::sync::Mutex;
 
+#[cfg(test)]
+#[path = "cluster_ut.rs"]
+mod cluster_ut;
+
 const DEFAULT_TASK_JITTER: usize = 3; // Task tolerance
+const PLACEMENT_LOG_SIZE: usize = 256; // Amount of placement decisions kept for audit
 
 #[derive(Debug, Clone, Default)]
 /// Representation of a clustered node
@@ -96,6 +111,7 @@ pub struct VirtualMinion {
     hostnames: Vec<String>,
     traits: SystemTraits,
     minions: HashMap<String, ClusterNode>, // Configured physical minions
+    placement: ClusterPlacement,
 }
 impl VirtualMinion {
     /// Match hostname with glob pattern
@@ -115,6 +131,54 @@ impl VirtualMinion {
     }
 }
 
+/// Audit record of one placement decision for a virtual minion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacementDecision {
+    timestamp: DateTime<Utc>,
+    vmid: String,
+    strategy: ClusterPlacementStrategy,
+    candidates: Vec<String>,
+    selected: Vec<String>,
+    reason: String,
+}
+
+impl PlacementDecision {
+    fn new(vmid: &str, strategy: ClusterPlacementStrategy, candidates: &[String], selected: &[String], reason: &str) -> Self {
+        PlacementDecision {
+            timestamp: Utc::now(),
+            vmid: vmid.to_string(),
+            strategy,
+            candidates: candidates.to_vec(),
+            selected: selected.to_vec(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// One-line representation for console output
+    pub fn format(&self) -> String {
+        format!(
+            "{} {} [{}] selected: {} of {} ({})",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.vmid,
+            self.strategy,
+            if self.selected.is_empty() { "<none>".to_string() } else { self.selected.join(", ") },
+            self.candidates.len(),
+            self.reason
+        )
+    }
+}
+
+/// Stable FNV-1a hash, used for consistent (rendezvous) hashing of context keys.
+/// It must not change between master restarts, therefore `DefaultHasher` is not used.
+fn stable_hash(data: &str) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in data.as_bytes() {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
 #[derive(Debug, Clone)]
 pub struct VirtualMinionsCluster {
     mreg: Arc<Mutex<MinionRegistry>>,
@@ -123,20 +187,83 @@ pub struct VirtualMinionsCluster {
     task_tracker: Arc<Mutex<TaskRegistry>>,
     virtual_minions: Vec<VirtualMinion>, // Configured clustered minions
     task_tolerance: usize,
+    rr_cursors: Arc<Mutex<HashMap<String, usize>>>, // Round-robin position per virtual minion
+    decisions: Arc<Mutex<VecDeque<PlacementDecision>>>,
+    log: Option<PathBuf>,         // File keeping the decisions over restarts
+    log_lines: Arc<Mutex<usize>>, // Lines in the placement log, also serialising its writes
 }
 
 impl VirtualMinionsCluster {
     pub fn new(
         cfg: Vec<ClusteredMinion>, mreg: Arc<Mutex<MinionRegistry>>, session: Arc<Mutex<SessionKeeper>>, task_tracker: Arc<Mutex<TaskRegistry>>,
     ) -> VirtualMinionsCluster {
-        VirtualMinionsCluster { virtual_minions: Vec::new(), mreg, cfg, session, task_tracker, task_tolerance: DEFAULT_TASK_JITTER }
+        VirtualMinionsCluster {
+            virtual_minions: Vec::new(),
+            mreg,
+            cfg,
+            session,
+            task_tracker,
+            task_tolerance: DEFAULT_TASK_JITTER,
+            rr_cursors: Arc::new(Mutex::new(HashMap::new())),
+            decisions: Arc::new(Mutex::new(VecDeque::new())),
+            log: None,
+            log_lines: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Keep placement decisions in a file, loading the ones recorded before
+    pub fn with_log(mut self, path: PathBuf) -> Self {
+        match Self::load_decisions(&path) {
+            Ok((decisions, lines)) => {
+                self.decisions = Arc::new(Mutex::new(decisions));
+                self.log_lines = Arc::new(Mutex::new(lines));
+            }
+            Err(err) => log::error!("Unable to load placement decisions from {}: {err}", path.display()),
+        }
+        self.log = Some(path);
+        self
     }
 
-    fn filter_traits(&self, traits: &HashMap<String, Value>) -> HashMap<String, Value> {
+    /// Read a placement log, one JSON record per line, and count its lines. Unreadable lines are skipped.
+    fn load_decisions(path: &Path) -> Result<(VecDeque<PlacementDecision>, usize), SysinspectError> {
+        if !path.exists() {
+            return Ok((VecDeque::new(), 0));
+        }
+        let data = fs::read_to_string(path)?;
+        let mut decisions: VecDeque<PlacementDecision> = data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+        while decisions.len() > PLACEMENT_LOG_SIZE {
+            decisions.pop_front();
+        }
+        Ok((decisions, data.lines().count()))
+    }
+
+    /// Append one decision to the placement log
+    fn append_decision(path: &Path, decision: &PlacementDecision) -> Result<(), SysinspectError> {
+        let mut line = serde_json::to_string(decision)?;
+        line.push('\n');
+        fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Replace the placement log with the current decisions
+    fn save_decisions(path: &Path, decisions: &VecDeque<PlacementDecision>) -> Result<(), SysinspectError> {
+        let mut data = String::new();
+        for d in decisions {
+            data.push_str(&serde_json::to_string(d)?);
+            data.push('\n');
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Keep only traits needed for the node matching: hostnames and affinity keys
+    fn filter_traits(&self, traits: &HashMap<String, Value>, extra: &[String]) -> HashMap<String, Value> {
         let mut filtered = HashMap::new();
         let tk = ["system.hostname", "system.hostname.fqdn", "system.hostname.ip"];
         for (k, v) in traits.iter() {
-            if tk.contains(&k.as_str()) {
+            if tk.contains(&k.as_str()) || extra.contains(k) {
                 filtered.insert(k.clone(), v.clone());
             }
         }
@@ -159,6 +286,7 @@ impl VirtualMinionsCluster {
                 Some(t) => t.clone().into_iter().map(|(k, v)| (k, serde_json::to_value(v).unwrap_or(Value::Null))).collect(),
                 None => HashMap::new(),
             };
+            let affinity_keys: Vec<String> = m.placement().traits().map(|t| t.keys().cloned().collect()).unwrap_or_default();
 
             for node_scope in m.nodes().iter() {
                 // Selectors
@@ -175,7 +303,8 @@ impl VirtualMinionsCluster {
                     for mm in mreg.get_by_hostname_or_ip(hn_sel)?.iter() {
                         if !nodes.contains_key(mm.id()) {
                             log::debug!("  Matched minion for clustered node by hostname {}: {:?}", hn_sel, mm.id());
-                            nodes.insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone())));
+                            nodes
+                                .insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone(), &affinity_keys)));
                         }
                     }
                 }
@@ -186,7 +315,7 @@ impl VirtualMinionsCluster {
                     && !nodes.contains_key(mm.id())
                 {
                     log::debug!("  Matched minion for clustered node by ID {}: {:?}", id_sel, mm.id());
-                    nodes.insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone())));
+                    nodes.insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone(), &affinity_keys)));
                 }
 
                 // Get minion records matching the query glob pattern (hostname)
@@ -194,7 +323,8 @@ impl VirtualMinionsCluster {
                     for mm in mreg.get_by_query(qr_sel)?.iter() {
                         if !nodes.contains_key(mm.id()) {
                             log::debug!("  Matched minion for clustered node by query {}: {:?}", qr_sel, mm.id());
-                            nodes.insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone())));
+                            nodes
+                                .insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone(), &affinity_keys)));
                         }
                     }
                 }
@@ -203,7 +333,8 @@ impl VirtualMinionsCluster {
                     for mm in mreg.get_by_traits(tr_sel.clone())?.iter() {
                         if !nodes.contains_key(mm.id()) {
                             log::debug!("  Matched minion for clustered node by traits {:?}: {:?}", tr_sel, mm.id());
-                            nodes.insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone())));
+                            nodes
+                                .insert(mm.id().to_string(), ClusterNode::new(mm.id(), self.filter_traits(&mm.get_traits().clone(), &affinity_keys)));
                         }
                     }
                 }
@@ -220,6 +351,7 @@ impl VirtualMinionsCluster {
                     hostnames: vec![m.hostname().clone()], // XXX: one for now
                     traits: SystemTraits::from_map(cm_traits),
                     minions: nodes,
+                    placement: m.placement().clone(),
                 });
             } else {
                 log::warn!("Clustered minion {} dismissed as it has no matched physical nodes", m.hostname().bright_yellow().bold());
@@ -262,9 +394,9 @@ impl VirtualMinionsCluster {
         vmids
     }
 
-    /// Decide the best-fit minion for a task based on current load and I/O pressure.
-    /// Returns a list of FQDN hostnames of selected minions one per a virtual minion.
-    pub async fn decide(&self, query: &str, traits: &str) -> Option<Vec<String>> {
+    /// Decide the best-fit minion(s) for a task according to the placement policy of each virtual minion.
+    /// Returns a list of FQDN hostnames of selected minions, usually one per a virtual minion.
+    pub async fn decide(&self, query: &str, traits: &str, context: &str) -> Option<Vec<String>> {
         let mut tpq: Option<Vec<Vec<IndexMap<String, Value>>>> = None;
         if !traits.is_empty() {
             match traits::parse_traits_query(traits) {
@@ -280,6 +412,7 @@ impl VirtualMinionsCluster {
                 Err(e) => log::error!("{e}"),
             };
         }
+        let context = context::get_context(context).unwrap_or_default();
 
         // Get virtual minion IDs matching the query
         let mut mids: Vec<String> = vec![];
@@ -292,21 +425,22 @@ impl VirtualMinionsCluster {
                 log::debug!("Virtual Minion {} was dropped as it does not match the traits", v.id.bright_yellow().bold());
                 continue;
             }
-            if let Some(hn) = self.decide_one_vminion(v.clone()).await {
-                mids.push(hn);
-            } else {
+            let hns = self.decide_one_vminion(v.clone(), &context).await;
+            if hns.is_empty() {
                 log::warn!("  No suitable minion found for virtual minion ID: {}", v.id.bright_yellow().bold());
             }
+            mids.extend(hns);
         }
 
         if mids.is_empty() { None } else { Some(mids) }
     }
 
-    async fn decide_one_vminion(&self, vmin: VirtualMinion) -> Option<String> {
-        let mids = vmin.minions.iter().map(|m| m.1.mid.clone()).collect::<Vec<String>>();
+    async fn decide_one_vminion(&self, vmin: VirtualMinion, context: &IndexMap<String, Value>) -> Vec<String> {
+        let mut mids = vmin.minions.iter().map(|m| m.1.mid.clone()).collect::<Vec<String>>();
         if mids.is_empty() {
-            return None;
+            return vec![];
         }
+        mids.sort(); // Stable order for round-robin and hashing
 
         // find alive minions
         let mut alive: Vec<String> = Vec::new();
@@ -319,49 +453,117 @@ impl VirtualMinionsCluster {
             }
         }
 
-        if alive.is_empty() {
-            return None;
-        }
+        let (selected, reason) = if alive.is_empty() {
+            (vec![], "no alive members".to_string())
+        } else {
+            let tasks: HashMap<String, usize> = {
+                let tracker = self.task_tracker.lock().await;
+                alive.iter().map(|mid| (mid.clone(), tracker.minion_tasks(mid).len())).collect()
+            };
+            let cursor = if vmin.placement.strategy() == ClusterPlacementStrategy::RoundRobin {
+                let mut cursors = self.rr_cursors.lock().await;
+                let c = cursors.entry(vmin.id.clone()).or_insert(0);
+                let current = *c;
+                *c = c.wrapping_add(1);
+                current
+            } else {
+                0
+            };
+            self.place(&vmin, &alive, &tasks, context, cursor)
+        };
 
-        // get IO rates
-        let mut rates: Vec<(String, f64)> = Vec::with_capacity(alive.len());
-        for mid in alive.iter() {
-            let mut bps = 0.0;
-            'outer: for vm in self.virtual_minions.iter() {
-                for m in vm.minions.iter() {
-                    if m.1.mid == *mid {
-                        bps = m.1.io_bps;
-                        break 'outer;
-                    }
+        let decision = PlacementDecision::new(&vmin.id, vmin.placement.strategy(), &alive, &selected, &reason);
+        log::info!("Placement for virtual minion {}: {}", vmin.id.bright_yellow(), decision.format());
+        self.record(decision).await;
+
+        let mut hostnames: Vec<String> = Vec::new();
+        for fmid in selected.iter() {
+            let mrec = match self.mreg.lock().await.get(fmid) {
+                Ok(r) => r,
+                Err(err) => {
+                    log::error!("Unable to get minion record for {fmid}: {err}");
+                    continue;
                 }
+            };
+            if let Some(hn) = mrec.and_then(|rec| rec.get_traits().get("system.hostname.fqdn").and_then(|v| v.as_str()).map(|s| s.to_string())) {
+                hostnames.push(hn);
             }
-            rates.push((mid.clone(), bps.max(0.0)));
         }
 
-        let weights: HashMap<String, f64> = Self::normalise_weights_percent(&rates);
+        hostnames
+    }
 
-        // minimum task count and lowest disk weight
-        let tracker = self.task_tracker.lock().await;
+    /// Select physical minion IDs out of alive candidates according to the placement policy.
+    /// Returns selected minion IDs and the reason of the decision.
+    fn place(
+        &self, vmin: &VirtualMinion, alive: &[String], tasks: &HashMap<String, usize>, context: &IndexMap<String, Value>, cursor: usize,
+    ) -> (Vec<String>, String) {
+        let placement = &vmin.placement;
+        match placement.strategy() {
+            ClusterPlacementStrategy::All => (alive.to_vec(), format!("all {} alive members", alive.len())),
+            ClusterPlacementStrategy::RoundRobin => {
+                let mid = alive[cursor % alive.len()].clone();
+                (vec![mid], format!("round-robin position {}", cursor % alive.len()))
+            }
+            ClusterPlacementStrategy::LeastIo => self.pick_least_io(alive, tasks),
+            ClusterPlacementStrategy::LeastLoaded => self.pick_least_loaded(alive, tasks, placement.metric()),
+            ClusterPlacementStrategy::Hashed => {
+                let key = placement.key().cloned().unwrap_or_default();
+                let Some(kv) = context.get(&key) else {
+                    let (sel, reason) = self.pick_least_io(alive, tasks);
+                    return (sel, format!("context key \"{key}\" missing, fallback to {reason}"));
+                };
+                let kv = match kv {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                };
 
-        let mut min_tasks = usize::MAX;
-        for mid in alive.iter() {
-            min_tasks = min_tasks.min(tracker.minion_tasks(mid).len());
+                // Rendezvous hashing: only keys of a gone member move elsewhere
+                let mid = alive.iter().max_by_key(|mid| stable_hash(&format!("{kv}/{mid}"))).cloned().unwrap_or_default();
+                (vec![mid], format!("{key}={kv} hashed"))
+            }
+            ClusterPlacementStrategy::Affinity => {
+                let affinity: HashMap<String, Value> = placement
+                    .traits()
+                    .map(|t| t.iter().map(|(k, v)| (k.clone(), serde_json::to_value(v).unwrap_or(Value::Null))).collect())
+                    .unwrap_or_default();
+                let preferred: Vec<String> =
+                    alive.iter().filter(|mid| vmin.minions.get(*mid).map(|n| n.matches_traits(&affinity)).unwrap_or(false)).cloned().collect();
+                if preferred.is_empty() {
+                    let (sel, reason) = self.pick_least_io(alive, tasks);
+                    (sel, format!("no member matches affinity traits, fallback to {reason}"))
+                } else {
+                    let (sel, reason) = self.pick_least_io(&preferred, tasks);
+                    (sel, format!("{} of {} members match affinity traits, {reason}", preferred.len(), alive.len()))
+                }
+            }
         }
+    }
+
+    /// Find stats of a physical minion, no matter where it belongs to
+    fn node(&self, mid: &str) -> Option<&ClusterNode> {
+        self.virtual_minions.iter().flat_map(|vm| vm.minions.values()).find(|m| m.mid == mid)
+    }
+
+    /// Drop candidates which have too many running tasks in comparison to the least busy one
+    fn within_task_cutoff(&self, alive: &[String], tasks: &HashMap<String, usize>) -> Vec<String> {
+        let min_tasks = alive.iter().map(|mid| *tasks.get(mid).unwrap_or(&0)).min().unwrap_or(0);
         let cutoff = min_tasks.saturating_add(self.task_tolerance);
+        alive.iter().filter(|mid| *tasks.get(*mid).unwrap_or(&0) <= cutoff).cloned().collect()
+    }
+
+    /// Lowest disk write weight, then fewer tasks if weights tie
+    fn pick_least_io(&self, alive: &[String], tasks: &HashMap<String, usize>) -> (Vec<String>, String) {
+        let rates: Vec<(String, f64)> = alive.iter().map(|mid| (mid.clone(), self.node(mid).map(|n| n.io_bps).unwrap_or(0.0).max(0.0))).collect();
+        let weights: HashMap<String, f64> = Self::normalise_weights_percent(&rates);
 
         let mut best_mid: Option<String> = None;
         let mut best_weight: f64 = f64::MAX;
         let mut best_tasks: usize = usize::MAX;
 
-        for mid in alive.iter() {
-            let tasks = tracker.minion_tasks(mid).len();
-            if tasks > cutoff {
-                continue;
-            }
-
+        for mid in self.within_task_cutoff(alive, tasks).iter() {
+            let tasks = *tasks.get(mid).unwrap_or(&0);
             let w = *weights.get(mid).unwrap_or(&0.0);
-
-            // lower disk weight, then fewer tasks if weights tie
             if w < best_weight || (w == best_weight && tasks < best_tasks) {
                 best_weight = w;
                 best_tasks = tasks;
@@ -369,16 +571,86 @@ impl VirtualMinionsCluster {
             }
         }
 
-        let fmid = best_mid?;
-        let mrec = match self.mreg.lock().await.get(&fmid) {
-            Ok(r) => r,
-            Err(err) => {
-                log::error!("Unable to get minion record for {fmid}: {err}");
-                return None;
+        match best_mid {
+            Some(mid) => (vec![mid], format!("least I/O: {best_weight:.1}% of writes, {best_tasks} tasks")),
+            None => (vec![], "all members are over the task tolerance".to_string()),
+        }
+    }
+
+    /// Lowest CPU usage or load average, then fewer tasks if values tie
+    fn pick_least_loaded(&self, alive: &[String], tasks: &HashMap<String, usize>, metric: ClusterLoadMetric) -> (Vec<String>, String) {
+        let mut best_mid: Option<String> = None;
+        let mut best_load: f32 = f32::MAX;
+        let mut best_tasks: usize = usize::MAX;
+
+        for mid in self.within_task_cutoff(alive, tasks).iter() {
+            let tasks = *tasks.get(mid).unwrap_or(&0);
+            let load = self
+                .node(mid)
+                .map(|n| match metric {
+                    ClusterLoadMetric::Cpu => n.cpu_usage,
+                    ClusterLoadMetric::Load => n.load_average,
+                })
+                .unwrap_or(0.0);
+            if load < best_load || (load == best_load && tasks < best_tasks) {
+                best_load = load;
+                best_tasks = tasks;
+                best_mid = Some(mid.clone());
             }
+        }
+
+        let metric = match metric {
+            ClusterLoadMetric::Cpu => "CPU",
+            ClusterLoadMetric::Load => "load average",
         };
+        match best_mid {
+            Some(mid) => (vec![mid], format!("least loaded: {metric} {best_load:.2}, {best_tasks} tasks")),
+            None => (vec![], "all members are over the task tolerance".to_string()),
+        }
+    }
+
+    /// Keep the decision in memory, dropping the oldest one. Returns all kept decisions, if asked to.
+    async fn keep(&self, decision: PlacementDecision, snapshot: bool) -> Option<VecDeque<PlacementDecision>> {
+        let mut decisions = self.decisions.lock().await;
+        if decisions.len() >= PLACEMENT_LOG_SIZE {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+        snapshot.then(|| decisions.clone())
+    }
+
+    /// Store the decision for audit, dropping the oldest ones.
+    ///
+    /// The decision is appended to the placement log. Once the log has twice as many lines
+    /// as decisions are kept, it is rewritten with the kept ones.
+    async fn record(&self, decision: PlacementDecision) {
+        let Some(path) = &self.log else {
+            self.keep(decision, false).await;
+            return;
+        };
+
+        // Writers take turns, so the log keeps the order of the decisions
+        let mut lines = self.log_lines.lock().await;
+        let kept = self.keep(decision.clone(), *lines >= PLACEMENT_LOG_SIZE * 2).await;
+        let (target, n) = (path.clone(), *lines);
+        let written = tokio::task::spawn_blocking(move || match kept {
+            Some(decisions) => Self::save_decisions(&target, &decisions).map(|_| decisions.len()),
+            None => Self::append_decision(&target, &decision).map(|_| n + 1),
+        })
+        .await
+        .map_err(|err| SysinspectError::MasterGeneralError(err.to_string()))
+        .and_then(|written| written);
+
+        match written {
+            Ok(n) => *lines = n,
+            Err(err) => log::error!("Unable to save placement decisions to {}: {err}", path.display()),
+        }
+    }
 
-        mrec.and_then(|rec| rec.get_traits().get("system.hostname.fqdn").and_then(|v| v.as_str()).map(|s| s.to_string()))
+    /// Get recorded placement decisions of virtual minions matching the query, oldest first
+    pub async fn decisions(&self, query: &str) -> Vec<PlacementDecision> {
+        let vmids: Vec<String> = self.query_vminions(if query.is_empty() { "*" } else { query }).iter().map(|vm| vm.id.clone()).collect();
+        self.decisions.lock().await.iter().filter(|d| vmids.contains(&d.vmid)).cloned().collect()
     }
 
     /// Update a physical minion stats, no matter where it belongs to
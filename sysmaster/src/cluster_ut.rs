@@ -0,0 +1,203 @@
+use super::{ClusterNode, PlacementDecision, VirtualMinion, VirtualMinionsCluster, stable_hash};
+use crate::registry::{mreg::MinionRegistry, session::SessionKeeper, taskreg::TaskRegistry};
+use indexmap::IndexMap;
+use libsysinspect::cfg::mmconf::{ClusterLoadMetric, ClusterPlacement, ClusterPlacementStrategy};
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+fn node(mid: &str, io_bps: f64, cpu: f32, la: f32) -> ClusterNode {
+    let mut n = ClusterNode::new(mid, HashMap::new());
+    n.set_io_bps(io_bps);
+    n.set_cpu_usage(cpu);
+    n.set_load_average(la);
+    n
+}
+
+fn vminion(placement: ClusterPlacement, nodes: Vec<ClusterNode>) -> VirtualMinion {
+    VirtualMinion {
+        id: "vm-1".to_string(),
+        hostnames: vec!["vm-1".to_string()],
+        minions: nodes.into_iter().map(|n| (n.mid.clone(), n)).collect(),
+        placement,
+        ..Default::default()
+    }
+}
+
+fn cluster(tmp: &tempfile::TempDir, vm: &VirtualMinion) -> VirtualMinionsCluster {
+    let mreg = Arc::new(Mutex::new(MinionRegistry::new(tmp.path().to_path_buf()).unwrap()));
+    let mut c = VirtualMinionsCluster::new(vec![], mreg, Arc::new(Mutex::new(SessionKeeper::new(30))), Arc::new(Mutex::new(TaskRegistry::new())));
+    c.virtual_minions = vec![vm.clone()];
+    c
+}
+
+fn alive() -> Vec<String> {
+    vec!["a".to_string(), "b".to_string(), "c".to_string()]
+}
+
+fn no_tasks() -> HashMap<String, usize> {
+    alive().into_iter().map(|m| (m, 0)).collect()
+}
+
+#[test]
+fn least_io_picks_lowest_write_rate() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(ClusterPlacement::default(), vec![node("a", 500.0, 0.0, 0.0), node("b", 10.0, 0.0, 0.0), node("c", 100.0, 0.0, 0.0)]);
+    let (sel, reason) = cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0);
+
+    assert_eq!(sel, vec!["b".to_string()]);
+    assert!(reason.starts_with("least I/O"));
+}
+
+#[test]
+fn least_io_skips_members_over_task_tolerance() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(ClusterPlacement::default(), vec![node("a", 500.0, 0.0, 0.0), node("b", 10.0, 0.0, 0.0), node("c", 100.0, 0.0, 0.0)]);
+    let mut tasks = no_tasks();
+    tasks.insert("b".to_string(), 10);
+    let (sel, _) = cluster(&tmp, &vm).place(&vm, &alive(), &tasks, &IndexMap::new(), 0);
+
+    assert_eq!(sel, vec!["c".to_string()]);
+}
+
+#[test]
+fn round_robin_rotates_by_cursor() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(ClusterPlacement::new(ClusterPlacementStrategy::RoundRobin), vec![node("a", 0.0, 0.0, 0.0), node("b", 0.0, 0.0, 0.0)]);
+    let c = cluster(&tmp, &vm);
+    let picks: Vec<String> = (0..4).flat_map(|i| c.place(&vm, &alive(), &no_tasks(), &IndexMap::new(), i).0).collect();
+
+    assert_eq!(picks, vec!["a", "b", "c", "a"]);
+}
+
+#[test]
+fn least_loaded_uses_configured_metric() {
+    let tmp = tempfile::tempdir().unwrap();
+    let nodes = vec![node("a", 0.0, 10.0, 4.0), node("b", 0.0, 80.0, 0.5), node("c", 0.0, 50.0, 2.0)];
+
+    let vm = vminion(ClusterPlacement::new(ClusterPlacementStrategy::LeastLoaded), nodes.clone());
+    assert_eq!(cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0).0, vec!["a".to_string()]);
+
+    let vm = vminion(ClusterPlacement::new(ClusterPlacementStrategy::LeastLoaded).set_metric(ClusterLoadMetric::Load), nodes);
+    assert_eq!(cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0).0, vec!["b".to_string()]);
+}
+
+#[test]
+fn hashed_is_sticky_per_context_key() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(
+        ClusterPlacement::new(ClusterPlacementStrategy::Hashed).set_key("tenant"),
+        vec![node("a", 0.0, 0.0, 0.0), node("b", 0.0, 0.0, 0.0), node("c", 0.0, 0.0, 0.0)],
+    );
+    let c = cluster(&tmp, &vm);
+    let ctx: IndexMap<String, Value> = [("tenant".to_string(), json!("acme"))].into_iter().collect();
+
+    let first = c.place(&vm, &alive(), &no_tasks(), &ctx, 0).0;
+    for i in 1..5 {
+        assert_eq!(c.place(&vm, &alive(), &no_tasks(), &ctx, i).0, first);
+    }
+
+    // Removing another member keeps the key on the same node
+    let dropped = alive().into_iter().find(|m| *m != first[0]).unwrap();
+    let remaining: Vec<String> = alive().into_iter().filter(|m| *m != dropped).collect();
+    assert_eq!(c.place(&vm, &remaining, &no_tasks(), &ctx, 0).0, first);
+}
+
+#[test]
+fn hashed_falls_back_without_context_key() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(
+        ClusterPlacement::new(ClusterPlacementStrategy::Hashed).set_key("tenant"),
+        vec![node("a", 50.0, 0.0, 0.0), node("b", 5.0, 0.0, 0.0), node("c", 20.0, 0.0, 0.0)],
+    );
+    let (sel, reason) = cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0);
+
+    assert_eq!(sel, vec!["b".to_string()]);
+    assert!(reason.contains("missing"));
+}
+
+#[test]
+fn affinity_prefers_matching_members() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut a = node("a", 0.0, 0.0, 0.0);
+    a.traits.insert("system.os.name".to_string(), json!("FreeBSD"));
+    let mut b = node("b", 900.0, 0.0, 0.0);
+    b.traits.insert("system.os.name".to_string(), json!("Ubuntu"));
+    let mut c = node("c", 100.0, 0.0, 0.0);
+    c.traits.insert("system.os.name".to_string(), json!("Ubuntu"));
+
+    let affinity: IndexMap<String, serde_yaml::Value> = [("system.os.name".to_string(), serde_yaml::Value::from("Ubuntu"))].into_iter().collect();
+    let vm = vminion(ClusterPlacement::new(ClusterPlacementStrategy::Affinity).set_traits(affinity), vec![a, b, c]);
+    let (sel, reason) = cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0);
+
+    assert_eq!(sel, vec!["c".to_string()]);
+    assert!(reason.starts_with("2 of 3"));
+}
+
+#[test]
+fn all_selects_every_alive_member() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(ClusterPlacement::new(ClusterPlacementStrategy::All), vec![node("a", 0.0, 0.0, 0.0)]);
+    let (sel, _) = cluster(&tmp, &vm).place(&vm, &alive(), &no_tasks(), &IndexMap::new(), 0);
+
+    assert_eq!(sel, alive());
+}
+
+#[test]
+fn stable_hash_does_not_change() {
+    assert_eq!(stable_hash(""), 0xcbf29ce484222325);
+    assert_eq!(stable_hash("a"), 0xaf63dc4c8601ec8c);
+}
+
+#[tokio::test]
+async fn decisions_are_recorded_and_bounded() {
+    let tmp = tempfile::tempdir().unwrap();
+    let vm = vminion(ClusterPlacement::default(), vec![node("a", 0.0, 0.0, 0.0)]);
+    let c = cluster(&tmp, &vm);
+
+    for _ in 0..(super::PLACEMENT_LOG_SIZE + 10) {
+        c.record(PlacementDecision::new("vm-1", ClusterPlacementStrategy::LeastIo, &alive(), &["a".to_string()], "test")).await;
+    }
+    c.record(PlacementDecision::new("vm-other", ClusterPlacementStrategy::All, &alive(), &alive(), "test")).await;
+
+    let recorded = c.decisions("vm-1").await;
+    assert_eq!(recorded.len(), super::PLACEMENT_LOG_SIZE - 1);
+    assert!(recorded.iter().all(|d| d.vmid == "vm-1"));
+    assert!(recorded[0].format().contains("[least-io] selected: a of 3 (test)"));
+}
+
+#[tokio::test]
+async fn decisions_survive_restart() {
+    let (tmp, state) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let log = state.path().join("placement.log");
+    let vm = vminion(ClusterPlacement::default(), vec![node("a", 0.0, 0.0, 0.0)]);
+
+    let c = cluster(&tmp, &vm).with_log(log.clone());
+    c.record(PlacementDecision::new("vm-1", ClusterPlacementStrategy::RoundRobin, &alive(), &["b".to_string()], "cursor 1")).await;
+    drop(c);
+
+    let recorded = cluster(&tmp, &vm).with_log(log).decisions("vm-1").await;
+    assert_eq!(recorded.len(), 1);
+    assert!(recorded[0].format().contains("[round-robin] selected: b of 3 (cursor 1)"));
+}
+
+#[tokio::test]
+async fn placement_log_is_appended_and_compacted() {
+    let (tmp, state) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let log = state.path().join("placement.log");
+    let vm = vminion(ClusterPlacement::default(), vec![node("a", 0.0, 0.0, 0.0)]);
+
+    let c = cluster(&tmp, &vm).with_log(log.clone());
+    for n in 0..(super::PLACEMENT_LOG_SIZE * 2) {
+        c.record(PlacementDecision::new("vm-1", ClusterPlacementStrategy::LeastIo, &alive(), &["a".to_string()], &format!("n{n}"))).await;
+    }
+    assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), super::PLACEMENT_LOG_SIZE * 2);
+
+    c.record(PlacementDecision::new("vm-1", ClusterPlacementStrategy::LeastIo, &alive(), &["a".to_string()], "last")).await;
+    assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), super::PLACEMENT_LOG_SIZE);
+    drop(c);
+
+    let recorded = cluster(&tmp, &vm).with_log(log).decisions("vm-1").await;
+    assert_eq!(recorded.len(), super::PLACEMENT_LOG_SIZE);
+    assert!(recorded.last().unwrap().format().contains("(last)"));
+}
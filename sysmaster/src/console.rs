@@ -7,22 +7,36 @@
 
 use super::*;
 
-use crate::hopstart::{HopStartTarget, HopStarter, shell_quote};
+use crate::{
+    hopstart::{HopStartTarget, HopStarter, shell_quote},
+    reboot::{RebootPhase, RebootRollout},
+};
 use libmodpak::{SysInspectModPak, mpk::ModPakRepoIndex};
 use libsysinspect::{
+    audit::{self, AUDIT_PAGE_SIZE, AuditChannel, AuditEntry, AuditFilter, AuditJournal, AuditOutcome},
     cfg::mmconf::MinionConfig,
     console::{
-        ConsoleEnvelope, ConsoleLibraryRow, ConsoleMasterLogSnapshot, ConsoleMinionInfoRow, ConsoleMinionLogRequest, ConsoleMinionLogSnapshot,
-        ConsoleMinionProcessSignalRequest, ConsoleMinionTopRequest, ConsoleMinionTopSnapshot, ConsoleMinionUpgradeSelfRequest, ConsoleModelRow,
+        ConsoleApiTokenRow, ConsoleEnvelope, ConsoleLibraryRow, ConsoleMasterLogSnapshot, ConsoleMinionInfoRow,
+        ConsoleMinionIntegrityRebaselineRequest, ConsoleMinionLogRequest, ConsoleMinionLogSnapshot, ConsoleMinionProcessSignalRequest,
+        ConsoleMinionRebootRequest, ConsoleMinionTopRequest, ConsoleMinionTopSnapshot, ConsoleMinionUpgradeSelfRequest, ConsoleModelRow,
         ConsoleModuleArgument, ConsoleModuleRow, ConsoleOnlineMinionRow, ConsolePayload, ConsoleQuery, ConsoleResponse, ConsoleSealed,
-        ConsoleTransportStatusRow, MinionCommandReply, authorised_console_client, load_master_private_key,
+        ConsoleTraitChangeRow, ConsoleTransportStatusRow, MinionCommandReply, console_client_name, load_master_private_key,
     },
     context::get_context,
-    mdescr::catalog::ModelCatalog,
+    mdescr::{
+        catalog::ModelCatalog,
+        versions::{ModelBundle, ModelStore},
+    },
+    rbac::{
+        self, Access, Principal,
+        tokens::{self, TokenOwner, TokenScope, TokenStore},
+    },
     traits::TraitSource,
 };
 use libsysproto::query::commands::{
-    CLUSTER_MARK_UPGRADE_REQUIRED, CLUSTER_MINION_TOP, CLUSTER_MINION_UPGRADE_SELF, CLUSTER_UPGRADE_MINIONS, CLUSTER_UPGRADE_STATUS,
+    CLUSTER_API_TOKENS, CLUSTER_AUDIT, CLUSTER_COMMANDS, CLUSTER_MARK_UPGRADE_REQUIRED, CLUSTER_MINION_INTEGRITY_REBASELINE, CLUSTER_MINION_TOP,
+    CLUSTER_MINION_UPGRADE_SELF, CLUSTER_MODEL_VERSIONS, CLUSTER_PLACEMENT, CLUSTER_REBOOT, CLUSTER_REBOOT_STATUS, CLUSTER_TRAITS_HISTORY,
+    CLUSTER_UPGRADE_MINIONS, CLUSTER_UPGRADE_STATUS,
 };
 use tokio::net::{TcpStream, tcp::OwnedReadHalf};
 use tokio::sync::oneshot;
@@ -41,6 +55,27 @@ const MAX_CONSOLE_FRAME_SIZE: usize = 256 * 1024;
 const CONSOLE_READ_TIMEOUT: StdDuration = StdDuration::from_secs(5);
 const CONSOLE_MINION_REPLY_TIMEOUT: StdDuration = StdDuration::from_secs(5);
 
+/// Read-only console commands that are audited only when they are denied.
+///
+/// The CLI and the TUI poll these continuously, recording every call would
+/// bury the operator actions in the audit journal.
+const UNAUDITED_CONSOLE_COMMANDS: &[&str] = &[
+    CLUSTER_ONLINE_MINIONS,
+    CLUSTER_MINION_INFO,
+    CLUSTER_MINION_LOGS,
+    CLUSTER_MINION_TOP,
+    CLUSTER_MASTER_LOGS,
+    CLUSTER_TRANSPORT_STATUS,
+    CLUSTER_REBOOT_STATUS,
+    CLUSTER_UPGRADE_STATUS,
+    CLUSTER_PLACEMENT,
+    CLUSTER_COMMANDS,
+    CLUSTER_TRAITS_HISTORY,
+    CLUSTER_MODELS,
+    CLUSTER_MODULE_INDEX,
+    CLUSTER_LIBRARY_INDEX,
+];
+
 /// Result returned by console helpers that both answer the caller and stage
 /// follow-up cluster messages that still need to be broadcast.
 type ConsoleOutcome = (ConsoleResponse, Vec<MasterMessage>);
@@ -81,6 +116,88 @@ struct MinionProcessSignalConsoleRequest {
     signal: Option<i32>,
 }
 
+/// Parsed filter for `cluster/commands` console requests.
+///
+/// Without a state every queued command of the selected minions is listed.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct QueuedCommandsConsoleRequest {
+    state: Option<String>,
+}
+
+/// Parsed `cluster/minion/integrity/rebaseline` console requests.
+///
+/// Without a sensor every `sys.integrity` sensor of the minion is rebaselined.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct IntegrityRebaselineConsoleRequest {
+    sensor: Option<String>,
+}
+
+/// Parsed filter for `cluster/traits/history` console requests.
+///
+/// Without a key every recorded trait change of the selected minions is listed.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct TraitHistoryConsoleRequest {
+    key: Option<String>,
+}
+
+/// Parsed `cluster/api-tokens` console requests.
+///
+/// `op` is one of `create`, `list` or `revoke`. Scopes are comma-separated and
+/// the expiry is a human-readable duration such as `90d`.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ApiTokenConsoleRequest {
+    op: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    scopes: String,
+    expires: Option<String>,
+}
+
+/// Parsed `cluster/audit` console requests.
+///
+/// Times are RFC 3339 or durations back from now, such as `24h`. With `verify`
+/// the whole chain is checked instead of listing records. Exports are read in
+/// pages: with `after` only the records following that sequence number are
+/// listed, as many as fit into one console response.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct AuditConsoleRequest {
+    since: Option<String>,
+    until: Option<String>,
+    actor: Option<String>,
+    limit: Option<usize>,
+    after: Option<u64>,
+    #[serde(default)]
+    verify: bool,
+}
+
+/// Parsed `cluster/model/versions` console requests.
+///
+/// `op` is one of `upload`, `list`, `diff` or `rollback`. Uploads carry the
+/// model files as a bundle. Diffs compare `from` with `to`, or with the active
+/// version. Rollbacks restore `version`, or the one before the active version.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ModelVersionConsoleRequest {
+    op: String,
+    name: String,
+    note: Option<String>,
+    bundle: Option<ModelBundle>,
+    from: Option<u64>,
+    to: Option<u64>,
+    version: Option<u64>,
+}
+
+/// Parsed options for `cluster/reboot` console requests.
+///
+/// All options are optional: one minion at a time, ten minutes to come back
+/// and one minute to drain.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RebootConsoleRequest {
+    batch: Option<usize>,
+    timeout: Option<u64>,
+    drain: Option<u64>,
+}
+
 impl MinionLogsConsoleRequest {
     fn from_context(context: &str) -> Result<Self, SysinspectError> {
         if context.trim().is_empty() {
@@ -145,6 +262,98 @@ impl MinionProcessSignalConsoleRequest {
     }
 }
 
+impl QueuedCommandsConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        if context.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(context)
+            .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse queued commands request context: {err}")))
+    }
+}
+
+impl IntegrityRebaselineConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        if context.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(context)
+            .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse integrity rebaseline request context: {err}")))
+    }
+
+    fn to_minion_request(&self) -> ConsoleMinionIntegrityRebaselineRequest {
+        ConsoleMinionIntegrityRebaselineRequest { sensor: self.sensor.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string) }
+    }
+}
+
+impl TraitHistoryConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        if context.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(context)
+            .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse trait history request context: {err}")))
+    }
+}
+
+impl ApiTokenConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        serde_json::from_str(context)
+            .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse API token request context: {err}")))
+    }
+}
+
+impl AuditConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        if context.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(context).map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse audit request context: {err}")))
+    }
+
+    fn filter(&self) -> Result<AuditFilter, SysinspectError> {
+        Ok(AuditFilter {
+            since: self.since.as_deref().map(audit::parse_time).transpose()?,
+            until: self.until.as_deref().map(audit::parse_time).transpose()?,
+            actor: self.actor.clone(),
+            limit: self.limit,
+        })
+    }
+}
+
+impl ModelVersionConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        serde_json::from_str(context)
+            .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse model version request context: {err}")))
+    }
+}
+
+impl RebootConsoleRequest {
+    fn from_context(context: &str) -> Result<Self, SysinspectError> {
+        if context.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(context).map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse reboot request context: {err}")))
+    }
+
+    fn batch(&self) -> usize {
+        self.batch.unwrap_or(1).max(1)
+    }
+
+    fn timeout(&self) -> StdDuration {
+        StdDuration::from_secs(self.timeout.unwrap_or(600))
+    }
+
+    fn to_minion_request(&self) -> ConsoleMinionRebootRequest {
+        ConsoleMinionRebootRequest { drain_timeout: self.drain.unwrap_or(60) }
+    }
+}
+
 impl CmdbStartupConsoleRequest {
     fn from_context(context: &str) -> Result<Self, SysinspectError> {
         if context.trim().is_empty() {
@@ -310,6 +519,7 @@ impl SysMaster {
                         os_name,
                         os_version,
                         kernel,
+                        sensors: self.sensor_health.get(minion.id()).cloned().unwrap_or_default(),
                     });
                 }
                 rows
@@ -652,6 +862,13 @@ impl SysMaster {
 
     async fn await_minion_console_reply(
         master: Arc<Mutex<Self>>, minion_id: &str, msg: MasterMessage,
+    ) -> Result<MinionCommandReply, SysinspectError> {
+        Self::await_minion_console_reply_within(master, minion_id, msg, CONSOLE_MINION_REPLY_TIMEOUT).await
+    }
+
+    /// Same as `await_minion_console_reply`, for commands which take longer to answer.
+    async fn await_minion_console_reply_within(
+        master: Arc<Mutex<Self>>, minion_id: &str, msg: MasterMessage, timeout: StdDuration,
     ) -> Result<MinionCommandReply, SysinspectError> {
         let cycle_id = msg.cycle().clone();
         let (direct_tx, reply_rx) = {
@@ -676,15 +893,12 @@ impl SysMaster {
             return Err(SysinspectError::ProtoError(format!("Failed to send direct console request to {minion_id}: {err}")));
         }
 
-        match time::timeout(CONSOLE_MINION_REPLY_TIMEOUT, reply_rx).await {
+        match time::timeout(timeout, reply_rx).await {
             Ok(Ok(reply)) => Ok(reply),
             Ok(Err(_)) => Err(SysinspectError::ProtoError(format!("Minion {minion_id} dropped the console reply channel"))),
             Err(_) => {
                 master.lock().await.pending_console_replies.remove(&cycle_id);
-                Err(SysinspectError::ProtoError(format!(
-                    "Timed out waiting {}s for minion {minion_id} log reply",
-                    CONSOLE_MINION_REPLY_TIMEOUT.as_secs()
-                )))
+                Err(SysinspectError::ProtoError(format!("Timed out waiting {}s for minion {minion_id} reply", timeout.as_secs())))
             }
         }
     }
@@ -1009,6 +1223,47 @@ impl SysMaster {
         Ok(ConsoleResponse::ok(ConsolePayload::Ack { action: "minion_reconnect".to_string(), target: minion_id, count: 1, items: vec![] }))
     }
 
+    async fn minion_integrity_rebaseline(
+        master: Arc<Mutex<Self>>, query: &str, traits: &str, mid: &str, request: &IntegrityRebaselineConsoleRequest,
+    ) -> Result<ConsoleResponse, SysinspectError> {
+        let (minion_id, _alive, msg) = {
+            let mut guard = master.lock().await;
+            let (minion_id, alive) = guard.selected_console_minion(query, traits, mid).await?;
+            if !alive {
+                return Err(SysinspectError::InvalidQuery(format!("Minion {minion_id} is offline")));
+            }
+            let context = serde_json::to_string(&request.to_minion_request())
+                .map_err(|err| SysinspectError::SerializationError(format!("Failed to encode integrity rebaseline request: {err}")))?;
+            let msg = guard
+                .msg_query_data(&format!("{SCHEME_COMMAND}{CLUSTER_MINION_INTEGRITY_REBASELINE}"), "", "", &minion_id, &context)
+                .await
+                .ok_or_else(|| SysinspectError::ProtoError(format!("Unable to construct integrity rebaseline request for {minion_id}")))?;
+            (minion_id, alive, msg)
+        };
+
+        let reply = Self::await_minion_console_reply(master, &minion_id, msg).await?;
+        if !reply.ok {
+            return Err(SysinspectError::ProtoError(if reply.error.is_empty() {
+                format!("Minion {minion_id} integrity rebaseline failed")
+            } else {
+                reply.error
+            }));
+        }
+
+        let sensors = reply
+            .payload
+            .get("sensors")
+            .and_then(|v| v.as_array())
+            .map(|v| v.iter().filter_map(|s| s.as_str().map(str::to_string)).collect::<Vec<_>>())
+            .unwrap_or_default();
+        Ok(ConsoleResponse::ok(ConsolePayload::Ack {
+            action: "integrity_rebaseline".to_string(),
+            target: minion_id,
+            count: sensors.len(),
+            items: sensors,
+        }))
+    }
+
     async fn minion_process_signal(
         master: Arc<Mutex<Self>>, query: &str, traits: &str, mid: &str, request: &MinionProcessSignalConsoleRequest,
     ) -> Result<ConsoleResponse, SysinspectError> {
@@ -1051,6 +1306,162 @@ impl SysMaster {
         }))
     }
 
+    /// Start a rolling reboot of the selected minions.
+    ///
+    /// The rollout runs in the background and is observed via `cluster/reboot/status`.
+    /// Offline minions are skipped right away, since they could not be verified.
+    async fn cluster_reboot(
+        master: Arc<Mutex<Self>>, query: &str, traits: &str, mid: &str, request: RebootConsoleRequest,
+    ) -> Result<ConsoleResponse, SysinspectError> {
+        let mut guard = master.lock().await;
+        let state = Arc::clone(&guard.reboot);
+        let mut current = state.lock().await;
+        if current.as_ref().is_some_and(|r| r.is_active()) {
+            return Err(SysinspectError::InvalidQuery("A rolling reboot is already in progress, see its status first".to_string()));
+        }
+
+        let minions = guard.selected_minions(query, traits, mid).await?;
+        if minions.is_empty() {
+            return Err(SysinspectError::InvalidQuery("No minions match the reboot selector".to_string()));
+        }
+
+        let mut targets = Vec::with_capacity(minions.len());
+        let mut offline = Vec::new();
+        for minion in &minions {
+            let cmdb = guard.mreg.lock().await.get_cmdb(minion.id()).unwrap_or_default();
+            let (fqdn, hostname, ip) = Self::preferred_host(minion, cmdb.as_ref());
+            let host = [fqdn, hostname, ip].into_iter().find(|h| !h.is_empty()).unwrap_or_else(|| minion.id().to_string());
+            let checksum = minion.get_traits().get("minion.binary.sha256").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            if !guard.session.lock().await.alive(minion.id()) {
+                offline.push(minion.id().to_string());
+            }
+            targets.push((minion.id().to_string(), host, checksum));
+        }
+
+        let mut rollout = RebootRollout::new(targets, request.batch());
+        for mid in &offline {
+            rollout.set_phase(mid, RebootPhase::Skipped("offline".to_string()));
+        }
+        let count = minions.len() - offline.len();
+        *current = Some(rollout);
+        drop(current);
+        drop(guard);
+
+        log::info!("Starting rolling reboot of {count} minions, batch of {}", request.batch());
+        tokio::spawn(Self::run_cluster_reboot(master, request));
+
+        Ok(ConsoleResponse::ok(ConsolePayload::Ack {
+            action: "cluster_reboot".to_string(),
+            target: "cluster".to_string(),
+            count,
+            items: offline.into_iter().map(|mid| format!("{mid}: skipped, offline")).collect(),
+        }))
+    }
+
+    /// Reboot the minions batch by batch, stopping at the first failed batch.
+    async fn run_cluster_reboot(master: Arc<Mutex<Self>>, request: RebootConsoleRequest) {
+        let (cfg, state) = {
+            let guard = master.lock().await;
+            (guard.cfg.clone(), Arc::clone(&guard.reboot))
+        };
+        let batches = state.lock().await.as_ref().map(|r| r.batches()).unwrap_or_default();
+        let minion_request = request.to_minion_request();
+        let context = serde_json::to_string(&minion_request).unwrap_or_default();
+
+        for batch in batches {
+            // Drain and reboot all minions of the batch at once
+            let mut replies = Vec::with_capacity(batch.len());
+            for mid in &batch {
+                let msg = master.lock().await.msg_query_data(&format!("{SCHEME_COMMAND}{CLUSTER_REBOOT}"), "", "", mid, &context).await;
+                let master = Arc::clone(&master);
+                let mid = mid.clone();
+                let timeout = StdDuration::from_secs(minion_request.drain_timeout + 30);
+                let state = Arc::clone(&state);
+                replies.push(async move {
+                    if let Some(r) = state.lock().await.as_mut() {
+                        r.draining(&mid);
+                    }
+                    let reply = match msg {
+                        Some(msg) => Self::await_minion_console_reply_within(master, &mid, msg, timeout).await,
+                        None => Err(SysinspectError::ProtoError(format!("Unable to construct reboot request for {mid}"))),
+                    };
+                    (mid, reply)
+                });
+            }
+
+            for (mid, reply) in futures::future::join_all(replies).await {
+                let phase = match reply {
+                    Ok(reply) if reply.ok => None,
+                    Ok(reply) => Some(RebootPhase::Failed(reply.error)),
+                    Err(err) => {
+                        // The host may go down before the reply makes it out
+                        log::warn!("No reboot confirmation from {mid}, waiting for it to come back: {err}");
+                        None
+                    }
+                };
+                if let Some(r) = state.lock().await.as_mut() {
+                    match phase {
+                        Some(phase) => r.set_phase(&mid, phase),
+                        None => r.issued(&mid),
+                    }
+                }
+            }
+
+            // Wait for the minions to come back with the same binary
+            let deadline = time::Instant::now() + request.timeout();
+            loop {
+                let pending = match state.lock().await.as_ref() {
+                    Some(r) => batch.iter().filter(|mid| r.target(mid).is_some_and(|t| !t.phase.is_settled())).cloned().collect::<Vec<_>>(),
+                    None => vec![],
+                };
+                if pending.is_empty() {
+                    break;
+                }
+                if time::Instant::now() >= deadline {
+                    if let Some(r) = state.lock().await.as_mut() {
+                        r.expire(&pending, &format!("did not come back within {}s", request.timeout().as_secs()));
+                    }
+                    break;
+                }
+
+                for mid in pending {
+                    let (alive, checksum, traits_at) = {
+                        let guard = master.lock().await;
+                        let alive = guard.session.lock().await.alive(&mid);
+                        let record = guard.mreg.lock().await.get(&mid).ok().flatten();
+                        let checksum = record
+                            .as_ref()
+                            .and_then(|m| m.get_traits().get("minion.binary.sha256").and_then(|v| v.as_str()).map(ToString::to_string))
+                            .unwrap_or_default();
+                        (alive, checksum, record.and_then(|m| m.refreshed_at()))
+                    };
+                    let handshake =
+                        TransportStore::for_master_minion(&cfg, &mid).ok().and_then(|s| s.load().ok().flatten()).and_then(|s| s.last_handshake_at);
+                    if let Some(r) = state.lock().await.as_mut() {
+                        r.observe(&mid, alive, &checksum, handshake, traits_at);
+                    }
+                }
+                time::sleep(StdDuration::from_secs(2)).await;
+            }
+
+            let failed = state.lock().await.as_ref().is_some_and(|r| r.failed(&batch));
+            if failed {
+                log::error!("Rolling reboot stopped: a minion of the batch failed");
+                if let Some(r) = state.lock().await.as_mut() {
+                    r.skip_remaining("rollout stopped after a failed batch");
+                }
+                break;
+            }
+        }
+
+        if let Some(r) = state.lock().await.as_mut() {
+            r.finished = Some(chrono::Utc::now());
+            for line in r.format() {
+                log::info!("{line}");
+            }
+        }
+    }
+
     /// Register the concrete minion ids targeted by one outbound console message.
     ///
     /// This keeps task tracking aligned with console-initiated broadcasts so the
@@ -1084,8 +1495,8 @@ impl SysMaster {
     /// data requests directly and delegates cluster-affecting operations to the
     /// existing `SysMaster` helpers that already know how to stage, persist, and
     /// build outbound master messages.
-    async fn dispatch_console_query(
-        master: Arc<Mutex<Self>>, bcast: &broadcast::Sender<MasterMessage>, cfg: &MasterConfig, query: ConsoleQuery,
+    pub(crate) async fn dispatch_console_query(
+        master: Arc<Mutex<Self>>, bcast: &broadcast::Sender<MasterMessage>, cfg: &MasterConfig, client: &str, query: ConsoleQuery,
     ) -> ConsoleResponse {
         if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_ONLINE_MINIONS}")) {
             return match master.lock().await.online_minions_data(&query.query, &query.traits, &query.mid).await {
@@ -1115,6 +1526,29 @@ impl SysMaster {
             };
         }
 
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_REBOOT}")) {
+            return match RebootConsoleRequest::from_context(&query.context) {
+                Ok(request) => match Self::cluster_reboot(Arc::clone(&master), &query.query, &query.traits, &query.mid, request).await {
+                    Ok(response) => response,
+                    Err(err) => ConsoleResponse::err(format!("Unable to start cluster reboot: {err}")),
+                },
+                Err(err) => ConsoleResponse::err(format!("Failed to parse reboot request: {err}")),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_REBOOT_STATUS}")) {
+            let state = Arc::clone(&master.lock().await.reboot);
+            return match state.lock().await.as_ref() {
+                Some(rollout) => ConsoleResponse::ok(ConsolePayload::StringList { items: rollout.format() }),
+                None => ConsoleResponse::ok(ConsolePayload::StringList { items: vec!["No rolling reboot has been started".to_string()] }),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_PLACEMENT}")) {
+            let decisions = master.lock().await.vmcluster.decisions(query.query.trim_start_matches("v:")).await;
+            return ConsoleResponse::ok(ConsolePayload::StringList { items: decisions.iter().map(|d| d.format()).collect() });
+        }
+
         if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_MINION_INFO}")) {
             return match master.lock().await.minion_info_rows(&query.query, &query.traits, &query.mid).await {
                 Ok(rows) => ConsoleResponse::ok(ConsolePayload::MinionInfo { rows }),
@@ -1173,6 +1607,56 @@ impl SysMaster {
             };
         }
 
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_COMMANDS}")) {
+            return match QueuedCommandsConsoleRequest::from_context(&query.context) {
+                Ok(request) => match master.lock().await.queued_commands_console_data(&request, &query.query, &query.traits, &query.mid).await {
+                    Ok(rows) => ConsoleResponse::ok(ConsolePayload::QueuedCommands { rows }),
+                    Err(err) => ConsoleResponse::err(format!("Unable to list queued commands: {err}")),
+                },
+                Err(err) => ConsoleResponse::err(format!("Failed to parse queued commands request: {err}")),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_TRAITS_HISTORY}")) {
+            return match TraitHistoryConsoleRequest::from_context(&query.context) {
+                Ok(request) => match master.lock().await.trait_history_console_data(&request, &query.query, &query.traits, &query.mid).await {
+                    Ok(rows) => ConsoleResponse::ok(ConsolePayload::TraitHistory { rows }),
+                    Err(err) => ConsoleResponse::err(format!("Unable to get trait history: {err}")),
+                },
+                Err(err) => ConsoleResponse::err(format!("Failed to parse trait history request: {err}")),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_API_TOKENS}")) {
+            return match ApiTokenConsoleRequest::from_context(&query.context) {
+                Ok(request) => Self::api_tokens_console_response(cfg, client, &request)
+                    .unwrap_or_else(|err| ConsoleResponse::err(format!("Unable to manage API tokens: {err}"))),
+                Err(err) => ConsoleResponse::err(format!("Failed to parse API token request: {err}")),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_AUDIT}")) {
+            return match AuditConsoleRequest::from_context(&query.context) {
+                Ok(request) => Self::audit_console_response(cfg, &request)
+                    .unwrap_or_else(|err| ConsoleResponse::err(format!("Unable to read audit journal: {err}"))),
+                Err(err) => ConsoleResponse::err(format!("Failed to parse audit request: {err}")),
+            };
+        }
+
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_MODEL_VERSIONS}")) {
+            return match ModelVersionConsoleRequest::from_context(&query.context) {
+                Ok(request) => {
+                    // Versions are copied and hashed on disk under the model lock
+                    let (cfg, client, name) = (cfg.clone(), client.to_string(), request.name.clone());
+                    tokio::task::spawn_blocking(move || Self::model_versions_console_response(&cfg, &client, &request))
+                        .await
+                        .unwrap_or_else(|err| Err(SysinspectError::MasterGeneralError(err.to_string())))
+                        .unwrap_or_else(|err| ConsoleResponse::err(format!("Unable to manage versions of model {name}: {err}")))
+                }
+                Err(err) => ConsoleResponse::err(format!("Failed to parse model version request: {err}")),
+            };
+        }
+
         if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_ROTATE}")) {
             let (response, msgs) = match RotationConsoleRequest::from_context(&query.context) {
                 Ok(request) => {
@@ -1216,6 +1700,18 @@ impl SysMaster {
             };
         }
 
+        if query.model.eq(&format!("{SCHEME_COMMAND}{CLUSTER_MINION_INTEGRITY_REBASELINE}")) {
+            return match IntegrityRebaselineConsoleRequest::from_context(&query.context) {
+                Ok(request) => {
+                    match Self::minion_integrity_rebaseline(Arc::clone(&master), &query.query, &query.traits, &query.mid, &request).await {
+                        Ok(response) => response,
+                        Err(err) => ConsoleResponse::err(err.to_string()),
+                    }
+                }
+                Err(err) => ConsoleResponse::err(format!("Failed to parse integrity rebaseline request: {err}")),
+            };
+        }
+
         if query.model.eq(&format!("{SCHEME_COMMAND}{}", libsysproto::query::commands::CLUSTER_MINION_PROCESS_SIGNAL)) {
             return match MinionProcessSignalConsoleRequest::from_context(&query.context) {
                 Ok(request) => match Self::minion_process_signal(Arc::clone(&master), &query.query, &query.traits, &query.mid, &request).await {
@@ -1308,7 +1804,9 @@ impl SysMaster {
             guard.msg_query_data(&query.model, &query.query, &query.traits, &query.mid, &query.context).await
         };
         if let Some(msg) = msg {
-            Self::broadcast_console_messages(Arc::clone(&master), bcast, cfg, vec![msg], true).await;
+            let opts = MasterCommandOptions { ttl: query.ttl.map(StdDuration::from_secs), supersede: query.supersede.clone() };
+            Self::bcast_queued_master_msg(bcast, cfg.telemetry_enabled(), Arc::clone(&master), Some(msg.clone()), opts).await;
+            Self::register_broadcast_targets(Arc::clone(&master), &msg).await;
             return ConsoleResponse {
                 ok: true,
                 error: String::new(),
@@ -1356,9 +1854,9 @@ impl SysMaster {
             Err(err) => return Self::console_error_json(format!("Failed to parse console request: {err}")),
         };
 
-        if !authorised_console_client(cfg, &envelope.bootstrap.client_pubkey).unwrap_or(false) {
+        let Some(client) = console_client_name(cfg, &envelope.bootstrap.client_pubkey).unwrap_or(None) else {
             return Self::console_error_json("Console client key is not authorised");
-        }
+        };
 
         let (key, _client_pkey) = match envelope.bootstrap.session_key(master_prk) {
             Ok(data) => data,
@@ -1370,7 +1868,19 @@ impl SysMaster {
             Err(err) => return Self::console_error_json(format!("Failed to open console query: {err}")),
         };
 
-        let mut response = Self::dispatch_console_query(master, bcast, cfg, query).await;
+        let access = Access::of_query(&query.model, &query.query, &query.traits, &query.mid);
+        let (mut response, outcome) = match rbac::authorise(cfg, &Principal::Console(client.clone()), &access) {
+            Ok(()) => {
+                let response = Self::dispatch_console_query(master, bcast, cfg, &client, query.clone()).await;
+                let outcome = if response.ok { AuditOutcome::Success } else { AuditOutcome::Failure };
+                (response, outcome)
+            }
+            Err(err) => {
+                log::warn!("Console client {client} denied: {err}");
+                (ConsoleResponse::err(err.to_string()), AuditOutcome::Denied)
+            }
+        };
+        Self::audit_console_request(cfg, &client, &query, outcome, &response.error).await;
         let seal_response = |response: &ConsoleResponse| {
             ConsoleSealed::seal(response, &key)
                 .and_then(|sealed| serde_json::to_string(&sealed).map_err(|e| SysinspectError::SerializationError(e.to_string())))
@@ -1715,6 +2225,143 @@ impl SysMaster {
         Ok((summary.response(), online_msgs))
     }
 
+    /// Create, list or revoke Web API tokens on behalf of a console client.
+    ///
+    /// Tokens created here are owned by the console client and get their roles
+    /// through the `tokens` bindings of the access control policy.
+    fn api_tokens_console_response(cfg: &MasterConfig, client: &str, request: &ApiTokenConsoleRequest) -> Result<ConsoleResponse, SysinspectError> {
+        let store = TokenStore::new(&cfg.api_keys_root());
+        match request.op.as_str() {
+            "create" => {
+                let ttl = tokens::parse_lifetime(request.expires.as_deref().unwrap_or_default())?;
+                let (token, secret) =
+                    store.create(&request.name, TokenOwner::Console(client.to_string()), TokenScope::parse_list(&request.scopes)?, ttl)?;
+                log::info!("Console client {client} created API token {}", token.name);
+                Ok(ConsoleResponse::ok(ConsolePayload::ApiTokenCreated { row: ConsoleApiTokenRow::from(token), token: secret }))
+            }
+            "list" => Ok(ConsoleResponse::ok(ConsolePayload::ApiTokens { rows: store.list()?.into_iter().map(ConsoleApiTokenRow::from).collect() })),
+            "revoke" => {
+                if !store.revoke(&request.name)? {
+                    return Err(SysinspectError::InvalidQuery(format!("Token {} not found", request.name)));
+                }
+                log::info!("Console client {client} revoked API token {}", request.name);
+                Ok(ConsoleResponse::ok(ConsolePayload::Ack {
+                    action: "revoke_api_token".to_string(),
+                    target: request.name.clone(),
+                    count: 1,
+                    items: vec![],
+                }))
+            }
+            op => Err(SysinspectError::InvalidQuery(format!("Unknown API token operation \"{op}\""))),
+        }
+    }
+
+    /// List or verify the audit journal.
+    fn audit_console_response(cfg: &MasterConfig, request: &AuditConsoleRequest) -> Result<ConsoleResponse, SysinspectError> {
+        let journal = AuditJournal::new(&cfg.audit_root());
+        if request.verify {
+            return Ok(ConsoleResponse::ok(ConsolePayload::AuditVerified { verification: journal.verify()? }));
+        }
+
+        let rows = match request.after {
+            Some(after) => journal.page(&request.filter()?, after, AUDIT_PAGE_SIZE)?,
+            None => journal.query(&request.filter()?)?,
+        };
+        Ok(ConsoleResponse::ok(ConsolePayload::AuditRecords { rows }))
+    }
+
+    /// Upload, list, compare or roll back versions of a model.
+    ///
+    /// Activated versions replace the model in the models directory of the file
+    /// server, the model watcher tells the minions about it.
+    fn model_versions_console_response(
+        cfg: &MasterConfig, client: &str, request: &ModelVersionConsoleRequest,
+    ) -> Result<ConsoleResponse, SysinspectError> {
+        let store = ModelStore::new(&cfg.model_versions_root(), &cfg.fileserver_models_root(false));
+        let name = request.name.as_str();
+        match request.op.as_str() {
+            "upload" => {
+                let bundle = request.bundle.as_ref().ok_or_else(|| SysinspectError::InvalidQuery("Model bundle is missing".to_string()))?;
+                let version = store.upload(name, bundle, client, request.note.as_deref())?;
+                log::info!("Console client {client} activated version {} of model {name}", version.version);
+                Ok(ConsoleResponse::ok(ConsolePayload::ModelVersionActivated { model: name.to_string(), version }))
+            }
+            "list" => Ok(ConsoleResponse::ok(ConsolePayload::ModelVersions { versions: store.versions(name)? })),
+            "diff" => {
+                let from = request.from.ok_or_else(|| SysinspectError::InvalidQuery("Version to compare from is missing".to_string()))?;
+                Ok(ConsoleResponse::ok(ConsolePayload::ModelDiff { diff: store.diff(name, from, request.to)? }))
+            }
+            "rollback" => {
+                let version = store.rollback(name, request.version)?;
+                log::info!("Console client {client} rolled model {name} back to version {}", version.version);
+                Ok(ConsoleResponse::ok(ConsolePayload::ModelVersionActivated { model: name.to_string(), version }))
+            }
+            op => Err(SysinspectError::InvalidQuery(format!("Unknown model version operation \"{op}\""))),
+        }
+    }
+
+    /// Record a console request in the audit journal.
+    ///
+    /// Failing to record does not fail the request, it is logged instead.
+    async fn audit_console_request(cfg: &MasterConfig, client: &str, query: &ConsoleQuery, outcome: AuditOutcome, error: &str) {
+        let action = query.model.strip_prefix(SCHEME_COMMAND).unwrap_or(&query.model);
+        if outcome != AuditOutcome::Denied && query.model.starts_with(SCHEME_COMMAND) && UNAUDITED_CONSOLE_COMMANDS.contains(&action) {
+            return;
+        }
+
+        let target = match action {
+            CLUSTER_MODEL_VERSIONS => {
+                ModelVersionConsoleRequest::from_context(&query.context).map(|r| format!("model:{}", r.name)).unwrap_or_default()
+            }
+            _ => audit::minion_target(&query.query, &query.traits, &query.mid),
+        };
+        let entry = AuditEntry::new(client, AuditChannel::Console, action, &target, outcome);
+        if let Err(err) = AuditJournal::new(&cfg.audit_root()).submit(entry.detail(error)).await {
+            log::error!("Unable to record console request of {client} in the audit journal: {err}");
+        }
+    }
+
+    async fn queued_commands_console_data(
+        &mut self, request: &QueuedCommandsConsoleRequest, query: &str, traits: &str, mid: &str,
+    ) -> Result<Vec<ConsoleQueuedCommandRow>, SysinspectError> {
+        let everyone = mid.is_empty() && traits.trim().is_empty() && matches!(query.trim(), "" | "*");
+        let minions = if everyone {
+            None
+        } else {
+            Some(self.selected_minions(query, traits, mid).await?.iter().map(|m| m.id().to_string()).collect::<Vec<_>>())
+        };
+
+        let mut rows = self.queued_commands_data(minions.as_deref())?;
+        if let Some(state) = request.state.as_deref().filter(|s| !s.is_empty()) {
+            rows.retain(|row| row.state.eq_ignore_ascii_case(state));
+        }
+        Ok(rows)
+    }
+
+    async fn trait_history_console_data(
+        &mut self, request: &TraitHistoryConsoleRequest, query: &str, traits: &str, mid: &str,
+    ) -> Result<Vec<ConsoleTraitChangeRow>, SysinspectError> {
+        let key = request.key.as_deref().filter(|key| !key.is_empty());
+        let mut rows = Vec::new();
+        for minion in self.selected_minions(query, traits, mid).await? {
+            let Some(cmdb) = self.mreg.lock().await.get_cmdb(minion.id())? else {
+                continue;
+            };
+            let (fqdn, hostname, ip) = Self::preferred_host(&minion, Some(&cmdb));
+            let host = [fqdn, hostname, ip].into_iter().find(|label| !label.is_empty()).unwrap_or_default();
+            rows.extend(cmdb.trait_history(key).into_iter().map(|change| ConsoleTraitChangeRow {
+                minion_id: minion.id().to_string(),
+                host: host.clone(),
+                key: change.key().to_string(),
+                old: change.old().cloned(),
+                new: change.new_value().cloned(),
+                changed_at: change.at(),
+            }));
+        }
+        rows.sort_by_key(|row| row.changed_at);
+        Ok(rows)
+    }
+
     /// Build raw transport-status rows for the selected minions.
     ///
     /// Each row captures host identity plus the currently persisted transport
@@ -0,0 +1,375 @@
+//! Active/standby master pair.
+//!
+//! Masters of the pair share a lease file and a state directory (typically on shared storage).
+//! Only the lease holder runs the master services. It periodically renews the lease and publishes
+//! snapshots of the minion registry, the outbound command queue, the minion keys and the placement
+//! decisions into the state directory. A standby waits for the lease to expire, restores the latest
+//! snapshots and takes over.
+
+use crate::{
+    master::SysMaster,
+    registry::{cmdq::MasterCommandQueue, mreg::MinionRegistry},
+};
+use libcommon::SysinspectError;
+use libsysinspect::cfg::mmconf::{CFG_PENDING_COMMANDS_ROOT, MasterConfig};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::{Mutex, oneshot},
+    task::JoinHandle,
+    time,
+};
+
+#[cfg(test)]
+#[path = "ha_ut.rs"]
+mod ha_ut;
+
+pub(crate) const HA_REGISTRY_SNAPSHOT: &str = "registry.snap";
+pub(crate) const HA_CMDQ_SNAPSHOT: &str = "cmdq.snap";
+pub(crate) const HA_KEYS_DIR: &str = "minion-keys";
+pub(crate) const HA_TRANSPORT_DIR: &str = "transport-minions";
+pub(crate) const HA_PLACEMENT_LOG: &str = "placement.log";
+
+/// Terminates entries of one tree in a snapshot
+const SNAPSHOT_TREE_END: u32 = u32::MAX;
+
+/// Lease content, as seen by all masters of the pair
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct LeaseRecord {
+    pub(crate) holder: String,
+    pub(crate) epoch: u64,
+    pub(crate) expires_ms: u128,
+}
+
+/// Lease stored in a file. Every read-modify-write is serialised by an exclusive
+/// `flock` on a sidecar lock file, so it works between processes on the same host
+/// as well as on shared storage that supports advisory locks.
+#[derive(Debug, Clone)]
+pub(crate) struct FileLease {
+    path: PathBuf,
+}
+
+impl FileLease {
+    pub(crate) fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    fn now_ms() -> u128 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+    }
+
+    /// Take the exclusive lock. It is released when the returned file is dropped.
+    fn lock(&self) -> Result<File, SysinspectError> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let f = OpenOptions::new().create(true).truncate(false).write(true).open(self.path.with_extension("lock"))?;
+        if unsafe { libc::flock(f.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(SysinspectError::IoErr(std::io::Error::last_os_error()));
+        }
+        Ok(f)
+    }
+
+    /// Current lease, if any
+    pub(crate) fn read(&self) -> Option<LeaseRecord> {
+        serde_json::from_slice(&fs::read(&self.path).ok()?).ok()
+    }
+
+    fn write(&self, rec: &LeaseRecord) -> Result<(), SysinspectError> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(rec)?)?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Acquire or renew the lease for the holder. Returns the lease epoch, or `None`
+    /// if another holder owns a lease that did not expire yet. The epoch grows
+    /// every time the lease changes hands.
+    pub(crate) fn acquire(&self, holder: &str, ttl: Duration) -> Result<Option<u64>, SysinspectError> {
+        let _lock = self.lock()?;
+        let now = Self::now_ms();
+        let epoch = match self.read() {
+            Some(rec) if rec.holder == holder => rec.epoch,
+            Some(rec) if rec.expires_ms > now => return Ok(None),
+            Some(rec) => rec.epoch + 1,
+            None => 1,
+        };
+        self.write(&LeaseRecord { holder: holder.to_string(), epoch, expires_ms: now + ttl.as_millis() })?;
+        Ok(Some(epoch))
+    }
+
+    /// Release the lease, if it is owned by the holder, so the standby takes over immediately
+    pub(crate) fn release(&self, holder: &str) -> Result<(), SysinspectError> {
+        let _lock = self.lock()?;
+        if let Some(mut rec) = self.read()
+            && rec.holder == holder
+        {
+            rec.expires_ms = 0;
+            self.write(&rec)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_chunk<W: Write>(w: &mut W, data: &[u8]) -> Result<(), SysinspectError> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(data)?;
+    Ok(())
+}
+
+fn read_len<R: Read>(r: &mut R) -> Result<Option<u32>, SysinspectError> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(_) => Ok(Some(u32::from_be_bytes(len))),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_chunk<R: Read>(r: &mut R, len: u32) -> Result<Vec<u8>, SysinspectError> {
+    let mut data = vec![0u8; len as usize];
+    r.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Write all trees of the database into a snapshot file. The file is replaced atomically,
+/// so a standby never reads a partial snapshot.
+pub(crate) fn export_db(db: &Db, path: &Path) -> Result<(), SysinspectError> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        for name in db.tree_names() {
+            write_chunk(&mut w, &name)?;
+            for entry in db.open_tree(&name)?.iter() {
+                let (k, v) = entry?;
+                write_chunk(&mut w, &k)?;
+                write_chunk(&mut w, &v)?;
+            }
+            w.write_all(&SNAPSHOT_TREE_END.to_be_bytes())?;
+        }
+        w.flush()?;
+    }
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Replace the content of the database with a snapshot file
+pub(crate) fn import_db(db: &Db, path: &Path) -> Result<(), SysinspectError> {
+    let mut r = BufReader::new(File::open(path)?);
+    let mut restored = Vec::new();
+    while let Some(len) = read_len(&mut r)? {
+        let name = read_chunk(&mut r, len)?;
+        let tree = db.open_tree(&name)?;
+        tree.clear()?;
+        loop {
+            let klen = read_len(&mut r)?.ok_or_else(|| SysinspectError::MasterGeneralError(format!("Truncated snapshot {}", path.display())))?;
+            if klen == SNAPSHOT_TREE_END {
+                break;
+            }
+            let k = read_chunk(&mut r, klen)?;
+            let vlen = read_len(&mut r)?.ok_or_else(|| SysinspectError::MasterGeneralError(format!("Truncated snapshot {}", path.display())))?;
+            tree.insert(k, read_chunk(&mut r, vlen)?)?;
+        }
+        restored.push(name.to_vec());
+    }
+
+    // Trees that are gone on the active master are gone here too
+    for name in db.tree_names() {
+        if !restored.contains(&name.to_vec()) && db.drop_tree(&name).is_err() {
+            db.open_tree(&name)?.clear()?;
+        }
+    }
+    db.flush()?;
+    Ok(())
+}
+
+/// Restore a sled database directory from a snapshot file
+fn import_db_dir(dir: &Path, path: &Path) -> Result<(), SysinspectError> {
+    import_db(&sled::open(dir)?, path)
+}
+
+/// Make the `dst` directory tree an exact copy of the `src` one
+pub(crate) fn mirror_dir(src: &Path, dst: &Path) -> Result<(), SysinspectError> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(dst)? {
+        let entry = entry?;
+        if !src.join(entry.file_name()).exists() {
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+
+    if !src.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            mirror_dir(&entry.path(), &target)?;
+        } else if fs::read(entry.path())? != fs::read(&target).unwrap_or_default() {
+            let tmp = target.with_extension("ha-tmp");
+            fs::copy(entry.path(), &tmp)?;
+            fs::rename(tmp, target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Atomically copy a file, if it exists
+fn copy_file(src: &Path, dst: &Path) -> Result<(), SysinspectError> {
+    if !src.exists() {
+        return Ok(());
+    }
+    let tmp = dst.with_extension("ha-tmp");
+    fs::copy(src, &tmp)?;
+    fs::rename(tmp, dst)?;
+    Ok(())
+}
+
+/// One master of an active/standby pair
+#[derive(Debug, Clone)]
+pub(crate) struct MasterHa {
+    lease: FileLease,
+    state: PathBuf,
+    node: String,
+    ttl: Duration,
+}
+
+impl MasterHa {
+    /// Returns `None` if the master is not configured for active/standby
+    pub(crate) fn new(cfg: &MasterConfig) -> Option<Self> {
+        Some(Self {
+            lease: FileLease::new(cfg.ha_lease()?),
+            state: cfg.ha_state()?,
+            node: cfg.ha_node().unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            ttl: cfg.ha_lease_ttl(),
+        })
+    }
+
+    /// Lease renewal period
+    fn period(&self) -> Duration {
+        (self.ttl / 3).max(Duration::from_millis(100))
+    }
+
+    /// Block until this master holds the lease
+    pub(crate) async fn wait_for_leadership(&self) -> Result<u64, SysinspectError> {
+        let mut announced = false;
+        loop {
+            if let Some(epoch) = self.lease.acquire(&self.node, self.ttl)? {
+                log::info!("Master \"{}\" is now active (lease epoch {epoch})", self.node);
+                return Ok(epoch);
+            }
+
+            if !announced {
+                let holder = self.lease.read().map(|r| r.holder).unwrap_or_default();
+                log::info!("Master \"{}\" is on standby, active master is \"{holder}\"", self.node);
+                announced = true;
+            }
+            time::sleep(self.period()).await;
+        }
+    }
+
+    /// Restore the state, replicated by the previously active master. Must be called
+    /// before the registries are opened.
+    pub(crate) fn restore(&self, cfg: &MasterConfig) -> Result<(), SysinspectError> {
+        let registry = self.state.join(HA_REGISTRY_SNAPSHOT);
+        if registry.exists() {
+            import_db_dir(&cfg.minion_registry_root(), &registry)?;
+            log::info!("Restored minion registry from {}", registry.display());
+        }
+
+        let cmdq = self.state.join(HA_CMDQ_SNAPSHOT);
+        if cmdq.exists() {
+            import_db_dir(&cfg.root_dir().join(CFG_PENDING_COMMANDS_ROOT), &cmdq)?;
+            log::info!("Restored command queue from {}", cmdq.display());
+        }
+
+        for (dir, local) in [(HA_KEYS_DIR, cfg.minion_keys_root()), (HA_TRANSPORT_DIR, cfg.transport_minions_root())] {
+            if self.state.join(dir).exists() {
+                mirror_dir(&self.state.join(dir), &local)?;
+            }
+        }
+        copy_file(&self.state.join(HA_PLACEMENT_LOG), &cfg.placement_log())?;
+
+        Ok(())
+    }
+
+    /// Publish the current state of the active master into the shared state directory
+    pub(crate) async fn replicate(
+        &self, cfg: &MasterConfig, mreg: &Arc<Mutex<MinionRegistry>>, cmdq: &MasterCommandQueue,
+    ) -> Result<(), SysinspectError> {
+        fs::create_dir_all(&self.state)?;
+        mreg.lock().await.export_snapshot(&self.state.join(HA_REGISTRY_SNAPSHOT))?;
+        cmdq.export_snapshot(&self.state.join(HA_CMDQ_SNAPSHOT))?;
+        mirror_dir(&cfg.minion_keys_root(), &self.state.join(HA_KEYS_DIR))?;
+        mirror_dir(&cfg.transport_minions_root(), &self.state.join(HA_TRANSPORT_DIR))?;
+        copy_file(&cfg.placement_log(), &self.state.join(HA_PLACEMENT_LOG))?;
+        Ok(())
+    }
+
+    /// Keep renewing the lease. The returned receiver is resolved once the lease is lost
+    /// (e.g. this master was stalled longer than the lease time to live), or it could not be
+    /// renewed within its time to live. The master must then stop, so there are never two
+    /// active masters.
+    pub(crate) fn renew(&self) -> (JoinHandle<()>, oneshot::Receiver<String>) {
+        let ha = self.clone();
+        let (lost, rx) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut renewed = Instant::now();
+            loop {
+                time::sleep(ha.period()).await;
+                match ha.lease.acquire(&ha.node, ha.ttl) {
+                    Ok(Some(_)) => renewed = Instant::now(),
+                    Ok(None) => {
+                        let _ = lost.send(format!("Master \"{}\" lost the lease", ha.node));
+                        return;
+                    }
+                    // The lease might have expired meanwhile and the standby could have taken over
+                    Err(err) if renewed.elapsed() >= ha.ttl => {
+                        let _ = lost.send(format!("Unable to renew master lease for {:?}: {err}", renewed.elapsed()));
+                        return;
+                    }
+                    Err(err) => log::error!("Unable to renew master lease: {err}"),
+                }
+            }
+        });
+
+        (task, rx)
+    }
+
+    /// Keep replicating the state of the active master for the standby
+    pub(crate) fn start(&self, master: Arc<Mutex<SysMaster>>, cfg: MasterConfig) -> JoinHandle<()> {
+        let ha = self.clone();
+        tokio::spawn(async move {
+            let (mreg, cmdq) = master.lock().await.ha_handles();
+            loop {
+                time::sleep(ha.period()).await;
+                if let Err(err) = ha.replicate(&cfg, &mreg, &cmdq).await {
+                    log::error!("Unable to replicate master state to {}: {err}", ha.state.display());
+                }
+            }
+        })
+    }
+
+    /// Hand the lease over to the standby master
+    pub(crate) fn release(&self) {
+        if let Err(err) = self.lease.release(&self.node) {
+            log::error!("Unable to release master lease: {err}");
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{FileLease, export_db, import_db, mirror_dir};
+use std::{fs, thread, time::Duration};
+
+#[test]
+fn lease_is_exclusive_until_expired() {
+    let tmp = tempfile::tempdir().unwrap();
+    let primary = FileLease::new(tmp.path().join("master.lease"));
+    let standby = FileLease::new(tmp.path().join("master.lease"));
+
+    assert_eq!(primary.acquire("a", Duration::from_millis(200)).unwrap(), Some(1));
+    assert_eq!(standby.acquire("b", Duration::from_millis(200)).unwrap(), None);
+
+    // Renewal keeps the epoch
+    assert_eq!(primary.acquire("a", Duration::from_millis(200)).unwrap(), Some(1));
+
+    thread::sleep(Duration::from_millis(250));
+    assert_eq!(standby.acquire("b", Duration::from_secs(10)).unwrap(), Some(2));
+    assert_eq!(primary.acquire("a", Duration::from_secs(10)).unwrap(), None);
+    assert_eq!(primary.read().unwrap().holder, "b");
+}
+
+#[test]
+fn lease_release_hands_over_immediately() {
+    let tmp = tempfile::tempdir().unwrap();
+    let lease = FileLease::new(tmp.path().join("master.lease"));
+
+    assert_eq!(lease.acquire("a", Duration::from_secs(60)).unwrap(), Some(1));
+
+    // Only the holder can release
+    lease.release("b").unwrap();
+    assert_eq!(lease.acquire("b", Duration::from_secs(60)).unwrap(), None);
+
+    lease.release("a").unwrap();
+    assert_eq!(lease.acquire("b", Duration::from_secs(60)).unwrap(), Some(2));
+}
+
+#[test]
+fn lease_contention_elects_single_holder() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("master.lease");
+
+    let winners: Vec<bool> = (0..8)
+        .map(|i| {
+            let lease = FileLease::new(&path);
+            thread::spawn(move || lease.acquire(&format!("node-{i}"), Duration::from_secs(60)).unwrap().is_some())
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|h| h.join().unwrap())
+        .collect();
+
+    assert_eq!(winners.iter().filter(|w| **w).count(), 1);
+}
+
+#[test]
+fn snapshot_roundtrip_replaces_content() {
+    let tmp = tempfile::tempdir().unwrap();
+    let active = sled::open(tmp.path().join("active")).unwrap();
+    active.open_tree("minions").unwrap().insert("m1", "traits").unwrap();
+    active.open_tree("cmdb").unwrap().insert("m1", vec![0u8, 1, 2]).unwrap();
+
+    let standby = sled::open(tmp.path().join("standby")).unwrap();
+    standby.open_tree("minions").unwrap().insert("stale", "x").unwrap();
+    standby.open_tree("gone").unwrap().insert("k", "v").unwrap();
+
+    let snap = tmp.path().join("registry.snap");
+    export_db(&active, &snap).unwrap();
+    import_db(&standby, &snap).unwrap();
+
+    let minions = standby.open_tree("minions").unwrap();
+    assert_eq!(minions.get("m1").unwrap().unwrap().as_ref(), b"traits");
+    assert!(minions.get("stale").unwrap().is_none());
+    assert_eq!(standby.open_tree("cmdb").unwrap().get("m1").unwrap().unwrap().as_ref(), &[0u8, 1, 2]);
+    assert!(standby.open_tree("gone").unwrap().is_empty());
+}
+
+#[test]
+fn mirror_dir_copies_and_prunes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let src = tmp.path().join("src");
+    let dst = tmp.path().join("dst");
+    fs::create_dir_all(src.join("sub")).unwrap();
+    fs::write(src.join("m1.pem"), "key").unwrap();
+    fs::write(src.join("sub").join("state.json"), "{}").unwrap();
+    fs::create_dir_all(&dst).unwrap();
+    fs::write(dst.join("removed.pem"), "old").unwrap();
+
+    mirror_dir(&src, &dst).unwrap();
+
+    assert_eq!(fs::read_to_string(dst.join("m1.pem")).unwrap(), "key");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("state.json")).unwrap(), "{}");
+    assert!(!dst.join("removed.pem").exists());
+}
@@ -1,9 +1,11 @@
 mod clidef;
 mod cluster;
 mod dataserv;
+mod ha;
 mod hopstart;
 mod master;
 mod master_itf;
+mod reboot;
 mod registry;
 mod telemetry;
 mod transport;
@@ -4,8 +4,10 @@ mod console;
 use crate::{
     cluster::VirtualMinionsCluster,
     dataserv::fls,
+    ha::MasterHa,
+    reboot::RebootRollout,
     registry::{
-        cmdq::{MasterCommandQueue, MasterCommandQueueStats, MasterCommandState},
+        cmdq::{MasterCommandOptions, MasterCommandQueue, MasterCommandQueueStats, MasterCommandState},
         mkb::MinionsKeyRegistry,
         mreg::MinionRegistry,
         session::{self, SessionKeeper},
@@ -20,16 +22,26 @@ use filescream::FileScream;
 use filescream::events::{FileScreamEvent, FileScreamMask};
 use indexmap::IndexMap;
 use libcommon::SysinspectError;
-use libdatastore::{cfg::DataStorageConfig, resources::DataStorage};
+use libdatastore::{
+    cfg::{DataNamespaceConfig, DataStorageConfig},
+    resources::{DataPins, DataStorage},
+};
 use libeventreg::{
     ipcs::DbIPCService,
     kvdb::{EventMinion, EventsRegistry},
 };
 use libsysinspect::{
+    audit::{AUDIT_SCHEDULER, AuditChannel, AuditEntry, AuditJournal, AuditOutcome},
     cfg::mmconf::{CFG_MODELS_ROOT, CFG_PENDING_COMMANDS_ROOT, MasterConfig},
-    console::{MinionCommandReply, ensure_console_keypair},
+    console::{ConsoleQueuedCommandRow, MinionCommandReply, ensure_console_keypair},
     context::ProfileConsoleRequest,
-    mdescr::{mspec::MODEL_FILE_EXT, mspecdef::ModelSpec, telemetry::DataExportType},
+    mdescr::{
+        datarefs::{DataRef, data_refs},
+        mspec::MODEL_FILE_EXT,
+        mspecdef::ModelSpec,
+        telemetry::DataExportType,
+        versions::ModelStore,
+    },
     rsa::rotation::{RotationActor, RsaTransportRotator, SignedRotationIntent},
     traits::TraitsTransportPayload,
     transport::TransportStore,
@@ -38,19 +50,21 @@ use libsysinspect::{
 use libsysproto::{
     self, MasterMessage, MinionMessage, MinionTarget,
     errcodes::ProtoErrorCode,
-    payload::{ModStatePayload, PingData, RegistrationReply},
+    payload::{ModStatePayload, PingData, RegistrationReply, SensorHealth},
     query::{
         SCHEME_COMMAND,
         commands::{
             CLUSTER_CMDB_UPSERT, CLUSTER_CONFIG_RELOAD, CLUSTER_HOPSTART, CLUSTER_LIBRARY_INDEX, CLUSTER_MASTER_LOGS, CLUSTER_MINION_HOPSTART,
             CLUSTER_MINION_INFO, CLUSTER_MINION_LOGS, CLUSTER_MINION_RECONNECT, CLUSTER_MINION_SHUTDOWN, CLUSTER_MODELS, CLUSTER_MODULE_INDEX,
-            CLUSTER_ONLINE_MINIONS, CLUSTER_PROFILE, CLUSTER_REMOVE_MINION, CLUSTER_ROTATE, CLUSTER_TRAITS_UPDATE, CLUSTER_TRANSPORT_STATUS,
+            CLUSTER_ONLINE_MINIONS, CLUSTER_PROFILE, CLUSTER_REMOVE_MINION, CLUSTER_ROTATE, CLUSTER_SYNC, CLUSTER_TRAITS_UPDATE,
+            CLUSTER_TRANSPORT_STATUS,
         },
     },
-    replay::{ReplayIdentity, replay_identity_for_master_command_cycle, replay_identity_from_minion_message},
+    replay::{ReplayIdentity, replay_identity_from_minion_message},
     rqtypes::{ProtoKey, ProtoValue, RequestType},
     secure::SECURE_PROTOCOL_VERSION,
 };
+use libwebapi::stream::{self as webstream, StreamEvent, StreamEventKind, StreamSender};
 use omnitrace_core::callbacks::{Callback, CallbackHub};
 use omnitrace_core::sensor::SensorCtx;
 use once_cell::sync::Lazy;
@@ -59,7 +73,7 @@ use serde_json::json;
 use std::path::Path;
 use std::time::Duration as StdDuration;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     path::PathBuf,
     sync::{Arc, Weak},
     vec,
@@ -138,6 +152,7 @@ pub(crate) struct RotationCommandPayload {
 pub struct SysMaster {
     cfg: MasterConfig,
     broadcast: broadcast::Sender<MasterMessage>,
+    stream: StreamSender, // Live feed of cycle and minion events for the Web API
     mkr: MinionsKeyRegistry,
     mreg: Arc<Mutex<MinionRegistry>>,
     taskreg: Arc<Mutex<TaskRegistry>>,
@@ -151,9 +166,11 @@ pub struct SysMaster {
     peer_direct_tx: HashMap<String, mpsc::Sender<OutgoingFrame>>,
     peer_cancel_tx: HashMap<String, tokio::sync::watch::Sender<bool>>,
     pending_console_replies: HashMap<String, oneshot::Sender<MinionCommandReply>>,
+    sensor_health: HashMap<String, Vec<SensorHealth>>, // Sensor health of online minions, by the last pong
     peer_transport: PeerTransport,
     datastore: Arc<Mutex<DataStorage>>,
     model_watcher_token: Option<CancellationToken>,
+    reboot: Arc<Mutex<Option<RebootRollout>>>,
 }
 
 fn model_id_from_path(path: &Path) -> Option<&str> {
@@ -203,23 +220,37 @@ impl Callback<FileScreamEvent> for ModelChangeCallback {
 
 impl SysMaster {
     pub(crate) fn should_durably_queue_command(msg: &MasterMessage) -> bool {
-        msg.req_type() == &RequestType::Command && !msg.target().scheme().starts_with(SCHEME_COMMAND)
+        msg.req_type() == &RequestType::Command
+            && (!msg.target().scheme().starts_with(SCHEME_COMMAND) || msg.target().scheme() == format!("{SCHEME_COMMAND}{CLUSTER_SYNC}"))
     }
 
-    async fn queue_durable_command_targets(&mut self, msg: &MasterMessage) -> Result<usize, SysinspectError> {
+    async fn queue_durable_command_targets(&mut self, msg: &MasterMessage, opts: MasterCommandOptions) -> Result<usize, SysinspectError> {
         if !Self::should_durably_queue_command(msg) {
             return Ok(0);
         }
 
-        let target_ids = self.mreg.lock().await.get_targeted_minions(msg.target(), true).await;
+        // Internal commands have no receipt and are only kept for minions which won't get the broadcast.
+        // Repeating them is pointless, the latest one wins.
+        let internal = msg.target().scheme().starts_with(SCHEME_COMMAND);
+        let mut target_ids = self.mreg.lock().await.get_targeted_minions(msg.target(), true).await;
+        if internal {
+            let online = self.mreg.lock().await.get_targeted_minions(msg.target(), false).await;
+            target_ids.retain(|mid| !online.contains(mid));
+        }
         if target_ids.is_empty() {
-            log::warn!("Durable command queue found no concrete target minions for cycle {} scheme {}", msg.cycle(), msg.target().scheme());
+            if !internal {
+                log::warn!("Durable command queue found no concrete target minions for cycle {} scheme {}", msg.cycle(), msg.target().scheme());
+            }
             return Ok(0);
         }
 
+        let opts = MasterCommandOptions {
+            ttl: opts.ttl.or(Some(self.cfg.commands_ttl())),
+            supersede: opts.supersede.or_else(|| internal.then(|| msg.target().scheme().to_string())),
+        };
         let mut queued = 0usize;
         for minion_id in target_ids {
-            self.cmdq.enqueue(&minion_id, msg)?;
+            self.cmdq.enqueue(&minion_id, msg, &opts)?;
             queued += 1;
         }
         log::info!("Queued durable master command cycle {} for {} minion(s); backlog: {}", msg.cycle(), queued, self.cmdq.stats()?.format());
@@ -236,17 +267,29 @@ impl SysMaster {
         let cmdq = Arc::new(MasterCommandQueue::open(cfg.root_dir().join(CFG_PENDING_COMMANDS_ROOT))?);
         let evtreg = Arc::new(Mutex::new(EventsRegistry::new(cfg.telemetry_location(), cfg.history())?));
         let evtipc = Arc::new(DbIPCService::new(Arc::clone(&evtreg), cfg.telemetry_socket().to_str().unwrap_or_default())?);
-        let vmcluster = VirtualMinionsCluster::new(cfg.cluster().to_owned(), Arc::clone(&mreg), Arc::clone(&SHARED_SESSION), Arc::clone(&taskreg));
+        let vmcluster = VirtualMinionsCluster::new(cfg.cluster().to_owned(), Arc::clone(&mreg), Arc::clone(&SHARED_SESSION), Arc::clone(&taskreg))
+            .with_log(cfg.placement_log());
 
-        let ds_cfg = DataStorageConfig::new()
+        let mut ds_cfg = DataStorageConfig::new()
             .expiration(StdDuration::from_secs(cfg.datastore_max_age()))
             .max_overall_size(cfg.datastore_max_size())
             .max_item_size(cfg.datastore_item_max_size());
+        for (name, ns) in cfg.datastore_namespaces() {
+            let mut ns_cfg = DataNamespaceConfig::new();
+            if let Some(max_size) = ns.max_size() {
+                ns_cfg = ns_cfg.max_size(max_size);
+            }
+            if let Some(max_age) = ns.max_age() {
+                ns_cfg = ns_cfg.expiration(max_age);
+            }
+            ds_cfg = ds_cfg.namespace(name, ns_cfg);
+        }
         let ds_path = cfg.datastore_path();
 
         Ok(SysMaster {
             cfg,
             broadcast: tx,
+            stream: webstream::channel(),
             mkr,
             to_drop: HashSet::default(),
             session: Arc::clone(&SHARED_SESSION),
@@ -260,12 +303,19 @@ impl SysMaster {
             peer_direct_tx: HashMap::new(),
             peer_cancel_tx: HashMap::new(),
             pending_console_replies: HashMap::new(),
+            sensor_health: HashMap::new(),
             peer_transport: PeerTransport::new(),
             datastore: Arc::new(Mutex::new(DataStorage::new(ds_cfg, ds_path)?)),
             model_watcher_token: None,
+            reboot: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// Registries, replicated to the standby master
+    pub(crate) fn ha_handles(&self) -> (Arc<Mutex<MinionRegistry>>, Arc<MasterCommandQueue>) {
+        (Arc::clone(&self.mreg), Arc::clone(&self.cmdq))
+    }
+
     /// Parse minion request
     fn to_request(&self, data: &str) -> Option<MinionMessage> {
         match serde_json::from_str::<MinionMessage>(data) {
@@ -324,8 +374,9 @@ impl SysMaster {
         Self::duplicate_replay_blocks_processing(identity)
     }
 
+    /// Record the execution receipt of a queued command, so it is not replayed anymore
     fn clear_completed_command_backlog(&self, minion_id: &str, cycle_id: &str) -> Result<usize, SysinspectError> {
-        self.cmdq.remove_by_replay_key(&replay_identity_for_master_command_cycle(minion_id, cycle_id).key())
+        self.cmdq.complete(minion_id, cycle_id)
     }
 
     #[cfg(test)]
@@ -365,7 +416,7 @@ impl SysMaster {
         ensure_console_keypair(&self.cfg.root_dir())?;
         std::fs::create_dir_all(self.cfg.console_keys_root()).map_err(SysinspectError::IoErr)?;
         let cmdq = self.command_queue_stats()?;
-        if cmdq.pending_commands > 0 || cmdq.replayed_commands > 0 {
+        if cmdq.pending_commands > 0 || cmdq.replayed_commands > 0 || cmdq.delivered_commands > 0 {
             log::warn!("Recovered durable master outbound backlog at startup: {}", cmdq.format());
         }
         self.backfill_cmdb().await?;
@@ -428,6 +479,52 @@ impl SysMaster {
         Arc::clone(&self.datastore)
     }
 
+    /// Get events registry service
+    pub fn evtipc(&self) -> Arc<DbIPCService> {
+        Arc::clone(&self.evtipc)
+    }
+
+    /// New event for the Web API live stream, stamped now
+    fn stream_event(kind: StreamEventKind) -> StreamEvent {
+        StreamEvent::new(kind, chrono::Utc::now().to_rfc3339())
+    }
+
+    /// Push an event to the current subscribers of the Web API live stream
+    fn publish(&self, evt: StreamEvent) {
+        _ = self.stream.send(evt);
+    }
+
+    /// Subscribe to the Web API live stream
+    pub(crate) fn subscribe_stream(&self) -> broadcast::Receiver<StreamEvent> {
+        self.stream.subscribe()
+    }
+
+    /// Publish a recorded action response of the cycle, started by the model query, to the Web API live stream
+    fn publish_action_events(&self, mid: &str, model: &str, pl: &HashMap<String, serde_json::Value>, accepted: bool) {
+        let get = |key: ProtoKey| util::dataconv::as_str(pl.get(&key.to_string()).cloned());
+        let (cycle_id, entity, action) = (get(ProtoKey::CycleId), get(ProtoKey::EntityId), get(ProtoKey::ActionId));
+        let response = pl.get(&ProtoKey::Response.to_string());
+        let outcome = response.and_then(|r| r.get("outcome")).and_then(|o| o.as_str()).map(str::to_string).unwrap_or_else(|| {
+            if response.and_then(|r| r.get("retcode")).and_then(|rc| rc.as_i64()).unwrap_or_default() == 0 {
+                "success".to_string()
+            } else {
+                "error".to_string()
+            }
+        });
+        let base = |kind| Self::stream_event(kind).cycle(&cycle_id).minion(mid).model(model);
+
+        if accepted {
+            self.publish(base(StreamEventKind::MinionAccepted));
+        }
+        self.publish(base(StreamEventKind::ActionResponse).action(&entity, &action, &outcome).data(json!(pl)));
+
+        if let Some(failures) =
+            pl.get(&ProtoKey::Constraints.to_string()).and_then(|c| c.get("failures")).and_then(|f| f.as_array()).filter(|f| !f.is_empty())
+        {
+            self.publish(base(StreamEventKind::ConstraintFailure).action(&entity, &action, &outcome).data(json!({"failures": failures})));
+        }
+    }
+
     pub async fn listener(&self) -> Result<TcpListener, SysinspectError> {
         Ok(TcpListener::bind(self.cfg.bind_addr()).await?)
     }
@@ -620,7 +717,7 @@ impl SysMaster {
         );
 
         let mut targeted = !mid.trim().is_empty();
-        if is_virtual && let Some(decided) = self.vmcluster.decide(&query, traits).await {
+        if is_virtual && let Some(decided) = self.vmcluster.decide(&query, traits, context).await {
             for hostname in decided.iter() {
                 log::debug!("Virtual minion requested. Decided to run on a physical: {}", hostname.bright_yellow());
                 tgt.add_hostname(hostname);
@@ -674,6 +771,20 @@ impl SysMaster {
         msg.set_target(tgt);
         msg.set_retcode(ProtoErrorCode::Success);
 
+        // Query path is `/<model>/[entities]/[states]` or `<model>:[labels]`
+        if !querypath.starts_with(SCHEME_COMMAND)
+            && let Some(model) = querypath.trim_start_matches('/').split(['/', ':']).next().filter(|m| !m.is_empty())
+        {
+            // Hashing the model takes a while, so it runs without holding the master
+            let store = ModelStore::new(&self.cfg.model_versions_root(), &self.cfg.fileserver_models_root(false));
+            let (cycle, model) = (msg.cycle().to_string(), model.to_string());
+            tokio::task::spawn_blocking(move || {
+                if let Err(err) = store.record_run(&cycle, &model) {
+                    log::warn!("Unable to record the version of model {model} for cycle {cycle}: {err}");
+                }
+            });
+        }
+
         log::debug!("Constructed message: {:#?}", msg);
 
         Some(msg)
@@ -760,6 +871,30 @@ impl SysMaster {
         Arc::clone(&self.taskreg)
     }
 
+    /// Return commands queued for the given minions (all if `None`) with their receipts.
+    pub(crate) fn queued_commands_data(&self, minions: Option<&[String]>) -> Result<Vec<ConsoleQueuedCommandRow>, SysinspectError> {
+        let at = |ms: u128| chrono::DateTime::from_timestamp_millis(ms as i64).unwrap_or_default();
+        Ok(self
+            .cmdq
+            .list(None)?
+            .into_iter()
+            .filter(|e| minions.is_none_or(|ids| ids.iter().any(|id| id == e.minion_id())))
+            .map(|e| ConsoleQueuedCommandRow {
+                id: e.id(),
+                minion_id: e.minion_id().to_string(),
+                cycle_id: e.message().cycle().to_string(),
+                scheme: e.message().target().scheme().to_string(),
+                state: e.state().as_str().to_string(),
+                supersede: e.supersede().map(ToString::to_string),
+                enqueued_at: at(e.enqueued_at_ms()),
+                expires_at: e.expires_at_ms().map(at),
+                delivered_at: e.delivered_at_ms().map(at),
+                finished_at: e.finished_at_ms().map(at),
+                error: e.error().map(ToString::to_string),
+            })
+            .collect())
+    }
+
     /// Return stats for the durable outbound master-command queue.
     pub(crate) fn command_queue_stats(&self) -> Result<MasterCommandQueueStats, SysinspectError> {
         self.cmdq.stats()
@@ -770,6 +905,7 @@ impl SysMaster {
         if let Some(mid) = self.conn_to_mid.remove(minion_addr) {
             log::info!("Minion connection {} dropped; clearing session for {}", minion_addr, mid);
             self.get_session().lock().await.remove(&mid);
+            self.publish(Self::stream_event(StreamEventKind::MinionOffline).minion(&mid));
 
             // Auto-hopstart: if this minion was just dispatched for self-upgrade,
             // try to bring it back up via SSH.
@@ -888,6 +1024,7 @@ impl SysMaster {
         }
 
         log::info!("{minion_id} connected successfully");
+        self.publish(Self::stream_event(StreamEventKind::MinionOnline).minion(minion_id));
         self.conn_to_mid.insert(minion_addr.to_string(), minion_id.to_string());
         self.get_session().lock().await.ping(minion_id, Some(sid));
         // Clean up post-upgrade pending — minion came back online on its own.
@@ -920,16 +1057,32 @@ impl SysMaster {
 
         self.get_session().lock().await.ping(minion_id, Some(pm.sid()));
         self.vmcluster.update_stats(minion_id, pm.payload().load_average(), pm.payload().disk_write_bps(), pm.payload().cpu_usage());
+        self.sensor_health.insert(minion_id.to_string(), pm.payload().sensors().clone());
 
         let taskreg = self.get_task_registry();
         let mut taskreg = taskreg.lock().await;
         taskreg.flush(minion_id, pm.payload().completed());
     }
 
+    /// Log a sensor lifecycle change on a minion and pass it to the Web API live stream.
+    fn on_sensor_notice(&self, minion_id: &str, payload: serde_json::Value) {
+        let get = |key: &str| payload.get(key).and_then(|v| v.as_str()).unwrap_or("?").to_string();
+        let (sid, listener, state) = (get("sid"), get("listener"), get("state"));
+        match payload.get("error").and_then(|v| v.as_str()) {
+            Some(err) => log::error!("Sensor '{sid}/{listener}' on {} {state}: {err}", minion_id.bright_yellow()),
+            None => log::info!("Sensor '{sid}/{listener}' on {} {state}", minion_id.bright_yellow()),
+        }
+
+        self.publish(Self::stream_event(StreamEventKind::SensorLifecycle).minion(minion_id).data(payload));
+    }
+
     /// Process a `bye` request and acknowledge the disconnect.
     async fn on_bye_request(&mut self, minion_addr: &str, minion_id: &str, payload: &str, bcast: &broadcast::Sender<MasterMessage>) {
         log::info!("Minion {} disconnects", minion_id);
-        self.conn_to_mid.remove(minion_addr);
+        self.sensor_health.remove(minion_id);
+        if self.conn_to_mid.remove(minion_addr).is_some() {
+            self.publish(Self::stream_event(StreamEventKind::MinionOffline).minion(minion_id));
+        }
         self.get_session().lock().await.remove(minion_id);
         self.peer_transport.remove_peer(minion_addr);
         self.peer_direct_tx.remove(minion_addr);
@@ -1050,6 +1203,16 @@ impl SysMaster {
                         OtelLogger::new(&pl).log(&mrec, DataExportType::Action);
                     }
 
+                    // Any event of the cycle is the delivery receipt of its queued command
+                    let cycle_id = util::dataconv::as_str(pl.get(&ProtoKey::CycleId.to_string()).cloned());
+                    let error = (util::dataconv::as_str(pl.get(&ProtoKey::ActionId.to_string()).cloned()) == "execution_error")
+                        .then(|| pl.get("response").and_then(|r| r.get("message")).and_then(|m| m.as_str()).unwrap_or("execution error").to_string());
+                    if !cycle_id.is_empty()
+                        && let Err(err) = m.cmdq.mark_delivered(req.id(), &cycle_id, error.as_deref())
+                    {
+                        log::error!("Failed to record delivery of cycle {} to {}: {}", cycle_id, req.id(), err);
+                    }
+
                     let sid = match m
                         .evtipc
                         .open_session(
@@ -1078,9 +1241,13 @@ impl SysMaster {
                         }
                     };
 
+                    // The first recorded event tells the minion has taken the cycle
+                    let accepted = m.evtipc.get_events(sid.sid(), &mid).await.is_ok_and(|events| events.is_empty());
+                    let stream_pl = pl.clone();
                     match m.evtipc.add_event(&sid, EventMinion::new(mid), pl).await {
                         Ok(_) => {
                             log::debug!("Event added for {} in {:#?}", req.id(), sid);
+                            m.publish_action_events(req.id(), sid.query(), &stream_pl, accepted);
                         }
                         Err(err) => {
                             log::error!("Unable to add event: {err}");
@@ -1157,6 +1324,14 @@ impl SysMaster {
                     }
                 });
             }
+            RequestType::SensorNotice => {
+                let c_master = Arc::clone(&master);
+                let c_id = req.id().to_string();
+                let c_payload = req.payload().clone();
+                tokio::spawn(async move {
+                    c_master.lock().await.on_sensor_notice(&c_id, c_payload);
+                });
+            }
             RequestType::SensorsSyncRequest => {
                 let c_master = Arc::clone(&master);
                 let c_bcast = bcast.clone();
@@ -1187,25 +1362,26 @@ impl SysMaster {
                         return;
                     };
                     let replay_key = replay_identity.key();
-                    match guard.evtipc.claim_replay_key(&replay_key).await {
-                        Ok(true) => {}
+                    let fresh = match guard.evtipc.claim_replay_key(&replay_key).await {
+                        Ok(true) => true,
                         Ok(false) => {
                             if Self::duplicate_replay_blocks_processing(&replay_identity) {
                                 log::debug!("Dropped duplicate ModelAck replay for {} with key {}", minion_id, replay_key);
                                 return;
                             }
                             log::debug!("Received duplicate ModelAck replay for {} with key {}; re-sending CycleAck", minion_id, replay_key);
+                            false
                         }
                         Err(err) => {
                             log::error!("Failed to claim ModelAck replay key for {}: {}", minion_id, err);
                             return;
                         }
-                    }
+                    };
                     let label = guard.resolved_peer_label(&minion_id, &c_addr).await;
                     match guard.clear_completed_command_backlog(&minion_id, &cycle_id) {
                         Ok(removed) if removed > 0 => match guard.cmdq.stats() {
                             Ok(stats) => log::debug!(
-                                "Cleared {} durable queued command(s) for {} cycle {}; remaining outbound backlog: {}",
+                                "Recorded execution of {} durable queued command(s) for {} cycle {}; remaining outbound backlog: {}",
                                 removed,
                                 label,
                                 cycle_id,
@@ -1225,6 +1401,10 @@ impl SysMaster {
                     // clearance. `Event` is intermediate progress only; `ModelAck` is the
                     // first point where the master may safely forget one per-minion command.
                     guard.taskreg.lock().await.deregister(&cycle_id, &minion_id);
+                    if fresh {
+                        let model = guard.evtipc.get_session(&cycle_id).await.map(|s| s.query().to_string()).unwrap_or_default();
+                        guard.publish(Self::stream_event(StreamEventKind::CycleComplete).cycle(&cycle_id).minion(&minion_id).model(&model));
+                    }
                     let ack = MasterMessage::new(RequestType::CycleAck, json!({"cycle_id": cycle_id}));
                     if let Some(tx) = guard.peer_direct_tx.get(&c_addr) {
                         match tx.try_send(OutgoingFrame::DirectMessage(Box::new(ack))) {
@@ -1281,17 +1461,43 @@ impl SysMaster {
             }
         };
         let mut replay_ready = false;
-        if !traits_payload.traits.is_empty() {
-            let traits: HashMap<String, serde_json::Value> = traits_payload.traits.into_iter().collect();
+        if !traits_payload.traits.is_empty() || traits_payload.delta {
             {
                 let mut mreg = self.mreg.lock().await;
+                let previous = mreg.get(&mid).unwrap_or_default().map(|m| m.get_traits().clone());
+                let delta = traits_payload.delta;
+                let traits: HashMap<String, serde_json::Value> = match (&previous, delta) {
+                    (Some(previous), true) => {
+                        let mut traits = previous.clone();
+                        for key in &traits_payload.removed {
+                            traits.remove(key);
+                        }
+                        traits.extend(traits_payload.traits);
+                        traits
+                    }
+                    (None, true) => {
+                        drop(mreg);
+                        log::warn!("Trait changes from {mid} without previous traits, asking for a full sync");
+                        let sid = self.get_session().lock().await.get_id(&mid);
+                        if let Some(sid) = sid {
+                            let msg = self.msg_request_traits(mid.clone(), sid);
+                            _ = self.broadcast().send(msg);
+                        }
+                        return;
+                    }
+                    _ => traits_payload.traits.into_iter().collect(),
+                };
                 if let Err(err) =
                     mreg.refresh(&mid, traits.clone(), traits_payload.static_keys.into_iter().collect(), traits_payload.fn_keys.into_iter().collect())
                 {
                     log::error!("Unable to sync traits: {err}");
                 } else {
-                    if let Err(err) = mreg.refresh_cmdb_observed(&mid, &traits) {
-                        log::error!("Unable to sync CMDB traits for {}: {err}", mid);
+                    match mreg.refresh_cmdb_observed(&mid, &traits, previous.as_ref()) {
+                        Ok(0) => {}
+                        Ok(changed) => {
+                            log::info!("Recorded {changed} trait change{} for minion {}", if changed == 1 { "" } else { "s" }, mid.green())
+                        }
+                        Err(err) => log::error!("Unable to sync CMDB traits for {}: {err}", mid),
                     }
                     if let Err(err) = mreg.clear_upgrade_required_if_checksum_matches(&mid, &traits) {
                         log::debug!("Unable to clear upgrade marker for {}: {err}", mid);
@@ -1313,7 +1519,8 @@ impl SysMaster {
                         .bright_green(),
                         mid.green()
                     );
-                    replay_ready = true;
+                    // Delta refreshes come from a live session, nothing to replay
+                    replay_ready = !delta;
                 }
             }
         }
@@ -1481,7 +1688,9 @@ impl SysMaster {
             match tx.send(OutgoingFrame::DirectMessage(Box::new(entry.message().clone()))).await {
                 Ok(()) => {
                     let guard = master.lock().await;
-                    if let Err(err) = guard.cmdq.set_state(entry.id(), MasterCommandState::Replayed) {
+                    // Internal commands report nothing back, handing them over is all that happens
+                    let state = if entry.is_internal() { MasterCommandState::Delivered } else { MasterCommandState::Replayed };
+                    if let Err(err) = guard.cmdq.set_state(entry.id(), state) {
                         log::error!("Failed to mark replayed command {} for {}: {}", entry.id(), minion_id, err);
                     }
                     replayed += 1;
@@ -1508,6 +1717,14 @@ impl SysMaster {
     /// Broadcast a logical master message so each connected peer can encode it with its own transport state.
     pub async fn bcast_master_msg(
         bcast: &broadcast::Sender<MasterMessage>, use_telemetry: bool, master: Arc<Mutex<SysMaster>>, msg: Option<MasterMessage>,
+    ) {
+        Self::bcast_queued_master_msg(bcast, use_telemetry, master, msg, MasterCommandOptions::default()).await;
+    }
+
+    /// Broadcast a message to all minions, queueing it for the offline ones with the given options
+    pub async fn bcast_queued_master_msg(
+        bcast: &broadcast::Sender<MasterMessage>, use_telemetry: bool, master: Arc<Mutex<SysMaster>>, msg: Option<MasterMessage>,
+        opts: MasterCommandOptions,
     ) {
         if msg.is_none() {
             log::error!("No message to broadcast");
@@ -1517,7 +1734,7 @@ impl SysMaster {
 
         {
             let mut guard = master.lock().await;
-            if let Err(err) = guard.queue_durable_command_targets(&msg).await {
+            if let Err(err) = guard.queue_durable_command_targets(&msg, opts).await {
                 log::error!("Failed to queue durable master command cycle {}: {}", msg.cycle(), err);
             }
         }
@@ -1551,6 +1768,83 @@ impl SysMaster {
         });
     }
 
+    /// Expire queued commands past their time to live and drop old receipts
+    pub async fn do_command_sweep(master: Arc<Mutex<Self>>) {
+        let (cmdq, history) = {
+            let guard = master.lock().await;
+            (Arc::clone(&guard.cmdq), guard.cfg.commands_history())
+        };
+        tokio::spawn(async move {
+            loop {
+                match cmdq.sweep(history) {
+                    Ok((expired, dropped)) if expired + dropped > 0 => {
+                        log::info!("Expired {expired} queued command(s), dropped {dropped} old command receipt(s)")
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::error!("Failed to sweep the command queue: {err}"),
+                }
+                _ = time::sleep(Duration::from_secs(60)).await;
+            }
+        });
+    }
+
+    /// Expire and evict datastore items, keeping those the active models still pull
+    pub async fn do_datastore_gc(master: Arc<Mutex<Self>>) {
+        let ds = Arc::clone(&master.lock().await.datastore);
+        tokio::spawn(async move {
+            loop {
+                // Exported models change with the configuration reload, so they are read on every pass
+                let (models_root, models) = {
+                    let guard = master.lock().await;
+                    (guard.cfg.fileserver_models_root(false), guard.cfg.fileserver_models().to_owned())
+                };
+                let refs = tokio::task::spawn_blocking(move || data_refs(&models_root, &models))
+                    .await
+                    .map_err(|err| SysinspectError::MasterGeneralError(err.to_string()))
+                    .and_then(|refs| refs);
+                match refs {
+                    Ok(refs) => Self::datastore_gc_pass(&ds, &refs).await,
+                    // Without the complete pin set a pulled item could be collected
+                    Err(err) => log::warn!("Skipping datastore cleanup: {err}"),
+                }
+                _ = time::sleep(Duration::from_secs(600)).await;
+            }
+        });
+    }
+
+    /// Clean up the datastore, keeping the items the models refer to.
+    /// Collection walks and removes files, so it runs on the blocking pool.
+    async fn datastore_gc_pass(ds: &Arc<Mutex<DataStorage>>, refs: &BTreeSet<DataRef>) {
+        let mut pins = DataPins::new();
+        for r in refs {
+            match (r.namespace_prefix, r.prefix) {
+                (true, prefix) => pins.spread(r.namespace.as_deref().unwrap_or_default(), &r.src, prefix),
+                (false, true) => pins.prefix(r.namespace.as_deref(), &r.src),
+                (false, false) => pins.name(r.namespace.as_deref(), &r.src),
+            }
+        }
+
+        let mut ds = Arc::clone(ds).lock_owned().await;
+        let report = tokio::task::spawn_blocking(move || {
+            ds.set_pins(pins);
+            ds.gc()
+        })
+        .await;
+        match report {
+            Ok(Ok(r)) if r.expired + r.evicted + r.orphans + r.uploads > 0 => log::info!(
+                "Datastore cleanup: {} expired, {} evicted, {} orphaned, {} abandoned upload(s), {} kept for the models",
+                r.expired,
+                r.evicted,
+                r.orphans,
+                r.uploads,
+                r.kept
+            ),
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => log::error!("Failed to clean up the datastore: {err}"),
+            Err(err) => log::error!("Datastore cleanup did not finish: {err}"),
+        }
+    }
+
     /// Encode one outbound frame for a connected peer, skipping broadcasts until the peer is allowed to receive them.
     async fn encode_outgoing_frame(&mut self, peer_addr: &str, frame: OutgoingFrame) -> Result<Option<Vec<u8>>, SysinspectError> {
         match frame {
@@ -1749,6 +2043,11 @@ impl SysMaster {
                             let mut master = master.lock().await;
                             (master.broadcast().clone(), master.msg_query(tdef.query().as_str()).await, master.cfg().clone())
                         };
+                        let outcome = if msg.is_some() { AuditOutcome::Success } else { AuditOutcome::Failure };
+                        let entry = AuditEntry::new(AUDIT_SCHEDULER, AuditChannel::Scheduler, tdef.name(), &tdef.query(), outcome);
+                        if let Err(err) = AuditJournal::new(&cfg.audit_root()).submit(entry).await {
+                            log::error!("Unable to record scheduled task {} in the audit journal: {err}", tdef.name());
+                        }
                         SysMaster::bcast_master_msg(&bcast, cfg.telemetry_enabled(), Arc::clone(&master), msg).await;
                     }
                 }) {
@@ -1778,6 +2077,17 @@ impl SysMaster {
 }
 
 pub(crate) async fn master(cfg: MasterConfig) -> Result<(), SysinspectError> {
+    // Active/standby: only the lease holder goes further
+    let ha = MasterHa::new(&cfg);
+    let mut lease = None;
+    if let Some(ha) = &ha {
+        ha.wait_for_leadership().await?;
+
+        // The startup can take longer than the lease time to live
+        lease = Some(ha.renew());
+        ha.restore(&cfg)?;
+    }
+
     let master = Arc::new(Mutex::new(SysMaster::new(cfg.clone())?));
     {
         let weak = Arc::downgrade(&master);
@@ -1802,7 +2112,7 @@ pub(crate) async fn master(cfg: MasterConfig) -> Result<(), SysinspectError> {
     // Start services
     let ipc = SysMaster::do_ipc_service(Arc::clone(&master)).await;
     let scheduler = SysMaster::do_scheduler_service(Arc::clone(&master)).await;
-    libtelemetry::init_otel_collector(cfg).await?;
+    libtelemetry::init_otel_collector(cfg.clone()).await?;
 
     SysMaster::do_console(Arc::clone(&master)).await;
     log::info!("Local console channel initialized");
@@ -1816,11 +2126,30 @@ pub(crate) async fn master(cfg: MasterConfig) -> Result<(), SysinspectError> {
     log::info!("Outgoing channel initialized");
 
     SysMaster::do_heartbeat(Arc::clone(&master)).await;
+    SysMaster::do_command_sweep(Arc::clone(&master)).await;
+    SysMaster::do_datastore_gc(Arc::clone(&master)).await;
     log::info!("Heartbeat service started");
 
-    // Listen for shutdown signal and cancel tasks
-    tokio::signal::ctrl_c().await.expect("Failed to listen for ctrl_c");
-    log::info!("Received shutdown signal.");
+    let replication = ha.as_ref().map(|ha| ha.start(Arc::clone(&master), cfg));
+    let (renewal, lost) = lease.unzip();
+    if replication.is_some() {
+        log::info!("Master state replication started");
+    }
+
+    // Listen for shutdown signal or a lost lease and cancel tasks
+    let stepdown = async move {
+        match lost {
+            Some(lost) => lost.await.unwrap_or_else(|_| "Master lease keeper stopped".to_string()),
+            None => std::future::pending().await,
+        }
+    };
+    let lost = tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            log::info!("Received shutdown signal.");
+            None
+        }
+        reason = stepdown => Some(reason),
+    };
 
     ipc.abort();
 
@@ -1828,5 +2157,18 @@ pub(crate) async fn master(cfg: MasterConfig) -> Result<(), SysinspectError> {
         scheduler.abort();
     }
 
+    for task in [replication, renewal].into_iter().flatten() {
+        task.abort();
+    }
+
+    if let Some(reason) = lost {
+        log::error!("{reason}, stepping down");
+        std::process::exit(1);
+    }
+
+    if let Some(ha) = &ha {
+        ha.release();
+    }
+
     std::process::exit(0);
 }
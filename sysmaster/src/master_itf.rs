@@ -1,11 +1,27 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use libcommon::SysinspectError;
 use libdatastore::resources::DataStorage;
-use libsysinspect::cfg::mmconf::MasterConfig;
-use libwebapi::MasterInterface;
+use libeventreg::kvdb::EventData;
+use libsysinspect::{
+    cfg::mmconf::MasterConfig,
+    console::{ConsoleQuery, ConsoleQueuedCommandRow, ConsoleResponse},
+    mdescr::versions::ModelStore,
+    traits::{SYS_NET_HOSTNAME, SYS_NET_HOSTNAME_FQDN, SYS_NET_HOSTNAME_IP},
+    util::dataconv::as_str,
+};
+use libwebapi::{
+    MasterInterface,
+    api::v1::cycles::{CycleEventInfo, CycleInfo, CycleMinionInfo},
+    stream::StreamEvent,
+};
 
-use crate::master::SysMaster;
+use tokio::sync::{broadcast, oneshot};
+
+use crate::{master::SysMaster, registry::cmdq::MasterCommandOptions};
+
+/// Console client name of the cluster commands issued through the Web API
+const WEBAPI_CLIENT: &str = "webapi";
 
 #[async_trait::async_trait]
 impl MasterInterface for SysMaster {
@@ -17,7 +33,7 @@ impl MasterInterface for SysMaster {
         self.datastore()
     }
 
-    async fn query(&mut self, query: String) -> Result<(), SysinspectError> {
+    async fn query(&mut self, query: String, ttl: Option<u64>, supersede: Option<String>) -> Result<String, SysinspectError> {
         let Some(msg) = self.msg_query(&query).await else {
             return Err(SysinspectError::InvalidQuery(format!("Invalid query: {query}")));
         };
@@ -26,14 +42,113 @@ impl MasterInterface for SysMaster {
             return Err(SysinspectError::InvalidQuery("Master pointer is not set".to_string()));
         };
 
-        SysMaster::bcast_master_msg(&self.broadcast(), self.cfg_ref().telemetry_enabled(), master.clone(), Some(msg.clone())).await;
+        let opts = MasterCommandOptions { ttl: ttl.map(Duration::from_secs), supersede };
+        SysMaster::bcast_queued_master_msg(&self.broadcast(), self.cfg_ref().telemetry_enabled(), master.clone(), Some(msg.clone()), opts).await;
 
         {
             let master_guard = master.lock().await;
             let ids = master_guard.get_minion_registry().lock().await.get_targeted_minions(msg.target(), false).await;
-            log::error!("Targeted minions: {:#?}", ids);
+            log::debug!("Targeted minions: {:#?}", ids);
+        }
+
+        Ok(msg.cycle().to_string())
+    }
+
+    async fn commands(&self, mid: Option<String>) -> Result<Vec<ConsoleQueuedCommandRow>, SysinspectError> {
+        self.queued_commands_data(mid.map(|mid| vec![mid]).as_deref())
+    }
+
+    async fn cycles(&self) -> Result<Vec<CycleInfo>, SysinspectError> {
+        let mut runs = ModelStore::new(&self.cfg_ref().model_versions_root(), &self.cfg_ref().fileserver_models_root(false)).runs()?;
+        Ok(self
+            .evtipc()
+            .get_sessions()
+            .await?
+            .into_iter()
+            .map(|s| {
+                let run = runs.remove(s.sid());
+                CycleInfo {
+                    cycle_id: s.sid().to_string(),
+                    query: s.query().to_string(),
+                    started_at: s.get_ts_rfc3339(),
+                    model_version: run.as_ref().and_then(|r| r.version),
+                    model_hash: run.map(|r| r.hash),
+                }
+            })
+            .collect())
+    }
+
+    async fn cycle_minions(&self, cycle_id: &str) -> Result<Option<Vec<CycleMinionInfo>>, SysinspectError> {
+        let evtipc = self.evtipc();
+        if evtipc.get_session(cycle_id).await.is_err() {
+            return Ok(None);
+        }
+
+        let mut minions = Vec::new();
+        for m in evtipc.get_minions(cycle_id).await? {
+            let events = evtipc.get_events(cycle_id, m.id()).await?.into_iter().map(cycle_event).collect::<Vec<_>>();
+            let mut outcomes = events.iter().map(|e| e.outcome.clone()).collect::<Vec<_>>();
+            outcomes.sort();
+            outcomes.dedup();
+
+            let mut hostname = as_str(m.get_trait(SYS_NET_HOSTNAME_FQDN).cloned());
+            if hostname.is_empty() {
+                hostname = as_str(m.get_trait(SYS_NET_HOSTNAME).cloned());
+            }
+
+            minions.push(CycleMinionInfo {
+                minion_id: m.id().to_string(),
+                hostname,
+                ipaddr: as_str(m.get_trait(SYS_NET_HOSTNAME_IP).cloned()),
+                errors: events.iter().filter(|e| e.is_error()).count(),
+                events: events.len(),
+                outcomes,
+            });
+        }
+
+        Ok(Some(minions))
+    }
+
+    async fn cycle_events(&self, cycle_id: &str, mid: &str) -> Result<Option<Vec<CycleEventInfo>>, SysinspectError> {
+        let evtipc = self.evtipc();
+        if evtipc.get_session(cycle_id).await.is_err() || !evtipc.get_minions(cycle_id).await?.iter().any(|m| m.id() == mid) {
+            return Ok(None);
         }
 
-        Ok(())
+        Ok(Some(evtipc.get_events(cycle_id, mid).await?.into_iter().map(cycle_event).collect()))
     }
+
+    async fn cluster_command(&self, query: ConsoleQuery) -> Result<oneshot::Receiver<ConsoleResponse>, SysinspectError> {
+        let Some(master) = self.as_ptr() else {
+            return Err(SysinspectError::MasterGeneralError("Master pointer is not set".to_string()));
+        };
+
+        let (bcast, cfg) = (self.broadcast(), self.cfg_ref().clone());
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = tx.send(SysMaster::dispatch_console_query(master, &bcast, &cfg, WEBAPI_CLIENT, query).await);
+        });
+
+        Ok(rx)
+    }
+
+    async fn minion_traits(&self, mid: &str) -> Result<Option<HashMap<String, serde_json::Value>>, SysinspectError> {
+        Ok(self.get_minion_registry().lock().await.get(mid)?.map(|r| r.get_traits().clone()))
+    }
+
+    async fn events(&self) -> broadcast::Receiver<StreamEvent> {
+        self.subscribe_stream()
+    }
+}
+
+fn cycle_event(e: EventData) -> CycleEventInfo {
+    CycleEventInfo::new(
+        e.get_cycle_id(),
+        e.get_entity_id(),
+        e.get_action_id(),
+        e.get_status_id(),
+        e.get_timestamp(),
+        e.get_constraints(),
+        e.get_response(),
+    )
 }
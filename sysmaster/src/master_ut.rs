@@ -285,18 +285,25 @@ fn duplicate_model_ack_still_allows_cycle_ack_resend() {
 }
 
 #[test]
-fn durable_master_queue_only_wraps_non_internal_commands() {
+fn durable_master_queue_wraps_model_commands_and_sync() {
     let mut model = MasterMessage::new(RequestType::Command, json!({"uri":"model://demo"}));
     model.set_target(MinionTarget::new("minion-1", ""));
 
-    let mut internal = MasterMessage::new(RequestType::Command, json!({"uri":"cmd://cluster/sync"}));
+    let mut internal = MasterMessage::new(RequestType::Command, json!({"uri":"cmd://cluster/reconnect"}));
     let mut internal_target = MinionTarget::new("minion-1", "");
-    internal_target.set_scheme("cmd://cluster/sync");
+    internal_target.set_scheme("cmd://cluster/reconnect");
     internal.set_target(internal_target);
 
+    // Sync is the only internal command worth catching up on after being offline
+    let mut sync = MasterMessage::new(RequestType::Command, json!({"uri":"cmd://cluster/sync"}));
+    let mut sync_target = MinionTarget::new("minion-1", "");
+    sync_target.set_scheme("cmd://cluster/sync");
+    sync.set_target(sync_target);
+
     let traits = MasterMessage::new(RequestType::Traits, json!("sid-1"));
 
     assert!(SysMaster::should_durably_queue_command(&model));
+    assert!(SysMaster::should_durably_queue_command(&sync));
     assert!(!SysMaster::should_durably_queue_command(&internal));
     assert!(!SysMaster::should_durably_queue_command(&traits));
 }
@@ -0,0 +1,191 @@
+//! State of an orchestrated rolling cluster reboot.
+//!
+//! The master reboots selected minions in batches. Each minion first drains
+//! (refuses new cycles and delivers its journal), then reboots the host via the
+//! `sys.service` module. A minion is verified once it came back with a fresh
+//! transport handshake, sent its traits again and reports the same minion
+//! binary checksum it had before.
+//! The rollout stops at the first failed batch so a broken image or boot
+//! configuration cannot take down the whole fleet.
+
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+
+/// Progress of one minion within a rolling reboot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebootPhase {
+    Pending,
+    Draining,
+    Rebooting,
+    Verified,
+    Failed(String),
+    Skipped(String),
+}
+
+impl RebootPhase {
+    /// Returns true if the minion needs no more attention from the orchestrator.
+    pub fn is_settled(&self) -> bool {
+        matches!(self, Self::Verified | Self::Failed(_) | Self::Skipped(_))
+    }
+}
+
+impl Display for RebootPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Draining => write!(f, "draining"),
+            Self::Rebooting => write!(f, "rebooting"),
+            Self::Verified => write!(f, "verified"),
+            Self::Failed(reason) => write!(f, "failed: {reason}"),
+            Self::Skipped(reason) => write!(f, "skipped: {reason}"),
+        }
+    }
+}
+
+/// One minion scheduled for reboot.
+#[derive(Debug, Clone)]
+pub struct RebootTarget {
+    pub mid: String,
+    pub host: String,
+    /// Minion binary checksum before the reboot
+    pub checksum: String,
+    pub phase: RebootPhase,
+    pub issued_at: Option<DateTime<Utc>>,
+}
+
+/// Rolling reboot across selected minions.
+#[derive(Debug, Clone)]
+pub struct RebootRollout {
+    pub started: DateTime<Utc>,
+    pub batch: usize,
+    pub targets: Vec<RebootTarget>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+impl RebootRollout {
+    /// Create a new rollout. Targets are `(minion id, host, binary checksum)`.
+    pub fn new(targets: Vec<(String, String, String)>, batch: usize) -> Self {
+        Self {
+            started: Utc::now(),
+            batch: batch.max(1),
+            targets: targets
+                .into_iter()
+                .map(|(mid, host, checksum)| RebootTarget { mid, host, checksum, phase: RebootPhase::Pending, issued_at: None })
+                .collect(),
+            finished: None,
+        }
+    }
+
+    /// Minion Ids of pending targets, grouped by batch size.
+    pub fn batches(&self) -> Vec<Vec<String>> {
+        self.targets
+            .iter()
+            .filter(|t| t.phase == RebootPhase::Pending)
+            .map(|t| t.mid.clone())
+            .collect::<Vec<_>>()
+            .chunks(self.batch)
+            .map(|c| c.to_vec())
+            .collect()
+    }
+
+    pub fn target(&self, mid: &str) -> Option<&RebootTarget> {
+        self.targets.iter().find(|t| t.mid == mid)
+    }
+
+    pub fn set_phase(&mut self, mid: &str, phase: RebootPhase) {
+        if let Some(t) = self.targets.iter_mut().find(|t| t.mid == mid) {
+            t.phase = phase;
+        }
+    }
+
+    /// Mark the reboot request as sent to the minion. Anything the minion
+    /// reports from now on may come from after the reboot.
+    pub fn draining(&mut self, mid: &str) {
+        if let Some(t) = self.targets.iter_mut().find(|t| t.mid == mid) {
+            t.phase = RebootPhase::Draining;
+            t.issued_at = Some(Utc::now());
+        }
+    }
+
+    /// Mark the reboot as accepted by the minion.
+    pub fn issued(&mut self, mid: &str) {
+        if let Some(t) = self.targets.iter_mut().find(|t| t.mid == mid) {
+            t.phase = RebootPhase::Rebooting;
+            t.issued_at.get_or_insert_with(Utc::now);
+        }
+    }
+
+    /// Update a rebooting minion from what the master currently sees.
+    ///
+    /// The minion is back once it is alive, has completed a transport
+    /// handshake and sent its traits after the reboot request was sent.
+    /// Its binary checksum must then be the one recorded before the reboot.
+    /// A minion without a known checksum cannot be verified.
+    pub fn observe(&mut self, mid: &str, alive: bool, checksum: &str, handshake: Option<DateTime<Utc>>, traits_at: Option<DateTime<Utc>>) {
+        let Some(t) = self.targets.iter_mut().find(|t| t.mid == mid) else {
+            return;
+        };
+        if t.phase != RebootPhase::Rebooting || !alive {
+            return;
+        }
+        let (Some(issued), Some(handshake), Some(traits_at)) = (t.issued_at, handshake, traits_at) else {
+            return;
+        };
+        if handshake <= issued || traits_at <= issued {
+            return;
+        }
+
+        t.phase = if t.checksum.is_empty() || checksum.is_empty() {
+            RebootPhase::Failed("binary checksum is unknown, reboot cannot be verified".to_string())
+        } else if t.checksum == checksum {
+            RebootPhase::Verified
+        } else {
+            RebootPhase::Failed(format!("binary checksum changed: {} → {}", t.checksum, checksum))
+        };
+    }
+
+    /// Fail every minion of the batch which is still rebooting.
+    pub fn expire(&mut self, mids: &[String], reason: &str) {
+        for t in self.targets.iter_mut().filter(|t| mids.contains(&t.mid) && !t.phase.is_settled()) {
+            t.phase = RebootPhase::Failed(reason.to_string());
+        }
+    }
+
+    /// Skip all minions that were not touched yet.
+    pub fn skip_remaining(&mut self, reason: &str) {
+        for t in self.targets.iter_mut().filter(|t| t.phase == RebootPhase::Pending) {
+            t.phase = RebootPhase::Skipped(reason.to_string());
+        }
+    }
+
+    /// Returns true if any minion of the batch failed.
+    pub fn failed(&self, mids: &[String]) -> bool {
+        self.targets.iter().any(|t| mids.contains(&t.mid) && matches!(t.phase, RebootPhase::Failed(_)))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.finished.is_none()
+    }
+
+    /// Human-readable status lines for the console.
+    pub fn format(&self) -> Vec<String> {
+        let verified = self.targets.iter().filter(|t| t.phase == RebootPhase::Verified).count();
+        let mut out = vec![format!(
+            "Rolling reboot started {}, batch {}, {}/{} verified, {}",
+            self.started.format("%Y-%m-%d %H:%M:%S"),
+            self.batch,
+            verified,
+            self.targets.len(),
+            match self.finished {
+                Some(finished) => format!("finished {}", finished.format("%Y-%m-%d %H:%M:%S")),
+                None => "in progress".to_string(),
+            }
+        )];
+        out.extend(self.targets.iter().map(|t| format!("{} ({}): {}", t.host, t.mid, t.phase)));
+        out
+    }
+}
+
+#[cfg(test)]
+#[path = "reboot_ut.rs"]
+mod reboot_ut;
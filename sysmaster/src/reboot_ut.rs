@@ -0,0 +1,92 @@
+use super::{RebootPhase, RebootRollout};
+use chrono::{Duration, Utc};
+
+fn rollout(batch: usize) -> RebootRollout {
+    RebootRollout::new(
+        vec![
+            ("m1".to_string(), "h1".to_string(), "sha-1".to_string()),
+            ("m2".to_string(), "h2".to_string(), "sha-2".to_string()),
+            ("m3".to_string(), "h3".to_string(), String::new()),
+        ],
+        batch,
+    )
+}
+
+#[test]
+fn batches_group_pending_targets() {
+    let mut r = rollout(2);
+    assert_eq!(r.batches(), vec![vec!["m1".to_string(), "m2".to_string()], vec!["m3".to_string()]]);
+
+    r.set_phase("m1", RebootPhase::Skipped("offline".to_string()));
+    assert_eq!(r.batches(), vec![vec!["m2".to_string(), "m3".to_string()]]);
+
+    // Zero batch size is clamped
+    assert_eq!(rollout(0).batches().len(), 3);
+}
+
+#[test]
+fn observe_verifies_after_fresh_handshake() {
+    let mut r = rollout(1);
+    r.issued("m1");
+    let issued = r.target("m1").unwrap().issued_at.unwrap();
+    let fresh = Some(Utc::now() + Duration::seconds(1));
+
+    // Offline or handshake from before the reboot changes nothing
+    r.observe("m1", false, "sha-1", Some(issued + Duration::seconds(5)), fresh);
+    r.observe("m1", true, "sha-1", Some(issued - Duration::seconds(5)), fresh);
+    r.observe("m1", true, "sha-1", None, fresh);
+    assert_eq!(r.target("m1").unwrap().phase, RebootPhase::Rebooting);
+
+    r.observe("m1", true, "sha-1", fresh, fresh);
+    assert_eq!(r.target("m1").unwrap().phase, RebootPhase::Verified);
+}
+
+#[test]
+fn observe_waits_for_traits_sent_after_the_request() {
+    let mut r = rollout(1);
+    r.draining("m1");
+    let sent = r.target("m1").unwrap().issued_at.unwrap();
+
+    // Confirmation arriving later keeps the time the request was sent
+    r.issued("m1");
+    assert_eq!(r.target("m1").unwrap().issued_at, Some(sent));
+
+    let fresh = Some(sent + Duration::seconds(5));
+    r.observe("m1", true, "sha-1", fresh, Some(sent - Duration::seconds(5)));
+    r.observe("m1", true, "sha-1", fresh, None);
+    assert_eq!(r.target("m1").unwrap().phase, RebootPhase::Rebooting);
+
+    r.observe("m1", true, "sha-1", fresh, fresh);
+    assert_eq!(r.target("m1").unwrap().phase, RebootPhase::Verified);
+}
+
+#[test]
+fn observe_fails_on_changed_checksum() {
+    let fresh = Some(Utc::now() + Duration::seconds(1));
+    let mut r = rollout(1);
+    r.issued("m2");
+    r.observe("m2", true, "sha-x", fresh, fresh);
+    assert!(matches!(r.target("m2").unwrap().phase, RebootPhase::Failed(_)));
+    assert!(r.failed(&["m2".to_string()]));
+
+    // Unknown checksum before the reboot cannot be compared
+    r.issued("m3");
+    r.observe("m3", true, "anything", fresh, fresh);
+    assert!(matches!(r.target("m3").unwrap().phase, RebootPhase::Failed(_)));
+
+    // Neither can a missing one after the reboot
+    r.issued("m1");
+    r.observe("m1", true, "", fresh, fresh);
+    assert!(matches!(r.target("m1").unwrap().phase, RebootPhase::Failed(_)));
+}
+
+#[test]
+fn failed_batch_stops_rollout() {
+    let mut r = rollout(1);
+    r.issued("m1");
+    r.expire(&["m1".to_string()], "did not come back");
+    r.skip_remaining("rollout stopped");
+    assert!(r.batches().is_empty());
+    assert_eq!(r.target("m2").unwrap().phase, RebootPhase::Skipped("rollout stopped".to_string()));
+    assert!(r.format()[1].contains("failed: did not come back"));
+}
@@ -1,6 +1,7 @@
 use libcommon::SysinspectError;
 use libsysproto::{
     MasterMessage,
+    query::SCHEME_COMMAND,
     replay::{replay_identity_for_master_command, replay_identity_for_master_command_cycle},
 };
 use serde::{Deserialize, Serialize};
@@ -9,7 +10,7 @@ use std::{
     collections::BTreeSet,
     path::Path,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(test)]
@@ -22,6 +23,51 @@ pub enum MasterCommandState {
     Pending,
     Replayed,
     Cleared,
+
+    /// The minion received the command
+    Delivered,
+
+    /// The minion finished the command (`ModelAck`)
+    Executed,
+
+    /// The minion finished the command with an execution error
+    Failed,
+
+    /// Not delivered, or delivered but not executed, within its time to live
+    Expired,
+
+    /// Replaced by a newer command with the same supersede key
+    Superseded,
+}
+
+impl MasterCommandState {
+    /// Returns true if nothing more happens to the command
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Cleared | Self::Executed | Self::Failed | Self::Expired | Self::Superseded)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Replayed => "replayed",
+            Self::Cleared => "cleared",
+            Self::Delivered => "delivered",
+            Self::Executed => "executed",
+            Self::Failed => "failed",
+            Self::Expired => "expired",
+            Self::Superseded => "superseded",
+        }
+    }
+}
+
+/// Queueing options of one command
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MasterCommandOptions {
+    /// Drop the command if it was not delivered within this time
+    pub ttl: Option<Duration>,
+
+    /// Only the latest command with the same key is kept per minion
+    pub supersede: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +78,16 @@ pub struct QueuedMasterCommand {
     message: MasterMessage,
     state: MasterCommandState,
     enqueued_at_ms: u128,
+    #[serde(default)]
+    expires_at_ms: Option<u128>,
+    #[serde(default)]
+    supersede: Option<String>,
+    #[serde(default)]
+    delivered_at_ms: Option<u128>,
+    #[serde(default)]
+    finished_at_ms: Option<u128>,
+    #[serde(default)]
+    error: Option<String>,
 }
 
 impl QueuedMasterCommand {
@@ -43,6 +99,10 @@ impl QueuedMasterCommand {
         &self.replay_key
     }
 
+    pub fn minion_id(&self) -> &str {
+        &self.minion_id
+    }
+
     pub fn message(&self) -> &MasterMessage {
         &self.message
     }
@@ -50,18 +110,75 @@ impl QueuedMasterCommand {
     pub fn state(&self) -> MasterCommandState {
         self.state
     }
+
+    pub fn enqueued_at_ms(&self) -> u128 {
+        self.enqueued_at_ms
+    }
+
+    pub fn expires_at_ms(&self) -> Option<u128> {
+        self.expires_at_ms
+    }
+
+    pub fn supersede(&self) -> Option<&str> {
+        self.supersede.as_deref()
+    }
+
+    pub fn delivered_at_ms(&self) -> Option<u128> {
+        self.delivered_at_ms
+    }
+
+    pub fn finished_at_ms(&self) -> Option<u128> {
+        self.finished_at_ms
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Internal `cmd://` commands have no execution receipt, delivery is all the master learns.
+    pub fn is_internal(&self) -> bool {
+        self.message.target().scheme().starts_with(SCHEME_COMMAND)
+    }
+
+    fn is_expired(&self, now_ms: u128) -> bool {
+        self.expires_at_ms.is_some_and(|at| at <= now_ms)
+    }
+
+    /// Returns true if the command still has to be (re)sent to the minion.
+    /// Delivered model commands are replayed until executed or expired, the minion drops duplicates.
+    fn is_replayable(&self, now_ms: u128) -> bool {
+        match self.state {
+            MasterCommandState::Pending | MasterCommandState::Replayed => !self.is_expired(now_ms),
+            MasterCommandState::Delivered => !self.is_internal() && !self.is_expired(now_ms),
+            _ => false,
+        }
+    }
+
+    fn set_state(&mut self, state: MasterCommandState, now_ms: u128) {
+        self.state = state;
+        if matches!(state, MasterCommandState::Delivered | MasterCommandState::Executed | MasterCommandState::Failed) {
+            self.delivered_at_ms.get_or_insert(now_ms);
+        }
+        if state.is_final() {
+            self.finished_at_ms = Some(now_ms);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct MasterCommandQueueStats {
     pub pending_commands: usize,
     pub replayed_commands: usize,
+    pub delivered_commands: usize,
     pub queued_minions: usize,
 }
 
 impl MasterCommandQueueStats {
     pub fn format(self) -> String {
-        format!("{}/{}/{} pending/replayed/minions", self.pending_commands, self.replayed_commands, self.queued_minions)
+        format!(
+            "{}/{}/{}/{} pending/replayed/delivered/minions",
+            self.pending_commands, self.replayed_commands, self.delivered_commands, self.queued_minions
+        )
     }
 }
 
@@ -115,7 +232,16 @@ impl MasterCommandQueue {
         replay_identity_for_master_command_cycle(minion_id, cycle_id).key()
     }
 
-    pub fn enqueue(&self, minion_id: &str, message: &MasterMessage) -> Result<u64, SysinspectError> {
+    pub fn enqueue(&self, minion_id: &str, message: &MasterMessage, opts: &MasterCommandOptions) -> Result<u64, SysinspectError> {
+        let now = Self::now_ms();
+        if let Some(key) = &opts.supersede {
+            for mut old in self.entries_where(|e| e.minion_id == minion_id && e.supersede.as_ref() == Some(key) && !e.state.is_final())? {
+                log::debug!("Command {} for {} is superseded by cycle {}", old.id, minion_id, message.cycle());
+                old.set_state(MasterCommandState::Superseded, now);
+                self.entries.insert(Self::u64_key(old.id), serde_json::to_vec(&old)?)?;
+            }
+        }
+
         let id = self.next_id()?;
         let entry = QueuedMasterCommand {
             id,
@@ -123,7 +249,12 @@ impl MasterCommandQueue {
             minion_id: minion_id.to_string(),
             message: message.clone(),
             state: MasterCommandState::Pending,
-            enqueued_at_ms: Self::now_ms(),
+            enqueued_at_ms: now,
+            expires_at_ms: opts.ttl.map(|ttl| now + ttl.as_millis()),
+            supersede: opts.supersede.clone(),
+            delivered_at_ms: None,
+            finished_at_ms: None,
+            error: None,
         };
         self.entries.insert(Self::u64_key(id), serde_json::to_vec(&entry)?)?;
         self.db.flush()?;
@@ -131,6 +262,19 @@ impl MasterCommandQueue {
         Ok(id)
     }
 
+    fn entries_where(&self, filter: impl Fn(&QueuedMasterCommand) -> bool) -> Result<Vec<QueuedMasterCommand>, SysinspectError> {
+        let mut out = Vec::new();
+        for item in self.entries.iter() {
+            let (_k, v) = item?;
+            let entry: QueuedMasterCommand = serde_json::from_slice(&v)?;
+            if filter(&entry) {
+                out.push(entry);
+            }
+        }
+        out.sort_by_key(|entry| entry.id);
+        Ok(out)
+    }
+
     fn load_entry(&self, id: u64) -> Result<Option<QueuedMasterCommand>, SysinspectError> {
         let Some(raw) = self.entries.get(Self::u64_key(id))? else {
             return Ok(None);
@@ -148,50 +292,89 @@ impl MasterCommandQueue {
         let Some(mut entry) = self.load_entry(id)? else {
             return Ok(false);
         };
-        entry.state = state;
+        entry.set_state(state, Self::now_ms());
         self.save_entry(&entry)?;
         Ok(true)
     }
 
-    pub fn remove(&self, id: u64) -> Result<bool, SysinspectError> {
-        let removed = self.entries.remove(Self::u64_key(id))?.is_some();
-        if removed {
-            self.db.flush()?;
+    /// Delivery receipt: the minion reported progress of the cycle.
+    /// An execution error is kept until the execution receipt arrives.
+    pub fn mark_delivered(&self, minion_id: &str, cycle_id: &str, error: Option<&str>) -> Result<usize, SysinspectError> {
+        let key = Self::replay_key(minion_id, cycle_id);
+        let entries = self.entries_where(|e| {
+            e.replay_key == key && matches!(e.state, MasterCommandState::Pending | MasterCommandState::Replayed | MasterCommandState::Delivered)
+        })?;
+        let now = Self::now_ms();
+        for mut entry in entries.iter().cloned() {
+            entry.set_state(MasterCommandState::Delivered, now);
+            if let Some(error) = error {
+                entry.error = Some(error.to_string());
+            }
+            self.save_entry(&entry)?;
         }
-        Ok(removed)
+        Ok(entries.len())
     }
 
-    pub fn remove_by_replay_key(&self, replay_key: &str) -> Result<usize, SysinspectError> {
-        let mut to_remove = Vec::new();
+    /// Execution receipt: the minion acknowledged the finished cycle.
+    pub fn complete(&self, minion_id: &str, cycle_id: &str) -> Result<usize, SysinspectError> {
+        let key = Self::replay_key(minion_id, cycle_id);
+        let entries = self.entries_where(|e| e.replay_key == key && !e.state.is_final())?;
+        let now = Self::now_ms();
+        for mut entry in entries.iter().cloned() {
+            let state = if entry.error.is_some() { MasterCommandState::Failed } else { MasterCommandState::Executed };
+            entry.set_state(state, now);
+            self.save_entry(&entry)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Expire unfinished commands past their time to live and drop finished
+    /// records older than `history`. Returns the number of expired and dropped entries.
+    pub fn sweep(&self, history: Duration) -> Result<(usize, usize), SysinspectError> {
+        let now = Self::now_ms();
+        let mut expired = 0usize;
+        let mut dropped = 0usize;
         for item in self.entries.iter() {
             let (k, v) = item?;
-            let entry: QueuedMasterCommand = serde_json::from_slice(&v)?;
-            if entry.replay_key == replay_key {
-                to_remove.push(k);
+            let mut entry: QueuedMasterCommand = serde_json::from_slice(&v)?;
+            if matches!(entry.state, MasterCommandState::Pending | MasterCommandState::Replayed | MasterCommandState::Delivered)
+                && entry.is_expired(now)
+            {
+                entry.set_state(MasterCommandState::Expired, now);
+                self.entries.insert(k, serde_json::to_vec(&entry)?)?;
+                expired += 1;
+            } else if entry.finished_at_ms.is_some_and(|at| at + history.as_millis() <= now) {
+                self.entries.remove(k)?;
+                dropped += 1;
             }
         }
-
-        let removed = to_remove.len();
-        for key in to_remove {
-            self.entries.remove(key)?;
+        if expired + dropped > 0 {
+            self.db.flush()?;
         }
-        if removed > 0 {
+        Ok((expired, dropped))
+    }
+
+    /// All known commands, optionally of one minion only, in the enqueue order.
+    pub fn list(&self, minion_id: Option<&str>) -> Result<Vec<QueuedMasterCommand>, SysinspectError> {
+        self.entries_where(|e| minion_id.is_none_or(|mid| e.minion_id == mid))
+    }
+
+    pub fn remove(&self, id: u64) -> Result<bool, SysinspectError> {
+        let removed = self.entries.remove(Self::u64_key(id))?.is_some();
+        if removed {
             self.db.flush()?;
         }
         Ok(removed)
     }
 
     pub fn pending_for_minion(&self, minion_id: &str) -> Result<Vec<QueuedMasterCommand>, SysinspectError> {
-        let mut out = Vec::new();
-        for item in self.entries.iter() {
-            let (_k, v) = item?;
-            let entry: QueuedMasterCommand = serde_json::from_slice(&v)?;
-            if entry.minion_id == minion_id && entry.state != MasterCommandState::Cleared {
-                out.push(entry);
-            }
-        }
-        out.sort_by_key(|entry| entry.id);
-        Ok(out)
+        let now = Self::now_ms();
+        self.entries_where(|e| e.minion_id == minion_id && e.is_replayable(now))
+    }
+
+    /// Write the whole queue into a snapshot file for the standby master
+    pub fn export_snapshot(&self, path: &Path) -> Result<(), SysinspectError> {
+        crate::ha::export_db(&self.db, path)
     }
 
     pub fn stats(&self) -> Result<MasterCommandQueueStats, SysinspectError> {
@@ -209,7 +392,11 @@ impl MasterCommandQueue {
                     stats.replayed_commands += 1;
                     minions.insert(entry.minion_id);
                 }
-                MasterCommandState::Cleared => {}
+                MasterCommandState::Delivered => {
+                    stats.delivered_commands += 1;
+                    minions.insert(entry.minion_id);
+                }
+                _ => {}
             }
         }
         stats.queued_minions = minions.len();
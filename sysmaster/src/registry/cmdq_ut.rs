@@ -1,4 +1,4 @@
-use super::{MasterCommandQueue, MasterCommandQueueStats, MasterCommandState};
+use super::{MasterCommandOptions, MasterCommandQueue, MasterCommandQueueStats, MasterCommandState};
 use libsysproto::{MasterMessage, MinionTarget, rqtypes::RequestType};
 use serde_json::json;
 use std::time::Duration;
@@ -30,7 +30,7 @@ fn enqueue_persists_across_reopen() {
     let msg = queued_message("minion-1");
     let cycle_id = msg.cycle().clone();
 
-    let id = queue.enqueue("minion-1", &msg).unwrap();
+    let id = queue.enqueue("minion-1", &msg, &MasterCommandOptions::default()).unwrap();
     drop(queue);
 
     let reopened = reopen_queue_with_retry(tmp.path());
@@ -51,9 +51,9 @@ fn pending_for_minion_returns_enqueue_order() {
     let second = queued_message("minion-1");
     let third = queued_message("minion-2");
 
-    let id1 = queue.enqueue("minion-1", &first).unwrap();
-    let id2 = queue.enqueue("minion-1", &second).unwrap();
-    let _ = queue.enqueue("minion-2", &third).unwrap();
+    let id1 = queue.enqueue("minion-1", &first, &MasterCommandOptions::default()).unwrap();
+    let id2 = queue.enqueue("minion-1", &second, &MasterCommandOptions::default()).unwrap();
+    let _ = queue.enqueue("minion-2", &third, &MasterCommandOptions::default()).unwrap();
 
     let pending = queue.pending_for_minion("minion-1").unwrap();
     assert_eq!(pending.iter().map(|entry| entry.id()).collect::<Vec<u64>>(), vec![id1, id2]);
@@ -67,13 +67,33 @@ fn stats_report_pending_replayed_and_distinct_minions() {
     let second = queued_message("minion-1");
     let third = queued_message("minion-2");
 
-    let id1 = queue.enqueue("minion-1", &first).unwrap();
-    let id2 = queue.enqueue("minion-1", &second).unwrap();
-    let _ = queue.enqueue("minion-2", &third).unwrap();
+    let id1 = queue.enqueue("minion-1", &first, &MasterCommandOptions::default()).unwrap();
+    let id2 = queue.enqueue("minion-1", &second, &MasterCommandOptions::default()).unwrap();
+    let _ = queue.enqueue("minion-2", &third, &MasterCommandOptions::default()).unwrap();
     assert!(queue.set_state(id2, MasterCommandState::Replayed).unwrap());
     assert!(queue.set_state(id1, MasterCommandState::Cleared).unwrap());
 
-    assert_eq!(queue.stats().unwrap(), MasterCommandQueueStats { pending_commands: 1, replayed_commands: 1, queued_minions: 2 });
+    assert_eq!(
+        queue.stats().unwrap(),
+        MasterCommandQueueStats { pending_commands: 1, replayed_commands: 1, delivered_commands: 0, queued_minions: 2 }
+    );
+}
+
+#[test]
+fn stats_count_delivered_commands_and_their_minions() {
+    let tmp = tempfile::tempdir().unwrap();
+    let queue = MasterCommandQueue::open(tmp.path()).unwrap();
+    let pending = queued_message("minion-1");
+    let delivered = queued_message("minion-2");
+
+    queue.enqueue("minion-1", &pending, &MasterCommandOptions::default()).unwrap();
+    queue.enqueue("minion-2", &delivered, &MasterCommandOptions::default()).unwrap();
+    assert_eq!(queue.mark_delivered("minion-2", delivered.cycle(), None).unwrap(), 1);
+
+    assert_eq!(
+        queue.stats().unwrap(),
+        MasterCommandQueueStats { pending_commands: 1, replayed_commands: 0, delivered_commands: 1, queued_minions: 2 }
+    );
 }
 
 #[test]
@@ -81,7 +101,7 @@ fn remove_drops_command_from_backlog() {
     let tmp = tempfile::tempdir().unwrap();
     let queue = MasterCommandQueue::open(tmp.path()).unwrap();
     let msg = queued_message("minion-1");
-    let id = queue.enqueue("minion-1", &msg).unwrap();
+    let id = queue.enqueue("minion-1", &msg, &MasterCommandOptions::default()).unwrap();
 
     assert!(queue.remove(id).unwrap());
     assert!(!queue.remove(id).unwrap());
@@ -89,18 +109,89 @@ fn remove_drops_command_from_backlog() {
 }
 
 #[test]
-fn remove_by_replay_key_clears_matching_entries() {
+fn receipts_move_command_through_delivery_and_execution() {
     let tmp = tempfile::tempdir().unwrap();
     let queue = MasterCommandQueue::open(tmp.path()).unwrap();
     let first = queued_message("minion-1");
     let second = queued_message("minion-1");
-    let key = format!("mcmd|minion-1|{}", first.cycle());
 
-    let _ = queue.enqueue("minion-1", &first).unwrap();
-    let _ = queue.enqueue("minion-1", &second).unwrap();
+    let id1 = queue.enqueue("minion-1", &first, &MasterCommandOptions::default()).unwrap();
+    let id2 = queue.enqueue("minion-1", &second, &MasterCommandOptions::default()).unwrap();
 
-    assert_eq!(queue.remove_by_replay_key(&key).unwrap(), 1);
-    let pending = queue.pending_for_minion("minion-1").unwrap();
-    assert_eq!(pending.len(), 1);
-    assert_eq!(pending[0].message().cycle(), second.cycle());
+    assert_eq!(queue.mark_delivered("minion-1", first.cycle(), None).unwrap(), 1);
+    assert_eq!(queue.mark_delivered("minion-1", second.cycle(), Some("boom")).unwrap(), 1);
+
+    // Delivered model commands stay replayable until executed
+    assert_eq!(queue.pending_for_minion("minion-1").unwrap().len(), 2);
+
+    assert_eq!(queue.complete("minion-1", first.cycle()).unwrap(), 1);
+    assert_eq!(queue.complete("minion-1", second.cycle()).unwrap(), 1);
+    assert_eq!(queue.complete("minion-1", second.cycle()).unwrap(), 0);
+    assert!(queue.pending_for_minion("minion-1").unwrap().is_empty());
+
+    let all = queue.list(Some("minion-1")).unwrap();
+    assert_eq!(
+        all.iter().map(|e| (e.id(), e.state())).collect::<Vec<_>>(),
+        vec![(id1, MasterCommandState::Executed), (id2, MasterCommandState::Failed)]
+    );
+    assert!(all[0].delivered_at_ms().is_some() && all[0].finished_at_ms().is_some());
+    assert_eq!(all[1].error(), Some("boom"));
+}
+
+#[test]
+fn supersede_keeps_only_latest_command() {
+    let tmp = tempfile::tempdir().unwrap();
+    let queue = MasterCommandQueue::open(tmp.path()).unwrap();
+    let opts = MasterCommandOptions { ttl: None, supersede: Some("sync".to_string()) };
+
+    let id1 = queue.enqueue("minion-1", &queued_message("minion-1"), &opts).unwrap();
+    let id2 = queue.enqueue("minion-2", &queued_message("minion-2"), &opts).unwrap();
+    let id3 = queue.enqueue("minion-1", &queued_message("minion-1"), &opts).unwrap();
+
+    assert_eq!(queue.pending_for_minion("minion-1").unwrap().iter().map(|e| e.id()).collect::<Vec<_>>(), vec![id3]);
+    assert_eq!(queue.pending_for_minion("minion-2").unwrap().iter().map(|e| e.id()).collect::<Vec<_>>(), vec![id2]);
+    assert_eq!(queue.list(Some("minion-1")).unwrap()[0].state(), MasterCommandState::Superseded);
+    assert_eq!(queue.list(None).unwrap()[0].id(), id1);
+}
+
+#[test]
+fn sweep_expires_undelivered_and_drops_old_receipts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let queue = MasterCommandQueue::open(tmp.path()).unwrap();
+    let short = MasterCommandOptions { ttl: Some(Duration::from_millis(1)), supersede: None };
+    let long = MasterCommandOptions { ttl: Some(Duration::from_secs(3600)), supersede: None };
+
+    let expiring = queue.enqueue("minion-1", &queued_message("minion-1"), &short).unwrap();
+    let kept = queue.enqueue("minion-1", &queued_message("minion-1"), &long).unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+
+    // Expired commands are never replayed, even before the sweep
+    assert_eq!(queue.pending_for_minion("minion-1").unwrap().iter().map(|e| e.id()).collect::<Vec<_>>(), vec![kept]);
+
+    assert_eq!(queue.sweep(Duration::from_secs(3600)).unwrap(), (1, 0));
+    assert_eq!(queue.list(None).unwrap()[0].id(), expiring);
+    assert_eq!(queue.list(None).unwrap()[0].state(), MasterCommandState::Expired);
+
+    assert_eq!(queue.sweep(Duration::ZERO).unwrap(), (0, 1));
+    assert_eq!(queue.list(None).unwrap().len(), 1);
+}
+
+#[test]
+fn sweep_expires_delivered_but_never_executed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let queue = MasterCommandQueue::open(tmp.path()).unwrap();
+    let short = MasterCommandOptions { ttl: Some(Duration::from_millis(1)), supersede: None };
+    let msg = queued_message("minion-1");
+
+    queue.enqueue("minion-1", &msg, &short).unwrap();
+    assert_eq!(queue.mark_delivered("minion-1", msg.cycle(), None).unwrap(), 1);
+    std::thread::sleep(Duration::from_millis(5));
+
+    assert!(queue.pending_for_minion("minion-1").unwrap().is_empty());
+    assert_eq!(queue.sweep(Duration::from_secs(3600)).unwrap(), (1, 0));
+    assert_eq!(queue.list(None).unwrap()[0].state(), MasterCommandState::Expired);
+    assert_eq!(queue.stats().unwrap().delivered_commands, 0);
+
+    assert_eq!(queue.sweep(Duration::ZERO).unwrap(), (0, 1));
+    assert!(queue.list(None).unwrap().is_empty());
 }
@@ -12,7 +12,7 @@ use sled::{Db, Tree};
 use std::{
     collections::{BTreeSet, HashMap},
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -172,6 +172,11 @@ impl MinionRegistry {
         })
     }
 
+    /// Write the whole registry into a snapshot file for the standby master
+    pub fn export_snapshot(&self, path: &Path) -> Result<(), SysinspectError> {
+        crate::ha::export_db(&self.conn, path)
+    }
+
     fn get_tree(&self, tid: &str) -> Result<Tree, SysinspectError> {
         let tree = self.conn.open_tree(tid);
         if let Err(err) = tree {
@@ -237,10 +242,16 @@ impl MinionRegistry {
         self.add_cmdb(mid, &record)
     }
 
-    pub fn refresh_cmdb_observed(&mut self, mid: &str, traits: &HashMap<String, Value>) -> Result<(), SysinspectError> {
+    /// Update observed host facts from fresh traits. If the previous traits
+    /// are known, every changed key is also added to the trait history.
+    pub fn refresh_cmdb_observed(
+        &mut self, mid: &str, traits: &HashMap<String, Value>, previous: Option<&HashMap<String, Value>>,
+    ) -> Result<usize, SysinspectError> {
         let mut record = self.get_cmdb(mid)?.unwrap_or_else(|| MinionCmdbRecord::new(mid.to_string()));
         record.apply_observed_traits(traits);
-        self.add_cmdb(mid, &record)
+        let changed = previous.map(|previous| record.record_trait_changes(previous, traits)).unwrap_or_default();
+        self.add_cmdb(mid, &record)?;
+        Ok(changed)
     }
 
     pub fn reconcile_cmdb(&mut self, mid: &str, max_age: std::time::Duration) -> Result<bool, SysinspectError> {
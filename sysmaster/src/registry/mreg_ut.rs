@@ -342,6 +342,7 @@ fn cmdb_for_test(host: &str, user: &str, root: &str, bin: &str, config: &str, ma
         backend: None,
         managed_by_init,
         updated_at: chrono::Utc::now(),
+        trait_history: Vec::new(),
     }
 }
 
@@ -478,3 +479,48 @@ fn clear_all_post_upgrade_pending_empties() {
     assert_eq!(registry.post_upgrade_pending_count().unwrap(), 0);
     assert!(!registry.has_post_upgrade_pending("mid-1").unwrap());
 }
+
+// ---------------------------------------------------------------------------
+//  Trait change history
+// ---------------------------------------------------------------------------
+
+#[test]
+fn refresh_cmdb_observed_records_trait_changes() {
+    let mut registry = registry_with_one_minion();
+    let mid = "30006546535e428aba0a0caa6712e225";
+    let first: HashMap<_, _> = [("system.kernel".to_string(), json!("6.1")), ("net.eth0".to_string(), json!("10.0.0.1"))].into_iter().collect();
+
+    // Initial inventory is not a change
+    assert_eq!(registry.refresh_cmdb_observed(mid, &first, None).unwrap(), 0);
+
+    let mut second = first.clone();
+    second.insert("system.kernel".to_string(), json!("6.8"));
+    second.remove("net.eth0");
+    second.insert("net.eth1".to_string(), json!("10.0.0.2"));
+    assert_eq!(registry.refresh_cmdb_observed(mid, &second, Some(&first)).unwrap(), 3);
+    assert_eq!(registry.refresh_cmdb_observed(mid, &second, Some(&second)).unwrap(), 0);
+
+    let record = registry.get_cmdb(mid).unwrap().unwrap();
+    let kernel = record.trait_history(Some("system.kernel"));
+    assert_eq!(kernel.len(), 1);
+    assert_eq!(kernel[0].old(), Some(&json!("6.1")));
+    assert_eq!(kernel[0].new_value(), Some(&json!("6.8")));
+
+    let gone = record.trait_history(Some("net.eth0"));
+    assert_eq!(gone[0].new_value(), None);
+    assert_eq!(record.trait_history(None).len(), 3);
+}
+
+#[test]
+fn trait_history_is_capped() {
+    let mut record = MinionCmdbRecord::new("mid".to_string());
+    for i in 0..crate::registry::rec::CMDB_TRAIT_HISTORY_MAX + 10 {
+        let old: HashMap<_, _> = [("counter".to_string(), json!(i))].into_iter().collect();
+        let new: HashMap<_, _> = [("counter".to_string(), json!(i + 1))].into_iter().collect();
+        record.record_trait_changes(&old, &new);
+    }
+
+    let history = record.trait_history(None);
+    assert_eq!(history.len(), crate::registry::rec::CMDB_TRAIT_HISTORY_MAX);
+    assert_eq!(history[0].old(), Some(&json!(10)));
+}
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::{BTreeSet, HashMap};
 
+/// Maximum number of trait changes kept per minion
+pub const CMDB_TRAIT_HISTORY_MAX: usize = 1000;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MinionRecord {
     id: String,
@@ -11,6 +14,9 @@ pub struct MinionRecord {
     static_keys: BTreeSet<String>,
     #[serde(default)]
     fn_keys: BTreeSet<String>,
+    /// When the minion sent these traits
+    #[serde(default)]
+    refreshed_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -23,6 +29,36 @@ pub struct MinionCmdbStartup {
     backend: String,
 }
 
+/// One observed change of a minion trait. A missing old value means the
+/// trait appeared, a missing new value means it disappeared.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TraitChange {
+    pub(crate) at: DateTime<Utc>,
+    pub(crate) key: String,
+    #[serde(default)]
+    pub(crate) old: Option<Value>,
+    #[serde(default)]
+    pub(crate) new: Option<Value>,
+}
+
+impl TraitChange {
+    pub fn at(&self) -> DateTime<Utc> {
+        self.at
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn old(&self) -> Option<&Value> {
+        self.old.as_ref()
+    }
+
+    pub fn new_value(&self) -> Option<&Value> {
+        self.new.as_ref()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct MinionCmdbRecord {
     pub(crate) mid: String,
@@ -47,6 +83,8 @@ pub struct MinionCmdbRecord {
     #[serde(default)]
     pub(crate) managed_by_init: Option<bool>,
     pub(crate) updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) trait_history: Vec<TraitChange>,
 }
 
 impl MinionCmdbStartup {
@@ -94,6 +132,7 @@ impl MinionCmdbRecord {
             backend: None,
             managed_by_init: None,
             updated_at: Utc::now(),
+            trait_history: Vec::new(),
         }
     }
 
@@ -117,6 +156,37 @@ impl MinionCmdbRecord {
         self.updated_at = Utc::now();
     }
 
+    /// Append every difference between two trait sets to the history, oldest
+    /// changes are dropped past `CMDB_TRAIT_HISTORY_MAX`. Returns the number
+    /// of recorded changes.
+    pub fn record_trait_changes(&mut self, old: &HashMap<String, Value>, new: &HashMap<String, Value>) -> usize {
+        let at = Utc::now();
+        let mut changes: Vec<TraitChange> = new
+            .iter()
+            .filter(|(key, value)| old.get(*key) != Some(*value))
+            .map(|(key, value)| TraitChange { at, key: key.clone(), old: old.get(key).cloned(), new: Some(value.clone()) })
+            .chain(old.iter().filter(|(key, _)| !new.contains_key(*key)).map(|(key, value)| TraitChange {
+                at,
+                key: key.clone(),
+                old: Some(value.clone()),
+                new: None,
+            }))
+            .collect();
+        changes.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let count = changes.len();
+        self.trait_history.extend(changes);
+        if self.trait_history.len() > CMDB_TRAIT_HISTORY_MAX {
+            self.trait_history.drain(..self.trait_history.len() - CMDB_TRAIT_HISTORY_MAX);
+        }
+        count
+    }
+
+    /// Trait changes, oldest first, optionally only of one key
+    pub fn trait_history(&self, key: Option<&str>) -> Vec<&TraitChange> {
+        self.trait_history.iter().filter(|change| key.is_none_or(|key| change.key == key)).collect()
+    }
+
     pub fn is_stale(&self, max_age: std::time::Duration) -> bool {
         chrono::Duration::from_std(max_age).map(|max_age| Utc::now() - self.updated_at >= max_age).unwrap_or(false)
     }
@@ -177,7 +247,12 @@ impl MinionCmdbRecord {
 
 impl MinionRecord {
     pub fn new(id: String, traits: HashMap<String, Value>, static_keys: BTreeSet<String>, fn_keys: BTreeSet<String>) -> Self {
-        MinionRecord { id, traits, static_keys, fn_keys }
+        MinionRecord { id, traits, static_keys, fn_keys, refreshed_at: Some(Utc::now()) }
+    }
+
+    /// When the minion sent these traits. Unknown for records stored by older masters.
+    pub fn refreshed_at(&self) -> Option<DateTime<Utc>> {
+        self.refreshed_at
     }
 
     /// Check if the record matches the value
@@ -17,8 +17,9 @@ use libcommon::SysinspectError;
 use libdpq::{DiskPersistentQueue, WorkItem};
 use libmodpak::{MODPAK_SYNC_STATE, SysInspectModPakMinion, mpk::ModPakProfile};
 use libsensors::sensors::SensorCtx;
+use libsensors::sensors::integrity;
 use libsensors::sensors::menotify::MeNotifySensor;
-use libsensors::service::SensorService;
+use libsensors::service::{SensorNotice, SensorNotifier, SensorService};
 use libsetup::get_ssh_client_ip;
 use libsetup::mnsetup::ensure_minion_tree;
 use libsysinspect::{
@@ -27,8 +28,8 @@ use libsysinspect::{
         mmconf::{CFG_MASTER_KEY_PUB, CFG_PENDING_TASKS_ROOT, DEFAULT_PORT, MinionConfig, MinionOfflineMode, SysInspectConfig},
     },
     console::{
-        ConsoleMinionLogRequest, ConsoleMinionLogSnapshot, ConsoleMinionProcessSignalRequest, ConsoleMinionTopRequest,
-        ConsoleMinionUpgradeSelfRequest, MinionCommandReply,
+        ConsoleMinionIntegrityRebaselineRequest, ConsoleMinionLogRequest, ConsoleMinionLogSnapshot, ConsoleMinionProcessSignalRequest,
+        ConsoleMinionRebootRequest, ConsoleMinionTopRequest, ConsoleMinionUpgradeSelfRequest, MinionCommandReply,
     },
     context,
     inspector::SysInspectRunner,
@@ -49,7 +50,7 @@ use libsysinspect::{
         self,
         rotation::{RotationActor, RsaTransportRotator, SignedRotationIntent},
     },
-    traits::{self, TraitUpdateRequest, effective_profiles, ensure_master_traits_file, systraits::SystemTraits},
+    traits::{self, TraitUpdateRequest, TraitsTransportPayload, effective_profiles, ensure_master_traits_file, systraits::SystemTraits},
     transport::{
         TransportStore,
         secure_bootstrap::SecureBootstrapSession,
@@ -64,9 +65,9 @@ use libsysproto::{
     query::{
         MinionQuery, SCHEME_COMMAND,
         commands::{
-            CLUSTER_MINION_LOGS, CLUSTER_MINION_PROCESS_SIGNAL, CLUSTER_MINION_RECONNECT, CLUSTER_MINION_SHUTDOWN, CLUSTER_MINION_TOP,
-            CLUSTER_MINION_UPGRADE_SELF, CLUSTER_REBOOT, CLUSTER_RECONNECT, CLUSTER_REMOVE_MINION, CLUSTER_ROTATE, CLUSTER_SHUTDOWN, CLUSTER_SYNC,
-            CLUSTER_TRAITS_UPDATE,
+            CLUSTER_MINION_INTEGRITY_REBASELINE, CLUSTER_MINION_LOGS, CLUSTER_MINION_PROCESS_SIGNAL, CLUSTER_MINION_RECONNECT,
+            CLUSTER_MINION_SHUTDOWN, CLUSTER_MINION_TOP, CLUSTER_MINION_UPGRADE_SELF, CLUSTER_REBOOT, CLUSTER_RECONNECT, CLUSTER_REMOVE_MINION,
+            CLUSTER_ROTATE, CLUSTER_SHUTDOWN, CLUSTER_SYNC, CLUSTER_TRAITS_UPDATE,
         },
     },
     replay::{ReplayIdentity, replay_identity_from_minion_bytes},
@@ -86,7 +87,7 @@ use std::{
     sync::Arc,
     sync::OnceLock,
     sync::RwLock,
-    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     time::{Duration, Instant},
     vec,
 };
@@ -268,13 +269,24 @@ pub struct SysMinion {
     minion_id: String,
     registration: Mutex<RegistrationOutcome>,
 
-    pub(crate) sensors_task: Mutex<Option<JoinHandle<()>>>,
+    pub(crate) sensors: Mutex<Option<SensorService>>,
+    pub(crate) sensors_events: Mutex<Option<Arc<Mutex<EventProcessor>>>>,
     pub(crate) sensors_pump: Mutex<Option<JoinHandle<()>>>,
     pub(crate) ping_task: Mutex<Option<JoinHandle<()>>>,
     pub(crate) proto_task: Mutex<Option<JoinHandle<()>>>,
     pub(crate) stats_task: Mutex<Option<JoinHandle<()>>>,
+    pub(crate) traits_task: Mutex<Option<JoinHandle<()>>>,
     recovery_epoch: AtomicU64,
     recovery_ready_tx: watch::Sender<u64>,
+
+    /// Index of the master currently in use, see `MinionConfig::masters()`
+    master_idx: AtomicUsize,
+
+    /// Set while draining before a reboot: new cycles are refused
+    draining: AtomicBool,
+
+    /// Traits as last sent to the master, base for delta refreshes
+    sent_traits: Mutex<Option<TraitsTransportPayload>>,
 }
 
 impl SysMinion {
@@ -422,21 +434,44 @@ impl SysMinion {
         }
     }
 
+    /// Connect to the first reachable master, starting from the one at `start` and
+    /// going through all configured masters (primary and standby ones) in turn.
+    async fn connect_master(cfg: &MinionConfig, start: usize) -> Result<(usize, TcpStream), std::io::Error> {
+        let total = cfg.masters().len();
+        let mut last_err = None;
+        for off in 0..total {
+            let idx = (start + off) % total;
+            let addr = cfg.with_master(idx).master();
+            match TcpStream::connect(&addr).await {
+                Ok(stream) => return Ok((idx, stream)),
+                Err(err) => {
+                    if total > 1 {
+                        log::warn!("Master {addr} is unreachable: {err}");
+                    }
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No master configured")))
+    }
+
     pub async fn new(cfg: MinionConfig, fingerprint: Option<String>, dpq: Arc<DiskPersistentQueue>) -> Result<Arc<SysMinion>, SysinspectError> {
         log::debug!("Configuration: {cfg:#?}");
-        log::debug!("Trying to connect at {}", cfg.master());
+        log::debug!("Trying to connect at {}", cfg.masters().join(", "));
         ensure_minion_tree(&cfg)?;
 
-        let (rstm, wstm) = match TcpStream::connect(cfg.master()).await {
-            Ok(stream) => {
-                log::debug!("Network bound at {}", cfg.master());
+        let mut master_idx = 0;
+        let (rstm, wstm) = match Self::connect_master(&cfg, 0).await {
+            Ok((idx, stream)) => {
+                log::debug!("Network bound at {}", cfg.with_master(idx).master());
+                master_idx = idx;
                 let (rstm, wstm) = stream.into_split();
                 (Some(rstm), Some(wstm))
             }
             Err(err) if cfg.offline() == MinionOfflineMode::Independent && fingerprint.is_none() => {
                 log::warn!(
                     "Initial transport connect to {} failed in independent mode: {}; starting with transport offline while local work continues",
-                    cfg.master(),
+                    cfg.masters().join(", "),
                     err
                 );
                 (None, None)
@@ -463,13 +498,18 @@ impl SysMinion {
             secure: Mutex::new(None),
             minion_id: dataconv::as_str(SystemTraits::new(cfg.clone(), true).get(traits::SYS_ID)),
             registration: Mutex::new(RegistrationOutcome::Pending),
-            sensors_task: Mutex::new(None),
+            sensors: Mutex::new(None),
+            sensors_events: Mutex::new(None),
             sensors_pump: Mutex::new(None),
             ping_task: Mutex::new(None),
             proto_task: Mutex::new(None),
             stats_task: Mutex::new(None),
+            traits_task: Mutex::new(None),
             recovery_epoch: AtomicU64::new(0),
             recovery_ready_tx,
+            master_idx: AtomicUsize::new(master_idx),
+            draining: AtomicBool::new(false),
+            sent_traits: Mutex::new(None),
         };
         log::debug!("Instance set up with root directory at {}", cfg.root_dir().to_str().unwrap_or_default());
         instance.init()?;
@@ -482,6 +522,16 @@ impl SysMinion {
         Ok(instance)
     }
 
+    /// Configuration, pointing to the master currently in use
+    pub(crate) fn active_cfg(&self) -> MinionConfig {
+        self.cfg.with_master(self.master_idx.load(Ordering::Relaxed))
+    }
+
+    /// Address of the master currently in use
+    pub(crate) fn master_addr(&self) -> String {
+        self.active_cfg().master()
+    }
+
     /// Initialise minion.
     /// This creates all directory structures if none etc.
     fn init(&self) -> Result<(), SysinspectError> {
@@ -521,10 +571,10 @@ impl SysMinion {
             h.abort();
             let _ = h.await;
         }
-        if let Some(h) = self.sensors_task.lock().await.take() {
-            h.abort();
-            let _ = h.await;
+        if let Some(mut service) = self.sensors.lock().await.take() {
+            service.stop();
         }
+        self.sensors_events.lock().await.take();
     }
 
     pub(crate) async fn stop_background(&self) {
@@ -540,6 +590,10 @@ impl SysMinion {
             h.abort();
             let _ = h.await;
         }
+        if let Some(h) = self.traits_task.lock().await.take() {
+            h.abort();
+            let _ = h.await;
+        }
     }
 
     /// Display minion info
@@ -674,7 +728,7 @@ impl SysMinion {
     ///   requesting fresh `Traits`
     /// - therefore local execution and durable-delivery recovery stay decoupled
     pub(crate) async fn reconnect_transport(self: &Arc<Self>) -> Result<(), SysinspectError> {
-        log::info!("Re-establishing transport to master {}...", self.cfg.master());
+        log::info!("Re-establishing transport to master {}...", self.master_addr());
         let (recovery_epoch, mut recovery_rx) = self.begin_recovery_wait();
 
         // 1. Stop proto, ping, and stats tasks so they don't fight for the old streams.
@@ -682,15 +736,22 @@ impl SysMinion {
 
         // 2. Drop old streams, then open a fresh connection.
         self.clear_streams().await;
-        let (rstm, wstm) = match TcpStream::connect(self.cfg.master()).await {
-            Ok(s) => s.into_split(),
+        let current = self.master_idx.load(Ordering::Relaxed);
+        let (rstm, wstm) = match Self::connect_master(&self.cfg, current).await {
+            Ok((idx, s)) => {
+                if idx != current {
+                    log::warn!("Failing over from master {} to {}", self.master_addr(), self.cfg.with_master(idx).master());
+                    self.master_idx.store(idx, Ordering::Relaxed);
+                }
+                s.into_split()
+            }
             Err(err) => {
                 log::error!("Failed to connect to master during transport recovery: {err}");
                 return Err(SysinspectError::MinionGeneralError(format!("Transport recovery connect failed: {err}")));
             }
         };
         self.set_streams(rstm, wstm).await;
-        log::info!("Transport socket reconnected to {}", self.cfg.master());
+        log::info!("Transport socket reconnected to {}", self.master_addr());
 
         // 3. Bootstrap a fresh secure session.
         *self.secure.lock().await = None;
@@ -716,6 +777,7 @@ impl SysMinion {
         let state = Arc::new(ExitState::new());
         Arc::clone(self).do_ping_update(state).await?;
         Arc::clone(self).do_stats_update().await?;
+        Arc::clone(self).do_traits_refresh().await?;
 
         log::info!("Transport recovery complete; backlog: {}", self.backlog_snapshot().format());
         Ok(())
@@ -758,7 +820,7 @@ impl SysMinion {
         let (opening, hello) = match SecureBootstrapSession::open(&state, &self.kman.private_key()?, &master_pbk) {
             Ok(opening) => opening,
             Err(err) => {
-                log::error!("Unable to prepare secure bootstrap for master {}: {}", self.cfg.master(), err);
+                log::error!("Unable to prepare secure bootstrap for master {}: {}", self.master_addr(), err);
                 self.mark_broken_transport(&store, &mut state, None);
                 return Err(err);
             }
@@ -778,7 +840,7 @@ impl SysMinion {
                     Err(err) => {
                         log::error!(
                             "Secure bootstrap ack verification failed for master {} using key {}: {}",
-                            self.cfg.master(),
+                            self.master_addr(),
                             opening_key_id,
                             err
                         );
@@ -799,7 +861,7 @@ impl SysMinion {
             SecureFrame::BootstrapDiagnostic(diag) => {
                 log::error!(
                     "Master {} rejected secure bootstrap with {:?}: {} (retryable={}, rate_limit={})",
-                    self.cfg.master(),
+                    self.master_addr(),
                     diag.code,
                     diag.message,
                     diag.failure.retryable,
@@ -809,7 +871,7 @@ impl SysMinion {
                 Err(SysinspectError::ProtoError(format!("Master rejected secure bootstrap with {:?}: {}", diag.code, diag.message)))
             }
             _ => {
-                log::error!("Master {} replied with a non-bootstrap frame during secure bootstrap", self.cfg.master());
+                log::error!("Master {} replied with a non-bootstrap frame during secure bootstrap", self.master_addr());
                 self.mark_broken_transport(&store, &mut state, Some(&opening_key_id));
                 Err(SysinspectError::ProtoError("Master replied with a non-bootstrap frame during secure bootstrap".to_string()))
             }
@@ -915,10 +977,7 @@ impl SysMinion {
             }
         }
 
-        self.stop_sensors().await;
-        log::info!("Restarting sensors service");
-
-        // Load spec before spawning anything
+        // Load spec before touching the running sensors
         let spec = match libsensors::load(self.cfg.sensors_dir().as_path()) {
             Ok(spec) => spec,
             Err(e) => {
@@ -934,6 +993,29 @@ impl SysMinion {
             events = events.set_config(Arc::new(cfg.clone()), None);
         }
 
+        // Sensors are already running: swap the event handlers in place and apply only the changes.
+        // The pending events are drained before the service is locked, as pongs read its stats meanwhile.
+        let shared = self.sensors_events.lock().await.clone();
+        let running = self.sensors.lock().await.is_some();
+        if let Some(shared) = shared.filter(|_| running) {
+            log::info!("Reloading sensors service");
+            {
+                let mut ep = shared.lock().await;
+                ep.process(true).await;
+                *ep = events;
+            }
+            match self.sensors.lock().await.as_mut() {
+                Some(service) => {
+                    service.reload(spec).await;
+                }
+                None => log::warn!("Sensors service was stopped while reloading, not starting it again"),
+            }
+            return Ok(());
+        }
+
+        self.stop_sensors().await;
+        log::info!("Starting sensors service");
+
         let events = Arc::new(Mutex::new(events));
 
         // Spawn pump and store handle immediately
@@ -948,14 +1030,26 @@ impl SysMinion {
             }
         });
         *self.sensors_pump.lock().await = Some(pump_handle);
+        *self.sensors_events.lock().await = Some(events.clone());
 
-        // Spawn service task and store it
-        let sensors_task = {
-            let mut service = SensorService::new(spec).with_ctx(SensorCtx::default().with_sharelib_root(self.cfg.sharelib_dir()));
-            service.set_event_processor(events.clone());
-            service.spawn()
-        };
-        *self.sensors_task.lock().await = Some(sensors_task);
+        // Start the service and keep it for the next sync
+        let mut ctx = SensorCtx::default().with_sharelib_root(self.cfg.sharelib_dir()).with_state_root(self.cfg.sensors_state_dir());
+        match self.kman.private_key() {
+            Ok(prk) => ctx = ctx.with_signing_key(prk),
+            Err(err) => log::warn!("Sensors will run without a signing key: {err}"),
+        }
+
+        let minion = Arc::downgrade(&self);
+        let notifier: SensorNotifier = Arc::new(move |notice: SensorNotice| {
+            if let Some(minion) = minion.upgrade() {
+                tokio::spawn(minion.send_sensor_notice(notice));
+            }
+        });
+
+        let mut service = SensorService::new(spec).with_ctx(ctx).with_notifier(notifier);
+        service.set_event_processor(events);
+        service.start();
+        *self.sensors.lock().await = Some(service);
 
         Ok(())
     }
@@ -1210,6 +1304,10 @@ impl SysMinion {
                                         log::debug!("Dropped internal master command for another minion");
                                     }
                                 } else {
+                                    if this.draining.load(Ordering::Relaxed) {
+                                        log::warn!("Refused cycle {}: minion is draining before reboot", msg.cycle());
+                                        continue;
+                                    }
                                     let model_id = msg.target().scheme().split('/').next().unwrap_or_default();
                                     if !model_id.is_empty() && !this.is_model_allowed_in_profiles(model_id).await {
                                         log::debug!("Dropped command for model {}: not in any assigned profile", model_id);
@@ -1321,11 +1419,13 @@ impl SysMinion {
                                     (l, d, i, io, cpu)
                                 };
 
+                                let sensors = this.sensors.lock().await.as_ref().map(|s| s.stats()).unwrap_or_default();
                                 let pl = json!({
                                     "ld": loadavg,
                                     "cd": if is_done { doneids } else { vec![] },
                                     "dbps": io_bps,
                                     "cpu": cpu_usage,
+                                    "sn": sensors,
                                 });
 
                                 this.request(
@@ -1379,8 +1479,40 @@ impl SysMinion {
     }
 
     pub async fn send_traits(self: Arc<Self>) -> Result<(), SysinspectError> {
-        let fresh_traits = minion_traits(&self.cfg, false, true);
-        let mut r = MinionMessage::new(self.get_minion_id().to_string(), RequestType::Traits, fresh_traits.to_transport_value()?);
+        let payload = minion_traits(&self.cfg, false, true).to_transport_payload();
+        self.send_traits_payload(&payload).await?;
+        *self.sent_traits.lock().await = Some(payload);
+        Ok(())
+    }
+
+    /// Recompute traits and send only the keys changed since the last sync.
+    pub async fn refresh_traits(self: Arc<Self>) -> Result<(), SysinspectError> {
+        let payload = minion_traits(&self.cfg, true, true).to_transport_payload();
+        let delta = {
+            let sent = self.sent_traits.lock().await;
+            let Some(sent) = sent.as_ref() else {
+                // Nothing was sent yet, the full set goes out on connect
+                return Ok(());
+            };
+            payload.delta_from(sent)
+        };
+        let Some(delta) = delta else {
+            log::debug!("Traits unchanged");
+            return Ok(());
+        };
+
+        log::info!(
+            "Pushing {} changed trait{} to the master",
+            delta.traits.len() + delta.removed.len(),
+            if delta.traits.len() + delta.removed.len() == 1 { "" } else { "s" }
+        );
+        self.send_traits_payload(&delta).await?;
+        *self.sent_traits.lock().await = Some(payload);
+        Ok(())
+    }
+
+    async fn send_traits_payload(&self, payload: &TraitsTransportPayload) -> Result<(), SysinspectError> {
+        let mut r = MinionMessage::new(self.get_minion_id().to_string(), RequestType::Traits, serde_json::to_value(payload)?);
         r.set_sid(MINION_SID.to_string());
         self.try_request(
             r.sendable().map_err(|e| {
@@ -1393,13 +1525,44 @@ impl SysMinion {
         Ok(())
     }
 
+    /// Refresh traits periodically (`traits.refresh`) and whenever the
+    /// `traits-refresh` event handler asks for it.
+    pub async fn do_traits_refresh(self: Arc<Self>) -> Result<(), SysinspectError> {
+        let this = self.clone();
+        let interval = self.cfg.traits_refresh();
+        let handle = tokio::spawn(async move {
+            loop {
+                match interval {
+                    Some(interval) => {
+                        tokio::select! {
+                            _ = sleep(interval) => {}
+                            _ = traits::traits_refresh_requested() => {}
+                        }
+                    }
+                    None => traits::traits_refresh_requested().await,
+                }
+                if !this.is_connected() {
+                    continue;
+                }
+                if let Err(err) = this.as_ptr().refresh_traits().await {
+                    log::error!("Unable to refresh traits: {err}");
+                }
+            }
+        });
+
+        if let Some(h) = self.traits_task.lock().await.replace(handle) {
+            h.abort();
+        }
+        Ok(())
+    }
+
     /// Send ehlo
     pub async fn send_ehlo(self: Arc<Self>) -> Result<(), SysinspectError> {
         let fresh_traits = minion_traits(&self.cfg, false, false);
         let mut r = MinionMessage::new(dataconv::as_str(fresh_traits.get(traits::SYS_ID)), RequestType::Ehlo, fresh_traits.to_json_value()?);
         r.set_sid(MINION_SID.to_string());
 
-        log::info!("Ehlo on {}", self.cfg.master());
+        log::info!("Ehlo on {}", self.master_addr());
         self.try_request(r.sendable()?, OutboundMessageClass::SessionControl).await?;
         Ok(())
     }
@@ -1408,7 +1571,7 @@ impl SysMinion {
     pub async fn send_registration(self: Arc<Self>, pbk_pem: String) -> Result<(), SysinspectError> {
         let r = MinionMessage::new(self.get_minion_id().to_string(), RequestType::Add, json!(pbk_pem));
 
-        log::info!("Registration request to {}", self.cfg.master());
+        log::info!("Registration request to {}", self.master_addr());
         self.try_request(r.sendable()?, OutboundMessageClass::SessionControl).await?;
         Ok(())
     }
@@ -1440,11 +1603,26 @@ impl SysMinion {
         Ok(())
     }
 
+    /// Report a sensor lifecycle change to the master
+    pub(crate) async fn send_sensor_notice(self: Arc<Self>, notice: SensorNotice) {
+        if !self.is_connected() {
+            log::debug!("Not connected, sensor '{}' {} is not reported", notice.sid, notice.state.as_str());
+            return;
+        }
+
+        let mut r = MinionMessage::new(self.get_minion_id().to_string(), RequestType::SensorNotice, json!(notice));
+        r.set_sid(MINION_SID.to_string());
+        match r.sendable() {
+            Ok(msg) => self.request(msg, OutboundMessageClass::SessionControl).await,
+            Err(e) => log::error!("Failed to send sensor notice: {e}"),
+        }
+    }
+
     /// Send bye message
     pub async fn send_bye(self: Arc<Self>) {
         let r = MinionMessage::new(self.get_minion_id().to_string(), RequestType::Bye, json!(MINION_SID.to_string()));
 
-        log::info!("Goodbye to {}", self.cfg.master());
+        log::info!("Goodbye to {}", self.master_addr());
         match r.sendable() {
             Ok(msg) => self.request(msg, OutboundMessageClass::SessionControl).await,
             Err(e) => log::error!("Failed to send bye message: {e}"),
@@ -1765,6 +1943,76 @@ impl SysMinion {
         std::process::exit(0);
     }
 
+    /// Drain the minion before a reboot: refuse new cycles, wait for the running
+    /// ones to finish and deliver the journal backlog to the master.
+    async fn drain(self: Arc<Self>, timeout: Duration) -> Result<(), SysinspectError> {
+        self.draining.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + timeout;
+        let mut last_replay: Option<Instant> = None;
+        loop {
+            let backlog = self.backlog_snapshot();
+            if self.pt_counter.lock().await.is_done() && backlog.dpq_pending == 0 && backlog.dpq_inflight == 0 {
+                if backlog.journal_cycles == 0 {
+                    return Ok(());
+                }
+
+                // Undelivered results are freed by the master's CycleAck
+                if last_replay.is_none_or(|t| t.elapsed() >= Duration::from_secs(5)) {
+                    self.clone().replay_pending().await;
+                    last_replay = Some(Instant::now());
+                }
+            }
+
+            if Instant::now() >= deadline {
+                self.draining.store(false, Ordering::Relaxed);
+                return Err(SysinspectError::MinionGeneralError(format!(
+                    "Drain timed out after {}s, backlog: {}",
+                    timeout.as_secs(),
+                    backlog.format()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Drain the minion and reboot the host through the service manager of the `sys.service` module
+    async fn reboot(self: Arc<Self>, request: ConsoleMinionRebootRequest, cycle_id: &str) {
+        log::warn!("Draining before reboot, waiting up to {}s", request.drain_timeout);
+        if let Err(err) = self.clone().drain(Duration::from_secs(request.drain_timeout)).await {
+            log::error!("Reboot cancelled: {err}");
+            self.as_ptr().send_command_reply(cycle_id, Err(err)).await;
+            return;
+        }
+
+        let sharelib = self.cfg.sharelib_dir();
+        let rsp = tokio::task::spawn_blocking(move || {
+            let _ = SysInspector::new(ModelSpec::default(), Some(sharelib.clone()), IndexMap::new()); // Sets sharelib for the module caller
+            let mut modcaller = ModCall::default().set_module_ns("sys.service", sharelib);
+            modcaller.add_opt("reboot".to_string());
+            modcaller.run()
+        })
+        .await;
+
+        let result = match rsp {
+            Ok(Ok(Some(rsp))) if rsp.response.retcode() == 0 => {
+                Ok(json!({"status": "rebooting", "checksum": MINION_BINARY_SHA256.get().cloned().unwrap_or_default()}))
+            }
+            Ok(Ok(Some(rsp))) => Err(SysinspectError::MinionGeneralError(format!("Reboot failed: {}", rsp.response.message()))),
+            Ok(Ok(None)) => Err(SysinspectError::MinionGeneralError("Reboot module returned no response".to_string())),
+            Ok(Err(err)) => Err(err),
+            Err(err) => Err(SysinspectError::MinionGeneralError(format!("Reboot task crashed: {err}"))),
+        };
+
+        match &result {
+            Ok(_) => log::warn!("{} the host", "Rebooting".bright_red().bold()),
+            Err(err) => {
+                log::error!("Unable to reboot: {err}");
+                self.draining.store(false, Ordering::Relaxed);
+            }
+        }
+        self.as_ptr().send_command_reply(cycle_id, result).await;
+    }
+
     /// Download a file from master
     async fn download_file(self: Arc<Self>, fname: &str) -> Result<Vec<u8>, SysinspectError> {
         async fn fetch_file(url: &str, filename: &str) -> Result<Vec<u8>, SysinspectError> {
@@ -1785,7 +2033,7 @@ impl SysMinion {
                 _ => Err(SysinspectError::MinionGeneralError("Unknown status".to_string())),
             }
         }
-        let addr = self.cfg.fileserver();
+        let addr = self.active_cfg().fileserver();
         let fname = fname.to_string();
         let h = tokio::spawn(async move {
             match fetch_file(&addr, &fname).await {
@@ -1972,9 +2220,19 @@ impl SysMinion {
                 tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
                 emit_reconnect_signal();
             }
-            CLUSTER_REBOOT => {
-                log::warn!("Command \"reboot\" is not implemented yet");
-            }
+            CLUSTER_REBOOT => match serde_json::from_str::<ConsoleMinionRebootRequest>(if context.trim().is_empty() { "{}" } else { context }) {
+                Ok(request) => {
+                    // Draining takes a while, the protocol loop must keep running meanwhile
+                    let this = self.clone();
+                    let cycle_id = cycle_id.to_string();
+                    tokio::spawn(async move { this.reboot(request, &cycle_id).await });
+                }
+                Err(err) => {
+                    self.as_ptr()
+                        .send_command_reply(cycle_id, Err(SysinspectError::DeserializationError(format!("Failed to parse reboot request: {err}"))))
+                        .await;
+                }
+            },
             CLUSTER_RECONNECT => {
                 log::info!("Requesting cluster-wide reconnect from the master");
                 emit_reconnect_signal();
@@ -1996,7 +2254,7 @@ impl SysMinion {
                 if let Err(e) = ensure_master_traits_file(&self.cfg) {
                     log::error!("Failed to ensure master-managed traits file: {e}");
                 }
-                if let Err(e) = SysInspectModPakMinion::new(self.cfg.clone()).sync().await {
+                if let Err(e) = SysInspectModPakMinion::new(self.active_cfg()).sync().await {
                     log::error!("Failed to sync minion with master: {e}");
                 }
                 if let Err(e) = self.as_ptr().send_traits().await {
@@ -2082,6 +2340,22 @@ impl SysMinion {
                     });
                 self.as_ptr().send_command_reply(cycle_id, payload).await;
             }
+            CLUSTER_MINION_INTEGRITY_REBASELINE => {
+                let payload = serde_json::from_str::<ConsoleMinionIntegrityRebaselineRequest>(if context.trim().is_empty() { "{}" } else { context })
+                    .map_err(|err| SysinspectError::DeserializationError(format!("Failed to parse integrity rebaseline request: {err}")))
+                    .and_then(|request| {
+                        let sensors = integrity::request_rebaseline(request.sensor.as_deref());
+                        if sensors.is_empty() {
+                            return Err(SysinspectError::InvalidQuery(match request.sensor {
+                                Some(sid) => format!("No running sys.integrity sensor '{sid}'"),
+                                None => "No sys.integrity sensor is running".to_string(),
+                            }));
+                        }
+                        log::info!("Rebaselining file integrity of {}", sensors.join(", ").bright_yellow());
+                        Ok(json!({"sensors": sensors, "status": "rebaselining"}))
+                    });
+                self.as_ptr().send_command_reply(cycle_id, payload).await;
+            }
             CLUSTER_MINION_UPGRADE_SELF => match serde_json::from_str::<ConsoleMinionUpgradeSelfRequest>(context) {
                 Ok(request) => {
                     self.clone().upgrade_self(request, cycle_id).await;
@@ -2157,8 +2431,8 @@ pub(crate) async fn _minion_instance(cfg: MinionConfig, fingerprint: Option<Stri
     // and keep this receiver for the entire instance lifetime.
     let mut reconnect_rx = CONNECTION_TX.subscribe();
 
-    let modpak = SysInspectModPakMinion::new(cfg.clone());
     let minion = SysMinion::new(cfg.clone(), fingerprint, dpq).await?;
+    let modpak = SysInspectModPakMinion::new(minion.active_cfg());
     let m = minion.as_ptr();
 
     let runner = m.as_ptr().dpq.clone().start_ack({
@@ -2327,6 +2601,7 @@ pub(crate) async fn _minion_instance(cfg: MinionConfig, fingerprint: Option<Stri
         minion.as_ptr().send_sensors_sync().await?;
         minion.as_ptr().do_ping_update(state.clone()).await?;
         minion.as_ptr().do_stats_update().await?;
+        minion.as_ptr().do_traits_refresh().await?;
     }
 
     // Keeps client running
@@ -9,8 +9,10 @@ mod tests {
     };
     use libcommon::SysinspectError;
     use libdpq::DiskPersistentQueue;
+    use libsensors::{service::SensorService, sspec::SensorSpec};
     use libsysinspect::{
         cfg::mmconf::{CFG_MASTER_KEY_PUB, MinionConfig, MinionOfflineMode},
+        reactor::evtproc::EventProcessor,
         rsa::keys::{RsaKey::Public, key_to_file, keygen},
         transport::{
             TransportKeyExchangeModel, TransportKeyStatus, TransportPeerState, TransportProvisioningMode, TransportRotationStatus, TransportStore,
@@ -25,6 +27,7 @@ mod tests {
     use rsa::{RsaPrivateKey, RsaPublicKey};
     use serde_json::json;
     use std::io::ErrorKind;
+    use std::str::FromStr;
     use std::sync::Arc;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::{TcpListener, TcpStream};
@@ -507,6 +510,33 @@ mod tests {
         let _ = h2.await;
     }
 
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn connect_fails_over_to_standby_master() {
+        let _guard = TEST_LOCK.lock().await;
+
+        // Standby master listens, primary on the same port does not.
+        let standby = TcpListener::bind("127.0.0.2:0").await.unwrap();
+        let port = standby.local_addr().unwrap().port();
+        let accepted = tokio::spawn(async move { standby.accept().await.map(|_| ()) });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let mut cfg = MinionConfig::default();
+        cfg.set_master_ip("127.0.0.3");
+        cfg.set_master_standby(vec!["127.0.0.2".to_string()]);
+        cfg.set_master_port(port.into());
+        cfg.set_root_dir(tmp.path().to_str().unwrap());
+        cfg.set_offline(MinionOfflineMode::Follow);
+        seed_managed_transport(&cfg, tmp.path());
+
+        let dpq = Arc::new(DiskPersistentQueue::open(tmp.path().join("pending-tasks")).unwrap());
+        let minion = SysMinion::new(cfg, None, dpq).await.expect("minion should connect to the standby master");
+
+        timeout(reconnect_accept_timeout(), accepted).await.expect("standby master never accepted").unwrap().unwrap();
+        assert_eq!(minion.master_addr(), format!("127.0.0.2:{port}"));
+        assert!(minion.active_cfg().fileserver().starts_with("127.0.0.2:"));
+    }
+
     #[tokio::test]
     async fn request_writes_len_prefix_and_payload() {
         let _guard = TEST_LOCK.lock().await;
@@ -732,6 +762,53 @@ mod tests {
         assert!(v["d"].is_object());
     }
 
+    #[tokio::test]
+    async fn refresh_traits_sends_only_changed_keys() {
+        let _guard = TEST_LOCK.lock().await;
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut out = Vec::new();
+            for _ in 0..2 {
+                let mut lenb = [0u8; 4];
+                sock.read_exact(&mut lenb).await.unwrap();
+                let mut msg = vec![0u8; u32::from_be_bytes(lenb) as usize];
+                sock.read_exact(&mut msg).await.unwrap();
+                out.push(serde_json::from_slice::<serde_json::Value>(&msg).unwrap());
+            }
+            out
+        });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let mut cfg = MinionConfig::default();
+        cfg.set_master_ip(&addr.ip().to_string());
+        cfg.set_master_port(addr.port().into());
+        cfg.set_root_dir(tmp.path().to_str().unwrap());
+
+        let dpq = Arc::new(DiskPersistentQueue::open(tmp.path().join("pending-tasks")).unwrap());
+        let minion = SysMinion::new(cfg.clone(), None, dpq).await.unwrap();
+
+        // Nothing was sent yet, so there is no base for a delta
+        minion.as_ptr().refresh_traits().await.unwrap();
+        minion.as_ptr().send_traits().await.unwrap();
+        // Unchanged traits are not sent again
+        minion.as_ptr().refresh_traits().await.unwrap();
+
+        std::fs::create_dir_all(cfg.traits_dir()).unwrap();
+        std::fs::write(cfg.traits_dir().join("site.cfg"), "site.rack: r42\n").unwrap();
+        minion.as_ptr().refresh_traits().await.unwrap();
+
+        let msgs = server.await.unwrap();
+        assert!(msgs[0]["d"].get("delta").is_none());
+        assert_eq!(msgs[1]["d"]["delta"], true);
+        assert_eq!(msgs[1]["d"]["traits"], serde_json::json!({"site.rack": "r42"}));
+    }
+
     #[tokio::test]
     async fn send_sensors_sync_emits_expected_r_code() {
         let _guard = TEST_LOCK.lock().await;
@@ -795,16 +872,19 @@ mod tests {
         let h1 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
         let h2 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
         let h3 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
+        let h4 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
 
         *minion.ping_task.lock().await = Some(h1);
         *minion.proto_task.lock().await = Some(h2);
         *minion.stats_task.lock().await = Some(h3);
+        *minion.traits_task.lock().await = Some(h4);
 
         minion.stop_background().await;
 
         assert!(minion.ping_task.lock().await.is_none());
         assert!(minion.proto_task.lock().await.is_none());
         assert!(minion.stats_task.lock().await.is_none());
+        assert!(minion.traits_task.lock().await.is_none());
     }
 
     #[tokio::test]
@@ -827,15 +907,17 @@ mod tests {
         let minion = SysMinion::new(cfg, None, dpq).await.unwrap();
 
         let h1 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
-        let h2 = tokio::spawn(async { tokio::time::sleep(Duration::from_secs(60)).await });
+        let spec = SensorSpec::from_str("sensors: {}").unwrap();
 
         *minion.sensors_pump.lock().await = Some(h1);
-        *minion.sensors_task.lock().await = Some(h2);
+        *minion.sensors.lock().await = Some(SensorService::new(spec));
+        *minion.sensors_events.lock().await = Some(Arc::new(Mutex::new(EventProcessor::new())));
 
         minion.stop_sensors().await;
 
         assert!(minion.sensors_pump.lock().await.is_none());
-        assert!(minion.sensors_task.lock().await.is_none());
+        assert!(minion.sensors.lock().await.is_none());
+        assert!(minion.sensors_events.lock().await.is_none());
     }
 
     #[tokio::test]